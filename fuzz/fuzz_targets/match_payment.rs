@@ -0,0 +1,115 @@
+//! Fuzzes `x402_runtime::match_payment`, and the deprecated
+//! `match_payment_legacy`, with arbitrary instructions.
+//!
+//! Input layout (kept byte-oriented so the seed corpus can hold real
+//! encoded transfers):
+//!
+//! - byte 0: program selector (0 System, 1 Token, 2 Token-2022, 3 the
+//!   invoking program, anything else reads a 32-byte program id)
+//! - next byte: account count (mod 16), then 33 bytes per account
+//!   (pubkey + flags, bit 0 signer, bit 1 writable)
+//! - remainder: instruction data, truncated to 4 KiB
+//!
+//! Every instruction `match_payment` accepts must also carry one of
+//! `PAYMENT_ENCODINGS` at its exact length.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use x402_runtime::matcher::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use x402_runtime::{is_payment_program, match_payment, MatchedPayment};
+
+const INVOKING_PROGRAM: Pubkey = Pubkey::new_from_array([7; 32]);
+const MAX_DATA_LEN: usize = 4096;
+
+fn take<'a>(input: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if input.len() < len {
+        return None;
+    }
+    let (head, tail) = input.split_at(len);
+    *input = tail;
+    Some(head)
+}
+
+fn decode(mut input: &[u8]) -> Option<Instruction> {
+    let program_id = match take(&mut input, 1)?[0] {
+        0 => system_program::ID,
+        1 => TOKEN_PROGRAM_ID,
+        2 => TOKEN_2022_PROGRAM_ID,
+        3 => INVOKING_PROGRAM,
+        _ => Pubkey::try_from(take(&mut input, 32)?).ok()?,
+    };
+
+    let count = take(&mut input, 1)?[0] as usize % 16;
+    let mut accounts = Vec::with_capacity(count);
+    for _ in 0..count {
+        let raw = take(&mut input, 33)?;
+        accounts.push(AccountMeta {
+            pubkey: Pubkey::try_from(&raw[..32]).ok()?,
+            is_signer: raw[32] & 1 != 0,
+            is_writable: raw[32] & 2 != 0,
+        });
+    }
+
+    let data = input[..input.len().min(MAX_DATA_LEN)].to_vec();
+    Some(Instruction {
+        program_id,
+        accounts,
+        data,
+    })
+}
+
+fn assert_enumerated(ix: &Instruction, payment: &MatchedPayment) {
+    use x402_runtime::matcher::{EncodingProgram, PaymentEncoding};
+
+    let program = if ix.program_id == system_program::ID {
+        EncodingProgram::System
+    } else if ix.program_id == INVOKING_PROGRAM {
+        EncodingProgram::Invoking
+    } else {
+        EncodingProgram::Token
+    };
+    let encoding = PaymentEncoding::find(program, &ix.data).expect("accepted an unenumerated encoding");
+    assert_eq!(payment.kind, encoding.kind);
+    if let Some(len) = encoding.len {
+        assert_eq!(ix.data.len(), len, "accepted {:?} at the wrong length", encoding.kind);
+    }
+}
+
+/// What either matcher must hold to for any payment it accepts.
+fn assert_consistent(ix: &Instruction, payment: &MatchedPayment) {
+    assert!(
+        is_payment_program(&ix.program_id, &INVOKING_PROGRAM),
+        "accepted an instruction from {}",
+        ix.program_id
+    );
+
+    let encoded = payment.amount.to_le_bytes();
+    assert!(
+        ix.data.windows(8).any(|window| window == encoded),
+        "reported amount {} is not encoded in the instruction data",
+        payment.amount
+    );
+
+    let keys: Vec<Pubkey> = ix.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert!(keys.contains(&payment.payer));
+    assert!(keys.contains(&payment.recipient));
+}
+
+fuzz_target!(|input: &[u8]| {
+    let Some(ix) = decode(input) else {
+        return;
+    };
+
+    if let Ok(payment) = match_payment(&ix, &INVOKING_PROGRAM) {
+        assert_consistent(&ix, &payment);
+        assert_enumerated(&ix, &payment);
+    }
+
+    #[cfg(not(feature = "strict-encodings"))]
+    if let Ok(payment) = x402_runtime::match_payment_legacy(&ix, &INVOKING_PROGRAM) {
+        assert_consistent(&ix, &payment);
+    }
+});
@@ -0,0 +1,143 @@
+//! Binding a payment to one request of an off-chain API.
+//!
+//! A server answering `402 Payment Required` issues
+//! [`PaymentRequirements`] from its [`NonceLedger`]: what to pay and a
+//! fresh random nonce. The client calls a handler with `require_nonce =
+//! true`, passing the nonce as its `x402_nonce` argument, and the handler
+//! emits it in its payment event. Before serving, the server redeems the
+//! nonce against the paid transaction. A nonce is redeemed
+//! once and expires after the ledger's time to live, so an old paid
+//! transaction replayed against a new request carries the wrong nonce, and
+//! replayed against its own request finds it spent.
+//!
+//! The program does not check that a nonce is unused; the ledger is the
+//! only place single use is enforced, so servers sharing requests must
+//! share one ledger.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use x402_common::{X402Event, X402Params};
+
+use crate::error::X402ClientError;
+use crate::events::{decode_program_events, PaymentVerified};
+
+/// What a `402 Payment Required` response asks the client to pay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaymentRequirements {
+    /// Program whose handler must be called.
+    pub program_id: Pubkey,
+    /// The handler's `<HANDLER>_X402_PARAMS`.
+    pub params: X402Params,
+    /// Passed as the handler's `x402_nonce` argument, its last one.
+    pub nonce: [u8; 32],
+}
+
+/// Nonces a server has issued and not yet redeemed.
+#[derive(Debug)]
+pub struct NonceLedger {
+    ttl: Duration,
+    outstanding: HashMap<[u8; 32], Outstanding>,
+}
+
+#[derive(Debug)]
+struct Outstanding {
+    requirements: PaymentRequirements,
+    issued_at: Instant,
+}
+
+impl NonceLedger {
+    /// A ledger whose nonces must be redeemed within `ttl` of being issued.
+    pub fn new(ttl: Duration) -> Self {
+        NonceLedger {
+            ttl,
+            outstanding: HashMap::new(),
+        }
+    }
+
+    /// Issues a fresh nonce for one request to pay `params` to
+    /// `program_id`, forgetting nonces that have expired.
+    pub fn issue(&mut self, program_id: &Pubkey, params: X402Params) -> PaymentRequirements {
+        let ttl = self.ttl;
+        self.outstanding.retain(|_, outstanding| outstanding.issued_at.elapsed() < ttl);
+
+        let requirements = PaymentRequirements {
+            program_id: *program_id,
+            params,
+            nonce: rand::random(),
+        };
+        self.outstanding.insert(
+            requirements.nonce,
+            Outstanding {
+                requirements,
+                issued_at: Instant::now(),
+            },
+        );
+        requirements
+    }
+
+    /// Redeems `nonce` against `events`, the events its program logged in
+    /// the paid transaction (see [`decode_program_events`]), and returns
+    /// the payment that carried it.
+    ///
+    /// Fails with `StaleNonce` when the nonce was never issued, was
+    /// already redeemed or has expired, and with `NonceNotPaid` when no
+    /// payment of the requirements carries it; the nonce stays redeemable
+    /// in that case.
+    pub fn redeem(
+        &mut self,
+        nonce: &[u8; 32],
+        events: &[X402Event],
+    ) -> Result<PaymentVerified, X402ClientError> {
+        let params = self.outstanding(nonce)?.params;
+        let payment = events
+            .iter()
+            .filter_map(PaymentVerified::from_event)
+            .find(|payment| {
+                payment.nonce == Some(*nonce) && payment.recipient == params.recipient && payment.price == params.price
+            })
+            .ok_or(X402ClientError::NonceNotPaid)?;
+
+        self.outstanding.remove(nonce);
+        Ok(payment)
+    }
+
+    /// [`redeem`](Self::redeem) against the transaction with `signature`,
+    /// which must have succeeded.
+    pub fn redeem_signature(
+        &mut self,
+        rpc: &RpcClient,
+        signature: &Signature,
+        nonce: &[u8; 32],
+    ) -> Result<PaymentVerified, X402ClientError> {
+        let program_id = self.outstanding(nonce)?.program_id;
+        let transaction = rpc.get_transaction_with_config(
+            signature,
+            RpcTransactionConfig {
+                commitment: Some(rpc.commitment()),
+                max_supported_transaction_version: Some(0),
+                ..RpcTransactionConfig::default()
+            },
+        )?;
+        // A failed transaction still logs the events it rolled back.
+        let logs: Option<Vec<String>> = match transaction.transaction.meta {
+            Some(meta) if meta.err.is_none() => meta.log_messages.into(),
+            _ => None,
+        };
+
+        self.redeem(nonce, &decode_program_events(&logs.unwrap_or_default(), &program_id))
+    }
+
+    fn outstanding(&self, nonce: &[u8; 32]) -> Result<PaymentRequirements, X402ClientError> {
+        self.outstanding
+            .get(nonce)
+            .filter(|outstanding| outstanding.issued_at.elapsed() < self.ttl)
+            .map(|outstanding| outstanding.requirements)
+            .ok_or(X402ClientError::StaleNonce)
+    }
+}
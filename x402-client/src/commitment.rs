@@ -0,0 +1,84 @@
+//! Checking a program's payment history against its commitment log.
+//!
+//! Handlers with `commitment_log = true` emit `X402PaymentCommittedEvent`,
+//! which carries the commitment before and after each payment. Replaying
+//! the events through `x402_common::commitment::next_commitment` checks
+//! that none was altered, and that none is missing between the first and
+//! the last, without reading any account. The stats account, decoded with
+//! [`decode_stats`], holds the commitment after the last one.
+
+use x402_common::commitment::next_commitment;
+use x402_common::events::X402PaymentCommittedEvent;
+use x402_common::stats::{StatsData, X402_STATS_DISCRIMINATOR};
+
+use crate::error::X402ClientError;
+
+/// Where a commitment chain stops holding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CommitmentBreak {
+    /// Position of the offending event in the slice checked.
+    pub index: usize,
+    /// Its `sequence`, as reported; not trusted.
+    pub sequence: u64,
+    pub kind: BreakKind,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BreakKind {
+    /// The event does not continue from the commitment before it: events
+    /// are missing or out of order, or the previous value was altered.
+    Unlinked,
+    /// The event's fields do not hash to its commitment.
+    Altered,
+}
+
+/// Checks that `events`, in the order they were emitted, form an unbroken
+/// chain, trusting the first event's `previous_commitment`.
+pub fn verify_commitment_chain(events: &[X402PaymentCommittedEvent]) -> Result<(), CommitmentBreak> {
+    match events.first() {
+        Some(first) => verify_commitment_chain_from(&first.previous_commitment, events),
+        None => Ok(()),
+    }
+}
+
+/// [`verify_commitment_chain`] from a known `start`, such as
+/// `GENESIS_COMMITMENT` for a history from the stats account's creation or
+/// a commitment read from `X402Stats` earlier.
+pub fn verify_commitment_chain_from(
+    start: &[u8; 32],
+    events: &[X402PaymentCommittedEvent],
+) -> Result<(), CommitmentBreak> {
+    let mut expected = *start;
+    for (index, event) in events.iter().enumerate() {
+        let broken = |kind| CommitmentBreak {
+            index,
+            sequence: event.sequence,
+            kind,
+        };
+        if event.previous_commitment != expected {
+            return Err(broken(BreakKind::Unlinked));
+        }
+        let commitment = next_commitment(
+            &event.previous_commitment,
+            &event.payer,
+            event.amount,
+            event.slot,
+            &event.instruction_tag,
+        );
+        if commitment != event.commitment {
+            return Err(broken(BreakKind::Altered));
+        }
+        expected = commitment;
+    }
+    Ok(())
+}
+
+/// Decodes raw `X402Stats` account data, the zero-copy layout's bytes.
+pub fn decode_stats(data: &[u8]) -> Result<StatsData, X402ClientError> {
+    let body = data
+        .strip_prefix(&X402_STATS_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not an X402Stats account".to_string()))?;
+
+    bytemuck::try_pod_read_unaligned(body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed stats: {}", err)))
+}
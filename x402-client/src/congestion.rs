@@ -0,0 +1,70 @@
+//! Quoting congestion-surcharged prices before paying them.
+//!
+//! A handler with `congestion_surcharge(...)` enforces its price raised by
+//! the level in its congestion account at the time the call executes.
+//! Reading the same account here, just before building the payment, keeps
+//! the transfer in step with the check.
+
+use anchor_lang::AnchorDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use x402_common::congestion::{CongestionLevelData, CONGESTION_LEVEL_DISCRIMINATOR};
+use x402_runtime::congestion::CongestionSurcharge;
+use x402_runtime::{Currency, X402Params};
+
+use crate::error::X402ClientError;
+
+/// Fetches the level held by `surcharge`'s congestion account.
+pub fn fetch_congestion_level(rpc: &RpcClient, surcharge: &CongestionSurcharge) -> Result<u8, X402ClientError> {
+    let data = rpc.get_account_data(&surcharge.account)?;
+    decode_congestion_level(&data).map(|congestion| congestion.level)
+}
+
+/// Decodes raw `CongestionLevel` account data.
+pub fn decode_congestion_level(data: &[u8]) -> Result<CongestionLevelData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&CONGESTION_LEVEL_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not a CongestionLevel account".to_string()))?;
+
+    CongestionLevelData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed congestion level: {}", err)))
+}
+
+/// `params` as the check will enforce them at the current congestion
+/// level. Handlers without a surcharge are charged `params` as they are.
+pub fn quote_params(
+    rpc: &RpcClient,
+    params: &X402Params,
+    surcharge: Option<&CongestionSurcharge>,
+) -> Result<X402Params, X402ClientError> {
+    let Some(surcharge) = surcharge else {
+        return Ok(*params);
+    };
+    let level = fetch_congestion_level(rpc, surcharge)?;
+    surcharged_params(params, surcharge, level)
+}
+
+/// `params` raised by `surcharge` at `level`, as the check computes it.
+pub fn surcharged_params(
+    params: &X402Params,
+    surcharge: &CongestionSurcharge,
+    level: u8,
+) -> Result<X402Params, X402ClientError> {
+    surcharge
+        .apply(params, level)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("congestion level {}: {}", level, err)))
+}
+
+/// The transfer that pays for a call priced at `params`, to place
+/// immediately before it; `None` for token prices, whose transfer needs
+/// the mint's decimals and token program (see [`crate::token`] for
+/// building them). `params`
+/// should come from [`quote_params`] for surcharged handlers.
+pub fn payment_instruction(payer: &Pubkey, params: &X402Params) -> Option<Instruction> {
+    match params.currency {
+        Currency::Sol => Some(system_instruction::transfer(payer, &params.recipient, params.price)),
+        Currency::Token(_) => None,
+    }
+}
@@ -0,0 +1,249 @@
+//! Exporting payment events exactly once, through the program's
+//! `IndexerCursor`.
+//!
+//! An indexer reads the events after its cursor with
+//! [`consume_events_since`], stores them, and only then advances the
+//! cursor with [`EventBatch::confirm`]. If it stops between the two, the
+//! next run returns the same batch again, and once the cursor has moved a
+//! batch is never returned again. Events are ordered and checked against
+//! the `X402Stats` sequence, so a payment whose logs cannot be found fails
+//! the batch instead of being skipped.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use x402_common::cursor::{
+    IndexerCursorData, ADVANCE_CURSOR_DISCRIMINATOR, CREATE_CURSOR_DISCRIMINATOR, INDEXER_CURSOR_DISCRIMINATOR,
+};
+use x402_common::seeds::stats_address;
+
+use crate::commitment::decode_stats;
+use crate::error::X402ClientError;
+use crate::events::{decode_x402_events, ReceivedEvent};
+
+pub use x402_common::seeds::indexer_cursor_address;
+
+/// Most signatures `getSignaturesForAddress` returns per request.
+const SIGNATURES_LIMIT: usize = 1_000;
+
+/// The payment events after an indexer's cursor, to store before calling
+/// [`EventBatch::confirm`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EventBatch {
+    pub indexer_id: String,
+    /// Sequence the cursor was at when the batch was read.
+    pub after: u64,
+    /// Sequence the cursor moves to on confirmation; equal to `after` when
+    /// there was nothing new.
+    pub through: u64,
+    /// Events of every payment in `after + 1..=through`, in sequence order.
+    pub events: Vec<ReceivedEvent>,
+}
+
+impl EventBatch {
+    /// `advance_cursor` to `through`, or `None` for an empty batch.
+    pub fn advance_instruction(&self, program_id: &Pubkey, creator: &Pubkey) -> Option<Instruction> {
+        (self.through > self.after)
+            .then(|| advance_cursor_instruction(program_id, creator, &self.indexer_id, self.through))
+    }
+
+    /// Advances the cursor past this batch once its events are stored.
+    /// Returns `None` for an empty batch, which sends nothing.
+    pub fn confirm(
+        &self,
+        rpc: &RpcClient,
+        program_id: &Pubkey,
+        creator: &Keypair,
+    ) -> Result<Option<Signature>, X402ClientError> {
+        let Some(instruction) = self.advance_instruction(program_id, &creator.pubkey()) else {
+            return Ok(None);
+        };
+        let blockhash = rpc.get_latest_blockhash()?;
+        let transaction =
+            Transaction::new_signed_with_payer(&[instruction], Some(&creator.pubkey()), &[creator], blockhash);
+        Ok(Some(rpc.send_and_confirm_transaction(&transaction)?))
+    }
+}
+
+/// `create_cursor(indexer_id)` of `program_id`, signed and paid for by
+/// `creator`, who alone may advance it.
+pub fn create_cursor_instruction(program_id: &Pubkey, creator: &Pubkey, indexer_id: &str) -> Instruction {
+    let mut data = CREATE_CURSOR_DISCRIMINATOR.to_vec();
+    indexer_id.to_string().serialize(&mut data).expect("serializing into a Vec cannot fail");
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*creator, true),
+            AccountMeta::new(indexer_cursor_address(program_id, indexer_id), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// `advance_cursor(indexer_id, new_sequence)` of `program_id`, signed by
+/// the cursor's `creator`.
+pub fn advance_cursor_instruction(
+    program_id: &Pubkey,
+    creator: &Pubkey,
+    indexer_id: &str,
+    new_sequence: u64,
+) -> Instruction {
+    let mut data = ADVANCE_CURSOR_DISCRIMINATOR.to_vec();
+    (indexer_id.to_string(), new_sequence)
+        .serialize(&mut data)
+        .expect("serializing into a Vec cannot fail");
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*creator, true),
+            AccountMeta::new(indexer_cursor_address(program_id, indexer_id), false),
+            AccountMeta::new_readonly(stats_address(program_id), false),
+        ],
+        data,
+    }
+}
+
+/// Fetches the cursor of the indexer named `indexer_id`, or `None` when it
+/// has not been created.
+pub fn fetch_indexer_cursor(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    indexer_id: &str,
+) -> Result<Option<IndexerCursorData>, X402ClientError> {
+    let address = indexer_cursor_address(program_id, indexer_id);
+    let Some(account) = rpc.get_account_with_commitment(&address, rpc.commitment())?.value else {
+        return Ok(None);
+    };
+
+    if account.owner != *program_id {
+        return Err(X402ClientError::InvalidAccount(format!(
+            "indexer cursor {} is owned by {}, not {}",
+            address, account.owner, program_id
+        )));
+    }
+
+    decode_indexer_cursor(&account.data).map(Some)
+}
+
+/// Decodes raw `IndexerCursor` account data.
+pub fn decode_indexer_cursor(data: &[u8]) -> Result<IndexerCursorData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&INDEXER_CURSOR_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not an IndexerCursor account".to_string()))?;
+
+    IndexerCursorData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed indexer cursor: {}", err)))
+}
+
+/// Reads the payment events after the cursor of the indexer named
+/// `indexer_id`, up to the latest payment counted in `X402Stats`.
+///
+/// The program's transactions are read newest first until the payment
+/// right after the cursor is found, so a cursor far behind costs one
+/// `getTransaction` per transaction since. Only events carrying a payment
+/// sequence are returned; see `X402Event::sequence`.
+pub fn consume_events_since(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    indexer_id: &str,
+) -> Result<EventBatch, X402ClientError> {
+    let after = fetch_indexer_cursor(rpc, program_id, indexer_id)?
+        .ok_or_else(|| X402ClientError::InvalidAccount(format!("indexer cursor {:?} does not exist", indexer_id)))?
+        .last_sequence;
+    let through = decode_stats(&rpc.get_account_data(&stats_address(program_id))?)?.sequence;
+
+    let mut received = Vec::new();
+    let mut before = None;
+    while through > after && !received_from(&received, after + 1) {
+        let signatures = rpc.get_signatures_for_address_with_config(
+            program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                until: None,
+                limit: Some(SIGNATURES_LIMIT),
+                commitment: Some(rpc.commitment()),
+            },
+        )?;
+        // A signature the node returned malformed ends the walk; the
+        // payments it hides surface as `MissingEvents`.
+        let Some(oldest) = signatures.last().and_then(|status| status.signature.parse().ok()) else {
+            break;
+        };
+        before = Some(oldest);
+
+        // Failed transactions were rolled back along with their events.
+        for status in signatures.iter().filter(|status| status.err.is_none()) {
+            let Ok(signature) = status.signature.parse::<Signature>() else {
+                continue;
+            };
+            let transaction = rpc.get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    commitment: Some(rpc.commitment()),
+                    max_supported_transaction_version: Some(0),
+                    ..RpcTransactionConfig::default()
+                },
+            )?;
+            let logs: Option<Vec<String>> = transaction.transaction.meta.and_then(|meta| meta.log_messages.into());
+            received.extend(decode_x402_events(&logs.unwrap_or_default()).into_iter().map(|event| ReceivedEvent {
+                slot: transaction.slot,
+                signature: status.signature.clone(),
+                event,
+            }));
+        }
+    }
+
+    Ok(EventBatch {
+        indexer_id: indexer_id.to_string(),
+        after,
+        through,
+        events: events_between(after, through, received)?,
+    })
+}
+
+/// Orders the events of payments `after + 1..=through` by sequence,
+/// dropping every other event and any transaction read twice.
+///
+/// Fails with `MissingEvents` naming the first payment in the range none
+/// of `received` reports, whose logs were truncated or are no longer kept
+/// by the RPC node.
+pub fn events_between(
+    after: u64,
+    through: u64,
+    received: impl IntoIterator<Item = ReceivedEvent>,
+) -> Result<Vec<ReceivedEvent>, X402ClientError> {
+    let mut by_sequence: BTreeMap<u64, Vec<ReceivedEvent>> = BTreeMap::new();
+    for event in received {
+        let Some(sequence) = event.event.sequence() else {
+            continue;
+        };
+        if sequence <= after || sequence > through {
+            continue;
+        }
+        let payment = by_sequence.entry(sequence).or_default();
+        // Every event of a payment shares its transaction; a second copy
+        // of one is a re-read, not another payment.
+        if payment.iter().all(|kept| kept.signature == event.signature && *kept != event) {
+            payment.push(event);
+        }
+    }
+
+    if let Some(missing) = (after + 1..=through).find(|sequence| !by_sequence.contains_key(sequence)) {
+        return Err(X402ClientError::MissingEvents(missing));
+    }
+    Ok(by_sequence.into_values().flatten().collect())
+}
+
+fn received_from(received: &[ReceivedEvent], sequence: u64) -> bool {
+    received.iter().any(|event| event.event.sequence() == Some(sequence))
+}
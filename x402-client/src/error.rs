@@ -0,0 +1,62 @@
+use std::fmt;
+
+use solana_client::client_error::ClientError;
+use solana_client::pubsub_client::PubsubClientError;
+use solana_sdk::transaction::TransactionError;
+
+#[derive(Debug)]
+pub enum X402ClientError {
+    /// The RPC request itself failed. Boxed, as is `Pubsub`, so every
+    /// `Result` of this crate stays small.
+    Rpc(Box<ClientError>),
+    /// The websocket subscription failed.
+    Pubsub(Box<PubsubClientError>),
+    /// An account exists but does not hold the expected x402 data.
+    InvalidAccount(String),
+    /// A simulated transaction failed; `logs` usually name the x402 check.
+    SimulationFailed {
+        err: TransactionError,
+        logs: Vec<String>,
+    },
+    /// A simulation succeeded but its return data could not be decoded.
+    InvalidReturnData(String),
+    /// No event reports the payment with this `X402Stats` sequence; its
+    /// logs were truncated or the RPC node no longer keeps them.
+    MissingEvents(u64),
+    /// A payment nonce was never issued, was already redeemed or expired.
+    StaleNonce,
+    /// No payment in the transaction carries the nonce.
+    NonceNotPaid,
+    /// A call cannot be built as its handler's layout requires.
+    Layout(String),
+}
+
+impl fmt::Display for X402ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            X402ClientError::Rpc(err) => write!(f, "rpc error: {}", err),
+            X402ClientError::Pubsub(err) => write!(f, "pubsub error: {}", err),
+            X402ClientError::InvalidAccount(reason) => write!(f, "invalid account: {}", reason),
+            X402ClientError::SimulationFailed { err, .. } => write!(f, "simulation failed: {}", err),
+            X402ClientError::InvalidReturnData(reason) => write!(f, "invalid return data: {}", reason),
+            X402ClientError::MissingEvents(sequence) => write!(f, "no events found for payment {}", sequence),
+            X402ClientError::StaleNonce => write!(f, "payment nonce is unknown, spent or expired"),
+            X402ClientError::NonceNotPaid => write!(f, "no payment carries the nonce"),
+            X402ClientError::Layout(reason) => write!(f, "cannot build the call: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for X402ClientError {}
+
+impl From<ClientError> for X402ClientError {
+    fn from(err: ClientError) -> Self {
+        X402ClientError::Rpc(Box::new(err))
+    }
+}
+
+impl From<PubsubClientError> for X402ClientError {
+    fn from(err: PubsubClientError) -> Self {
+        X402ClientError::Pubsub(Box::new(err))
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_client::pubsub_client::{LogsSubscription, PubsubClient};
+use solana_client::rpc_config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_program::pubkey::Pubkey;
+use x402_common::X402Event;
+
+use crate::error::X402ClientError;
+
+const PROGRAM_DATA: &str = "Program data: ";
+const LOG_TRUNCATED: &str = "Log truncated";
+
+/// Decodes every x402 event in a transaction's log messages.
+///
+/// `Program data:` lines are decoded wherever they appear, so events
+/// emitted from inner (CPI) instructions are included. Lines that are not
+/// valid base64 or not x402 events are skipped, and decoding stops at the
+/// runtime's `Log truncated` marker since later lines are missing.
+pub fn decode_x402_events(logs: &[String]) -> Vec<X402Event> {
+    let mut events = Vec::new();
+
+    for line in logs {
+        if line.starts_with(LOG_TRUNCATED) {
+            break;
+        }
+        events.extend(decode_line(line));
+    }
+
+    events
+}
+
+/// Like [`decode_x402_events`], but keeps only the events `program_id`
+/// itself logged, following the runtime's `invoke` and `success`/`failed`
+/// lines to tell which program was running. Any program can log an x402
+/// event; only this one's are evidence of its checks.
+pub fn decode_program_events(logs: &[String], program_id: &Pubkey) -> Vec<X402Event> {
+    let program = program_id.to_string();
+    let mut running: Vec<&str> = Vec::new();
+    let mut events = Vec::new();
+
+    for line in logs {
+        if line.starts_with(LOG_TRUNCATED) {
+            break;
+        }
+        let Some(rest) = line.strip_prefix("Program ") else {
+            continue;
+        };
+        let (id, status) = rest.split_once(' ').unwrap_or((rest, ""));
+        if status.starts_with("invoke [") {
+            running.push(id);
+        } else if running.last() == Some(&id) && (status == "success" || status.starts_with("failed")) {
+            running.pop();
+        } else if running.last() == Some(&program.as_str()) {
+            events.extend(decode_line(line));
+        }
+    }
+
+    events
+}
+
+fn decode_line(line: &str) -> Option<X402Event> {
+    let payload = line.strip_prefix(PROGRAM_DATA)?;
+    // An event line may carry several space-separated base64 chunks; the
+    // event is the first one.
+    let encoded = payload.split_whitespace().next()?;
+    STANDARD.decode(encoded).ok().and_then(|data| X402Event::decode(&data))
+}
+
+/// An accepted payment as any version of the payment event reports it.
+/// Each version has its own event type (see `x402_common::events`); this
+/// is the one shape indexers store.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentVerified {
+    /// 1 for `X402PaymentVerifiedEvent`, 2 for `X402PaymentVerifiedEventV2`.
+    pub version: u8,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub price: u64,
+    pub sequence: u64,
+    pub experiment_arm: Option<u8>,
+    pub nonce: Option<[u8; 32]>,
+}
+
+impl PaymentVerified {
+    /// The payment `event` reports, if it is a payment event of any
+    /// version.
+    pub fn from_event(event: &X402Event) -> Option<Self> {
+        match event {
+            X402Event::PaymentVerified(event) => Some(PaymentVerified {
+                version: 1,
+                payer: event.payer,
+                recipient: event.recipient,
+                amount: event.amount,
+                price: event.price,
+                sequence: event.sequence,
+                experiment_arm: event.experiment_arm,
+                nonce: event.nonce,
+            }),
+            X402Event::PaymentVerifiedV2(event) => Some(PaymentVerified {
+                version: event.version,
+                payer: event.payer,
+                recipient: event.recipient,
+                amount: event.amount,
+                price: event.price,
+                sequence: event.sequence,
+                experiment_arm: event.experiment_arm,
+                nonce: event.nonce,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// Decodes the payment events of every version in a transaction's log
+/// messages, as [`decode_x402_events`] does, into one shape.
+pub fn decode_payments_verified(logs: &[String]) -> Vec<PaymentVerified> {
+    decode_x402_events(logs).iter().filter_map(PaymentVerified::from_event).collect()
+}
+
+/// An event received from a live logs subscription.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReceivedEvent {
+    pub slot: u64,
+    pub signature: String,
+    pub event: X402Event,
+}
+
+/// Blocking iterator over x402 events of one program, built on the
+/// websocket `logsSubscribe` API. Failed transactions are skipped: their
+/// events were rolled back with them.
+pub struct X402EventStream {
+    subscription: LogsSubscription,
+    pending: VecDeque<ReceivedEvent>,
+}
+
+/// Subscribes to the logs of `program_id` on `pubsub_url` (e.g.
+/// `ws://localhost:8900`) and yields decoded x402 events.
+pub fn subscribe_x402_events(
+    pubsub_url: &str,
+    program_id: &Pubkey,
+) -> Result<X402EventStream, X402ClientError> {
+    let subscription = PubsubClient::logs_subscribe(
+        pubsub_url,
+        RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+        RpcTransactionLogsConfig { commitment: None },
+    )?;
+
+    Ok(X402EventStream {
+        subscription,
+        pending: VecDeque::new(),
+    })
+}
+
+impl Iterator for X402EventStream {
+    type Item = ReceivedEvent;
+
+    fn next(&mut self) -> Option<ReceivedEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+
+            let response = self.subscription.1.recv().ok()?;
+            if response.value.err.is_some() {
+                continue;
+            }
+
+            let slot = response.context.slot;
+            let signature = response.value.signature;
+            self.pending.extend(
+                decode_x402_events(&response.value.logs)
+                    .into_iter()
+                    .map(|event| ReceivedEvent {
+                        slot,
+                        signature: signature.clone(),
+                        event,
+                    }),
+            );
+        }
+    }
+}
@@ -0,0 +1,69 @@
+//! Showing what a transaction pays before it is signed.
+//!
+//! A wallet appends [`explain_payment_instruction`] to the transaction it
+//! is about to sign, in place of the priced call, and simulates it with
+//! [`explain`]. The program prices the call as the call itself would be
+//! priced and verifies the transfers before it, so the answer is the
+//! program's own: the simulation fails with the call's error when the
+//! transaction would not pay it.
+
+use anchor_lang::AnchorSerialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::instructions;
+use solana_sdk::transaction::Transaction;
+use x402_common::explanation::{PaymentExplanation, EXPLAIN_PAYMENT_DISCRIMINATOR};
+
+use crate::error::X402ClientError;
+use crate::simulate::{decode_return_data, simulate_return_data};
+
+/// `explain_payment(tag)` of `program_id` for `payer`, reading the price
+/// from the accounts given, as the instruction tagged `tag` reads it:
+/// `config` for a recipient the program's config holds, `congestion` for a
+/// surcharge and `experiment` for a price experiment.
+pub fn explain_payment_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    tag: &str,
+    config: Option<Pubkey>,
+    congestion: Option<Pubkey>,
+    experiment: Option<Pubkey>,
+) -> Instruction {
+    let mut data = EXPLAIN_PAYMENT_DISCRIMINATOR.to_vec();
+    tag.to_string().serialize(&mut data).expect("serializing into a Vec cannot fail");
+
+    // Anchor reads an optional account passed as the program id as absent.
+    let optional = |account: Option<Pubkey>| AccountMeta::new_readonly(account.unwrap_or(*program_id), false);
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new_readonly(*payer, false),
+            optional(config),
+            optional(congestion),
+            optional(experiment),
+            AccountMeta::new_readonly(instructions::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Simulates `transaction`, whose last instruction is `explain_payment`,
+/// and decodes what it reports. The transaction is simulated as
+/// [`simulate_priced_query`](crate::simulate_priced_query) simulates one,
+/// so nothing is paid.
+pub fn explain(rpc: &RpcClient, transaction: &Transaction) -> Result<PaymentExplanation, X402ClientError> {
+    let message = &transaction.message;
+    let program_id = message
+        .instructions
+        .last()
+        .filter(|instruction| instruction.data.starts_with(&EXPLAIN_PAYMENT_DISCRIMINATOR))
+        .and_then(|instruction| message.account_keys.get(usize::from(instruction.program_id_index)))
+        .ok_or_else(|| X402ClientError::Layout("the transaction does not end with explain_payment".to_string()))?;
+    decode_explanation(&simulate_return_data(rpc, transaction, program_id)?)
+}
+
+/// Decodes `explain_payment`'s return data, as the RPC reports it.
+pub fn decode_explanation(data: &[u8]) -> Result<PaymentExplanation, X402ClientError> {
+    decode_return_data(data)
+}
@@ -0,0 +1,112 @@
+//! Reporting rejected payment attempts to the program's `FailureLog`.
+//!
+//! A call an x402 check rejects reverts with its transaction, so the
+//! rejection leaves no event. The payer reports it afterwards with
+//! `report_failed_payment`, in a transaction of its own.
+
+use anchor_lang::AnchorSerialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use x402_common::failures::{
+    FailureLogData, FAILURE_LOG_DISCRIMINATOR, REPORT_FAILED_PAYMENT_DISCRIMINATOR,
+};
+use x402_runtime::{X402Error, X402_ERRORS, X402_ERROR_CODE_OFFSET};
+
+use crate::error::X402ClientError;
+use crate::preflight::PreflightOutcome;
+
+pub use x402_common::seeds::failure_log_address;
+
+/// The x402 error a preflight, or a classified failed call, reports.
+///
+/// `Indeterminate` preflights may still pass once sent; report those only
+/// after the call itself has failed.
+pub fn payment_failure(outcome: &PreflightOutcome) -> Option<X402Error> {
+    match outcome {
+        PreflightOutcome::PaymentRejected { error, .. } | PreflightOutcome::Indeterminate { error, .. } => {
+            Some(*error)
+        }
+        PreflightOutcome::Passes | PreflightOutcome::CallFailed { .. } => None,
+    }
+}
+
+/// The `reason` byte recorded for `error`: its index in `X402_ERRORS`,
+/// which lists the errors in code order.
+pub fn failure_reason(error: X402Error) -> u8 {
+    (u32::from(error) - X402_ERROR_CODE_OFFSET) as u8
+}
+
+/// The error a recorded `reason` byte stands for.
+pub fn reason_error(reason: u8) -> Option<X402Error> {
+    X402_ERRORS.get(reason as usize).copied()
+}
+
+/// `report_failed_payment(reason, attempted_amount)` of `program_id`,
+/// signed by `payer`, who also pays for creating the log the first time.
+pub fn report_failed_payment_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    error: X402Error,
+    attempted_amount: u64,
+) -> Instruction {
+    let mut data = REPORT_FAILED_PAYMENT_DISCRIMINATOR.to_vec();
+    (failure_reason(error), attempted_amount)
+        .serialize(&mut data)
+        .expect("serializing into a Vec cannot fail");
+
+    Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(failure_log_address(program_id), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+/// Sends `report_failed_payment` for `payer`'s rejected attempt.
+pub fn report_failed_payment(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    payer: &Keypair,
+    error: X402Error,
+    attempted_amount: u64,
+) -> Result<Signature, X402ClientError> {
+    let instruction = report_failed_payment_instruction(program_id, &payer.pubkey(), error, attempted_amount);
+    let blockhash = rpc.get_latest_blockhash()?;
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[payer], blockhash);
+    Ok(rpc.send_and_confirm_transaction(&transaction)?)
+}
+
+/// Fetches the `FailureLog` of `program_id`, or `None` when no failure has
+/// been reported yet.
+pub fn fetch_failure_log(rpc: &RpcClient, program_id: &Pubkey) -> Result<Option<FailureLogData>, X402ClientError> {
+    let address = failure_log_address(program_id);
+    let Some(account) = rpc.get_account_with_commitment(&address, rpc.commitment())?.value else {
+        return Ok(None);
+    };
+
+    if account.owner != *program_id {
+        return Err(X402ClientError::InvalidAccount(format!(
+            "failure log {} is owned by {}, not {}",
+            address, account.owner, program_id
+        )));
+    }
+
+    decode_failure_log(&account.data).map(Some)
+}
+
+/// Decodes raw `FailureLog` account data, the zero-copy layout's bytes.
+pub fn decode_failure_log(data: &[u8]) -> Result<FailureLogData, X402ClientError> {
+    let body = data
+        .strip_prefix(&FAILURE_LOG_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not a FailureLog account".to_string()))?;
+
+    bytemuck::try_pod_read_unaligned(body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed failure log: {}", err)))
+}
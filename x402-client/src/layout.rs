@@ -0,0 +1,177 @@
+//! Building a paid call from its handler's layout.
+//!
+//! Each `#[x402]` handler comes with `<handler>_x402_layout()`, the
+//! transaction its check accepts (see `x402_common::layout`). The builders
+//! here walk it, so one code path builds for every mode: the transfers a
+//! preceding payment needs, none for a deposit, an accumulator or a body
+//! that pays, and the accounts the priced instruction must carry.
+//! [`check_paid_call`] checks a transaction a wallet has added to before
+//! it is sent.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use solana_program::sysvar;
+use x402_common::layout::{CallAccount, Requirement, Trailing, TxLayout};
+use x402_common::pricing::revenue_shares;
+use x402_common::seeds::{
+    accumulator_address, config_address, deposit_address, experiment_address, reentrancy_guard_address,
+};
+use x402_common::{Currency, X402Params};
+use x402_runtime::{is_ignorable_instruction, match_payment};
+
+use crate::error::X402ClientError;
+
+/// The instructions of a transaction calling `call` as `layout` requires,
+/// paid by `payer` at `params` (normally [`crate::Quote::params`]), in
+/// order. The instructions sysvar is appended to `call` as its last
+/// account, where the handler reads it, unless `call` names it already as
+/// a declared `x402_instructions`; build `call` with any remaining accounts
+/// of the handler's own first. Any other account the check reads must be
+/// among its accounts.
+pub fn paid_call_instructions(
+    layout: &TxLayout,
+    payer: &Pubkey,
+    params: &X402Params,
+    call: Instruction,
+) -> Result<Vec<Instruction>, X402ClientError> {
+    paid_call_instructions_with(layout, payer, params, call, None)
+}
+
+/// [`paid_call_instructions`] for a handler with `recipient_from_arg`,
+/// whose layout asks for `authorization`, the Ed25519 instruction its
+/// authority signed (see `x402_common::authorization::ed25519_instruction`).
+pub fn paid_call_instructions_with(
+    layout: &TxLayout,
+    payer: &Pubkey,
+    params: &X402Params,
+    call: Instruction,
+    authorization: Option<Instruction>,
+) -> Result<Vec<Instruction>, X402ClientError> {
+    let bps: Vec<u16> = layout
+        .requirements
+        .iter()
+        .filter_map(|requirement| match requirement {
+            Requirement::Transfer { bps, .. } => Some(*bps),
+            _ => None,
+        })
+        .collect();
+    let mut amounts = revenue_shares(params.price, &bps).into_iter();
+
+    let mut call = Some(call);
+    let mut authorization = authorization;
+    let mut instructions = Vec::new();
+    for requirement in &layout.requirements {
+        match requirement {
+            Requirement::RecipientAuthorization { authority } => {
+                let authorization = authorization.take().ok_or_else(|| {
+                    X402ClientError::Layout(format!("the call needs a recipient authorization signed by {}", authority))
+                })?;
+                instructions.push(authorization);
+            }
+            Requirement::Transfer { to, currency, .. } => {
+                if let Currency::Token(mint) = currency {
+                    return Err(X402ClientError::Layout(format!(
+                        "payments in {} are built with token_payment_instructions",
+                        mint
+                    )));
+                }
+                let amount = amounts.next().unwrap_or_default();
+                instructions.push(system_instruction::transfer(payer, &to.unwrap_or(params.recipient), amount));
+            }
+            Requirement::IgnorableOk { .. } => {}
+            Requirement::ThisInstruction { accounts } => {
+                let mut call = call
+                    .take()
+                    .ok_or_else(|| X402ClientError::Layout("the layout names the call twice".to_string()))?;
+                for account in accounts {
+                    add_account(&mut call, account, payer, params)?;
+                }
+                instructions.push(call);
+            }
+        }
+    }
+    if call.is_some() {
+        return Err(X402ClientError::Layout("the layout never names the call".to_string()));
+    }
+    Ok(instructions)
+}
+
+/// Appends the instructions sysvar to `call`, or checks it carries
+/// `account` otherwise.
+fn add_account(
+    call: &mut Instruction,
+    account: &CallAccount,
+    payer: &Pubkey,
+    params: &X402Params,
+) -> Result<(), X402ClientError> {
+    let (address, name) = match account {
+        CallAccount::InstructionsSysvar => {
+            if !call.accounts.iter().any(|meta| meta.pubkey == sysvar::instructions::ID) {
+                call.accounts.push(AccountMeta::new_readonly(sysvar::instructions::ID, false));
+            }
+            return Ok(());
+        }
+        CallAccount::Recipient => (params.recipient, "recipient"),
+        CallAccount::Deposit => (deposit_address(&call.program_id, payer), "deposit"),
+        CallAccount::Config => (config_address(&call.program_id), "x402 config"),
+        CallAccount::Congestion { address } => (*address, "congestion account"),
+        CallAccount::Experiment { bucket_seed } => {
+            (experiment_address(&call.program_id, bucket_seed.as_bytes()), "experiment state")
+        }
+        CallAccount::ReentrancyGuard => (reentrancy_guard_address(&call.program_id), "reentrancy guard"),
+        CallAccount::Accumulator { tag } => (accumulator_address(&call.program_id, tag, payer), "accumulator"),
+    };
+    if call.accounts.iter().any(|meta| meta.pubkey == address) {
+        Ok(())
+    } else {
+        Err(X402ClientError::Layout(format!("the call lacks its {} {}", name, address)))
+    }
+}
+
+/// Checks `instructions`, a whole transaction calling the instruction at
+/// `call_index` as `layout` requires, for payments placed where the check
+/// never reads them: a transfer the layout asks for that only follows the
+/// call. Returns what follows the call, which is always supported.
+pub fn check_paid_call(
+    layout: &TxLayout,
+    params: &X402Params,
+    instructions: &[Instruction],
+    call_index: usize,
+) -> Result<Vec<Trailing>, X402ClientError> {
+    let call = instructions
+        .get(call_index)
+        .ok_or_else(|| X402ClientError::Layout(format!("the transaction has no instruction {}", call_index)))?;
+    let (before, after) = (&instructions[..call_index], &instructions[call_index + 1..]);
+    let pays = |ix: &Instruction, to: &Pubkey| {
+        match_payment(ix, &call.program_id).is_ok_and(|payment| payment.recipient == *to)
+    };
+
+    for requirement in &layout.requirements {
+        let Requirement::Transfer { to, .. } = requirement else {
+            continue;
+        };
+        let to = to.unwrap_or(params.recipient);
+        if !before.iter().any(|ix| pays(ix, &to)) && after.iter().any(|ix| pays(ix, &to)) {
+            return Err(X402ClientError::Layout(format!(
+                "the payment to {} follows the call, where its check never reads it",
+                to
+            )));
+        }
+    }
+
+    Ok(after
+        .iter()
+        .map(|ix| {
+            if is_ignorable_instruction(ix) {
+                Trailing::Ignorable
+            } else if match_payment(ix, &call.program_id).is_ok() {
+                Trailing::Payment
+            } else if ix.program_id == call.program_id {
+                Trailing::SameProgram
+            } else {
+                Trailing::OtherProgram
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,61 @@
+//! Off-chain helpers for programs gated with `#[x402]`.
+
+pub mod challenge;
+pub mod commitment;
+pub mod congestion;
+pub mod cursor;
+pub mod error;
+pub mod events;
+pub mod explain;
+pub mod failures;
+pub mod layout;
+pub mod listing;
+pub mod manifest;
+pub mod preflight;
+pub mod provenance;
+pub mod quote;
+pub mod retry;
+pub mod simulate;
+pub mod snapshot;
+pub mod split;
+pub mod token;
+pub mod units;
+pub mod verify;
+
+pub use challenge::{NonceLedger, PaymentRequirements};
+pub use commitment::{decode_stats, verify_commitment_chain, verify_commitment_chain_from, BreakKind, CommitmentBreak};
+pub use congestion::{
+    decode_congestion_level, fetch_congestion_level, payment_instruction, quote_params, surcharged_params,
+};
+pub use cursor::{
+    advance_cursor_instruction, consume_events_since, create_cursor_instruction, events_between, fetch_indexer_cursor,
+    indexer_cursor_address, EventBatch,
+};
+pub use error::X402ClientError;
+pub use events::{
+    decode_payments_verified, decode_program_events, decode_x402_events, subscribe_x402_events, PaymentVerified,
+    ReceivedEvent, X402EventStream,
+};
+pub use explain::{decode_explanation, explain, explain_payment_instruction};
+pub use failures::{
+    failure_log_address, fetch_failure_log, payment_failure, report_failed_payment, report_failed_payment_instruction,
+};
+pub use layout::{check_paid_call, paid_call_instructions, paid_call_instructions_with};
+pub use listing::{list_accounts, list_payment_ledgers, summarize_ledgers, Page, PageRequest, PayerTotals};
+pub use manifest::{decode_manifest, fetch_manifest, manifest_address};
+pub use preflight::{preflight_paid_call, x402_error, Preflight, PreflightOutcome};
+pub use provenance::{audit_price, fetch_pricing_state, PricingState, ProvenanceMismatch};
+pub use quote::{
+    decode_price_experiment, fetch_experiment_state, quote, quote_for_payer, quote_with, quote_with_payer,
+    PricedInstruction, Quote, QuoteComponent,
+};
+pub use retry::{check_before_retry, compute_result_address, decode_compute_result, retry_decision, RetryDecision};
+pub use simulate::{decode_return_data, simulate_priced_query};
+pub use snapshot::{fetch_ledger_tree, InclusionProof, LedgerTree};
+pub use split::revenue_split_instructions;
+pub use token::{
+    create_ata_idempotent, recipient_ata, recipient_ata_with, token_payment_instructions,
+    token_payment_instructions_with, TokenPaymentContext,
+};
+pub use units::{Lamports, TokenAmount, UiSol};
+pub use verify::verify_message_payment;
@@ -0,0 +1,179 @@
+//! Paginated listing of a program's accounts for dashboards, without an
+//! indexer.
+//!
+//! `getProgramAccounts` has no pagination of its own, so a listing first
+//! asks for matching addresses only (an empty `dataSlice`), orders them by
+//! key and then fetches one page of full accounts with
+//! `getMultipleAccounts`. The page's `next` cursor is the last key
+//! returned; accounts created or closed between pages may be missed or
+//! appear at a later position, as with any key-ordered cursor.
+
+use std::collections::BTreeMap;
+
+use anchor_lang::AnchorDeserialize;
+use solana_account_decoder::{UiAccountEncoding, UiDataSliceConfig};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig};
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_program::pubkey::Pubkey;
+use x402_common::ledger::{PaymentLedgerData, PAYMENT_LEDGER_DISCRIMINATOR, PAYMENT_LEDGER_PAYER_OFFSET};
+
+use crate::error::X402ClientError;
+
+/// Most accounts `getMultipleAccounts` returns per request.
+const MULTIPLE_ACCOUNTS_LIMIT: usize = 100;
+
+/// Where a listing starts and how much it returns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Return accounts with keys strictly after this one; `None` starts
+    /// from the first.
+    pub after: Option<Pubkey>,
+    pub limit: usize,
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        PageRequest { after: None, limit: 100 }
+    }
+}
+
+/// One page of decoded accounts, ordered by address.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<(Pubkey, T)>,
+    /// Pass as `PageRequest::after` for the next page; `None` on the last.
+    pub next: Option<Pubkey>,
+}
+
+/// Totals of one payer's ledgers, as folded by [`summarize_ledgers`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PayerTotals {
+    pub total_payments: u64,
+    pub total_amount: u64,
+    /// Latest `last_payment` across the payer's ledgers.
+    pub last_payment: i64,
+    pub total_rent_paid: u64,
+}
+
+/// Lists one page of `program_id`'s accounts whose data starts with
+/// `discriminator` and matches `filters`, decoding each body (the data
+/// after the discriminator) with `decode`.
+pub fn list_accounts<T>(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    discriminator: [u8; 8],
+    filters: Vec<RpcFilterType>,
+    page: PageRequest,
+    decode: impl Fn(&[u8]) -> Result<T, X402ClientError>,
+) -> Result<Page<T>, X402ClientError> {
+    let mut all_filters = vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &discriminator))];
+    all_filters.extend(filters);
+
+    let keys = rpc
+        .get_program_accounts_with_config(
+            program_id,
+            RpcProgramAccountsConfig {
+                filters: Some(all_filters),
+                account_config: RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    data_slice: Some(UiDataSliceConfig { offset: 0, length: 0 }),
+                    commitment: Some(rpc.commitment()),
+                    min_context_slot: None,
+                },
+                with_context: None,
+            },
+        )?
+        .into_iter()
+        .map(|(key, _)| key)
+        .collect();
+
+    let (keys, next) = page_keys(keys, page);
+    let mut items = Vec::with_capacity(keys.len());
+    for chunk in keys.chunks(MULTIPLE_ACCOUNTS_LIMIT) {
+        let accounts = rpc.get_multiple_accounts(chunk)?;
+        for (key, account) in chunk.iter().zip(accounts) {
+            // Closed since the key listing.
+            let Some(account) = account else {
+                continue;
+            };
+            let body = account
+                .data
+                .strip_prefix(&discriminator[..])
+                .ok_or_else(|| X402ClientError::InvalidAccount(format!("{} changed type while listing", key)))?;
+            items.push((*key, decode(body)?));
+        }
+    }
+
+    Ok(Page { items, next })
+}
+
+/// Orders `keys` and selects the page after `page.after`, returning it
+/// with the cursor for the following page.
+pub fn page_keys(mut keys: Vec<Pubkey>, page: PageRequest) -> (Vec<Pubkey>, Option<Pubkey>) {
+    keys.sort_unstable();
+    keys.dedup();
+
+    let start = match page.after {
+        Some(after) => keys.partition_point(|key| *key <= after),
+        None => 0,
+    };
+    let end = start.saturating_add(page.limit).min(keys.len());
+    let next = (end < keys.len() && end > start).then(|| keys[end - 1]);
+    (keys[start..end].to_vec(), next)
+}
+
+/// Filters restricting a ledger listing to `payer`, if given.
+pub fn ledger_filters(payer: Option<&Pubkey>) -> Vec<RpcFilterType> {
+    payer
+        .map(|payer| {
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(PAYMENT_LEDGER_PAYER_OFFSET, payer.as_ref()))
+        })
+        .into_iter()
+        .collect()
+}
+
+/// Decodes a `PaymentLedger` body, after the discriminator.
+pub fn decode_payment_ledger(mut body: &[u8]) -> Result<PaymentLedgerData, X402ClientError> {
+    PaymentLedgerData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed payment ledger: {}", err)))
+}
+
+/// Lists one page of the program's `PaymentLedger` accounts, optionally
+/// only `payer`'s.
+pub fn list_payment_ledgers(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    payer: Option<&Pubkey>,
+    page: PageRequest,
+) -> Result<Page<PaymentLedgerData>, X402ClientError> {
+    list_accounts(
+        rpc,
+        program_id,
+        PAYMENT_LEDGER_DISCRIMINATOR,
+        ledger_filters(payer),
+        page,
+        decode_payment_ledger,
+    )
+}
+
+/// Folds ledgers into totals per payer. Ledgers whose last payment is
+/// before `since` (a unix timestamp) are left out, so `since = now - 86400`
+/// keeps payers active in the last day.
+pub fn summarize_ledgers<'a>(
+    ledgers: impl IntoIterator<Item = &'a PaymentLedgerData>,
+    since: Option<i64>,
+) -> BTreeMap<Pubkey, PayerTotals> {
+    let mut totals: BTreeMap<Pubkey, PayerTotals> = BTreeMap::new();
+    for ledger in ledgers {
+        if since.is_some_and(|since| ledger.last_payment < since) {
+            continue;
+        }
+        let entry = totals.entry(ledger.payer).or_default();
+        entry.total_payments = entry.total_payments.saturating_add(ledger.total_payments);
+        entry.total_amount = entry.total_amount.saturating_add(ledger.total_amount);
+        entry.last_payment = entry.last_payment.max(ledger.last_payment);
+        entry.total_rent_paid = entry.total_rent_paid.saturating_add(ledger.total_rent_paid);
+    }
+    totals
+}
@@ -0,0 +1,43 @@
+use anchor_lang::AnchorDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use x402_runtime::manifest::{ManifestData, MANIFEST_DISCRIMINATOR};
+
+use crate::error::X402ClientError;
+
+pub use x402_common::seeds::manifest_address;
+
+/// Fetches the price manifest published by `program_id`, or `None` when
+/// the program has not published one.
+pub fn fetch_manifest(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+) -> Result<Option<ManifestData>, X402ClientError> {
+    let address = manifest_address(program_id);
+    let Some(account) = rpc
+        .get_account_with_commitment(&address, rpc.commitment())?
+        .value
+    else {
+        return Ok(None);
+    };
+
+    if account.owner != *program_id {
+        return Err(X402ClientError::InvalidAccount(format!(
+            "manifest {} is owned by {}, not {}",
+            address, account.owner, program_id
+        )));
+    }
+
+    decode_manifest(&account.data).map(Some)
+}
+
+/// Decodes raw `X402Manifest` account data. Trailing bytes left over from
+/// a shrinking realloc are ignored.
+pub fn decode_manifest(data: &[u8]) -> Result<ManifestData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&MANIFEST_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not an X402Manifest account".to_string()))?;
+
+    ManifestData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed manifest: {}", err)))
+}
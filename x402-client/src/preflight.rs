@@ -0,0 +1,122 @@
+//! Simulating a paid call before sending it, so a call that would fail is
+//! caught before the payment is signed away.
+
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use x402_runtime::{X402Error, X402_ERRORS, X402_ERROR_CODE_OFFSET};
+
+use crate::error::X402ClientError;
+
+/// What a simulated paid call would do.
+#[derive(Clone, Debug)]
+pub struct Preflight {
+    pub outcome: PreflightOutcome,
+    /// Compute units the simulation consumed, when the RPC reports them.
+    pub units_consumed: Option<u64>,
+    pub logs: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PreflightOutcome {
+    /// The transaction succeeds as simulated.
+    Passes,
+    /// An x402 check would reject the payment at `instruction`; the payment
+    /// (amount, recipient, accounts) needs fixing, not the call.
+    PaymentRejected { instruction: u8, error: X402Error },
+    /// The transaction fails for a reason other than an x402 check: the
+    /// handler body, the transfer itself, or another instruction.
+    /// `instruction` is `None` for failures outside any instruction, such
+    /// as an unfunded fee payer.
+    CallFailed {
+        instruction: Option<u8>,
+        err: TransactionError,
+    },
+    /// An x402 check that reads the clock failed. The simulation ran at the
+    /// RPC node's current time, so the executed transaction may pass (a
+    /// spending cap period may have rolled over by then).
+    Indeterminate { instruction: u8, error: X402Error },
+}
+
+/// Simulates `transaction` and classifies its outcome.
+///
+/// The transaction is simulated with signature checks off and a fresh
+/// blockhash, so it may be unsigned; nothing is sent or paid.
+pub fn preflight_paid_call(rpc: &RpcClient, transaction: &Transaction) -> Result<Preflight, X402ClientError> {
+    let result = rpc
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(rpc.commitment()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+
+    Ok(classify_simulation(
+        result.err,
+        result.logs.unwrap_or_default(),
+        result.units_consumed,
+    ))
+}
+
+/// Classifies a simulation result as [`preflight_paid_call`] does.
+pub fn classify_simulation(err: Option<TransactionError>, logs: Vec<String>, units_consumed: Option<u64>) -> Preflight {
+    let outcome = match err {
+        None => PreflightOutcome::Passes,
+        Some(err) => classify_error(err, &logs),
+    };
+    Preflight { outcome, units_consumed, logs }
+}
+
+fn classify_error(err: TransactionError, logs: &[String]) -> PreflightOutcome {
+    let (instruction, code) = match &err {
+        TransactionError::InstructionError(index, InstructionError::Custom(code)) => (*index, *code),
+        TransactionError::InstructionError(index, _) => {
+            return PreflightOutcome::CallFailed { instruction: Some(*index), err };
+        }
+        _ => return PreflightOutcome::CallFailed { instruction: None, err },
+    };
+
+    // A program may still number its own errors into the x402 range, so an
+    // error logged with the code must carry the x402 name.
+    let logged = logs.iter().rev().find_map(|line| logged_anchor_error(line));
+    let error = x402_error(code).filter(|error| match logged {
+        Some((name, number)) if number == code => error.name() == name,
+        _ => true,
+    });
+
+    match error {
+        Some(error) if is_clock_dependent(error) => PreflightOutcome::Indeterminate { instruction, error },
+        Some(error) => PreflightOutcome::PaymentRejected { instruction, error },
+        None => PreflightOutcome::CallFailed { instruction: Some(instruction), err },
+    }
+}
+
+/// The x402 error with `code`, counted from `X402_ERROR_CODE_OFFSET`.
+pub fn x402_error(code: u32) -> Option<X402Error> {
+    let index = code.checked_sub(X402_ERROR_CODE_OFFSET)?;
+    X402_ERRORS.get(index as usize).copied()
+}
+
+/// Whether `error` can depend on the time the transaction executes.
+pub fn is_clock_dependent(error: X402Error) -> bool {
+    matches!(
+        error,
+        X402Error::SpendingCapExceeded | X402Error::RecipientAuthorizationExpired | X402Error::ClockUnavailable
+    )
+}
+
+/// The name and number of an error from an Anchor error log line, e.g.
+/// `Program log: AnchorError occurred. Error Code: NotAPayment. Error
+/// Number: 7003. Error Message: ...`.
+fn logged_anchor_error(line: &str) -> Option<(&str, u32)> {
+    let rest = line.strip_prefix("Program log: AnchorError")?;
+    let (_, rest) = rest.split_once("Error Code: ")?;
+    let (name, rest) = rest.split_once(". Error Number: ")?;
+    let (number, _) = rest.split_once('.')?;
+    Some((name, number.parse().ok()?))
+}
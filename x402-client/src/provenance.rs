@@ -0,0 +1,162 @@
+//! Auditing the price a stored result was charged.
+//!
+//! A `record_provenance = true` handler binds what it priced the call
+//! from, and the example program keeps it in `ComputeResult::provenance`.
+//! [`audit_price`] re-derives the price from the handler's declared
+//! requirements and the pricing account as it was at the call, with the
+//! same `x402_common::pricing` arithmetic the check ran, and reports the
+//! first way the recorded price does not follow.
+//!
+//! RPC nodes serve an account's current state only. The state at the call
+//! is best kept as a snapshot taken when the result was written;
+//! [`fetch_pricing_state`] reads the current one instead, which still
+//! audits a congestion provenance as long as the level has not changed
+//! since (its `updated_at` is unchanged).
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use x402_common::congestion::CongestionLevelData;
+use x402_common::pricing::surcharged_price;
+use x402_common::provenance::PriceProvenance;
+use x402_runtime::experiment::ExperimentState;
+
+use crate::congestion::decode_congestion_level;
+use crate::error::X402ClientError;
+use crate::quote::{fetch_experiment_state, PricedInstruction};
+
+/// A pricing account as it was when the audited call executed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PricingState {
+    /// A static price reads no account.
+    None,
+    Congestion(CongestionLevelData),
+    Experiment(ExperimentState),
+}
+
+/// Why a recorded price does not follow from its provenance.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProvenanceMismatch {
+    /// The provenance, or the state given for it, is of another kind than
+    /// the instruction's pricing, e.g. `Static` for a surcharged handler.
+    WrongKind,
+    /// The provenance names another account than the one the handler reads.
+    Account { recorded: Pubkey, expected: Pubkey },
+    /// The congestion account held neither the recorded level nor, as its
+    /// previous level, the one a grace window would have enforced.
+    Level { recorded: u8, held: u8 },
+    /// The congestion account's level changed at another time than
+    /// recorded. Against fetched state this may only mean it changed since
+    /// the call.
+    UpdatedAt { recorded: i64, held: i64 },
+    /// The payer is charged another experiment arm.
+    Arm { recorded: u8, expected: u8 },
+    /// The provenance prices the call differently.
+    Price { recorded: u64, expected: u64 },
+}
+
+/// Checks that `price`, recorded as charged to `payer` for a call to
+/// `instruction` of `program_id`, is the price `provenance` and `state`
+/// give. A `price_grace_*` handler may have accepted less, which shows as a
+/// `Price` mismatch with the lower price recorded.
+///
+/// An experiment arm is accepted if it is the payer's bucket or, once the
+/// experiment has ended, its winner: the state cannot tell whether it had
+/// ended before the call.
+pub fn audit_price(
+    instruction: &PricedInstruction,
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    provenance: &PriceProvenance,
+    state: &PricingState,
+    price: u64,
+) -> Result<(), ProvenanceMismatch> {
+    let declared = instruction.params.price;
+    let expected = match (provenance, state) {
+        (PriceProvenance::Static, PricingState::None)
+            if instruction.surcharge.is_none() && instruction.experiment.is_none() =>
+        {
+            declared
+        }
+        (
+            PriceProvenance::Congestion {
+                account,
+                level,
+                updated_at,
+            },
+            PricingState::Congestion(held),
+        ) => {
+            let Some(surcharge) = &instruction.surcharge else {
+                return Err(ProvenanceMismatch::WrongKind);
+            };
+            require_account(account, &surcharge.account)?;
+            if *level != held.level && *level != held.previous_level {
+                return Err(ProvenanceMismatch::Level {
+                    recorded: *level,
+                    held: held.level,
+                });
+            }
+            if *updated_at != held.updated_at {
+                return Err(ProvenanceMismatch::UpdatedAt {
+                    recorded: *updated_at,
+                    held: held.updated_at,
+                });
+            }
+            // A level the check could not price cannot have been charged.
+            surcharged_price(declared, *level, surcharge.bps_per_level).map_err(|_| ProvenanceMismatch::Level {
+                recorded: *level,
+                held: held.level,
+            })?
+        }
+        (PriceProvenance::Experiment { account, arm }, PricingState::Experiment(held)) => {
+            let Some(experiment) = &instruction.experiment else {
+                return Err(ProvenanceMismatch::WrongKind);
+            };
+            require_account(account, &experiment.address(program_id))?;
+            let bucket = experiment.bucket(payer);
+            if *arm != bucket && !(held.ended && *arm == held.winner) {
+                return Err(ProvenanceMismatch::Arm {
+                    recorded: *arm,
+                    expected: if held.ended { held.winner } else { bucket },
+                });
+            }
+            // An arm past the experiment's prices fails the check instead
+            // of being charged.
+            *experiment.prices.get(*arm as usize).ok_or(ProvenanceMismatch::Arm {
+                recorded: *arm,
+                expected: bucket,
+            })?
+        }
+        _ => return Err(ProvenanceMismatch::WrongKind),
+    };
+
+    if price != expected {
+        return Err(ProvenanceMismatch::Price {
+            recorded: price,
+            expected,
+        });
+    }
+    Ok(())
+}
+
+/// Fetches the current state of the account `provenance` names, to audit
+/// against when no snapshot was kept.
+pub fn fetch_pricing_state(rpc: &RpcClient, provenance: &PriceProvenance) -> Result<PricingState, X402ClientError> {
+    match provenance {
+        PriceProvenance::Static => Ok(PricingState::None),
+        PriceProvenance::Congestion { account, .. } => {
+            let data = rpc.get_account_data(account)?;
+            decode_congestion_level(&data).map(PricingState::Congestion)
+        }
+        PriceProvenance::Experiment { account, .. } => fetch_experiment_state(rpc, account).map(PricingState::Experiment),
+    }
+}
+
+fn require_account(recorded: &Pubkey, expected: &Pubkey) -> Result<(), ProvenanceMismatch> {
+    if recorded != expected {
+        return Err(ProvenanceMismatch::Account {
+            recorded: *recorded,
+            expected: *expected,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,311 @@
+//! "What will this call cost right now?" for any priced handler.
+//!
+//! A quote reads the same accounts the handler's `#[x402]` check reads and
+//! prices the call with the same `x402_common::pricing` arithmetic, so
+//! paying exactly the quoted amount to the quoted recipient is accepted as
+//! long as those accounts do not change before the call lands. Only an
+//! `experiment(...)` price depends on the payer, so only its quotes take
+//! one; no price depends on the instruction arguments yet.
+//!
+//! When they do change, a surcharged handler is protected by its
+//! `max_acceptable_price` argument: pass [`Quote::max_acceptable_price`]
+//! and a raised price fails the call instead of being charged.
+
+use anchor_lang::AnchorDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use x402_common::config::{GlobalConfigData, X402_CONFIG_DISCRIMINATOR};
+use x402_common::experiment::{PriceExperimentData, PRICE_EXPERIMENT_DISCRIMINATOR};
+use x402_common::pricing::{congestion_surcharge, PricingError};
+use x402_common::{Currency, X402Params};
+use x402_runtime::congestion::CongestionSurcharge;
+use x402_runtime::experiment::{Experiment, ExperimentState};
+use x402_runtime::revenue_split::RevenueShare;
+
+use crate::congestion::{fetch_congestion_level, payment_instruction};
+use crate::error::X402ClientError;
+use crate::split::revenue_split_instructions;
+
+pub use x402_common::seeds::config_address;
+
+/// A priced handler as its program declares it: the `<HANDLER>_X402_PARAMS`
+/// constant and, when it has them, its `<HANDLER>_X402_SURCHARGE`,
+/// `<HANDLER>_X402_EXPERIMENT` and `<HANDLER>_X402_SPLITS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PricedInstruction {
+    pub params: X402Params,
+    pub surcharge: Option<CongestionSurcharge>,
+    pub experiment: Option<Experiment>,
+    pub splits: Option<&'static [RevenueShare]>,
+}
+
+impl PricedInstruction {
+    pub fn new(params: X402Params) -> Self {
+        PricedInstruction {
+            params,
+            surcharge: None,
+            experiment: None,
+            splits: None,
+        }
+    }
+
+    pub fn with_surcharge(self, surcharge: CongestionSurcharge) -> Self {
+        PricedInstruction {
+            surcharge: Some(surcharge),
+            ..self
+        }
+    }
+
+    pub fn with_experiment(self, experiment: Experiment) -> Self {
+        PricedInstruction {
+            experiment: Some(experiment),
+            ..self
+        }
+    }
+
+    pub fn with_splits(self, splits: &'static [RevenueShare]) -> Self {
+        PricedInstruction {
+            splits: Some(splits),
+            ..self
+        }
+    }
+
+    /// The recipient comes from the program's `X402GlobalConfig`, as for
+    /// an `#[x402]` without `address`, whose params hold the zero key.
+    pub fn uses_configured_recipient(&self) -> bool {
+        self.params.recipient == Pubkey::default()
+    }
+}
+
+/// One step of a quoted price, in the order the check applies them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum QuoteComponent {
+    /// The handler's declared price.
+    Base { price: u64 },
+    /// The price of the experiment `arm` the payer is charged, in place of
+    /// `Base`.
+    ExperimentArm { arm: u8, price: u64 },
+    /// `amount` added at congestion `level`.
+    CongestionSurcharge { level: u8, bps_per_level: u16, amount: u64 },
+}
+
+impl QuoteComponent {
+    pub fn amount(&self) -> u64 {
+        match *self {
+            QuoteComponent::Base { price } | QuoteComponent::ExperimentArm { price, .. } => price,
+            QuoteComponent::CongestionSurcharge { amount, .. } => amount,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Quote {
+    /// What to pay: the sum of `components`.
+    pub amount: u64,
+    pub currency: Currency,
+    pub recipient: Pubkey,
+    pub components: Vec<QuoteComponent>,
+    /// How the amount is split between recipients, for a handler with
+    /// `splits(...)`; `recipient` is then the first of them.
+    pub splits: Option<&'static [RevenueShare]>,
+}
+
+impl Quote {
+    /// The requirements the check will enforce, for building the payment
+    /// with [`crate::payment_instruction`].
+    pub fn params(&self) -> X402Params {
+        X402Params {
+            price: self.amount,
+            recipient: self.recipient,
+            currency: self.currency,
+        }
+    }
+
+    /// The transfers that pay the quoted amount from `payer`: one per
+    /// recipient of a split, and otherwise the single
+    /// [`crate::payment_instruction`], if any.
+    pub fn payment_instructions(&self, payer: &Pubkey) -> Vec<Instruction> {
+        match self.splits {
+            Some(splits) => revenue_split_instructions(payer, &self.params(), splits),
+            None => payment_instruction(payer, &self.params()).into_iter().collect(),
+        }
+    }
+
+    /// The `max_acceptable_price` argument to call the instruction with:
+    /// the quoted amount, for handlers whose price can change between the
+    /// quote and the call. `None` for fixed prices, whose handlers take no
+    /// such argument.
+    pub fn max_acceptable_price(&self) -> Option<u64> {
+        self.components
+            .iter()
+            .any(|component| matches!(component, QuoteComponent::CongestionSurcharge { .. }))
+            .then_some(self.amount)
+    }
+}
+
+/// Quotes a call to `instruction` of `program_id`, fetching its configured
+/// recipient and congestion level when it has them. An instruction with an
+/// experiment is quoted per payer, with [`quote_for_payer`].
+pub fn quote(rpc: &RpcClient, program_id: &Pubkey, instruction: &PricedInstruction) -> Result<Quote, X402ClientError> {
+    if instruction.experiment.is_some() {
+        return Err(needs_payer());
+    }
+    let (recipient, level) = fetch_pricing_state(rpc, program_id, instruction)?;
+    quote_with(instruction, recipient, level)
+}
+
+/// Quotes a call to `instruction` of `program_id` paid by `payer`, also
+/// fetching the state of its experiment when it has one.
+pub fn quote_for_payer(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    instruction: &PricedInstruction,
+    payer: &Pubkey,
+) -> Result<Quote, X402ClientError> {
+    let (recipient, level) = fetch_pricing_state(rpc, program_id, instruction)?;
+    let state = instruction
+        .experiment
+        .as_ref()
+        .map(|experiment| fetch_experiment_state(rpc, &experiment.address(program_id)))
+        .transpose()?
+        .unwrap_or_default();
+    quote_with_payer(instruction, recipient, level, payer, state)
+}
+
+fn fetch_pricing_state(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    instruction: &PricedInstruction,
+) -> Result<(Pubkey, Option<u8>), X402ClientError> {
+    let recipient = if instruction.uses_configured_recipient() {
+        fetch_configured_recipient(rpc, program_id)?
+    } else {
+        instruction.params.recipient
+    };
+    let level = instruction
+        .surcharge
+        .as_ref()
+        .map(|surcharge| fetch_congestion_level(rpc, surcharge))
+        .transpose()?;
+    Ok((recipient, level))
+}
+
+/// Quotes `instruction` from already-fetched state: the `recipient` it
+/// pays and, when it has a surcharge, the congestion `level`.
+pub fn quote_with(
+    instruction: &PricedInstruction,
+    recipient: Pubkey,
+    level: Option<u8>,
+) -> Result<Quote, X402ClientError> {
+    if instruction.experiment.is_some() {
+        return Err(needs_payer());
+    }
+    let base = QuoteComponent::Base {
+        price: instruction.params.price,
+    };
+    quote_components(instruction, recipient, level, base)
+}
+
+/// [`quote_with`] for `payer`, who is charged the arm of the instruction's
+/// experiment picked as the check picks it under `state`.
+pub fn quote_with_payer(
+    instruction: &PricedInstruction,
+    recipient: Pubkey,
+    level: Option<u8>,
+    payer: &Pubkey,
+    state: ExperimentState,
+) -> Result<Quote, X402ClientError> {
+    let Some(experiment) = &instruction.experiment else {
+        return quote_with(instruction, recipient, level);
+    };
+    let (params, arm) = experiment
+        .apply(&instruction.params, payer, state)
+        .map_err(|_| X402ClientError::InvalidAccount(format!("experiment ended on unknown arm {}", state.winner)))?;
+    let base = QuoteComponent::ExperimentArm { arm, price: params.price };
+    quote_components(instruction, recipient, level, base)
+}
+
+fn quote_components(
+    instruction: &PricedInstruction,
+    recipient: Pubkey,
+    level: Option<u8>,
+    base: QuoteComponent,
+) -> Result<Quote, X402ClientError> {
+    let price = base.amount();
+    let mut components = vec![base];
+
+    if let Some(surcharge) = &instruction.surcharge {
+        let level = level.ok_or_else(|| {
+            X402ClientError::InvalidAccount("a surcharged instruction needs its congestion level".to_string())
+        })?;
+        let amount =
+            congestion_surcharge(price, level, surcharge.bps_per_level).map_err(|err| pricing_error(err, level))?;
+        components.push(QuoteComponent::CongestionSurcharge {
+            level,
+            bps_per_level: surcharge.bps_per_level,
+            amount,
+        });
+    }
+
+    let amount = components
+        .iter()
+        .try_fold(0u64, |total, component| total.checked_add(component.amount()))
+        .ok_or_else(|| pricing_error(PricingError::Overflow, level.unwrap_or(0)))?;
+
+    Ok(Quote {
+        amount,
+        currency: instruction.params.currency,
+        recipient,
+        components,
+        splits: instruction.splits,
+    })
+}
+
+/// Fetches whether the experiment at `address` ended, and on which arm.
+pub fn fetch_experiment_state(rpc: &RpcClient, address: &Pubkey) -> Result<ExperimentState, X402ClientError> {
+    let data = rpc.get_account_data(address)?;
+    decode_price_experiment(&data).map(|experiment| ExperimentState {
+        ended: experiment.ended,
+        winner: experiment.winner,
+    })
+}
+
+/// Decodes raw `PriceExperiment` account data.
+pub fn decode_price_experiment(data: &[u8]) -> Result<PriceExperimentData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&PRICE_EXPERIMENT_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not a PriceExperiment account".to_string()))?;
+
+    PriceExperimentData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed price experiment: {}", err)))
+}
+
+/// Fetches the recipient configured for `program_id`'s handlers without
+/// an `address`.
+pub fn fetch_configured_recipient(rpc: &RpcClient, program_id: &Pubkey) -> Result<Pubkey, X402ClientError> {
+    let address = config_address(program_id);
+    let data = rpc.get_account_data(&address)?;
+    decode_config(&data).map(|config| config.recipient)
+}
+
+/// Decodes raw `X402GlobalConfig` account data.
+pub fn decode_config(data: &[u8]) -> Result<GlobalConfigData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&X402_CONFIG_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not an X402GlobalConfig account".to_string()))?;
+
+    GlobalConfigData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed x402 config: {}", err)))
+}
+
+fn needs_payer() -> X402ClientError {
+    X402ClientError::InvalidAccount("an experiment's price depends on the payer; quote it for one".to_string())
+}
+
+fn pricing_error(err: PricingError, level: u8) -> X402ClientError {
+    X402ClientError::InvalidAccount(match err {
+        PricingError::CongestionLevelOutOfRange => format!("congestion level {} is out of range", level),
+        PricingError::Overflow => format!("price at congestion level {} overflows", level),
+    })
+}
@@ -0,0 +1,75 @@
+//! Retrying a paid compute call without paying twice.
+//!
+//! The example program's compute tiers write their result to the PDA of
+//! the payer and a `nonce` the caller picks. A call retried with the same
+//! nonce after a timeout or a dropped confirmation therefore finds the
+//! result of an attempt that did land, and fails with `ResultAlreadyExists`
+//! instead of charging again. Checking the address first, with
+//! [`check_before_retry`], tells the caller not to send that payment at all.
+
+use anchor_lang::AnchorDeserialize;
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_sdk::account::Account;
+use x402_common::result::{ComputeResultData, COMPUTE_RESULT_DISCRIMINATOR};
+
+use crate::error::X402ClientError;
+
+pub use x402_common::seeds::compute_result_address;
+
+/// What a retried compute call should do, given what its result address
+/// holds.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RetryDecision {
+    /// Nothing was created: pay and call again with the same nonce.
+    Pay,
+    /// An earlier attempt succeeded and paid; use its result.
+    AlreadySucceeded(ComputeResultData),
+    /// The address holds something the call cannot replace, and the call
+    /// would fail whatever is paid; retry with a new nonce.
+    Conflict,
+}
+
+/// Fetches the result address of `payer`'s call with `nonce` and decides
+/// whether the call may be paid for again.
+pub fn check_before_retry(
+    rpc: &RpcClient,
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    nonce: u64,
+) -> Result<RetryDecision, X402ClientError> {
+    let address = compute_result_address(program_id, payer, nonce);
+    let account = rpc.get_account_with_commitment(&address, rpc.commitment())?.value;
+    Ok(retry_decision(program_id, payer, account.as_ref()))
+}
+
+/// [`check_before_retry`] for an already-fetched `account`, `None` when
+/// the address holds none. Mirrors the program's `require_fresh_result`.
+pub fn retry_decision(program_id: &Pubkey, payer: &Pubkey, account: Option<&Account>) -> RetryDecision {
+    let Some(account) = account else {
+        return RetryDecision::Pay;
+    };
+    if account.owner == system_program::ID && account.data.is_empty() {
+        // Lamports sent to the address; the program creates the result
+        // around them.
+        return RetryDecision::Pay;
+    }
+    if account.owner != *program_id {
+        return RetryDecision::Conflict;
+    }
+    match decode_compute_result(&account.data) {
+        Ok(result) if result.owner == *payer && result.paid => RetryDecision::AlreadySucceeded(result),
+        _ => RetryDecision::Conflict,
+    }
+}
+
+/// Decodes raw `ComputeResult` account data.
+pub fn decode_compute_result(data: &[u8]) -> Result<ComputeResultData, X402ClientError> {
+    let mut body = data
+        .strip_prefix(&COMPUTE_RESULT_DISCRIMINATOR[..])
+        .ok_or_else(|| X402ClientError::InvalidAccount("not a ComputeResult account".to_string()))?;
+
+    ComputeResultData::deserialize(&mut body)
+        .map_err(|err| X402ClientError::InvalidAccount(format!("malformed compute result: {}", err)))
+}
@@ -0,0 +1,90 @@
+use anchor_lang::AnchorDeserialize;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcSimulateTransactionConfig;
+use solana_program::instruction::Instruction;
+use solana_program::program::MAX_RETURN_DATA;
+use solana_program::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+
+use crate::error::X402ClientError;
+
+/// Simulates `instructions` (typically a payment followed by a priced view
+/// instruction of `program_id`) and decodes the view's return data.
+///
+/// The transaction is left unsigned and simulated with signature checks
+/// off and a fresh blockhash, so it cannot be landed and nothing is paid.
+/// Simulation grants no access either: a real call must still carry its
+/// own transfer, verified when that transaction executes.
+pub fn simulate_priced_query<T: AnchorDeserialize>(
+    rpc: &RpcClient,
+    instructions: &[Instruction],
+    payer: &Pubkey,
+    program_id: &Pubkey,
+) -> Result<T, X402ClientError> {
+    let transaction = Transaction::new_with_payer(instructions, Some(payer));
+    decode_return_data(&simulate_return_data(rpc, &transaction, program_id)?)
+}
+
+/// Simulates `transaction` as [`simulate_priced_query`] does and returns
+/// the return data `program_id` set, as the RPC reports it.
+pub(crate) fn simulate_return_data(
+    rpc: &RpcClient,
+    transaction: &Transaction,
+    program_id: &Pubkey,
+) -> Result<Vec<u8>, X402ClientError> {
+    let result = rpc
+        .simulate_transaction_with_config(
+            transaction,
+            RpcSimulateTransactionConfig {
+                sig_verify: false,
+                replace_recent_blockhash: true,
+                commitment: Some(rpc.commitment()),
+                ..RpcSimulateTransactionConfig::default()
+            },
+        )?
+        .value;
+
+    if let Some(err) = result.err {
+        return Err(X402ClientError::SimulationFailed {
+            err,
+            logs: result.logs.unwrap_or_default(),
+        });
+    }
+
+    match result.return_data {
+        Some(return_data) => {
+            if return_data.program_id != program_id.to_string() {
+                return Err(X402ClientError::InvalidReturnData(format!(
+                    "return data was set by {}, not {}",
+                    return_data.program_id, program_id
+                )));
+            }
+            STANDARD.decode(&return_data.data.0).map_err(|err| {
+                X402ClientError::InvalidReturnData(format!("return data is not base64: {}", err))
+            })
+        }
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Decodes return data as reported by the RPC.
+///
+/// The runtime strips trailing zero bytes from recorded return data (and
+/// reports none when every byte is zero), so the data is padded back to
+/// the maximum return data size before deserializing.
+pub fn decode_return_data<T: AnchorDeserialize>(data: &[u8]) -> Result<T, X402ClientError> {
+    if data.len() > MAX_RETURN_DATA {
+        return Err(X402ClientError::InvalidReturnData(format!(
+            "{} bytes exceeds the {} byte return data limit",
+            data.len(),
+            MAX_RETURN_DATA
+        )));
+    }
+
+    let mut padded = data.to_vec();
+    padded.resize(MAX_RETURN_DATA, 0);
+    T::deserialize(&mut padded.as_slice())
+        .map_err(|err| X402ClientError::InvalidReturnData(format!("malformed return data: {}", err)))
+}
@@ -0,0 +1,105 @@
+//! Checking a ledger snapshot off-chain and proving payers' lines of it.
+//!
+//! [`fetch_ledger_tree`] lists every `PaymentLedger` of the program and
+//! builds the tree `snapshot_ledgers` builds from them, so its
+//! [`LedgerTree::root`] is the root a `LedgerSnapshotEvent` reports when
+//! no ledger changed in between. [`LedgerTree::proof`] then proves one
+//! payer's totals against that root, to anyone holding only the event.
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::pubkey::Pubkey;
+use x402_common::ledger::PaymentLedgerData;
+use x402_common::snapshot::{self, MAX_SNAPSHOT_LEAVES};
+
+use crate::error::X402ClientError;
+use crate::listing::{list_payment_ledgers, PageRequest};
+
+/// The snapshot tree over a set of ledgers, in the program's order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerTree {
+    ledgers: Vec<PaymentLedgerData>,
+    leaves: Vec<[u8; 32]>,
+}
+
+/// One payer's totals and the siblings placing them in a snapshot.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InclusionProof {
+    pub payer: Pubkey,
+    pub total_payments: u64,
+    pub total_amount: u64,
+    /// Position of the payer's leaf.
+    pub index: u64,
+    /// Lowest first, `SNAPSHOT_DEPTH` of them.
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl InclusionProof {
+    /// Whether these totals are in the snapshot with `root`.
+    pub fn verify(&self, root: &[u8; 32]) -> bool {
+        let leaf = snapshot::leaf_hash(&self.payer, self.total_payments, self.total_amount);
+        snapshot::verify_inclusion(root, &leaf, self.index, &self.siblings)
+    }
+}
+
+impl LedgerTree {
+    /// The tree over `ledgers`, ordered by payer as the program adds them.
+    /// A payer has one ledger, so two with the same payer are refused.
+    pub fn new(ledgers: impl IntoIterator<Item = PaymentLedgerData>) -> Result<Self, X402ClientError> {
+        let mut ledgers: Vec<_> = ledgers.into_iter().collect();
+        ledgers.sort_unstable_by_key(|ledger| ledger.payer);
+        if let Some(pair) = ledgers.windows(2).find(|pair| pair[0].payer == pair[1].payer) {
+            return Err(X402ClientError::InvalidAccount(format!("two ledgers of {}", pair[0].payer)));
+        }
+        if ledgers.len() as u64 > MAX_SNAPSHOT_LEAVES {
+            return Err(X402ClientError::InvalidAccount(format!(
+                "{} ledgers are more than a snapshot holds",
+                ledgers.len()
+            )));
+        }
+        let leaves = ledgers
+            .iter()
+            .map(|ledger| snapshot::leaf_hash(&ledger.payer, ledger.total_payments, ledger.total_amount))
+            .collect();
+        Ok(LedgerTree { ledgers, leaves })
+    }
+
+    /// The ledgers, in snapshot order: the order to pass them to
+    /// `snapshot_ledgers` in.
+    pub fn ledgers(&self) -> &[PaymentLedgerData] {
+        &self.ledgers
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        snapshot::snapshot_root(&self.leaves).expect("LedgerTree::new bounds the leaves")
+    }
+
+    /// The proof of `payer`'s totals, or `None` when the tree has no
+    /// ledger of theirs.
+    pub fn proof(&self, payer: &Pubkey) -> Option<InclusionProof> {
+        let index = self.ledgers.binary_search_by_key(payer, |ledger| ledger.payer).ok()?;
+        let ledger = &self.ledgers[index];
+        Some(InclusionProof {
+            payer: ledger.payer,
+            total_payments: ledger.total_payments,
+            total_amount: ledger.total_amount,
+            index: index as u64,
+            siblings: snapshot::inclusion_proof(&self.leaves, index)?,
+        })
+    }
+}
+
+/// Builds the tree over every `PaymentLedger` of `program_id`, a page at
+/// a time.
+pub fn fetch_ledger_tree(rpc: &RpcClient, program_id: &Pubkey) -> Result<LedgerTree, X402ClientError> {
+    let mut ledgers = Vec::new();
+    let mut page = PageRequest::default();
+    loop {
+        let listed = list_payment_ledgers(rpc, program_id, None, page)?;
+        ledgers.extend(listed.items.into_iter().map(|(_, ledger)| ledger));
+        match listed.next {
+            Some(next) => page.after = Some(next),
+            None => break,
+        }
+    }
+    LedgerTree::new(ledgers)
+}
@@ -0,0 +1,27 @@
+//! Paying prices split between several recipients.
+//!
+//! A handler with `splits(...)` needs one transfer per recipient, each for
+//! at least its share of the price. The shares are computed here by the
+//! same `x402_common::pricing::revenue_shares` the check uses, so the legs
+//! built for a quoted price are exactly the ones it requires.
+
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use x402_runtime::revenue_split::{required_amounts, RevenueShare};
+use x402_runtime::{Currency, X402Params};
+
+/// The transfers that pay a call priced at `params` split by `shares`
+/// (its `<HANDLER>_X402_SPLITS`), one per recipient in the order given,
+/// to place immediately before it. Empty for token prices, which a split
+/// cannot have.
+pub fn revenue_split_instructions(payer: &Pubkey, params: &X402Params, shares: &[RevenueShare]) -> Vec<Instruction> {
+    if let Currency::Token(_) = params.currency {
+        return Vec::new();
+    }
+    shares
+        .iter()
+        .zip(required_amounts(params.price, shares))
+        .map(|(share, amount)| system_instruction::transfer(payer, &share.recipient, amount))
+        .collect()
+}
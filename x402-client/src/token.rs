@@ -0,0 +1,146 @@
+//! Paying token prices into recipients that may not hold the mint yet.
+//!
+//! A token price is paid into the recipient's associated token account for
+//! the mint. When that account does not exist, the payment is preceded by
+//! the associated token account program's `CreateIdempotent`, which the
+//! on-chain scan steps over like an ignorable program, so the first
+//! payment to a fresh recipient needs no separate transaction.
+
+use solana_client::rpc_client::RpcClient;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use x402_common::{Currency, X402Params};
+use x402_runtime::associated_token_address;
+use x402_runtime::matcher::{ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+
+use crate::error::X402ClientError;
+
+/// Offset of `decimals` in a mint account: after the optional mint
+/// authority and the supply.
+const MINT_DECIMALS_OFFSET: usize = 4 + 32 + 8;
+
+/// Deposit address of a token price under the SPL Token program: the
+/// recipient's associated token account for the mint. `None` for SOL
+/// prices, which are paid to the recipient itself.
+pub fn recipient_ata(params: &X402Params) -> Option<Pubkey> {
+    recipient_ata_with(params, &TOKEN_PROGRAM_ID)
+}
+
+/// As [`recipient_ata`], for a mint owned by `token_program`.
+pub fn recipient_ata_with(params: &X402Params, token_program: &Pubkey) -> Option<Pubkey> {
+    match params.currency {
+        Currency::Sol => None,
+        Currency::Token(mint) => Some(associated_token_address(&params.recipient, &mint, token_program)),
+    }
+}
+
+/// `CreateIdempotent` of `wallet`'s associated token account for `mint`,
+/// funded by `payer`.
+pub fn create_ata_idempotent(payer: &Pubkey, wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: ASSOCIATED_TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(associated_token_address(wallet, mint, token_program), false),
+            AccountMeta::new_readonly(*wallet, false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(*token_program, false),
+        ],
+        data: vec![1],
+    }
+}
+
+/// A `TransferChecked` of `amount` from `payer`'s associated token account
+/// into `recipient`'s.
+fn transfer_checked(
+    payer: &Pubkey,
+    recipient: &Pubkey,
+    mint: &Pubkey,
+    token_program: &Pubkey,
+    amount: u64,
+    decimals: u8,
+) -> Instruction {
+    let mut data = vec![12];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(decimals);
+    Instruction {
+        program_id: *token_program,
+        accounts: vec![
+            AccountMeta::new(associated_token_address(payer, mint, token_program), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(associated_token_address(recipient, mint, token_program), false),
+            AccountMeta::new_readonly(*payer, true),
+        ],
+        data,
+    }
+}
+
+/// What [`token_payment_instructions`] reads from the cluster about a
+/// token price.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TokenPaymentContext {
+    /// Owner of the mint: SPL Token or Token-2022.
+    pub token_program: Pubkey,
+    pub decimals: u8,
+    /// Whether the recipient's associated token account already exists.
+    pub recipient_ata_exists: bool,
+}
+
+/// The instructions that pay a token price from `payer`'s associated
+/// token account, to place immediately before the priced call: the
+/// creation of the recipient's account when it is missing, then the
+/// transfer. Empty for SOL prices; see `congestion::payment_instruction`.
+pub fn token_payment_instructions(
+    rpc: &RpcClient,
+    payer: &Pubkey,
+    params: &X402Params,
+) -> Result<Vec<Instruction>, X402ClientError> {
+    let Currency::Token(mint) = params.currency else {
+        return Ok(Vec::new());
+    };
+    let account = rpc.get_account(&mint)?;
+    if account.owner != TOKEN_PROGRAM_ID && account.owner != TOKEN_2022_PROGRAM_ID {
+        return Err(X402ClientError::InvalidAccount(format!(
+            "mint {} is owned by {}, not a token program",
+            mint, account.owner
+        )));
+    }
+    let decimals = *account
+        .data
+        .get(MINT_DECIMALS_OFFSET)
+        .ok_or_else(|| X402ClientError::InvalidAccount(format!("{} is not a mint", mint)))?;
+
+    let ata = associated_token_address(&params.recipient, &mint, &account.owner);
+    let context = TokenPaymentContext {
+        token_program: account.owner,
+        decimals,
+        recipient_ata_exists: rpc.get_account_with_commitment(&ata, rpc.commitment())?.value.is_some(),
+    };
+    Ok(token_payment_instructions_with(payer, params, &context))
+}
+
+/// [`token_payment_instructions`] from values already fetched.
+pub fn token_payment_instructions_with(
+    payer: &Pubkey,
+    params: &X402Params,
+    context: &TokenPaymentContext,
+) -> Vec<Instruction> {
+    let Currency::Token(mint) = params.currency else {
+        return Vec::new();
+    };
+    let mut instructions = Vec::with_capacity(2);
+    if !context.recipient_ata_exists {
+        instructions.push(create_ata_idempotent(payer, &params.recipient, &mint, &context.token_program));
+    }
+    instructions.push(transfer_checked(
+        payer,
+        &params.recipient,
+        &mint,
+        &context.token_program,
+        params.price,
+        context.decimals,
+    ));
+    instructions
+}
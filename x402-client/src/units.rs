@@ -0,0 +1,134 @@
+//! Amounts in lamports, SOL and token base units.
+//!
+//! Parsing uses the same grammar as the macro's `price_sol` and
+//! `price_usdc` arguments (see `x402_units`), so a price written in a
+//! client config means exactly what it means in `#[x402]`.
+
+use std::fmt;
+use std::str::FromStr;
+
+pub use x402_units::{Unit, UnitsError, SOL_DECIMALS, USDC_DECIMALS};
+
+/// An amount of lamports. Displays as `"50000000 lamports"`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lamports(pub u64);
+
+/// An amount of SOL, held exactly as lamports. Displays and parses as
+/// `"0.05 SOL"`; the unit is optional when parsing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UiSol(Lamports);
+
+/// An amount of a token in base units of a mint with `decimals` decimals.
+/// Displays as the UI amount without a unit, since the mint is not known.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TokenAmount {
+    pub base_units: u64,
+    pub decimals: u8,
+}
+
+impl Lamports {
+    pub fn checked_add(self, other: Lamports) -> Option<Lamports> {
+        self.0.checked_add(other.0).map(Lamports)
+    }
+
+    pub fn checked_sub(self, other: Lamports) -> Option<Lamports> {
+        self.0.checked_sub(other.0).map(Lamports)
+    }
+}
+
+impl fmt::Display for Lamports {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} lamports", self.0)
+    }
+}
+
+impl UiSol {
+    /// Parses a decimal amount of SOL without a unit, e.g. `"0.05"`.
+    pub fn parse(s: &str) -> Result<UiSol, UnitsError> {
+        x402_units::parse_decimal(s, SOL_DECIMALS).map(|lamports| UiSol(Lamports(lamports)))
+    }
+
+    pub fn lamports(self) -> Lamports {
+        self.0
+    }
+}
+
+impl From<Lamports> for UiSol {
+    fn from(lamports: Lamports) -> Self {
+        UiSol(lamports)
+    }
+}
+
+impl From<UiSol> for Lamports {
+    fn from(sol: UiSol) -> Self {
+        sol.0
+    }
+}
+
+impl fmt::Display for UiSol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} SOL", x402_units::format_decimal(self.0 .0, SOL_DECIMALS))
+    }
+}
+
+impl FromStr for UiSol {
+    type Err = UnitsError;
+
+    /// Accepts `"0.05"` and `"0.05 SOL"`, but not other units.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if !s.contains(char::is_whitespace) {
+            return UiSol::parse(s);
+        }
+        match x402_units::parse_amount(s)? {
+            (lamports, Unit::Sol) => Ok(UiSol(Lamports(lamports))),
+            (_, found) => Err(UnitsError::WrongUnit {
+                expected: Unit::Sol,
+                found,
+            }),
+        }
+    }
+}
+
+impl TokenAmount {
+    /// Parses a decimal UI amount, e.g. `"1.25"` with 6 decimals is
+    /// 1_250_000 base units.
+    pub fn parse(s: &str, decimals: u8) -> Result<TokenAmount, UnitsError> {
+        x402_units::parse_decimal(s, decimals).map(|base_units| TokenAmount { base_units, decimals })
+    }
+
+    /// The same amount expressed with `decimals` decimals, failing when
+    /// that would drop digits or overflow.
+    pub fn rescale(self, decimals: u8) -> Result<TokenAmount, UnitsError> {
+        x402_units::parse_decimal(&x402_units::format_decimal(self.base_units, self.decimals), decimals)
+            .map(|base_units| TokenAmount { base_units, decimals })
+    }
+}
+
+impl From<Lamports> for TokenAmount {
+    fn from(lamports: Lamports) -> Self {
+        TokenAmount {
+            base_units: lamports.0,
+            decimals: SOL_DECIMALS,
+        }
+    }
+}
+
+impl fmt::Display for TokenAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&x402_units::format_decimal(self.base_units, self.decimals))
+    }
+}
+
+impl FromStr for TokenAmount {
+    type Err = UnitsError;
+
+    /// Accepts an amount with its unit, e.g. `"1.25 USDC"` or `"0.05 SOL"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (base_units, unit) = x402_units::parse_amount(s)?;
+        Ok(TokenAmount {
+            base_units,
+            decimals: unit.decimals(),
+        })
+    }
+}
@@ -0,0 +1,101 @@
+//! Verifying a payment off-chain, from the transaction a client is about to
+//! send or a server has been handed.
+//!
+//! This is an independent implementation of the check `verify_payment`
+//! runs on-chain: it decompiles the message itself instead of reading the
+//! instructions sysvar, then walks back from the priced instruction with
+//! the same matcher. The differential tests in `tests/differential.rs`
+//! hold the two to the same verdicts.
+
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::message::Message;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::instructions;
+use x402_runtime::{
+    is_ignorable_instruction, Currency, NearMiss, PaymentScan, ScanOptions, VerifiedPayment, X402Error, X402Params,
+    MAX_LOOKBACK,
+};
+
+/// The instructions of `message` with the signer and writable flags the
+/// runtime gives them.
+pub fn decompile(message: &Message) -> Vec<Instruction> {
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: message.account_keys[usize::from(ix.program_id_index)],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = usize::from(index);
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: message.is_writable(index),
+                    }
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
+/// Whether the instruction at `priced_index` of `message`, an instruction
+/// of `program_id`, would find a payment of `params` within `scan`, and
+/// the payment it would accept. Fails with the error the on-chain check
+/// would return.
+pub fn verify_message_payment(
+    message: &Message,
+    priced_index: usize,
+    program_id: &Pubkey,
+    params: &X402Params,
+    scan: &ScanOptions,
+) -> Result<VerifiedPayment, X402Error> {
+    let instructions = decompile(message);
+    // The on-chain check cannot run from an instruction that is not there.
+    if priced_index >= instructions.len() {
+        return Err(X402Error::SysvarUnavailable);
+    }
+    let preceding = &instructions[..priced_index];
+
+    let max_lookback = scan.max_lookback.clamp(1, MAX_LOOKBACK);
+    let mint = match params.currency {
+        Currency::Sol => None,
+        Currency::Token(mint) => Some(mint),
+    };
+    let mut wanted = PaymentScan::new(params.recipient, params.price, mint);
+    let mut examined = 0u8;
+    for (index, ix) in preceding.iter().enumerate().rev() {
+        if examined == max_lookback {
+            break;
+        }
+        if is_ignorable_instruction(ix) || wanted.creates_recipient_account(ix) {
+            continue;
+        }
+        examined += 1;
+        if let Ok(payment) = scan.match_payment(ix, program_id) {
+            if wanted.offer(&payment, &ix.program_id) {
+                return Ok(VerifiedPayment {
+                    amount: payment.amount,
+                    payer: payment.payer,
+                    recipient: params.recipient,
+                    currency: params.currency,
+                    ix_index: index as u16,
+                });
+            }
+        }
+        let reads_instructions = ix.accounts.iter().any(|meta| meta.pubkey == instructions::ID);
+        if scan.strict || ix.program_id == *program_id || reads_instructions {
+            break;
+        }
+    }
+
+    Err(match wanted.near_miss() {
+        Some(NearMiss::Insufficient { .. }) => X402Error::InsufficientPayment,
+        Some(NearMiss::WrongToken { .. }) => X402Error::WrongPaymentToken,
+        Some(NearMiss::WrongRecipient { .. }) => X402Error::WrongRecipient,
+        None if examined == 0 => X402Error::NoPaymentInstruction,
+        None => X402Error::NotAPayment,
+    })
+}
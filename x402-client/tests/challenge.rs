@@ -0,0 +1,149 @@
+//! A server binds each paid call to one request: it issues a nonce with
+//! the payment requirements and serves only once a payment carrying that
+//! nonce is redeemed. Replayed transactions are turned away.
+
+use std::time::Duration;
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_program::pubkey::Pubkey;
+use x402_client::{decode_program_events, NonceLedger, PaymentRequirements, X402ClientError};
+use x402_common::events::{X402PaymentVerifiedEventV2, PAYMENT_VERIFIED_VERSION};
+use x402_common::{Currency, X402Params};
+
+const PRICE: u64 = 1_000_000;
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: Pubkey::new_from_array([0xee; 32]),
+        currency: Currency::Sol,
+    }
+}
+
+fn payment(nonce: [u8; 32]) -> X402PaymentVerifiedEventV2 {
+    X402PaymentVerifiedEventV2 {
+        version: PAYMENT_VERIFIED_VERSION,
+        payer: Pubkey::new_from_array([0xaa; 32]),
+        recipient: params().recipient,
+        amount: PRICE,
+        price: PRICE,
+        sequence: 1,
+        experiment_arm: None,
+        nonce: Some(nonce),
+    }
+}
+
+/// The logs of a transaction in which `program_id` emitted `event`.
+fn logs(program_id: &Pubkey, event: &X402PaymentVerifiedEventV2) -> Vec<String> {
+    let mut data = X402PaymentVerifiedEventV2::DISCRIMINATOR.to_vec();
+    event.serialize(&mut data).unwrap();
+    vec![
+        format!("Program {} invoke [1]", program_id),
+        "Program log: Instruction: GetWeather".to_string(),
+        format!("Program data: {}", STANDARD.encode(data)),
+        format!("Program {} consumed 12000 of 200000 compute units", program_id),
+        format!("Program {} success", program_id),
+    ]
+}
+
+#[test]
+fn requirements_round_trip_through_json() {
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    let requirements = ledger.issue(&Pubkey::new_unique(), params());
+
+    let json = serde_json::to_string(&requirements).unwrap();
+    assert_eq!(serde_json::from_str::<PaymentRequirements>(&json).unwrap(), requirements);
+}
+
+#[test]
+fn serves_a_payment_carrying_the_nonce_once() {
+    let program_id = Pubkey::new_unique();
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    let requirements = ledger.issue(&program_id, params());
+    assert_eq!(requirements.params, params());
+
+    let paid = logs(&program_id, &payment(requirements.nonce));
+    let events = decode_program_events(&paid, &program_id);
+    let redeemed = ledger.redeem(&requirements.nonce, &events).unwrap();
+    assert_eq!((redeemed.version, redeemed.amount, redeemed.nonce), (2, PRICE, Some(requirements.nonce)));
+
+    // The same transaction again, for the request it paid for.
+    let err = ledger.redeem(&requirements.nonce, &events).unwrap_err();
+    assert!(matches!(err, X402ClientError::StaleNonce));
+}
+
+#[test]
+fn rejects_an_old_payment_for_a_new_request() {
+    let program_id = Pubkey::new_unique();
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    let first = ledger.issue(&program_id, params());
+    let old = decode_program_events(&logs(&program_id, &payment(first.nonce)), &program_id);
+    ledger.redeem(&first.nonce, &old).unwrap();
+
+    let second = ledger.issue(&program_id, params());
+    assert_ne!(second.nonce, first.nonce);
+    let err = ledger.redeem(&second.nonce, &old).unwrap_err();
+    assert!(matches!(err, X402ClientError::NonceNotPaid));
+
+    // The request can still be paid for.
+    let fresh = decode_program_events(&logs(&program_id, &payment(second.nonce)), &program_id);
+    assert!(ledger.redeem(&second.nonce, &fresh).is_ok());
+}
+
+#[test]
+fn rejects_payments_short_of_the_requirements() {
+    let program_id = Pubkey::new_unique();
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    let requirements = ledger.issue(&program_id, params());
+
+    let cheaper = X402PaymentVerifiedEventV2 {
+        price: PRICE - 1,
+        ..payment(requirements.nonce)
+    };
+    let elsewhere = X402PaymentVerifiedEventV2 {
+        recipient: Pubkey::new_unique(),
+        ..payment(requirements.nonce)
+    };
+    for event in [cheaper, elsewhere] {
+        let events = decode_program_events(&logs(&program_id, &event), &program_id);
+        let err = ledger.redeem(&requirements.nonce, &events).unwrap_err();
+        assert!(matches!(err, X402ClientError::NonceNotPaid));
+    }
+}
+
+#[test]
+fn ignores_events_other_programs_log() {
+    let program_id = Pubkey::new_unique();
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    let requirements = ledger.issue(&program_id, params());
+
+    // Another program logs a well-formed event with the nonce, from a CPI
+    // of the gated program and on its own.
+    let impostor = Pubkey::new_unique();
+    let forged = logs(&impostor, &payment(requirements.nonce));
+    let mut nested = vec![format!("Program {} invoke [1]", program_id)];
+    nested.extend(forged.iter().map(|line| line.replace("invoke [1]", "invoke [2]")));
+    nested.push(format!("Program {} success", program_id));
+
+    for logs in [forged, nested] {
+        assert!(decode_program_events(&logs, &program_id).is_empty());
+        let err = ledger.redeem(&requirements.nonce, &decode_program_events(&logs, &program_id)).unwrap_err();
+        assert!(matches!(err, X402ClientError::NonceNotPaid));
+    }
+}
+
+#[test]
+fn rejects_unknown_and_expired_nonces() {
+    let program_id = Pubkey::new_unique();
+    let events = decode_program_events(&logs(&program_id, &payment([7; 32])), &program_id);
+
+    let mut ledger = NonceLedger::new(Duration::from_secs(60));
+    assert!(matches!(ledger.redeem(&[7; 32], &events), Err(X402ClientError::StaleNonce)));
+
+    let mut expired = NonceLedger::new(Duration::ZERO);
+    let requirements = expired.issue(&program_id, params());
+    let events = decode_program_events(&logs(&program_id, &payment(requirements.nonce)), &program_id);
+    assert!(matches!(expired.redeem(&requirements.nonce, &events), Err(X402ClientError::StaleNonce)));
+}
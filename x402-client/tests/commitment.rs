@@ -0,0 +1,110 @@
+//! Commitment chains built by the runtime, as the on-chain check records
+//! them, and checked from the events alone.
+
+use solana_program::pubkey::Pubkey;
+use x402_client::commitment::{
+    decode_stats, verify_commitment_chain, verify_commitment_chain_from, BreakKind, CommitmentBreak,
+};
+use x402_client::X402ClientError;
+use x402_common::events::X402PaymentCommittedEvent;
+use x402_common::stats::X402_STATS_DISCRIMINATOR;
+use x402_runtime::commitment::{record_at, GENESIS_COMMITMENT};
+use x402_runtime::{Currency, VerifiedPayment};
+
+/// Ten payments from three payers, each returned with the commitment the
+/// stats account ends up holding.
+fn chain() -> (Vec<X402PaymentCommittedEvent>, [u8; 32]) {
+    let payers = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+    let mut commitment = GENESIS_COMMITMENT;
+    let events = (0..10u64)
+        .map(|i| {
+            let payment = VerifiedPayment {
+                amount: 1_000_000 * (i + 1),
+                payer: payers[i as usize % payers.len()],
+                recipient: Pubkey::new_from_array([9; 32]),
+                currency: Currency::Sol,
+                ix_index: 0,
+            };
+            let tag = if i % 2 == 0 { "premium_v1" } else { "standard_v1" };
+            record_at(&mut commitment, &payment, i + 1, tag, 300 + i / 3)
+        })
+        .collect();
+    (events, commitment)
+}
+
+fn broken(index: usize, kind: BreakKind) -> Result<(), CommitmentBreak> {
+    Err(CommitmentBreak {
+        index,
+        sequence: index as u64 + 1,
+        kind,
+    })
+}
+
+#[test]
+fn an_untouched_chain_verifies() {
+    let (events, last) = chain();
+    verify_commitment_chain_from(&GENESIS_COMMITMENT, &events).unwrap();
+    verify_commitment_chain(&events[4..]).unwrap();
+    assert_eq!(events.last().unwrap().commitment, last);
+}
+
+#[test]
+fn an_altered_event_is_pinpointed() {
+    let (events, _) = chain();
+
+    let mut altered = events.clone();
+    altered[6].amount += 1;
+    assert_eq!(verify_commitment_chain(&altered), broken(6, BreakKind::Altered));
+
+    let mut altered = events.clone();
+    altered[3].instruction_tag = "enterprise_v1".to_string();
+    assert_eq!(verify_commitment_chain(&altered), broken(3, BreakKind::Altered));
+
+    let mut altered = events.clone();
+    altered[8].commitment[0] ^= 1;
+    assert_eq!(verify_commitment_chain(&altered), broken(8, BreakKind::Altered));
+}
+
+#[test]
+fn a_missing_event_is_pinpointed() {
+    let (mut events, _) = chain();
+    events.remove(5);
+    assert_eq!(
+        verify_commitment_chain(&events),
+        Err(CommitmentBreak {
+            index: 5,
+            sequence: 7,
+            kind: BreakKind::Unlinked,
+        })
+    );
+
+    let (events, _) = chain();
+    assert_eq!(
+        verify_commitment_chain_from(&GENESIS_COMMITMENT, &events[1..]),
+        Err(CommitmentBreak {
+            index: 0,
+            sequence: 2,
+            kind: BreakKind::Unlinked,
+        })
+    );
+}
+
+#[test]
+fn the_stats_account_holds_the_last_commitment() {
+    let (events, last) = chain();
+    // The zero-copy layout: the version byte padded to eight, three `u64`s,
+    // the commitment and the rent total.
+    let mut data = X402_STATS_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[4, 0, 0, 0, 0, 0, 0, 0]);
+    data.extend([10u64, 10, 55_000_000].iter().flat_map(|v| v.to_le_bytes()));
+    data.extend_from_slice(&last);
+    data.extend_from_slice(&2_039_280u64.to_le_bytes());
+
+    let stats = decode_stats(&data).unwrap();
+    assert_eq!((stats.version, stats.sequence, stats.total_payments), (4, 10, 10));
+    assert_eq!((stats.total_amount, stats.total_rent_paid), (55_000_000, 2_039_280));
+    assert_eq!(stats.commitment, events.last().unwrap().commitment);
+
+    assert!(matches!(decode_stats(&data[8..]), Err(X402ClientError::InvalidAccount(_))));
+    assert!(matches!(decode_stats(&data[..data.len() - 1]), Err(X402ClientError::InvalidAccount(_))));
+}
@@ -0,0 +1,72 @@
+//! Quoting surcharged prices from a `CongestionLevel` account and paying
+//! them with the transfer the check accepts.
+
+use anchor_lang::AnchorSerialize;
+use solana_program::system_instruction;
+use solana_sdk::pubkey::Pubkey;
+use x402_client::congestion::{decode_congestion_level, payment_instruction, surcharged_params};
+use x402_client::X402ClientError;
+use x402_common::congestion::{CongestionLevelData, CONGESTION_LEVEL_DISCRIMINATOR};
+use x402_runtime::congestion::{CongestionSurcharge, MAX_CONGESTION_LEVEL};
+use x402_runtime::{Currency, X402Params};
+
+const PARAMS: X402Params = X402Params {
+    price: 200_000,
+    recipient: Pubkey::new_from_array([9; 32]),
+    currency: Currency::Sol,
+};
+
+fn account_data(level: u8) -> Vec<u8> {
+    let mut data = CONGESTION_LEVEL_DISCRIMINATOR.to_vec();
+    CongestionLevelData {
+        version: 2,
+        authority: Pubkey::new_unique(),
+        level,
+        previous_level: 0,
+        updated_at: 0,
+    }
+    .serialize(&mut data)
+    .unwrap();
+    data
+}
+
+#[test]
+fn pays_the_quoted_price() {
+    let surcharge = CongestionSurcharge {
+        account: Pubkey::new_unique(),
+        bps_per_level: 500,
+    };
+    let payer = Pubkey::new_unique();
+
+    for level in [0, 4, 1] {
+        let decoded = decode_congestion_level(&account_data(level)).unwrap();
+        let quoted = surcharged_params(&PARAMS, &surcharge, decoded.level).unwrap();
+        assert_eq!(quoted.price, 200_000 + level as u64 * 10_000);
+        assert_eq!(
+            payment_instruction(&payer, &quoted),
+            Some(system_instruction::transfer(&payer, &PARAMS.recipient, quoted.price))
+        );
+    }
+
+    let err = surcharged_params(&PARAMS, &surcharge, MAX_CONGESTION_LEVEL + 1).unwrap_err();
+    assert!(matches!(err, X402ClientError::InvalidAccount(_)));
+}
+
+#[test]
+fn rejects_other_accounts() {
+    let mut data = account_data(2);
+    data[0] ^= 1;
+    assert!(matches!(
+        decode_congestion_level(&data),
+        Err(X402ClientError::InvalidAccount(_))
+    ));
+}
+
+#[test]
+fn token_prices_have_no_transfer() {
+    let params = X402Params {
+        currency: Currency::Token(Pubkey::new_unique()),
+        ..PARAMS
+    };
+    assert_eq!(payment_instruction(&Pubkey::new_unique(), &params), None);
+}
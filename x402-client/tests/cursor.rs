@@ -0,0 +1,154 @@
+//! Ordering and checking the events after an indexer's cursor, as
+//! `consume_events_since` does with what it reads from the program's
+//! transactions: restarts resume after the cursor, re-read transactions
+//! are not exported twice, and a missing payment fails the batch.
+
+use anchor_lang::AnchorDeserialize;
+use solana_program::pubkey::Pubkey;
+use x402_client::{events_between, indexer_cursor_address, EventBatch, ReceivedEvent, X402ClientError};
+use x402_common::cursor::ADVANCE_CURSOR_DISCRIMINATOR;
+use x402_common::events::{X402PaymentCommittedEvent, X402PaymentLatencyEvent, X402PaymentVerifiedEvent};
+use x402_common::X402Event;
+
+/// The events of the payment with `sequence`, as its transaction logs them
+/// every time it is read.
+fn payment(sequence: u64) -> Vec<ReceivedEvent> {
+    let payer = Pubkey::new_from_array([sequence as u8; 32]);
+    let signature = format!("tx{}", sequence);
+    let events = [
+        X402Event::PaymentVerified(X402PaymentVerifiedEvent {
+            payer,
+            recipient: Pubkey::new_from_array([0xee; 32]),
+            amount: 1_000 * sequence,
+            price: 1_000 * sequence,
+            sequence,
+            experiment_arm: None,
+            nonce: None,
+        }),
+        X402Event::PaymentCommitted(X402PaymentCommittedEvent {
+            payer,
+            amount: 1_000 * sequence,
+            slot: 100 + sequence,
+            instruction_tag: "compute".to_string(),
+            sequence,
+            previous_commitment: [0; 32],
+            commitment: [sequence as u8; 32],
+        }),
+    ];
+    events
+        .into_iter()
+        .map(|event| ReceivedEvent {
+            slot: 100 + sequence,
+            signature: signature.clone(),
+            event,
+        })
+        .collect()
+}
+
+/// Payments `sequences`, newest first as `getSignaturesForAddress` lists
+/// their transactions.
+fn history(sequences: impl DoubleEndedIterator<Item = u64>) -> Vec<ReceivedEvent> {
+    sequences.rev().flat_map(payment).collect()
+}
+
+fn sequences(events: &[ReceivedEvent]) -> Vec<u64> {
+    let mut sequences: Vec<u64> = events.iter().filter_map(|event| event.event.sequence()).collect();
+    sequences.dedup();
+    sequences
+}
+
+#[test]
+fn restart_mid_stream_resumes_after_the_cursor() {
+    // The indexer reads payments 1..=3, then stops before advancing.
+    let first = events_between(0, 3, history(1..=3)).unwrap();
+    assert_eq!(sequences(&first), vec![1, 2, 3]);
+
+    // Restarted, it reads the same batch from the unmoved cursor.
+    let retried = events_between(0, 3, history(1..=4)).unwrap();
+    assert_eq!(retried, first);
+
+    // Once advanced to 3, every transaction is read again but only the
+    // payments after it are returned.
+    let next = events_between(3, 6, history(1..=6)).unwrap();
+    assert_eq!(sequences(&next), vec![4, 5, 6]);
+
+    let exported: Vec<u64> = sequences(&retried).into_iter().chain(sequences(&next)).collect();
+    assert_eq!(exported, (1..=6).collect::<Vec<_>>());
+}
+
+#[test]
+fn transactions_read_twice_are_exported_once() {
+    let mut received = history(1..=3);
+    received.extend(payment(2));
+    received.extend(payment(3));
+
+    let events = events_between(0, 3, received).unwrap();
+    assert_eq!(events, (1..=3).flat_map(payment).collect::<Vec<_>>());
+}
+
+#[test]
+fn payments_after_the_latest_counted_wait_for_the_next_batch() {
+    let events = events_between(0, 2, history(1..=4)).unwrap();
+    assert_eq!(sequences(&events), vec![1, 2]);
+}
+
+#[test]
+fn events_come_in_sequence_order() {
+    let events = events_between(0, 3, history(1..=3)).unwrap();
+    let ordered: Vec<ReceivedEvent> = (1..=3).flat_map(payment).collect();
+    assert_eq!(events, ordered);
+}
+
+#[test]
+fn a_missing_payment_fails_the_batch() {
+    let received: Vec<ReceivedEvent> = [1, 2, 4, 5].into_iter().flat_map(payment).collect();
+    let err = events_between(0, 5, received).unwrap_err();
+    assert!(matches!(err, X402ClientError::MissingEvents(3)));
+
+    // A counted payment whose transaction was not found.
+    let err = events_between(3, 4, history(1..=3)).unwrap_err();
+    assert!(matches!(err, X402ClientError::MissingEvents(4)));
+}
+
+#[test]
+fn events_without_a_sequence_are_skipped() {
+    let mut received = history(1..=2);
+    received.push(ReceivedEvent {
+        slot: 200,
+        signature: "consume".to_string(),
+        // Emitted when a result is consumed, repeating its payment's
+        // sequence in a later transaction.
+        event: X402Event::PaymentLatency(X402PaymentLatencyEvent {
+            payer: Pubkey::new_unique(),
+            sequence: 1,
+            payment_slot: 101,
+            consumed_slot: 200,
+        }),
+    });
+    // A payment of an instruction that does not track stats.
+    received.extend(payment(0));
+
+    let events = events_between(0, 2, received).unwrap();
+    assert_eq!(events, (1..=2).flat_map(payment).collect::<Vec<_>>());
+}
+
+#[test]
+fn confirming_advances_to_the_end_of_the_batch() {
+    let (program_id, creator) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut batch = EventBatch {
+        indexer_id: "revenue".to_string(),
+        after: 3,
+        through: 3,
+        events: Vec::new(),
+    };
+    assert_eq!(batch.advance_instruction(&program_id, &creator), None);
+
+    batch.through = 6;
+    let instruction = batch.advance_instruction(&program_id, &creator).unwrap();
+    assert_eq!(instruction.data[..8], ADVANCE_CURSOR_DISCRIMINATOR);
+    let (indexer_id, new_sequence) = <(String, u64)>::try_from_slice(&instruction.data[8..]).unwrap();
+    assert_eq!((indexer_id.as_str(), new_sequence), ("revenue", 6));
+    assert_eq!(instruction.accounts[0].pubkey, creator);
+    assert!(instruction.accounts[0].is_signer);
+    assert_eq!(instruction.accounts[1].pubkey, indexer_cursor_address(&program_id, "revenue"));
+}
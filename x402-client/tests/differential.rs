@@ -0,0 +1,319 @@
+//! Differential tests: the on-chain check, reading an instructions sysvar
+//! built from the message the way the runtime builds it, and the
+//! off-chain `verify_message_payment`, decompiling the same serialized
+//! transaction, must reach the same verdict on randomized transactions.
+//!
+//! A disagreement is written to `tests/differential/` as a fixture before
+//! the case fails; `recorded_cases_agree` replays every fixture there, so
+//! a fixed disagreement stays fixed.
+
+use std::fs;
+use std::path::PathBuf;
+
+use anchor_lang::prelude::AccountInfo;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use proptest::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::message::{Message, SanitizedMessage};
+use solana_program::sysvar::{self, instructions};
+use solana_program::{pubkey, system_instruction, system_program};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use x402_client::token::create_ata_idempotent;
+use x402_client::verify::verify_message_payment;
+use x402_runtime::matcher::{TOKEN_PROGRAM_ID, X402_PAY_DISCRIMINATOR};
+use x402_runtime::{
+    associated_token_address, verify_sol_payment, Currency, ScanOptions, VerifiedPayment, X402Error, X402Params,
+};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const OTHER: Pubkey = Pubkey::new_from_array([10; 32]);
+const PAYERS: [Pubkey; 3] = [
+    Pubkey::new_from_array([21; 32]),
+    Pubkey::new_from_array([22; 32]),
+    Pubkey::new_from_array([23; 32]),
+];
+const USDC: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+const PRICE: u64 = 1_000_000;
+
+/// One instruction of a generated transaction.
+#[derive(Clone, Debug)]
+struct Step {
+    kind: u8,
+    payer: usize,
+    to_recipient: bool,
+    amount: u64,
+    /// Overwrites one data byte: `(index, byte)`.
+    mutation: Option<(usize, u8)>,
+    junk: Vec<u8>,
+}
+
+fn amount() -> impl Strategy<Value = u64> {
+    prop_oneof![Just(PRICE - 1), Just(PRICE), PRICE..2 * PRICE, any::<u64>()]
+}
+
+fn step() -> impl Strategy<Value = Step> {
+    (
+        0u8..9,
+        0..PAYERS.len(),
+        any::<bool>(),
+        amount(),
+        prop::option::weighted(0.2, (any::<usize>(), any::<u8>())),
+        prop::collection::vec(any::<u8>(), 0..24),
+    )
+        .prop_map(|(kind, payer, to_recipient, amount, mutation, junk)| Step {
+            kind,
+            payer,
+            to_recipient,
+            amount,
+            mutation,
+            junk,
+        })
+}
+
+impl Step {
+    fn instruction(&self) -> Instruction {
+        let payer = PAYERS[self.payer];
+        let to = if self.to_recipient { RECIPIENT } else { OTHER };
+        let mut ix = match self.kind {
+            0 => system_instruction::transfer(&payer, &to, self.amount),
+            1 => system_instruction::transfer_with_seed(
+                &Pubkey::create_with_seed(&payer, "x402", &system_program::ID).unwrap(),
+                &payer,
+                "x402".to_string(),
+                &system_program::ID,
+                &to,
+                self.amount,
+            ),
+            2 => {
+                let mut data = vec![12];
+                data.extend_from_slice(&self.amount.to_le_bytes());
+                data.push(6);
+                Instruction {
+                    program_id: TOKEN_PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new(associated_token_address(&payer, &USDC, &TOKEN_PROGRAM_ID), false),
+                        AccountMeta::new_readonly(USDC, false),
+                        AccountMeta::new(associated_token_address(&to, &USDC, &TOKEN_PROGRAM_ID), false),
+                        AccountMeta::new_readonly(payer, true),
+                    ],
+                    data,
+                }
+            }
+            3 => {
+                let mut data = X402_PAY_DISCRIMINATOR.to_vec();
+                data.extend_from_slice(&self.amount.to_le_bytes());
+                Instruction {
+                    program_id: PROGRAM_ID,
+                    accounts: vec![
+                        AccountMeta::new(payer, true),
+                        AccountMeta::new(to, false),
+                        AccountMeta::new_readonly(system_program::ID, false),
+                    ],
+                    data,
+                }
+            }
+            4 => Instruction::new_with_bytes(pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"), b"x402", vec![]),
+            5 => Instruction::new_with_bytes(
+                pubkey!("ComputeBudget111111111111111111111111111111"),
+                &[2, 0x40, 0x0d, 0x03, 0x00],
+                vec![],
+            ),
+            6 => create_ata_idempotent(&payer, &to, &USDC, &TOKEN_PROGRAM_ID),
+            // Another x402 program's priced call.
+            7 => Instruction::new_with_bytes(
+                Pubkey::new_from_array([24; 32]),
+                &[0; 8],
+                vec![AccountMeta::new_readonly(payer, true), AccountMeta::new_readonly(instructions::ID, false)],
+            ),
+            _ => Instruction::new_with_bytes(
+                Pubkey::new_from_array(hash(&self.junk).to_bytes()),
+                &self.junk,
+                vec![AccountMeta::new(payer, true)],
+            ),
+        };
+        if let Some((index, byte)) = self.mutation {
+            if !ix.data.is_empty() {
+                let index = index % ix.data.len();
+                ix.data[index] = byte;
+            }
+        }
+        ix
+    }
+}
+
+/// A generated transaction: `preceding`, the priced call, then `trailing`.
+#[derive(Clone, Debug)]
+struct Case {
+    preceding: Vec<Step>,
+    trailing: Vec<Step>,
+    token_price: bool,
+    max_lookback: u8,
+    strict: bool,
+}
+
+fn case() -> impl Strategy<Value = Case> {
+    (
+        prop::collection::vec(step(), 0..6),
+        prop::collection::vec(step(), 0..2),
+        prop::bool::weighted(0.05),
+        0u8..20,
+        any::<bool>(),
+    )
+        .prop_map(|(preceding, trailing, token_price, max_lookback, strict)| Case {
+            preceding,
+            trailing,
+            token_price,
+            max_lookback,
+            strict,
+        })
+}
+
+impl Case {
+    fn params(&self) -> X402Params {
+        X402Params {
+            price: PRICE,
+            recipient: RECIPIENT,
+            currency: if self.token_price { Currency::Token(USDC) } else { Currency::Sol },
+        }
+    }
+
+    fn scan(&self) -> ScanOptions {
+        ScanOptions {
+            max_lookback: self.max_lookback,
+            strict: self.strict,
+            legacy_matcher: false,
+        }
+    }
+
+    /// The transaction, serialized as it would be sent.
+    fn transaction(&self) -> Vec<u8> {
+        let call = Instruction::new_with_bytes(PROGRAM_ID, &[0; 8], vec![AccountMeta::new_readonly(PAYERS[0], true)]);
+        let mut all: Vec<Instruction> = self.preceding.iter().map(Step::instruction).collect();
+        all.push(call);
+        all.extend(self.trailing.iter().map(Step::instruction));
+        let message = Message::new(&all, Some(&PAYERS[0]));
+        bincode::serialize(&Transaction::new_unsigned(message)).unwrap()
+    }
+}
+
+/// The verdict of the on-chain check: the instructions sysvar is built
+/// from the sanitized message, as the runtime builds it.
+fn on_chain(message: &Message, priced_index: usize, params: &X402Params, scan: &ScanOptions) -> Verdict {
+    let sanitized = SanitizedMessage::try_from(message.clone()).unwrap();
+    let mut data = instructions::construct_instructions_data(&sanitized.decompile_instructions());
+    instructions::store_current_index(&mut data, priced_index as u16);
+
+    let (key, owner, mut lamports) = (instructions::ID, sysvar::ID, 0);
+    let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    verify_sol_payment(&[info], &PROGRAM_ID, params, scan)
+}
+
+type Verdict = Result<VerifiedPayment, X402Error>;
+
+fn agree(on_chain: &Verdict, off_chain: &Verdict) -> bool {
+    match (on_chain, off_chain) {
+        (Ok(a), Ok(b)) => a.amount == b.amount && a.payer == b.payer && a.ix_index == b.ix_index,
+        (Err(a), Err(b)) => u32::from(*a) == u32::from(*b),
+        _ => false,
+    }
+}
+
+/// Runs both checks on `transaction`. A disagreement is recorded as a
+/// fixture and described in the error.
+fn compare(
+    transaction: &[u8],
+    priced_index: usize,
+    params: &X402Params,
+    scan: &ScanOptions,
+) -> Result<(), String> {
+    let message = bincode::deserialize::<Transaction>(transaction).unwrap().message;
+    let on_chain = on_chain(&message, priced_index, params, scan);
+    let off_chain = verify_message_payment(&message, priced_index, &PROGRAM_ID, params, scan);
+    if agree(&on_chain, &off_chain) {
+        return Ok(());
+    }
+
+    let path = record(transaction, priced_index, params, scan);
+    Err(format!(
+        "on-chain {:?}, off-chain {:?}; recorded as {}",
+        on_chain,
+        off_chain,
+        path.display()
+    ))
+}
+
+fn fixtures() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/differential")
+}
+
+fn record(transaction: &[u8], priced_index: usize, params: &X402Params, scan: &ScanOptions) -> PathBuf {
+    let mint = match params.currency {
+        Currency::Sol => None,
+        Currency::Token(mint) => Some(mint.to_string()),
+    };
+    let fixture = serde_json::json!({
+        "transaction": STANDARD.encode(transaction),
+        "priced_index": priced_index,
+        "price": params.price,
+        "recipient": params.recipient.to_string(),
+        "mint": mint,
+        "max_lookback": scan.max_lookback,
+        "strict": scan.strict,
+    });
+
+    let name = hash(transaction).to_bytes()[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let path = fixtures().join(format!("{}.json", name));
+    fs::create_dir_all(fixtures()).unwrap();
+    fs::write(&path, serde_json::to_string_pretty(&fixture).unwrap()).unwrap();
+    path
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(512))]
+
+    #[test]
+    fn verifiers_agree(case in case()) {
+        let transaction = case.transaction();
+        let result = compare(&transaction, case.preceding.len(), &case.params(), &case.scan());
+        prop_assert!(result.is_ok(), "{}", result.unwrap_err());
+    }
+}
+
+#[test]
+fn recorded_cases_agree() {
+    let Ok(entries) = fs::read_dir(fixtures()) else {
+        return;
+    };
+    for entry in entries {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("json".as_ref()) {
+            continue;
+        }
+        let fixture: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        let transaction = STANDARD.decode(fixture["transaction"].as_str().unwrap()).unwrap();
+        let params = X402Params {
+            price: fixture["price"].as_u64().unwrap(),
+            recipient: fixture["recipient"].as_str().unwrap().parse().unwrap(),
+            currency: match fixture["mint"].as_str() {
+                Some(mint) => Currency::Token(mint.parse().unwrap()),
+                None => Currency::Sol,
+            },
+        };
+        let scan = ScanOptions {
+            max_lookback: fixture["max_lookback"].as_u64().unwrap() as u8,
+            strict: fixture["strict"].as_bool().unwrap(),
+            legacy_matcher: false,
+        };
+        let priced_index = fixture["priced_index"].as_u64().unwrap() as usize;
+        if let Err(disagreement) = compare(&transaction, priced_index, &params, &scan) {
+            panic!("{}: {}", path.display(), disagreement);
+        }
+    }
+}
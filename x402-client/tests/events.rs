@@ -0,0 +1,110 @@
+//! Decoding of events from transaction logs: the latency event, as an
+//! indexer computing payment-to-consumption latency reads it, and both
+//! versions of the payment event through one decoder.
+
+use anchor_lang::{AnchorSerialize, Discriminator};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use solana_program::pubkey::Pubkey;
+use x402_client::{decode_payments_verified, decode_x402_events, PaymentVerified};
+use x402_common::events::X402PaymentLatencyEvent;
+use x402_common::X402Event;
+
+fn log_line(event: &X402PaymentLatencyEvent) -> String {
+    let mut data = X402PaymentLatencyEvent::DISCRIMINATOR.to_vec();
+    event.serialize(&mut data).unwrap();
+    format!("Program data: {}", STANDARD.encode(data))
+}
+
+#[test]
+fn decodes_latency_events() {
+    let stored = X402PaymentLatencyEvent {
+        payer: Pubkey::new_unique(),
+        sequence: 0,
+        payment_slot: 1_000,
+        consumed_slot: 1_150,
+    };
+    let same_transaction = X402PaymentLatencyEvent {
+        payer: Pubkey::new_unique(),
+        sequence: 7,
+        payment_slot: 2_000,
+        consumed_slot: 2_000,
+    };
+    let logs = vec![log_line(&stored), "Program log: unrelated".to_string(), log_line(&same_transaction)];
+
+    let events = decode_x402_events(&logs);
+    assert_eq!(
+        events,
+        vec![
+            X402Event::PaymentLatency(stored.clone()),
+            X402Event::PaymentLatency(same_transaction.clone())
+        ]
+    );
+    assert_eq!(stored.latency_slots(), 150);
+    assert_eq!(same_transaction.latency_slots(), 0);
+}
+
+#[test]
+fn latency_is_never_negative() {
+    // Not produced by the program, but a hand-built or corrupted event must
+    // not underflow.
+    let event = X402PaymentLatencyEvent {
+        payer: Pubkey::new_unique(),
+        sequence: 0,
+        payment_slot: 10,
+        consumed_slot: 9,
+    };
+    assert_eq!(event.latency_slots(), 0);
+}
+
+/// Payment events captured from program logs: version 1, then version 2
+/// with an experiment arm and a nonce.
+const PAYMENT_LOGS: [&str; 2] = [
+    "Program data: 8ZO4WNN72/0BAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQICAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICQEIPAAAAAABAQg8AAAAAAAcAAAAAAAAAAAA=",
+    "Program data: gjggAHVYGzQCAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQECAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAoBPEgAAAAAAQEIPAAAAAAAIAAAAAAAAAAEBAQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJ",
+];
+
+#[test]
+fn decodes_both_payment_event_versions() {
+    let logs: Vec<String> = PAYMENT_LOGS.iter().map(|line| line.to_string()).collect();
+    let payer = "4vJ9JU1bJJE96FWSJKvHsmmFADCg4gpZQff4P3bkLKi".parse().unwrap();
+    let recipient = "8qbHbw2BbbTHBW1sbeqakYXVKRQM8Ne7pLK7m6CVfeR".parse().unwrap();
+
+    assert_eq!(
+        decode_payments_verified(&logs),
+        vec![
+            PaymentVerified {
+                version: 1,
+                payer,
+                recipient,
+                amount: 1_000_000,
+                price: 1_000_000,
+                sequence: 7,
+                experiment_arm: None,
+                nonce: None,
+            },
+            PaymentVerified {
+                version: 2,
+                payer,
+                recipient,
+                amount: 1_200_000,
+                price: 1_000_000,
+                sequence: 8,
+                experiment_arm: Some(1),
+                nonce: Some([9; 32]),
+            },
+        ]
+    );
+    let sequences: Vec<_> = decode_x402_events(&logs).iter().map(X402Event::sequence).collect();
+    assert_eq!(sequences, vec![Some(7), Some(8)]);
+}
+
+#[test]
+fn skips_unknown_payment_event_versions() {
+    // The version 2 line above with its version tag set to 3.
+    let logs = vec![
+        "Program data: gjggAHVYGzQDAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQECAgICAgICAgICAgICAgICAgICAgICAgICAgICAgICAoBPEgAAAAAAQEIPAAAAAAAIAAAAAAAAAAEBAQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJCQkJ"
+            .to_string(),
+    ];
+    assert!(decode_x402_events(&logs).is_empty());
+}
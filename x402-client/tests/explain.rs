@@ -0,0 +1,42 @@
+//! Building `explain_payment` and decoding its answer as the RPC reports
+//! it, with trailing zero bytes stripped.
+
+use anchor_lang::{AnchorDeserialize, AnchorSerialize};
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::instructions;
+use x402_client::{decode_explanation, explain_payment_instruction};
+use x402_common::explanation::{PaymentExplanation, EXPLAIN_PAYMENT_DISCRIMINATOR};
+use x402_common::{Currency, Mode, PriceProvenance};
+
+#[test]
+fn absent_pricing_accounts_are_passed_as_the_program() {
+    let (program_id, payer, config) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let instruction = explain_payment_instruction(&program_id, &payer, "treasury_query", Some(config), None, None);
+
+    let keys: Vec<_> = instruction.accounts.iter().map(|meta| meta.pubkey).collect();
+    assert_eq!(keys, [payer, config, program_id, program_id, instructions::ID]);
+    assert!(instruction.accounts.iter().all(|meta| !meta.is_signer && !meta.is_writable));
+
+    let (discriminator, mut args) = instruction.data.split_at(8);
+    assert_eq!(discriminator, EXPLAIN_PAYMENT_DISCRIMINATOR);
+    assert_eq!(String::deserialize(&mut args).unwrap(), "treasury_query");
+}
+
+#[test]
+fn decodes_an_explanation_with_its_zeroes_stripped() {
+    for paid in [None, Some(0), Some(1_000_000)] {
+        let explanation = PaymentExplanation {
+            price: 1_000_000,
+            recipient: Pubkey::new_unique(),
+            currency: Currency::Sol,
+            mode: Mode::PrecedingInstruction,
+            provenance: PriceProvenance::Static,
+            paid,
+        };
+        let mut data = explanation.try_to_vec().unwrap();
+        while data.last() == Some(&0) {
+            data.pop();
+        }
+        assert_eq!(decode_explanation(&data).unwrap(), explanation);
+    }
+}
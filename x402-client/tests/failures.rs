@@ -0,0 +1,136 @@
+//! The `FailureLog` ring buffer and the `report_failed_payment`
+//! instruction clients build after a rejected call.
+
+use anchor_lang::AnchorDeserialize;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::TransactionError;
+use x402_client::failures::{
+    decode_failure_log, failure_log_address, failure_reason, payment_failure, reason_error,
+    report_failed_payment_instruction,
+};
+use x402_client::preflight::classify_simulation;
+use x402_client::X402ClientError;
+use x402_common::failures::{
+    FailureEntry, FailureLogData, FailureRing, FAILURE_LOG_CAPACITY, FAILURE_LOG_DISCRIMINATOR,
+    REPORT_FAILED_PAYMENT_DISCRIMINATOR,
+};
+use x402_runtime::{X402Error, X402_ERRORS};
+
+fn entry(n: u64) -> FailureEntry {
+    FailureEntry::new(Pubkey::new_unique(), (n % X402_ERRORS.len() as u64) as u8, n, 1_000 + n)
+}
+
+fn amounts(ring: &FailureRing) -> Vec<u64> {
+    ring.recent().map(|entry| entry.attempted_amount).collect()
+}
+
+#[test]
+fn ring_fills_newest_first() {
+    let mut ring = FailureRing::default();
+    assert_eq!(ring.recent().count(), 0);
+
+    for n in 1..=3 {
+        ring.push(entry(n));
+    }
+    assert_eq!(ring.len, 3);
+    assert_eq!(ring.head, 3);
+    assert_eq!(amounts(&ring), vec![3, 2, 1]);
+}
+
+#[test]
+fn ring_wraps_around_over_the_oldest() {
+    let mut ring = FailureRing::default();
+    let capacity = FAILURE_LOG_CAPACITY as u64;
+
+    for n in 1..=capacity {
+        ring.push(entry(n));
+    }
+    assert_eq!(ring.len as usize, FAILURE_LOG_CAPACITY);
+    assert_eq!(ring.head, 0);
+    assert_eq!(amounts(&ring), (1..=capacity).rev().collect::<Vec<_>>());
+
+    // Each further entry overwrites exactly the oldest one.
+    for n in capacity + 1..=capacity + 5 {
+        ring.push(entry(n));
+    }
+    assert_eq!(ring.len as usize, FAILURE_LOG_CAPACITY);
+    assert_eq!(ring.head, 5);
+    assert_eq!(amounts(&ring), (6..=capacity + 5).rev().collect::<Vec<_>>());
+    assert_eq!(ring.entries[4].attempted_amount, capacity + 5);
+    assert_eq!(ring.entries[5].attempted_amount, 6);
+
+    // Many laps later, still the last `FAILURE_LOG_CAPACITY` entries.
+    for n in capacity + 6..=10 * capacity + 3 {
+        ring.push(entry(n));
+    }
+    let expected: Vec<u64> = (9 * capacity + 4..=10 * capacity + 3).rev().collect();
+    assert_eq!(amounts(&ring), expected);
+}
+
+#[test]
+fn decodes_the_log_account() {
+    let mut ring = FailureRing::default();
+    ring.push(entry(7));
+    let log = FailureLogData::new(2, ring);
+
+    let mut data = FAILURE_LOG_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(&log));
+    assert_eq!(decode_failure_log(&data).unwrap(), log);
+    // Account data fetched over RPC need not be aligned.
+    let unaligned = [&[0][..], &data].concat();
+    assert_eq!(decode_failure_log(&unaligned[1..]).unwrap(), log);
+
+    assert!(matches!(decode_failure_log(&data[8..]), Err(X402ClientError::InvalidAccount(_))));
+    assert!(matches!(
+        decode_failure_log(&data[..data.len() - 1]),
+        Err(X402ClientError::InvalidAccount(_))
+    ));
+}
+
+#[test]
+fn reasons_round_trip() {
+    for (index, error) in X402_ERRORS.iter().enumerate() {
+        assert_eq!(failure_reason(*error) as usize, index);
+        assert_eq!(reason_error(index as u8).map(u32::from), Some(u32::from(*error)));
+    }
+    assert!(reason_error(X402_ERRORS.len() as u8).is_none());
+}
+
+#[test]
+fn builds_the_report_instruction() {
+    let (program_id, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let ix = report_failed_payment_instruction(&program_id, &payer, X402Error::InsufficientPayment, 999_999);
+
+    assert_eq!(ix.program_id, program_id);
+    assert_eq!(ix.data[..8], REPORT_FAILED_PAYMENT_DISCRIMINATOR);
+    let (reason, amount) = <(u8, u64)>::try_from_slice(&ix.data[8..]).unwrap();
+    assert_eq!(reason, failure_reason(X402Error::InsufficientPayment));
+    assert_eq!(amount, 999_999);
+
+    assert_eq!(ix.accounts.len(), 3);
+    assert_eq!(ix.accounts[0].pubkey, payer);
+    assert!(ix.accounts[0].is_signer && ix.accounts[0].is_writable);
+    assert_eq!(ix.accounts[1].pubkey, failure_log_address(&program_id));
+    assert!(!ix.accounts[1].is_signer && ix.accounts[1].is_writable);
+}
+
+#[test]
+fn only_x402_rejections_are_reported() {
+    let rejected = classify_simulation(
+        Some(TransactionError::InstructionError(1, InstructionError::Custom(7004))),
+        vec!["Program log: AnchorError occurred. Error Code: InsufficientPayment. Error Number: 7004. \
+              Error Message: Payment amount is below the required price."
+            .to_string()],
+        None,
+    );
+    assert_eq!(
+        payment_failure(&rejected.outcome).map(u32::from),
+        Some(u32::from(X402Error::InsufficientPayment))
+    );
+
+    let passes = classify_simulation(None, Vec::new(), None);
+    assert!(payment_failure(&passes.outcome).is_none());
+    let unfunded = classify_simulation(Some(TransactionError::AccountNotFound), Vec::new(), None);
+    assert!(payment_failure(&unfunded.outcome).is_none());
+}
@@ -0,0 +1,134 @@
+//! Paging, filters and summaries behind the ledger listing. The RPC calls
+//! themselves need a validator and are not exercised here.
+
+use anchor_lang::AnchorSerialize;
+use solana_client::rpc_filter::{Memcmp, RpcFilterType};
+use solana_program::pubkey::Pubkey;
+use x402_client::listing::{decode_payment_ledger, ledger_filters, page_keys, summarize_ledgers, PageRequest};
+use x402_client::X402ClientError;
+use x402_common::ledger::{PaymentLedgerData, PAYMENT_LEDGER_PAYER_OFFSET};
+
+fn keys(count: usize) -> Vec<Pubkey> {
+    (0..count).map(|_| Pubkey::new_unique()).collect()
+}
+
+/// A ledger whose payer paid 7 lamports of rent per payment, so rent and
+/// revenue totals differ.
+fn ledger(payer: Pubkey, total_payments: u64, total_amount: u64, last_payment: i64) -> PaymentLedgerData {
+    let total_rent_paid = total_payments.saturating_mul(7);
+    PaymentLedgerData { version: 2, payer, total_payments, total_amount, last_payment, total_rent_paid }
+}
+
+#[test]
+fn pages_cover_every_key_once_in_order() {
+    let mut all = keys(47);
+    let mut sorted = all.clone();
+    sorted.sort();
+    all.reverse();
+
+    let mut seen = Vec::new();
+    let mut after = None;
+    loop {
+        let (page, next) = page_keys(all.clone(), PageRequest { after, limit: 10 });
+        assert!(page.len() <= 10);
+        seen.extend(page);
+        match next {
+            Some(next) => after = Some(next),
+            None => break,
+        }
+    }
+    assert_eq!(seen, sorted);
+}
+
+#[test]
+fn page_boundaries() {
+    let all = keys(20);
+    let mut sorted = all.clone();
+    sorted.sort();
+
+    // Exactly one page: no cursor.
+    let (page, next) = page_keys(all.clone(), PageRequest { after: None, limit: 20 });
+    assert_eq!(page, sorted);
+    assert_eq!(next, None);
+
+    // A cursor past the last key, or a zero limit, yields nothing.
+    let (page, next) = page_keys(all.clone(), PageRequest { after: sorted.last().copied(), limit: 5 });
+    assert!(page.is_empty());
+    assert_eq!(next, None);
+    let (page, next) = page_keys(all.clone(), PageRequest { after: None, limit: 0 });
+    assert!(page.is_empty());
+    assert_eq!(next, None);
+
+    // A cursor that is no longer listed (its account was closed) resumes
+    // at the next key.
+    let mut without = sorted.clone();
+    let closed = without.remove(7);
+    let (page, _) = page_keys(without, PageRequest { after: Some(closed), limit: 3 });
+    assert_eq!(page, sorted[8..11]);
+
+    // Duplicates are listed once.
+    let mut doubled = all.clone();
+    doubled.extend(all);
+    let (page, _) = page_keys(doubled, PageRequest { after: None, limit: 40 });
+    assert_eq!(page, sorted);
+}
+
+#[test]
+fn payer_filter_matches_the_payer_field() {
+    assert!(ledger_filters(None).is_empty());
+
+    let payer = Pubkey::new_unique();
+    assert_eq!(
+        ledger_filters(Some(&payer)),
+        vec![RpcFilterType::Memcmp(Memcmp::new_base58_encoded(
+            PAYMENT_LEDGER_PAYER_OFFSET,
+            payer.as_ref()
+        ))]
+    );
+
+    // The offset counts the discriminator and `version`.
+    let mut data = vec![0u8; 8];
+    ledger(payer, 1, 2, 3).serialize(&mut data).unwrap();
+    assert_eq!(&data[PAYMENT_LEDGER_PAYER_OFFSET..PAYMENT_LEDGER_PAYER_OFFSET + 32], payer.as_ref());
+}
+
+#[test]
+fn decodes_ledger_bodies() {
+    let expected = ledger(Pubkey::new_unique(), 3, 3_000_000, 1_700_000_000);
+    let body = expected.try_to_vec().unwrap();
+    assert_eq!(decode_payment_ledger(&body).unwrap(), expected);
+
+    assert!(matches!(
+        decode_payment_ledger(&body[..body.len() - 1]),
+        Err(X402ClientError::InvalidAccount(_))
+    ));
+}
+
+#[test]
+fn summarizes_per_payer() {
+    let payers = keys(3);
+    let now = 1_700_000_000;
+    let mut ledgers = Vec::new();
+    for (i, payer) in payers.iter().enumerate() {
+        for j in 0..10 {
+            let i = i as u64;
+            ledgers.push(ledger(*payer, i + 1, (i + 1) * 1_000, now - 3_600 * (j + i as i64 * 20)));
+        }
+    }
+    ledgers.push(ledger(payers[0], u64::MAX, u64::MAX, now));
+
+    let totals = summarize_ledgers(&ledgers, None);
+    assert_eq!(totals.len(), 3);
+    assert_eq!(totals[&payers[0]].total_payments, u64::MAX);
+    assert_eq!(totals[&payers[0]].last_payment, now);
+    assert_eq!(totals[&payers[1]].total_payments, 20);
+    assert_eq!(totals[&payers[1]].total_amount, 20_000);
+    assert_eq!(totals[&payers[1]].total_rent_paid, 140);
+    assert_eq!(totals[&payers[2]].last_payment, now - 3_600 * 40);
+
+    // Only ledgers paid into within the last day.
+    let recent = summarize_ledgers(&ledgers, Some(now - 86_400));
+    assert_eq!(recent.len(), 2);
+    assert_eq!(recent[&payers[1]].total_payments, 2 * 5);
+    assert!(!recent.contains_key(&payers[2]));
+}
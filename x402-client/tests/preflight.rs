@@ -0,0 +1,174 @@
+//! Classification of canned simulation results, and one simulation against
+//! a live cluster.
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::instruction::InstructionError;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use x402_client::preflight::{classify_simulation, is_clock_dependent, preflight_paid_call, x402_error, PreflightOutcome};
+use x402_runtime::{X402Error, X402_ERRORS, X402_ERROR_CODE_OFFSET};
+
+/// A program's own errors, numbered from 6000 by Anchor, with names the
+/// x402 errors also use.
+#[anchor_lang::error_code]
+pub enum OwnError {
+    #[msg("Insufficient payment for x402 access.")]
+    InsufficientPayment,
+    #[msg("Missing sysvar.")]
+    SysvarMissing,
+    #[msg("Unavailable sysvar.")]
+    SysvarUnavailable,
+    #[msg("Not a payment.")]
+    NotAPayment,
+}
+
+fn logs(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+fn custom(instruction: u8, code: u32) -> Option<TransactionError> {
+    Some(TransactionError::InstructionError(instruction, InstructionError::Custom(code)))
+}
+
+#[test]
+fn error_list_is_in_code_order() {
+    for (index, error) in X402_ERRORS.iter().enumerate() {
+        assert_eq!(u32::from(*error), X402_ERROR_CODE_OFFSET + index as u32);
+        assert_eq!(x402_error(u32::from(*error)).map(u32::from), Some(u32::from(*error)));
+    }
+    assert_eq!(X402_ERROR_CODE_OFFSET, 7000);
+    assert!(x402_error(X402_ERROR_CODE_OFFSET - 1).is_none());
+    assert!(x402_error(X402_ERROR_CODE_OFFSET + X402_ERRORS.len() as u32).is_none());
+}
+
+#[test]
+fn success_reports_compute_units() {
+    let preflight = classify_simulation(None, logs(&["Program log: Instruction: Compute"]), Some(31_337));
+    assert!(matches!(preflight.outcome, PreflightOutcome::Passes));
+    assert_eq!(preflight.units_consumed, Some(31_337));
+    assert_eq!(preflight.logs.len(), 1);
+}
+
+#[test]
+fn x402_error_is_a_rejected_payment() {
+    let preflight = classify_simulation(
+        custom(1, 7003),
+        logs(&[
+            "Program 11111111111111111111111111111111 success",
+            "Program log: Instruction: PremiumCompute",
+            "Program log: AnchorError thrown in x402-runtime/src/verify.rs:210. Error Code: NotAPayment. \
+             Error Number: 7003. Error Message: Preceding instruction is not a recognized payment.",
+        ]),
+        Some(4_200),
+    );
+    match preflight.outcome {
+        PreflightOutcome::PaymentRejected { instruction, error } => {
+            assert_eq!(instruction, 1);
+            assert_eq!(u32::from(error), u32::from(X402Error::NotAPayment));
+        }
+        other => panic!("expected a rejected payment, got {:?}", other),
+    }
+}
+
+#[test]
+fn program_error_with_a_shared_name_is_a_handler_failure() {
+    // The example program's own `InsufficientPayment` is code 6000, below the x402 range.
+    let preflight = classify_simulation(
+        custom(1, 6000),
+        logs(&["Program log: AnchorError occurred. Error Code: InsufficientPayment. Error Number: 6000. \
+                Error Message: Insufficient payment for x402 access."]),
+        None,
+    );
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::CallFailed { instruction: Some(1), .. }
+    ));
+
+    // Nor is a program's error numbered into the x402 range.
+    let preflight = classify_simulation(
+        custom(1, 7003),
+        logs(&["Program log: AnchorError occurred. Error Code: StaleQuote. Error Number: 7003. \
+                Error Message: The quote has expired."]),
+        None,
+    );
+    assert!(matches!(preflight.outcome, PreflightOutcome::CallFailed { .. }));
+}
+
+#[test]
+fn x402_errors_are_not_mistaken_for_the_programs_own() {
+    let anchor_log = |name: String, number: u32| {
+        vec![format!("Program log: AnchorError occurred. Error Code: {}. Error Number: {}. Error Message: .", name, number)]
+    };
+
+    let own = OwnError::NotAPayment;
+    let preflight = classify_simulation(custom(1, own.into()), anchor_log(own.name(), own.into()), None);
+    assert!(matches!(preflight.outcome, PreflightOutcome::CallFailed { instruction: Some(1), .. }));
+
+    let x402 = X402Error::NotAPayment;
+    let preflight = classify_simulation(custom(1, x402.into()), anchor_log(x402.name(), x402.into()), None);
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::PaymentRejected { instruction: 1, error: X402Error::NotAPayment }
+    ));
+
+    // The code alone tells them apart when the logs are missing.
+    let preflight = classify_simulation(custom(1, own.into()), Vec::new(), None);
+    assert!(matches!(preflight.outcome, PreflightOutcome::CallFailed { .. }));
+    let preflight = classify_simulation(custom(1, x402.into()), Vec::new(), None);
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::PaymentRejected { instruction: 1, error: X402Error::NotAPayment }
+    ));
+}
+
+#[test]
+fn non_custom_errors_are_call_failures() {
+    let preflight = classify_simulation(
+        Some(TransactionError::InstructionError(0, InstructionError::InsufficientFunds)),
+        Vec::new(),
+        None,
+    );
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::CallFailed { instruction: Some(0), .. }
+    ));
+
+    let preflight = classify_simulation(Some(TransactionError::AccountNotFound), Vec::new(), None);
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::CallFailed { instruction: None, err: TransactionError::AccountNotFound }
+    ));
+}
+
+#[test]
+fn spending_cap_is_indeterminate() {
+    assert!(is_clock_dependent(X402Error::SpendingCapExceeded));
+    assert!(!is_clock_dependent(X402Error::InsufficientPayment));
+
+    let preflight = classify_simulation(
+        custom(1, u32::from(X402Error::SpendingCapExceeded)),
+        logs(&["Program log: AnchorError thrown in x402-runtime/src/spending_cap.rs:43. Error Code: \
+                SpendingCapExceeded. Error Number: 7006. Error Message: Payment would exceed the payer's spending cap."]),
+        None,
+    );
+    assert!(matches!(
+        preflight.outcome,
+        PreflightOutcome::Indeterminate { instruction: 1, error: X402Error::SpendingCapExceeded }
+    ));
+}
+
+/// Needs a cluster at `SOLANA_RPC_URL` (default a local validator):
+/// `cargo test -p x402-client --test preflight -- --ignored`.
+#[test]
+#[ignore = "needs a running cluster"]
+fn live_transfer_from_an_unfunded_payer_fails() {
+    let url = std::env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "http://127.0.0.1:8899".to_string());
+    let rpc = RpcClient::new(url);
+    let payer = Pubkey::new_unique();
+    let transfer = system_instruction::transfer(&payer, &Pubkey::new_unique(), 5_000_000);
+    let transaction = Transaction::new_with_payer(&[transfer], Some(&payer));
+
+    let preflight = preflight_paid_call(&rpc, &transaction).unwrap();
+    assert!(matches!(preflight.outcome, PreflightOutcome::CallFailed { .. }));
+}
@@ -0,0 +1,145 @@
+//! Auditing recorded prices: a price the check enforced, with the
+//! provenance the expansion records for it, passes the audit, and a
+//! provenance or price that does not match the pricing state fails it.
+
+use solana_sdk::pubkey::Pubkey;
+use x402_client::provenance::{audit_price, PricingState, ProvenanceMismatch};
+use x402_client::quote::PricedInstruction;
+use x402_common::congestion::CongestionLevelData;
+use x402_runtime::congestion::{CongestionSurcharge, LevelChange};
+use x402_runtime::experiment::{Experiment, ExperimentState};
+use x402_runtime::{Currency, PriceProvenance, X402Params};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+const PARAMS: X402Params = X402Params {
+    price: 200_000,
+    recipient: Pubkey::new_from_array([9; 32]),
+    currency: Currency::Sol,
+};
+
+const SURCHARGE: CongestionSurcharge = CongestionSurcharge {
+    account: Pubkey::new_from_array([11; 32]),
+    bps_per_level: 500,
+};
+
+const EXPERIMENT: Experiment = Experiment {
+    bucket_seed: "exp1",
+    prices: &[200_000, 160_000],
+};
+
+fn congestion(level: u8, previous_level: u8, updated_at: i64) -> CongestionLevelData {
+    CongestionLevelData {
+        version: 2,
+        authority: Pubkey::new_unique(),
+        level,
+        previous_level,
+        updated_at,
+    }
+}
+
+#[test]
+fn static_prices_are_the_declared_price() {
+    let instruction = PricedInstruction::new(PARAMS);
+    let audit = |provenance, price| audit_price(&instruction, &PROGRAM_ID, &PAYER, &provenance, &PricingState::None, price);
+
+    audit(PriceProvenance::Static, 200_000).unwrap();
+    assert_eq!(
+        audit(PriceProvenance::Static, 150_000),
+        Err(ProvenanceMismatch::Price {
+            recorded: 150_000,
+            expected: 200_000
+        })
+    );
+    let surcharged = PriceProvenance::Congestion {
+        account: SURCHARGE.account,
+        level: 0,
+        updated_at: 0,
+    };
+    assert_eq!(audit(surcharged, 200_000), Err(ProvenanceMismatch::WrongKind));
+}
+
+#[test]
+fn congestion_prices_follow_the_level_the_check_enforced() {
+    let instruction = PricedInstruction::new(PARAMS).with_surcharge(SURCHARGE);
+    let change = LevelChange {
+        previous_level: 1,
+        updated_at: 1_700_000_000,
+    };
+    let held = PricingState::Congestion(congestion(3, change.previous_level, change.updated_at));
+
+    // Raised from 1 to 3: inside the grace window level 1 was charged, and
+    // past it level 3.
+    for now in [change.updated_at, change.updated_at + 30] {
+        let enforced = SURCHARGE.apply_with_window_at(&PARAMS, &SURCHARGE.account, 3, change, now, 30).unwrap();
+        let provenance = SURCHARGE.provenance(&PARAMS, &enforced, &SURCHARGE.account, 3, change);
+        audit_price(&instruction, &PROGRAM_ID, &PAYER, &provenance, &held, enforced.price).unwrap();
+    }
+
+    let recorded = |level, updated_at| PriceProvenance::Congestion {
+        account: SURCHARGE.account,
+        level,
+        updated_at,
+    };
+    let audit = |provenance, price| audit_price(&instruction, &PROGRAM_ID, &PAYER, &provenance, &held, price);
+    assert_eq!(
+        audit(recorded(3, change.updated_at), 220_000),
+        Err(ProvenanceMismatch::Price {
+            recorded: 220_000,
+            expected: 230_000
+        })
+    );
+    assert_eq!(
+        audit(recorded(2, change.updated_at), 220_000),
+        Err(ProvenanceMismatch::Level { recorded: 2, held: 3 })
+    );
+    assert_eq!(
+        audit(recorded(3, 1_600_000_000), 230_000),
+        Err(ProvenanceMismatch::UpdatedAt {
+            recorded: 1_600_000_000,
+            held: change.updated_at
+        })
+    );
+    let elsewhere = Pubkey::new_unique();
+    let provenance = PriceProvenance::Congestion {
+        account: elsewhere,
+        level: 3,
+        updated_at: change.updated_at,
+    };
+    assert_eq!(
+        audit(provenance, 230_000),
+        Err(ProvenanceMismatch::Account {
+            recorded: elsewhere,
+            expected: SURCHARGE.account
+        })
+    );
+}
+
+#[test]
+fn experiment_prices_are_the_payers_arm() {
+    let instruction = PricedInstruction::new(PARAMS).with_experiment(EXPERIMENT);
+    let account = EXPERIMENT.address(&PROGRAM_ID);
+    let running = PricingState::Experiment(ExperimentState::default());
+    let bucket = EXPERIMENT.bucket(&PAYER);
+    let (params, arm) = EXPERIMENT.apply(&PARAMS, &PAYER, ExperimentState::default()).unwrap();
+    let provenance = PriceProvenance::Experiment { account, arm };
+    audit_price(&instruction, &PROGRAM_ID, &PAYER, &provenance, &running, params.price).unwrap();
+
+    let other = PriceProvenance::Experiment { account, arm: 1 - bucket };
+    let other_price = EXPERIMENT.prices[usize::from(1 - bucket)];
+    assert_eq!(
+        audit_price(&instruction, &PROGRAM_ID, &PAYER, &other, &running, other_price),
+        Err(ProvenanceMismatch::Arm {
+            recorded: 1 - bucket,
+            expected: bucket
+        })
+    );
+
+    // Ended on the other arm, which every later payer is charged.
+    let ended = PricingState::Experiment(ExperimentState {
+        ended: true,
+        winner: 1 - bucket,
+    });
+    audit_price(&instruction, &PROGRAM_ID, &PAYER, &other, &ended, other_price).unwrap();
+}
@@ -0,0 +1,284 @@
+//! Quotes against enforcement: for every pricing mode, paying exactly the
+//! quoted amount to the quoted recipient must pass the check the handler
+//! runs, and a lamport less must not.
+
+use anchor_lang::prelude::AccountInfo;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction;
+use solana_program::sysvar::{self, instructions};
+use solana_sdk::pubkey::Pubkey;
+use x402_client::congestion::payment_instruction;
+use x402_client::quote::{
+    decode_config, decode_price_experiment, quote_with, quote_with_payer, PricedInstruction, QuoteComponent,
+};
+use x402_client::X402ClientError;
+use x402_runtime::config::configured_recipient;
+use x402_runtime::congestion::{CongestionSurcharge, MAX_CONGESTION_LEVEL};
+use x402_runtime::experiment::{Experiment, ExperimentState};
+use x402_runtime::revenue_split::{verify_revenue_split, RevenueShare};
+use x402_runtime::{verify_payment, Currency, X402Params};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const CONFIGURED: Pubkey = Pubkey::new_from_array([10; 32]);
+
+fn params(recipient: Pubkey) -> X402Params {
+    X402Params {
+        price: 200_000,
+        recipient,
+        currency: Currency::Sol,
+    }
+}
+
+fn surcharge() -> CongestionSurcharge {
+    CongestionSurcharge {
+        account: Pubkey::new_from_array([11; 32]),
+        bps_per_level: 500,
+    }
+}
+
+const SPLITS: &[RevenueShare] = &[
+    RevenueShare { recipient: RECIPIENT, bps: 6_000 },
+    RevenueShare {
+        recipient: Pubkey::new_from_array([12; 32]),
+        bps: 3_333,
+    },
+    RevenueShare {
+        recipient: Pubkey::new_from_array([13; 32]),
+        bps: 667,
+    },
+];
+
+const EXPERIMENT: Experiment = Experiment {
+    bucket_seed: "exp1",
+    prices: &[200_000, 160_000],
+};
+
+/// The params the `#[x402]` expansion enforces for `instruction`, given
+/// the recipient in the program's config and the congestion level.
+fn enforced(instruction: &PricedInstruction, level: u8) -> X402Params {
+    let mut params = instruction.params;
+    if instruction.uses_configured_recipient() {
+        params.recipient = configured_recipient(Some(CONFIGURED)).unwrap();
+    }
+    match &instruction.surcharge {
+        Some(surcharge) => surcharge.apply_from(&params, &surcharge.account, level).unwrap(),
+        None => params,
+    }
+}
+
+/// Runs the check against `payment` followed by the priced call.
+fn accepts(params: &X402Params, payment: Instruction, payer: &Pubkey) -> bool {
+    let mut data = sysvar_data(vec![payment], payer);
+    let (key, owner, mut lamports) = (instructions::ID, sysvar::ID, 0);
+    let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    verify_payment(&[info], &PROGRAM_ID, params).is_ok()
+}
+
+/// Runs the revenue split check against `payments` followed by the priced
+/// call.
+fn accepts_split(params: &X402Params, shares: &[RevenueShare], payments: Vec<Instruction>, payer: &Pubkey) -> bool {
+    let mut data = sysvar_data(payments, payer);
+    let (key, owner, mut lamports) = (instructions::ID, sysvar::ID, 0);
+    let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    verify_revenue_split(&[info], &PROGRAM_ID, params, shares).is_ok()
+}
+
+/// Instructions sysvar data for `payments` followed by a priced call from
+/// `payer`, positioned at the call.
+fn sysvar_data(mut all: Vec<Instruction>, payer: &Pubkey) -> Vec<u8> {
+    all.push(Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(*payer, true)],
+        data: vec![0; 8],
+    });
+    let borrowed: Vec<_> = all
+        .iter()
+        .map(|ix| instructions::BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| instructions::BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, (all.len() - 1) as u16);
+    data
+}
+
+fn assert_quote_is_enforced(instruction: PricedInstruction, level: u8) {
+    let recipient = if instruction.uses_configured_recipient() { CONFIGURED } else { RECIPIENT };
+    let quote = quote_with(&instruction, recipient, Some(level)).unwrap();
+    let enforced = enforced(&instruction, level);
+    assert_eq!(quote.params(), enforced);
+    assert_eq!(quote.amount, quote.components.iter().map(QuoteComponent::amount).sum::<u64>());
+
+    let payer = Pubkey::new_unique();
+    let payment = payment_instruction(&payer, &quote.params()).unwrap();
+    assert!(accepts(&enforced, payment, &payer), "quoted {:?} was rejected", quote);
+
+    let short = X402Params {
+        price: quote.amount - 1,
+        ..quote.params()
+    };
+    let payment = payment_instruction(&payer, &short).unwrap();
+    assert!(!accepts(&enforced, payment, &payer), "underpaying {:?} was accepted", quote);
+}
+
+#[test]
+fn fixed_price() {
+    assert_quote_is_enforced(PricedInstruction::new(params(RECIPIENT)), 0);
+}
+
+#[test]
+fn configured_recipient_price() {
+    let instruction = PricedInstruction::new(params(Pubkey::default()));
+    assert!(instruction.uses_configured_recipient());
+    assert_quote_is_enforced(instruction, 0);
+}
+
+#[test]
+fn congestion_surcharged_price_at_every_level() {
+    for level in 0..=MAX_CONGESTION_LEVEL {
+        assert_quote_is_enforced(PricedInstruction::new(params(RECIPIENT)).with_surcharge(surcharge()), level);
+        assert_quote_is_enforced(
+            PricedInstruction::new(params(Pubkey::default())).with_surcharge(surcharge()),
+            level,
+        );
+    }
+}
+
+#[test]
+fn itemizes_the_surcharge() {
+    let instruction = PricedInstruction::new(params(RECIPIENT)).with_surcharge(surcharge());
+    let quote = quote_with(&instruction, RECIPIENT, Some(2)).unwrap();
+    assert_eq!(
+        quote.components,
+        vec![
+            QuoteComponent::Base { price: 200_000 },
+            QuoteComponent::CongestionSurcharge {
+                level: 2,
+                bps_per_level: 500,
+                amount: 20_000,
+            },
+        ]
+    );
+    assert_eq!(quote.amount, 220_000);
+}
+
+#[test]
+fn refuses_what_the_check_would_refuse() {
+    let instruction = PricedInstruction::new(params(RECIPIENT)).with_surcharge(surcharge());
+    let err = quote_with(&instruction, RECIPIENT, Some(MAX_CONGESTION_LEVEL + 1)).unwrap_err();
+    assert!(matches!(err, X402ClientError::InvalidAccount(_)));
+    let err = quote_with(&instruction, RECIPIENT, None).unwrap_err();
+    assert!(matches!(err, X402ClientError::InvalidAccount(_)));
+
+    assert!(matches!(decode_config(&[0; 8]), Err(X402ClientError::InvalidAccount(_))));
+}
+
+#[test]
+fn only_dynamic_prices_carry_a_maximum() {
+    let fixed = quote_with(&PricedInstruction::new(params(RECIPIENT)), RECIPIENT, None).unwrap();
+    assert_eq!(fixed.max_acceptable_price(), None);
+
+    let instruction = PricedInstruction::new(params(RECIPIENT)).with_surcharge(surcharge());
+    let quote = quote_with(&instruction, RECIPIENT, Some(0)).unwrap();
+    assert_eq!(quote.max_acceptable_price(), Some(200_000));
+    let quote = quote_with(&instruction, RECIPIENT, Some(2)).unwrap();
+    assert_eq!(quote.max_acceptable_price(), Some(220_000));
+}
+
+#[test]
+fn experiment_quotes_match_the_charged_arm() {
+    let instruction = PricedInstruction::new(params(RECIPIENT)).with_experiment(EXPERIMENT);
+    let states = [
+        ExperimentState::default(),
+        ExperimentState { ended: true, winner: 0 },
+        ExperimentState { ended: true, winner: 1 },
+    ];
+    let mut arms = [false; 2];
+    for _ in 0..32 {
+        let payer = Pubkey::new_unique();
+        for state in states {
+            let quote = quote_with_payer(&instruction, RECIPIENT, None, &payer, state).unwrap();
+            let (enforced, arm) = EXPERIMENT
+                .apply_from(&instruction.params, &EXPERIMENT.address(&PROGRAM_ID), &PROGRAM_ID, &payer, state)
+                .unwrap();
+            assert_eq!(quote.params(), enforced);
+            assert_eq!(quote.components, vec![QuoteComponent::ExperimentArm { arm, price: enforced.price }]);
+            assert_eq!(quote.max_acceptable_price(), None);
+            arms[arm as usize] = true;
+
+            let payment = payment_instruction(&payer, &quote.params()).unwrap();
+            assert!(accepts(&enforced, payment, &payer), "quoted {:?} was rejected", quote);
+            let short = X402Params {
+                price: quote.amount - 1,
+                ..quote.params()
+            };
+            let payment = payment_instruction(&payer, &short).unwrap();
+            assert!(!accepts(&enforced, payment, &payer), "underpaying {:?} was accepted", quote);
+        }
+        assert_eq!(
+            quote_with_payer(&instruction, RECIPIENT, None, &payer, states[0]).unwrap(),
+            quote_with_payer(&instruction, RECIPIENT, None, &payer, states[0]).unwrap(),
+        );
+    }
+    assert_eq!(arms, [true; 2]);
+}
+
+#[test]
+fn experiments_are_quoted_per_payer() {
+    let instruction = PricedInstruction::new(params(RECIPIENT)).with_experiment(EXPERIMENT);
+    assert!(matches!(
+        quote_with(&instruction, RECIPIENT, None),
+        Err(X402ClientError::InvalidAccount(_))
+    ));
+
+    let ended = ExperimentState { ended: true, winner: 2 };
+    assert!(quote_with_payer(&instruction, RECIPIENT, None, &Pubkey::new_unique(), ended).is_err());
+}
+
+#[test]
+fn decodes_a_price_experiment() {
+    let mut data = x402_common::experiment::PRICE_EXPERIMENT_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&[1]);
+    data.extend_from_slice(RECIPIENT.as_ref());
+    data.extend_from_slice(&[1, 1]);
+    let experiment = decode_price_experiment(&data).unwrap();
+    assert_eq!((experiment.authority, experiment.ended, experiment.winner), (RECIPIENT, true, 1));
+
+    assert!(decode_price_experiment(&data[8..]).is_err());
+}
+
+#[test]
+fn split_quotes_build_every_leg() {
+    let instruction = PricedInstruction::new(params(RECIPIENT))
+        .with_surcharge(surcharge())
+        .with_splits(SPLITS);
+    let payer = Pubkey::new_unique();
+    for level in 0..=MAX_CONGESTION_LEVEL {
+        let quote = quote_with(&instruction, RECIPIENT, Some(level)).unwrap();
+        let enforced = enforced(&instruction, level);
+        assert_eq!(quote.params(), enforced);
+
+        let legs = quote.payment_instructions(&payer);
+        assert_eq!(legs.len(), SPLITS.len());
+        assert!(accepts_split(&enforced, SPLITS, legs.clone(), &payer), "quoted {:?} was rejected", quote);
+        for short in 0..legs.len() {
+            let mut legs = legs.clone();
+            legs[short] = system_instruction::transfer(&payer, &SPLITS[short].recipient, 1);
+            assert!(!accepts_split(&enforced, SPLITS, legs, &payer), "leg {} was not needed", short);
+        }
+    }
+
+    let fixed = quote_with(&PricedInstruction::new(params(RECIPIENT)), RECIPIENT, None).unwrap();
+    assert_eq!(fixed.payment_instructions(&payer), vec![payment_instruction(&payer, &fixed.params()).unwrap()]);
+}
@@ -0,0 +1,126 @@
+//! Retrying a compute call whose first attempt may have landed: the client
+//! decides from the result address alone whether to pay again, so a call
+//! that succeeded is paid for once however often it is retried.
+
+use anchor_lang::AnchorSerialize;
+use solana_program::pubkey::Pubkey;
+use solana_program::system_program;
+use solana_sdk::account::Account;
+use x402_client::retry::{compute_result_address, decode_compute_result, retry_decision, RetryDecision};
+use x402_common::result::{ComputeResultData, COMPUTE_RESULT_DISCRIMINATOR, COMPUTE_RESULT_SEED};
+use x402_common::PriceProvenance;
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const PRICE: u64 = 1_000_000;
+
+fn result(owner: &Pubkey, paid: bool) -> ComputeResultData {
+    ComputeResultData {
+        version: 5,
+        owner: *owner,
+        value: 42,
+        paid,
+        amount_paid: PRICE,
+        tier: 1,
+        price_paid: PRICE,
+        created_slot: 10,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    }
+}
+
+fn account(owner: Pubkey, data: Vec<u8>) -> Account {
+    Account {
+        lamports: 2_000_000,
+        data,
+        owner,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn result_account(result: &ComputeResultData) -> Account {
+    let mut data = COMPUTE_RESULT_DISCRIMINATOR.to_vec();
+    result.serialize(&mut data).unwrap();
+    account(PROGRAM_ID, data)
+}
+
+/// The result address of one call as attempts land or revert, and how
+/// many payments landed with them.
+#[derive(Default)]
+struct Chain {
+    result: Option<Account>,
+    payments: u32,
+}
+
+impl Chain {
+    /// Sends the call, paying for it, unless the result address shows an
+    /// earlier success. A landed attempt commits its payment and result
+    /// together; a reverted one neither.
+    fn attempt(&mut self, payer: &Pubkey, lands: bool) -> RetryDecision {
+        let decision = retry_decision(&PROGRAM_ID, payer, self.result.as_ref());
+        if decision == RetryDecision::Pay && lands {
+            self.payments += 1;
+            self.result = Some(result_account(&result(payer, true)));
+        }
+        decision
+    }
+}
+
+#[test]
+fn a_landed_attempt_is_not_paid_again() {
+    let payer = Pubkey::new_unique();
+    let mut chain = Chain::default();
+
+    // The first attempt lands, but its confirmation never reaches the
+    // client, which retries twice.
+    assert_eq!(chain.attempt(&payer, true), RetryDecision::Pay);
+    for _ in 0..2 {
+        assert_eq!(
+            chain.attempt(&payer, true),
+            RetryDecision::AlreadySucceeded(result(&payer, true))
+        );
+    }
+    assert_eq!(chain.payments, 1);
+}
+
+#[test]
+fn a_reverted_attempt_is_paid_once_on_retry() {
+    let payer = Pubkey::new_unique();
+    let mut chain = Chain::default();
+
+    assert_eq!(chain.attempt(&payer, false), RetryDecision::Pay);
+    assert_eq!(chain.payments, 0);
+    assert_eq!(chain.attempt(&payer, true), RetryDecision::Pay);
+    assert!(matches!(chain.attempt(&payer, true), RetryDecision::AlreadySucceeded(_)));
+    assert_eq!(chain.payments, 1);
+}
+
+#[test]
+fn anything_else_at_the_address_needs_a_new_nonce() {
+    let payer = Pubkey::new_unique();
+    let decide = |account: &Account| retry_decision(&PROGRAM_ID, &payer, Some(account));
+
+    assert_eq!(decide(&result_account(&result(&Pubkey::new_unique(), true))), RetryDecision::Conflict);
+    assert_eq!(decide(&result_account(&result(&payer, false))), RetryDecision::Conflict);
+    assert_eq!(decide(&account(PROGRAM_ID, vec![1; 16])), RetryDecision::Conflict);
+    let foreign = result_account(&result(&payer, true));
+    assert_eq!(decide(&account(Pubkey::new_unique(), foreign.data)), RetryDecision::Conflict);
+
+    // Lamports sent to the address ahead of the call do not block it.
+    assert_eq!(decide(&account(system_program::ID, Vec::new())), RetryDecision::Pay);
+}
+
+#[test]
+fn addresses_and_layout_match_the_program() {
+    let payer = Pubkey::new_unique();
+    let (pda, _) =
+        Pubkey::find_program_address(&[COMPUTE_RESULT_SEED, payer.as_ref(), &7u64.to_le_bytes()], &PROGRAM_ID);
+    assert_eq!(compute_result_address(&PROGRAM_ID, &payer, 7), pda);
+    assert_ne!(compute_result_address(&PROGRAM_ID, &payer, 8), pda);
+
+    let stored = result_account(&result(&payer, true));
+    assert_eq!(decode_compute_result(&stored.data).unwrap(), result(&payer, true));
+    assert!(decode_compute_result(&stored.data[8..]).is_err());
+}
@@ -0,0 +1,30 @@
+//! Decoding of view return data as the RPC reports it, with trailing zero
+//! bytes stripped.
+
+use x402_client::{decode_return_data, X402ClientError};
+
+#[test]
+fn decodes_untrimmed_value() {
+    let data = 0x0102_0304_0506_0708u64.to_le_bytes();
+    assert_eq!(decode_return_data::<u64>(&data).unwrap(), 0x0102_0304_0506_0708);
+}
+
+#[test]
+fn restores_trimmed_zero_bytes() {
+    // 42u64 is reported as the single byte 0x2a.
+    assert_eq!(decode_return_data::<u64>(&[42]).unwrap(), 42);
+}
+
+#[test]
+fn missing_return_data_is_zero() {
+    assert_eq!(decode_return_data::<u64>(&[]).unwrap(), 0);
+}
+
+#[test]
+fn rejects_oversized_data() {
+    let data = vec![1u8; 1025];
+    assert!(matches!(
+        decode_return_data::<u64>(&data),
+        Err(X402ClientError::InvalidReturnData(_))
+    ));
+}
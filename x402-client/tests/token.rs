@@ -0,0 +1,128 @@
+//! A first token payment to a fresh recipient: the recipient's account is
+//! created in the same transaction, and the runtime's check steps over its
+//! creation to the transfer.
+
+use anchor_lang::prelude::AccountInfo;
+use solana_program::message::{Message, SanitizedMessage};
+use solana_program::sysvar::{self, instructions};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use x402_client::token::{
+    create_ata_idempotent, recipient_ata, recipient_ata_with, token_payment_instructions_with, TokenPaymentContext,
+};
+use x402_runtime::matcher::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use x402_runtime::{
+    associated_token_address, created_associated_account, verify_sol_payment, Currency, ScanOptions,
+    VerifiedPayment, X402Error, X402Params,
+};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const MINT: Pubkey = Pubkey::new_from_array([12; 32]);
+const PRICE: u64 = 1_250_000;
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: RECIPIENT,
+        currency: Currency::Token(MINT),
+    }
+}
+
+fn context(recipient_ata_exists: bool) -> TokenPaymentContext {
+    TokenPaymentContext {
+        token_program: TOKEN_PROGRAM_ID,
+        decimals: 6,
+        recipient_ata_exists,
+    }
+}
+
+/// The on-chain check of a call by `payer` after `preceding`, run over the
+/// instructions sysvar the runtime builds for the transaction.
+fn verify(preceding: &[Instruction], payer: &Pubkey, params: &X402Params) -> Result<VerifiedPayment, X402Error> {
+    let mut all = preceding.to_vec();
+    all.push(Instruction::new_with_bytes(PROGRAM_ID, &[0; 8], vec![AccountMeta::new_readonly(*payer, true)]));
+    let sanitized = SanitizedMessage::try_from(Message::new(&all, Some(payer))).unwrap();
+    let mut data = instructions::construct_instructions_data(&sanitized.decompile_instructions());
+    instructions::store_current_index(&mut data, preceding.len() as u16);
+
+    let (key, owner, mut lamports) = (instructions::ID, sysvar::ID, 0);
+    let info = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+    verify_sol_payment(&[info], &PROGRAM_ID, params, &ScanOptions::DEFAULT)
+}
+
+#[test]
+fn deposit_address_is_the_recipient_ata() {
+    assert_eq!(
+        recipient_ata(&params()),
+        Some(associated_token_address(&RECIPIENT, &MINT, &TOKEN_PROGRAM_ID))
+    );
+    assert_eq!(
+        recipient_ata_with(&params(), &TOKEN_2022_PROGRAM_ID),
+        Some(associated_token_address(&RECIPIENT, &MINT, &TOKEN_2022_PROGRAM_ID))
+    );
+
+    let sol = X402Params {
+        currency: Currency::Sol,
+        ..params()
+    };
+    assert_eq!(recipient_ata(&sol), None);
+}
+
+#[test]
+fn missing_recipient_account_is_created_first() {
+    let payer = Pubkey::new_unique();
+    let instructions = token_payment_instructions_with(&payer, &params(), &context(false));
+    assert_eq!(instructions.len(), 2);
+    assert_eq!(instructions[0], create_ata_idempotent(&payer, &RECIPIENT, &MINT, &TOKEN_PROGRAM_ID));
+    assert_eq!(created_associated_account(&instructions[0]), Some((RECIPIENT, MINT)));
+
+    let payment = verify(&instructions, &payer, &params()).unwrap();
+    assert_eq!((payment.payer, payment.recipient), (payer, RECIPIENT));
+    assert_eq!((payment.amount, payment.currency, payment.ix_index), (PRICE, Currency::Token(MINT), 1));
+}
+
+#[test]
+fn existing_recipient_account_is_paid_directly() {
+    let payer = Pubkey::new_unique();
+    let instructions = token_payment_instructions_with(&payer, &params(), &context(true));
+    assert_eq!(instructions.len(), 1);
+    assert_eq!(verify(&instructions, &payer, &params()).unwrap().ix_index, 0);
+}
+
+#[test]
+fn created_account_is_the_one_paid_into() {
+    let payer = Pubkey::new_unique();
+    let instructions = token_payment_instructions_with(&payer, &params(), &context(false));
+    let created = instructions[0].accounts[1].pubkey;
+    let credited = instructions[1].accounts[2].pubkey;
+    assert_eq!(Some(created), recipient_ata(&params()));
+    assert_eq!(created, credited);
+}
+
+#[test]
+fn sol_prices_have_no_token_instructions() {
+    let sol = X402Params {
+        currency: Currency::Sol,
+        ..params()
+    };
+    assert!(token_payment_instructions_with(&Pubkey::new_unique(), &sol, &context(false)).is_empty());
+}
+
+#[test]
+fn token_price_is_not_paid_in_lamports() {
+    let payer = Pubkey::new_unique();
+    let lamports = solana_program::system_instruction::transfer(&payer, &RECIPIENT, PRICE);
+    assert!(matches!(verify(&[lamports], &payer, &params()), Err(X402Error::WrongPaymentToken)));
+}
+
+#[test]
+fn short_token_payment_is_insufficient() {
+    let payer = Pubkey::new_unique();
+    let short = X402Params {
+        price: PRICE - 1,
+        ..params()
+    };
+    let instructions = token_payment_instructions_with(&payer, &short, &context(true));
+    assert!(matches!(verify(&instructions, &payer, &params()), Err(X402Error::InsufficientPayment)));
+}
@@ -0,0 +1,81 @@
+//! Round-trips between base units and their decimal form, and rejection
+//! of amounts that cannot be represented exactly.
+
+use proptest::prelude::*;
+use x402_client::units::{Lamports, TokenAmount, UiSol, Unit, UnitsError, SOL_DECIMALS, USDC_DECIMALS};
+
+#[test]
+fn formats_and_parses_examples() {
+    assert_eq!(UiSol::from(Lamports(50_000_000)).to_string(), "0.05 SOL");
+    assert_eq!(UiSol::from(Lamports(1_000_000_000)).to_string(), "1 SOL");
+    assert_eq!("0.05 SOL".parse::<UiSol>().unwrap().lamports(), Lamports(50_000_000));
+    assert_eq!("0.05".parse::<UiSol>().unwrap().lamports(), Lamports(50_000_000));
+
+    let usdc: TokenAmount = "1.25 USDC".parse().unwrap();
+    assert_eq!(usdc, TokenAmount { base_units: 1_250_000, decimals: USDC_DECIMALS });
+    assert_eq!(usdc.to_string(), "1.25");
+}
+
+#[test]
+fn rejects_malformed_amounts() {
+    for s in ["", ".5", "5.", "-1", "1e9", "1_000", "0x10", "1.2.3"] {
+        assert!(UiSol::parse(s).is_err(), "{:?} parsed", s);
+    }
+    assert_eq!("1.25".parse::<TokenAmount>(), Err(UnitsError::MissingUnit));
+    assert_eq!("1.25 USDT".parse::<TokenAmount>(), Err(UnitsError::UnknownUnit("USDT".to_string())));
+    assert_eq!(
+        "1.25 USDC".parse::<UiSol>(),
+        Err(UnitsError::WrongUnit { expected: Unit::Sol, found: Unit::Usdc })
+    );
+}
+
+#[test]
+fn rejects_precision_overflow() {
+    assert_eq!(
+        TokenAmount::parse("0.0000001", USDC_DECIMALS),
+        Err(UnitsError::TooPrecise { decimals: USDC_DECIMALS })
+    );
+    // Trailing zeros are not extra precision.
+    assert_eq!(TokenAmount::parse("0.1000000000", USDC_DECIMALS).unwrap().base_units, 100_000);
+    assert_eq!(UiSol::parse("18446744074"), Err(UnitsError::Overflow));
+}
+
+proptest! {
+    #[test]
+    fn lamports_round_trip(lamports in any::<u64>()) {
+        let sol = UiSol::from(Lamports(lamports));
+        prop_assert_eq!(sol.to_string().parse::<UiSol>().unwrap(), sol);
+    }
+
+    #[test]
+    fn token_amounts_round_trip(base_units in any::<u64>(), decimals in 0u8..=19) {
+        let amount = TokenAmount { base_units, decimals };
+        prop_assert_eq!(TokenAmount::parse(&amount.to_string(), decimals).unwrap(), amount);
+    }
+
+    #[test]
+    fn rescale_up_and_back(base_units in 0u64..=u64::from(u32::MAX)) {
+        let usdc = TokenAmount { base_units, decimals: USDC_DECIMALS };
+        let rescaled = usdc.rescale(SOL_DECIMALS).unwrap();
+        prop_assert_eq!(rescaled.base_units, base_units * 1_000);
+        prop_assert_eq!(rescaled.rescale(USDC_DECIMALS).unwrap(), usdc);
+    }
+
+    #[test]
+    fn rejects_digits_beyond_decimals(whole in any::<u32>(), extra in 1u64..10, decimals in 0u8..=12) {
+        // One significant digit more than the unit carries.
+        let s = format!("{}.{}{}", whole, "0".repeat(usize::from(decimals)), extra);
+        prop_assert_eq!(TokenAmount::parse(&s, decimals), Err(UnitsError::TooPrecise { decimals }));
+    }
+
+    #[test]
+    fn rescale_down_rejects_dropped_digits(base_units in any::<u64>()) {
+        let sol = TokenAmount::from(Lamports(base_units));
+        let result = sol.rescale(USDC_DECIMALS);
+        if base_units % 1_000 == 0 {
+            prop_assert_eq!(result.unwrap().base_units, base_units / 1_000);
+        } else {
+            prop_assert_eq!(result, Err(UnitsError::TooPrecise { decimals: USDC_DECIMALS }));
+        }
+    }
+}
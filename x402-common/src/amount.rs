@@ -0,0 +1,68 @@
+//! Amounts as people read them, for the logs wallets show when simulating
+//! a call fails: `0.05 SOL` rather than `50000000`.
+//!
+//! [`fmt_amount`] writes into a buffer the caller provides and never
+//! allocates, so a program can format amounts for `msg!` without touching
+//! its heap.
+
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+
+use crate::payment::Currency;
+
+/// The USDC mint; amounts in its tokens are shown in USDC.
+pub const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+/// Longest text [`fmt_amount`] writes: `u64::MAX` base units of a mint
+/// other than USDC.
+pub const MAX_AMOUNT_LEN: usize = 31;
+
+const SOL_DECIMALS: usize = 9;
+const USDC_DECIMALS: usize = 6;
+/// Digits of `u64::MAX`.
+const MAX_DIGITS: usize = 20;
+
+/// Writes `amount`, in base units of `currency`, to `buf` as a decimal
+/// with its unit and returns the text: `"0.05 SOL"`, `"1.25 USDC"`.
+///
+/// The value is exact, never rounded: trailing zeros of the fraction are
+/// dropped and every other digit is kept, so one lamport is
+/// `"0.000000001 SOL"`. A mint other than USDC has no known decimals and
+/// is shown in base units, `"1250000 base units"`. A `buf` shorter than
+/// [`MAX_AMOUNT_LEN`] may cut the text short.
+pub fn fmt_amount(amount: u64, currency: Currency, buf: &mut [u8]) -> &str {
+    let (decimals, unit) = match currency {
+        Currency::Sol => (SOL_DECIMALS, " SOL"),
+        Currency::Token(mint) if mint == USDC_MINT => (USDC_DECIMALS, " USDC"),
+        Currency::Token(_) => (0, " base units"),
+    };
+
+    // Least significant first, zero-padded so there is a whole digit.
+    let mut digits = [b'0'; MAX_DIGITS];
+    let mut count = 0;
+    let mut rest = amount;
+    while rest > 0 {
+        digits[count] = b'0' + (rest % 10) as u8;
+        count += 1;
+        rest /= 10;
+    }
+    let count = count.max(decimals + 1);
+    let dropped = digits[..decimals].iter().take_while(|&&digit| digit == b'0').count();
+
+    let mut len = 0;
+    let mut push = |byte: u8| {
+        if let Some(slot) = buf.get_mut(len) {
+            *slot = byte;
+            len += 1;
+        }
+    };
+    digits[decimals..count].iter().rev().for_each(|&digit| push(digit));
+    if dropped < decimals {
+        push(b'.');
+        digits[dropped..decimals].iter().rev().for_each(|&digit| push(digit));
+    }
+    unit.bytes().for_each(push);
+
+    // Only ASCII was written, so any cut is a valid `str`.
+    core::str::from_utf8(&buf[..len]).unwrap_or_default()
+}
@@ -0,0 +1,109 @@
+//! Recipient authorizations: a key's signature allowing payments at a
+//! price to a recipient chosen per call, until an expiry.
+//!
+//! The signed message is the recipient, the little-endian price and the
+//! little-endian expiry in unix seconds, [`AUTHORIZATION_MESSAGE_LEN`]
+//! bytes. It travels in an Ed25519 program instruction of the paying
+//! transaction, whose signature the runtime verifies before any program
+//! runs; [`ed25519_instruction`] builds one holding the public key,
+//! signature and message itself, the only layout [`signed_messages`]
+//! reads back.
+
+use solana_program::ed25519_program;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+
+/// Length of a signed [`RecipientAuthorization`] message.
+pub const AUTHORIZATION_MESSAGE_LEN: usize = 32 + 8 + 8;
+
+const SIGNATURE_LEN: usize = 64;
+/// Signature count and padding, before the offsets.
+const HEADER_LEN: usize = 2;
+/// Seven `u16` offsets per signature.
+const OFFSETS_LEN: usize = 14;
+/// Instruction index meaning "this instruction".
+const THIS_INSTRUCTION: u16 = u16::MAX;
+
+/// Payments of `price` to `recipient` allowed until `expiry`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecipientAuthorization {
+    pub recipient: Pubkey,
+    pub price: u64,
+    /// Unix seconds after which the authorization no longer applies.
+    pub expiry: i64,
+}
+
+impl RecipientAuthorization {
+    /// The bytes the authorizing key signs.
+    pub fn message(&self) -> [u8; AUTHORIZATION_MESSAGE_LEN] {
+        let mut message = [0; AUTHORIZATION_MESSAGE_LEN];
+        message[..32].copy_from_slice(self.recipient.as_ref());
+        message[32..40].copy_from_slice(&self.price.to_le_bytes());
+        message[40..].copy_from_slice(&self.expiry.to_le_bytes());
+        message
+    }
+
+    /// The authorization `message` encodes, if it has the length of one.
+    pub fn from_message(message: &[u8]) -> Option<Self> {
+        let message: &[u8; AUTHORIZATION_MESSAGE_LEN] = message.try_into().ok()?;
+        Some(RecipientAuthorization {
+            recipient: Pubkey::new_from_array(message[..32].try_into().ok()?),
+            price: u64::from_le_bytes(message[32..40].try_into().ok()?),
+            expiry: i64::from_le_bytes(message[40..].try_into().ok()?),
+        })
+    }
+}
+
+/// An Ed25519 program instruction verifying `signature` by `signer` over
+/// `message`, all three held in its own data.
+pub fn ed25519_instruction(signer: &Pubkey, signature: &[u8; SIGNATURE_LEN], message: &[u8]) -> Instruction {
+    let public_key_offset = HEADER_LEN + OFFSETS_LEN;
+    let signature_offset = public_key_offset + 32;
+    let message_offset = signature_offset + SIGNATURE_LEN;
+
+    let mut data = Vec::with_capacity(message_offset + message.len());
+    data.extend_from_slice(&[1, 0]);
+    for value in [
+        signature_offset as u16,
+        THIS_INSTRUCTION,
+        public_key_offset as u16,
+        THIS_INSTRUCTION,
+        message_offset as u16,
+        message.len() as u16,
+        THIS_INSTRUCTION,
+    ] {
+        data.extend_from_slice(&value.to_le_bytes());
+    }
+    data.extend_from_slice(signer.as_ref());
+    data.extend_from_slice(signature);
+    data.extend_from_slice(message);
+
+    Instruction {
+        program_id: ed25519_program::ID,
+        accounts: Vec::new(),
+        data,
+    }
+}
+
+/// The signer and message of each signature in the data of an Ed25519
+/// program instruction whose key and message lie in that same data.
+/// Signatures pointing into other instructions are skipped: their bytes
+/// are not where this data says.
+pub fn signed_messages(data: &[u8]) -> Vec<(Pubkey, &[u8])> {
+    let count = data.first().copied().unwrap_or(0) as usize;
+    (0..count)
+        .filter_map(|index| {
+            let start = HEADER_LEN + index * OFFSETS_LEN;
+            let offsets = data.get(start..start + OFFSETS_LEN)?;
+            let field = |at: usize| u16::from_le_bytes([offsets[2 * at], offsets[2 * at + 1]]);
+            let (public_key_offset, public_key_index) = (field(2) as usize, field(3));
+            let (message_offset, message_len, message_index) = (field(4) as usize, field(5) as usize, field(6));
+            if public_key_index != THIS_INSTRUCTION || message_index != THIS_INSTRUCTION {
+                return None;
+            }
+            let signer = data.get(public_key_offset..public_key_offset + 32)?;
+            let message = data.get(message_offset..message_offset + message_len)?;
+            Some((Pubkey::new_from_array(signer.try_into().ok()?), message))
+        })
+        .collect()
+}
@@ -0,0 +1,50 @@
+//! The cluster a program's deployment belongs to.
+//!
+//! A program cannot read the genesis hash of the cluster it runs on, so
+//! the cluster is recorded instead: `initialize_x402` writes it into the
+//! program's `x402_config`, and a program built with
+//! `#[x402_program(network = "mainnet-beta")]` refuses every priced call
+//! whose config records another. A devnet build deployed to mainnet, or
+//! a mainnet build pointed at a devnet treasury, then fails with
+//! `WrongCluster` before any payment is verified.
+
+#[cfg(feature = "anchor")]
+use anchor_lang::prelude::borsh;
+
+/// A Solana cluster, by the name the CLI gives it.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, anchor_lang::InitSpace)
+)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    /// Not recorded yet, as in a config migrated from before the cluster
+    /// was kept; no build accepts it.
+    Unset,
+    MainnetBeta,
+    Devnet,
+    Testnet,
+    Localnet,
+}
+
+impl Cluster {
+    /// Every recorded cluster, in the order of their names.
+    pub const ALL: [Cluster; 4] = [Cluster::MainnetBeta, Cluster::Devnet, Cluster::Testnet, Cluster::Localnet];
+
+    /// `"mainnet-beta"`, `"devnet"`, `"testnet"`, `"localnet"`, or
+    /// `"unset"`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Cluster::Unset => "unset",
+            Cluster::MainnetBeta => "mainnet-beta",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Localnet => "localnet",
+        }
+    }
+
+    /// The cluster named `name`; `Unset` is never named.
+    pub fn from_name(name: &str) -> Option<Cluster> {
+        Cluster::ALL.into_iter().find(|cluster| cluster.name() == name)
+    }
+}
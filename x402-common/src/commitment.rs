@@ -0,0 +1,36 @@
+//! The payment commitment log: a rolling hash over every payment verified
+//! by `commitment_log = true` handlers, cheaper than a receipt account per
+//! payment and still tamper-evident.
+//!
+//! Each payment moves the commitment held in `X402Stats` to
+//! `sha256(previous || payer || amount || slot || instruction_tag)`, with
+//! the amount and slot little-endian and the tag's bytes last. The event
+//! for the payment carries both values, so an indexer can recompute the
+//! whole chain from events alone: a changed event no longer hashes to its
+//! commitment, and a missing one leaves the next event's previous value
+//! unmatched. The layout is fixed; changing it would break every chain.
+
+use solana_program::hash::hashv;
+use solana_program::pubkey::Pubkey;
+
+/// Commitment of a stats account before its first payment.
+pub const GENESIS_COMMITMENT: [u8; 32] = [0; 32];
+
+/// The commitment after `payer` pays `amount` in `slot` for the
+/// instruction tagged `instruction_tag`.
+pub fn next_commitment(
+    previous: &[u8; 32],
+    payer: &Pubkey,
+    amount: u64,
+    slot: u64,
+    instruction_tag: &str,
+) -> [u8; 32] {
+    hashv(&[
+        previous,
+        payer.as_ref(),
+        &amount.to_le_bytes(),
+        &slot.to_le_bytes(),
+        instruction_tag.as_bytes(),
+    ])
+    .to_bytes()
+}
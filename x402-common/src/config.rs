@@ -0,0 +1,24 @@
+//! Layout of the example program's `X402GlobalConfig` account, which holds
+//! the recipient of every `#[x402]` handler without an `address` and the
+//! cluster the program was deployed to.
+
+use anchor_lang::prelude::*;
+
+use crate::cluster::Cluster;
+
+/// Anchor discriminator of the `X402GlobalConfig` account,
+/// `sha256("account:X402GlobalConfig")[..8]`.
+pub const X402_CONFIG_DISCRIMINATOR: [u8; 8] = [9, 220, 34, 209, 150, 5, 197, 92];
+
+/// An `X402GlobalConfig` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GlobalConfigData {
+    pub version: u8,
+    /// May hand itself over, appoint `operator` and rotate `recipient`.
+    pub authority: Pubkey,
+    pub recipient: Pubkey,
+    /// May rotate `recipient`.
+    pub operator: Pubkey,
+    /// `Unset` until recorded, for configs migrated from version 2.
+    pub cluster: Cluster,
+}
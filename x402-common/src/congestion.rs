@@ -0,0 +1,22 @@
+//! Layout of the example program's `CongestionLevel` account, which a
+//! `congestion_surcharge` reads its level from.
+
+use anchor_lang::prelude::*;
+
+/// Anchor discriminator of the `CongestionLevel` account,
+/// `sha256("account:CongestionLevel")[..8]`.
+pub const CONGESTION_LEVEL_DISCRIMINATOR: [u8; 8] = [248, 5, 92, 94, 123, 116, 40, 23];
+
+/// A `CongestionLevel` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct CongestionLevelData {
+    pub version: u8,
+    /// May change `level`.
+    pub authority: Pubkey,
+    /// 0 (uncongested) to `x402_runtime::congestion::MAX_CONGESTION_LEVEL`.
+    pub level: u8,
+    /// `level` before its latest change.
+    pub previous_level: u8,
+    /// Unix timestamp of the latest change, 0 if never changed.
+    pub updated_at: i64,
+}
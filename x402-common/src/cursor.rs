@@ -0,0 +1,30 @@
+//! Layout of the example program's `IndexerCursor` account, the last
+//! payment sequence an indexer has stored, so a restarted indexer resumes
+//! after it instead of re-reading logs it already exported.
+
+use anchor_lang::prelude::*;
+
+pub use crate::seeds::INDEXER_CURSOR_SEED;
+
+/// Anchor discriminator of the `IndexerCursor` account,
+/// `sha256("account:IndexerCursor")[..8]`.
+pub const INDEXER_CURSOR_DISCRIMINATOR: [u8; 8] = [244, 229, 94, 147, 20, 234, 140, 44];
+
+/// Anchor discriminator of the `create_cursor` instruction,
+/// `sha256("global:create_cursor")[..8]`.
+pub const CREATE_CURSOR_DISCRIMINATOR: [u8; 8] = [10, 196, 78, 101, 250, 36, 240, 6];
+
+/// Anchor discriminator of the `advance_cursor` instruction,
+/// `sha256("global:advance_cursor")[..8]`.
+pub const ADVANCE_CURSOR_DISCRIMINATOR: [u8; 8] = [79, 137, 196, 54, 180, 155, 150, 248];
+
+/// An `IndexerCursor` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexerCursorData {
+    pub version: u8,
+    /// Created the cursor; the only signer that may advance it.
+    pub creator: Pubkey,
+    /// `X402Stats` sequence of the last payment the indexer stored, 0
+    /// before the first.
+    pub last_sequence: u64,
+}
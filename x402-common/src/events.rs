@@ -0,0 +1,313 @@
+//! Events emitted by x402-gated programs.
+//!
+//! An event's layout never changes once a release emits it: Anchor events
+//! carry no length or version, so an indexer built against one layout
+//! fails to decode, or misreads, any other. A new field goes into a new
+//! event type with the next version, next to the old one, and the runtime
+//! keeps a feature to emit the old one until indexers have moved over.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+
+/// Version of the payment event the runtime emits by default,
+/// [`X402PaymentVerifiedEventV2`].
+pub const PAYMENT_VERIFIED_VERSION: u8 = 2;
+
+/// Version 1 of the event reporting an accepted payment, emitted only by a
+/// runtime built with `legacy-events`. It has no version tag; its layout
+/// is that of [`X402PaymentVerifiedEventV2`] without the leading byte.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402PaymentVerifiedEvent {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    /// Amount actually transferred.
+    pub amount: u64,
+    /// Price the instruction required.
+    pub price: u64,
+    /// Position of this payment in the program's `X402Stats` sequence,
+    /// starting at 1, or 0 when the instruction does not track stats.
+    pub sequence: u64,
+    /// Arm of the handler's price experiment whose price was charged, or
+    /// `None` when it runs no experiment.
+    pub experiment_arm: Option<u8>,
+    /// Server nonce the call carried as its `x402_nonce` argument, or
+    /// `None` when the handler does not set `require_nonce`.
+    pub nonce: Option<[u8; 32]>,
+}
+
+/// Emitted by the injected check for every accepted payment.
+///
+/// The layout is frozen. After the discriminator, Borsh-encoded: `version`
+/// (`u8`, always [`PAYMENT_VERIFIED_VERSION`]), `payer` and `recipient`
+/// (32 bytes each), `amount`, `price` and `sequence` (`u64`,
+/// little-endian), then `experiment_arm` (`Option<u8>`) and `nonce`
+/// (`Option<[u8; 32]>`), each a presence byte followed by the value when
+/// present.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402PaymentVerifiedEventV2 {
+    /// Always [`PAYMENT_VERIFIED_VERSION`], so a decoder can tell the
+    /// layout from the data alone.
+    pub version: u8,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    /// Amount actually transferred.
+    pub amount: u64,
+    /// Price the instruction required.
+    pub price: u64,
+    /// As in [`X402PaymentVerifiedEvent`].
+    pub sequence: u64,
+    /// As in [`X402PaymentVerifiedEvent`].
+    pub experiment_arm: Option<u8>,
+    /// As in [`X402PaymentVerifiedEvent`].
+    pub nonce: Option<[u8; 32]>,
+}
+
+/// One payer's share of a split payment.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PaymentContribution {
+    pub payer: Pubkey,
+    pub amount: u64,
+}
+
+/// Emitted instead of `X402PaymentVerifiedEvent` when several payers
+/// split the price.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402SplitPaymentVerifiedEvent {
+    /// The handler's payer, who is always one of the contributors.
+    pub signer: Pubkey,
+    pub recipient: Pubkey,
+    /// Sum of every contribution.
+    pub total: u64,
+    /// Price the instruction required.
+    pub price: u64,
+    /// Nearest transfer first; at most `MAX_SPLIT_PAYERS` entries.
+    pub contributions: Vec<PaymentContribution>,
+    /// As in `X402PaymentVerifiedEvent`.
+    pub sequence: u64,
+}
+
+/// One recipient's leg of a revenue split.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct RevenueLeg {
+    pub recipient: Pubkey,
+    /// The recipient's share of the price, in basis points.
+    pub bps: u16,
+    /// Least the recipient had to be paid; see `pricing::revenue_shares`.
+    pub required: u64,
+    /// Amount of the transfer that paid the leg.
+    pub paid: u64,
+}
+
+/// Emitted instead of `X402PaymentVerifiedEvent` when the price is split
+/// between several recipients with `splits(...)`.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402RevenueSplitVerifiedEvent {
+    pub payer: Pubkey,
+    /// Sum of every leg.
+    pub total: u64,
+    /// Price the instruction required.
+    pub price: u64,
+    /// In the order the handler lists its recipients; at most
+    /// `MAX_REVENUE_SHARES` entries.
+    pub legs: Vec<RevenueLeg>,
+    /// As in `X402PaymentVerifiedEvent`.
+    pub sequence: u64,
+}
+
+/// Emitted with `emit_latency = true`, and when a paid result is consumed,
+/// for off-chain latency reporting.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402PaymentLatencyEvent {
+    pub payer: Pubkey,
+    /// As in `X402PaymentVerifiedEvent`; 0 when not known.
+    pub sequence: u64,
+    /// Slot the payment was recorded in. Equal to `consumed_slot` when the
+    /// payment and its use share a transaction.
+    pub payment_slot: u64,
+    /// Slot the payment was used in.
+    pub consumed_slot: u64,
+}
+
+impl X402PaymentLatencyEvent {
+    /// Slots between the payment and its use.
+    pub fn latency_slots(&self) -> u64 {
+        self.consumed_slot.saturating_sub(self.payment_slot)
+    }
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402PaymentEvent {
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub amount: u64,
+    pub timestamp: i64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentRecordedEvent {
+    pub payer: Pubkey,
+    pub amount: u64,
+    pub total_payments: u64,
+}
+
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ComputeEvent {
+    pub payer: Pubkey,
+    pub result: u64,
+    pub timestamp: i64,
+}
+
+/// Emitted by `report_failed_payment` for an attempt that an x402 check
+/// rejected in an earlier transaction.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentFailedEvent {
+    pub payer: Pubkey,
+    /// As in `FailureEntry::reason`.
+    pub reason: u8,
+    pub attempted_amount: u64,
+    /// Slot the report was made in, not that of the failed attempt.
+    pub slot: u64,
+}
+
+/// Emitted with `record_fingerprint = true`, after the payment is
+/// verified.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402RequestFingerprintEvent {
+    pub payer: Pubkey,
+    /// Price the instruction required.
+    pub price: u64,
+    /// As computed by `fingerprint::instruction_fingerprint`.
+    pub fingerprint: [u8; 32],
+}
+
+/// Emitted with `commitment_log = true`, after the payment is counted in
+/// `X402Stats`; see `commitment::next_commitment`.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct X402PaymentCommittedEvent {
+    pub payer: Pubkey,
+    /// Amount actually transferred.
+    pub amount: u64,
+    /// Slot the payment was verified in.
+    pub slot: u64,
+    /// Tag of the instruction paid for.
+    pub instruction_tag: String,
+    /// As in `X402PaymentVerifiedEvent`.
+    pub sequence: u64,
+    /// The commitment before this payment.
+    pub previous_commitment: [u8; 32],
+    /// The commitment after it, now held in `X402Stats`.
+    pub commitment: [u8; 32],
+}
+
+/// Emitted by `finalize_snapshot` with the root of the ledgers folded in
+/// by `snapshot_ledgers` since the last one; see `snapshot::snapshot_root`.
+#[event]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LedgerSnapshotEvent {
+    /// Label the authority gave the snapshot.
+    pub epoch: u64,
+    pub root: [u8; 32],
+    /// Ledgers in the snapshot, its leaves.
+    pub ledgers: u64,
+    /// Sum of their `total_amount`s.
+    pub total_amount: u64,
+    /// Slot the snapshot was finalized in.
+    pub slot: u64,
+}
+
+/// Any event an x402 indexer cares about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum X402Event {
+    PaymentVerified(X402PaymentVerifiedEvent),
+    PaymentVerifiedV2(X402PaymentVerifiedEventV2),
+    SplitPaymentVerified(X402SplitPaymentVerifiedEvent),
+    PaymentLatency(X402PaymentLatencyEvent),
+    Payment(X402PaymentEvent),
+    PaymentRecorded(PaymentRecordedEvent),
+    Compute(ComputeEvent),
+    PaymentFailed(PaymentFailedEvent),
+    RequestFingerprint(X402RequestFingerprintEvent),
+    PaymentCommitted(X402PaymentCommittedEvent),
+    RevenueSplitVerified(X402RevenueSplitVerifiedEvent),
+    LedgerSnapshot(LedgerSnapshotEvent),
+}
+
+impl X402Event {
+    /// The `X402Stats` sequence of the payment this event reports, or
+    /// `None` when it reports no payment of its own or the paid
+    /// instruction does not track stats (sequence 0). Latency events are
+    /// left out: they repeat their payment's sequence when the result is
+    /// consumed, in a later transaction.
+    pub fn sequence(&self) -> Option<u64> {
+        let sequence = match self {
+            X402Event::PaymentVerified(event) => event.sequence,
+            X402Event::PaymentVerifiedV2(event) => event.sequence,
+            X402Event::SplitPaymentVerified(event) => event.sequence,
+            X402Event::RevenueSplitVerified(event) => event.sequence,
+            X402Event::PaymentCommitted(event) => event.sequence,
+            _ => return None,
+        };
+        (sequence != 0).then_some(sequence)
+    }
+
+    /// Decodes the payload of a `Program data:` log line (discriminator
+    /// followed by the Borsh-encoded event). Returns `None` for events of
+    /// other types and for malformed payloads, including a
+    /// `X402PaymentVerifiedEventV2` with another version tag.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if data.len() < 8 {
+            return None;
+        }
+        let (discriminator, mut body) = data.split_at(8);
+
+        if discriminator == X402PaymentVerifiedEvent::DISCRIMINATOR {
+            X402PaymentVerifiedEvent::deserialize(&mut body).ok().map(X402Event::PaymentVerified)
+        } else if discriminator == X402PaymentVerifiedEventV2::DISCRIMINATOR {
+            X402PaymentVerifiedEventV2::deserialize(&mut body)
+                .ok()
+                .filter(|event| event.version == PAYMENT_VERIFIED_VERSION)
+                .map(X402Event::PaymentVerifiedV2)
+        } else if discriminator == X402SplitPaymentVerifiedEvent::DISCRIMINATOR {
+            X402SplitPaymentVerifiedEvent::deserialize(&mut body)
+                .ok()
+                .map(X402Event::SplitPaymentVerified)
+        } else if discriminator == X402PaymentLatencyEvent::DISCRIMINATOR {
+            X402PaymentLatencyEvent::deserialize(&mut body).ok().map(X402Event::PaymentLatency)
+        } else if discriminator == X402PaymentEvent::DISCRIMINATOR {
+            X402PaymentEvent::deserialize(&mut body).ok().map(X402Event::Payment)
+        } else if discriminator == PaymentRecordedEvent::DISCRIMINATOR {
+            PaymentRecordedEvent::deserialize(&mut body).ok().map(X402Event::PaymentRecorded)
+        } else if discriminator == ComputeEvent::DISCRIMINATOR {
+            ComputeEvent::deserialize(&mut body).ok().map(X402Event::Compute)
+        } else if discriminator == PaymentFailedEvent::DISCRIMINATOR {
+            PaymentFailedEvent::deserialize(&mut body).ok().map(X402Event::PaymentFailed)
+        } else if discriminator == X402RequestFingerprintEvent::DISCRIMINATOR {
+            X402RequestFingerprintEvent::deserialize(&mut body)
+                .ok()
+                .map(X402Event::RequestFingerprint)
+        } else if discriminator == X402PaymentCommittedEvent::DISCRIMINATOR {
+            X402PaymentCommittedEvent::deserialize(&mut body)
+                .ok()
+                .map(X402Event::PaymentCommitted)
+        } else if discriminator == X402RevenueSplitVerifiedEvent::DISCRIMINATOR {
+            X402RevenueSplitVerifiedEvent::deserialize(&mut body)
+                .ok()
+                .map(X402Event::RevenueSplitVerified)
+        } else if discriminator == LedgerSnapshotEvent::DISCRIMINATOR {
+            LedgerSnapshotEvent::deserialize(&mut body).ok().map(X402Event::LedgerSnapshot)
+        } else {
+            None
+        }
+    }
+}
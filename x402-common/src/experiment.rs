@@ -0,0 +1,19 @@
+//! Layout of the example program's `PriceExperiment` account, which an
+//! `experiment(...)` handler reads to learn whether its experiment ended.
+
+use anchor_lang::prelude::*;
+
+/// Anchor discriminator of the `PriceExperiment` account,
+/// `sha256("account:PriceExperiment")[..8]`.
+pub const PRICE_EXPERIMENT_DISCRIMINATOR: [u8; 8] = [82, 43, 191, 102, 95, 251, 79, 149];
+
+/// A `PriceExperiment` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PriceExperimentData {
+    pub version: u8,
+    /// May end the experiment.
+    pub authority: Pubkey,
+    /// Every payer is charged the `winner` arm's price once set.
+    pub ended: bool,
+    pub winner: u8,
+}
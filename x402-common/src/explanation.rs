@@ -0,0 +1,53 @@
+//! What a priced call pays, as the program itself reports it.
+//!
+//! A program's `explain_payment` instruction prices a call of one of its
+//! priced instructions, selected by tag, from the same accounts and
+//! through the same checks as the instruction does, and returns a
+//! [`PaymentExplanation`] as return data instead of running it. A wallet
+//! inserts it into a simulated transaction to show "this transaction pays
+//! X to Y" from the program's own answer (see `x402_client::explain`).
+
+#[cfg(feature = "anchor")]
+use anchor_lang::prelude::borsh;
+use solana_program::pubkey::Pubkey;
+
+use crate::payment::{Currency, Mode, X402Params};
+use crate::provenance::PriceProvenance;
+
+/// Anchor discriminator of the `explain_payment` instruction,
+/// `sha256("global:explain_payment")[..8]`.
+pub const EXPLAIN_PAYMENT_DISCRIMINATOR: [u8; 8] = [151, 116, 157, 226, 99, 127, 109, 77];
+
+/// The payment a priced instruction requires at the time of the call.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaymentExplanation {
+    /// The least the call accepts, in `currency`'s base units.
+    pub price: u64,
+    /// The wallet paid, resolved from the program's config when the
+    /// instruction declares no address.
+    pub recipient: Pubkey,
+    pub currency: Currency,
+    pub mode: Mode,
+    /// Where `price` came from.
+    pub provenance: PriceProvenance,
+    /// The amount of the preceding transfer that pays it, verified as the
+    /// instruction verifies it; `None` when the payment is not a transfer
+    /// the transaction carries.
+    pub paid: Option<u64>,
+}
+
+impl PaymentExplanation {
+    /// The requirements the instruction checks the payment against.
+    pub fn params(&self) -> X402Params {
+        X402Params {
+            price: self.price,
+            recipient: self.recipient,
+            currency: self.currency,
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Layout of the example program's `FailureLog` account, a ring buffer of
+//! the most recent rejected payment attempts reported by their payers.
+//!
+//! Rejected calls revert, so nothing they emit survives; clients report
+//! them afterwards in a separate `report_failed_payment` transaction.
+//!
+//! The log is a zero-copy account: these types are `#[repr(C)]` and
+//! `Pod`, with their padding spelled out, so the program writes one entry
+//! in place instead of (de)serializing the whole ring, and clients read
+//! the account's bytes as they are.
+
+use anchor_lang::prelude::*;
+use bytemuck::{Pod, Zeroable};
+
+pub use crate::seeds::FAILURE_LOG_SEED;
+
+/// Entries the log keeps before overwriting the oldest.
+pub const FAILURE_LOG_CAPACITY: usize = 32;
+
+/// Anchor discriminator of the `FailureLog` account,
+/// `sha256("account:FailureLog")[..8]`.
+pub const FAILURE_LOG_DISCRIMINATOR: [u8; 8] = [169, 213, 189, 172, 177, 99, 185, 252];
+
+/// Anchor discriminator of the `report_failed_payment` instruction,
+/// `sha256("global:report_failed_payment")[..8]`.
+pub const REPORT_FAILED_PAYMENT_DISCRIMINATOR: [u8; 8] = [46, 202, 171, 8, 93, 227, 15, 6];
+
+/// One reported failure. `reason` is the index of the `X402Error` in
+/// `x402_runtime::X402_ERRORS`.
+#[derive(AnchorSerialize, AnchorDeserialize, Pod, Zeroable, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct FailureEntry {
+    pub payer: Pubkey,
+    pub reason: u8,
+    _padding: [u8; 7],
+    pub attempted_amount: u64,
+    pub slot: u64,
+}
+
+impl FailureEntry {
+    pub fn new(payer: Pubkey, reason: u8, attempted_amount: u64, slot: u64) -> Self {
+        FailureEntry {
+            payer,
+            reason,
+            _padding: [0; 7],
+            attempted_amount,
+            slot,
+        }
+    }
+}
+
+/// The ring buffer itself: `entries[head]` is the next slot written, and
+/// the first `len` writes are the only ones that hold entries.
+#[derive(AnchorSerialize, AnchorDeserialize, Pod, Zeroable, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct FailureRing {
+    pub head: u8,
+    pub len: u8,
+    _padding: [u8; 6],
+    pub entries: [FailureEntry; FAILURE_LOG_CAPACITY],
+}
+
+impl FailureRing {
+    /// Writes `entry` over the oldest one once the ring is full.
+    pub fn push(&mut self, entry: FailureEntry) {
+        self.entries[self.head as usize % FAILURE_LOG_CAPACITY] = entry;
+        self.head = ((self.head as usize + 1) % FAILURE_LOG_CAPACITY) as u8;
+        self.len = (self.len as usize + 1).min(FAILURE_LOG_CAPACITY) as u8;
+    }
+
+    /// Entries held, newest first.
+    pub fn recent(&self) -> impl Iterator<Item = &FailureEntry> + '_ {
+        let len = (self.len as usize).min(FAILURE_LOG_CAPACITY);
+        let head = self.head as usize;
+        (1..=len).map(move |back| &self.entries[(head + FAILURE_LOG_CAPACITY - back) % FAILURE_LOG_CAPACITY])
+    }
+}
+
+/// A `FailureLog` after its discriminator.
+#[derive(Pod, Zeroable, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct FailureLogData {
+    pub version: u8,
+    _padding: [u8; 7],
+    pub ring: FailureRing,
+}
+
+impl FailureLogData {
+    pub fn new(version: u8, ring: FailureRing) -> Self {
+        FailureLogData {
+            version,
+            _padding: [0; 7],
+            ring,
+        }
+    }
+}
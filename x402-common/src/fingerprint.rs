@@ -0,0 +1,35 @@
+//! Request fingerprints: a stable hash of who paid what for which call,
+//! computed identically on-chain and by off-chain caches.
+//!
+//! The fingerprint is `sha256` over the length-prefixed concatenation of
+//! the payer, the 8-byte instruction tag, the little-endian price and the
+//! hash of the arguments, each field preceded by its length as a
+//! little-endian `u32`. The layout is fixed; changing it would orphan
+//! every cached entry.
+
+use solana_program::hash::{hash, hashv};
+use solana_program::pubkey::Pubkey;
+
+/// Fingerprint of a call by `payer` to the instruction tagged
+/// `instruction_tag` at `price`, with arguments hashing to `args_hash`.
+pub fn fingerprint(payer: &Pubkey, instruction_tag: &[u8; 8], price: u64, args_hash: &[u8; 32]) -> [u8; 32] {
+    let price = price.to_le_bytes();
+    let fields: [&[u8]; 4] = [payer.as_ref(), instruction_tag, &price, args_hash];
+
+    let lengths = fields.map(|field| (field.len() as u32).to_le_bytes());
+    let mut parts: Vec<&[u8]> = Vec::with_capacity(2 * fields.len());
+    for (length, field) in lengths.iter().zip(fields) {
+        parts.push(length);
+        parts.push(field);
+    }
+    hashv(&parts).to_bytes()
+}
+
+/// Fingerprint of an instruction's raw data, as `#[x402(record_fingerprint
+/// = true)]` computes it: the tag is the first 8 bytes (the Anchor
+/// discriminator) and `args_hash` is the `sha256` of the whole data.
+/// `None` for data shorter than a tag.
+pub fn instruction_fingerprint(payer: &Pubkey, data: &[u8], price: u64) -> Option<[u8; 32]> {
+    let tag: &[u8; 8] = data.get(..8)?.try_into().ok()?;
+    Some(fingerprint(payer, tag, price, &hash(data).to_bytes()))
+}
@@ -0,0 +1,25 @@
+//! Layout of the budget a priced instruction group keeps per payer, which
+//! `x402_runtime::group` charges the group's calls against.
+
+use anchor_lang::prelude::*;
+
+pub use crate::seeds::GROUP_BUDGET_SEED;
+
+/// What one payer has consumed and paid in a group's calls in the current
+/// transaction. Accounts embed it as a `budget` field; it describes the
+/// transaction that last wrote it until another transaction resets it.
+#[derive(AnchorSerialize, AnchorDeserialize, InitSpace, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct GroupBudget {
+    /// Slot of the transaction the budget describes.
+    pub last_slot: u64,
+    /// Hash of that transaction's instructions (see
+    /// `x402_runtime::group::transaction_id`).
+    pub transaction: [u8; 32],
+    /// Sum of the list prices of the group's calls in the transaction.
+    pub consumed_this_slot: u64,
+    /// Sum of the payments verified for those calls, overpayment included.
+    pub paid_this_slot: u64,
+    /// One past the index of the last payment counted in `paid_this_slot`,
+    /// so no payment is counted twice; 0 before any.
+    pub counted_through: u16,
+}
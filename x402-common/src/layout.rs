@@ -0,0 +1,125 @@
+//! What a paid call's transaction must look like.
+//!
+//! `#[x402]` emits `<handler>_x402_layout()` next to each handler,
+//! returning a [`TxLayout`]: the instructions and accounts its check reads,
+//! in transaction order. Clients build from it (see
+//! `x402_client::layout`) instead of knowing each mode, and integrators can
+//! print it as the handler's documentation. What may follow the priced
+//! instruction is the same for every handler, a [`Trailing`].
+//!
+//! The layout describes the default path only: calls through the
+//! `allow_trampoline` trampoline, and group calls a budget already covers,
+//! need less.
+
+use solana_program::pubkey::Pubkey;
+
+use crate::payment::{Currency, Mode};
+
+/// The requirements of one priced instruction's transaction, in order.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TxLayout {
+    pub mode: Mode,
+    /// Earliest first; the last is always [`Requirement::ThisInstruction`].
+    pub requirements: Vec<Requirement>,
+}
+
+/// One step of a [`TxLayout`].
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Requirement {
+    /// An Ed25519 instruction in which `authority` signed the recipient at
+    /// the price (see `x402_common::authorization`), anywhere before the
+    /// priced instruction.
+    RecipientAuthorization { authority: Pubkey },
+    /// A transfer of at least `min` to `to`. `None` is the recipient of the
+    /// quote: the program's configured one, or the handler's `recipient`
+    /// argument. With `max_payers` above 1, up to that many consecutive
+    /// transfers from different payers may share `min`.
+    Transfer {
+        to: Option<Pubkey>,
+        /// Share of the price, in basis points, 10 000 unless split.
+        bps: u16,
+        /// At the declared price; a surcharge, experiment arm or group
+        /// budget changes what is due, so quote before paying.
+        min: u64,
+        currency: Currency,
+        max_payers: u8,
+    },
+    /// Instructions of `x402_runtime::IGNORABLE_PROGRAMS` may sit here in
+    /// any number, with up to `others` instructions of any other kind.
+    IgnorableOk { others: u8 },
+    /// The priced instruction, with the accounts its check reads.
+    ThisInstruction { accounts: Vec<CallAccount> },
+}
+
+/// An account the check reads from the priced instruction.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CallAccount {
+    /// The instructions sysvar, as the accounts struct's `x402_instructions`
+    /// or as the last remaining account.
+    InstructionsSysvar,
+    /// The writable recipient, `x402_recipient`.
+    Recipient,
+    /// The payer's deposit, `x402_deposit`.
+    Deposit,
+    /// The program's `X402GlobalConfig`, `x402_config`, naming the
+    /// recipient of a handler without `address` and the cluster of a
+    /// program built for one.
+    Config,
+    /// The congestion account, `x402_congestion`.
+    Congestion { address: Pubkey },
+    /// The state of the price experiment with `bucket_seed`,
+    /// `x402_experiment`.
+    Experiment { bucket_seed: String },
+    /// The program's writable reentrancy guard, `x402_reentrancy_guard`.
+    ReentrancyGuard,
+    /// The payer's writable accumulator toward the instruction tagged
+    /// `tag`, `x402_accumulator`.
+    Accumulator { tag: String },
+}
+
+/// An instruction after the priced one, in any number and order.
+///
+/// Every check reads only the instructions before the priced one, so
+/// these can neither pay for the call nor fail it; they are the shapes the
+/// tests run after a call, and [`Trailing::ALL`] lists them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Trailing {
+    /// An instruction of `x402_runtime::IGNORABLE_PROGRAMS`, such as a
+    /// memo.
+    Ignorable,
+    /// A payment to any account, the recipient included, such as a tip.
+    /// It never counts toward the call before it.
+    Payment,
+    /// An instruction of another program.
+    OtherProgram,
+    /// A further instruction of the called program. A priced one needs a
+    /// payment of its own between the two: its scan stops at the earlier
+    /// call, so it never reaches that call's payment.
+    SameProgram,
+}
+
+impl Trailing {
+    pub const ALL: [Trailing; 4] = [
+        Trailing::Ignorable,
+        Trailing::Payment,
+        Trailing::OtherProgram,
+        Trailing::SameProgram,
+    ];
+}
+
+impl TxLayout {
+    /// The accounts the priced instruction must carry.
+    pub fn call_accounts(&self) -> &[CallAccount] {
+        self.requirements
+            .iter()
+            .find_map(|requirement| match requirement {
+                Requirement::ThisInstruction { accounts } => Some(&accounts[..]),
+                _ => None,
+            })
+            .unwrap_or(&[])
+    }
+}
@@ -0,0 +1,27 @@
+//! Layout of the example program's per-payer `PaymentLedger` account, for
+//! clients listing ledgers without linking the program crate.
+
+use anchor_lang::prelude::*;
+
+pub use crate::seeds::PAYMENT_LEDGER_SEED;
+
+/// Anchor discriminator of the `PaymentLedger` account,
+/// `sha256("account:PaymentLedger")[..8]`.
+pub const PAYMENT_LEDGER_DISCRIMINATOR: [u8; 8] = [66, 79, 9, 3, 77, 135, 16, 67];
+
+/// Offset of `payer` in the account data, after the discriminator and
+/// `version`.
+pub const PAYMENT_LEDGER_PAYER_OFFSET: usize = 8 + 1;
+
+/// A `PaymentLedger` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaymentLedgerData {
+    pub version: u8,
+    pub payer: Pubkey,
+    pub total_payments: u64,
+    /// Verified payments only.
+    pub total_amount: u64,
+    pub last_payment: i64,
+    /// Rent the payer paid to create accounts, kept out of `total_amount`.
+    pub total_rent_paid: u64,
+}
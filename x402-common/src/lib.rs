@@ -0,0 +1,62 @@
+//! Types shared by on-chain x402 code and off-chain clients.
+//!
+//! Anything both sides (de)serialize is defined once here so the program
+//! and its indexers cannot drift apart.
+//!
+//! The payment types, price provenance, payment explanations, recipient
+//! authorizations, tags, seeds, fingerprints, commitments, ledger snapshots, pricing,
+//! transaction layouts and amount formatting depend
+//! on `solana-program` alone, and the matcher's reference model on nothing.
+//! The `anchor` feature, on by default, adds the account layouts
+//! and events and derives Borsh
+//! (de)serialization; the `serde` feature derives `Serialize` and
+//! `Deserialize` for the payment types.
+
+pub mod amount;
+pub mod authorization;
+pub mod cluster;
+pub mod commitment;
+#[cfg(feature = "anchor")]
+pub mod config;
+#[cfg(feature = "anchor")]
+pub mod congestion;
+#[cfg(feature = "anchor")]
+pub mod cursor;
+#[cfg(feature = "anchor")]
+pub mod events;
+#[cfg(feature = "anchor")]
+pub mod experiment;
+pub mod explanation;
+#[cfg(feature = "anchor")]
+pub mod failures;
+pub mod fingerprint;
+#[cfg(feature = "anchor")]
+pub mod group;
+pub mod layout;
+#[cfg(feature = "anchor")]
+pub mod ledger;
+pub mod payment;
+pub mod pricing;
+pub mod provenance;
+pub mod reference;
+#[cfg(feature = "anchor")]
+pub mod result;
+pub mod seeds;
+pub mod snapshot;
+#[cfg(feature = "anchor")]
+pub mod stats;
+pub mod tag;
+
+pub use amount::{fmt_amount, MAX_AMOUNT_LEN};
+#[cfg(feature = "anchor")]
+pub use events::X402Event;
+pub use explanation::PaymentExplanation;
+pub use layout::TxLayout;
+pub use payment::{Currency, MismatchKind, Mode, VerifiedPayment, X402Params};
+pub use provenance::PriceProvenance;
+pub use tag::{InstructionTag, TagAliases, MAX_TAG_LEN};
+
+/// Code of the first `x402_runtime::X402Error`. Anchor numbers a program's
+/// own `#[error_code]` enum from 6000, so the x402 errors start at 7000,
+/// where they cannot be mistaken for the program's.
+pub const X402_ERROR_CODE_OFFSET: u32 = 7000;
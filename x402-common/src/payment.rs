@@ -0,0 +1,102 @@
+//! Payment requirements and verified payments.
+//!
+//! These are the values the `#[x402]` expansion builds and checks on-chain
+//! and that clients quote and pay against off-chain. With the `anchor`
+//! feature they (de)serialize with Borsh as Anchor accounts and events
+//! embed them; with `serde` they also round-trip through JSON and other
+//! serde formats.
+
+#[cfg(feature = "anchor")]
+use anchor_lang::prelude::borsh;
+use solana_program::pubkey::Pubkey;
+
+/// Payment requirements of one priced instruction.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct X402Params {
+    /// Minimum lamports the preceding transfer must carry.
+    pub price: u64,
+    /// Wallet the preceding transfer must credit.
+    pub recipient: Pubkey,
+    pub currency: Currency,
+}
+
+/// What a payment was made in.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Currency {
+    /// Lamports.
+    Sol,
+    /// Base units of the given mint.
+    Token(Pubkey),
+}
+
+/// How a priced instruction is paid, as selected by the `mode`, `deposit`
+/// and `accumulated` arguments of `#[x402]`. The expansion emits it next to each
+/// handler as `<HANDLER>_X402_MODE`.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// A transfer instruction before the priced one, read through the
+    /// instructions sysvar.
+    PrecedingInstruction,
+    /// A transfer made by the handler body, checked through the
+    /// recipient's balance once it returns.
+    BalanceDelta,
+    /// A debit of the payer's deposit account by the program itself.
+    Deposit,
+    /// A debit of the payments the payer accumulated toward the
+    /// instruction in earlier calls.
+    Accumulated,
+}
+
+/// A payment the runtime accepted.
+///
+/// The `#[x402]` expansion binds it as `x402_payment` in the handler body.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct VerifiedPayment {
+    /// Amount actually transferred, including any overpayment.
+    pub amount: u64,
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    pub currency: Currency,
+    /// Index of the payment instruction within the transaction, or
+    /// `x402_runtime::balance_delta::CURRENT_INSTRUCTION` when the priced
+    /// instruction paid for itself.
+    pub ix_index: u16,
+}
+
+/// Why a decoded payment did not satisfy the requirements, closest first.
+///
+/// The runtime reports the closest one it saw when nothing matched, as
+/// `x402_runtime::NearMiss`.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MismatchKind {
+    /// Right recipient and currency, but less than the price.
+    Insufficient { paid: u64, price: u64 },
+    /// Paid to the recipient, in another currency. `None` is SOL.
+    WrongToken {
+        expected: Option<Pubkey>,
+        found: Option<Pubkey>,
+    },
+    /// Right currency, paid to someone else.
+    WrongRecipient { found: Pubkey },
+}
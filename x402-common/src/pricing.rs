@@ -0,0 +1,82 @@
+//! Price arithmetic shared by the on-chain checks and client quotes, so a
+//! quoted price is computed by the very code that enforces it.
+
+use solana_program::hash::hashv;
+use solana_program::pubkey::Pubkey;
+
+/// Basis points in a whole.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Highest level a congestion account may hold.
+pub const MAX_CONGESTION_LEVEL: u8 = 5;
+
+/// Most basis points below the current price a `price_grace_bps` handler
+/// may forgive.
+pub const MAX_GRACE_BPS: u16 = 1_000;
+
+/// Most prices a price experiment may compare.
+pub const MAX_EXPERIMENT_ARMS: u8 = 8;
+
+/// Most recipients a revenue split may pay.
+pub const MAX_REVENUE_SHARES: usize = 5;
+
+/// Why a price could not be computed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PricingError {
+    /// The congestion level is above [`MAX_CONGESTION_LEVEL`].
+    CongestionLevelOutOfRange,
+    /// The price does not fit in a `u64`.
+    Overflow,
+}
+
+/// The amount a congestion surcharge adds to `price`: `level *
+/// bps_per_level` basis points of it, rounded down.
+pub fn congestion_surcharge(price: u64, level: u8, bps_per_level: u16) -> Result<u64, PricingError> {
+    if level > MAX_CONGESTION_LEVEL {
+        return Err(PricingError::CongestionLevelOutOfRange);
+    }
+    let bps = level as u128 * bps_per_level as u128;
+    u64::try_from(price as u128 * bps / BPS_DENOMINATOR as u128).map_err(|_| PricingError::Overflow)
+}
+
+/// `price` with its congestion surcharge added.
+pub fn surcharged_price(price: u64, level: u8, bps_per_level: u16) -> Result<u64, PricingError> {
+    price
+        .checked_add(congestion_surcharge(price, level, bps_per_level)?)
+        .ok_or(PricingError::Overflow)
+}
+
+/// The least payment accepted for `price` when up to `grace_bps` basis
+/// points below it are forgiven. The forgiven amount rounds down, so a
+/// grace never lowers the price by more than it says.
+pub fn graced_price(price: u64, grace_bps: u16) -> u64 {
+    let forgiven = price as u128 * grace_bps.min(MAX_GRACE_BPS) as u128 / BPS_DENOMINATOR as u128;
+    price - forgiven as u64
+}
+
+/// The arm of a price experiment with `arms` prices that `payer` is
+/// bucketed into: the first 8 bytes of `sha256(bucket_seed || payer)`,
+/// little-endian, modulo `arms`. Nothing but the payer's key and the
+/// handler's seed goes in, so a payer sees the same price on every call.
+pub fn experiment_bucket(payer: &Pubkey, bucket_seed: &[u8], arms: u8) -> u8 {
+    let hash = hashv(&[bucket_seed, payer.as_ref()]).to_bytes();
+    let mut head = [0; 8];
+    head.copy_from_slice(&hash[..8]);
+    (u64::from_le_bytes(head) % u64::from(arms.max(1))) as u8
+}
+
+/// The least each recipient of a revenue split must be paid out of
+/// `price`, one amount per entry of `shares_bps` (which sum to
+/// [`BPS_DENOMINATOR`]). Each share rounds down and whatever the rounding
+/// leaves is added to the first, so the amounts sum to `price` exactly.
+pub fn revenue_shares(price: u64, shares_bps: &[u16]) -> Vec<u64> {
+    let mut amounts: Vec<u64> = shares_bps
+        .iter()
+        .map(|&bps| (price as u128 * bps as u128 / BPS_DENOMINATOR as u128) as u64)
+        .collect();
+    let assigned = amounts.iter().fold(0u64, |total, amount| total.saturating_add(*amount));
+    if let Some(first) = amounts.first_mut() {
+        *first = first.saturating_add(price.saturating_sub(assigned));
+    }
+    amounts
+}
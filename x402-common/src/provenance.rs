@@ -0,0 +1,41 @@
+//! Where a charged price came from.
+//!
+//! With `record_provenance = true` the `#[x402]` expansion binds the
+//! inputs it priced the call from as `x402_provenance`, for the body to
+//! store next to its result: the declared price, the experiment arm, or
+//! the congestion account with the level enforced and its `updated_at:
+//! i64`, which the congestion account type must then have. An auditor can
+//! then re-derive the price from the handler's declared requirements and
+//! those inputs alone (see `x402_client::provenance`).
+
+#[cfg(feature = "anchor")]
+use anchor_lang::prelude::borsh;
+use solana_program::pubkey::Pubkey;
+
+/// The state a handler's price was computed from.
+#[cfg_attr(
+    feature = "anchor",
+    derive(anchor_lang::AnchorSerialize, anchor_lang::AnchorDeserialize, anchor_lang::InitSpace)
+)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PriceProvenance {
+    /// The price declared in `#[x402]`, read from no account.
+    Static,
+    /// The declared price raised by a congestion surcharge.
+    Congestion {
+        /// The congestion account read.
+        account: Pubkey,
+        /// The level whose price was enforced: the account's previous
+        /// level while a `price_grace_window_secs` window was open.
+        level: u8,
+        /// When the account's level last changed, as it held at the call.
+        updated_at: i64,
+    },
+    /// The price of one arm of a price experiment.
+    Experiment {
+        /// The experiment's state account.
+        account: Pubkey,
+        arm: u8,
+    },
+}
@@ -0,0 +1,125 @@
+//! Reference model of the payment matcher's acceptance rules.
+//!
+//! [`classify`] states which instructions `x402_runtime::match_payment`
+//! accepts as a payment and what it reads from them, in one function over
+//! plain bytes, written to be read rather than run: keys are `[u8; 32]`
+//! and nothing here uses `solana-program`. The runtime's
+//! `tests/reference_model.rs` runs both over the golden vectors, the fuzz
+//! corpus and generated instructions and requires identical verdicts, so
+//! an acceptance rule changes in both places or the tests fail.
+//!
+//! The deprecated legacy matcher is not modeled.
+
+/// The System program.
+pub const SYSTEM_PROGRAM: [u8; 32] = [0; 32];
+/// The SPL Token program, `TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA`.
+pub const TOKEN_PROGRAM: [u8; 32] = [
+    6, 221, 246, 225, 215, 101, 161, 147, 217, 203, 225, 70, 206, 235, 121, 172, 28, 180, 133, 237, 95, 91, 55, 145,
+    58, 140, 245, 133, 126, 255, 0, 169,
+];
+/// The Token-2022 program, `TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb`.
+pub const TOKEN_2022_PROGRAM: [u8; 32] = [
+    6, 221, 246, 225, 238, 117, 143, 222, 24, 66, 93, 188, 228, 108, 205, 218, 182, 26, 252, 77, 131, 185, 13, 39,
+    254, 189, 249, 40, 216, 161, 139, 252,
+];
+/// `sha256("global:x402_pay")[..8]`.
+pub const X402_PAY: [u8; 8] = [7, 105, 114, 162, 141, 186, 58, 215];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Kind {
+    SystemTransfer,
+    SystemTransferWithSeed,
+    ProgramPay,
+    TokenTransfer,
+    TokenTransferChecked,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Payment {
+    pub kind: Kind,
+    pub payer: [u8; 32],
+    pub recipient: [u8; 32],
+    pub amount: u64,
+    pub mint: Option<[u8; 32]>,
+}
+
+/// Why an instruction is not a payment, checked in this order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rejection {
+    /// Not the System or a token program, nor the invoking program.
+    UnsupportedProgram,
+    /// The data does not start with one of the program's payment tags.
+    UnrecognizedPaymentEncoding,
+    /// The data is not exactly as long as its tag says.
+    MalformedData,
+    /// An account the payment is read from is missing.
+    MissingAccounts,
+}
+
+/// Classifies an instruction of `program` with `accounts` and `data`, where
+/// `invoking` is the program running the check.
+pub fn classify(
+    program: &[u8; 32],
+    invoking: &[u8; 32],
+    accounts: &[[u8; 32]],
+    data: &[u8],
+) -> Result<Payment, Rejection> {
+    // The payment tag, and how many bytes it takes.
+    let (kind, tag_len) = if *program == SYSTEM_PROGRAM {
+        match data.get(..4) {
+            Some([2, 0, 0, 0]) => (Kind::SystemTransfer, 4),
+            Some([11, 0, 0, 0]) => (Kind::SystemTransferWithSeed, 4),
+            _ => return Err(Rejection::UnrecognizedPaymentEncoding),
+        }
+    } else if *program == TOKEN_PROGRAM || *program == TOKEN_2022_PROGRAM {
+        match data.first() {
+            Some(3) => (Kind::TokenTransfer, 1),
+            Some(12) => (Kind::TokenTransferChecked, 1),
+            _ => return Err(Rejection::UnrecognizedPaymentEncoding),
+        }
+    } else if program == invoking {
+        if !data.starts_with(&X402_PAY) {
+            return Err(Rejection::UnrecognizedPaymentEncoding);
+        }
+        (Kind::ProgramPay, 8)
+    } else {
+        return Err(Rejection::UnsupportedProgram);
+    };
+
+    // The tag, the `u64` amount, then:
+    // - TransferWithSeed: the seed as a `u64` length and its bytes, and the
+    //   seed's owner (32 bytes);
+    // - TransferChecked: the mint's decimals (1 byte);
+    // - nothing else for the others.
+    let len = match kind {
+        Kind::SystemTransferWithSeed => {
+            let seed_len = data.get(12..20).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+            let seed_len = seed_len.and_then(|seed_len| usize::try_from(seed_len).ok());
+            seed_len.and_then(|seed_len| seed_len.checked_add(4 + 8 + 8 + 32))
+        }
+        Kind::TokenTransferChecked => Some(tag_len + 8 + 1),
+        _ => Some(tag_len + 8),
+    };
+    if len != Some(data.len()) {
+        return Err(Rejection::MalformedData);
+    }
+    // Every length above leaves the amount in the data.
+    let amount = u64::from_le_bytes(data[tag_len..tag_len + 8].try_into().unwrap());
+
+    // Positions of the paying authority, the credited account and the mint.
+    let (payer, recipient, mint) = match kind {
+        Kind::SystemTransfer | Kind::ProgramPay => (0, 1, None),
+        // The base account signs for the source derived from it.
+        Kind::SystemTransferWithSeed => (1, 2, None),
+        Kind::TokenTransfer => (2, 1, None),
+        Kind::TokenTransferChecked => (3, 2, Some(1)),
+    };
+    let account = |index: usize| accounts.get(index).copied().ok_or(Rejection::MissingAccounts);
+    Ok(Payment {
+        kind,
+        payer: account(payer)?,
+        recipient: account(recipient)?,
+        amount,
+        mint: mint.map(account).transpose()?,
+    })
+}
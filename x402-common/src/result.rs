@@ -0,0 +1,30 @@
+//! Layout of the example program's `ComputeResult` account, for clients
+//! checking whether a call they are about to retry already succeeded and
+//! auditing the price it was charged.
+
+use anchor_lang::prelude::*;
+
+use crate::provenance::PriceProvenance;
+
+pub use crate::seeds::COMPUTE_RESULT_SEED;
+
+/// Anchor discriminator of the `ComputeResult` account,
+/// `sha256("account:ComputeResult")[..8]`.
+pub const COMPUTE_RESULT_DISCRIMINATOR: [u8; 8] = [77, 235, 119, 140, 29, 102, 252, 49];
+
+/// A `ComputeResult` after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ComputeResultData {
+    pub version: u8,
+    pub owner: Pubkey,
+    pub value: u64,
+    pub paid: bool,
+    pub amount_paid: u64,
+    pub tier: u8,
+    pub price_paid: u64,
+    pub created_slot: u64,
+    pub bind_to: Option<Pubkey>,
+    pub fingerprint: [u8; 32],
+    pub ref_count: u16,
+    pub provenance: PriceProvenance,
+}
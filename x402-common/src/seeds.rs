@@ -0,0 +1,182 @@
+//! PDA seeds of the accounts x402 programs keep, so clients derive the
+//! same addresses without linking the program crate.
+//!
+//! Programs name the seeds in `#[account(seeds = ...)]`; anything deriving
+//! an address off-chain, or checking one on-chain outside an accounts
+//! struct, calls the `*_address` functions below, so a seed is only ever
+//! spelled once.
+
+use solana_program::pubkey::{Pubkey, MAX_SEED_LEN};
+
+/// The program's `X402GlobalConfig`, holding the default recipient.
+pub const CONFIG_SEED: &[u8] = b"x402_config";
+
+/// The program's congestion level, read by `congestion_surcharge`.
+pub const CONGESTION_SEED: &[u8] = b"x402_congestion";
+
+/// The program's payment statistics, written by `track_stats`.
+pub const STATS_SEED: &[u8] = b"x402_stats";
+
+/// A payer's spending cap, followed by the payer's key.
+pub const SPENDING_CAP_SEED: &[u8] = b"spending_cap";
+
+/// The program's price manifest.
+pub const MANIFEST_SEED: &[u8] = b"x402_manifest";
+
+/// The example program's per-payer `PaymentLedger`.
+pub const PAYMENT_LEDGER_SEED: &[u8] = b"payment_ledger";
+
+/// A `ComputeResult` of the example program's compute tiers, followed by
+/// the payer's key and the call's little-endian `u64` nonce.
+pub const COMPUTE_RESULT_SEED: &[u8] = b"compute_result";
+
+/// The example program's `FailureLog`.
+pub const FAILURE_LOG_SEED: &[u8] = b"failure_log";
+
+/// A payer's lamport deposit, drawn down by `deposit = true` handlers,
+/// followed by the payer's key.
+pub const DEPOSIT_SEED: &[u8] = b"x402_deposit";
+
+/// A price experiment's state, followed by its `bucket_seed`.
+pub const EXPERIMENT_SEED: &[u8] = b"x402_experiment";
+
+/// The example program's `FreeTierConfig`.
+pub const FREE_TIER_CONFIG_SEED: &[u8] = b"free_tier_config";
+
+/// A payer's `UsageCounter` of the example program's free tier, followed
+/// by the payer's key.
+pub const USAGE_COUNTER_SEED: &[u8] = b"usage_counter";
+
+/// An indexer's `IndexerCursor` in the example program, followed by the
+/// indexer's id.
+pub const INDEXER_CURSOR_SEED: &[u8] = b"x402_cursor";
+
+/// A payer's budget in a priced instruction group, followed by the
+/// group's name and the payer's key.
+pub const GROUP_BUDGET_SEED: &[u8] = b"x402_group_budget";
+
+/// The program's reentrancy guard, set by `reentrancy_guard` handlers
+/// while they run.
+pub const REENTRANCY_GUARD_SEED: &[u8] = b"x402_reentrancy_guard";
+
+/// A payer's payments accumulated toward an `accumulated = true`
+/// instruction, followed by the instruction's tag and the payer's key.
+pub const ACCUMULATOR_SEED: &[u8] = b"x402_accumulator";
+
+/// The ledger snapshot being built by `snapshot_ledgers`.
+pub const SNAPSHOT_STATE_SEED: &[u8] = b"x402_snapshot";
+
+/// Every seed above, for checks that must cover them all.
+pub const ALL_SEEDS: &[&[u8]] = &[
+    CONFIG_SEED,
+    CONGESTION_SEED,
+    STATS_SEED,
+    SPENDING_CAP_SEED,
+    MANIFEST_SEED,
+    PAYMENT_LEDGER_SEED,
+    COMPUTE_RESULT_SEED,
+    FAILURE_LOG_SEED,
+    DEPOSIT_SEED,
+    EXPERIMENT_SEED,
+    FREE_TIER_CONFIG_SEED,
+    USAGE_COUNTER_SEED,
+    INDEXER_CURSOR_SEED,
+    GROUP_BUDGET_SEED,
+    REENTRANCY_GUARD_SEED,
+    ACCUMULATOR_SEED,
+    SNAPSHOT_STATE_SEED,
+];
+
+// A seed longer than `MAX_SEED_LEN` makes every derivation with it fail.
+const _: () = {
+    let mut i = 0;
+    while i < ALL_SEEDS.len() {
+        assert!(ALL_SEEDS[i].len() <= MAX_SEED_LEN, "PDA seed longer than MAX_SEED_LEN");
+        i += 1;
+    }
+};
+
+/// Address of the `X402GlobalConfig` PDA of `program_id`.
+pub fn config_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CONFIG_SEED], program_id).0
+}
+
+/// Address of the congestion level PDA of `program_id`.
+pub fn congestion_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[CONGESTION_SEED], program_id).0
+}
+
+/// Address of the `X402Stats` PDA of `program_id`.
+pub fn stats_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[STATS_SEED], program_id).0
+}
+
+/// Address of `payer`'s spending cap.
+pub fn spending_cap_address(program_id: &Pubkey, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SPENDING_CAP_SEED, payer.as_ref()], program_id).0
+}
+
+/// Address of the `X402Manifest` PDA of `program_id`.
+pub fn manifest_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[MANIFEST_SEED], program_id).0
+}
+
+/// Address of `payer`'s `PaymentLedger`.
+pub fn payment_ledger_address(program_id: &Pubkey, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PAYMENT_LEDGER_SEED, payer.as_ref()], program_id).0
+}
+
+/// Address of the result of `payer`'s compute call with `nonce`.
+pub fn compute_result_address(program_id: &Pubkey, payer: &Pubkey, nonce: u64) -> Pubkey {
+    Pubkey::find_program_address(&[COMPUTE_RESULT_SEED, payer.as_ref(), &nonce.to_le_bytes()], program_id).0
+}
+
+/// Address of the `FailureLog` PDA of `program_id`.
+pub fn failure_log_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[FAILURE_LOG_SEED], program_id).0
+}
+
+/// Address of `payer`'s deposit.
+pub fn deposit_address(program_id: &Pubkey, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[DEPOSIT_SEED, payer.as_ref()], program_id).0
+}
+
+/// Address of the state of the price experiment with `bucket_seed`.
+pub fn experiment_address(program_id: &Pubkey, bucket_seed: &[u8]) -> Pubkey {
+    Pubkey::find_program_address(&[EXPERIMENT_SEED, bucket_seed], program_id).0
+}
+
+/// Address of the `FreeTierConfig` PDA of `program_id`.
+pub fn free_tier_config_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[FREE_TIER_CONFIG_SEED], program_id).0
+}
+
+/// Address of `payer`'s free tier `UsageCounter`.
+pub fn usage_counter_address(program_id: &Pubkey, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[USAGE_COUNTER_SEED, payer.as_ref()], program_id).0
+}
+
+/// Address of the `IndexerCursor` of the indexer named `indexer_id`.
+pub fn indexer_cursor_address(program_id: &Pubkey, indexer_id: &str) -> Pubkey {
+    Pubkey::find_program_address(&[INDEXER_CURSOR_SEED, indexer_id.as_bytes()], program_id).0
+}
+
+/// Address of `payer`'s budget in the instruction group named `group`.
+pub fn group_budget_address(program_id: &Pubkey, group: &str, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[GROUP_BUDGET_SEED, group.as_bytes(), payer.as_ref()], program_id).0
+}
+
+/// Address of the reentrancy guard of `program_id`.
+pub fn reentrancy_guard_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[REENTRANCY_GUARD_SEED], program_id).0
+}
+
+/// Address of `payer`'s accumulator toward the instruction tagged `tag`.
+pub fn accumulator_address(program_id: &Pubkey, tag: &str, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[ACCUMULATOR_SEED, tag.as_bytes(), payer.as_ref()], program_id).0
+}
+
+/// Address of the `SnapshotState` of `program_id`.
+pub fn snapshot_state_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[SNAPSHOT_STATE_SEED], program_id).0
+}
@@ -0,0 +1,140 @@
+//! Ledger snapshots: a merkle root over every payer's `PaymentLedger`, so
+//! finance can commit the totals at one point in time and later prove any
+//! payer's line of it.
+//!
+//! Each ledger is a leaf `sha256(0x00 || payer || total_payments ||
+//! total_amount)`, with the counts little-endian, and each inner node
+//! `sha256(0x01 || left || right)`; the prefixes keep a leaf from passing
+//! as a node. Leaves are in ascending payer order, in a tree of
+//! [`SNAPSHOT_DEPTH`] levels whose unused leaves are empty subtrees, as in
+//! an incremental merkle tree. The program folds ledgers in a batch at a
+//! time, keeping only the [`Frontier`] of left nodes still waiting for
+//! their right sibling, and the root it emits is [`snapshot_root`] of the
+//! same leaves. The layout is fixed; changing it would break every proof.
+
+use solana_program::hash::hashv;
+use solana_program::pubkey::Pubkey;
+
+/// Levels of the snapshot tree; a snapshot holds up to
+/// [`MAX_SNAPSHOT_LEAVES`] ledgers and a proof has this many siblings.
+pub const SNAPSHOT_DEPTH: usize = 20;
+
+/// Most ledgers one snapshot holds.
+pub const MAX_SNAPSHOT_LEAVES: u64 = 1 << SNAPSHOT_DEPTH;
+
+const LEAF_PREFIX: &[u8] = &[0];
+const NODE_PREFIX: &[u8] = &[1];
+
+/// The leaf of `payer`'s ledger with these totals.
+pub fn leaf_hash(payer: &Pubkey, total_payments: u64, total_amount: u64) -> [u8; 32] {
+    hashv(&[
+        LEAF_PREFIX,
+        payer.as_ref(),
+        &total_payments.to_le_bytes(),
+        &total_amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// The node over `left` and `right`.
+pub fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    hashv(&[NODE_PREFIX, left, right]).to_bytes()
+}
+
+/// The root of an empty subtree of each height, from a lone empty leaf
+/// (all zero) up.
+pub fn empty_subtrees() -> [[u8; 32]; SNAPSHOT_DEPTH] {
+    let mut empty = [[0; 32]; SNAPSHOT_DEPTH];
+    for height in 1..SNAPSHOT_DEPTH {
+        empty[height] = node_hash(&empty[height - 1], &empty[height - 1]);
+    }
+    empty
+}
+
+/// The snapshot tree as far as it is built: at each height, the left
+/// node whose right sibling is not yet known.
+pub type Frontier = [[u8; 32]; SNAPSHOT_DEPTH];
+
+/// Why a leaf could not be added.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnapshotFull;
+
+/// Adds `leaf` after the `count` leaves `frontier` holds.
+pub fn append(frontier: &mut Frontier, count: u64, leaf: [u8; 32]) -> Result<(), SnapshotFull> {
+    if count >= MAX_SNAPSHOT_LEAVES {
+        return Err(SnapshotFull);
+    }
+    let (mut index, mut node) = (count, leaf);
+    for sibling in frontier.iter_mut() {
+        if index & 1 == 0 {
+            *sibling = node;
+            return Ok(());
+        }
+        node = node_hash(sibling, &node);
+        index >>= 1;
+    }
+    unreachable!("a tree below MAX_SNAPSHOT_LEAVES has room at some height")
+}
+
+/// The root of the `count` leaves `frontier` holds.
+pub fn frontier_root(frontier: &Frontier, count: u64) -> [u8; 32] {
+    let empty = empty_subtrees();
+    let (mut index, mut node) = (count, [0; 32]);
+    for height in 0..SNAPSHOT_DEPTH {
+        node = if index & 1 == 1 {
+            node_hash(&frontier[height], &node)
+        } else {
+            node_hash(&node, &empty[height])
+        };
+        index >>= 1;
+    }
+    node
+}
+
+/// The root of a snapshot of `leaves`, in order, as the program computes
+/// it, or `None` for more than [`MAX_SNAPSHOT_LEAVES`].
+pub fn snapshot_root(leaves: &[[u8; 32]]) -> Option<[u8; 32]> {
+    let mut frontier = [[0; 32]; SNAPSHOT_DEPTH];
+    for (count, leaf) in leaves.iter().enumerate() {
+        append(&mut frontier, count as u64, *leaf).ok()?;
+    }
+    Some(frontier_root(&frontier, leaves.len() as u64))
+}
+
+/// The siblings proving the leaf at `index` of `leaves`, lowest first, or
+/// `None` when there is no such leaf.
+pub fn inclusion_proof(leaves: &[[u8; 32]], index: usize) -> Option<Vec<[u8; 32]>> {
+    if index >= leaves.len() || leaves.len() as u64 > MAX_SNAPSHOT_LEAVES {
+        return None;
+    }
+    let empty = empty_subtrees();
+    let mut level = leaves.to_vec();
+    let mut position = index;
+    let mut proof = Vec::with_capacity(SNAPSHOT_DEPTH);
+    for empty in empty {
+        proof.push(level.get(position ^ 1).copied().unwrap_or(empty));
+        level = level
+            .chunks(2)
+            .map(|pair| node_hash(&pair[0], pair.get(1).unwrap_or(&empty)))
+            .collect();
+        position >>= 1;
+    }
+    Some(proof)
+}
+
+/// Whether `proof` places `leaf` at `index` of the snapshot with `root`.
+pub fn verify_inclusion(root: &[u8; 32], leaf: &[u8; 32], index: u64, proof: &[[u8; 32]]) -> bool {
+    if proof.len() != SNAPSHOT_DEPTH || index >= MAX_SNAPSHOT_LEAVES {
+        return false;
+    }
+    let (mut position, mut node) = (index, *leaf);
+    for sibling in proof {
+        node = if position & 1 == 1 {
+            node_hash(sibling, &node)
+        } else {
+            node_hash(&node, sibling)
+        };
+        position >>= 1;
+    }
+    node == *root
+}
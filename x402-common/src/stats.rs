@@ -0,0 +1,30 @@
+//! Layout of the example program's `X402Stats` account, the program-wide
+//! payment count that `track_stats = true` handlers update.
+//!
+//! Every tracked payment writes it, so it is a zero-copy account: this
+//! type is `#[repr(C)]` and `Pod`, with its padding spelled out, and
+//! clients read the account's bytes as they are.
+
+use bytemuck::{Pod, Zeroable};
+
+pub use crate::seeds::STATS_SEED;
+
+/// Anchor discriminator of the `X402Stats` account,
+/// `sha256("account:X402Stats")[..8]`.
+pub const X402_STATS_DISCRIMINATOR: [u8; 8] = [105, 193, 33, 138, 108, 122, 154, 136];
+
+/// An `X402Stats` after its discriminator.
+#[derive(Pod, Zeroable, Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(C)]
+pub struct StatsData {
+    pub version: u8,
+    _padding: [u8; 7],
+    pub sequence: u64,
+    pub total_payments: u64,
+    pub total_amount: u64,
+    /// The commitment after the most recent committed payment.
+    pub commitment: [u8; 32],
+    /// Rent paid to create accounts alongside tracked payments, kept out
+    /// of `total_amount`.
+    pub total_rent_paid: u64,
+}
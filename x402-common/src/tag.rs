@@ -0,0 +1,61 @@
+//! Instruction tags.
+//!
+//! Every `#[x402]` handler has a tag, pinned with `tag = "..."` or else its
+//! name, emitted as `<HANDLER>_X402_TAG`. Accounts seeded per instruction
+//! and events keyed per instruction use the tag, so renaming a handler
+//! whose tag is pinned keeps them. A handler without a pinned tag
+//! compiles with a warning asking for one. Tags are at most
+//! [`MAX_TAG_LEN`] bytes. `#[x402_program]` lists every tag with its
+//! handler in the module's `X402_TAGS`.
+//!
+//! Changing a pinned tag leaves accounts created under the old one behind.
+//! `accepts_tags("old_v1", until = <unix time>)` lets the instruction
+//! accept them under its new tag until the migration window closes,
+//! emitted as `<HANDLER>_X402_TAG_ALIASES`. An `accumulated` handler then
+//! also debits an accumulator toward one of them while the window is open,
+//! reading the clock to tell.
+
+/// Longest tag, in bytes; short enough to share a PDA seed list with a
+/// pubkey.
+pub const MAX_TAG_LEN: usize = 16;
+
+/// A handler's tag and the name it currently has.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InstructionTag {
+    pub tag: &'static str,
+    pub instruction: &'static str,
+    /// The earlier tags it still accepts, if any.
+    pub aliases: Option<TagAliases>,
+}
+
+/// Earlier tags whose accounts an instruction accepts as its own until
+/// `until`, a unix time; after it, only the instruction's tag is accepted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TagAliases {
+    pub tags: &'static [&'static str],
+    pub until: i64,
+}
+
+impl TagAliases {
+    /// Whether the aliases are still accepted at `now`.
+    pub fn open(&self, now: i64) -> bool {
+        now <= self.until
+    }
+
+    /// `own`, then the aliases while they are accepted at `now`.
+    pub fn accepted(&self, own: &'static str, now: i64) -> Vec<&'static str> {
+        let aliases = if self.open(now) { self.tags } else { &[] };
+        std::iter::once(own).chain(aliases.iter().copied()).collect()
+    }
+}
+
+/// The instruction `tag` belongs to in `tags`, such as a program's
+/// `X402_TAGS`.
+pub fn instruction_for(tags: &[InstructionTag], tag: &str) -> Option<&'static str> {
+    tags.iter().find(|entry| entry.tag == tag).map(|entry| entry.instruction)
+}
+
+/// The tag of `instruction` in `tags`.
+pub fn tag_for(tags: &[InstructionTag], instruction: &str) -> Option<&'static str> {
+    tags.iter().find(|entry| entry.instruction == instruction).map(|entry| entry.tag)
+}
@@ -0,0 +1,64 @@
+//! `fmt_amount` shows exact amounts in the units people pay in, and reads
+//! back to the same base units through `x402_units`.
+
+use solana_program::pubkey::Pubkey;
+use x402_common::amount::USDC_MINT;
+use x402_common::{fmt_amount, Currency, MAX_AMOUNT_LEN};
+
+const USDC: Currency = Currency::Token(USDC_MINT);
+
+fn text(amount: u64, currency: Currency) -> String {
+    fmt_amount(amount, currency, &mut [0; MAX_AMOUNT_LEN]).to_string()
+}
+
+#[test]
+fn sol_and_usdc() {
+    assert_eq!(text(50_000_000, Currency::Sol), "0.05 SOL");
+    assert_eq!(text(2_000_000_000, Currency::Sol), "2 SOL");
+    assert_eq!(text(1_250_000, USDC), "1.25 USDC");
+    assert_eq!(text(10_000_000, USDC), "10 USDC");
+}
+
+#[test]
+fn zero() {
+    assert_eq!(text(0, Currency::Sol), "0 SOL");
+    assert_eq!(text(0, USDC), "0 USDC");
+    assert_eq!(text(0, Currency::Token(Pubkey::new_unique())), "0 base units");
+}
+
+#[test]
+fn never_rounds() {
+    assert_eq!(text(1, Currency::Sol), "0.000000001 SOL");
+    assert_eq!(text(1_999_999_999, Currency::Sol), "1.999999999 SOL");
+    assert_eq!(text(1, USDC), "0.000001 USDC");
+    // Only trailing zeros of the fraction are dropped.
+    assert_eq!(text(1_000_000_010, Currency::Sol), "1.00000001 SOL");
+    assert_eq!(text(10_500_000_000, Currency::Sol), "10.5 SOL");
+}
+
+#[test]
+fn u64_max() {
+    assert_eq!(text(u64::MAX, Currency::Sol), "18446744073.709551615 SOL");
+    assert_eq!(text(u64::MAX, USDC), "18446744073709.551615 USDC");
+
+    let other = text(u64::MAX, Currency::Token(Pubkey::new_unique()));
+    assert_eq!(other, "18446744073709551615 base units");
+    assert_eq!(other.len(), MAX_AMOUNT_LEN);
+}
+
+#[test]
+fn short_buffers_cut_the_text() {
+    assert_eq!(fmt_amount(50_000_000, Currency::Sol, &mut [0; 4]), "0.05");
+    assert_eq!(fmt_amount(u64::MAX, Currency::Sol, &mut []), "");
+}
+
+#[test]
+fn reads_back_to_the_same_amount() {
+    let amounts = [0, 1, 9, 10, 999_999, 1_000_000, 50_000_000, 123_456_789_012, u64::MAX];
+    for amount in amounts {
+        for currency in [Currency::Sol, USDC] {
+            let (parsed, _) = x402_units::parse_amount(&text(amount, currency)).unwrap();
+            assert_eq!(parsed, amount, "{} in {:?}", amount, currency);
+        }
+    }
+}
@@ -0,0 +1,75 @@
+//! The payment types decode to what was encoded, both with Borsh as the
+//! program stores them and with serde as clients exchange them.
+
+use anchor_lang::prelude::*;
+use x402_common::{Currency, MismatchKind, VerifiedPayment, X402Params};
+
+fn params() -> Vec<X402Params> {
+    vec![
+        X402Params {
+            price: 1_000_000,
+            recipient: Pubkey::new_unique(),
+            currency: Currency::Sol,
+        },
+        X402Params {
+            price: u64::MAX,
+            recipient: Pubkey::new_unique(),
+            currency: Currency::Token(Pubkey::new_unique()),
+        },
+    ]
+}
+
+#[test]
+fn params_round_trip_through_borsh() {
+    for params in params() {
+        let bytes = params.try_to_vec().unwrap();
+        assert_eq!(X402Params::try_from_slice(&bytes).unwrap(), params);
+    }
+}
+
+#[test]
+fn params_round_trip_through_serde() {
+    for params in params() {
+        let json = serde_json::to_string(&params).unwrap();
+        assert_eq!(serde_json::from_str::<X402Params>(&json).unwrap(), params);
+    }
+}
+
+#[test]
+fn borsh_layout_is_price_recipient_currency() {
+    let recipient = Pubkey::new_unique();
+    let mint = Pubkey::new_unique();
+    let params = X402Params {
+        price: 5,
+        recipient,
+        currency: Currency::Token(mint),
+    };
+
+    let mut expected = 5u64.to_le_bytes().to_vec();
+    expected.extend_from_slice(recipient.as_ref());
+    expected.push(1);
+    expected.extend_from_slice(mint.as_ref());
+    assert_eq!(params.try_to_vec().unwrap(), expected);
+}
+
+#[test]
+fn verified_payments_and_mismatches_round_trip() {
+    let payment = VerifiedPayment {
+        amount: 1_500_000,
+        payer: Pubkey::new_unique(),
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 3,
+    };
+    let bytes = payment.try_to_vec().unwrap();
+    assert_eq!(VerifiedPayment::try_from_slice(&bytes).unwrap(), payment);
+    let json = serde_json::to_string(&payment).unwrap();
+    assert_eq!(serde_json::from_str::<VerifiedPayment>(&json).unwrap(), payment);
+
+    let miss = MismatchKind::WrongToken {
+        expected: None,
+        found: Some(Pubkey::new_unique()),
+    };
+    let json = serde_json::to_string(&miss).unwrap();
+    assert_eq!(serde_json::from_str::<MismatchKind>(&json).unwrap(), miss);
+}
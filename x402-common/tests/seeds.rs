@@ -0,0 +1,54 @@
+//! Every `*_address` helper derives the address the program's accounts
+//! structs expect, checked against the seeds spelled out by hand.
+
+use solana_program::pubkey::Pubkey;
+use x402_common::seeds::*;
+
+fn pda(seeds: &[&[u8]], program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(seeds, program_id).0
+}
+
+#[test]
+fn helpers_match_hand_derived_addresses() {
+    let (id, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let nonce = 0x0102_0304_0506_0708u64;
+    let cases = [
+        (config_address(&id), pda(&[b"x402_config"], &id)),
+        (congestion_address(&id), pda(&[b"x402_congestion"], &id)),
+        (stats_address(&id), pda(&[b"x402_stats"], &id)),
+        (spending_cap_address(&id, &payer), pda(&[b"spending_cap", payer.as_ref()], &id)),
+        (manifest_address(&id), pda(&[b"x402_manifest"], &id)),
+        (payment_ledger_address(&id, &payer), pda(&[b"payment_ledger", payer.as_ref()], &id)),
+        (
+            compute_result_address(&id, &payer, nonce),
+            pda(&[b"compute_result", payer.as_ref(), &[8, 7, 6, 5, 4, 3, 2, 1]], &id),
+        ),
+        (failure_log_address(&id), pda(&[b"failure_log"], &id)),
+        (deposit_address(&id, &payer), pda(&[b"x402_deposit", payer.as_ref()], &id)),
+        (experiment_address(&id, b"banner"), pda(&[b"x402_experiment", b"banner"], &id)),
+        (free_tier_config_address(&id), pda(&[b"free_tier_config"], &id)),
+        (usage_counter_address(&id, &payer), pda(&[b"usage_counter", payer.as_ref()], &id)),
+        (indexer_cursor_address(&id, "revenue"), pda(&[b"x402_cursor", b"revenue"], &id)),
+        (
+            group_budget_address(&id, "compute", &payer),
+            pda(&[b"x402_group_budget", b"compute", payer.as_ref()], &id),
+        ),
+        (reentrancy_guard_address(&id), pda(&[b"x402_reentrancy_guard"], &id)),
+        (
+            accumulator_address(&id, "enterprise_v1", &payer),
+            pda(&[b"x402_accumulator", b"enterprise_v1", payer.as_ref()], &id),
+        ),
+    ];
+    for (i, (helper, by_hand)) in cases.into_iter().enumerate() {
+        assert_eq!(helper, by_hand, "address {i}");
+    }
+}
+
+#[test]
+fn seeds_are_distinct() {
+    for (i, a) in ALL_SEEDS.iter().enumerate() {
+        for b in &ALL_SEEDS[i + 1..] {
+            assert_ne!(a, b);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+//! The snapshot tree: the frontier the program folds leaves into has the
+//! root of the whole tree built level by level, at every size, and a proof
+//! holds for its own leaf and position only.
+
+use solana_program::pubkey::Pubkey;
+use x402_common::snapshot::{
+    append, empty_subtrees, frontier_root, inclusion_proof, leaf_hash, node_hash, snapshot_root, verify_inclusion,
+    SnapshotFull, MAX_SNAPSHOT_LEAVES, SNAPSHOT_DEPTH,
+};
+
+fn leaves(count: u64) -> Vec<[u8; 32]> {
+    (0..count).map(|i| leaf_hash(&Pubkey::new_unique(), i, i * 1_000)).collect()
+}
+
+/// The root of the tree over `leaves`, padded with empty leaves, one level
+/// at a time.
+fn level_by_level(leaves: &[[u8; 32]]) -> [u8; 32] {
+    let mut level = leaves.to_vec();
+    for empty in empty_subtrees() {
+        if level.len() % 2 == 1 {
+            level.push(empty);
+        }
+        if level.is_empty() {
+            level.push(node_hash(&empty, &empty));
+            continue;
+        }
+        level = level.chunks(2).map(|pair| node_hash(&pair[0], &pair[1])).collect();
+    }
+    level[0]
+}
+
+#[test]
+fn the_frontier_root_is_the_whole_tree_root() {
+    for count in 0..=33 {
+        let leaves = leaves(count);
+        assert_eq!(snapshot_root(&leaves).unwrap(), level_by_level(&leaves), "{} leaves", count);
+    }
+    // The empty tree is the empty subtree of full height.
+    let empty = empty_subtrees();
+    assert_eq!(snapshot_root(&[]).unwrap(), node_hash(&empty[SNAPSHOT_DEPTH - 1], &empty[SNAPSHOT_DEPTH - 1]));
+}
+
+#[test]
+fn proofs_hold_for_their_leaf_and_position_only() {
+    let leaves = leaves(13);
+    let root = snapshot_root(&leaves).unwrap();
+    for (index, leaf) in leaves.iter().enumerate() {
+        let proof = inclusion_proof(&leaves, index).unwrap();
+        assert_eq!(proof.len(), SNAPSHOT_DEPTH);
+        assert!(verify_inclusion(&root, leaf, index as u64, &proof));
+
+        assert!(!verify_inclusion(&root, &leaves[(index + 1) % 13], index as u64, &proof));
+        assert!(!verify_inclusion(&root, leaf, index as u64 + 1, &proof));
+        assert!(!verify_inclusion(&root, leaf, index as u64, &proof[1..]));
+        let mut tampered = proof.clone();
+        tampered[SNAPSHOT_DEPTH - 1][0] ^= 1;
+        assert!(!verify_inclusion(&root, leaf, index as u64, &tampered));
+    }
+    assert_eq!(inclusion_proof(&leaves, 13), None);
+}
+
+#[test]
+fn leaves_and_nodes_hash_apart() {
+    let payer = Pubkey::new_unique();
+    assert_ne!(leaf_hash(&payer, 1, 2), leaf_hash(&payer, 2, 1));
+    let (left, right) = (leaf_hash(&payer, 1, 2), leaf_hash(&payer, 3, 4));
+    assert_ne!(node_hash(&left, &right), node_hash(&right, &left));
+}
+
+#[test]
+fn a_full_frontier_takes_no_more_leaves() {
+    let mut frontier = [[0; 32]; SNAPSHOT_DEPTH];
+    assert_eq!(append(&mut frontier, MAX_SNAPSHOT_LEAVES, [7; 32]), Err(SnapshotFull));
+    assert_eq!(frontier, [[0; 32]; SNAPSHOT_DEPTH]);
+    append(&mut frontier, 0, [7; 32]).unwrap();
+    assert_eq!(frontier_root(&frontier, 1), snapshot_root(&[[7; 32]]).unwrap());
+}
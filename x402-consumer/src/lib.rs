@@ -0,0 +1,147 @@
+//! A program built on `x402-example`, calling it the way other programs
+//! would.
+//!
+//! `use_result` consumes a `ComputeResult` its signer already paid
+//! `x402-example` for, through `consume_result`, and returns its value.
+//! `priced_query_directly` and `compute_premium_directly` instead invoke
+//! priced instructions themselves, which `x402-example` refuses: the
+//! payment before the transaction's instruction would otherwise pay for
+//! every call this program makes. A program that does need to call a
+//! priced instruction asks for `allow_cpi = true` on its handler.
+//! `deposit_query_directly` invokes `deposit_query`, which is paid from the
+//! payer's deposit and so serves other programs, unless it is itself in
+//! the middle of a call of it.
+
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use x402_example::cpi::accounts::{ConsumeResult, DepositQuery, PremiumCompute, PricedQuery};
+use x402_example::program::X402Example;
+use x402_example::{ComputeResult, CongestionLevel};
+
+declare_id!("8nPtFXcmLiSLuecmonGxsNsC2H3WCC4g1W2FWzei7scA");
+
+#[program]
+pub mod x402_consumer {
+    use super::*;
+
+    /// Consumes `result`, paid at `min_price` or more, and returns its
+    /// value. `consumer` must own the result or be the one it is bound to.
+    pub fn use_result(ctx: Context<UseResult>, min_price: u64) -> Result<u64> {
+        x402_example::cpi::consume_result(
+            CpiContext::new(
+                ctx.accounts.x402_example.to_account_info(),
+                ConsumeResult {
+                    consumer: ctx.accounts.consumer.to_account_info(),
+                    result: ctx.accounts.result.to_account_info(),
+                },
+            ),
+            min_price,
+        )?;
+        ctx.accounts.result.reload()?;
+        Ok(ctx.accounts.result.value)
+    }
+
+    /// Invokes `priced_query`, which fails with `InvokedByCpi` however the
+    /// transaction pays.
+    pub fn priced_query_directly(ctx: Context<PricedQueryDirectly>, max_acceptable_price: u64) -> Result<u64> {
+        let answer = x402_example::cpi::priced_query(
+            CpiContext::new(
+                ctx.accounts.x402_example.to_account_info(),
+                PricedQuery {
+                    payer: ctx.accounts.payer.to_account_info(),
+                    x402_congestion: ctx.accounts.x402_congestion.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(vec![ctx.accounts.instructions.to_account_info()]),
+            max_acceptable_price,
+        )?;
+        Ok(answer.get())
+    }
+
+    /// Invokes `compute_premium`, which fails with `NotInvokedByTrampoline`:
+    /// the compute tiers accept being invoked only by `x402-example`'s own
+    /// `pay_and_invoke`.
+    pub fn compute_premium_directly(ctx: Context<ComputePremiumDirectly>, nonce: u64) -> Result<()> {
+        x402_example::cpi::compute_premium(
+            CpiContext::new(
+                ctx.accounts.x402_example.to_account_info(),
+                PremiumCompute {
+                    payer: ctx.accounts.payer.to_account_info(),
+                    result: ctx.accounts.result.to_account_info(),
+                    spending_cap: None,
+                    x402_ledger: None,
+                    x402_group_budget: None,
+                    x402_stats: ctx.accounts.x402_stats.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    x402_instructions: ctx.accounts.instructions.to_account_info(),
+                },
+            ),
+            None,
+            nonce,
+        )
+    }
+
+    /// Invokes `deposit_query`, paid from `payer`'s deposit, which fails
+    /// with `ReentrantCall` while `x402-example` is running a call of it.
+    pub fn deposit_query_directly(ctx: Context<DepositQueryDirectly>) -> Result<u64> {
+        let answer = x402_example::cpi::deposit_query(CpiContext::new(
+            ctx.accounts.x402_example.to_account_info(),
+            DepositQuery {
+                payer: ctx.accounts.payer.to_account_info(),
+                x402_deposit: ctx.accounts.deposit.to_account_info(),
+                x402_recipient: ctx.accounts.recipient.to_account_info(),
+                x402_reentrancy_guard: ctx.accounts.reentrancy_guard.to_account_info(),
+            },
+        ))?;
+        Ok(answer.get())
+    }
+}
+
+#[derive(Accounts)]
+pub struct UseResult<'info> {
+    pub consumer: Signer<'info>,
+    #[account(mut)]
+    pub result: Account<'info, ComputeResult>,
+    pub x402_example: Program<'info, X402Example>,
+}
+
+#[derive(Accounts)]
+pub struct PricedQueryDirectly<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+    /// CHECK: the instructions sysvar, checked by `x402-example`.
+    pub instructions: UncheckedAccount<'info>,
+    pub x402_example: Program<'info, X402Example>,
+}
+
+#[derive(Accounts)]
+pub struct ComputePremiumDirectly<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: the result PDA, checked by `x402-example`.
+    #[account(mut)]
+    pub result: UncheckedAccount<'info>,
+    /// CHECK: the stats PDA, checked by `x402-example`.
+    #[account(mut)]
+    pub x402_stats: UncheckedAccount<'info>,
+    /// CHECK: the instructions sysvar, checked by `x402-example`.
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub x402_example: Program<'info, X402Example>,
+}
+
+#[derive(Accounts)]
+pub struct DepositQueryDirectly<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: the payer's deposit, checked by `x402-example`.
+    #[account(mut)]
+    pub deposit: UncheckedAccount<'info>,
+    /// CHECK: `deposit_query`'s recipient, checked by `x402-example`.
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    /// CHECK: the reentrancy guard PDA, checked by `x402-example`.
+    #[account(mut)]
+    pub reentrancy_guard: UncheckedAccount<'info>,
+    pub x402_example: Program<'info, X402Example>,
+}
@@ -0,0 +1,311 @@
+//! What `x402-example` does when `x402-consumer` invokes it: each call
+//! below runs the handler as the consumer's instruction would reach it,
+//! at stack height 2, with the consumer's instruction at transaction level.
+//!
+//! Consuming a paid result works from another program. Priced
+//! instructions refuse it: `priced_query` with `InvokedByCpi`, even when
+//! the transaction pays, and the compute tiers with
+//! `NotInvokedByTrampoline`. `deposit_query` serves other programs, one
+//! call after another, but fails with `ReentrantCall` when reached while a
+//! call of it is still running.
+
+#![allow(unexpected_cfgs)]
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use anchor_lang::{Discriminator, InstructionData};
+use x402_example::x402_example::{DEPOSIT_QUERY_X402_PARAMS, PRICED_QUERY_X402_PARAMS};
+use x402_example::{
+    CongestionLevel, ConsumeResult, ConsumeResultBumps, ComputeResult, DepositAccount, DepositQuery,
+    DepositQueryBumps, PremiumCompute, PremiumComputeBumps, PricedQuery, PricedQueryBumps, ReentrancyGuard,
+    Versioned, X402Stats, TIER_PREMIUM,
+};
+use x402_runtime::congestion::CONGESTION_SEED;
+use x402_runtime::{PriceProvenance, X402Error};
+
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+static STACK_HEIGHT: AtomicU64 = AtomicU64::new(1);
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_stack_height(&self) -> u64 {
+        STACK_HEIGHT.load(Ordering::SeqCst)
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // Safety: the runtime passes the address of a `Clock`.
+        unsafe { var_addr.cast::<Clock>().write(Clock::default()) };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // Safety: the runtime passes the address of a `Rent`.
+        unsafe { var_addr.cast::<Rent>().write(Rent::default()) };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+}
+
+/// Runs `call` as if at `height`. The stubs are process-wide, so calls
+/// take turns.
+fn at_stack_height<T>(height: u64, call: impl FnOnce() -> T) -> T {
+    static TURN: Mutex<()> = Mutex::new(());
+    let _turn = TURN.lock().unwrap_or_else(PoisonError::into_inner);
+    set_syscall_stubs(Box::new(Stubs));
+    STACK_HEIGHT.store(height, Ordering::SeqCst);
+    call()
+}
+
+fn borrow(ix: &Instruction) -> BorrowedInstruction<'_> {
+    BorrowedInstruction {
+        program_id: &ix.program_id,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| BorrowedAccountMeta {
+                pubkey: &meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: &ix.data,
+    }
+}
+
+/// Instructions sysvar data for a transfer of `paid` to the recipient
+/// followed by `top`, the instruction being executed.
+fn sysvar_data(paid: Option<u64>, top: Instruction) -> Vec<u8> {
+    let recipient = PRICED_QUERY_X402_PARAMS.recipient;
+    let mut all: Vec<Instruction> =
+        paid.map(|paid| system_instruction::transfer(&PAYER, &recipient, paid)).into_iter().collect();
+    all.push(top);
+    let borrowed: Vec<_> = all.iter().map(borrow).collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, (all.len() - 1) as u16);
+    data
+}
+
+/// The consumer's instruction, as the transaction carries it.
+fn consumer_instruction(data: impl InstructionData) -> Instruction {
+    Instruction {
+        program_id: x402_consumer::ID,
+        accounts: vec![AccountMeta::new(PAYER, true)],
+        data: data.data(),
+    }
+}
+
+fn account_data(account: &impl AccountSerialize) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn call_priced_query(height: u64, paid: Option<u64>, top: Instruction) -> Result<u64> {
+    let (system, sysvar_owner, example) = (system_program::ID, sysvar::ID, x402_example::ID);
+    let (congestion_key, _) = Pubkey::find_program_address(&[CONGESTION_SEED], &example);
+    let (mut payer_lamports, mut payer_data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0);
+    let level = CongestionLevel {
+        version: CongestionLevel::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        level: 0,
+        previous_level: 0,
+        updated_at: 0,
+    };
+    let (mut congestion_lamports, mut congestion_data) = (1_000_000, account_data(&level));
+    let congestion = AccountInfo::new(
+        &congestion_key,
+        false,
+        false,
+        &mut congestion_lamports,
+        &mut congestion_data,
+        &example,
+        false,
+        0,
+    );
+    let (mut sysvar_lamports, mut sysvar_data) = (0, sysvar_data(paid, top));
+    let remaining = [AccountInfo::new(
+        &instructions::ID,
+        false,
+        false,
+        &mut sysvar_lamports,
+        &mut sysvar_data,
+        &sysvar_owner,
+        false,
+        0,
+    )];
+
+    let mut accounts = PricedQuery {
+        payer: Signer::try_from(&payer)?,
+        x402_congestion: Account::try_from(&congestion)?,
+    };
+    let ctx = Context::new(&example, &mut accounts, &remaining, PricedQueryBumps::default());
+    at_stack_height(height, || x402_example::x402_example::priced_query(ctx, PRICED_QUERY_X402_PARAMS.price))
+}
+
+#[test]
+fn consuming_a_paid_result_works_from_another_program() {
+    let example = x402_example::ID;
+    let (mut consumer_lamports, mut consumer_data) = (5_000_000, []);
+    let system = system_program::ID;
+    let consumer =
+        AccountInfo::new(&PAYER, true, false, &mut consumer_lamports, &mut consumer_data, &system, false, 0);
+    let paid = ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: PAYER,
+        value: 42,
+        paid: true,
+        amount_paid: 1_000_000,
+        tier: TIER_PREMIUM,
+        price_paid: 1_000_000,
+        created_slot: 1,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    };
+    let result_key = Pubkey::new_unique();
+    let (mut result_lamports, mut result_data) = (1_000_000, account_data(&paid));
+    let result =
+        AccountInfo::new(&result_key, false, true, &mut result_lamports, &mut result_data, &example, false, 0);
+
+    let mut accounts = ConsumeResult {
+        consumer: Signer::try_from(&consumer).unwrap(),
+        result: Account::try_from(&result).unwrap(),
+    };
+    let ctx = Context::new(&example, &mut accounts, &[], ConsumeResultBumps::default());
+    at_stack_height(2, || x402_example::x402_example::consume_result(ctx, 1_000_000)).unwrap();
+    assert_eq!((accounts.result.value, accounts.result.ref_count), (42, 1));
+}
+
+#[test]
+fn priced_query_refuses_other_programs_even_when_paid() {
+    let top = || consumer_instruction(x402_consumer::instruction::PricedQueryDirectly { max_acceptable_price: 0 });
+    for paid in [None, Some(PRICED_QUERY_X402_PARAMS.price)] {
+        let err = call_priced_query(2, paid, top()).unwrap_err();
+        assert_eq!(err, X402Error::InvokedByCpi.into());
+    }
+
+    // The same transaction calling it at transaction level is served.
+    let direct = Instruction {
+        program_id: x402_example::ID,
+        accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+        data: x402_example::instruction::PricedQuery::DISCRIMINATOR.to_vec(),
+    };
+    assert_eq!(call_priced_query(1, Some(PRICED_QUERY_X402_PARAMS.price), direct).unwrap(), 42);
+}
+
+#[test]
+fn compute_tiers_refuse_programs_other_than_their_trampoline() {
+    let (system, sysvar_owner, example) = (system_program::ID, sysvar::ID, x402_example::ID);
+    let (mut payer_lamports, mut payer_data) = (50_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0);
+    let result_key = Pubkey::new_unique();
+    let (mut result_lamports, mut result_data) = (0, []);
+    let result = AccountInfo::new(&result_key, false, true, &mut result_lamports, &mut result_data, &system, false, 0);
+    let stats_key = Pubkey::new_unique();
+    let mut stats = X402Stats::DISCRIMINATOR.to_vec();
+    stats.resize(8 + std::mem::size_of::<X402Stats>(), 0);
+    stats[8] = X402Stats::CURRENT_VERSION;
+    let mut stats_lamports = 1_000_000;
+    let stats = AccountInfo::new(&stats_key, false, true, &mut stats_lamports, &mut stats, &example, false, 0);
+    let (mut system_lamports, mut system_data) = (1, []);
+    let system_info =
+        AccountInfo::new(&system, false, false, &mut system_lamports, &mut system_data, &system, true, 0);
+    let top = consumer_instruction(x402_consumer::instruction::ComputePremiumDirectly { nonce: 0 });
+    let (mut sysvar_lamports, mut sysvar_data) = (0, sysvar_data(Some(1_000_000), top));
+    let sysvar_info = AccountInfo::new(
+        &instructions::ID,
+        false,
+        false,
+        &mut sysvar_lamports,
+        &mut sysvar_data,
+        &sysvar_owner,
+        false,
+        0,
+    );
+
+    let mut accounts = PremiumCompute {
+        payer: Signer::try_from(&payer).unwrap(),
+        result: UncheckedAccount::try_from(&result),
+        spending_cap: None,
+        x402_ledger: None,
+        x402_group_budget: None,
+        x402_stats: AccountLoader::try_from(&stats).unwrap(),
+        system_program: Program::try_from(&system_info).unwrap(),
+        x402_instructions: UncheckedAccount::try_from(&sysvar_info),
+    };
+    let remaining = [sysvar_info.clone()];
+    let ctx = Context::new(&example, &mut accounts, &remaining, PremiumComputeBumps::default());
+    let err = at_stack_height(2, || x402_example::x402_example::compute_premium(ctx, None, 0)).unwrap_err();
+    assert_eq!(err, X402Error::NotInvokedByTrampoline.into());
+}
+
+/// Runs `deposit_query` as the consumer's `deposit_query_directly` reaches
+/// it, against the stored `deposit` balance and `guard` data.
+fn call_deposit_query(deposit_lamports: &mut u64, guard: &mut [u8]) -> Result<u64> {
+    let (system, example) = (system_program::ID, x402_example::ID);
+    let (mut payer_lamports, mut payer_data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, false, &mut payer_lamports, &mut payer_data, &system, false, 0);
+    let deposit_key = Pubkey::new_unique();
+    let mut deposit_data = account_data(&DepositAccount {
+        version: DepositAccount::CURRENT_VERSION,
+        depositor: PAYER,
+    });
+    let deposit =
+        AccountInfo::new(&deposit_key, false, true, deposit_lamports, &mut deposit_data, &example, false, 0);
+    let recipient_key = DEPOSIT_QUERY_X402_PARAMS.recipient;
+    let (mut recipient_lamports, mut recipient_data) = (1_000_000, []);
+    let recipient =
+        AccountInfo::new(&recipient_key, false, true, &mut recipient_lamports, &mut recipient_data, &system, false, 0);
+    let guard_key = Pubkey::new_unique();
+    let mut guard_lamports = 1_000_000;
+    let guard = AccountInfo::new(&guard_key, false, true, &mut guard_lamports, guard, &example, false, 0);
+
+    let mut accounts = DepositQuery {
+        payer: Signer::try_from(&payer)?,
+        x402_deposit: Account::try_from(&deposit)?,
+        x402_recipient: SystemAccount::try_from(&recipient)?,
+        x402_reentrancy_guard: AccountLoader::try_from(&guard)?,
+    };
+    let ctx = Context::new(&example, &mut accounts, &[], DepositQueryBumps::default());
+    at_stack_height(2, || x402_example::x402_example::deposit_query(ctx))
+}
+
+fn guard_data(entered: u8) -> Vec<u8> {
+    let mut data = ReentrancyGuard::DISCRIMINATOR.to_vec();
+    data.extend([ReentrancyGuard::CURRENT_VERSION, entered]);
+    data
+}
+
+#[test]
+fn deposit_query_serves_other_programs_one_call_after_another() {
+    let price = DEPOSIT_QUERY_X402_PARAMS.price;
+    let funded = Rent::default().minimum_balance(8 + DepositAccount::INIT_SPACE) + 2 * price;
+    let mut deposit = funded;
+    let mut guard = guard_data(0);
+    for _ in 0..2 {
+        assert_eq!(call_deposit_query(&mut deposit, &mut guard).unwrap(), 42);
+        assert_eq!(guard[8..], [ReentrancyGuard::CURRENT_VERSION, 0]);
+    }
+    assert_eq!(deposit, funded - 2 * price);
+}
+
+#[test]
+fn deposit_query_refuses_to_be_reentered() {
+    // The guard as an outer `deposit_query` leaves it while its body runs.
+    let funded = Rent::default().minimum_balance(8 + DepositAccount::INIT_SPACE) + DEPOSIT_QUERY_X402_PARAMS.price;
+    let mut deposit = funded;
+    let mut guard = guard_data(1);
+    let err = call_deposit_query(&mut deposit, &mut guard).unwrap_err();
+    assert_eq!(err, X402Error::ReentrantCall.into());
+    assert_eq!(deposit, funded);
+    assert_eq!(guard[8..], [ReentrancyGuard::CURRENT_VERSION, 1]);
+}
@@ -0,0 +1,174 @@
+//! Deployment commands for the example program. `init` creates every
+//! account a deployment needs in one transaction, through `initialize_all`,
+//! signed by the program's upgrade authority:
+//!
+//! ```text
+//! cargo run -p x402-example --example cli -- init \
+//!     --recipient <PUBKEY> --operator <PUBKEY> --cluster devnet
+//! ```
+//!
+//! With `--idempotent` it sends `initialize_all_idempotent` instead, which
+//! leaves the accounts that exist as they are, so a deployment stopped
+//! partway is finished by running it again. `--keypair` names the upgrade
+//! authority's keypair file, which also pays (default
+//! `~/.config/solana/id.json`), and `--url` the RPC endpoint (default
+//! `X402_RPC_URL`, else a local validator).
+
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::signature::{read_keypair_file, Signer};
+use solana_sdk::transaction::Transaction;
+use x402_common::seeds::{
+    config_address, congestion_address, experiment_address, free_tier_config_address, manifest_address,
+    reentrancy_guard_address, stats_address,
+};
+use x402_example::x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT;
+use x402_example::InitParams;
+use x402_runtime::Cluster;
+
+const LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
+
+const USAGE: &str = "usage: cli init --recipient <PUBKEY> --operator <PUBKEY> --cluster <CLUSTER> \
+                     [--idempotent] [--keypair <PATH>] [--url <URL>]";
+
+/// A parsed `init` command.
+#[derive(Debug, PartialEq)]
+pub struct Init {
+    pub params: InitParams,
+    /// Skip the accounts that already exist.
+    pub idempotent: bool,
+    pub keypair: PathBuf,
+    pub url: String,
+}
+
+/// Parses the arguments after the program name, or returns what is wrong
+/// with them.
+pub fn parse(args: &[String]) -> Result<Init, String> {
+    let (command, mut rest) = args.split_first().ok_or(USAGE)?;
+    if command != "init" {
+        return Err(format!("unknown command `{}`\n{}", command, USAGE));
+    }
+
+    let (mut recipient, mut operator, mut cluster) = (None, None, None);
+    let mut idempotent = false;
+    let mut keypair = None;
+    let mut url = None;
+    while let Some((flag, tail)) = rest.split_first() {
+        rest = tail;
+        match flag.as_str() {
+            "--idempotent" => {
+                idempotent = true;
+                continue;
+            }
+            "--recipient" | "--operator" | "--cluster" | "--keypair" | "--url" => {}
+            _ => return Err(format!("unknown flag `{}`\n{}", flag, USAGE)),
+        }
+        let (value, tail) = rest.split_first().ok_or_else(|| format!("{} needs a value", flag))?;
+        rest = tail;
+        match flag.as_str() {
+            "--recipient" => recipient = Some(pubkey(flag, value)?),
+            "--operator" => operator = Some(pubkey(flag, value)?),
+            "--cluster" => {
+                let names: Vec<_> = Cluster::ALL.iter().map(|cluster| cluster.name()).collect();
+                cluster = Some(Cluster::from_name(value).ok_or_else(|| {
+                    format!("--cluster must be one of {}, not `{}`", names.join(", "), value)
+                })?)
+            }
+            "--keypair" => keypair = Some(PathBuf::from(value)),
+            _ => url = Some(value.clone()),
+        }
+    }
+
+    Ok(Init {
+        params: InitParams {
+            recipient: recipient.ok_or("--recipient is required")?,
+            operator: operator.ok_or("--operator is required")?,
+            cluster: cluster.ok_or("--cluster is required")?,
+        },
+        idempotent,
+        keypair: keypair.unwrap_or_else(default_keypair),
+        url: url.or_else(|| env::var("X402_RPC_URL").ok()).unwrap_or_else(|| LOCAL_RPC_URL.to_string()),
+    })
+}
+
+fn pubkey(flag: &str, value: &str) -> Result<Pubkey, String> {
+    Pubkey::from_str(value).map_err(|_| format!("{} is not a public key: `{}`", flag, value))
+}
+
+/// The Solana CLI's default keypair.
+fn default_keypair() -> PathBuf {
+    let home = env::var_os("HOME").unwrap_or_default();
+    PathBuf::from(home).join(".config/solana/id.json")
+}
+
+/// `initialize_all`, or `initialize_all_idempotent`, of `program_id` with
+/// `params`, paid by `payer` and signed by `authority`, the program's
+/// upgrade authority.
+pub fn init_instruction(
+    program_id: &Pubkey,
+    payer: &Pubkey,
+    authority: &Pubkey,
+    params: InitParams,
+    idempotent: bool,
+) -> Instruction {
+    let bucket_seed = EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes();
+    let accounts = x402_example::accounts::InitializeAll {
+        payer: *payer,
+        authority: *authority,
+        program: *program_id,
+        program_data: Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0,
+        x402_config: config_address(program_id),
+        free_tier_config: free_tier_config_address(program_id),
+        x402_congestion: congestion_address(program_id),
+        x402_experiment: experiment_address(program_id, bucket_seed),
+        x402_stats: stats_address(program_id),
+        x402_reentrancy_guard: reentrancy_guard_address(program_id),
+        manifest: manifest_address(program_id),
+        system_program: system_program::ID,
+    };
+    let data = if idempotent {
+        x402_example::instruction::InitializeAllIdempotent { params }.data()
+    } else {
+        x402_example::instruction::InitializeAll { params }.data()
+    };
+    Instruction {
+        program_id: *program_id,
+        accounts: accounts.to_account_metas(None),
+        data,
+    }
+}
+
+/// Runs `init`, signed and paid by the keypair it names.
+pub fn init(command: &Init) -> Result<(), Box<dyn Error>> {
+    let authority = read_keypair_file(&command.keypair)
+        .map_err(|err| format!("reading {}: {}", command.keypair.display(), err))?;
+    let rpc = RpcClient::new_with_commitment(command.url.clone(), CommitmentConfig::confirmed());
+    let program_id = x402_example::ID;
+    let instruction =
+        init_instruction(&program_id, &authority.pubkey(), &authority.pubkey(), command.params, command.idempotent);
+
+    let transaction = Transaction::new_signed_with_payer(
+        &[instruction],
+        Some(&authority.pubkey()),
+        &[&authority],
+        rpc.get_latest_blockhash()?,
+    );
+    let signature = rpc.send_and_confirm_transaction(&transaction)?;
+    println!("initialized {} on {}: {}", program_id, command.params.cluster.name(), signature);
+    println!("config: {}", config_address(&program_id));
+    Ok(())
+}
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    init(&parse(&args)?)
+}
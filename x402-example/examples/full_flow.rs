@@ -0,0 +1,287 @@
+//! The whole x402 loop against a local validator: an HTTP client asks for a
+//! paid resource, is answered `402 Payment Required` with what to pay, pays
+//! `priced_query` on chain and asks again with the transaction's signature,
+//! which the server checks before answering with the program's result.
+//!
+//! ```text
+//! anchor build
+//! cargo run -p x402-example --example full_flow
+//! cargo test -p x402-example --test full_flow -- --ignored
+//! ```
+//!
+//! It starts `solana-test-validator` with `target/deploy/x402_example.so`
+//! deployed upgradeable at the program id, with the payer as its upgrade
+//! authority so it may create the congestion level, unless `X402_RPC_URL`
+//! names a validator the program is already deployed and initialized on. The server is a bare `std::net` loop
+//! standing in for a web framework's handler; what it does per request is
+//! what a middleware would.
+
+mod validator;
+
+use std::collections::HashSet;
+use std::error::Error;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::str::FromStr;
+use std::thread;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signature, Signer};
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::UiTransactionReturnData;
+use x402_client::{
+    decode_program_events, decode_return_data, paid_call_instructions, preflight_paid_call, quote, PaymentVerified,
+    PreflightOutcome, PricedInstruction,
+};
+use x402_example::x402_example::{priced_query_x402_layout, PRICED_QUERY_X402_PARAMS, PRICED_QUERY_X402_SURCHARGE};
+use x402_runtime::X402Params;
+
+use validator::start_validator;
+
+/// Header carrying the signature of the paid transaction.
+const PAYMENT_HEADER: &str = "x-payment";
+
+/// The body of a `402 Payment Required` response.
+#[derive(Debug, Serialize, Deserialize)]
+struct Challenge {
+    program_id: Pubkey,
+    /// What to transfer before calling `priced_query`.
+    params: X402Params,
+    /// The `max_acceptable_price` argument to call it with.
+    max_acceptable_price: Option<u64>,
+}
+
+fn priced_query() -> PricedInstruction {
+    PricedInstruction::new(PRICED_QUERY_X402_PARAMS).with_surcharge(PRICED_QUERY_X402_SURCHARGE)
+}
+
+/// The seller: answers unpaid requests with a [`Challenge`] and paid ones
+/// with what `priced_query` returned, once per paid transaction.
+struct Server {
+    rpc: RpcClient,
+    redeemed: HashSet<Signature>,
+}
+
+impl Server {
+    fn respond(&mut self, payment: Option<&str>) -> (u16, String) {
+        if let Some(payment) = payment {
+            match self.redeem(payment) {
+                Ok(value) => return (200, value.to_string()),
+                Err(reason) => println!("server: refusing payment {}: {}", payment, reason),
+            }
+        }
+        match self.challenge() {
+            Ok(challenge) => (402, challenge),
+            Err(err) => (500, err.to_string()),
+        }
+    }
+
+    fn challenge(&self) -> Result<String, Box<dyn Error>> {
+        let quote = quote(&self.rpc, &x402_example::ID, &priced_query())?;
+        let challenge = Challenge {
+            program_id: x402_example::ID,
+            params: quote.params(),
+            max_acceptable_price: quote.max_acceptable_price(),
+        };
+        Ok(serde_json::to_string(&challenge)?)
+    }
+
+    /// The answer of the `priced_query` call in transaction `payment`, if it
+    /// succeeded, paid the recipient, and was not redeemed before.
+    fn redeem(&mut self, payment: &str) -> Result<u64, Box<dyn Error>> {
+        let signature = Signature::from_str(payment)?;
+        if self.redeemed.contains(&signature) {
+            return Err("already redeemed".into());
+        }
+        let transaction = self.rpc.get_transaction_with_config(
+            &signature,
+            RpcTransactionConfig {
+                commitment: Some(self.rpc.commitment()),
+                max_supported_transaction_version: Some(0),
+                ..RpcTransactionConfig::default()
+            },
+        )?;
+        let meta = transaction
+            .transaction
+            .meta
+            .filter(|meta| meta.err.is_none())
+            .ok_or("the transaction failed")?;
+
+        let logs: Option<Vec<String>> = meta.log_messages.into();
+        let paid = decode_program_events(&logs.unwrap_or_default(), &x402_example::ID)
+            .iter()
+            .filter_map(PaymentVerified::from_event)
+            .any(|payment| {
+                payment.recipient == PRICED_QUERY_X402_PARAMS.recipient && payment.price >= PRICED_QUERY_X402_PARAMS.price
+            });
+        if !paid {
+            return Err("no payment to the recipient".into());
+        }
+        let return_data: Option<UiTransactionReturnData> = meta.return_data.into();
+        let return_data = return_data
+            .filter(|data| data.program_id == x402_example::ID.to_string())
+            .ok_or("no answer from priced_query")?;
+        let value = decode_return_data(&STANDARD.decode(&return_data.data.0)?)?;
+
+        self.redeemed.insert(signature);
+        Ok(value)
+    }
+}
+
+fn serve(listener: TcpListener, url: String) {
+    let mut server = Server {
+        rpc: RpcClient::new_with_commitment(url, CommitmentConfig::confirmed()),
+        redeemed: HashSet::new(),
+    };
+    for stream in listener.incoming().flatten() {
+        if let Err(err) = handle(&mut server, stream) {
+            println!("server: {}", err);
+        }
+    }
+}
+
+fn handle(server: &mut Server, mut stream: TcpStream) -> std::io::Result<()> {
+    let mut payment = None;
+    for line in BufReader::new(&stream).lines() {
+        let line = line?;
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case(PAYMENT_HEADER) {
+                payment = Some(value.trim().to_string());
+            }
+        }
+    }
+    let (status, body) = server.respond(payment.as_deref());
+    let reason = match status {
+        200 => "OK",
+        402 => "Payment Required",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )
+}
+
+/// `GET /query`, with the paid transaction's signature if there is one.
+fn get(server: SocketAddr, payment: Option<&Signature>) -> std::io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(server)?;
+    write!(stream, "GET /query HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", server)?;
+    if let Some(payment) = payment {
+        write!(stream, "{}: {}\r\n", PAYMENT_HEADER, payment)?;
+    }
+    write!(stream, "\r\n")?;
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.split(' ').nth(1).and_then(|status| status.parse().ok()).unwrap_or(0);
+    let body = response.split_once("\r\n\r\n").map(|(_, body)| body.to_string()).unwrap_or_default();
+    Ok((status, body))
+}
+
+fn send(rpc: &RpcClient, payer: &Keypair, instructions: &[Instruction]) -> Result<Signature, Box<dyn Error>> {
+    let transaction =
+        Transaction::new_signed_with_payer(instructions, Some(&payer.pubkey()), &[payer], rpc.get_latest_blockhash()?);
+    Ok(rpc.send_and_confirm_transaction(&transaction)?)
+}
+
+/// Public so the `full_flow` test can run it.
+pub fn main() -> Result<(), Box<dyn Error>> {
+    let program_id = x402_example::ID;
+    // The payer deploys the program, so it may create the congestion level.
+    let payer = Keypair::new();
+    let (_validator, url) = start_validator(&payer.pubkey())?;
+    let rpc = RpcClient::new_with_commitment(url.clone(), CommitmentConfig::confirmed());
+    let airdrop = rpc.request_airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)?;
+    rpc.poll_for_signature(&airdrop)?;
+    println!("validator: {} funded {} with 1 SOL", url, payer.pubkey());
+
+    // `priced_query` reads the congestion level, created once per program.
+    let congestion = PRICED_QUERY_X402_SURCHARGE.account;
+    if rpc.get_account_with_commitment(&congestion, rpc.commitment())?.value.is_none() {
+        let program_data = Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0;
+        let initialize = Instruction {
+            program_id,
+            accounts: x402_example::accounts::InitializeCongestion {
+                payer: payer.pubkey(),
+                authority: payer.pubkey(),
+                program: program_id,
+                program_data,
+                x402_congestion: congestion,
+                system_program: system_program::ID,
+            }
+            .to_account_metas(None),
+            data: x402_example::instruction::InitializeCongestion {
+                authority: payer.pubkey(),
+            }
+            .data(),
+        };
+        send(&rpc, &payer, &[initialize])?;
+        println!("setup: created the congestion level at {}", congestion);
+    }
+
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let server = listener.local_addr()?;
+    thread::spawn(move || serve(listener, url));
+    println!("server: listening on {}", server);
+
+    let (status, body) = get(server, None)?;
+    assert_eq!(status, 402, "an unpaid request is refused: {}", body);
+    let challenge: Challenge = serde_json::from_str(&body)?;
+    assert_eq!(challenge.program_id, program_id);
+    println!(
+        "client: 402, pay {} lamports to {}",
+        challenge.params.price, challenge.params.recipient
+    );
+
+    // The layout says what goes around the call: the transfer before it
+    // and the instructions sysvar appended to it.
+    let call = Instruction {
+        program_id,
+        accounts: x402_example::accounts::PricedQuery {
+            payer: payer.pubkey(),
+            x402_congestion: congestion,
+        }
+        .to_account_metas(None),
+        data: x402_example::instruction::PricedQuery {
+            max_acceptable_price: challenge.max_acceptable_price.ok_or("priced_query is surcharged")?,
+        }
+        .data(),
+    };
+    let instructions = paid_call_instructions(&priced_query_x402_layout(), &payer.pubkey(), &challenge.params, call)?;
+    let transaction =
+        Transaction::new_signed_with_payer(&instructions, Some(&payer.pubkey()), &[&payer], rpc.get_latest_blockhash()?);
+    let preflight = preflight_paid_call(&rpc, &transaction)?;
+    assert!(
+        matches!(preflight.outcome, PreflightOutcome::Passes),
+        "the payment passes preflight: {:?}",
+        preflight.outcome
+    );
+    let signature = rpc.send_and_confirm_transaction(&transaction)?;
+    println!("client: paid and called priced_query in {}", signature);
+
+    let (status, body) = get(server, Some(&signature))?;
+    assert_eq!((status, body.as_str()), (200, "42"), "the paid request is served");
+    println!("client: 200, priced_query answered {}", body);
+
+    let (status, _) = get(server, Some(&signature))?;
+    assert_eq!(status, 402, "a payment is redeemed once");
+    println!("client: 402 again when the same payment is replayed");
+    Ok(())
+}
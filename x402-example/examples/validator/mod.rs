@@ -0,0 +1,65 @@
+//! A local validator with the example program loaded, for the examples and
+//! the tests that need one.
+
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+
+const LOCAL_RPC_URL: &str = "http://127.0.0.1:8899";
+
+/// A validator started for a run, stopped when dropped.
+pub struct Validator(Option<Child>);
+
+impl Drop for Validator {
+    fn drop(&mut self) {
+        if let Some(child) = &mut self.0 {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    }
+}
+
+/// Starts `solana-test-validator` with `target/deploy/x402_example.so`
+/// deployed at the program id, upgradeable by `upgrade_authority` so it
+/// can run the program's initializers, and returns its RPC URL. When
+/// `X402_RPC_URL` names a validator the program is already deployed to,
+/// that one is used instead, and its accounts must already be
+/// initialized by its own upgrade authority.
+pub fn start_validator(upgrade_authority: &Pubkey) -> Result<(Validator, String), Box<dyn Error>> {
+    if let Ok(url) = env::var("X402_RPC_URL") {
+        return Ok((Validator(None), url));
+    }
+    let program = Path::new(env!("CARGO_MANIFEST_DIR")).join("../target/deploy/x402_example.so");
+    if !program.exists() {
+        return Err(format!("{} not found; run `anchor build` first", program.display()).into());
+    }
+    let ledger = env::temp_dir().join(format!("x402-validator-{}", std::process::id()));
+    let child = Command::new("solana-test-validator")
+        .arg("--reset")
+        .arg("--quiet")
+        .arg("--ledger")
+        .arg(&ledger)
+        .arg("--upgradeable-program")
+        .arg(x402_example::ID.to_string())
+        .arg(&program)
+        .arg(upgrade_authority.to_string())
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|err| format!("starting solana-test-validator: {}", err))?;
+    let validator = Validator(Some(child));
+
+    let rpc = RpcClient::new(LOCAL_RPC_URL.to_string());
+    for _ in 0..60 {
+        if rpc.get_health().is_ok() {
+            return Ok((validator, LOCAL_RPC_URL.to_string()));
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    Err("solana-test-validator did not become healthy within 60 seconds".into())
+}
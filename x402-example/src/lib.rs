@@ -1,183 +1,2990 @@
 #![allow(unexpected_cfgs)]
 
+use std::cell::RefMut;
+
 use anchor_lang::prelude::*;
-use x402_macros::x402;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program::invoke;
+use anchor_lang::system_program;
+use anchor_lang::{Discriminator, ZeroCopy};
+use x402_common::cursor::INDEXER_CURSOR_SEED;
+use x402_common::events::{ComputeEvent, LedgerSnapshotEvent, PaymentFailedEvent, PaymentRecordedEvent};
+use x402_common::failures::{FailureEntry, FailureRing, FAILURE_LOG_SEED};
+use x402_common::ledger::PAYMENT_LEDGER_SEED;
+use x402_common::result::COMPUTE_RESULT_SEED;
+use x402_common::seeds::{self, SNAPSHOT_STATE_SEED};
+use x402_common::snapshot::{self, SNAPSHOT_DEPTH};
+use x402_macros::{x402, x402_accounts, x402_program};
+use x402_runtime::accumulator::ACCUMULATOR_SEED;
+use x402_runtime::admin::X402Admin;
+use x402_runtime::clock::{self, MAX_HORIZON_SECS};
+use x402_runtime::commitment::GENESIS_COMMITMENT;
+use x402_runtime::config::CONFIG_SEED;
+use x402_runtime::congestion::{CongestionSurcharge, LevelChange, CONGESTION_SEED, MAX_CONGESTION_LEVEL};
+use x402_runtime::deposit::DEPOSIT_SEED;
+use x402_runtime::experiment::{ExperimentState, EXPERIMENT_SEED};
+use x402_runtime::group::{GroupBudget, GROUP_BUDGET_SEED};
+use x402_runtime::ledger::LedgerSnapshot;
+use x402_runtime::manifest::{ManifestData, ManifestEntry, MANIFEST_SEED};
+use x402_runtime::reentrancy::REENTRANCY_GUARD_SEED;
+use x402_runtime::rent::{rent_for, rent_for_len};
+use x402_runtime::spending_cap::SPENDING_CAP_SEED;
+use x402_runtime::stats::STATS_SEED;
+use x402_runtime::{Cluster, InstructionTag, Mode, PaymentExplanation, PriceProvenance, X402Params, X402_ERRORS};
+
+pub mod migrate;
 
 declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
 
+/// A program account whose first field is a layout `version`.
+///
+/// Accounts created before the version byte existed are version 0 and are
+/// upgraded in place by the `migrate_*` instructions.
+pub trait Versioned {
+    const CURRENT_VERSION: u8;
+
+    fn version(&self) -> u8;
+}
+
+/// Rejects accounts on an older layout, which need migrating, and on a
+/// newer one, which this program does not understand.
+pub fn require_current_version<T: Versioned>(account: &T) -> Result<()> {
+    require_gte!(T::CURRENT_VERSION, account.version(), ErrorCode::UnsupportedAccountVersion);
+    require_eq!(account.version(), T::CURRENT_VERSION, ErrorCode::AccountNeedsMigration);
+    Ok(())
+}
+
+/// The version of a zero-copy account. Accounts too short for its layout
+/// predate it and need migrating; `load` would panic on them.
+pub fn zero_copy_version<T: ZeroCopy + Owner + Versioned>(account: &AccountLoader<T>) -> Result<u8> {
+    if account.as_ref().data_len() < 8 + std::mem::size_of::<T>() {
+        msg!("account of {} bytes predates the current layout", account.as_ref().data_len());
+        return err!(ErrorCode::AccountNeedsMigration);
+    }
+    Ok(account.load()?.version())
+}
+
+/// `ComputeResult::tier` of each compute instruction.
+pub const TIER_FREE: u8 = 0;
+pub const TIER_PREMIUM: u8 = 1;
+pub const TIER_STANDARD: u8 = 2;
+pub const TIER_ENTERPRISE: u8 = 3;
+
+/// Rejects `consumer` unless it is the result's owner or the consumer the
+/// owner bound it to at creation. Nobody else can spend another payer's
+/// result, even if a relayer or RPC operator sees it first.
+pub fn require_consumer(result: &ComputeResult, consumer: &Pubkey) -> Result<()> {
+    if *consumer == result.owner || result.bind_to.as_ref() == Some(consumer) {
+        return Ok(());
+    }
+    msg!("consume_result: {} may not consume a result of {}", consumer, result.owner);
+    err!(ErrorCode::ConsumerNotAuthorized)
+}
+
+/// Records one more account depending on `result`, as `consume_result`
+/// does for every consumption.
+pub fn add_dependent(result: &mut ComputeResult) -> Result<()> {
+    result.ref_count = result.ref_count.checked_add(1).ok_or_else(|| {
+        msg!("consume_result: result of {} has the most dependents it can count", result.owner);
+        error!(ErrorCode::TooManyDependents)
+    })?;
+    Ok(())
+}
+
+/// Rejects closing `result` for free while other accounts depend on it;
+/// `paid_close_result` closes it for a fee instead.
+pub fn require_no_dependents(result: &ComputeResult) -> Result<()> {
+    if result.ref_count > 0 {
+        msg!(
+            "reclaim_result: result has {} dependents; close it with paid_close_result",
+            result.ref_count
+        );
+        return err!(ErrorCode::ResultHasDependents);
+    }
+    Ok(())
+}
+
+/// Refuses an account an `init_*` instruction is about to fill in if its
+/// version is already set. `init` already refuses an existing account;
+/// this keeps the state of one that slipped past it from being reset.
+pub fn require_uninitialized<T: Versioned>(account: &T) -> Result<()> {
+    if account.version() != 0 {
+        msg!("account already initialized at version {}", account.version());
+        return err!(ErrorCode::AccountAlreadyInitialized);
+    }
+    Ok(())
+}
+
+/// Loads the `FailureLog` `report_failed_payment` appends to, and whether
+/// `init_if_needed` has just created it. A new log has no discriminator
+/// until the instruction exits and is given its version here; an existing
+/// one is loaded as it is, so its entries are never reset.
+pub fn open_failure_log<'a>(loader: &'a AccountLoader<FailureLog>) -> Result<(RefMut<'a, FailureLog>, bool)> {
+    let created = loader.as_ref().try_borrow_data()?[..8] == [0; 8];
+    let log = if created {
+        let mut log = loader.load_init()?;
+        log.version = FailureLog::CURRENT_VERSION;
+        log
+    } else {
+        zero_copy_version(loader)?;
+        loader.load_mut()?
+    };
+    require_current_version(&*log)?;
+    Ok((log, created))
+}
+
+/// Fills in a ledger `init_payment_ledger` just created for `payer`.
+/// `init` already refuses an existing account; this also refuses a ledger
+/// whose version is set, so its totals can never be reset.
+pub fn open_ledger(ledger: &mut PaymentLedger, payer: &Pubkey) -> Result<()> {
+    if ledger.version != 0 {
+        msg!("init_payment_ledger: ledger of {} already exists", ledger.payer);
+        return err!(ErrorCode::LedgerAlreadyInitialized);
+    }
+    ledger.version = PaymentLedger::CURRENT_VERSION;
+    ledger.payer = *payer;
+    Ok(())
+}
+
+/// Adds a payment of `amount` by `payer`, made at `now`, to `ledger`.
+/// `RecordPayment` already ties the ledger to the payer through its seeds
+/// and `has_one`; this repeats the check for callers outside Anchor's.
+pub fn record_into_ledger(ledger: &mut PaymentLedger, payer: &Pubkey, amount: u64, now: i64) -> Result<()> {
+    require!(amount > 0, ErrorCode::InvalidPaymentAmount);
+    require_current_version(ledger)?;
+    require_keys_eq!(ledger.payer, *payer, ErrorCode::LedgerPayerMismatch);
+
+    ledger.total_payments += 1;
+    ledger.total_amount += amount;
+    ledger.last_payment = now;
+    Ok(())
+}
+
+/// Adds `rent`, which the ledger's payer paid to create an account, to
+/// `ledger`. Rent stays with the account, so it is kept out of
+/// `total_amount`, which counts only payments.
+pub fn record_rent_into_ledger(ledger: &mut PaymentLedger, rent: u64) -> Result<()> {
+    require_current_version(ledger)?;
+    x402_runtime::rent::record(&mut ledger.total_rent_paid, rent)?;
+    Ok(())
+}
+
+/// Readies the `SnapshotState` `snapshot_ledgers` cranks into. One
+/// `init_if_needed` has just created, all zeroes, becomes an empty
+/// snapshot; an existing one, even partway through a snapshot, is left as
+/// it is.
+pub fn open_snapshot(state: &mut SnapshotState) -> Result<()> {
+    if state.version == 0 {
+        *state = SnapshotState::empty();
+    }
+    require_current_version(state)
+}
+
+/// Adds `payer`'s ledger to the snapshot `state` is building, as the next
+/// leaf. Ledgers go in ascending payer order, across batches too, so each
+/// is in a snapshot once and the off-chain tree orders them the same way.
+pub fn snapshot_ledger(state: &mut SnapshotState, payer: &Pubkey, ledger: &PaymentLedger) -> Result<()> {
+    require_current_version(ledger)?;
+    require_keys_eq!(ledger.payer, *payer, ErrorCode::SnapshotBatchMismatch);
+    if state.leaves > 0 && *payer <= state.last_payer {
+        msg!("snapshot_ledgers: ledger of {} does not follow that of {}", payer, state.last_payer);
+        return err!(ErrorCode::SnapshotOutOfOrder);
+    }
+
+    let leaf = snapshot::leaf_hash(payer, ledger.total_payments, ledger.total_amount);
+    snapshot::append(&mut state.frontier, state.leaves, leaf).map_err(|_| error!(ErrorCode::SnapshotFull))?;
+    state.leaves += 1;
+    state.last_payer = *payer;
+    state.total_amount = state.total_amount.checked_add(ledger.total_amount).ok_or(ErrorCode::SnapshotFull)?;
+    Ok(())
+}
+
+/// The event finalizing the snapshot `state` has built, labelled `epoch`,
+/// leaving `state` empty for the next one.
+pub fn finish_snapshot(state: &mut SnapshotState, epoch: u64, slot: u64) -> LedgerSnapshotEvent {
+    let event = LedgerSnapshotEvent {
+        epoch,
+        root: snapshot::frontier_root(&state.frontier, state.leaves),
+        ledgers: state.leaves,
+        total_amount: state.total_amount,
+        slot,
+    };
+    *state = SnapshotState::empty();
+    event
+}
+
+/// Every this many payments a payer's ledger records, the compute result
+/// they pay for is worth double.
+pub const LOYALTY_BONUS_EVERY: u64 = 10;
+
+/// `value` of a compute result, doubled when `ledger`, the payer's history
+/// including this payment, completes another `LOYALTY_BONUS_EVERY`
+/// payments. Payers calling without their ledger get no bonus.
+pub fn with_loyalty_bonus(value: u64, ledger: Option<&LedgerSnapshot>) -> u64 {
+    match ledger {
+        Some(ledger) if ledger.total_payments % LOYALTY_BONUS_EVERY == 0 => value * 2,
+        _ => value,
+    }
+}
+
+/// Fails a compute call whose result address is already in use, saying
+/// why: `ResultAlreadyExists` when it holds the payer's result, so an
+/// earlier attempt with the same nonce succeeded and the retry must not
+/// pay again, and `NotResultOwner` when it holds anything else. The
+/// payment of the failed call reverts with it.
+pub fn require_fresh_result(result: &AccountInfo, payer: &Pubkey) -> Result<()> {
+    if result.owner == &system_program::ID && result.data_is_empty() {
+        return Ok(());
+    }
+    let data = result.try_borrow_data()?;
+    match ComputeResult::try_deserialize(&mut &data[..]) {
+        Ok(existing) if result.owner == &crate::ID && existing.owner == *payer => {
+            msg!("compute: result {} already exists (paid: {})", result.key, existing.paid);
+            err!(ErrorCode::ResultAlreadyExists)
+        }
+        _ => {
+            msg!("compute: result address {} holds an account not owned by {}", result.key, payer);
+            err!(ErrorCode::NotResultOwner)
+        }
+    }
+}
+
+/// Creates the result PDA signed for by `seeds` and writes `value` to it,
+/// with rent from `payer`, and returns the rent `payer` paid. Lamports
+/// already sent to the address are kept, so nobody can block a result by
+/// funding its address first, and the payer pays only the rest.
+fn create_result<'info>(
+    result: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    seeds: &[&[u8]],
+    value: &ComputeResult,
+) -> Result<u64> {
+    let space = 8 + ComputeResult::INIT_SPACE;
+    let rent = rent_for::<ComputeResult>()?;
+    let signer = &[seeds];
+    let paid = rent.saturating_sub(result.lamports());
+
+    if result.lamports() == 0 {
+        system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: result.clone(),
+                },
+                signer,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        if paid > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: result.clone(),
+                    },
+                ),
+                paid,
+            )?;
+        }
+        system_program::allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Allocate {
+                    account_to_allocate: result.clone(),
+                },
+                signer,
+            ),
+            space as u64,
+        )?;
+        system_program::assign(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::Assign {
+                    account_to_assign: result.clone(),
+                },
+                signer,
+            ),
+            &crate::ID,
+        )?;
+    }
+
+    let mut data = result.try_borrow_mut_data()?;
+    value.try_serialize(&mut &mut data[..])?;
+    Ok(paid)
+}
+
+/// Rejects results not produced by a paid call priced at `min_price` or
+/// more. Instructions that consume results call this before trusting them.
+pub fn require_paid_result(result: &ComputeResult, min_price: u64) -> Result<()> {
+    require_current_version(result)?;
+    require!(result.paid, ErrorCode::ResultNotPaid);
+    require_gte!(result.price_paid, min_price, ErrorCode::ResultPriceTooLow);
+    Ok(())
+}
+
+pub use x402_common::seeds::{FREE_TIER_CONFIG_SEED, USAGE_COUNTER_SEED};
+
+/// Cooldown between free computes of one payer until the authority tunes it.
+pub const DEFAULT_FREE_COOLDOWN_SECS: i64 = 300;
+
+/// Spends one free compute of `counter`'s payer at `now`, or fails with
+/// `FreeTierExhausted` if the free tier is disabled or the payer's last
+/// free compute was less than `cooldown_secs` ago.
+pub fn use_free_tier(config: &FreeTierConfig, counter: &mut UsageCounter, now: i64) -> Result<()> {
+    require_current_version(config)?;
+    require_current_version(counter)?;
+    if !config.enabled {
+        msg!("free_compute: the free tier is disabled");
+        return err!(ErrorCode::FreeTierExhausted);
+    }
+    if counter.uses > 0 {
+        let next = counter.last_used.saturating_add(config.cooldown_secs);
+        if now < next {
+            msg!("free_compute: next free compute at {}, now {}", next, now);
+            return err!(ErrorCode::FreeTierExhausted);
+        }
+    }
+
+    counter.last_used = now;
+    counter.uses = counter.uses.saturating_add(1);
+    Ok(())
+}
+
+/// Limits `spending_cap` to `cap` per period of `period_secs`, the first
+/// starting now with nothing spent. `instruction` names the caller in logs.
+fn start_spending_period(spending_cap: &mut SpendingCap, cap: u64, period_secs: i64, instruction: &str) -> Result<()> {
+    require!(period_secs > 0 && period_secs <= MAX_HORIZON_SECS, ErrorCode::InvalidSpendingPeriod);
+
+    spending_cap.cap = cap;
+    spending_cap.period_secs = period_secs;
+    spending_cap.period_start =
+        clock::now().inspect_err(|_| msg!("{}: Clock sysvar unavailable while starting the period", instruction))?;
+    spending_cap.spent = 0;
+    Ok(())
+}
+
+/// What a call priced at `params` with `surcharge` costs at the level held
+/// by `congestion`: the amount the `#[x402]` check enforces and the
+/// `quote_priced_query` view returns.
+pub fn quote_price(params: &X402Params, surcharge: &CongestionSurcharge, congestion: &CongestionLevel) -> Result<u64> {
+    surcharge.apply(params, congestion.level).map(|params| params.price)
+}
+
+/// Moves `congestion` to `level` at `now`, keeping the level it replaces
+/// so `price_grace_window_secs` can still accept its price for a while.
+pub fn change_congestion_level(congestion: &mut CongestionLevel, level: u8, now: i64) -> Result<()> {
+    require_gte!(MAX_CONGESTION_LEVEL, level, ErrorCode::InvalidCongestionLevel);
+    congestion.previous_level = congestion.level;
+    congestion.level = level;
+    congestion.updated_at = now;
+    Ok(())
+}
+
+/// Ends `experiment` on its `winner` arm, which every payer is charged from
+/// then on. An experiment over `arms` prices has arms `0..arms`.
+pub fn end_experiment(experiment: &mut PriceExperiment, winner: u8, arms: usize) -> Result<()> {
+    require_gt!(arms, winner as usize, ErrorCode::InvalidExperimentWinner);
+    experiment.ended = true;
+    experiment.winner = winner;
+    Ok(())
+}
+
+/// Moves `cursor` to `new_sequence`, which must be past its current
+/// position and no later than `latest`, the sequence of the most recent
+/// payment. A cursor never moves back, so an indexer that restarts from it
+/// never exports a payment twice.
+pub fn advance_indexer_cursor(cursor: &mut IndexerCursor, new_sequence: u64, latest: u64) -> Result<()> {
+    require_gt!(new_sequence, cursor.last_sequence, ErrorCode::CursorNotAdvanced);
+    require_gte!(latest, new_sequence, ErrorCode::CursorPastSequence);
+    cursor.last_sequence = new_sequence;
+    Ok(())
+}
+
+/// Priced instructions published in the `X402Manifest`, built from the same
+/// constants the `#[x402]` checks enforce. `treasury_query` and
+/// `paid_close_result` are left out: their recipient lives in
+/// `X402GlobalConfig`, not in a constant. So are `deposit_query` and
+/// `savings_query`, which are paid from a deposit and from accumulated
+/// parts rather than by a transfer, and `experiment_query`, whose price
+/// depends on the payer.
+pub fn manifest_entries() -> Vec<ManifestEntry> {
+    vec![
+        ManifestEntry::new(
+            instruction::ComputePremium::DISCRIMINATOR,
+            &x402_example::COMPUTE_PREMIUM_X402_PARAMS,
+        ),
+        ManifestEntry::new(
+            instruction::ComputeStandard::DISCRIMINATOR,
+            &x402_example::COMPUTE_STANDARD_X402_PARAMS,
+        ),
+        ManifestEntry::new(
+            instruction::ComputeEnterprise::DISCRIMINATOR,
+            &x402_example::COMPUTE_ENTERPRISE_X402_PARAMS,
+        ),
+        ManifestEntry::new(
+            instruction::PricedQuery::DISCRIMINATOR,
+            &x402_example::PRICED_QUERY_X402_PARAMS,
+        ),
+    ]
+}
+
+/// What `pay_and_invoke` must pay before invoking the instruction whose
+/// data is `inner_data`: the compute tiers' prices, and nothing else may
+/// be invoked. `priced_query`, `treasury_query` and `paid_close_result`
+/// are left out, since their prices depend on accounts the trampoline does
+/// not read.
+pub fn trampoline_params(inner_data: &[u8]) -> Result<X402Params> {
+    let allowlist = [
+        (
+            instruction::ComputePremium::DISCRIMINATOR,
+            x402_example::COMPUTE_PREMIUM_X402_PARAMS,
+        ),
+        (
+            instruction::ComputeStandard::DISCRIMINATOR,
+            x402_example::COMPUTE_STANDARD_X402_PARAMS,
+        ),
+        (
+            instruction::ComputeEnterprise::DISCRIMINATOR,
+            x402_example::COMPUTE_ENTERPRISE_X402_PARAMS,
+        ),
+    ];
+    allowlist
+        .iter()
+        .find(|(discriminator, _)| inner_data.starts_with(discriminator))
+        .map(|(_, params)| *params)
+        .ok_or_else(|| {
+            msg!("pay_and_invoke: the inner instruction is not allowlisted");
+            error!(ErrorCode::InstructionNotAllowlisted)
+        })
+}
+
+/// The requirements of the `accumulated = true` instruction tagged `tag`,
+/// toward which `accumulate_payment` adds transfers: `savings_query`'s,
+/// and no other.
+pub fn accumulated_params(tag: &str) -> Result<X402Params> {
+    let accumulated = [(
+        x402_example::SAVINGS_QUERY_X402_TAG,
+        x402_example::SAVINGS_QUERY_X402_PARAMS,
+    )];
+    accumulated
+        .iter()
+        .find(|(accumulated_tag, _)| *accumulated_tag == tag)
+        .map(|(_, params)| *params)
+        .ok_or_else(|| {
+            msg!("accumulate_payment: no instruction tagged \"{}\" is paid by accumulation", tag);
+            error!(ErrorCode::NotAccumulated)
+        })
+}
+
+/// Tag of every priced instruction, with its current name, published
+/// alongside `manifest_entries` so off-chain code keyed by tag can find
+/// the instruction to call.
+pub fn tags() -> &'static [InstructionTag] {
+    &x402_example::X402_TAGS
+}
+
+/// `priced_query`'s `price_grace_window_secs`, which `explain_payment`
+/// prices it with.
+pub const PRICED_QUERY_GRACE_WINDOW_SECS: u32 = 30;
+
+/// What a call of the instruction tagged `tag` by `payer` requires at
+/// `now`, priced as its `#[x402]` check prices it from the accounts
+/// passed, with `paid` left unset. An instruction priced from an account
+/// not passed fails with `PricingAccountMissing`, as the instruction would
+/// fail without it; one whose recipient lives in the config fails with
+/// `ConfigNotInitialized` without one.
+pub fn payment_requirements(
+    tag: &str,
+    payer: &Pubkey,
+    config: Option<&X402GlobalConfig>,
+    congestion: Option<(&Pubkey, &CongestionLevel)>,
+    experiment: Option<(&Pubkey, &PriceExperiment)>,
+    now: i64,
+) -> Result<PaymentExplanation> {
+    use crate::x402_example::*;
+
+    let explained = |params: X402Params, mode: Mode, provenance: PriceProvenance| PaymentExplanation {
+        price: params.price,
+        recipient: params.recipient,
+        currency: params.currency,
+        mode,
+        provenance,
+        paid: None,
+    };
+    let configured = |params: X402Params| -> Result<X402Params> {
+        let recipient = x402_runtime::config::configured_recipient(config.map(|config| config.recipient))?;
+        Ok(X402Params { recipient, ..params })
+    };
+    let missing = |account: &str| {
+        msg!("explain_payment: \"{}\" is priced from the {} account", tag, account);
+        error!(ErrorCode::PricingAccountMissing)
+    };
+
+    let explanation = match tag {
+        COMPUTE_PREMIUM_X402_TAG => {
+            explained(COMPUTE_PREMIUM_X402_PARAMS, COMPUTE_PREMIUM_X402_MODE, PriceProvenance::Static)
+        }
+        COMPUTE_STANDARD_X402_TAG => {
+            explained(COMPUTE_STANDARD_X402_PARAMS, COMPUTE_STANDARD_X402_MODE, PriceProvenance::Static)
+        }
+        COMPUTE_ENTERPRISE_X402_TAG => {
+            explained(COMPUTE_ENTERPRISE_X402_PARAMS, COMPUTE_ENTERPRISE_X402_MODE, PriceProvenance::Static)
+        }
+        PRICED_QUERY_X402_TAG => {
+            let (account, congestion) = congestion.ok_or_else(|| missing("congestion"))?;
+            let change = LevelChange {
+                previous_level: congestion.previous_level,
+                updated_at: congestion.updated_at,
+            };
+            let params = PRICED_QUERY_X402_SURCHARGE.apply_with_window_at(
+                &PRICED_QUERY_X402_PARAMS,
+                account,
+                congestion.level,
+                change,
+                now,
+                PRICED_QUERY_GRACE_WINDOW_SECS,
+            )?;
+            let provenance = PRICED_QUERY_X402_SURCHARGE.provenance(
+                &PRICED_QUERY_X402_PARAMS,
+                &params,
+                account,
+                congestion.level,
+                change,
+            );
+            explained(params, PRICED_QUERY_X402_MODE, provenance)
+        }
+        TREASURY_QUERY_X402_TAG => {
+            explained(configured(TREASURY_QUERY_X402_PARAMS)?, TREASURY_QUERY_X402_MODE, PriceProvenance::Static)
+        }
+        PAID_CLOSE_RESULT_X402_TAG => explained(
+            configured(PAID_CLOSE_RESULT_X402_PARAMS)?,
+            PAID_CLOSE_RESULT_X402_MODE,
+            PriceProvenance::Static,
+        ),
+        DEPOSIT_QUERY_X402_TAG => {
+            explained(DEPOSIT_QUERY_X402_PARAMS, DEPOSIT_QUERY_X402_MODE, PriceProvenance::Static)
+        }
+        EXPERIMENT_QUERY_X402_TAG => {
+            let (account, experiment) = experiment.ok_or_else(|| missing("experiment"))?;
+            let state = ExperimentState {
+                ended: experiment.ended,
+                winner: experiment.winner,
+            };
+            let (params, arm) = EXPERIMENT_QUERY_X402_EXPERIMENT.apply_from(
+                &EXPERIMENT_QUERY_X402_PARAMS,
+                account,
+                &crate::ID,
+                payer,
+                state,
+            )?;
+            let provenance = PriceProvenance::Experiment { account: *account, arm };
+            explained(params, EXPERIMENT_QUERY_X402_MODE, provenance)
+        }
+        SAVINGS_QUERY_X402_TAG => {
+            explained(SAVINGS_QUERY_X402_PARAMS, SAVINGS_QUERY_X402_MODE, PriceProvenance::Static)
+        }
+        _ => {
+            msg!("explain_payment: no priced instruction is tagged \"{}\"", tag);
+            return err!(ErrorCode::UnknownTag);
+        }
+    };
+    Ok(explanation)
+}
+
+#[x402_program]
 #[program]
 pub mod x402_example {
     use super::*;
 
-    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
-    pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
-        ctx.accounts.result.owner = ctx.accounts.payer.key();
-        ctx.accounts.result.value = 42;
-        ctx.accounts.result.paid = true;
+    #[x402_tiers(
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        premium = 1_000_000,
+        standard = 5_000_000,
+        enterprise = 50_000_000,
+        respect_spending_cap = true,
+        track_stats = true,
+        commitment_log = true,
+        record_fingerprint = true,
+        allow_trampoline = true,
+        ledger = true,
+        record_provenance = true,
+        group = "compute",
+        group_cap = 50_000_000,
+        tags(premium = "premium_v1", standard = "standard_v1", enterprise = "enterprise_v1"),
+    )]
+    pub fn compute(ctx: Context<PremiumCompute>, bind_to: Option<Pubkey>, nonce: u64) -> Result<()> {
+        let (value, tier_tag, price) = match tier {
+            "premium" => (42, TIER_PREMIUM, COMPUTE_PREMIUM_X402_PARAMS.price),
+            "standard" => (100, TIER_STANDARD, COMPUTE_STANDARD_X402_PARAMS.price),
+            _ => (1000, TIER_ENTERPRISE, COMPUTE_ENTERPRISE_X402_PARAMS.price),
+        };
+        let value = with_loyalty_bonus(value, x402_ledger.as_ref());
+        let payer = ctx.accounts.payer.key();
+        require_fresh_result(&ctx.accounts.result, &payer)?;
+
+        let clock = clock::get()
+            .inspect_err(|_| msg!("compute: Clock sysvar unavailable while timestamping the result"))?;
+        let result = ComputeResult {
+            version: ComputeResult::CURRENT_VERSION,
+            owner: payer,
+            value,
+            paid: true,
+            amount_paid: x402_payment.amount,
+            tier: tier_tag,
+            price_paid: price,
+            created_slot: clock.slot,
+            bind_to,
+            fingerprint: x402_fingerprint,
+            ref_count: 0,
+            provenance: x402_provenance,
+        };
+        let nonce = nonce.to_le_bytes();
+        let seeds: &[&[u8]] = &[COMPUTE_RESULT_SEED, payer.as_ref(), &nonce, &[ctx.bumps.result]];
+        let rent = create_result(
+            &ctx.accounts.result,
+            &ctx.accounts.payer,
+            &ctx.accounts.system_program,
+            seeds,
+            &result,
+        )?;
+        // The payment is already in `total_amount`; the result's rent is
+        // not revenue, so it is counted apart.
+        if let Some(ledger) = ctx.accounts.x402_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent)?;
+        }
+        x402_runtime::rent::record(&mut ctx.accounts.x402_stats.load_mut()?.total_rent_paid, rent)?;
 
         emit!(ComputeEvent {
-            payer: ctx.accounts.payer.key(),
-            result: 42,
-            timestamp: Clock::get()?.unix_timestamp,
+            payer,
+            result: value,
+            timestamp: clock.unix_timestamp,
         });
 
         Ok(())
     }
 
-    #[x402(price = 5_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
-    pub fn standard_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    /// Pays `amount` to the recipient and invokes a compute tier with
+    /// `inner_data` in one instruction, for wallets that sign only one.
+    /// Pass the tier's accounts, in its order, as remaining accounts. The
+    /// tier checks that it was invoked this way, at this price, instead of
+    /// after a transfer; it must itself be the transaction's instruction.
+    pub fn pay_and_invoke<'info>(
+        ctx: Context<'_, '_, 'info, 'info, PayAndInvoke<'info>>,
+        amount: u64,
+        inner_data: Vec<u8>,
+    ) -> Result<()> {
+        x402_runtime::trampoline::require_top_level()?;
+        let params = trampoline_params(&inner_data)?;
+        require_keys_eq!(
+            ctx.accounts.recipient.key(),
+            params.recipient,
+            ErrorCode::InvalidPaymentRecipient
+        );
+        require_gte!(amount, params.price, ErrorCode::InsufficientPayment);
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.recipient.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        let inner = Instruction {
+            program_id: crate::ID,
+            accounts: ctx
+                .remaining_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.key(),
+                    is_signer: account.is_signer,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data: inner_data,
+        };
+        let mut infos = ctx.remaining_accounts.to_vec();
+        infos.push(ctx.accounts.this_program.to_account_info());
+        invoke(&inner, &infos)?;
+
+        Ok(())
+    }
+
+    /// Pay-to-query: answers from return data instead of persisting a
+    /// `ComputeResult`, so the caller pays no rent. Clients can preview it
+    /// with `x402_client::simulate_priced_query`. The price carries a 5%
+    /// surcharge per `CongestionLevel` level, as `quote_priced_query`
+    /// reports; for 30 seconds after the level changes, the price at the
+    /// previous level is still accepted if it is lower.
+    #[x402(
+        price = 200_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+        price_grace_window_secs = 30,
+        tag = "priced_query",
+    )]
+    pub fn priced_query(ctx: Context<PricedQuery>) -> Result<u64> {
+        msg!("priced_query: answering {}", ctx.accounts.payer.key());
+        Ok(42)
+    }
+
+    /// The price `priced_query` currently enforces, read from the same
+    /// `CongestionLevel`; simulate it to quote a call before paying.
+    pub fn quote_priced_query(ctx: Context<QuotePrice>) -> Result<u64> {
+        quote_price(
+            &PRICED_QUERY_X402_PARAMS,
+            &PRICED_QUERY_X402_SURCHARGE,
+            &ctx.accounts.x402_congestion,
+        )
+    }
+
+    /// What a call of the priced instruction tagged `tag` pays, returned
+    /// instead of making it, for wallets to insert into a simulation and
+    /// show before signing. The price is read from the accounts the
+    /// instruction reads, passed here under the same names, and a
+    /// preceding transfer is verified as the instruction verifies it, with
+    /// the instructions sysvar in remaining accounts; a transaction that
+    /// would not pay the call fails here with the same error. A transfer
+    /// covered by a compute group's budget is not recognised. Changes
+    /// nothing.
+    pub fn explain_payment(ctx: Context<ExplainPayment>, tag: String) -> Result<PaymentExplanation> {
+        let now = clock::now().inspect_err(|_| msg!("explain_payment: Clock sysvar unavailable"))?;
+        let accounts = &ctx.accounts;
+        let congestion = accounts.x402_congestion.as_ref().map(|congestion| (congestion.key(), &**congestion));
+        let experiment = accounts.x402_experiment.as_ref().map(|experiment| (experiment.key(), &**experiment));
+        let mut explanation = payment_requirements(
+            &tag,
+            &accounts.payer.key(),
+            accounts.x402_config.as_deref(),
+            congestion.as_ref().map(|(key, congestion)| (key, *congestion)),
+            experiment.as_ref().map(|(key, experiment)| (key, *experiment)),
+            now,
+        )?;
+        if explanation.mode == Mode::PrecedingInstruction {
+            let payment = x402_runtime::verify_payment(ctx.remaining_accounts, ctx.program_id, &explanation.params())?;
+            explanation.paid = Some(payment.amount);
+        }
+
+        Ok(explanation)
+    }
+
+    /// Like `priced_query`, but paid to the recipient in `X402GlobalConfig`
+    /// rather than a hard-coded address.
+    #[x402(price = 200_000, tag = "treasury_query")]
+    pub fn treasury_query(ctx: Context<TreasuryQuery>) -> Result<u64> {
+        msg!("treasury_query: answering {}", ctx.accounts.payer.key());
+        Ok(42)
+    }
+
+    /// Like `priced_query`, but paid from the payer's `DepositAccount`, so
+    /// the transaction needs no transfer. Other programs may call it, but
+    /// not from within another call of it.
+    #[x402(
+        price = 100_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        deposit = true,
+        reentrancy_guard = true,
+        tag = "deposit_query",
+    )]
+    pub fn deposit_query(ctx: Context<DepositQuery>) -> Result<u64> {
+        msg!("deposit_query: answering {}", ctx.accounts.payer.key());
+        Ok(42)
+    }
+
+    /// Like `priced_query`, at 300K or 250K lamports depending on the
+    /// payer's bucket in the `query_price_v1` experiment, until the
+    /// authority ends it with `end_experiment`.
+    #[x402(
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        experiment(bucket_seed = "query_price_v1", prices(300_000, 250_000)),
+        tag = "experiment_query",
+    )]
+    pub fn experiment_query(ctx: Context<ExperimentQuery>) -> Result<u64> {
+        msg!("experiment_query: answering {}", ctx.accounts.payer.key());
+        Ok(42)
+    }
+
+    /// Like `priced_query`, at 1M lamports paid in as many parts as the
+    /// payer likes: each transfer, followed by `accumulate_payment`, adds
+    /// to the payer's `Accumulator` for this instruction, and a call is
+    /// answered once it holds the price, which the call spends.
+    #[x402(
+        price = 1_000_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        accumulated = true,
+        tag = "savings_query",
+    )]
+    pub fn savings_query(ctx: Context<SavingsQuery>) -> Result<u64> {
+        msg!("savings_query: answering {}", ctx.accounts.payer.key());
+        Ok(42)
+    }
+
+    /// Creates the signer's `UsageCounter`. Run once per payer before
+    /// their first `free_compute`, in the same transaction if need be.
+    pub fn init_usage_counter(ctx: Context<InitUsageCounter>) -> Result<()> {
+        let counter = &mut ctx.accounts.usage_counter;
+        require_uninitialized(&**counter)?;
+        counter.version = UsageCounter::CURRENT_VERSION;
+        counter.payer = ctx.accounts.payer.key();
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<UsageCounter>()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Rate-limited by the payer's `UsageCounter` under `FreeTierConfig`,
+    /// so results cannot be created without bound for free.
+    ///
+    /// Breaking change: the counter used to be created here on first use
+    /// (`init_if_needed`). Clients now call `init_usage_counter` first.
+    pub fn free_compute(ctx: Context<FreeCompute>) -> Result<()> {
+        let clock = clock::get()
+            .inspect_err(|_| msg!("free_compute: Clock sysvar unavailable while checking the cooldown"))?;
+
+        use_free_tier(&ctx.accounts.free_tier_config, &mut ctx.accounts.usage_counter, clock.unix_timestamp)?;
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<ComputeResult>()?)?;
+        }
+
+        ctx.accounts.result.version = ComputeResult::CURRENT_VERSION;
         ctx.accounts.result.owner = ctx.accounts.payer.key();
-        ctx.accounts.result.value = 100;
-        ctx.accounts.result.paid = true;
+        ctx.accounts.result.value = 0;
+        ctx.accounts.result.paid = false;
+        ctx.accounts.result.amount_paid = 0;
+        ctx.accounts.result.tier = TIER_FREE;
+        ctx.accounts.result.price_paid = 0;
+        ctx.accounts.result.bind_to = None;
+        ctx.accounts.result.fingerprint = [0; 32];
+        ctx.accounts.result.ref_count = 0;
+        ctx.accounts.result.provenance = PriceProvenance::Static;
+        ctx.accounts.result.created_slot = clock.slot;
 
-        emit!(ComputeEvent {
-            payer: ctx.accounts.payer.key(),
-            result: 100,
-            timestamp: Clock::get()?.unix_timestamp,
+        Ok(())
+    }
+
+    /// Creates the `FreeTierConfig`, enabled with the default cooldown.
+    /// Run once at deploy time by the program's upgrade authority.
+    pub fn initialize_free_tier(ctx: Context<InitializeFreeTier>, authority: Pubkey) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        *ctx.accounts.free_tier_config = FreeTierConfig::new(authority);
+
+        Ok(())
+    }
+
+    /// Enables or disables the free tier and sets its per-payer cooldown.
+    pub fn set_free_tier(ctx: Context<SetFreeTier>, enabled: bool, cooldown_secs: i64) -> Result<()> {
+        require_gte!(cooldown_secs, 0, ErrorCode::InvalidFreeTierCooldown);
+        require_gte!(MAX_HORIZON_SECS, cooldown_secs, ErrorCode::InvalidFreeTierCooldown);
+
+        let config = &mut ctx.accounts.free_tier_config;
+        config.enabled = enabled;
+        config.cooldown_secs = cooldown_secs;
+
+        Ok(())
+    }
+
+    /// Creates the `CongestionLevel` at level 0. Run once at deploy time by
+    /// the program's upgrade authority.
+    pub fn initialize_congestion(ctx: Context<InitializeCongestion>, authority: Pubkey) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        *ctx.accounts.x402_congestion = CongestionLevel::new(authority);
+
+        Ok(())
+    }
+
+    /// Starts `experiment_query`'s price experiment. Run once at deploy
+    /// time by the program's upgrade authority.
+    pub fn initialize_experiment(ctx: Context<InitializeExperiment>, authority: Pubkey) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        *ctx.accounts.x402_experiment = PriceExperiment::new(authority);
+
+        Ok(())
+    }
+
+    /// Ends `experiment_query`'s price experiment: every payer pays the
+    /// `winner` arm's price from the next call.
+    pub fn end_experiment(ctx: Context<EndExperiment>, winner: u8) -> Result<()> {
+        super::end_experiment(
+            &mut ctx.accounts.x402_experiment,
+            winner,
+            EXPERIMENT_QUERY_X402_EXPERIMENT.prices.len(),
+        )
+    }
+
+    /// Sets the congestion level; surcharged prices follow from the next
+    /// call, within `priced_query`'s grace window.
+    pub fn set_congestion_level(ctx: Context<SetCongestionLevel>, level: u8) -> Result<()> {
+        let now = clock::now()
+            .inspect_err(|_| msg!("set_congestion_level: Clock sysvar unavailable while recording the change"))?;
+        change_congestion_level(&mut ctx.accounts.x402_congestion, level, now)
+    }
+
+    /// Consumes a result produced by a call priced at `min_price` or more,
+    /// recording the consumer as one more dependent of the result, which
+    /// stays open. Only the owner, or the consumer the result was bound
+    /// to, may sign. The latency event spans the paid call that created
+    /// the result and this one.
+    pub fn consume_result(ctx: Context<ConsumeResult>, min_price: u64) -> Result<()> {
+        require_consumer(&ctx.accounts.result, &ctx.accounts.consumer.key())?;
+        require_paid_result(&ctx.accounts.result, min_price)?;
+        add_dependent(&mut ctx.accounts.result)?;
+        msg!(
+            "consume_result: consumed tier {} result {}",
+            ctx.accounts.result.tier,
+            ctx.accounts.result.value
+        );
+        x402_runtime::emit_payment_latency(&ctx.accounts.result.owner, 0, Some(ctx.accounts.result.created_slot))
+    }
+
+    /// Records, in the `FailureLog`, an attempt of the signer's that an
+    /// x402 check rejected. The rejected transaction reverted, so clients
+    /// report it in a follow-up one. Anyone may call it, but only about
+    /// their own payments. `reason` indexes `x402_runtime::X402_ERRORS`.
+    pub fn report_failed_payment(ctx: Context<ReportFailedPayment>, reason: u8, attempted_amount: u64) -> Result<()> {
+        require!((reason as usize) < X402_ERRORS.len(), ErrorCode::InvalidFailureReason);
+
+        let (mut log, created) = open_failure_log(&ctx.accounts.failure_log)?;
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut().filter(|_| created) {
+            record_rent_into_ledger(ledger, rent_for_len(8 + std::mem::size_of::<FailureLog>())?)?;
+        }
+
+        let slot = clock::slot()
+            .inspect_err(|_| msg!("report_failed_payment: Clock sysvar unavailable while recording the failure"))?;
+        let entry = FailureEntry::new(ctx.accounts.payer.key(), reason, attempted_amount, slot);
+        log.ring.push(entry);
+
+        emit!(PaymentFailedEvent {
+            payer: entry.payer,
+            reason: entry.reason,
+            attempted_amount: entry.attempted_amount,
+            slot: entry.slot,
         });
 
         Ok(())
     }
 
-    #[x402(price = 50_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
-    pub fn enterprise_compute(ctx: Context<PremiumCompute>) -> Result<()> {
-        ctx.accounts.result.owner = ctx.accounts.payer.key();
-        ctx.accounts.result.value = 1000;
-        ctx.accounts.result.paid = true;
+    /// Closes one of the owner's unconsumed results, paid or free, and
+    /// returns its rent. Results never expire, so the owner can do this at
+    /// any time, unless the result has dependents.
+    pub fn reclaim_result(ctx: Context<ReclaimResult>) -> Result<()> {
+        require_no_dependents(&ctx.accounts.result)?;
+        msg!(
+            "reclaim_result: reclaimed unconsumed tier {} result of {}",
+            ctx.accounts.result.tier,
+            ctx.accounts.owner.key()
+        );
+        Ok(())
+    }
 
-        emit!(ComputeEvent {
+    /// Closes a result that other accounts depend on, which
+    /// `reclaim_result` refuses, for a fee to the recipient in
+    /// `X402GlobalConfig`. The rent still goes to the owner; the fee only
+    /// makes deleting shared data cost something.
+    #[x402(price = 100_000, tag = "paid_close_v1")]
+    pub fn paid_close_result(ctx: Context<PaidCloseResult>) -> Result<()> {
+        msg!(
+            "paid_close_result: closed tier {} result with {} dependents",
+            ctx.accounts.result.tier,
+            ctx.accounts.result.ref_count
+        );
+        Ok(())
+    }
+
+    pub fn verify_payment(ctx: Context<VerifyPayment>) -> Result<()> {
+        let required_lamports = 1_000_000u64;
+
+        if ctx.accounts.payer.lamports() < required_lamports {
+            return Err(error!(ErrorCode::InsufficientPayment));
+        }
+
+        Ok(())
+    }
+    /// Creates the signer's `PaymentLedger`. Run once per payer before
+    /// their first `record_payment`, in the same transaction if need be.
+    pub fn init_payment_ledger(ctx: Context<InitPaymentLedger>) -> Result<()> {
+        let ledger = &mut ctx.accounts.payment_ledger;
+        open_ledger(ledger, &ctx.accounts.payer.key())?;
+        record_rent_into_ledger(ledger, rent_for::<PaymentLedger>()?)
+    }
+
+    /// Adds a payment to the signer's existing ledger.
+    ///
+    /// Breaking change: the ledger used to be created here on first use
+    /// (`init_if_needed`), which would let a reinitialization reset it once
+    /// its layout gains authority-like fields. Clients now call
+    /// `init_payment_ledger` first. The accounts they already pass (payer,
+    /// ledger, system program) are still accepted; the system program is
+    /// just no longer read.
+    pub fn record_payment(ctx: Context<RecordPayment>, amount: u64) -> Result<()> {
+        let now = clock::now()
+            .inspect_err(|_| msg!("record_payment: Clock sysvar unavailable while updating the ledger"))?;
+        record_into_ledger(&mut ctx.accounts.payment_ledger, &ctx.accounts.payer.key(), amount, now)?;
+
+        emit!(PaymentRecordedEvent {
             payer: ctx.accounts.payer.key(),
-            result: 1000,
-            timestamp: Clock::get()?.unix_timestamp,
+            amount,
+            total_payments: ctx.accounts.payment_ledger.total_payments,
         });
 
-        Ok(())
-    }
-    pub fn free_compute(ctx: Context<FreeCompute>) -> Result<()> {
-        ctx.accounts.result.owner = ctx.accounts.payer.key();
-        ctx.accounts.result.value = 0;
-        ctx.accounts.result.paid = false;
+        Ok(())
+    }
+
+    /// Adds the `PaymentLedger`s passed as remaining accounts, those of the
+    /// payers in `batch` in the same order, to the snapshot being built.
+    /// The operator cranks every ledger through in ascending payer order,
+    /// as many batches as it takes, then the authority finalizes it.
+    pub fn snapshot_ledgers<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SnapshotLedgers<'info>>,
+        batch: Vec<Pubkey>,
+    ) -> Result<()> {
+        ctx.accounts.x402_config.admin().require_operator(&ctx.accounts.signer)?;
+        require_eq!(batch.len(), ctx.remaining_accounts.len(), ErrorCode::SnapshotBatchMismatch);
+
+        let state: &mut SnapshotState = &mut ctx.accounts.snapshot_state;
+        open_snapshot(state)?;
+        for (payer, info) in batch.iter().zip(ctx.remaining_accounts) {
+            // Checks the owner and discriminator: only this program
+            // creates ledgers, each at its payer's address.
+            let ledger = Account::<PaymentLedger>::try_from(info)?;
+            snapshot_ledger(state, payer, &ledger)?;
+        }
+        msg!("snapshot_ledgers: {} ledgers so far", state.leaves);
+
+        Ok(())
+    }
+
+    /// Emits the root of the ledgers snapshotted since the last snapshot,
+    /// labelled `epoch`, and starts the next one.
+    pub fn finalize_snapshot(ctx: Context<FinalizeSnapshot>, epoch: u64) -> Result<()> {
+        ctx.accounts.x402_config.admin().require_authority(&ctx.accounts.signer)?;
+        let slot = Clock::get()?.slot;
+        emit!(finish_snapshot(&mut ctx.accounts.snapshot_state, epoch, slot));
+
+        Ok(())
+    }
+
+    pub fn publish_manifest(ctx: Context<PublishManifest>) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        *ctx.accounts.manifest = X402Manifest::new(ctx.accounts.authority.key());
+
+        Ok(())
+    }
+
+    pub fn update_manifest(ctx: Context<UpdateManifest>) -> Result<()> {
+        let manifest = &mut ctx.accounts.manifest;
+        manifest.manifest_version = manifest
+            .manifest_version
+            .checked_add(1)
+            .ok_or(ErrorCode::ManifestVersionOverflow)?;
+        manifest.entries = manifest_entries();
+
+        Ok(())
+    }
+
+    pub fn initialize_stats(ctx: Context<InitializeStats>) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        ctx.accounts.x402_stats.load_init()?.open();
+
+        Ok(())
+    }
+
+    /// Creates the guard of `reentrancy_guard` handlers, clear. Only the
+    /// program's upgrade authority may create it.
+    pub fn initialize_reentrancy_guard(ctx: Context<InitializeReentrancyGuard>) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        ctx.accounts.x402_reentrancy_guard.load_init()?.open();
+
+        Ok(())
+    }
+
+    /// Returns the sequence number of the most recent verified payment, so
+    /// reconcilers can check they have seen every event up to it.
+    pub fn get_sequence(ctx: Context<GetSequence>) -> Result<u64> {
+        Ok(ctx.accounts.x402_stats.load()?.sequence)
+    }
+
+    /// Creates the cursor of the indexer named `indexer_id` (at most 32
+    /// bytes), at sequence 0. Anyone may create one; the signer becomes
+    /// its creator.
+    pub fn create_cursor(ctx: Context<CreateCursor>, _indexer_id: String) -> Result<()> {
+        let cursor = &mut ctx.accounts.cursor;
+        cursor.version = IndexerCursor::CURRENT_VERSION;
+        cursor.creator = ctx.accounts.creator.key();
+        cursor.last_sequence = 0;
+
+        Ok(())
+    }
+
+    /// Records that the indexer has stored every payment up to
+    /// `new_sequence`. Only the cursor's creator may sign.
+    pub fn advance_cursor(ctx: Context<AdvanceCursor>, _indexer_id: String, new_sequence: u64) -> Result<()> {
+        let latest = ctx.accounts.x402_stats.load()?.sequence;
+        advance_indexer_cursor(&mut ctx.accounts.cursor, new_sequence, latest)
+    }
+
+    /// Sets the recipient used by instructions whose `#[x402]` has no
+    /// `address`, and records the cluster the program is deployed to. Run
+    /// once at deploy time by the program's upgrade authority, who becomes
+    /// the config's authority; `operator` may then rotate the recipient.
+    pub fn initialize_x402(
+        ctx: Context<InitializeX402>,
+        recipient: Pubkey,
+        operator: Pubkey,
+        cluster: Cluster,
+    ) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        require!(cluster != Cluster::Unset, ErrorCode::ClusterNotGiven);
+
+        let params = InitParams {
+            recipient,
+            operator,
+            cluster,
+        };
+        *ctx.accounts.x402_config = X402GlobalConfig::new(ctx.accounts.authority.key(), &params);
+
+        Ok(())
+    }
+
+    /// Creates every account a deployment needs in one transaction, under
+    /// the program's upgrade authority: the config as `initialize_x402`
+    /// does with `params`, and the free tier, congestion level, experiment,
+    /// stats, reentrancy guard and manifest with their defaults, the
+    /// authority owning each. Fails with `AlreadyInitialized` if any of
+    /// them exists; `initialize_all_idempotent` skips those instead.
+    pub fn initialize_all(ctx: Context<InitializeAll>, params: InitParams) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        initialize_deployment(ctx.accounts, &params, false)?;
+
+        Ok(())
+    }
+
+    /// `initialize_all`, leaving the accounts that already exist as they
+    /// are, so a deployment stopped partway is finished by sending it
+    /// again, and one already complete is left untouched.
+    pub fn initialize_all_idempotent(ctx: Context<InitializeAll>, params: InitParams) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        initialize_deployment(ctx.accounts, &params, true)?;
+
+        Ok(())
+    }
+
+    /// Records the cluster of a config migrated from before version 3,
+    /// which has none; only the authority may, and only once.
+    pub fn set_x402_cluster(ctx: Context<AdministerX402>, cluster: Cluster) -> Result<()> {
+        let config = &mut ctx.accounts.x402_config;
+        config.admin().require_authority(&ctx.accounts.signer)?;
+        record_cluster(config, cluster)
+    }
+
+    /// Rotates the configured recipient; the next priced call pays it.
+    pub fn set_recipient(ctx: Context<SetRecipient>, recipient: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.x402_config;
+        config.admin().require_operator(&ctx.accounts.signer)?;
+        config.recipient = recipient;
+
+        Ok(())
+    }
+
+    /// Delegates operational changes to `operator`; the authority's own key
+    /// takes them back.
+    pub fn set_x402_operator(ctx: Context<AdministerX402>, operator: Pubkey) -> Result<()> {
+        let config = &mut ctx.accounts.x402_config;
+        config.admin().require_authority(&ctx.accounts.signer)?;
+        config.operator = operator;
+
+        Ok(())
+    }
+
+    /// Hands the config's authority over to `new_authority`.
+    pub fn transfer_x402_authority(ctx: Context<AdministerX402>, new_authority: Pubkey) -> Result<()> {
+        hand_over_x402_authority(&mut ctx.accounts.x402_config, &ctx.accounts.signer, new_authority)
+    }
+
+    /// Gives the config's authority back to the program's current upgrade
+    /// authority, for when the authority's key is lost or the program has
+    /// changed hands.
+    pub fn reclaim_x402_authority(ctx: Context<ReclaimX402Authority>) -> Result<()> {
+        x402_runtime::admin::assert_upgrade_authority(
+            ctx.program_id,
+            &ctx.accounts.program,
+            &ctx.accounts.program_data,
+            &ctx.accounts.authority,
+        )?;
+        ctx.accounts.x402_config.authority = ctx.accounts.authority.key();
+
+        Ok(())
+    }
+
+    /// Creates the signer's `SpendingCap`, limiting them to `cap` per
+    /// period of `period_secs` from now. Run once per payer; later changes
+    /// go through `set_spending_cap`.
+    pub fn init_spending_cap(ctx: Context<InitSpendingCap>, cap: u64, period_secs: i64) -> Result<()> {
+        let spending_cap = &mut ctx.accounts.spending_cap;
+        require_uninitialized(&**spending_cap)?;
+        spending_cap.version = SpendingCap::CURRENT_VERSION;
+        spending_cap.payer = ctx.accounts.payer.key();
+        start_spending_period(spending_cap, cap, period_secs, "init_spending_cap")?;
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<SpendingCap>()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Limits how much `payer` can spend on priced instructions per period.
+    /// Changing the cap or period starts a new period.
+    ///
+    /// Breaking change: the cap used to be created here on first use
+    /// (`init_if_needed`). Clients now call `init_spending_cap` first. The
+    /// system program and ledger they already pass are left over as
+    /// remaining accounts and not read.
+    pub fn set_spending_cap(ctx: Context<SetSpendingCap>, cap: u64, period_secs: i64) -> Result<()> {
+        let spending_cap = &mut ctx.accounts.spending_cap;
+        require_current_version(&**spending_cap)?;
+        start_spending_period(spending_cap, cap, period_secs, "set_spending_cap")
+    }
+
+    /// Opens `payer`'s budget in the compute tiers' group, with which any
+    /// combination of tiers called in one transaction costs at most the
+    /// enterprise price.
+    pub fn init_compute_budget(ctx: Context<InitComputeBudget>) -> Result<()> {
+        ctx.accounts.budget.version = ComputeBudget::CURRENT_VERSION;
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<ComputeBudget>()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Creates the signer's `DepositAccount`, empty but for its rent. Run
+    /// once per depositor before their first `deposit`, in the same
+    /// transaction if need be.
+    pub fn init_deposit(ctx: Context<InitDeposit>) -> Result<()> {
+        let deposit = &mut ctx.accounts.deposit;
+        require_uninitialized(&**deposit)?;
+        deposit.version = DepositAccount::CURRENT_VERSION;
+        deposit.depositor = ctx.accounts.depositor.key();
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<DepositAccount>()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Tops up the signer's existing `DepositAccount` with `amount`
+    /// lamports.
+    ///
+    /// Breaking change: the deposit used to be created here on first use
+    /// (`init_if_needed`). Clients now call `init_deposit` first. A ledger
+    /// they still pass is left over as a remaining account and not read.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
+        require_gt!(amount, 0, ErrorCode::InvalidDepositAmount);
+
+        anchor_lang::system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                anchor_lang::system_program::Transfer {
+                    from: ctx.accounts.depositor.to_account_info(),
+                    to: ctx.accounts.deposit.to_account_info(),
+                },
+            ),
+            amount,
+        )
+    }
+
+    /// Returns `amount` unspent lamports of the signer's `DepositAccount`,
+    /// which keeps its rent-exempt minimum.
+    pub fn withdraw_deposit(ctx: Context<WithdrawDeposit>, amount: u64) -> Result<()> {
+        require_gt!(amount, 0, ErrorCode::InvalidDepositAmount);
+        x402_runtime::deposit::withdraw(
+            &ctx.accounts.deposit.to_account_info(),
+            &ctx.accounts.depositor.to_account_info(),
+            amount,
+        )
+    }
+
+    /// Creates the signer's `Accumulator` toward the `accumulated = true`
+    /// instruction tagged `tag`, empty. Run once per payer and tag before
+    /// their first `accumulate_payment`, in the same transaction if need be.
+    pub fn init_accumulator(ctx: Context<InitAccumulator>, tag: String) -> Result<()> {
+        accumulated_params(&tag)?;
+        let accumulator = &mut ctx.accounts.accumulator;
+        require_uninitialized(&**accumulator)?;
+        accumulator.version = Accumulator::CURRENT_VERSION;
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            record_rent_into_ledger(ledger, rent_for::<Accumulator>()?)?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds the transfer before this instruction, of any amount, to the
+    /// signer's existing `Accumulator` toward the `accumulated = true`
+    /// instruction tagged `tag`. The transfer must come from the signer and
+    /// go to that instruction's recipient.
+    ///
+    /// Breaking change: the accumulator used to be created here on first
+    /// use (`init_if_needed`). Clients now call `init_accumulator` first;
+    /// the accounts they pass are unchanged, and the system program is
+    /// just no longer read.
+    pub fn accumulate_payment(ctx: Context<AccumulatePayment>, tag: String) -> Result<()> {
+        let params = accumulated_params(&tag)?;
+        let contribution = x402_runtime::accumulator::verify_contribution(
+            ctx.remaining_accounts,
+            ctx.program_id,
+            &params,
+            "accumulate_payment",
+        )?;
+
+        let accumulator = &mut ctx.accounts.accumulator;
+        require_current_version(&**accumulator)?;
+        let balance = x402_runtime::accumulator::credit(
+            &accumulator.key(),
+            ctx.program_id,
+            &tag,
+            &mut accumulator.balance,
+            &contribution,
+        )?;
+
+        if let Some(ledger) = ctx.accounts.payment_ledger.as_mut() {
+            let now = clock::now()
+                .inspect_err(|_| msg!("accumulate_payment: Clock sysvar unavailable while updating the ledger"))?;
+            record_into_ledger(ledger, &contribution.payer, contribution.amount, now)?;
+        }
+        msg!(
+            "accumulate_payment: {} of {} lamports toward \"{}\"",
+            balance,
+            params.price,
+            tag
+        );
+        Ok(())
+    }
+
+    /// Upgrades a version 0 to 5 `ComputeResult`; only its owner may.
+    pub fn migrate_compute_result(ctx: Context<MigrateAccount>) -> Result<()> {
+        let data = migrate::legacy_data::<ComputeResult>(
+            &ctx.accounts.account,
+            &[
+                migrate::COMPUTE_RESULT_V0_SPACE,
+                migrate::COMPUTE_RESULT_V1_SPACE,
+                migrate::COMPUTE_RESULT_V2_SPACE,
+                migrate::COMPUTE_RESULT_V3_SPACE,
+                migrate::COMPUTE_RESULT_V4_SPACE,
+                migrate::COMPUTE_RESULT_V5_SPACE,
+            ],
+        )?;
+        let upgraded = match data.len() {
+            migrate::COMPUTE_RESULT_V0_SPACE => migrate::compute_result_from_v0(&data)?,
+            migrate::COMPUTE_RESULT_V1_SPACE => migrate::compute_result_from_v1(&data)?,
+            migrate::COMPUTE_RESULT_V2_SPACE => migrate::compute_result_from_v2(&data)?,
+            migrate::COMPUTE_RESULT_V3_SPACE => migrate::compute_result_from_v3(&data)?,
+            migrate::COMPUTE_RESULT_V4_SPACE => migrate::compute_result_from_v4(&data)?,
+            _ => migrate::compute_result_from_v5(&data)?,
+        };
+        require_keys_eq!(upgraded.owner, ctx.accounts.authority.key(), ErrorCode::MigrationNotAuthorized);
+
+        migrate::upgrade(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+            8 + ComputeResult::INIT_SPACE,
+        )
+    }
+
+    /// Upgrades the signer's version 0 or 1 `PaymentLedger`.
+    pub fn migrate_payment_ledger(ctx: Context<MigrateAccount>) -> Result<()> {
+        let authority = ctx.accounts.authority.key();
+        let ledger = seeds::payment_ledger_address(ctx.program_id, &authority);
+        require_keys_eq!(ledger, ctx.accounts.account.key(), ErrorCode::MigrationNotAuthorized);
+
+        let data = migrate::legacy_data::<PaymentLedger>(
+            &ctx.accounts.account,
+            &[migrate::PAYMENT_LEDGER_V0_SPACE, migrate::PAYMENT_LEDGER_V1_SPACE],
+        )?;
+        let upgraded = match data.len() {
+            migrate::PAYMENT_LEDGER_V0_SPACE => migrate::payment_ledger_from_v0(&data, authority)?,
+            _ => migrate::payment_ledger_from_v1(&data)?,
+        };
+
+        migrate::upgrade(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+            8 + PaymentLedger::INIT_SPACE,
+        )
+    }
+
+    /// Upgrades a version 0 to 3 `X402Stats` onto the current zero-copy
+    /// layout. Anyone may, since the upgrade only changes the layout.
+    pub fn migrate_stats(ctx: Context<MigrateAccount>) -> Result<()> {
+        let stats = seeds::stats_address(ctx.program_id);
+        require_keys_eq!(stats, ctx.accounts.account.key(), ErrorCode::MigrationNotAuthorized);
+
+        let data = migrate::legacy_data::<X402Stats>(
+            &ctx.accounts.account,
+            &[
+                migrate::X402_STATS_V0_SPACE,
+                migrate::X402_STATS_V1_SPACE,
+                migrate::X402_STATS_V2_SPACE,
+                migrate::X402_STATS_V3_SPACE,
+            ],
+        )?;
+        let upgraded = match data.len() {
+            migrate::X402_STATS_V0_SPACE => migrate::x402_stats_from_v0(&data)?,
+            migrate::X402_STATS_V1_SPACE => migrate::x402_stats_from_v1(&data)?,
+            migrate::X402_STATS_V2_SPACE => migrate::x402_stats_from_v2(&data)?,
+            _ => migrate::x402_stats_from_v3(&data)?,
+        };
+
+        migrate::upgrade_zero_copy(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+        )
+    }
+
+    /// Upgrades the version 1 `FailureLog` onto the zero-copy layout,
+    /// keeping its entries. Anyone may, since the upgrade only changes the
+    /// layout.
+    pub fn migrate_failure_log(ctx: Context<MigrateAccount>) -> Result<()> {
+        let log = seeds::failure_log_address(ctx.program_id);
+        require_keys_eq!(log, ctx.accounts.account.key(), ErrorCode::MigrationNotAuthorized);
+
+        let data = migrate::legacy_data::<FailureLog>(&ctx.accounts.account, &[migrate::FAILURE_LOG_V1_SPACE])?;
+        let upgraded = migrate::failure_log_from_v1(&data)?;
+
+        migrate::upgrade_zero_copy(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+        )
+    }
+
+    /// Upgrades the signer's version 0 `SpendingCap`.
+    pub fn migrate_spending_cap(ctx: Context<MigrateAccount>) -> Result<()> {
+        let data = migrate::v0_data::<SpendingCap>(&ctx.accounts.account, migrate::SPENDING_CAP_V0_SPACE)?;
+        let upgraded = migrate::spending_cap_from_v0(&data)?;
+        require_keys_eq!(upgraded.payer, ctx.accounts.authority.key(), ErrorCode::MigrationNotAuthorized);
+
+        migrate::upgrade(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+            8 + SpendingCap::INIT_SPACE,
+        )
+    }
+
+    /// Upgrades a version 1 or 2 `X402GlobalConfig`; only its authority
+    /// may. A version 1 config has delegated nothing yet, so the authority
+    /// is also the operator. Neither recorded a cluster, which the
+    /// authority then sets with `set_x402_cluster`.
+    pub fn migrate_x402_config(ctx: Context<MigrateAccount>) -> Result<()> {
+        let config = seeds::config_address(ctx.program_id);
+        require_keys_eq!(config, ctx.accounts.account.key(), ErrorCode::MigrationNotAuthorized);
+        let data = migrate::legacy_data::<X402GlobalConfig>(
+            &ctx.accounts.account,
+            &[migrate::X402_CONFIG_V1_SPACE, migrate::X402_CONFIG_V2_SPACE],
+        )?;
+        let upgraded = match data.len() {
+            migrate::X402_CONFIG_V1_SPACE => migrate::x402_config_from_v1(&data)?,
+            _ => migrate::x402_config_from_v2(&data)?,
+        };
+        require_keys_eq!(upgraded.authority, ctx.accounts.authority.key(), ErrorCode::MigrationNotAuthorized);
+
+        migrate::upgrade(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+            8 + X402GlobalConfig::INIT_SPACE,
+        )
+    }
+
+    /// Upgrades a version 1 `CongestionLevel`; only its authority may.
+    pub fn migrate_congestion_level(ctx: Context<MigrateAccount>) -> Result<()> {
+        let pda = seeds::congestion_address(ctx.program_id);
+        require_keys_eq!(ctx.accounts.account.key(), pda, ErrorCode::MigrationNotAuthorized);
+        let data = migrate::legacy_data::<CongestionLevel>(&ctx.accounts.account, &[migrate::CONGESTION_LEVEL_V1_SPACE])?;
+        let upgraded = migrate::congestion_level_from_v1(&data)?;
+        require_keys_eq!(upgraded.authority, ctx.accounts.authority.key(), ErrorCode::MigrationNotAuthorized);
+
+        migrate::upgrade(
+            &ctx.accounts.account,
+            &ctx.accounts.authority,
+            &ctx.accounts.system_program,
+            &upgraded,
+            8 + CongestionLevel::INIT_SPACE,
+        )
+    }
+}
+
+/// `#[x402_accounts]` adds the instructions sysvar as `x402_instructions`,
+/// so clients pass it by name instead of in remaining accounts.
+///
+/// The result is the PDA of the payer and the call's `nonce`, so a retried
+/// call finds the result of an earlier attempt that succeeded.
+#[x402_accounts(mode = "preceding_instruction")]
+#[derive(Accounts)]
+#[instruction(bind_to: Option<Pubkey>, nonce: u64)]
+pub struct PremiumCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: created by `compute` once `require_fresh_result` finds it
+    /// empty, instead of by `init`, whose failure would not say why.
+    #[account(
+        mut,
+        seeds = [COMPUTE_RESULT_SEED, payer.key().as_ref(), &nonce.to_le_bytes()],
+        bump
+    )]
+    pub result: UncheckedAccount<'info>,
+    /// The payer's spending cap, if they set one. Omit it to pay uncapped.
+    #[account(
+        mut,
+        seeds = [SPENDING_CAP_SEED, payer.key().as_ref()],
+        bump,
+        constraint = spending_cap.version == SpendingCap::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub spending_cap: Option<Account<'info, SpendingCap>>,
+    /// The payer's ledger, if they opened one. Every tenth payment it
+    /// records earns the loyalty bonus.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = x402_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_ledger: Option<Account<'info, PaymentLedger>>,
+    /// The payer's budget in the `compute` group, if they opened one with
+    /// `init_compute_budget`. Omit it to pay each tier's price.
+    #[account(
+        mut,
+        seeds = [GROUP_BUDGET_SEED, x402_example::COMPUTE_PREMIUM_X402_GROUP.name.as_bytes(), payer.key().as_ref()],
+        bump,
+        constraint = x402_group_budget.version == ComputeBudget::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_group_budget: Option<Account<'info, ComputeBudget>>,
+    #[account(
+        mut,
+        seeds = [STATS_SEED],
+        bump,
+        constraint = zero_copy_version(&x402_stats)? == X402Stats::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_stats: AccountLoader<'info, X402Stats>,
+    pub system_program: Program<'info, System>,
+}
+
+/// The payer and recipient come first, where the tier's check reads them
+/// from the `pay_and_invoke` instruction.
+#[derive(Accounts)]
+pub struct PayAndInvoke<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: checked against the invoked tier's recipient.
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub this_program: Program<'info, crate::program::X402Example>,
+}
+
+/// No writable state: the instructions sysvar goes in remaining accounts
+/// like every other priced instruction.
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [CONGESTION_SEED],
+        bump,
+        constraint = x402_congestion.version == CongestionLevel::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+#[derive(Accounts)]
+pub struct QuotePrice<'info> {
+    #[account(
+        seeds = [CONGESTION_SEED],
+        bump,
+        constraint = x402_congestion.version == CongestionLevel::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+/// The accounts any priced instruction reads its price from, each passed
+/// only when the instruction explained reads it.
+#[derive(Accounts)]
+pub struct ExplainPayment<'info> {
+    /// CHECK: the payer whose price is explained; only its key is read.
+    pub payer: UncheckedAccount<'info>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Option<Account<'info, X402GlobalConfig>>,
+    #[account(
+        seeds = [CONGESTION_SEED],
+        bump,
+        constraint = x402_congestion.version == CongestionLevel::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_congestion: Option<Account<'info, CongestionLevel>>,
+    #[account(
+        seeds = [EXPERIMENT_SEED, x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes()],
+        bump,
+        constraint = x402_experiment.version == PriceExperiment::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_experiment: Option<Account<'info, PriceExperiment>>,
+}
+
+#[derive(Accounts)]
+pub struct TreasuryQuery<'info> {
+    pub payer: Signer<'info>,
+    /// Omitting it fails the call with `ConfigNotInitialized`.
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Option<Account<'info, X402GlobalConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct DepositQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [DEPOSIT_SEED, payer.key().as_ref()],
+        bump,
+        constraint = x402_deposit.version == DepositAccount::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_deposit: Account<'info, DepositAccount>,
+    /// The recipient in `deposit_query`'s `#[x402]`, credited from the
+    /// deposit.
+    #[account(mut)]
+    pub x402_recipient: SystemAccount<'info>,
+    #[account(
+        mut,
+        seeds = [REENTRANCY_GUARD_SEED],
+        bump,
+        constraint = zero_copy_version(&x402_reentrancy_guard)? == ReentrancyGuard::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_reentrancy_guard: AccountLoader<'info, ReentrancyGuard>,
+}
+
+#[derive(Accounts)]
+pub struct ExperimentQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [EXPERIMENT_SEED, x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes()],
+        bump,
+        constraint = x402_experiment.version == PriceExperiment::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_experiment: Account<'info, PriceExperiment>,
+}
+
+#[derive(Accounts)]
+pub struct SavingsQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ACCUMULATOR_SEED, x402_example::SAVINGS_QUERY_X402_TAG.as_bytes(), payer.key().as_ref()],
+        bump,
+        constraint = x402_accumulator.version == Accumulator::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_accumulator: Account<'info, Accumulator>,
+}
+
+#[derive(Accounts)]
+pub struct InitUsageCounter<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + UsageCounter::INIT_SPACE,
+        seeds = [USAGE_COUNTER_SEED, payer.key().as_ref()],
+        bump
+    )]
+    pub usage_counter: Account<'info, UsageCounter>,
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the account created here.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+#[derive(Accounts)]
+pub struct FreeCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ComputeResult::INIT_SPACE
+    )]
+    pub result: Account<'info, ComputeResult>,
+    #[account(seeds = [FREE_TIER_CONFIG_SEED], bump)]
+    pub free_tier_config: Account<'info, FreeTierConfig>,
+    #[account(
+        mut,
+        seeds = [USAGE_COUNTER_SEED, payer.key().as_ref()],
+        bump,
+        has_one = payer
+    )]
+    pub usage_counter: Account<'info, UsageCounter>,
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the accounts created here. Optional and last, so clients from
+    /// before rent was recorded may leave it out.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeFreeTier<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + FreeTierConfig::INIT_SPACE,
+        seeds = [FREE_TIER_CONFIG_SEED],
+        bump
+    )]
+    pub free_tier_config: Account<'info, FreeTierConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetFreeTier<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [FREE_TIER_CONFIG_SEED],
+        bump,
+        has_one = authority,
+        constraint = free_tier_config.version == FreeTierConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub free_tier_config: Account<'info, FreeTierConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeCongestion<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + CongestionLevel::INIT_SPACE,
+        seeds = [CONGESTION_SEED],
+        bump
+    )]
+    pub x402_congestion: Account<'info, CongestionLevel>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeExperiment<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PriceExperiment::INIT_SPACE,
+        seeds = [EXPERIMENT_SEED, x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes()],
+        bump
+    )]
+    pub x402_experiment: Account<'info, PriceExperiment>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EndExperiment<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [EXPERIMENT_SEED, x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes()],
+        bump,
+        has_one = authority,
+        constraint = x402_experiment.version == PriceExperiment::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_experiment: Account<'info, PriceExperiment>,
+}
+
+#[derive(Accounts)]
+pub struct SetCongestionLevel<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONGESTION_SEED],
+        bump,
+        has_one = authority,
+        constraint = x402_congestion.version == CongestionLevel::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+#[derive(Accounts)]
+pub struct ConsumeResult<'info> {
+    /// The owner, or the consumer the result is bound to.
+    pub consumer: Signer<'info>,
+    #[account(mut)]
+    pub result: Account<'info, ComputeResult>,
+}
+
+#[derive(Accounts)]
+pub struct ReclaimResult<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    /// Closing reallocs the account to zero bytes and hands it back to the
+    /// system program, so no stale result can be read or revived.
+    #[account(mut, has_one = owner, close = owner)]
+    pub result: Account<'info, ComputeResult>,
+}
+
+#[derive(Accounts)]
+pub struct PaidCloseResult<'info> {
+    /// The result's owner, who pays the fee and receives the rent.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        constraint = result.owner == payer.key() @ ErrorCode::NotResultOwner,
+        close = payer
+    )]
+    pub result: Account<'info, ComputeResult>,
+    /// Omitting it fails the call with `ConfigNotInitialized`.
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Option<Account<'info, X402GlobalConfig>>,
+}
+
+#[derive(Accounts)]
+pub struct ReportFailedPayment<'info> {
+    /// The payer whose attempt failed.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// Created by the first report, so reporting stays one follow-up
+    /// transaction. `open_failure_log` only fills in a log without a
+    /// discriminator; an existing one is only ever appended to.
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<FailureLog>(),
+        seeds = [FAILURE_LOG_SEED],
+        bump
+    )]
+    pub failure_log: AccountLoader<'info, FailureLog>,
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the accounts created here. Optional and last, so clients from
+    /// before rent was recorded may leave it out.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyPayment<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct X402Pay<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    /// CHECK: Payment recipient account, validated by x402 macro
+    pub payment_recipient: AccountInfo<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitPaymentLedger<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + PaymentLedger::INIT_SPACE,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump
+    )]
+    pub payment_ledger: Account<'info, PaymentLedger>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SnapshotLedgers<'info> {
+    /// The config's operator or authority, checked by the instruction.
+    #[account(mut)]
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+    /// Created by the operator's first crank. `open_snapshot` only empties
+    /// a state whose version is unset; one partway through a snapshot is
+    /// left as it is.
+    #[account(
+        init_if_needed,
+        payer = signer,
+        space = 8 + SnapshotState::INIT_SPACE,
+        seeds = [SNAPSHOT_STATE_SEED],
+        bump
+    )]
+    pub snapshot_state: Box<Account<'info, SnapshotState>>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeSnapshot<'info> {
+    /// The config's authority, checked by the instruction.
+    pub signer: Signer<'info>,
+    #[account(
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+    #[account(
+        mut,
+        seeds = [SNAPSHOT_STATE_SEED],
+        bump,
+        constraint = snapshot_state.version == SnapshotState::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub snapshot_state: Box<Account<'info, SnapshotState>>,
+}
+
+/// Never creates the ledger: another payer's ledger fails the seeds check,
+/// and a ledger whose `payer` is someone else fails `has_one`.
+#[derive(Accounts)]
+pub struct RecordPayment<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        has_one = payer @ ErrorCode::LedgerPayerMismatch
+    )]
+    pub payment_ledger: Account<'info, PaymentLedger>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeStats<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<X402Stats>(),
+        seeds = [STATS_SEED],
+        bump
+    )]
+    pub x402_stats: AccountLoader<'info, X402Stats>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeReentrancyGuard<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<ReentrancyGuard>(),
+        seeds = [REENTRANCY_GUARD_SEED],
+        bump
+    )]
+    pub x402_reentrancy_guard: AccountLoader<'info, ReentrancyGuard>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GetSequence<'info> {
+    #[account(
+        seeds = [STATS_SEED],
+        bump,
+        constraint = zero_copy_version(&x402_stats)? == X402Stats::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_stats: AccountLoader<'info, X402Stats>,
+}
+
+#[derive(Accounts)]
+#[instruction(indexer_id: String)]
+pub struct CreateCursor<'info> {
+    #[account(mut)]
+    pub creator: Signer<'info>,
+    #[account(
+        init,
+        payer = creator,
+        space = 8 + IndexerCursor::INIT_SPACE,
+        seeds = [INDEXER_CURSOR_SEED, indexer_id.as_bytes()],
+        bump
+    )]
+    pub cursor: Account<'info, IndexerCursor>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(indexer_id: String)]
+pub struct AdvanceCursor<'info> {
+    pub creator: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [INDEXER_CURSOR_SEED, indexer_id.as_bytes()],
+        bump,
+        has_one = creator,
+        constraint = cursor.version == IndexerCursor::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub cursor: Account<'info, IndexerCursor>,
+    #[account(
+        seeds = [STATS_SEED],
+        bump,
+        constraint = zero_copy_version(&x402_stats)? == X402Stats::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_stats: AccountLoader<'info, X402Stats>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeX402<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + X402GlobalConfig::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+    pub system_program: Program<'info, System>,
+}
 
-        Ok(())
-    }
+/// Every account of a deployment, at the addresses and sizes of the
+/// instruction creating each alone. Accounts are created only if missing,
+/// which is what lets `initialize_all_idempotent` finish a deployment
+/// stopped partway; `initialize_deployment` tells a just created one from
+/// an existing one, which it refuses or leaves untouched.
+#[derive(Accounts)]
+pub struct InitializeAll<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// The program's upgrade authority, which may also be `payer`.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + X402GlobalConfig::INIT_SPACE,
+        seeds = [CONFIG_SEED],
+        bump
+    )]
+    pub x402_config: Box<Account<'info, X402GlobalConfig>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + FreeTierConfig::INIT_SPACE,
+        seeds = [FREE_TIER_CONFIG_SEED],
+        bump
+    )]
+    pub free_tier_config: Box<Account<'info, FreeTierConfig>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + CongestionLevel::INIT_SPACE,
+        seeds = [CONGESTION_SEED],
+        bump
+    )]
+    pub x402_congestion: Box<Account<'info, CongestionLevel>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + PriceExperiment::INIT_SPACE,
+        seeds = [EXPERIMENT_SEED, x402_example::EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes()],
+        bump
+    )]
+    pub x402_experiment: Box<Account<'info, PriceExperiment>>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<X402Stats>(),
+        seeds = [STATS_SEED],
+        bump
+    )]
+    pub x402_stats: AccountLoader<'info, X402Stats>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 8 + std::mem::size_of::<ReentrancyGuard>(),
+        seeds = [REENTRANCY_GUARD_SEED],
+        bump
+    )]
+    pub x402_reentrancy_guard: AccountLoader<'info, ReentrancyGuard>,
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = ManifestData::space(manifest_entries().len()),
+        seeds = [MANIFEST_SEED],
+        bump
+    )]
+    pub manifest: Box<Account<'info, X402Manifest>>,
+    pub system_program: Program<'info, System>,
+}
 
-    pub fn verify_payment(ctx: Context<VerifyPayment>) -> Result<()> {
-        let required_lamports = 1_000_000u64;
+/// The config's authority or operator, as each instruction requires.
+#[derive(Accounts)]
+pub struct SetRecipient<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+}
 
-        if ctx.accounts.payer.lamports() < required_lamports {
-            return Err(error!(ErrorCode::InsufficientPayment));
-        }
+/// The config's authority, checked by the instruction.
+#[derive(Accounts)]
+pub struct AdministerX402<'info> {
+    pub signer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+}
 
-        Ok(())
-    }
-    pub fn record_payment(ctx: Context<RecordPayment>, amount: u64) -> Result<()> {
-        require!(amount > 0, ErrorCode::InvalidPaymentAmount);
+#[derive(Accounts)]
+pub struct ReclaimX402Authority<'info> {
+    /// The program's current upgrade authority.
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        seeds = [CONFIG_SEED],
+        bump,
+        constraint = x402_config.version == X402GlobalConfig::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub x402_config: Account<'info, X402GlobalConfig>,
+}
 
-        ctx.accounts.payment_ledger.total_payments += 1;
-        ctx.accounts.payment_ledger.total_amount += amount;
-        ctx.accounts.payment_ledger.last_payment = Clock::get()?.unix_timestamp;
+#[derive(Accounts)]
+pub struct InitDeposit<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + DepositAccount::INIT_SPACE,
+        seeds = [DEPOSIT_SEED, depositor.key().as_ref()],
+        bump
+    )]
+    pub deposit: Account<'info, DepositAccount>,
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the account created here.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, depositor.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
 
-        emit!(PaymentRecordedEvent {
-            payer: ctx.accounts.payer.key(),
-            amount,
-            total_payments: ctx.accounts.payment_ledger.total_payments,
-        });
+#[derive(Accounts)]
+pub struct Deposit<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [DEPOSIT_SEED, depositor.key().as_ref()],
+        bump,
+        has_one = depositor,
+        constraint = deposit.version == DepositAccount::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub deposit: Account<'info, DepositAccount>,
+    pub system_program: Program<'info, System>,
+}
 
-        Ok(())
-    }
+#[derive(Accounts)]
+pub struct WithdrawDeposit<'info> {
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [DEPOSIT_SEED, depositor.key().as_ref()],
+        bump,
+        has_one = depositor,
+        constraint = deposit.version == DepositAccount::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub deposit: Account<'info, DepositAccount>,
 }
 
 #[derive(Accounts)]
-pub struct PremiumCompute<'info> {
+#[instruction(tag: String)]
+pub struct InitAccumulator<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(
         init,
         payer = payer,
-        space = 8 + 32 + 8 + 1
+        space = 8 + Accumulator::INIT_SPACE,
+        seeds = [ACCUMULATOR_SEED, tag.as_bytes(), payer.key().as_ref()],
+        bump
     )]
-    pub result: Account<'info, ComputeResult>,
+    pub accumulator: Account<'info, Accumulator>,
     pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the account created here.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
 }
 
+/// The transfer's instructions sysvar goes in remaining accounts, after
+/// the optional ledger.
 #[derive(Accounts)]
-pub struct FreeCompute<'info> {
+#[instruction(tag: String)]
+pub struct AccumulatePayment<'info> {
+    pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [ACCUMULATOR_SEED, tag.as_bytes(), payer.key().as_ref()],
+        bump
+    )]
+    pub accumulator: Account<'info, Accumulator>,
+    /// No longer read; kept so the ledger and the sysvar after it stay
+    /// where clients put them.
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records each part
+    /// paid.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+#[derive(Accounts)]
+pub struct InitSpendingCap<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
     #[account(
         init,
         payer = payer,
-        space = 8 + 32 + 8 + 1
+        space = 8 + SpendingCap::INIT_SPACE,
+        seeds = [SPENDING_CAP_SEED, payer.key().as_ref()],
+        bump
     )]
-    pub result: Account<'info, ComputeResult>,
+    pub spending_cap: Account<'info, SpendingCap>,
     pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the rent of
+    /// the account created here.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
 }
 
 #[derive(Accounts)]
-pub struct VerifyPayment<'info> {
+pub struct SetSpendingCap<'info> {
     pub payer: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [SPENDING_CAP_SEED, payer.key().as_ref()],
+        bump,
+        has_one = payer
+    )]
+    pub spending_cap: Account<'info, SpendingCap>,
 }
 
 #[derive(Accounts)]
-pub struct X402Pay<'info> {
+pub struct InitComputeBudget<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ComputeBudget::INIT_SPACE,
+        seeds = [GROUP_BUDGET_SEED, x402_example::COMPUTE_PREMIUM_X402_GROUP.name.as_bytes(), payer.key().as_ref()],
+        bump
+    )]
+    pub budget: Account<'info, ComputeBudget>,
+    pub system_program: Program<'info, System>,
+    /// The payer's ledger, if they opened one, which records the budget's
+    /// rent.
+    #[account(
+        mut,
+        seeds = [PAYMENT_LEDGER_SEED, payer.key().as_ref()],
+        bump,
+        constraint = payment_ledger.version == PaymentLedger::CURRENT_VERSION @ ErrorCode::UnsupportedAccountVersion
+    )]
+    pub payment_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateAccount<'info> {
     #[account(mut)]
-    /// CHECK: Payment recipient account, validated by x402 macro
-    pub payment_recipient: AccountInfo<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: A version 0 account, which `Account` cannot deserialize; the
+    /// handler checks its discriminator, length and who may migrate it.
+    #[account(mut, owner = crate::ID)]
+    pub account: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct RecordPayment<'info> {
+pub struct PublishManifest<'info> {
+    /// The program's upgrade authority, who pays for the manifest.
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: this program, checked by address; its upgrade authority is
+    /// checked by `assert_upgrade_authority`.
+    #[account(address = crate::ID)]
+    pub program: UncheckedAccount<'info>,
+    /// CHECK: the program's `ProgramData`, checked by
+    /// `assert_upgrade_authority`.
+    pub program_data: UncheckedAccount<'info>,
     #[account(
-        init_if_needed,
-        payer = payer,
-        space = 8 + 32 + 8 + 8 + 8,
-        seeds = [b"payment_ledger", payer.key().as_ref()],
+        init,
+        payer = authority,
+        space = ManifestData::space(manifest_entries().len()),
+        seeds = [MANIFEST_SEED],
         bump
     )]
-    pub payment_ledger: Account<'info, PaymentLedger>,
+    pub manifest: Account<'info, X402Manifest>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateManifest<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        seeds = [MANIFEST_SEED],
+        bump,
+        has_one = authority,
+        realloc = ManifestData::space(manifest_entries().len()),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub manifest: Account<'info, X402Manifest>,
     pub system_program: Program<'info, System>,
 }
 
+/// Output of the compute instructions.
+///
+/// Version 1 added `version`, `tier` and `price_paid`; version 2 added
+/// `created_slot`; version 3 added `bind_to`; version 4 added
+/// `fingerprint`; version 5 added `ref_count`; version 6 added
+/// `provenance`.
 #[account]
+#[derive(InitSpace)]
 pub struct ComputeResult {
+    pub version: u8,
     pub owner: Pubkey,
     pub value: u64,
     pub paid: bool,
+    /// Amount actually transferred, including any overpayment.
+    pub amount_paid: u64,
+    /// One of the `TIER_*` constants.
+    pub tier: u8,
+    /// Price of the instruction that produced the result.
+    pub price_paid: u64,
+    /// Slot the result was paid for and created in; 0 for results migrated
+    /// from before version 2.
+    pub created_slot: u64,
+    /// A key the owner authorized at creation to consume the result in
+    /// their place, e.g. a sponsor's; `None` for the owner alone.
+    pub bind_to: Option<Pubkey>,
+    /// Request fingerprint of the paid call that produced the result (see
+    /// `x402_common::fingerprint`); all zeros for free results and those
+    /// migrated from before version 4.
+    pub fingerprint: [u8; 32],
+    /// Accounts depending on the result, one per `consume_result`. Only
+    /// `paid_close_result` closes a result with dependents.
+    pub ref_count: u16,
+    /// What the producing call's price was computed from; `Static` for
+    /// free results and those migrated from before version 6, whose tiers
+    /// had fixed prices.
+    pub provenance: PriceProvenance,
 }
 
 #[account]
+#[derive(InitSpace)]
 pub struct PaymentLedger {
+    pub version: u8,
     pub payer: Pubkey,
     pub total_payments: u64,
+    /// Sum of the payer's verified payments: revenue only.
     pub total_amount: u64,
     pub last_payment: i64,
+    /// Rent the payer paid to create accounts, recorded from version 2.
+    /// Not revenue: it stays with the accounts and returns on closing.
+    pub total_rent_paid: u64,
 }
 
-#[event]
-pub struct ComputeEvent {
-    pub payer: Pubkey,
-    pub result: u64,
-    pub timestamp: i64,
+/// The ledger snapshot `snapshot_ledgers` is building, folded into the
+/// frontier of its merkle tree; see `x402_common::snapshot`.
+#[account]
+#[derive(InitSpace)]
+pub struct SnapshotState {
+    pub version: u8,
+    /// Ledgers added so far.
+    pub leaves: u64,
+    /// Payer of the last ledger added; the next must sort after it.
+    pub last_payer: Pubkey,
+    /// Sum of the added ledgers' `total_amount`s.
+    pub total_amount: u64,
+    pub frontier: [[u8; 32]; SNAPSHOT_DEPTH],
+}
+
+impl SnapshotState {
+    /// A snapshot with no ledgers yet.
+    pub fn empty() -> Self {
+        SnapshotState {
+            version: SnapshotState::CURRENT_VERSION,
+            leaves: 0,
+            last_payer: Pubkey::default(),
+            total_amount: 0,
+            frontier: [[0; 32]; SNAPSHOT_DEPTH],
+        }
+    }
+}
+
+/// Program-wide count of verified payments. `sequence` is the number
+/// carried by the most recent `X402PaymentVerifiedEvent`, and `commitment`
+/// the one carried by the most recent `X402PaymentCommittedEvent`.
+///
+/// Every tracked payment writes it, so it is zero-copy: the fields are
+/// updated in place rather than the account being deserialized and
+/// written back whole. Same layout as `x402_common::stats::StatsData`.
+#[account(zero_copy)]
+pub struct X402Stats {
+    pub version: u8,
+    _padding: [u8; 7],
+    pub sequence: u64,
+    pub total_payments: u64,
+    pub total_amount: u64,
+    pub commitment: [u8; 32],
+    /// Rent payers paid to create accounts alongside a tracked payment,
+    /// recorded from version 4. Not part of `total_amount`.
+    pub total_rent_paid: u64,
+}
+
+impl X402Stats {
+    /// Starts freshly created stats at no payments.
+    pub fn open(&mut self) {
+        self.version = X402Stats::CURRENT_VERSION;
+        self.sequence = 0;
+        self.total_payments = 0;
+        self.total_amount = 0;
+        self.commitment = GENESIS_COMMITMENT;
+        self.total_rent_paid = 0;
+    }
+}
+
+/// Set while a `reentrancy_guard` handler runs (see
+/// `x402_runtime::reentrancy`). Zero-copy, so setting and clearing it
+/// writes one byte.
+#[account(zero_copy)]
+pub struct ReentrancyGuard {
+    pub version: u8,
+    pub entered: u8,
 }
 
-#[event]
-pub struct X402PaymentEvent {
+impl ReentrancyGuard {
+    /// Starts a freshly created guard clear.
+    pub fn open(&mut self) {
+        self.version = ReentrancyGuard::CURRENT_VERSION;
+        self.entered = 0;
+    }
+}
+
+#[account]
+#[derive(InitSpace)]
+pub struct SpendingCap {
+    pub version: u8,
     pub payer: Pubkey,
+    pub period_start: i64,
+    pub period_secs: i64,
+    pub cap: u64,
+    pub spent: u64,
+}
+
+/// A payer's budget in the compute tiers' group, reset by each transaction
+/// that calls them (see `x402_runtime::group`).
+#[account]
+#[derive(InitSpace)]
+pub struct ComputeBudget {
+    pub version: u8,
+    pub budget: GroupBudget,
+}
+
+/// Lamports a payer has deposited for `deposit = true` instructions, held
+/// as the account's balance above its rent-exempt minimum.
+#[account]
+#[derive(InitSpace)]
+pub struct DepositAccount {
+    pub version: u8,
+    /// May withdraw, and pays with the deposit.
+    pub depositor: Pubkey,
+}
+
+/// Lamports a payer has paid toward one `accumulated = true` instruction
+/// and not yet spent on it (see `x402_runtime::accumulator`).
+#[account]
+#[derive(InitSpace)]
+pub struct Accumulator {
+    pub version: u8,
+    pub balance: u64,
+}
+
+/// Recipient of every `#[x402]` instruction without an `address`, and the
+/// cluster the program was deployed to.
+#[account]
+#[derive(InitSpace)]
+pub struct X402GlobalConfig {
+    pub version: u8,
+    /// The program's upgrade authority when the config was created; may
+    /// hand itself over, appoint `operator` and rotate `recipient`.
+    pub authority: Pubkey,
+    pub recipient: Pubkey,
+    /// May rotate `recipient`.
+    pub operator: Pubkey,
+    /// Set at initialization, or once by the authority for configs
+    /// migrated from before version 3; never changed after.
+    pub cluster: Cluster,
+}
+
+impl X402GlobalConfig {
+    /// A config created by `authority` with `params`.
+    pub fn new(authority: Pubkey, params: &InitParams) -> Self {
+        X402GlobalConfig {
+            version: X402GlobalConfig::CURRENT_VERSION,
+            authority,
+            recipient: params.recipient,
+            operator: params.operator,
+            cluster: params.cluster,
+        }
+    }
+
+    /// The keys allowed to change this config.
+    pub fn admin(&self) -> X402Admin {
+        X402Admin {
+            authority: self.authority,
+            operator: self.operator,
+        }
+    }
+}
+
+/// What `initialize_all` writes into the config; every other account it
+/// creates starts from its defaults.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InitParams {
+    /// Paid by every `#[x402]` instruction without an `address`.
     pub recipient: Pubkey,
-    pub amount: u64,
-    pub timestamp: i64,
+    /// May rotate `recipient`.
+    pub operator: Pubkey,
+    /// The cluster the program is deployed to; not `Unset`.
+    pub cluster: Cluster,
+}
+
+/// Fills each account of `accounts` still blank, as `initialize_all` does
+/// once the upgrade authority is checked, and returns how many it filled.
+/// A blank account has no discriminator yet, as one `init_if_needed` has
+/// just created. Any other fails with `AlreadyInitialized`, or is left as
+/// it is when `skip_existing`.
+pub fn initialize_deployment(accounts: &mut InitializeAll, params: &InitParams, skip_existing: bool) -> Result<u8> {
+    require!(params.cluster != Cluster::Unset, ErrorCode::ClusterNotGiven);
+    let authority = accounts.authority.key();
+    let mut filled = 0;
+
+    if is_blank(&accounts.x402_config.to_account_info(), "config", skip_existing)? {
+        **accounts.x402_config = X402GlobalConfig::new(authority, params);
+        filled += 1;
+    }
+    if is_blank(&accounts.free_tier_config.to_account_info(), "free tier config", skip_existing)? {
+        **accounts.free_tier_config = FreeTierConfig::new(authority);
+        filled += 1;
+    }
+    if is_blank(&accounts.x402_congestion.to_account_info(), "congestion level", skip_existing)? {
+        **accounts.x402_congestion = CongestionLevel::new(authority);
+        filled += 1;
+    }
+    if is_blank(&accounts.x402_experiment.to_account_info(), "experiment", skip_existing)? {
+        **accounts.x402_experiment = PriceExperiment::new(authority);
+        filled += 1;
+    }
+    if is_blank(accounts.x402_stats.as_ref(), "stats", skip_existing)? {
+        accounts.x402_stats.load_init()?.open();
+        filled += 1;
+    }
+    if is_blank(accounts.x402_reentrancy_guard.as_ref(), "reentrancy guard", skip_existing)? {
+        accounts.x402_reentrancy_guard.load_init()?.open();
+        filled += 1;
+    }
+    if is_blank(&accounts.manifest.to_account_info(), "manifest", skip_existing)? {
+        **accounts.manifest = X402Manifest::new(authority);
+        filled += 1;
+    }
+    Ok(filled)
 }
 
-#[event]
-pub struct PaymentRecordedEvent {
+fn is_blank(account: &AccountInfo, name: &str, skip_existing: bool) -> Result<bool> {
+    if account.try_borrow_data()?.get(..8).is_some_and(|discriminator| discriminator == [0; 8]) {
+        return Ok(true);
+    }
+    if !skip_existing {
+        msg!("initialize_all: the {} {} already exists", name, account.key);
+        return err!(ErrorCode::AlreadyInitialized);
+    }
+    msg!("initialize_all: the {} {} already exists; left as it is", name, account.key);
+    Ok(false)
+}
+
+/// Records `cluster` in a `config` that has none yet. A recorded cluster
+/// never changes, so a config cannot be pointed at another cluster.
+pub fn record_cluster(config: &mut X402GlobalConfig, cluster: Cluster) -> Result<()> {
+    require!(cluster != Cluster::Unset, ErrorCode::ClusterNotGiven);
+    require!(config.cluster == Cluster::Unset, ErrorCode::ClusterAlreadySet);
+    config.cluster = cluster;
+    Ok(())
+}
+
+/// Hands `config`'s authority to `new_authority` if `signer` holds it.
+/// The operator stays appointed until the new authority replaces it.
+pub fn hand_over_x402_authority(
+    config: &mut X402GlobalConfig,
+    signer: &AccountInfo,
+    new_authority: Pubkey,
+) -> Result<()> {
+    config.admin().require_authority(signer)?;
+    config.authority = new_authority;
+    Ok(())
+}
+
+/// Whether, and how often, each payer may call `free_compute`.
+#[account]
+#[derive(InitSpace)]
+pub struct FreeTierConfig {
+    pub version: u8,
+    /// May change `enabled` and `cooldown_secs`.
+    pub authority: Pubkey,
+    pub enabled: bool,
+    /// Seconds a payer waits between free computes; 0 for no limit.
+    pub cooldown_secs: i64,
+}
+
+impl FreeTierConfig {
+    /// The free tier enabled with the default cooldown.
+    pub fn new(authority: Pubkey) -> Self {
+        FreeTierConfig {
+            version: FreeTierConfig::CURRENT_VERSION,
+            authority,
+            enabled: true,
+            cooldown_secs: DEFAULT_FREE_COOLDOWN_SECS,
+        }
+    }
+}
+
+/// A payer's use of the free tier.
+#[account]
+#[derive(InitSpace)]
+pub struct UsageCounter {
+    pub version: u8,
     pub payer: Pubkey,
-    pub amount: u64,
-    pub total_payments: u64,
+    /// Unix timestamp of the last free compute.
+    pub last_used: i64,
+    pub uses: u64,
+}
+
+/// The last `FAILURE_LOG_CAPACITY` payment failures payers reported,
+/// zero-copy so a report writes one entry rather than the whole ring. Same
+/// layout as `x402_common::failures::FailureLogData`.
+#[account(zero_copy)]
+pub struct FailureLog {
+    pub version: u8,
+    _padding: [u8; 7],
+    pub ring: FailureRing,
+}
+
+/// State of `experiment_query`'s price experiment. Same layout as
+/// `x402_common::experiment::PriceExperimentData`.
+#[account]
+#[derive(InitSpace)]
+pub struct PriceExperiment {
+    pub version: u8,
+    /// May end the experiment.
+    pub authority: Pubkey,
+    /// Every payer pays the `winner` arm once set.
+    pub ended: bool,
+    pub winner: u8,
+}
+
+impl PriceExperiment {
+    /// An experiment still running.
+    pub fn new(authority: Pubkey) -> Self {
+        PriceExperiment {
+            version: PriceExperiment::CURRENT_VERSION,
+            authority,
+            ended: false,
+            winner: 0,
+        }
+    }
+}
+
+/// Congestion level the `priced_query` surcharge is computed from. Same
+/// layout as `x402_common::congestion::CongestionLevelData`.
+#[account]
+#[derive(InitSpace)]
+pub struct CongestionLevel {
+    pub version: u8,
+    /// May change `level`.
+    pub authority: Pubkey,
+    /// 0 to `MAX_CONGESTION_LEVEL`.
+    pub level: u8,
+    /// `level` before its latest change.
+    pub previous_level: u8,
+    /// Unix timestamp of the latest change, 0 if never changed.
+    pub updated_at: i64,
+}
+
+impl CongestionLevel {
+    /// Level 0, never changed.
+    pub fn new(authority: Pubkey) -> Self {
+        CongestionLevel {
+            version: CongestionLevel::CURRENT_VERSION,
+            authority,
+            level: 0,
+            previous_level: 0,
+            updated_at: 0,
+        }
+    }
+}
+
+/// How far an indexer has exported the payment sequence. Same layout as
+/// `x402_common::cursor::IndexerCursorData`.
+#[account]
+#[derive(InitSpace)]
+pub struct IndexerCursor {
+    pub version: u8,
+    /// May advance the cursor.
+    pub creator: Pubkey,
+    /// Sequence of the last payment the indexer stored.
+    pub last_sequence: u64,
+}
+
+macro_rules! versioned {
+    ($($account:ty => $version:expr),* $(,)?) => {
+        $(
+            impl Versioned for $account {
+                const CURRENT_VERSION: u8 = $version;
+
+                fn version(&self) -> u8 {
+                    self.version
+                }
+            }
+        )*
+    };
+}
+
+versioned! {
+    ComputeResult => 6,
+    PaymentLedger => 2,
+    X402Stats => 4,
+    SpendingCap => 1,
+    X402GlobalConfig => 3,
+    FreeTierConfig => 1,
+    UsageCounter => 1,
+    FailureLog => 2,
+    CongestionLevel => 2,
+    DepositAccount => 1,
+    PriceExperiment => 1,
+    IndexerCursor => 1,
+    ComputeBudget => 1,
+    ReentrancyGuard => 1,
+    Accumulator => 1,
+    SnapshotState => 1,
+}
+
+/// Same layout as `x402_runtime::manifest::ManifestData`, which off-chain
+/// clients decode.
+#[account]
+pub struct X402Manifest {
+    pub authority: Pubkey,
+    pub manifest_version: u32,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl X402Manifest {
+    /// The first version of the manifest, listing `manifest_entries()`.
+    pub fn new(authority: Pubkey) -> Self {
+        X402Manifest {
+            authority,
+            manifest_version: 1,
+            entries: manifest_entries(),
+        }
+    }
 }
 
 #[error_code]
@@ -192,4 +2999,72 @@ pub enum ErrorCode {
     PaymentVerificationFailed,
     #[msg("Insufficient balance for payment")]
     InsufficientBalance,
+    #[msg("Manifest version overflowed")]
+    ManifestVersionOverflow,
+    #[msg("Spending cap period must be positive and at most 100 years")]
+    InvalidSpendingPeriod,
+    #[msg("Compute result was not produced by a paid call")]
+    ResultNotPaid,
+    #[msg("Compute result was produced by a call below the required price")]
+    ResultPriceTooLow,
+    #[msg("Account was written by a newer version of this program")]
+    UnsupportedAccountVersion,
+    #[msg("Account uses an old layout and must be migrated")]
+    AccountNeedsMigration,
+    #[msg("Account is not on the version 0 layout")]
+    AccountAlreadyMigrated,
+    #[msg("Signer may not migrate this account")]
+    MigrationNotAuthorized,
+    #[msg("Free tier is disabled or the payer's cooldown has not elapsed")]
+    FreeTierExhausted,
+    #[msg("Free tier cooldown must be from zero to 100 years")]
+    InvalidFreeTierCooldown,
+    #[msg("Failure reason is not an x402 error")]
+    InvalidFailureReason,
+    #[msg("Signer is neither the result's owner nor its bound consumer")]
+    ConsumerNotAuthorized,
+    #[msg("Congestion level is above the maximum")]
+    InvalidCongestionLevel,
+    #[msg("Payment ledger belongs to a different payer")]
+    LedgerPayerMismatch,
+    #[msg("Payment ledger already exists")]
+    LedgerAlreadyInitialized,
+    #[msg("pay_and_invoke may not invoke this instruction")]
+    InstructionNotAllowlisted,
+    #[msg("Compute result has dependents; close it with paid_close_result")]
+    ResultHasDependents,
+    #[msg("Compute result has the most dependents it can count")]
+    TooManyDependents,
+    #[msg("Signer does not own the compute result")]
+    NotResultOwner,
+    #[msg("Deposit and withdrawal amounts must be greater than zero")]
+    InvalidDepositAmount,
+    #[msg("The experiment has no such arm")]
+    InvalidExperimentWinner,
+    #[msg("Compute result already exists; the earlier call with this nonce succeeded")]
+    ResultAlreadyExists,
+    #[msg("Indexer cursor must move past its current sequence")]
+    CursorNotAdvanced,
+    #[msg("Indexer cursor cannot move past the latest payment")]
+    CursorPastSequence,
+    #[msg("No accumulated = true instruction has this tag")]
+    NotAccumulated,
+    #[msg("The config needs a cluster other than Unset")]
+    ClusterNotGiven,
+    #[msg("The config's cluster is already recorded")]
+    ClusterAlreadySet,
+    #[msg("An account initialize_all creates already exists; initialize_all_idempotent skips it")]
+    AlreadyInitialized,
+    #[msg("No priced instruction has this tag")]
+    UnknownTag,
+    #[msg("The instruction explained is priced from an account not passed")]
+    PricingAccountMissing,
+    #[msg("A snapshot ledger is not that of the payer in the batch at its position")]
+    SnapshotBatchMismatch,
+    #[msg("Snapshot ledgers must be added in ascending payer order, each once")]
+    SnapshotOutOfOrder,
+    #[msg("The snapshot holds as many ledgers as it can")]
+    SnapshotFull,
+    #[msg("Account already exists")]
+    AccountAlreadyInitialized,
 }
@@ -0,0 +1,575 @@
+//! Upgrades of accounts on an older layout: those created before the
+//! program's accounts carried a `version` byte, and version 1 to 5
+//! `ComputeResult`s, which predate `created_slot`, `bind_to`,
+//! `fingerprint`, `ref_count` and `provenance`, version 1
+//! `CongestionLevel`s, which predate `previous_level` and `updated_at`,
+//! version 1 `PaymentLedger`s, which predate `total_rent_paid`, version 1
+//! and 2 `X402Stats`, which predate `commitment` and the zero-copy layout,
+//! and version 3 ones, which predate `total_rent_paid`, the version 1
+//! `X402GlobalConfig`, which predates `operator`, and version 2 ones,
+//! which predate `cluster`, and the version 1 `FailureLog`, which predates
+//! the zero-copy layout.
+//!
+//! Old accounts are recognized by their exact length, since every one was
+//! created with the fixed space of its layout. The `from_v*` functions are
+//! pure conversions of the bytes after the discriminator; [`upgrade`], or
+//! [`upgrade_zero_copy`] for zero-copy accounts, then reallocs the account
+//! and rewrites it.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use anchor_lang::{Discriminator, ZeroCopy};
+use x402_common::failures::{FailureEntry, FailureRing, FAILURE_LOG_CAPACITY};
+use x402_runtime::commitment::GENESIS_COMMITMENT;
+use x402_runtime::{Cluster, PriceProvenance};
+
+use crate::{
+    x402_example, ComputeResult, CongestionLevel, ErrorCode, FailureLog, PaymentLedger, SpendingCap, Versioned,
+    X402GlobalConfig, X402Stats, TIER_ENTERPRISE, TIER_FREE, TIER_PREMIUM, TIER_STANDARD,
+};
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV0 {
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV1 {
+    _version: u8,
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+    tier: u8,
+    price_paid: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV2 {
+    _version: u8,
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+    tier: u8,
+    price_paid: u64,
+    created_slot: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV3 {
+    _version: u8,
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+    tier: u8,
+    price_paid: u64,
+    created_slot: u64,
+    bind_to: Option<Pubkey>,
+}
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV4 {
+    _version: u8,
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+    tier: u8,
+    price_paid: u64,
+    created_slot: u64,
+    bind_to: Option<Pubkey>,
+    fingerprint: [u8; 32],
+}
+
+#[derive(AnchorDeserialize)]
+struct ComputeResultV5 {
+    _version: u8,
+    owner: Pubkey,
+    value: u64,
+    paid: bool,
+    amount_paid: u64,
+    tier: u8,
+    price_paid: u64,
+    created_slot: u64,
+    bind_to: Option<Pubkey>,
+    fingerprint: [u8; 32],
+    ref_count: u16,
+}
+
+#[derive(AnchorDeserialize)]
+struct PaymentLedgerV0 {
+    _payer: Pubkey,
+    total_payments: u64,
+    total_amount: u64,
+    last_payment: i64,
+}
+
+#[derive(AnchorDeserialize)]
+struct PaymentLedgerV1 {
+    _version: u8,
+    payer: Pubkey,
+    total_payments: u64,
+    total_amount: u64,
+    last_payment: i64,
+}
+
+#[derive(AnchorDeserialize)]
+struct X402StatsV0 {
+    sequence: u64,
+    total_payments: u64,
+    total_amount: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct X402StatsV1 {
+    _version: u8,
+    sequence: u64,
+    total_payments: u64,
+    total_amount: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct X402StatsV2 {
+    _version: u8,
+    sequence: u64,
+    total_payments: u64,
+    total_amount: u64,
+    commitment: [u8; 32],
+}
+
+/// The zero-copy layout before `total_rent_paid`, padding included.
+#[derive(AnchorDeserialize)]
+struct X402StatsV3 {
+    _version: u8,
+    _padding: [u8; 7],
+    sequence: u64,
+    total_payments: u64,
+    total_amount: u64,
+    commitment: [u8; 32],
+}
+
+#[derive(AnchorDeserialize)]
+struct SpendingCapV0 {
+    payer: Pubkey,
+    period_start: i64,
+    period_secs: i64,
+    cap: u64,
+    spent: u64,
+}
+
+#[derive(AnchorDeserialize)]
+struct CongestionLevelV1 {
+    _version: u8,
+    authority: Pubkey,
+    level: u8,
+}
+
+#[derive(AnchorDeserialize)]
+struct X402GlobalConfigV1 {
+    _version: u8,
+    authority: Pubkey,
+    recipient: Pubkey,
+}
+
+#[derive(AnchorDeserialize)]
+struct X402GlobalConfigV2 {
+    _version: u8,
+    authority: Pubkey,
+    recipient: Pubkey,
+    operator: Pubkey,
+}
+
+/// A `FailureEntry` as Borsh laid it out, without padding.
+#[derive(AnchorDeserialize)]
+struct FailureEntryV1 {
+    payer: Pubkey,
+    reason: u8,
+    attempted_amount: u64,
+    slot: u64,
+}
+
+pub const COMPUTE_RESULT_V0_SPACE: usize = 32 + 8 + 1 + 8;
+pub const COMPUTE_RESULT_V1_SPACE: usize = 1 + COMPUTE_RESULT_V0_SPACE + 1 + 8;
+pub const COMPUTE_RESULT_V2_SPACE: usize = COMPUTE_RESULT_V1_SPACE + 8;
+/// Space reserved for a `Some` `bind_to`, which `None` results also took.
+pub const COMPUTE_RESULT_V3_SPACE: usize = COMPUTE_RESULT_V2_SPACE + 1 + 32;
+pub const COMPUTE_RESULT_V4_SPACE: usize = COMPUTE_RESULT_V3_SPACE + 32;
+pub const COMPUTE_RESULT_V5_SPACE: usize = COMPUTE_RESULT_V4_SPACE + 2;
+pub const PAYMENT_LEDGER_V0_SPACE: usize = 32 + 8 + 8 + 8;
+pub const PAYMENT_LEDGER_V1_SPACE: usize = 1 + PAYMENT_LEDGER_V0_SPACE;
+pub const X402_STATS_V0_SPACE: usize = 8 + 8 + 8;
+pub const X402_STATS_V1_SPACE: usize = 1 + X402_STATS_V0_SPACE;
+pub const X402_STATS_V2_SPACE: usize = X402_STATS_V1_SPACE + 32;
+pub const X402_STATS_V3_SPACE: usize = X402_STATS_V2_SPACE + 7;
+pub const SPENDING_CAP_V0_SPACE: usize = 32 + 8 + 8 + 8 + 8;
+pub const CONGESTION_LEVEL_V1_SPACE: usize = 1 + 32 + 1;
+pub const X402_CONFIG_V1_SPACE: usize = 1 + 32 + 32;
+pub const X402_CONFIG_V2_SPACE: usize = X402_CONFIG_V1_SPACE + 32;
+const FAILURE_ENTRY_V1_SPACE: usize = 32 + 1 + 8 + 8;
+pub const FAILURE_LOG_V1_SPACE: usize = 1 + 1 + 1 + FAILURE_LOG_CAPACITY * FAILURE_ENTRY_V1_SPACE;
+
+/// Version 0 results did not record the tier, so it is inferred as the
+/// most expensive tier the amount paid covers.
+pub fn compute_result_from_v0(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV0::try_from_slice(data)?;
+
+    let (tier, price_paid) = if !old.paid {
+        (TIER_FREE, 0)
+    } else {
+        [
+            (TIER_ENTERPRISE, x402_example::COMPUTE_ENTERPRISE_X402_PARAMS.price),
+            (TIER_STANDARD, x402_example::COMPUTE_STANDARD_X402_PARAMS.price),
+            (TIER_PREMIUM, x402_example::COMPUTE_PREMIUM_X402_PARAMS.price),
+        ]
+        .into_iter()
+        .find(|&(_, price)| old.amount_paid >= price)
+        .ok_or(ErrorCode::ResultPriceTooLow)?
+    };
+
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier,
+        price_paid,
+        created_slot: 0,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// The slot a version 1 result was created in was not recorded;
+/// `created_slot` is left 0.
+pub fn compute_result_from_v1(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV1::try_from_slice(data)?;
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier: old.tier,
+        price_paid: old.price_paid,
+        created_slot: 0,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// Results from before version 3 stay consumable by their owner alone.
+pub fn compute_result_from_v2(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV2::try_from_slice(data)?;
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier: old.tier,
+        price_paid: old.price_paid,
+        created_slot: old.created_slot,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// Results from before version 4 were not fingerprinted.
+pub fn compute_result_from_v3(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV3::deserialize(&mut &data[..])?;
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier: old.tier,
+        price_paid: old.price_paid,
+        created_slot: old.created_slot,
+        bind_to: old.bind_to,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// Results from before version 5 had no dependents recorded, since
+/// `consume_result` closed them.
+pub fn compute_result_from_v4(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV4::deserialize(&mut &data[..])?;
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier: old.tier,
+        price_paid: old.price_paid,
+        created_slot: old.created_slot,
+        bind_to: old.bind_to,
+        fingerprint: old.fingerprint,
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// Results from before version 6 were all produced at the tiers' fixed
+/// prices.
+pub fn compute_result_from_v5(data: &[u8]) -> Result<ComputeResult> {
+    let old = ComputeResultV5::deserialize(&mut &data[..])?;
+    Ok(ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: old.owner,
+        value: old.value,
+        paid: old.paid,
+        amount_paid: old.amount_paid,
+        tier: old.tier,
+        price_paid: old.price_paid,
+        created_slot: old.created_slot,
+        bind_to: old.bind_to,
+        fingerprint: old.fingerprint,
+        ref_count: old.ref_count,
+        provenance: PriceProvenance::Static,
+    })
+}
+
+/// Version 0 ledgers never had `payer` set; the migrating payer, whose
+/// ledger PDA the account is, takes its place.
+pub fn payment_ledger_from_v0(data: &[u8], payer: Pubkey) -> Result<PaymentLedger> {
+    let old = PaymentLedgerV0::try_from_slice(data)?;
+    Ok(PaymentLedger {
+        version: PaymentLedger::CURRENT_VERSION,
+        payer,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        last_payment: old.last_payment,
+        total_rent_paid: 0,
+    })
+}
+
+/// Ledgers from before version 2 never recorded rent, so their rent total
+/// starts at zero; `total_amount` is unchanged, whatever rent it may
+/// already have been mistaken for.
+pub fn payment_ledger_from_v1(data: &[u8]) -> Result<PaymentLedger> {
+    let old = PaymentLedgerV1::try_from_slice(data)?;
+    Ok(PaymentLedger {
+        version: PaymentLedger::CURRENT_VERSION,
+        payer: old.payer,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        last_payment: old.last_payment,
+        total_rent_paid: 0,
+    })
+}
+
+/// Stats from before version 2 start their commitment chain over, from
+/// `GENESIS_COMMITMENT`, at their current sequence.
+pub fn x402_stats_from_v0(data: &[u8]) -> Result<X402Stats> {
+    let old = X402StatsV0::try_from_slice(data)?;
+    Ok(X402Stats {
+        version: X402Stats::CURRENT_VERSION,
+        _padding: [0; 7],
+        sequence: old.sequence,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        commitment: GENESIS_COMMITMENT,
+        total_rent_paid: 0,
+    })
+}
+
+/// As [`x402_stats_from_v0`].
+pub fn x402_stats_from_v1(data: &[u8]) -> Result<X402Stats> {
+    let old = X402StatsV1::try_from_slice(data)?;
+    Ok(X402Stats {
+        version: X402Stats::CURRENT_VERSION,
+        _padding: [0; 7],
+        sequence: old.sequence,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        commitment: GENESIS_COMMITMENT,
+        total_rent_paid: 0,
+    })
+}
+
+/// Version 2 stats keep their commitment chain; only the layout changes.
+pub fn x402_stats_from_v2(data: &[u8]) -> Result<X402Stats> {
+    let old = X402StatsV2::try_from_slice(data)?;
+    Ok(X402Stats {
+        version: X402Stats::CURRENT_VERSION,
+        _padding: [0; 7],
+        sequence: old.sequence,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        commitment: old.commitment,
+        total_rent_paid: 0,
+    })
+}
+
+/// Version 3 stats keep everything; their rent total starts at zero.
+pub fn x402_stats_from_v3(data: &[u8]) -> Result<X402Stats> {
+    let old = X402StatsV3::try_from_slice(data)?;
+    Ok(X402Stats {
+        version: X402Stats::CURRENT_VERSION,
+        _padding: [0; 7],
+        sequence: old.sequence,
+        total_payments: old.total_payments,
+        total_amount: old.total_amount,
+        commitment: old.commitment,
+        total_rent_paid: 0,
+    })
+}
+
+pub fn spending_cap_from_v0(data: &[u8]) -> Result<SpendingCap> {
+    let old = SpendingCapV0::try_from_slice(data)?;
+    Ok(SpendingCap {
+        version: SpendingCap::CURRENT_VERSION,
+        payer: old.payer,
+        period_start: old.period_start,
+        period_secs: old.period_secs,
+        cap: old.cap,
+        spent: old.spent,
+    })
+}
+
+/// Version 1 levels never recorded a change, so the previous level is the
+/// current one and any grace window is already over.
+pub fn congestion_level_from_v1(data: &[u8]) -> Result<CongestionLevel> {
+    let old = CongestionLevelV1::try_from_slice(data)?;
+    Ok(CongestionLevel {
+        version: CongestionLevel::CURRENT_VERSION,
+        authority: old.authority,
+        level: old.level,
+        previous_level: old.level,
+        updated_at: 0,
+    })
+}
+
+/// Version 1 configs had no operator; their authority keeps the role.
+pub fn x402_config_from_v1(data: &[u8]) -> Result<X402GlobalConfig> {
+    let old = X402GlobalConfigV1::try_from_slice(data)?;
+    Ok(X402GlobalConfig {
+        version: X402GlobalConfig::CURRENT_VERSION,
+        authority: old.authority,
+        recipient: old.recipient,
+        operator: old.authority,
+        cluster: Cluster::Unset,
+    })
+}
+
+/// Version 2 configs recorded no cluster; until the authority sets one, a
+/// program built for a network refuses every priced call.
+pub fn x402_config_from_v2(data: &[u8]) -> Result<X402GlobalConfig> {
+    let old = X402GlobalConfigV2::try_from_slice(data)?;
+    Ok(X402GlobalConfig {
+        version: X402GlobalConfig::CURRENT_VERSION,
+        authority: old.authority,
+        recipient: old.recipient,
+        operator: old.operator,
+        cluster: Cluster::Unset,
+    })
+}
+
+/// Version 1 logs keep every entry and their place in the ring.
+pub fn failure_log_from_v1(data: &[u8]) -> Result<FailureLog> {
+    if data.len() != FAILURE_LOG_V1_SPACE {
+        return Err(ProgramError::InvalidAccountData.into());
+    }
+    let mut ring = FailureRing::default();
+    (ring.head, ring.len) = (data[1], data[2]);
+    for (entry, old) in ring.entries.iter_mut().zip(data[3..].chunks_exact(FAILURE_ENTRY_V1_SPACE)) {
+        let old = FailureEntryV1::try_from_slice(old)?;
+        *entry = FailureEntry::new(old.payer, old.reason, old.attempted_amount, old.slot);
+    }
+    Ok(FailureLog {
+        version: FailureLog::CURRENT_VERSION,
+        _padding: [0; 7],
+        ring,
+    })
+}
+
+/// Copies out the version 0 body of `account`, checking its discriminator
+/// and that it still has the version 0 length.
+pub fn v0_data<T: Discriminator>(account: &AccountInfo, v0_space: usize) -> Result<Vec<u8>> {
+    legacy_data::<T>(account, &[v0_space])
+}
+
+/// Copies out the body of `account`, checking its discriminator and that
+/// it has one of the `legacy_spaces` lengths.
+pub fn legacy_data<T: Discriminator>(account: &AccountInfo, legacy_spaces: &[usize]) -> Result<Vec<u8>> {
+    let data = account.try_borrow_data()?;
+    if data.len() < 8 || data[..8] != T::DISCRIMINATOR {
+        return Err(anchor_lang::error::ErrorCode::AccountDiscriminatorMismatch.into());
+    }
+    if !legacy_spaces.contains(&(data.len() - 8)) {
+        msg!("migrate: account of {} bytes is on the current layout", data.len());
+        return err!(ErrorCode::AccountAlreadyMigrated);
+    }
+    Ok(data[8..].to_vec())
+}
+
+/// Resizes `account` to `space` bytes, topping up rent from `payer`, and
+/// writes `upgraded` over it.
+pub fn upgrade<'info, T: AccountSerialize>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    upgraded: &T,
+    space: usize,
+) -> Result<()> {
+    resize(account, payer, system_program, space)?;
+    let mut data = account.try_borrow_mut_data()?;
+    upgraded.try_serialize(&mut &mut data[..])
+}
+
+/// [`upgrade`] for a zero-copy account, which is written as its
+/// discriminator and then its bytes.
+pub fn upgrade_zero_copy<'info, T: ZeroCopy + Discriminator>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    upgraded: &T,
+) -> Result<()> {
+    let space = 8 + std::mem::size_of::<T>();
+    resize(account, payer, system_program, space)?;
+    let mut data = account.try_borrow_mut_data()?;
+    data[..8].copy_from_slice(&T::DISCRIMINATOR);
+    data[8..space].copy_from_slice(bytemuck::bytes_of(upgraded));
+    Ok(())
+}
+
+fn resize<'info>(
+    account: &AccountInfo<'info>,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+) -> Result<()> {
+    let rent = Rent::get()?.minimum_balance(space);
+    let shortfall = rent.saturating_sub(account.lamports());
+    if shortfall > 0 {
+        transfer(
+            CpiContext::new(
+                system_program.clone(),
+                Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            shortfall,
+        )?;
+    }
+
+    account.realloc(space, false)?;
+    Ok(())
+}
@@ -0,0 +1,303 @@
+//! Account layout of `PremiumCompute`, whose instructions sysvar is
+//! injected by `#[x402_accounts]`. The client-side struct Anchor generates
+//! is what the IDL describes, so this pins the layout clients build, along
+//! with the `PaymentLedger` layout clients list through `x402-common`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions;
+use anchor_lang::system_program;
+
+#[test]
+fn premium_compute_ends_with_the_instructions_sysvar() {
+    let payer = Pubkey::new_unique();
+    let result = Pubkey::new_unique();
+    let x402_stats = Pubkey::new_unique();
+    let accounts = x402_example::accounts::PremiumCompute {
+        payer,
+        result,
+        spending_cap: None,
+        x402_ledger: None,
+        x402_group_budget: None,
+        x402_stats,
+        system_program: system_program::ID,
+        x402_instructions: instructions::ID,
+    };
+
+    let metas = accounts.to_account_metas(None);
+    let layout: Vec<(Pubkey, bool, bool)> = metas
+        .iter()
+        .map(|meta| (meta.pubkey, meta.is_signer, meta.is_writable))
+        .collect();
+    assert_eq!(
+        layout,
+        vec![
+            (payer, true, true),
+            // A PDA of the payer and nonce, which the program signs for.
+            (result, false, true),
+            // The omitted optional spending cap, ledger and group budget
+            // are the program id.
+            (x402_example::ID, false, false),
+            (x402_example::ID, false, false),
+            (x402_example::ID, false, false),
+            (x402_stats, false, true),
+            (system_program::ID, false, false),
+            (instructions::ID, false, false),
+        ]
+    );
+}
+
+#[test]
+fn payment_ledger_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::ledger::{PaymentLedgerData, PAYMENT_LEDGER_DISCRIMINATOR};
+
+    assert_eq!(x402_example::PaymentLedger::DISCRIMINATOR, PAYMENT_LEDGER_DISCRIMINATOR);
+
+    let ledger = x402_example::PaymentLedger {
+        version: 2,
+        payer: Pubkey::new_unique(),
+        total_payments: 4,
+        total_amount: 4_000_000,
+        last_payment: 1_700_000_000,
+        total_rent_paid: 2_039_280,
+    };
+    let mut data = Vec::new();
+    ledger.try_serialize(&mut data).unwrap();
+    let shared = PaymentLedgerData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        PaymentLedgerData {
+            version: ledger.version,
+            payer: ledger.payer,
+            total_payments: ledger.total_payments,
+            total_amount: ledger.total_amount,
+            last_payment: ledger.last_payment,
+            total_rent_paid: ledger.total_rent_paid,
+        }
+    );
+}
+
+#[test]
+fn failure_log_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::failures::{
+        FailureEntry, FailureLogData, FAILURE_LOG_DISCRIMINATOR, REPORT_FAILED_PAYMENT_DISCRIMINATOR,
+    };
+
+    assert_eq!(x402_example::FailureLog::DISCRIMINATOR, FAILURE_LOG_DISCRIMINATOR);
+    assert_eq!(
+        x402_example::instruction::ReportFailedPayment::DISCRIMINATOR,
+        REPORT_FAILED_PAYMENT_DISCRIMINATOR
+    );
+
+    let mut log: x402_example::FailureLog = bytemuck::Zeroable::zeroed();
+    log.version = 2;
+    log.ring.push(FailureEntry::new(Pubkey::new_unique(), 4, 999_999, 77));
+    assert_eq!(std::mem::size_of::<FailureLogData>(), std::mem::size_of::<x402_example::FailureLog>());
+    let shared: FailureLogData = bytemuck::pod_read_unaligned(bytemuck::bytes_of(&log));
+    assert_eq!(shared, FailureLogData::new(log.version, log.ring));
+}
+
+#[test]
+fn indexer_cursor_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::cursor::{
+        IndexerCursorData, ADVANCE_CURSOR_DISCRIMINATOR, CREATE_CURSOR_DISCRIMINATOR, INDEXER_CURSOR_DISCRIMINATOR,
+    };
+
+    assert_eq!(x402_example::IndexerCursor::DISCRIMINATOR, INDEXER_CURSOR_DISCRIMINATOR);
+    assert_eq!(x402_example::instruction::CreateCursor::DISCRIMINATOR, CREATE_CURSOR_DISCRIMINATOR);
+    assert_eq!(x402_example::instruction::AdvanceCursor::DISCRIMINATOR, ADVANCE_CURSOR_DISCRIMINATOR);
+
+    let cursor = x402_example::IndexerCursor {
+        version: 1,
+        creator: Pubkey::new_unique(),
+        last_sequence: 41,
+    };
+    let mut data = Vec::new();
+    cursor.try_serialize(&mut data).unwrap();
+    let shared = IndexerCursorData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        IndexerCursorData {
+            version: cursor.version,
+            creator: cursor.creator,
+            last_sequence: cursor.last_sequence,
+        }
+    );
+}
+
+#[test]
+fn stats_match_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::stats::{StatsData, X402_STATS_DISCRIMINATOR};
+
+    assert_eq!(x402_example::X402Stats::DISCRIMINATOR, X402_STATS_DISCRIMINATOR);
+
+    let mut stats: x402_example::X402Stats = bytemuck::Zeroable::zeroed();
+    stats.version = 4;
+    stats.sequence = 12;
+    stats.total_payments = 11;
+    stats.total_amount = 11_000_000;
+    stats.commitment = [6; 32];
+    stats.total_rent_paid = 2_039_280;
+    assert_eq!(std::mem::size_of::<StatsData>(), std::mem::size_of::<x402_example::X402Stats>());
+    let shared: StatsData = bytemuck::pod_read_unaligned(bytemuck::bytes_of(&stats));
+    assert_eq!(
+        (shared.version, shared.sequence, shared.total_payments, shared.total_amount, shared.commitment),
+        (4, 12, 11, 11_000_000, [6; 32])
+    );
+    assert_eq!(shared.total_rent_paid, 2_039_280);
+}
+
+#[test]
+fn congestion_level_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::congestion::{CongestionLevelData, CONGESTION_LEVEL_DISCRIMINATOR};
+
+    assert_eq!(x402_example::CongestionLevel::DISCRIMINATOR, CONGESTION_LEVEL_DISCRIMINATOR);
+
+    let congestion = x402_example::CongestionLevel {
+        version: 2,
+        authority: Pubkey::new_unique(),
+        level: 3,
+        previous_level: 1,
+        updated_at: 1_700_000_000,
+    };
+    let mut data = Vec::new();
+    congestion.try_serialize(&mut data).unwrap();
+    let shared = CongestionLevelData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        CongestionLevelData {
+            version: congestion.version,
+            authority: congestion.authority,
+            level: congestion.level,
+            previous_level: congestion.previous_level,
+            updated_at: congestion.updated_at,
+        }
+    );
+}
+
+#[test]
+fn global_config_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::config::{GlobalConfigData, X402_CONFIG_DISCRIMINATOR};
+
+    assert_eq!(x402_example::X402GlobalConfig::DISCRIMINATOR, X402_CONFIG_DISCRIMINATOR);
+
+    let config = x402_example::X402GlobalConfig {
+        version: 3,
+        authority: Pubkey::new_unique(),
+        recipient: Pubkey::new_unique(),
+        operator: Pubkey::new_unique(),
+        cluster: x402_runtime::Cluster::Testnet,
+    };
+    let mut data = Vec::new();
+    config.try_serialize(&mut data).unwrap();
+    let shared = GlobalConfigData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        GlobalConfigData {
+            version: config.version,
+            authority: config.authority,
+            recipient: config.recipient,
+            operator: config.operator,
+            cluster: config.cluster,
+        }
+    );
+}
+
+#[test]
+fn price_experiment_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::experiment::{PriceExperimentData, PRICE_EXPERIMENT_DISCRIMINATOR};
+
+    assert_eq!(x402_example::PriceExperiment::DISCRIMINATOR, PRICE_EXPERIMENT_DISCRIMINATOR);
+
+    let experiment = x402_example::PriceExperiment {
+        version: 1,
+        authority: Pubkey::new_unique(),
+        ended: true,
+        winner: 1,
+    };
+    let mut data = Vec::new();
+    experiment.try_serialize(&mut data).unwrap();
+    let shared = PriceExperimentData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        PriceExperimentData {
+            version: experiment.version,
+            authority: experiment.authority,
+            ended: experiment.ended,
+            winner: experiment.winner,
+        }
+    );
+}
+
+#[test]
+fn compute_result_matches_the_shared_layout() {
+    use anchor_lang::Discriminator;
+    use x402_common::result::{ComputeResultData, COMPUTE_RESULT_DISCRIMINATOR};
+
+    assert_eq!(x402_example::ComputeResult::DISCRIMINATOR, COMPUTE_RESULT_DISCRIMINATOR);
+
+    let result = x402_example::ComputeResult {
+        version: 6,
+        owner: Pubkey::new_unique(),
+        value: 42,
+        paid: true,
+        amount_paid: 1_000_001,
+        tier: x402_example::TIER_PREMIUM,
+        price_paid: 1_000_000,
+        created_slot: 300,
+        bind_to: Some(Pubkey::new_unique()),
+        fingerprint: [7; 32],
+        ref_count: 2,
+        provenance: x402_runtime::PriceProvenance::Congestion {
+            account: Pubkey::new_unique(),
+            level: 3,
+            updated_at: 1_700_000_000,
+        },
+    };
+    let mut data = Vec::new();
+    result.try_serialize(&mut data).unwrap();
+    let shared = ComputeResultData::try_from_slice(&data[8..]).unwrap();
+    assert_eq!(
+        shared,
+        ComputeResultData {
+            version: result.version,
+            owner: result.owner,
+            value: result.value,
+            paid: result.paid,
+            amount_paid: result.amount_paid,
+            tier: result.tier,
+            price_paid: result.price_paid,
+            created_slot: result.created_slot,
+            bind_to: result.bind_to,
+            fingerprint: result.fingerprint,
+            ref_count: result.ref_count,
+            provenance: result.provenance,
+        }
+    );
+}
+
+#[test]
+fn compute_budget_embeds_the_shared_group_budget() {
+    use x402_runtime::group::GroupBudget;
+
+    let account = x402_example::ComputeBudget {
+        version: 1,
+        budget: GroupBudget {
+            last_slot: 300,
+            transaction: [4; 32],
+            consumed_this_slot: 56_000_000,
+            paid_this_slot: 50_000_000,
+            counted_through: 5,
+        },
+    };
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    assert_eq!(data.len(), 8 + x402_example::ComputeBudget::INIT_SPACE);
+    assert_eq!(GroupBudget::try_from_slice(&data[9..]).unwrap(), account.budget);
+}
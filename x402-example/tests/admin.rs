@@ -0,0 +1,65 @@
+//! Handing over the `X402GlobalConfig`'s authority, and what its operator
+//! may and may not do.
+
+use anchor_lang::prelude::*;
+use x402_example::{hand_over_x402_authority, record_cluster, ErrorCode, Versioned, X402GlobalConfig};
+use x402_runtime::{Cluster, X402Error};
+
+fn config(authority: Pubkey, operator: Pubkey) -> X402GlobalConfig {
+    X402GlobalConfig {
+        version: X402GlobalConfig::CURRENT_VERSION,
+        authority,
+        recipient: Pubkey::new_unique(),
+        operator,
+        cluster: Cluster::MainnetBeta,
+    }
+}
+
+fn as_signer<T>(key: &Pubkey, run: impl FnOnce(&AccountInfo) -> T) -> T {
+    let (mut lamports, mut data) = (0, vec![]);
+    let owner = Pubkey::default();
+    run(&AccountInfo::new(key, true, false, &mut lamports, &mut data, &owner, false, 0))
+}
+
+#[test]
+fn handover_moves_every_authority_right() {
+    let (old, new, operator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut config = config(old, operator);
+
+    as_signer(&old, |signer| hand_over_x402_authority(&mut config, signer, new)).unwrap();
+    assert_eq!(config.authority, new);
+    assert_eq!(config.operator, operator);
+
+    let err = as_signer(&old, |signer| hand_over_x402_authority(&mut config, signer, old)).unwrap_err();
+    assert_eq!(err, X402Error::NotX402Admin.into());
+    let err = as_signer(&old, |signer| config.admin().require_operator(signer)).unwrap_err();
+    assert_eq!(err, X402Error::NotX402Admin.into());
+
+    as_signer(&new, |signer| hand_over_x402_authority(&mut config, signer, old)).unwrap();
+    assert_eq!(config.authority, old);
+}
+
+#[test]
+fn operator_cannot_take_the_authority() {
+    let (authority, operator) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut config = config(authority, operator);
+
+    as_signer(&operator, |signer| config.admin().require_operator(signer)).unwrap();
+    let err = as_signer(&operator, |signer| hand_over_x402_authority(&mut config, signer, operator)).unwrap_err();
+    assert_eq!(err, X402Error::NotX402Admin.into());
+    assert_eq!(config.authority, authority);
+}
+
+#[test]
+fn a_recorded_cluster_never_changes() {
+    let mut config = config(Pubkey::new_unique(), Pubkey::new_unique());
+    let err = record_cluster(&mut config, Cluster::Devnet).unwrap_err();
+    assert_eq!(err, ErrorCode::ClusterAlreadySet.into());
+    assert_eq!(config.cluster, Cluster::MainnetBeta);
+
+    // A config migrated from version 2 records one once.
+    config.cluster = Cluster::Unset;
+    assert_eq!(record_cluster(&mut config, Cluster::Unset).unwrap_err(), ErrorCode::ClusterNotGiven.into());
+    record_cluster(&mut config, Cluster::Devnet).unwrap();
+    assert_eq!(config.cluster, Cluster::Devnet);
+}
@@ -0,0 +1,95 @@
+//! `examples/cli.rs`'s `init`: its arguments, and the instruction it sends.
+
+#[path = "../examples/cli.rs"]
+#[allow(dead_code)]
+mod cli;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::{AnchorDeserialize, Discriminator};
+use x402_common::seeds::{config_address, manifest_address};
+use x402_example::InitParams;
+use x402_runtime::Cluster;
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+#[test]
+fn parses_init() {
+    let (recipient, operator) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (recipient_arg, operator_arg) = (recipient.to_string(), operator.to_string());
+    let command = cli::parse(&args(&[
+        "init",
+        "--recipient",
+        &recipient_arg,
+        "--operator",
+        &operator_arg,
+        "--cluster",
+        "devnet",
+        "--idempotent",
+        "--keypair",
+        "deployer.json",
+        "--url",
+        "https://api.devnet.solana.com",
+    ]))
+    .unwrap();
+
+    assert_eq!(
+        command.params,
+        InitParams {
+            recipient,
+            operator,
+            cluster: Cluster::Devnet,
+        }
+    );
+    assert!(command.idempotent);
+    assert_eq!(command.keypair, std::path::PathBuf::from("deployer.json"));
+    assert_eq!(command.url, "https://api.devnet.solana.com");
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    let key = Pubkey::new_unique().to_string();
+    let err = cli::parse(&args(&["deploy"])).unwrap_err();
+    assert!(err.starts_with("unknown command `deploy`"), "{}", err);
+
+    let err = cli::parse(&args(&["init", "--recipient", &key, "--operator", &key])).unwrap_err();
+    assert_eq!(err, "--cluster is required");
+    let err = cli::parse(&args(&["init", "--recipient", &key, "--operator", &key, "--cluster", "moon"])).unwrap_err();
+    assert_eq!(err, "--cluster must be one of mainnet-beta, devnet, testnet, localnet, not `moon`");
+    let err = cli::parse(&args(&["init", "--recipient", "alice"])).unwrap_err();
+    assert_eq!(err, "--recipient is not a public key: `alice`");
+    let err = cli::parse(&args(&["init", "--operator"])).unwrap_err();
+    assert_eq!(err, "--operator needs a value");
+    let err = cli::parse(&args(&["init", "--force"])).unwrap_err();
+    assert!(err.starts_with("unknown flag `--force`"), "{}", err);
+}
+
+#[test]
+fn init_sends_initialize_all_signed_by_the_authority() {
+    let (payer, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let params = InitParams {
+        recipient: Pubkey::new_unique(),
+        operator: Pubkey::new_unique(),
+        cluster: Cluster::MainnetBeta,
+    };
+
+    for idempotent in [false, true] {
+        let instruction = cli::init_instruction(&x402_example::ID, &payer, &authority, params, idempotent);
+        assert_eq!(instruction.program_id, x402_example::ID);
+
+        let signers: Vec<_> =
+            instruction.accounts.iter().filter(|meta| meta.is_signer).map(|meta| meta.pubkey).collect();
+        assert_eq!(signers, [payer, authority]);
+        assert_eq!(instruction.accounts[4].pubkey, config_address(&x402_example::ID));
+        assert_eq!(instruction.accounts[10].pubkey, manifest_address(&x402_example::ID));
+
+        let discriminator = if idempotent {
+            x402_example::instruction::InitializeAllIdempotent::DISCRIMINATOR
+        } else {
+            x402_example::instruction::InitializeAll::DISCRIMINATOR
+        };
+        assert_eq!(&instruction.data[..8], discriminator);
+        assert_eq!(InitParams::deserialize(&mut &instruction.data[8..]).unwrap(), params);
+    }
+}
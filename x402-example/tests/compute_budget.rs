@@ -0,0 +1,291 @@
+//! What the injected check costs, measured on a local validator. The
+//! compute units `priced_query` consumes with its payment are compared with
+//! those of `quote_priced_query`, which reads the same `CongestionLevel` and
+//! computes the same price without checking a payment; the difference must
+//! stay under [`VERIFICATION_CU_BUDGET`]. It needs `solana-test-validator`
+//! and the program built with `anchor build`, so it only runs when asked for:
+//!
+//! ```text
+//! anchor build
+//! cargo test -p x402-example --test compute_budget -- --ignored --nocapture
+//! ```
+
+#[path = "../examples/validator/mod.rs"]
+mod validator;
+
+use std::error::Error;
+
+use anchor_lang::prelude::Pubkey;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::{bpf_loader_upgradeable, system_program, sysvar};
+use anchor_lang::{InstructionData, ToAccountMetas};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use x402_client::{
+    paid_call_instructions, preflight_paid_call, quote, Preflight, PreflightOutcome, PricedInstruction, Quote,
+};
+use x402_example::x402_example::{priced_query_x402_layout, PRICED_QUERY_X402_PARAMS, PRICED_QUERY_X402_SURCHARGE};
+use x402_runtime::{X402Error, X402Params, IGNORABLE_PROGRAMS};
+
+use validator::{start_validator, Validator};
+
+/// Compute units the check injected into a priced handler may add to it.
+const VERIFICATION_CU_BUDGET: u64 = 10_000;
+
+/// A funded payer on a validator where `priced_query` can be called.
+struct Bench {
+    rpc: RpcClient,
+    payer: Keypair,
+    quote: Quote,
+}
+
+impl Bench {
+    /// Starts a validator with the program deployed by the payer, who may
+    /// then create the congestion level.
+    fn start() -> Result<(Validator, Bench), Box<dyn Error>> {
+        let payer = Keypair::new();
+        let (validator, url) = start_validator(&payer.pubkey())?;
+        let rpc = RpcClient::new_with_commitment(url, CommitmentConfig::confirmed());
+        let airdrop = rpc.request_airdrop(&payer.pubkey(), LAMPORTS_PER_SOL)?;
+        rpc.poll_for_signature(&airdrop)?;
+
+        let congestion = PRICED_QUERY_X402_SURCHARGE.account;
+        if rpc.get_account_with_commitment(&congestion, rpc.commitment())?.value.is_none() {
+            let program_data =
+                Pubkey::find_program_address(&[x402_example::ID.as_ref()], &bpf_loader_upgradeable::ID).0;
+            let initialize = Instruction {
+                program_id: x402_example::ID,
+                accounts: x402_example::accounts::InitializeCongestion {
+                    payer: payer.pubkey(),
+                    authority: payer.pubkey(),
+                    program: x402_example::ID,
+                    program_data,
+                    x402_congestion: congestion,
+                    system_program: system_program::ID,
+                }
+                .to_account_metas(None),
+                data: x402_example::instruction::InitializeCongestion {
+                    authority: payer.pubkey(),
+                }
+                .data(),
+            };
+            let transaction = Transaction::new_signed_with_payer(
+                &[initialize],
+                Some(&payer.pubkey()),
+                &[&payer],
+                rpc.get_latest_blockhash()?,
+            );
+            rpc.send_and_confirm_transaction(&transaction)?;
+        }
+
+        let priced_query = PricedInstruction::new(PRICED_QUERY_X402_PARAMS).with_surcharge(PRICED_QUERY_X402_SURCHARGE);
+        let quote = quote(&rpc, &x402_example::ID, &priced_query)?;
+        Ok((validator, Bench { rpc, payer, quote }))
+    }
+
+    fn priced_query(&self) -> Result<Instruction, Box<dyn Error>> {
+        Ok(Instruction {
+            program_id: x402_example::ID,
+            accounts: x402_example::accounts::PricedQuery {
+                payer: self.payer.pubkey(),
+                x402_congestion: PRICED_QUERY_X402_SURCHARGE.account,
+            }
+            .to_account_metas(None),
+            data: x402_example::instruction::PricedQuery {
+                max_acceptable_price: self.quote.max_acceptable_price().ok_or("priced_query is surcharged")?,
+            }
+            .data(),
+        })
+    }
+
+    /// `priced_query` paid as its layout asks.
+    fn paid_call(&self) -> Result<Vec<Instruction>, Box<dyn Error>> {
+        let layout = priced_query_x402_layout();
+        Ok(paid_call_instructions(&layout, &self.payer.pubkey(), &self.quote.params(), self.priced_query()?)?)
+    }
+
+    /// The same congestion read and price, without a payment.
+    fn unpriced_call(&self) -> Instruction {
+        Instruction {
+            program_id: x402_example::ID,
+            accounts: x402_example::accounts::QuotePrice {
+                x402_congestion: PRICED_QUERY_X402_SURCHARGE.account,
+            }
+            .to_account_metas(None),
+            data: x402_example::instruction::QuotePricedQuery {}.data(),
+        }
+    }
+
+    fn simulate(&self, instructions: &[Instruction]) -> Result<Preflight, Box<dyn Error>> {
+        let transaction = Transaction::new_with_payer(instructions, Some(&self.payer.pubkey()));
+        Ok(preflight_paid_call(&self.rpc, &transaction)?)
+    }
+
+    /// Compute units the program's instruction consumed in a simulation of
+    /// `instructions`.
+    fn units(&self, instructions: &[Instruction]) -> Result<u64, Box<dyn Error>> {
+        let preflight = self.simulate(instructions)?;
+        Ok(program_units(&preflight.logs, &x402_example::ID).ok_or("the program logged no compute units")?)
+    }
+}
+
+/// The units of the first `Program <id> consumed N of M compute units` line
+/// of `program_id` in `logs`.
+fn program_units(logs: &[String], program_id: &Pubkey) -> Option<u64> {
+    let prefix = format!("Program {} consumed ", program_id);
+    logs.iter().find_map(|line| line.strip_prefix(&prefix)?.split(' ').next()?.parse().ok())
+}
+
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn the_check_stays_within_its_budget() {
+    let (_validator, bench) = Bench::start().unwrap();
+
+    let unpriced = bench.units(&[bench.unpriced_call()]).unwrap();
+    let paid = bench.paid_call().unwrap();
+    let preflight = bench.simulate(&paid).unwrap();
+    assert!(
+        matches!(preflight.outcome, PreflightOutcome::Passes),
+        "the paid call passes: {:?}",
+        preflight.outcome
+    );
+    let priced = program_units(&preflight.logs, &x402_example::ID).unwrap();
+
+    let overhead = priced.saturating_sub(unpriced);
+    println!("priced_query: {} CU, quote_priced_query: {} CU, check: {} CU", priced, unpriced, overhead);
+    assert!(
+        overhead < VERIFICATION_CU_BUDGET,
+        "the check costs {} CU, over its budget of {}",
+        overhead,
+        VERIFICATION_CU_BUDGET
+    );
+}
+
+/// An underpaid call fails with `InsufficientPayment` and logs the amounts
+/// in SOL; formatting them stays within the budget.
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn an_underpayment_is_reported_within_the_budget() {
+    let (_validator, bench) = Bench::start().unwrap();
+
+    let unpriced = bench.units(&[bench.unpriced_call()]).unwrap();
+    let params = bench.quote.params();
+    let underpaid = X402Params {
+        price: params.price - 1,
+        ..params
+    };
+    let layout = priced_query_x402_layout();
+    let instructions =
+        paid_call_instructions(&layout, &bench.payer.pubkey(), &underpaid, bench.priced_query().unwrap()).unwrap();
+    let preflight = bench.simulate(&instructions).unwrap();
+    assert!(
+        matches!(
+            preflight.outcome,
+            PreflightOutcome::PaymentRejected {
+                error: X402Error::InsufficientPayment,
+                ..
+            }
+        ),
+        "the underpaid call is rejected: {:?}",
+        preflight.outcome
+    );
+    assert!(
+        preflight
+            .logs
+            .iter()
+            .any(|line| line.contains("SOL to the recipient, but the price is") && line.ends_with(" SOL")),
+        "the amounts are logged in SOL: {:?}",
+        preflight.logs
+    );
+
+    let rejected = program_units(&preflight.logs, &x402_example::ID).unwrap();
+    let overhead = rejected.saturating_sub(unpriced);
+    println!("underpaid priced_query: {} CU, check: {} CU", rejected, overhead);
+    assert!(
+        overhead < VERIFICATION_CU_BUDGET,
+        "rejecting the underpayment costs {} CU, over the budget of {}",
+        overhead,
+        VERIFICATION_CU_BUDGET
+    );
+}
+
+/// A call without the instructions sysvar fails on the first check, for a
+/// fraction of what verifying a payment costs.
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn a_missing_sysvar_fails_before_the_scan() {
+    let (_validator, bench) = Bench::start().unwrap();
+
+    let unpriced = bench.units(&[bench.unpriced_call()]).unwrap();
+    let paid = bench.units(&bench.paid_call().unwrap()).unwrap();
+    let mut instructions = bench.paid_call().unwrap();
+    let call = instructions.last_mut().unwrap();
+    call.accounts.retain(|meta| meta.pubkey != sysvar::instructions::ID);
+    let preflight = bench.simulate(&instructions).unwrap();
+    assert!(
+        matches!(
+            preflight.outcome,
+            PreflightOutcome::PaymentRejected {
+                error: X402Error::SysvarMissing,
+                ..
+            }
+        ),
+        "the call without the sysvar is rejected: {:?}",
+        preflight.outcome
+    );
+
+    let verified = paid.saturating_sub(unpriced);
+    let rejected = program_units(&preflight.logs, &x402_example::ID).unwrap().saturating_sub(unpriced);
+    println!("check: {} CU verifying the payment, {} CU without the sysvar", verified, rejected);
+    assert!(
+        rejected < verified / 2,
+        "failing without the sysvar costs {} CU, not much less than the {} CU of a verification",
+        rejected,
+        verified
+    );
+}
+
+/// Compute budget and memo instructions between the payment and the call
+/// are skipped by program id, for little each.
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn skipping_ignorable_instructions_stays_within_the_budget() {
+    const SKIPPED: u64 = 6;
+
+    let (_validator, bench) = Bench::start().unwrap();
+
+    let unpriced = bench.units(&[bench.unpriced_call()]).unwrap();
+    let paid = bench.units(&bench.paid_call().unwrap()).unwrap();
+    let mut instructions = bench.paid_call().unwrap();
+    let call = instructions.pop().unwrap();
+    instructions.push(ComputeBudgetInstruction::set_compute_unit_limit(400_000));
+    for n in 1..SKIPPED {
+        instructions.push(Instruction {
+            program_id: IGNORABLE_PROGRAMS[1],
+            accounts: vec![],
+            data: format!("memo {}", n).into_bytes(),
+        });
+    }
+    instructions.push(call);
+    let preflight = bench.simulate(&instructions).unwrap();
+    assert!(
+        matches!(preflight.outcome, PreflightOutcome::Passes),
+        "the paid call passes: {:?}",
+        preflight.outcome
+    );
+
+    let skipping = program_units(&preflight.logs, &x402_example::ID).unwrap();
+    let per_skip = skipping.saturating_sub(paid) / SKIPPED;
+    println!("check: {} CU skipping {} instructions, {} CU each", skipping - unpriced, SKIPPED, per_skip);
+    assert!(
+        skipping.saturating_sub(unpriced) < VERIFICATION_CU_BUDGET,
+        "the check costs {} CU, over its budget of {}",
+        skipping - unpriced,
+        VERIFICATION_CU_BUDGET
+    );
+    assert!(per_skip < 1_000, "skipping an ignorable instruction costs {} CU", per_skip);
+}
@@ -0,0 +1,127 @@
+//! The congestion surcharge on `priced_query`: the price its `#[x402]`
+//! check enforces and the one `quote_priced_query` reports must follow the
+//! `CongestionLevel` together as the authority moves it.
+
+use anchor_lang::prelude::*;
+use x402_example::x402_example::{PRICED_QUERY_X402_PARAMS, PRICED_QUERY_X402_SURCHARGE};
+use x402_example::{change_congestion_level, quote_price, CongestionLevel, ErrorCode, Versioned};
+use x402_runtime::congestion::{LevelChange, CONGESTION_SEED, MAX_CONGESTION_LEVEL};
+use x402_runtime::X402Error;
+
+fn congestion(level: u8) -> CongestionLevel {
+    CongestionLevel {
+        version: CongestionLevel::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        level,
+        previous_level: 0,
+        updated_at: 0,
+    }
+}
+
+/// `priced_query`'s `price_grace_window_secs`.
+const WINDOW_SECS: u32 = 30;
+
+/// The price `#[x402]` enforces at `now`, within the grace window.
+fn enforced_at(congestion: &CongestionLevel, now: i64) -> Result<u64> {
+    PRICED_QUERY_X402_SURCHARGE
+        .apply_with_window_at(
+            &PRICED_QUERY_X402_PARAMS,
+            &PRICED_QUERY_X402_SURCHARGE.account,
+            congestion.level,
+            LevelChange {
+                previous_level: congestion.previous_level,
+                updated_at: congestion.updated_at,
+            },
+            now,
+            WINDOW_SECS,
+        )
+        .map(|params| params.price)
+}
+
+/// The price `#[x402]` enforces given the handler's `x402_congestion`.
+fn enforced(account: &Pubkey, congestion: &CongestionLevel) -> Result<u64> {
+    PRICED_QUERY_X402_SURCHARGE
+        .apply_from(&PRICED_QUERY_X402_PARAMS, account, congestion.level)
+        .map(|params| params.price)
+}
+
+#[test]
+fn surcharge_reads_the_congestion_pda() {
+    let (pda, _) = Pubkey::find_program_address(&[CONGESTION_SEED], &x402_example::ID);
+    assert_eq!(PRICED_QUERY_X402_SURCHARGE.account, pda);
+    assert_eq!(PRICED_QUERY_X402_SURCHARGE.bps_per_level, 500);
+}
+
+#[test]
+fn enforced_and_quoted_prices_move_together() {
+    let account = PRICED_QUERY_X402_SURCHARGE.account;
+    let mut level = congestion(0);
+    let base = PRICED_QUERY_X402_PARAMS.price;
+
+    let quoted = quote_price(&PRICED_QUERY_X402_PARAMS, &PRICED_QUERY_X402_SURCHARGE, &level).unwrap();
+    assert_eq!(quoted, base);
+    assert_eq!(enforced(&account, &level).unwrap(), quoted);
+
+    // The authority raises the level between calls.
+    level.level = 3;
+    let quoted = quote_price(&PRICED_QUERY_X402_PARAMS, &PRICED_QUERY_X402_SURCHARGE, &level).unwrap();
+    assert_eq!(quoted, base * 115 / 100);
+    assert_eq!(enforced(&account, &level).unwrap(), quoted);
+
+    level.level = MAX_CONGESTION_LEVEL;
+    let quoted = quote_price(&PRICED_QUERY_X402_PARAMS, &PRICED_QUERY_X402_SURCHARGE, &level).unwrap();
+    assert_eq!(quoted, base * 125 / 100);
+    assert_eq!(enforced(&account, &level).unwrap(), quoted);
+
+    // And lowers it again.
+    level.level = 1;
+    assert_eq!(enforced(&account, &level).unwrap(), base * 105 / 100);
+}
+
+#[test]
+fn out_of_range_levels_are_rejected_by_both() {
+    let level = congestion(MAX_CONGESTION_LEVEL + 1);
+    let err = quote_price(&PRICED_QUERY_X402_PARAMS, &PRICED_QUERY_X402_SURCHARGE, &level).unwrap_err();
+    assert_eq!(err, error!(X402Error::InvalidCongestionLevel));
+    let err = enforced(&PRICED_QUERY_X402_SURCHARGE.account, &level).unwrap_err();
+    assert_eq!(err, error!(X402Error::InvalidCongestionLevel));
+}
+
+#[test]
+fn another_congestion_account_is_rejected() {
+    let err = enforced(&Pubkey::new_unique(), &congestion(0)).unwrap_err();
+    assert_eq!(err, error!(X402Error::CongestionAccountMismatch));
+}
+
+#[test]
+fn a_price_bump_is_graced_until_the_window_closes() {
+    let base = PRICED_QUERY_X402_PARAMS.price;
+    let mut level = congestion(0);
+    let quoted = quote_price(&PRICED_QUERY_X402_PARAMS, &PRICED_QUERY_X402_SURCHARGE, &level).unwrap();
+
+    // The authority raises the level just before the call lands.
+    change_congestion_level(&mut level, 4, 1_000).unwrap();
+    assert_eq!(level.previous_level, 0);
+    assert_eq!(enforced_at(&level, 1_000).unwrap(), quoted);
+    assert_eq!(enforced_at(&level, 1_000 + WINDOW_SECS as i64 - 1).unwrap(), quoted);
+
+    // Past the window the old quote no longer covers the price.
+    let after = enforced_at(&level, 1_000 + WINDOW_SECS as i64).unwrap();
+    assert_eq!(after, base * 120 / 100);
+    assert!(quoted < after);
+}
+
+#[test]
+fn a_price_drop_applies_at_once() {
+    let mut level = congestion(4);
+    change_congestion_level(&mut level, 1, 1_000).unwrap();
+    assert_eq!(enforced_at(&level, 1_000).unwrap(), PRICED_QUERY_X402_PARAMS.price * 105 / 100);
+}
+
+#[test]
+fn out_of_range_changes_are_rejected() {
+    let mut level = congestion(2);
+    let err = change_congestion_level(&mut level, MAX_CONGESTION_LEVEL + 1, 1_000).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::InvalidCongestionLevel));
+    assert_eq!((level.level, level.updated_at), (2, 0));
+}
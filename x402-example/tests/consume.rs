@@ -0,0 +1,101 @@
+//! `require_consumer` and `require_paid_result`, the checks
+//! `consume_result` applies before spending a compute result, and the
+//! dependents it records, which keep `reclaim_result` from closing it.
+
+use anchor_lang::prelude::*;
+use x402_example::{
+    add_dependent, require_consumer, require_no_dependents, require_paid_result, ComputeResult, ErrorCode,
+    Versioned, TIER_ENTERPRISE, TIER_FREE, TIER_PREMIUM,
+};
+use x402_runtime::PriceProvenance;
+
+fn result(tier: u8, paid: bool, price_paid: u64) -> ComputeResult {
+    ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: Pubkey::new_unique(),
+        value: 42,
+        paid,
+        amount_paid: price_paid,
+        tier,
+        price_paid,
+        created_slot: 1,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    }
+}
+
+fn assert_rejected(result: &ComputeResult, min_price: u64, expected: ErrorCode) {
+    let err = require_paid_result(result, min_price).unwrap_err();
+    assert_eq!(err, error!(expected));
+}
+
+#[test]
+fn accepts_results_priced_at_or_above_threshold() {
+    require_paid_result(&result(TIER_PREMIUM, true, 1_000_000), 1_000_000).unwrap();
+    require_paid_result(&result(TIER_ENTERPRISE, true, 50_000_000), 5_000_000).unwrap();
+}
+
+#[test]
+fn rejects_cheaper_tiers() {
+    assert_rejected(&result(TIER_PREMIUM, true, 1_000_000), 5_000_000, ErrorCode::ResultPriceTooLow);
+}
+
+#[test]
+fn rejects_free_results() {
+    assert_rejected(&result(TIER_FREE, false, 0), 0, ErrorCode::ResultNotPaid);
+}
+
+#[test]
+fn rejects_other_versions() {
+    let mut stale = result(TIER_PREMIUM, true, 1_000_000);
+    stale.version = 0;
+    assert_rejected(&stale, 0, ErrorCode::AccountNeedsMigration);
+
+    let mut newer = result(TIER_PREMIUM, true, 1_000_000);
+    newer.version = ComputeResult::CURRENT_VERSION + 1;
+    assert_rejected(&newer, 0, ErrorCode::UnsupportedAccountVersion);
+}
+
+#[test]
+fn third_party_cannot_consume_an_unbound_result() {
+    let result = result(TIER_PREMIUM, true, 1_000_000);
+    let err = require_consumer(&result, &Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::ConsumerNotAuthorized));
+}
+
+#[test]
+fn owner_and_bound_consumer_may_consume() {
+    let sponsor = Pubkey::new_unique();
+    let mut result = result(TIER_PREMIUM, true, 1_000_000);
+    require_consumer(&result, &result.owner).unwrap();
+
+    result.bind_to = Some(sponsor);
+    require_consumer(&result, &sponsor).unwrap();
+    // Binding adds a consumer; the owner keeps access.
+    require_consumer(&result, &result.owner).unwrap();
+    let err = require_consumer(&result, &Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::ConsumerNotAuthorized));
+}
+
+#[test]
+fn consuming_adds_a_dependent_that_blocks_a_free_close() {
+    let mut result = result(TIER_PREMIUM, true, 1_000_000);
+    require_no_dependents(&result).unwrap();
+
+    add_dependent(&mut result).unwrap();
+    add_dependent(&mut result).unwrap();
+    assert_eq!(result.ref_count, 2);
+    let err = require_no_dependents(&result).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::ResultHasDependents));
+}
+
+#[test]
+fn dependents_do_not_wrap_around() {
+    let mut result = result(TIER_PREMIUM, true, 1_000_000);
+    result.ref_count = u16::MAX;
+    let err = add_dependent(&mut result).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::TooManyDependents));
+    assert_eq!(result.ref_count, u16::MAX);
+}
@@ -0,0 +1,43 @@
+//! Advancing an indexer's cursor: it only moves forward, and never past
+//! the latest payment, so a restarted indexer resumes exactly where it
+//! stopped.
+
+use anchor_lang::prelude::*;
+use x402_example::{advance_indexer_cursor, ErrorCode, IndexerCursor, Versioned};
+
+fn cursor() -> IndexerCursor {
+    IndexerCursor {
+        version: IndexerCursor::CURRENT_VERSION,
+        creator: Pubkey::new_unique(),
+        last_sequence: 0,
+    }
+}
+
+#[test]
+fn advances_through_the_latest_payment() {
+    let mut cursor = cursor();
+    advance_indexer_cursor(&mut cursor, 3, 10).unwrap();
+    advance_indexer_cursor(&mut cursor, 10, 10).unwrap();
+    assert_eq!(cursor.last_sequence, 10);
+}
+
+#[test]
+fn never_moves_back_or_stays() {
+    let mut cursor = cursor();
+    advance_indexer_cursor(&mut cursor, 5, 10).unwrap();
+
+    // A restarted indexer re-confirming the batch it already advanced past.
+    let err = advance_indexer_cursor(&mut cursor, 5, 10).unwrap_err();
+    assert_eq!(err, ErrorCode::CursorNotAdvanced.into());
+    let err = advance_indexer_cursor(&mut cursor, 4, 10).unwrap_err();
+    assert_eq!(err, ErrorCode::CursorNotAdvanced.into());
+    assert_eq!(cursor.last_sequence, 5);
+}
+
+#[test]
+fn cannot_skip_past_payments_not_made_yet() {
+    let mut cursor = cursor();
+    let err = advance_indexer_cursor(&mut cursor, 11, 10).unwrap_err();
+    assert_eq!(err, ErrorCode::CursorPastSequence.into());
+    assert_eq!(cursor.last_sequence, 0);
+}
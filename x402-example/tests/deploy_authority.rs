@@ -0,0 +1,239 @@
+//! The one-off initializers besides `initialize_x402` and `initialize_all`
+//! (free tier, congestion level, experiment, stats, reentrancy guard and
+//! manifest) are open only to the program's upgrade authority, so nobody
+//! else can create their accounts first and set their authority.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use anchor_lang::system_program;
+use x402_example::x402_example as handlers;
+use x402_example::{
+    manifest_entries, CongestionLevel, FreeTierConfig, InitializeCongestion, InitializeExperiment, InitializeFreeTier,
+    InitializeReentrancyGuard, InitializeStats, PriceExperiment, PublishManifest, ReentrancyGuard, X402Stats,
+};
+use x402_runtime::manifest::ManifestData;
+use x402_runtime::X402Error;
+
+const PAYER: usize = 0;
+const AUTHORITY: usize = 1;
+const PROGRAM: usize = 2;
+const PROGRAM_DATA: usize = 3;
+const CREATED: usize = 4;
+const SYSTEM: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+enum Initializer {
+    FreeTier,
+    Congestion,
+    Experiment,
+    Stats,
+    ReentrancyGuard,
+    Manifest,
+}
+
+const INITIALIZERS: [Initializer; 6] = [
+    Initializer::FreeTier,
+    Initializer::Congestion,
+    Initializer::Experiment,
+    Initializer::Stats,
+    Initializer::ReentrancyGuard,
+    Initializer::Manifest,
+];
+
+impl Initializer {
+    /// The size of the account it creates.
+    fn space(self) -> usize {
+        match self {
+            Initializer::FreeTier => 8 + FreeTierConfig::INIT_SPACE,
+            Initializer::Congestion => 8 + CongestionLevel::INIT_SPACE,
+            Initializer::Experiment => 8 + PriceExperiment::INIT_SPACE,
+            Initializer::Stats => 8 + std::mem::size_of::<X402Stats>(),
+            Initializer::ReentrancyGuard => 8 + std::mem::size_of::<ReentrancyGuard>(),
+            Initializer::Manifest => ManifestData::space(manifest_entries().len()),
+        }
+    }
+}
+
+/// One account: its data is backed by `u64`s so `AccountLoader` can cast
+/// it in place.
+struct Slot {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    words: Vec<u64>,
+    len: usize,
+    signer: bool,
+    executable: bool,
+}
+
+impl Slot {
+    fn new(key: Pubkey, owner: Pubkey, bytes: &[u8]) -> Self {
+        let mut words = vec![0; bytes.len().div_ceil(8)];
+        bytemuck::cast_slice_mut(&mut words)[..bytes.len()].copy_from_slice(bytes);
+        Slot {
+            key,
+            owner,
+            lamports: 1_000_000_000,
+            words,
+            len: bytes.len(),
+            signer: false,
+            executable: false,
+        }
+    }
+}
+
+/// The example program deployed under `upgrade_authority`, and the
+/// accounts of an initializer as they are once its `init` has run.
+struct Deployment {
+    slots: Vec<Slot>,
+}
+
+impl Deployment {
+    fn new(upgrade_authority: Pubkey, initializer: Initializer) -> Self {
+        let loader = bpf_loader_upgradeable::ID;
+        let program_data = Pubkey::find_program_address(&[x402_example::ID.as_ref()], &loader).0;
+        let mut program = 2u32.to_le_bytes().to_vec();
+        program.extend_from_slice(program_data.as_ref());
+        let mut data = 3u32.to_le_bytes().to_vec();
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(upgrade_authority.as_ref());
+
+        let mut slots = vec![
+            Slot::new(Pubkey::new_unique(), system_program::ID, &[]),
+            Slot::new(Pubkey::new_unique(), system_program::ID, &[]),
+            Slot::new(x402_example::ID, loader, &program),
+            Slot::new(program_data, loader, &data),
+            Slot::new(Pubkey::new_unique(), x402_example::ID, &vec![0; initializer.space()]),
+            Slot::new(system_program::ID, Pubkey::default(), &[]),
+        ];
+        slots[PAYER].signer = true;
+        slots[AUTHORITY].signer = true;
+        slots[PROGRAM].executable = true;
+        slots[SYSTEM].executable = true;
+        Deployment { slots }
+    }
+
+    /// Runs `initializer` signed by `signer`, who also pays for the
+    /// manifest.
+    fn initialize(&mut self, initializer: Initializer, signer: Pubkey) -> Result<()> {
+        self.slots[AUTHORITY].key = signer;
+        let infos: Vec<AccountInfo> = self
+            .slots
+            .iter_mut()
+            .map(|slot| {
+                let data = &mut bytemuck::cast_slice_mut(&mut slot.words)[..slot.len];
+                AccountInfo::new(
+                    &slot.key,
+                    slot.signer,
+                    true,
+                    &mut slot.lamports,
+                    data,
+                    &slot.owner,
+                    slot.executable,
+                    0,
+                )
+            })
+            .collect();
+        let id = x402_example::ID;
+        let payer = Signer::try_from(&infos[PAYER])?;
+        let authority = Signer::try_from(&infos[AUTHORITY])?;
+        let program = UncheckedAccount::try_from(&infos[PROGRAM]);
+        let program_data = UncheckedAccount::try_from(&infos[PROGRAM_DATA]);
+        let system_program = Program::try_from(&infos[SYSTEM])?;
+        match initializer {
+            Initializer::FreeTier => {
+                let mut accounts = InitializeFreeTier {
+                    payer,
+                    authority,
+                    program,
+                    program_data,
+                    free_tier_config: Account::try_from_unchecked(&infos[CREATED])?,
+                    system_program,
+                };
+                handlers::initialize_free_tier(Context::new(&id, &mut accounts, &[], Default::default()), signer)
+            }
+            Initializer::Congestion => {
+                let mut accounts = InitializeCongestion {
+                    payer,
+                    authority,
+                    program,
+                    program_data,
+                    x402_congestion: Account::try_from_unchecked(&infos[CREATED])?,
+                    system_program,
+                };
+                handlers::initialize_congestion(Context::new(&id, &mut accounts, &[], Default::default()), signer)
+            }
+            Initializer::Experiment => {
+                let mut accounts = InitializeExperiment {
+                    payer,
+                    authority,
+                    program,
+                    program_data,
+                    x402_experiment: Account::try_from_unchecked(&infos[CREATED])?,
+                    system_program,
+                };
+                handlers::initialize_experiment(Context::new(&id, &mut accounts, &[], Default::default()), signer)
+            }
+            Initializer::Stats => {
+                let mut accounts = InitializeStats {
+                    payer,
+                    authority,
+                    program,
+                    program_data,
+                    x402_stats: AccountLoader::try_from_unchecked(&id, &infos[CREATED])?,
+                    system_program,
+                };
+                handlers::initialize_stats(Context::new(&id, &mut accounts, &[], Default::default()))
+            }
+            Initializer::ReentrancyGuard => {
+                let mut accounts = InitializeReentrancyGuard {
+                    payer,
+                    authority,
+                    program,
+                    program_data,
+                    x402_reentrancy_guard: AccountLoader::try_from_unchecked(&id, &infos[CREATED])?,
+                    system_program,
+                };
+                handlers::initialize_reentrancy_guard(Context::new(&id, &mut accounts, &[], Default::default()))
+            }
+            Initializer::Manifest => {
+                let mut accounts = PublishManifest {
+                    authority,
+                    program,
+                    program_data,
+                    manifest: Account::try_from_unchecked(&infos[CREATED])?,
+                    system_program,
+                };
+                handlers::publish_manifest(Context::new(&id, &mut accounts, &[], Default::default()))?;
+                assert_eq!(accounts.manifest.authority, signer);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[test]
+fn only_the_upgrade_authority_may_initialize() {
+    let upgrade_authority = Pubkey::new_unique();
+    for initializer in INITIALIZERS {
+        let mut deployment = Deployment::new(upgrade_authority, initializer);
+        let err = deployment.initialize(initializer, Pubkey::new_unique()).unwrap_err();
+        assert_eq!(err, X402Error::NotUpgradeAuthority.into(), "{:?}", initializer);
+
+        deployment.initialize(initializer, upgrade_authority).unwrap();
+    }
+}
+
+#[test]
+fn an_immutable_program_cannot_be_initialized() {
+    let upgrade_authority = Pubkey::new_unique();
+    for initializer in INITIALIZERS {
+        let mut deployment = Deployment::new(upgrade_authority, initializer);
+        // `upgrade_authority_address: None`.
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut deployment.slots[PROGRAM_DATA].words);
+        data[12..45].fill(0);
+        let err = deployment.initialize(initializer, upgrade_authority).unwrap_err();
+        assert_eq!(err, X402Error::ProgramNotUpgradeable.into(), "{:?}", initializer);
+    }
+}
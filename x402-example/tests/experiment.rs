@@ -0,0 +1,74 @@
+//! The price experiment on `experiment_query`: each payer keeps one arm
+//! until the authority ends the experiment, after which everyone pays the
+//! winner's price.
+
+use anchor_lang::prelude::*;
+use x402_common::pricing::experiment_bucket;
+use x402_example::x402_example::{EXPERIMENT_QUERY_X402_EXPERIMENT, EXPERIMENT_QUERY_X402_PARAMS};
+use x402_example::{end_experiment, ErrorCode, PriceExperiment, Versioned};
+use x402_runtime::experiment::{ExperimentState, EXPERIMENT_SEED};
+
+fn experiment() -> PriceExperiment {
+    PriceExperiment {
+        version: PriceExperiment::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        ended: false,
+        winner: 0,
+    }
+}
+
+/// The price `#[x402]` charges `payer` given the handler's `x402_experiment`.
+fn enforced(payer: &Pubkey, experiment: &PriceExperiment) -> (u64, u8) {
+    let state = ExperimentState {
+        ended: experiment.ended,
+        winner: experiment.winner,
+    };
+    let (params, arm) = EXPERIMENT_QUERY_X402_EXPERIMENT
+        .apply_from(
+            &EXPERIMENT_QUERY_X402_PARAMS,
+            &EXPERIMENT_QUERY_X402_EXPERIMENT.address(&x402_example::ID),
+            &x402_example::ID,
+            payer,
+            state,
+        )
+        .unwrap();
+    (params.price, arm)
+}
+
+#[test]
+fn experiment_reads_its_pda() {
+    let (pda, _) = Pubkey::find_program_address(&[EXPERIMENT_SEED, b"query_price_v1"], &x402_example::ID);
+    assert_eq!(EXPERIMENT_QUERY_X402_EXPERIMENT.address(&x402_example::ID), pda);
+    assert_eq!(EXPERIMENT_QUERY_X402_EXPERIMENT.prices, &[300_000, 250_000]);
+    assert_eq!(EXPERIMENT_QUERY_X402_PARAMS.price, 300_000);
+}
+
+#[test]
+fn buckets_are_stable_and_shared_with_quotes() {
+    let running = experiment();
+    for _ in 0..64 {
+        let payer = Pubkey::new_unique();
+        let (price, arm) = enforced(&payer, &running);
+        assert_eq!(enforced(&payer, &running), (price, arm));
+        assert_eq!(arm, experiment_bucket(&payer, b"query_price_v1", 2));
+        assert_eq!(price, EXPERIMENT_QUERY_X402_EXPERIMENT.prices[arm as usize]);
+    }
+}
+
+#[test]
+fn ending_charges_everyone_the_winner() {
+    let mut ended = experiment();
+    end_experiment(&mut ended, 1, EXPERIMENT_QUERY_X402_EXPERIMENT.prices.len()).unwrap();
+    assert!(ended.ended);
+    for _ in 0..16 {
+        assert_eq!(enforced(&Pubkey::new_unique(), &ended), (250_000, 1));
+    }
+}
+
+#[test]
+fn cannot_end_on_a_missing_arm() {
+    let mut running = experiment();
+    let err = end_experiment(&mut running, 2, EXPERIMENT_QUERY_X402_EXPERIMENT.prices.len()).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::InvalidExperimentWinner));
+    assert!(!running.ended);
+}
@@ -0,0 +1,233 @@
+//! `explain_payment` against what the priced instructions enforce: for a
+//! static price, a recipient read from the config, a congestion surcharge
+//! and the lower price of its grace window, and a price experiment, the
+//! explanation is the price and recipient the `#[x402]` check enforces,
+//! and a transfer of exactly that much passes the check while one short
+//! of it does not.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::{self, instructions};
+use anchor_lang::solana_program::sysvar::instructions::{BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::system_instruction::transfer;
+use anchor_lang::Discriminator;
+use x402_common::explanation::EXPLAIN_PAYMENT_DISCRIMINATOR;
+use x402_example::x402_example::{
+    COMPUTE_STANDARD_X402_PARAMS, DEPOSIT_QUERY_X402_TAG, EXPERIMENT_QUERY_X402_EXPERIMENT,
+    EXPERIMENT_QUERY_X402_PARAMS, EXPERIMENT_QUERY_X402_TAG, PRICED_QUERY_X402_PARAMS, PRICED_QUERY_X402_SURCHARGE,
+    PRICED_QUERY_X402_TAG, SAVINGS_QUERY_X402_TAG, TREASURY_QUERY_X402_PARAMS, TREASURY_QUERY_X402_TAG,
+};
+use x402_example::{
+    payment_requirements, CongestionLevel, ErrorCode, PriceExperiment, Versioned, X402GlobalConfig,
+    PRICED_QUERY_GRACE_WINDOW_SECS,
+};
+use x402_runtime::congestion::LevelChange;
+use x402_runtime::experiment::ExperimentState;
+use x402_runtime::{verify_payment, Cluster, Mode, PaymentExplanation, PriceProvenance, X402Error, X402Params};
+
+const NOW: i64 = 1_700_000_000;
+
+/// The standard compute tier's tag.
+const STANDARD: &str = "standard_v1";
+
+fn config(recipient: Pubkey) -> X402GlobalConfig {
+    X402GlobalConfig {
+        version: X402GlobalConfig::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        recipient,
+        operator: Pubkey::new_unique(),
+        cluster: Cluster::MainnetBeta,
+    }
+}
+
+fn congestion(level: u8, previous_level: u8, updated_at: i64) -> CongestionLevel {
+    CongestionLevel {
+        version: CongestionLevel::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        level,
+        previous_level,
+        updated_at,
+    }
+}
+
+fn explain(tag: &str, payer: &Pubkey) -> Result<PaymentExplanation> {
+    payment_requirements(tag, payer, None, None, None, NOW)
+}
+
+/// Why `tag` cannot be explained from no accounts.
+fn explain_missing(tag: &str) -> Error {
+    explain(tag, &Pubkey::new_unique()).unwrap_err()
+}
+
+/// The amount `verify_payment` accepts from a transfer of `amount` from
+/// `payer` to `params.recipient` just before the priced call.
+fn pays(payer: &Pubkey, params: &X402Params, amount: u64) -> Result<u64> {
+    let transaction = [
+        transfer(payer, &params.recipient, amount),
+        anchor_lang::solana_program::instruction::Instruction {
+            program_id: x402_example::ID,
+            accounts: vec![],
+            data: vec![],
+        },
+    ];
+    let borrowed: Vec<_> = transaction
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, 1);
+
+    let mut lamports = 0;
+    let info = AccountInfo::new(&instructions::ID, false, false, &mut lamports, &mut data, &sysvar::ID, false, 0);
+    verify_payment(&[info], &x402_example::ID, params).map(|payment| payment.amount)
+}
+
+/// A transfer of the explained price passes the check of the instruction
+/// priced at `enforced`, and one lamport less does not.
+fn assert_enforced(payer: &Pubkey, explanation: &PaymentExplanation, enforced: &X402Params) {
+    assert_eq!(explanation.params(), *enforced);
+    assert_eq!(pays(payer, enforced, explanation.price).unwrap(), explanation.price);
+    let short = pays(payer, enforced, explanation.price - 1).unwrap_err();
+    assert_eq!(short, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn explain_payment_is_the_shared_instruction() {
+    assert_eq!(x402_example::instruction::ExplainPayment::DISCRIMINATOR, EXPLAIN_PAYMENT_DISCRIMINATOR);
+}
+
+#[test]
+fn a_static_price_is_the_declared_one() {
+    let payer = Pubkey::new_unique();
+    let explanation = explain(STANDARD, &payer).unwrap();
+    assert_eq!((explanation.mode, explanation.provenance), (Mode::PrecedingInstruction, PriceProvenance::Static));
+    assert_eq!(explanation.paid, None);
+    assert_enforced(&payer, &explanation, &COMPUTE_STANDARD_X402_PARAMS);
+}
+
+#[test]
+fn a_configured_recipient_is_the_one_the_config_holds() {
+    let payer = Pubkey::new_unique();
+    let mut config = config(Pubkey::new_unique());
+    let explain = |config: &X402GlobalConfig| {
+        payment_requirements(TREASURY_QUERY_X402_TAG, &payer, Some(config), None, None, NOW).unwrap()
+    };
+
+    let explanation = explain(&config);
+    let enforced = X402Params {
+        recipient: config.recipient,
+        ..TREASURY_QUERY_X402_PARAMS
+    };
+    assert_enforced(&payer, &explanation, &enforced);
+
+    // Rotating the recipient moves the explanation with the check.
+    config.recipient = Pubkey::new_unique();
+    assert_eq!(explain(&config).recipient, config.recipient);
+
+    let err = payment_requirements(TREASURY_QUERY_X402_TAG, &payer, None, None, None, NOW).unwrap_err();
+    assert_eq!(err, X402Error::ConfigNotInitialized.into());
+}
+
+#[test]
+fn a_surcharge_and_its_grace_window_are_priced_as_enforced() {
+    let payer = Pubkey::new_unique();
+    let account = PRICED_QUERY_X402_SURCHARGE.account;
+    let explain = |congestion: &CongestionLevel| {
+        payment_requirements(PRICED_QUERY_X402_TAG, &payer, None, Some((&account, congestion)), None, NOW).unwrap()
+    };
+    let enforced = |congestion: &CongestionLevel| {
+        PRICED_QUERY_X402_SURCHARGE
+            .apply_with_window_at(
+                &PRICED_QUERY_X402_PARAMS,
+                &account,
+                congestion.level,
+                LevelChange {
+                    previous_level: congestion.previous_level,
+                    updated_at: congestion.updated_at,
+                },
+                NOW,
+                PRICED_QUERY_GRACE_WINDOW_SECS,
+            )
+            .unwrap()
+    };
+
+    // Long after a change to level 4: the surcharged price.
+    let settled = congestion(4, 0, NOW - 3_600);
+    let explanation = explain(&settled);
+    assert_eq!(explanation.price, PRICED_QUERY_X402_PARAMS.price * 120 / 100);
+    assert_eq!(
+        explanation.provenance,
+        PriceProvenance::Congestion {
+            account,
+            level: 4,
+            updated_at: NOW - 3_600
+        }
+    );
+    assert_enforced(&payer, &explanation, &enforced(&settled));
+
+    // Just after it, within the window: the lower price of the level before.
+    let raised = congestion(4, 1, NOW - 10);
+    let explanation = explain(&raised);
+    assert_eq!(explanation.price, PRICED_QUERY_X402_PARAMS.price * 105 / 100);
+    assert!(matches!(explanation.provenance, PriceProvenance::Congestion { level: 1, .. }));
+    assert_enforced(&payer, &explanation, &enforced(&raised));
+
+    let err = explain_missing(PRICED_QUERY_X402_TAG);
+    assert_eq!(err, ErrorCode::PricingAccountMissing.into());
+    let elsewhere = Pubkey::new_unique();
+    let err = payment_requirements(PRICED_QUERY_X402_TAG, &payer, None, Some((&elsewhere, &settled)), None, NOW)
+        .unwrap_err();
+    assert_eq!(err, X402Error::CongestionAccountMismatch.into());
+}
+
+#[test]
+fn an_experiment_prices_each_payer_at_their_arm() {
+    let account = EXPERIMENT_QUERY_X402_EXPERIMENT.address(&x402_example::ID);
+    let mut experiment = PriceExperiment::new(Pubkey::new_unique());
+    for _ in 0..16 {
+        let payer = Pubkey::new_unique();
+        let explanation =
+            payment_requirements(EXPERIMENT_QUERY_X402_TAG, &payer, None, None, Some((&account, &experiment)), NOW)
+                .unwrap();
+        let (enforced, arm) = EXPERIMENT_QUERY_X402_EXPERIMENT
+            .apply_from(
+                &EXPERIMENT_QUERY_X402_PARAMS,
+                &account,
+                &x402_example::ID,
+                &payer,
+                ExperimentState::default(),
+            )
+            .unwrap();
+        assert_eq!(explanation.provenance, PriceProvenance::Experiment { account, arm });
+        assert_enforced(&payer, &explanation, &enforced);
+    }
+
+    // Once ended, every payer is explained the winner's price.
+    experiment.ended = true;
+    experiment.winner = 1;
+    let payer = Pubkey::new_unique();
+    let explanation =
+        payment_requirements(EXPERIMENT_QUERY_X402_TAG, &payer, None, None, Some((&account, &experiment)), NOW)
+            .unwrap();
+    assert_eq!(explanation.price, EXPERIMENT_QUERY_X402_EXPERIMENT.prices[1]);
+    assert_eq!(explain_missing(EXPERIMENT_QUERY_X402_TAG), ErrorCode::PricingAccountMissing.into());
+}
+
+#[test]
+fn payments_made_without_a_transfer_say_so() {
+    let payer = Pubkey::new_unique();
+    assert_eq!(explain(DEPOSIT_QUERY_X402_TAG, &payer).unwrap().mode, Mode::Deposit);
+    assert_eq!(explain(SAVINGS_QUERY_X402_TAG, &payer).unwrap().mode, Mode::Accumulated);
+    assert_eq!(explain("compute", &payer).unwrap_err(), ErrorCode::UnknownTag.into());
+}
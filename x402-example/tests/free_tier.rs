@@ -0,0 +1,87 @@
+//! `use_free_tier`, the cooldown `free_compute` applies per payer. The
+//! clock is passed in, so warping it is just a later timestamp.
+
+use anchor_lang::prelude::*;
+use x402_example::{
+    use_free_tier, ErrorCode, FreeTierConfig, UsageCounter, Versioned, DEFAULT_FREE_COOLDOWN_SECS,
+};
+
+const NOW: i64 = 1_700_000_000;
+
+fn config(enabled: bool) -> FreeTierConfig {
+    FreeTierConfig {
+        version: FreeTierConfig::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        enabled,
+        cooldown_secs: DEFAULT_FREE_COOLDOWN_SECS,
+    }
+}
+
+fn fresh_counter() -> UsageCounter {
+    UsageCounter {
+        version: UsageCounter::CURRENT_VERSION,
+        payer: Pubkey::new_unique(),
+        last_used: 0,
+        uses: 0,
+    }
+}
+
+fn assert_exhausted(result: Result<()>) {
+    assert_eq!(result.unwrap_err(), error!(ErrorCode::FreeTierExhausted));
+}
+
+#[test]
+fn first_call_passes() {
+    let mut counter = fresh_counter();
+    use_free_tier(&config(true), &mut counter, NOW).unwrap();
+    assert_eq!(counter.uses, 1);
+    assert_eq!(counter.last_used, NOW);
+}
+
+#[test]
+fn immediate_second_call_fails() {
+    let config = config(true);
+    let mut counter = fresh_counter();
+    use_free_tier(&config, &mut counter, NOW).unwrap();
+
+    assert_exhausted(use_free_tier(&config, &mut counter, NOW));
+    assert_exhausted(use_free_tier(&config, &mut counter, NOW + DEFAULT_FREE_COOLDOWN_SECS - 1));
+    // A rejected call does not restart the cooldown.
+    assert_eq!(counter.uses, 1);
+    assert_eq!(counter.last_used, NOW);
+}
+
+#[test]
+fn passes_again_after_the_cooldown() {
+    let config = config(true);
+    let mut counter = fresh_counter();
+    use_free_tier(&config, &mut counter, NOW).unwrap();
+
+    use_free_tier(&config, &mut counter, NOW + DEFAULT_FREE_COOLDOWN_SECS).unwrap();
+    assert_eq!(counter.uses, 2);
+    assert_exhausted(use_free_tier(&config, &mut counter, NOW + DEFAULT_FREE_COOLDOWN_SECS + 1));
+}
+
+#[test]
+fn zero_cooldown_is_unlimited() {
+    let mut config = config(true);
+    config.cooldown_secs = 0;
+    let mut counter = fresh_counter();
+    for _ in 0..3 {
+        use_free_tier(&config, &mut counter, NOW).unwrap();
+    }
+    assert_eq!(counter.uses, 3);
+}
+
+#[test]
+fn disabled_tier_rejects_everyone() {
+    let config = config(false);
+    let mut counter = fresh_counter();
+    assert_exhausted(use_free_tier(&config, &mut counter, NOW));
+
+    let mut used_long_ago = fresh_counter();
+    used_long_ago.uses = 1;
+    used_long_ago.last_used = NOW - 86_400;
+    assert_exhausted(use_free_tier(&config, &mut used_long_ago, NOW));
+    assert_eq!(used_long_ago.uses, 1);
+}
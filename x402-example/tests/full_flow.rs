@@ -0,0 +1,15 @@
+//! `examples/full_flow.rs` as a test. It needs `solana-test-validator` and
+//! the program built with `anchor build`, so it only runs when asked for:
+//!
+//! ```text
+//! cargo test -p x402-example --test full_flow -- --ignored
+//! ```
+
+#[path = "../examples/full_flow.rs"]
+mod full_flow;
+
+#[test]
+#[ignore = "needs solana-test-validator and `anchor build`"]
+fn pays_for_a_request_end_to_end() {
+    full_flow::main().unwrap();
+}
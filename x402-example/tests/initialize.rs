@@ -0,0 +1,225 @@
+//! `initialize_all` over a whole deployment: every account created with its
+//! defaults at once, a deployment stopped partway finished by the
+//! idempotent variant, and a complete one left untouched.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use x402_example::{
+    initialize_deployment, manifest_entries, CongestionLevel, ErrorCode, FreeTierConfig, InitParams, InitializeAll,
+    PriceExperiment, ReentrancyGuard, Versioned, X402GlobalConfig, X402Manifest, X402Stats,
+};
+use x402_runtime::manifest::ManifestData;
+use x402_runtime::Cluster;
+
+const PAYER: usize = 0;
+const AUTHORITY: usize = 1;
+const CONFIG: usize = 4;
+const FREE_TIER: usize = 5;
+const STATS: usize = 8;
+const GUARD: usize = 9;
+const MANIFEST: usize = 10;
+
+/// One account: its data is backed by `u64`s so `AccountLoader` can cast
+/// it in place.
+struct Slot {
+    key: Pubkey,
+    owner: Pubkey,
+    lamports: u64,
+    words: Vec<u64>,
+    len: usize,
+    signer: bool,
+    executable: bool,
+}
+
+impl Slot {
+    fn new(owner: Pubkey, len: usize) -> Self {
+        Slot {
+            key: Pubkey::new_unique(),
+            owner,
+            lamports: 1_000_000_000,
+            words: vec![0; len.div_ceil(8)],
+            len,
+            signer: false,
+            executable: false,
+        }
+    }
+
+    fn data(&self) -> &[u8] {
+        &bytemuck::cast_slice(&self.words)[..self.len]
+    }
+
+    /// Back to the zeroes of an account `init_if_needed` has just created.
+    fn wipe(&mut self) {
+        self.words.iter_mut().for_each(|word| *word = 0);
+    }
+}
+
+/// The accounts of `InitializeAll`, in its order, as they are once its
+/// `init_if_needed` constraints have run.
+struct Deployment {
+    slots: Vec<Slot>,
+}
+
+impl Deployment {
+    fn new() -> Self {
+        let mut slots = vec![
+            Slot::new(system_program::ID, 0),
+            Slot::new(system_program::ID, 0),
+            Slot::new(Pubkey::default(), 0),
+            Slot::new(Pubkey::default(), 0),
+            Slot::new(x402_example::ID, 8 + X402GlobalConfig::INIT_SPACE),
+            Slot::new(x402_example::ID, 8 + FreeTierConfig::INIT_SPACE),
+            Slot::new(x402_example::ID, 8 + CongestionLevel::INIT_SPACE),
+            Slot::new(x402_example::ID, 8 + PriceExperiment::INIT_SPACE),
+            Slot::new(x402_example::ID, 8 + std::mem::size_of::<X402Stats>()),
+            Slot::new(x402_example::ID, 8 + std::mem::size_of::<ReentrancyGuard>()),
+            Slot::new(x402_example::ID, ManifestData::space(manifest_entries().len())),
+            Slot::new(Pubkey::default(), 0),
+        ];
+        slots[PAYER].signer = true;
+        slots[AUTHORITY].signer = true;
+        slots[2].key = x402_example::ID;
+        let system = slots.last_mut().unwrap();
+        system.key = system_program::ID;
+        system.executable = true;
+        Deployment { slots }
+    }
+
+    fn authority(&self) -> Pubkey {
+        self.slots[AUTHORITY].key
+    }
+
+    /// `initialize_deployment` then the accounts' exit, as the instruction
+    /// runs them.
+    fn initialize(&mut self, params: &InitParams, skip_existing: bool) -> Result<u8> {
+        let infos: Vec<AccountInfo> = self
+            .slots
+            .iter_mut()
+            .map(|slot| {
+                let data = &mut bytemuck::cast_slice_mut(&mut slot.words)[..slot.len];
+                AccountInfo::new(
+                    &slot.key,
+                    slot.signer,
+                    true,
+                    &mut slot.lamports,
+                    data,
+                    &slot.owner,
+                    slot.executable,
+                    0,
+                )
+            })
+            .collect();
+        let mut accounts = InitializeAll {
+            payer: Signer::try_from(&infos[0])?,
+            authority: Signer::try_from(&infos[1])?,
+            program: UncheckedAccount::try_from(&infos[2]),
+            program_data: UncheckedAccount::try_from(&infos[3]),
+            x402_config: Box::new(Account::try_from_unchecked(&infos[4])?),
+            free_tier_config: Box::new(Account::try_from_unchecked(&infos[5])?),
+            x402_congestion: Box::new(Account::try_from_unchecked(&infos[6])?),
+            x402_experiment: Box::new(Account::try_from_unchecked(&infos[7])?),
+            x402_stats: AccountLoader::try_from_unchecked(&x402_example::ID, &infos[8])?,
+            x402_reentrancy_guard: AccountLoader::try_from_unchecked(&x402_example::ID, &infos[9])?,
+            manifest: Box::new(Account::try_from_unchecked(&infos[10])?),
+            system_program: Program::try_from(&infos[11])?,
+        };
+        let filled = initialize_deployment(&mut accounts, params, skip_existing)?;
+        accounts.exit(&x402_example::ID)?;
+        Ok(filled)
+    }
+
+    fn read<T: AccountDeserialize>(&self, index: usize) -> T {
+        T::try_deserialize(&mut self.slots[index].data()).unwrap()
+    }
+
+    fn snapshot(&self) -> Vec<Vec<u8>> {
+        self.slots.iter().map(|slot| slot.data().to_vec()).collect()
+    }
+}
+
+fn params() -> InitParams {
+    InitParams {
+        recipient: Pubkey::new_unique(),
+        operator: Pubkey::new_unique(),
+        cluster: Cluster::Devnet,
+    }
+}
+
+#[test]
+fn a_fresh_deployment_gets_every_account() {
+    let mut deployment = Deployment::new();
+    let params = params();
+    assert_eq!(deployment.initialize(&params, false).unwrap(), 7);
+
+    let authority = deployment.authority();
+    let config: X402GlobalConfig = deployment.read(CONFIG);
+    assert_eq!(config.version, X402GlobalConfig::CURRENT_VERSION);
+    assert_eq!(
+        (config.authority, config.recipient, config.operator, config.cluster),
+        (authority, params.recipient, params.operator, Cluster::Devnet)
+    );
+    let free_tier: FreeTierConfig = deployment.read(FREE_TIER);
+    assert_eq!((free_tier.authority, free_tier.enabled), (authority, true));
+    let congestion: CongestionLevel = deployment.read(6);
+    assert_eq!(congestion.version, CongestionLevel::CURRENT_VERSION);
+    assert_eq!((congestion.authority, congestion.level), (authority, 0));
+    let experiment: PriceExperiment = deployment.read(7);
+    assert_eq!((experiment.authority, experiment.ended), (authority, false));
+    let manifest: X402Manifest = deployment.read(MANIFEST);
+    assert_eq!((manifest.authority, manifest.manifest_version), (authority, 1));
+    assert_eq!(manifest.entries, manifest_entries());
+
+    let stats = deployment.slots[STATS].data();
+    assert_eq!((&stats[..8], stats[8]), (&X402Stats::DISCRIMINATOR[..], X402Stats::CURRENT_VERSION));
+    let guard = deployment.slots[GUARD].data();
+    assert_eq!((&guard[..8], guard[8]), (&ReentrancyGuard::DISCRIMINATOR[..], ReentrancyGuard::CURRENT_VERSION));
+}
+
+#[test]
+fn a_deployment_stopped_partway_is_finished_by_the_idempotent_variant() {
+    let mut deployment = Deployment::new();
+    deployment.initialize(&params(), false).unwrap();
+    // As if only the config and the stats had been created, one
+    // instruction at a time, before the rest failed.
+    for index in [FREE_TIER, 6, 7, GUARD, MANIFEST] {
+        deployment.slots[index].wipe();
+    }
+    let config = deployment.slots[CONFIG].data().to_vec();
+    let stats = deployment.slots[STATS].data().to_vec();
+
+    let err = deployment.initialize(&params(), false).unwrap_err();
+    assert_eq!(err, ErrorCode::AlreadyInitialized.into());
+
+    assert_eq!(deployment.initialize(&params(), true).unwrap(), 5);
+    assert_eq!(deployment.slots[CONFIG].data(), config);
+    assert_eq!(deployment.slots[STATS].data(), stats);
+    let free_tier: FreeTierConfig = deployment.read(FREE_TIER);
+    assert_eq!(free_tier.version, FreeTierConfig::CURRENT_VERSION);
+    let manifest: X402Manifest = deployment.read(MANIFEST);
+    assert_eq!(manifest.entries, manifest_entries());
+}
+
+#[test]
+fn a_complete_deployment_is_left_as_it_is() {
+    let mut deployment = Deployment::new();
+    deployment.initialize(&params(), false).unwrap();
+    let before = deployment.snapshot();
+
+    assert_eq!(deployment.initialize(&params(), true).unwrap(), 0);
+    assert_eq!(deployment.snapshot(), before);
+
+    let err = deployment.initialize(&params(), false).unwrap_err();
+    assert_eq!(err, ErrorCode::AlreadyInitialized.into());
+    assert_eq!(deployment.snapshot(), before);
+}
+
+#[test]
+fn a_deployment_needs_its_cluster() {
+    let mut deployment = Deployment::new();
+    let params = InitParams {
+        cluster: Cluster::Unset,
+        ..params()
+    };
+    assert_eq!(deployment.initialize(&params, true).unwrap_err(), ErrorCode::ClusterNotGiven.into());
+}
@@ -0,0 +1,474 @@
+//! Transactions built from nothing but a handler's `<handler>_x402_layout()`
+//! and the accounts of its call, through `x402_client::paid_call_instructions`,
+//! for every mode the example uses. Each is run the way the runtime runs
+//! it: the call's accounts are loaded by Anchor as the transaction names
+//! them, and the handler reads the instructions sysvar of the whole
+//! transaction.
+
+use std::collections::BTreeSet;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::Instruction;
+use anchor_lang::solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use anchor_lang::solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use anchor_lang::{Bumps, Discriminator, InstructionData};
+use x402_client::{check_paid_call, paid_call_instructions, X402ClientError};
+use x402_common::layout::{CallAccount, Requirement, Trailing};
+use x402_common::seeds::{
+    accumulator_address, config_address, congestion_address, deposit_address, experiment_address,
+    reentrancy_guard_address,
+};
+use x402_example::x402_example::{
+    compute_premium_x402_layout, deposit_query_x402_layout, experiment_query_x402_layout, priced_query_x402_layout,
+    savings_query_x402_layout, treasury_query_x402_layout, COMPUTE_PREMIUM_X402_PARAMS, DEPOSIT_QUERY_X402_PARAMS,
+    EXPERIMENT_QUERY_X402_EXPERIMENT, EXPERIMENT_QUERY_X402_PARAMS, PRICED_QUERY_X402_PARAMS,
+    SAVINGS_QUERY_X402_PARAMS, SAVINGS_QUERY_X402_TAG, TREASURY_QUERY_X402_PARAMS,
+};
+use x402_example::{
+    accounts, instruction, Accumulator, CongestionLevel, DepositAccount, PriceExperiment, ReentrancyGuard, Versioned,
+    X402GlobalConfig,
+};
+use x402_runtime::experiment::ExperimentState;
+use x402_runtime::{Cluster, Mode, X402Error, X402Params, IGNORABLE_PROGRAMS};
+
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+const TREASURY: Pubkey = Pubkey::new_from_array([7; 32]);
+
+struct Stubs;
+
+impl SyscallStubs for Stubs {
+    fn sol_get_stack_height(&self) -> u64 {
+        1
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // Safety: the runtime passes the address of a `Clock`.
+        unsafe { var_addr.cast::<Clock>().write(Clock::default()) };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // Safety: the runtime passes the address of a `Rent`.
+        unsafe { var_addr.cast::<Rent>().write(Rent::default()) };
+        anchor_lang::solana_program::entrypoint::SUCCESS
+    }
+}
+
+/// An account of the cluster the transaction runs against.
+struct Stored {
+    key: Pubkey,
+    signer: bool,
+    writable: bool,
+    lamports: u64,
+    data: Vec<u8>,
+    owner: Pubkey,
+}
+
+fn account_data(account: &impl AccountSerialize) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+/// The accounts of the last instruction of `transaction`, the priced call,
+/// as the runtime hands them to the program: `state` for those it names,
+/// the instructions sysvar of `transaction`, and empty system accounts
+/// otherwise.
+fn load(transaction: &[Instruction], state: Vec<(Pubkey, u64, Vec<u8>)>) -> Vec<Stored> {
+    load_at(transaction, transaction.len() - 1, state)
+}
+
+/// [`load`] for the priced call at `call_index`, with the instructions
+/// after it still in the sysvar.
+fn load_at(transaction: &[Instruction], call_index: usize, state: Vec<(Pubkey, u64, Vec<u8>)>) -> Vec<Stored> {
+    let borrowed: Vec<_> = transaction
+        .iter()
+        .map(|ix| BorrowedInstruction {
+            program_id: &ix.program_id,
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: &ix.data,
+        })
+        .collect();
+    let mut sysvar_data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut sysvar_data, call_index as u16);
+
+    let call = &transaction[call_index];
+    call.accounts
+        .iter()
+        .map(|meta| {
+            let (lamports, data, owner) = if meta.pubkey == instructions::ID {
+                (0, sysvar_data.clone(), sysvar::ID)
+            } else if let Some((_, lamports, data)) = state.iter().find(|(key, _, _)| *key == meta.pubkey) {
+                (*lamports, data.clone(), x402_example::ID)
+            } else {
+                (5_000_000, Vec::new(), system_program::ID)
+            };
+            Stored {
+                key: meta.pubkey,
+                signer: meta.is_signer,
+                writable: meta.is_writable,
+                lamports,
+                data,
+                owner,
+            }
+        })
+        .collect()
+}
+
+fn infos(stored: &mut [Stored]) -> Vec<AccountInfo<'_>> {
+    stored
+        .iter_mut()
+        .map(|account| {
+            AccountInfo::new(
+                &account.key,
+                account.signer,
+                account.writable,
+                &mut account.lamports,
+                &mut account.data,
+                &account.owner,
+                false,
+                0,
+            )
+        })
+        .collect()
+}
+
+/// Loads `T` from `infos` as Anchor's dispatcher does and runs `handler`
+/// on it, with whatever `T` does not name as remaining accounts.
+fn run<'info, T, R>(
+    infos: &'info [AccountInfo<'info>],
+    data: &[u8],
+    handler: impl FnOnce(Context<'_, '_, 'info, 'info, T>) -> Result<R>,
+) -> Result<R>
+where
+    T: Accounts<'info, T::Bumps> + Bumps,
+    T::Bumps: Default,
+{
+    set_syscall_stubs(Box::new(Stubs));
+    let mut rest = infos;
+    let mut bumps = T::Bumps::default();
+    let mut accounts = T::try_accounts(&x402_example::ID, &mut rest, &data[8..], &mut bumps, &mut BTreeSet::new())?;
+    handler(Context::new(&x402_example::ID, &mut accounts, rest, bumps))
+}
+
+fn congestion() -> (Pubkey, u64, Vec<u8>) {
+    let level = CongestionLevel {
+        version: CongestionLevel::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        level: 0,
+        previous_level: 0,
+        updated_at: 0,
+    };
+    (congestion_address(&x402_example::ID), 1_000_000, account_data(&level))
+}
+
+#[test]
+fn priced_query_appends_the_sysvar_after_its_transfer() {
+    let layout = priced_query_x402_layout();
+    assert_eq!(layout.mode, Mode::PrecedingInstruction);
+    assert_eq!(
+        layout.call_accounts(),
+        [
+            CallAccount::InstructionsSysvar,
+            CallAccount::Congestion {
+                address: congestion_address(&x402_example::ID)
+            }
+        ]
+    );
+
+    let data = instruction::PricedQuery {
+        max_acceptable_price: PRICED_QUERY_X402_PARAMS.price,
+    }
+    .data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::PricedQuery {
+            payer: PAYER,
+            x402_congestion: congestion_address(&x402_example::ID),
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let transaction = paid_call_instructions(&layout, &PAYER, &PRICED_QUERY_X402_PARAMS, call).unwrap();
+    assert_eq!(transaction.len(), 2);
+    assert_eq!(transaction[1].accounts.last().unwrap().pubkey, instructions::ID);
+
+    let mut stored = load(&transaction, vec![congestion()]);
+    let infos = infos(&mut stored);
+    let answer = run(&infos, &data, |ctx| {
+        x402_example::x402_example::priced_query(ctx, PRICED_QUERY_X402_PARAMS.price)
+    });
+    assert_eq!(answer.unwrap(), 42);
+}
+
+#[test]
+fn treasury_query_pays_the_configured_recipient() {
+    let layout = treasury_query_x402_layout();
+    let Requirement::Transfer { to, min, .. } = layout.requirements[0] else {
+        panic!("treasury_query is paid by a transfer first: {:?}", layout);
+    };
+    assert_eq!((to, min), (None, TREASURY_QUERY_X402_PARAMS.price));
+    assert!(layout.call_accounts().contains(&CallAccount::Config));
+
+    let config = X402GlobalConfig {
+        version: X402GlobalConfig::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        recipient: TREASURY,
+        operator: Pubkey::new_unique(),
+        cluster: Cluster::Devnet,
+    };
+    let data = instruction::TreasuryQuery {}.data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::TreasuryQuery {
+            payer: PAYER,
+            x402_config: Some(config_address(&x402_example::ID)),
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let params = X402Params {
+        recipient: TREASURY,
+        ..TREASURY_QUERY_X402_PARAMS
+    };
+    let transaction = paid_call_instructions(&layout, &PAYER, &params, call).unwrap();
+
+    let mut stored = load(&transaction, vec![(config_address(&x402_example::ID), 1_000_000, account_data(&config))]);
+    let infos = infos(&mut stored);
+    let answer = run(&infos, &data, x402_example::x402_example::treasury_query);
+    assert_eq!(answer.unwrap(), 42);
+}
+
+#[test]
+fn experiment_query_pays_the_payers_arm() {
+    let layout = experiment_query_x402_layout();
+    let experiment = experiment_address(&x402_example::ID, EXPERIMENT_QUERY_X402_EXPERIMENT.bucket_seed.as_bytes());
+    let state = PriceExperiment {
+        version: PriceExperiment::CURRENT_VERSION,
+        authority: Pubkey::new_unique(),
+        ended: false,
+        winner: 0,
+    };
+    let data = instruction::ExperimentQuery {}.data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::ExperimentQuery {
+            payer: PAYER,
+            x402_experiment: experiment,
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let (params, _) = EXPERIMENT_QUERY_X402_EXPERIMENT
+        .apply(&EXPERIMENT_QUERY_X402_PARAMS, &PAYER, ExperimentState::default())
+        .unwrap();
+    let transaction = paid_call_instructions(&layout, &PAYER, &params, call).unwrap();
+
+    let mut stored = load(&transaction, vec![(experiment, 1_000_000, account_data(&state))]);
+    let infos = infos(&mut stored);
+    let answer = run(&infos, &data, x402_example::x402_example::experiment_query);
+    assert_eq!(answer.unwrap(), 42);
+}
+
+#[test]
+fn compute_keeps_its_declared_sysvar() {
+    let layout = compute_premium_x402_layout();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::PremiumCompute {
+            payer: PAYER,
+            result: Pubkey::new_unique(),
+            spending_cap: None,
+            x402_ledger: None,
+            x402_group_budget: None,
+            x402_stats: Pubkey::new_unique(),
+            system_program: system_program::ID,
+            x402_instructions: instructions::ID,
+        }
+        .to_account_metas(None),
+        data: instruction::ComputePremium { bind_to: None, nonce: 0 }.data(),
+    };
+    let declared = call.accounts.clone();
+    let transaction = paid_call_instructions(&layout, &PAYER, &COMPUTE_PREMIUM_X402_PARAMS, call).unwrap();
+    assert_eq!(transaction[1].accounts, declared);
+
+    // The handler creates its result through the System program, which
+    // cannot run here, so only its check is run, on the declared sysvar.
+    let mut stored = load(&transaction, Vec::new());
+    let infos = infos(&mut stored);
+    let sysvar = infos.iter().find(|info| *info.key == instructions::ID).unwrap();
+    let payment =
+        x402_runtime::verify_payment(std::slice::from_ref(sysvar), &x402_example::ID, &COMPUTE_PREMIUM_X402_PARAMS);
+    assert_eq!(payment.unwrap().ix_index, 0);
+}
+
+#[test]
+fn deposit_query_needs_no_transfer() {
+    let layout = deposit_query_x402_layout();
+    assert_eq!(layout.mode, Mode::Deposit);
+    assert_eq!(layout.requirements.len(), 1);
+    assert!(layout.call_accounts().contains(&CallAccount::ReentrancyGuard));
+
+    let deposit = deposit_address(&x402_example::ID, &PAYER);
+    let guard = reentrancy_guard_address(&x402_example::ID);
+    let data = instruction::DepositQuery {}.data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::DepositQuery {
+            payer: PAYER,
+            x402_deposit: deposit,
+            x402_recipient: DEPOSIT_QUERY_X402_PARAMS.recipient,
+            x402_reentrancy_guard: guard,
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let transaction = paid_call_instructions(&layout, &PAYER, &DEPOSIT_QUERY_X402_PARAMS, call).unwrap();
+    assert_eq!(transaction.len(), 1);
+
+    let account = DepositAccount {
+        version: DepositAccount::CURRENT_VERSION,
+        depositor: PAYER,
+    };
+    let data_len = account_data(&account).len();
+    let funded = Rent::default().minimum_balance(data_len) + DEPOSIT_QUERY_X402_PARAMS.price;
+    let mut guard_data = ReentrancyGuard::DISCRIMINATOR.to_vec();
+    guard_data.extend([ReentrancyGuard::CURRENT_VERSION, 0]);
+    let mut stored =
+        load(&transaction, vec![(deposit, funded, account_data(&account)), (guard, 1_000_000, guard_data)]);
+    {
+        let infos = infos(&mut stored);
+        let answer = run(&infos, &data, x402_example::x402_example::deposit_query);
+        assert_eq!(answer.unwrap(), 42);
+    }
+    let recipient = stored.iter().find(|account| account.key == DEPOSIT_QUERY_X402_PARAMS.recipient).unwrap();
+    assert_eq!(recipient.lamports, 5_000_000 + DEPOSIT_QUERY_X402_PARAMS.price);
+    let guard = stored.iter().find(|account| account.key == guard).unwrap();
+    assert_eq!(guard.data[8..], [ReentrancyGuard::CURRENT_VERSION, 0]);
+}
+
+#[test]
+fn savings_query_needs_no_transfer() {
+    let layout = savings_query_x402_layout();
+    assert_eq!(layout.mode, Mode::Accumulated);
+    assert_eq!(layout.requirements.len(), 1);
+    assert_eq!(
+        layout.call_accounts(),
+        [CallAccount::Accumulator {
+            tag: SAVINGS_QUERY_X402_TAG.to_string()
+        }]
+    );
+
+    let accumulator = accumulator_address(&x402_example::ID, SAVINGS_QUERY_X402_TAG, &PAYER);
+    let data = instruction::SavingsQuery {}.data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::SavingsQuery {
+            payer: PAYER,
+            x402_accumulator: accumulator,
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let transaction = paid_call_instructions(&layout, &PAYER, &SAVINGS_QUERY_X402_PARAMS, call).unwrap();
+    assert_eq!(transaction.len(), 1);
+
+    // Answered once the accumulator holds the price, and not before.
+    let price = SAVINGS_QUERY_X402_PARAMS.price;
+    for (balance, answered) in [(price + 1, true), (price, true), (price - 1, false)] {
+        let account = Accumulator {
+            version: Accumulator::CURRENT_VERSION,
+            balance,
+        };
+        let mut stored = load(&transaction, vec![(accumulator, 1_000_000, account_data(&account))]);
+        let infos = infos(&mut stored);
+        let answer = run(&infos, &data, x402_example::x402_example::savings_query);
+        match answered {
+            true => assert_eq!(answer.unwrap(), 42),
+            false => assert_eq!(answer.unwrap_err(), X402Error::InsufficientAccumulation.into()),
+        }
+    }
+}
+
+#[test]
+fn a_call_missing_an_account_its_check_reads_is_not_built() {
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::TreasuryQuery {
+            payer: PAYER,
+            x402_config: None,
+        }
+        .to_account_metas(None),
+        data: instruction::TreasuryQuery {}.data(),
+    };
+    let err = paid_call_instructions(&treasury_query_x402_layout(), &PAYER, &TREASURY_QUERY_X402_PARAMS, call);
+    assert!(matches!(err, Err(X402ClientError::Layout(_))), "{:?}", err);
+}
+
+/// `priced_query` as `paid_call_instructions` builds it, and its data.
+fn priced_query_transaction() -> (Vec<Instruction>, Vec<u8>) {
+    let data = instruction::PricedQuery {
+        max_acceptable_price: PRICED_QUERY_X402_PARAMS.price,
+    }
+    .data();
+    let call = Instruction {
+        program_id: x402_example::ID,
+        accounts: accounts::PricedQuery {
+            payer: PAYER,
+            x402_congestion: congestion_address(&x402_example::ID),
+        }
+        .to_account_metas(None),
+        data: data.clone(),
+    };
+    let layout = priced_query_x402_layout();
+    (paid_call_instructions(&layout, &PAYER, &PRICED_QUERY_X402_PARAMS, call).unwrap(), data)
+}
+
+#[test]
+fn instructions_after_the_call_leave_its_check_alone() {
+    let (mut transaction, data) = priced_query_transaction();
+    let memo = Instruction::new_with_bytes(IGNORABLE_PROGRAMS[1], b"order 42", vec![]);
+    let tip = system_instruction::transfer(&PAYER, &Pubkey::new_unique(), 10_000);
+    let again = system_instruction::transfer(&PAYER, &PRICED_QUERY_X402_PARAMS.recipient, 1);
+    let close = Instruction::new_with_bytes(x402_example::ID, &[0; 8], vec![]);
+    transaction.extend([memo, tip, again, close]);
+
+    let trailing = check_paid_call(&priced_query_x402_layout(), &PRICED_QUERY_X402_PARAMS, &transaction, 1).unwrap();
+    assert_eq!(
+        trailing,
+        [Trailing::Ignorable, Trailing::Payment, Trailing::Payment, Trailing::SameProgram]
+    );
+
+    let mut stored = load_at(&transaction, 1, vec![congestion()]);
+    let infos = infos(&mut stored);
+    let answer = run(&infos, &data, |ctx| {
+        x402_example::x402_example::priced_query(ctx, PRICED_QUERY_X402_PARAMS.price)
+    });
+    assert_eq!(answer.unwrap(), 42);
+}
+
+#[test]
+fn a_payment_moved_after_the_call_is_caught_before_sending() {
+    let (mut transaction, data) = priced_query_transaction();
+    transaction.swap(0, 1);
+
+    let err = check_paid_call(&priced_query_x402_layout(), &PRICED_QUERY_X402_PARAMS, &transaction, 0);
+    assert!(matches!(err, Err(X402ClientError::Layout(_))), "{:?}", err);
+
+    // Sent anyway, the check fails the call.
+    let mut stored = load_at(&transaction, 0, vec![congestion()]);
+    let infos = infos(&mut stored);
+    let err = run(&infos, &data, |ctx| {
+        x402_example::x402_example::priced_query(ctx, PRICED_QUERY_X402_PARAMS.price)
+    });
+    assert_eq!(err.unwrap_err(), X402Error::NoPaymentInstruction.into());
+}
@@ -0,0 +1,212 @@
+//! `PaymentLedger` creation and recording. A payer can only record into
+//! the ledger at their own PDA, and an existing ledger can never be
+//! initialized again.
+
+use std::collections::BTreeSet;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::Accounts;
+use x402_common::ledger::PAYMENT_LEDGER_SEED;
+use x402_example::{
+    open_ledger, record_into_ledger, record_rent_into_ledger, with_loyalty_bonus, ComputeResult, ErrorCode,
+    PaymentLedger, RecordPayment, RecordPaymentBumps, Versioned, LOYALTY_BONUS_EVERY,
+};
+use x402_runtime::{ledger, Currency, VerifiedPayment};
+
+const NOW: i64 = 1_700_000_000;
+
+fn ledger_address(payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[PAYMENT_LEDGER_SEED, payer.as_ref()], &x402_example::ID).0
+}
+
+fn ledger(payer: Pubkey) -> PaymentLedger {
+    PaymentLedger {
+        version: PaymentLedger::CURRENT_VERSION,
+        payer,
+        total_payments: 1,
+        total_amount: 1_000_000,
+        last_payment: NOW - 60,
+        total_rent_paid: 0,
+    }
+}
+
+/// An account as the runtime would pass it, leaked so it lives as long as
+/// `try_accounts` needs.
+fn account_info(
+    key: Pubkey,
+    owner: Pubkey,
+    data: Vec<u8>,
+    is_signer: bool,
+    is_writable: bool,
+) -> AccountInfo<'static> {
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        is_writable,
+        Box::leak(Box::new(1_000_000_000)),
+        Box::leak(data.into_boxed_slice()),
+        Box::leak(Box::new(owner)),
+        false,
+        0,
+    )
+}
+
+/// Runs `RecordPayment`'s account validation for `payer` signing against
+/// the ledger account at `address` holding `stored`.
+fn validate(payer: &Pubkey, address: Pubkey, stored: &PaymentLedger) -> Result<()> {
+    let mut data = Vec::new();
+    stored.try_serialize(&mut data).unwrap();
+    let infos: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+        account_info(*payer, system_program::ID, Vec::new(), true, false),
+        account_info(address, x402_example::ID, data, false, true),
+    ]));
+
+    let mut accounts = infos;
+    RecordPayment::try_accounts(
+        &x402_example::ID,
+        &mut accounts,
+        &[],
+        &mut RecordPaymentBumps::default(),
+        &mut BTreeSet::new(),
+    )
+    .map(|_| ())
+}
+
+#[test]
+fn records_into_the_payers_own_ledger() {
+    let payer = Pubkey::new_unique();
+    validate(&payer, ledger_address(&payer), &ledger(payer)).unwrap();
+
+    let mut own = ledger(payer);
+    record_into_ledger(&mut own, &payer, 500_000, NOW).unwrap();
+    assert_eq!(own.total_payments, 2);
+    assert_eq!(own.total_amount, 1_500_000);
+    assert_eq!(own.last_payment, NOW);
+}
+
+#[test]
+fn cannot_record_into_another_payers_ledger() {
+    let (payer, victim) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    // The victim's ledger PDA is not the one the signer's seeds derive.
+    let err = validate(&payer, ledger_address(&victim), &ledger(victim)).unwrap_err();
+    assert_eq!(err, error!(anchor_lang::error::ErrorCode::ConstraintSeeds));
+
+    // A ledger at the signer's PDA naming someone else fails `has_one`.
+    let err = validate(&payer, ledger_address(&payer), &ledger(victim)).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::LedgerPayerMismatch));
+
+    let mut theirs = ledger(victim);
+    let err = record_into_ledger(&mut theirs, &payer, 500_000, NOW).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::LedgerPayerMismatch));
+    assert_eq!(theirs.total_payments, 1);
+}
+
+#[test]
+fn reinitializing_an_existing_ledger_fails() {
+    let payer = Pubkey::new_unique();
+    let mut fresh = PaymentLedger {
+        version: 0,
+        payer: Pubkey::default(),
+        total_payments: 0,
+        total_amount: 0,
+        last_payment: 0,
+        total_rent_paid: 0,
+    };
+    open_ledger(&mut fresh, &payer).unwrap();
+    assert_eq!(fresh.version, PaymentLedger::CURRENT_VERSION);
+    assert_eq!(fresh.payer, payer);
+
+    let mut existing = ledger(payer);
+    let err = open_ledger(&mut existing, &Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::LedgerAlreadyInitialized));
+    assert_eq!(existing.payer, payer);
+    assert_eq!(existing.total_amount, 1_000_000);
+}
+
+#[test]
+fn rejects_empty_payments() {
+    let payer = Pubkey::new_unique();
+    let err = record_into_ledger(&mut ledger(payer), &payer, 0, NOW).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::InvalidPaymentAmount));
+}
+
+#[test]
+fn every_tenth_paid_compute_earns_the_bonus() {
+    let payer = Pubkey::new_unique();
+    let mut stored = PaymentLedger {
+        total_payments: 8,
+        ..ledger(payer)
+    };
+    let payment = VerifiedPayment {
+        amount: 1_000_000,
+        payer,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    };
+
+    // The 9th, 10th and 11th payments, as `ledger = true` records them
+    // before the compute tiers' body runs.
+    let mut values = Vec::new();
+    for _ in 0..3 {
+        let snapshot = ledger::record_at(
+            &stored.payer,
+            &mut stored.total_payments,
+            &mut stored.total_amount,
+            &mut stored.last_payment,
+            &payment,
+            NOW,
+        )
+        .unwrap();
+        values.push((snapshot.total_payments, with_loyalty_bonus(42, Some(&snapshot))));
+    }
+    assert_eq!(LOYALTY_BONUS_EVERY, 10);
+    assert_eq!(values, [(9, 42), (10, 84), (11, 42)]);
+
+    // Without their ledger the payer is not counted.
+    assert_eq!(with_loyalty_bonus(42, None), 42);
+}
+
+#[test]
+fn a_paid_compute_counts_its_result_rent_apart() {
+    let payer = Pubkey::new_unique();
+    let mut stored = ledger(payer);
+    let payment = VerifiedPayment {
+        amount: 1_000_000,
+        payer,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    };
+    let result_rent = Rent::default().minimum_balance(8 + ComputeResult::INIT_SPACE);
+
+    // As `compute` does: `ledger = true` records the payment, then the
+    // body records the rent of the result it creates.
+    ledger::record_at(
+        &stored.payer,
+        &mut stored.total_payments,
+        &mut stored.total_amount,
+        &mut stored.last_payment,
+        &payment,
+        NOW,
+    )
+    .unwrap();
+    record_rent_into_ledger(&mut stored, result_rent).unwrap();
+
+    assert_eq!(stored.total_payments, 2);
+    assert_eq!(stored.total_amount, 2_000_000);
+    assert_eq!(stored.total_rent_paid, result_rent);
+}
+
+#[test]
+fn rent_needs_a_current_ledger() {
+    let mut stale = PaymentLedger {
+        version: 1,
+        ..ledger(Pubkey::new_unique())
+    };
+    let err = record_rent_into_ledger(&mut stale, 1_000).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::AccountNeedsMigration));
+    assert_eq!(stale.total_rent_paid, 0);
+}
@@ -0,0 +1,214 @@
+//! The published manifest against `X402_TABLE`, the price table every
+//! `#[x402]` check in the program reads its row from, and as
+//! `x402_client::fetch_manifest` reads it back.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+use anchor_lang::prelude::*;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::json;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use x402_client::{fetch_manifest, manifest_address};
+use x402_example::{manifest_entries, x402_example::X402_TABLE, X402Manifest};
+use x402_runtime::manifest::{ManifestData, MANIFEST_SEED};
+use x402_runtime::{Currency, ManifestEntry};
+
+/// Set to a file path, has `write_manifest` write the manifest there.
+const OUTPUT_VAR: &str = "X402_MANIFEST_OUTPUT";
+
+/// The manifest account's body as `publish_manifest` writes it.
+fn manifest_bytes() -> Vec<u8> {
+    let manifest = ManifestData {
+        authority: Pubkey::new_from_array([1; 32]),
+        manifest_version: 1,
+        entries: manifest_entries(),
+    };
+    manifest.try_to_vec().unwrap()
+}
+
+#[test]
+fn manifest_entries_are_table_rows() {
+    let entries = manifest_entries();
+    for entry in &entries {
+        assert!(
+            X402_TABLE
+                .iter()
+                .any(|params| *entry == ManifestEntry::new(entry.discriminator, params)),
+            "manifest entry {:?} is not a row of X402_TABLE",
+            entry
+        );
+    }
+}
+
+#[test]
+fn only_configured_recipient_rows_are_unpublished() {
+    use x402_example::x402_example::{
+        DEPOSIT_QUERY_X402_PARAMS, EXPERIMENT_QUERY_X402_PARAMS, SAVINGS_QUERY_X402_PARAMS,
+    };
+
+    let entries = manifest_entries();
+    let unpublished: Vec<_> = X402_TABLE
+        .iter()
+        // Paid from a deposit or from accumulated parts, or at a price that
+        // depends on the payer, so there is no one transfer to describe.
+        .filter(|params| {
+            ![DEPOSIT_QUERY_X402_PARAMS, EXPERIMENT_QUERY_X402_PARAMS, SAVINGS_QUERY_X402_PARAMS].contains(params)
+        })
+        .filter(|params| {
+            !entries
+                .iter()
+                .any(|entry| *entry == ManifestEntry::new(entry.discriminator, params))
+        })
+        .collect();
+
+    // `treasury_query` and `paid_close_result` pay the recipient in
+    // `X402GlobalConfig`, so their rows have no fixed recipient to publish.
+    assert_eq!(unpublished.len(), 2);
+    for params in unpublished {
+        assert_eq!(params.recipient, Default::default());
+        assert_eq!(params.currency, Currency::Sol);
+    }
+}
+
+#[test]
+fn tiers_come_from_the_table() {
+    use x402_example::x402_example::{
+        COMPUTE_ENTERPRISE_X402_PARAMS, COMPUTE_PREMIUM_X402_PARAMS, COMPUTE_STANDARD_X402_PARAMS,
+    };
+
+    assert_eq!(
+        [COMPUTE_PREMIUM_X402_PARAMS, COMPUTE_STANDARD_X402_PARAMS, COMPUTE_ENTERPRISE_X402_PARAMS],
+        X402_TABLE[..3]
+    );
+    assert_eq!(X402_TABLE.map(|params| params.price)[..3], [1_000_000, 5_000_000, 50_000_000]);
+}
+
+#[test]
+fn tags_name_every_priced_instruction() {
+    use x402_runtime::tag::instruction_for;
+
+    let tags = x402_example::tags();
+    assert_eq!(tags.len(), X402_TABLE.len());
+    assert_eq!(instruction_for(tags, "premium_v1"), Some("compute_premium"));
+    assert_eq!(instruction_for(tags, "enterprise_v1"), Some("compute_enterprise"));
+    assert_eq!(instruction_for(tags, "treasury_query"), Some("treasury_query"));
+    assert_eq!(instruction_for(tags, "paid_close_v1"), Some("paid_close_result"));
+    assert_eq!(x402_example::x402_example::COMPUTE_STANDARD_X402_TAG, "standard_v1");
+}
+
+#[test]
+fn modes_name_how_each_instruction_is_paid() {
+    use x402_example::x402_example::{DEPOSIT_QUERY_X402_MODE, PRICED_QUERY_X402_MODE, TREASURY_QUERY_X402_MODE};
+    use x402_runtime::Mode;
+
+    assert_eq!(PRICED_QUERY_X402_MODE, Mode::PrecedingInstruction);
+    assert_eq!(TREASURY_QUERY_X402_MODE, Mode::PrecedingInstruction);
+    assert_eq!(DEPOSIT_QUERY_X402_MODE, Mode::Deposit);
+    assert_eq!(x402_example::x402_example::SAVINGS_QUERY_X402_MODE, Mode::Accumulated);
+}
+
+/// Run by `manifest_is_the_same_in_another_process` in a child process;
+/// does nothing otherwise.
+#[test]
+fn write_manifest() {
+    if let Some(path) = std::env::var_os(OUTPUT_VAR) {
+        std::fs::write(path, manifest_bytes()).unwrap();
+    }
+}
+
+#[test]
+fn manifest_is_the_same_in_another_process() {
+    let manifest = manifest_bytes();
+    assert_eq!(manifest, manifest_bytes());
+
+    let path = std::env::temp_dir().join(format!("x402-manifest-{}", std::process::id()));
+    let child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "write_manifest"])
+        .env(OUTPUT_VAR, &path)
+        .output()
+        .unwrap();
+    assert!(child.status.success(), "{}", String::from_utf8_lossy(&child.stdout));
+    let other = std::fs::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(manifest == other, "the manifest differs between processes");
+}
+
+/// The manifest account as `publish_manifest` and `update_manifest` leave
+/// it: sized for `space` entries, the rest zeroed.
+fn manifest_account(manifest: &X402Manifest, space: usize) -> Vec<u8> {
+    let mut data = Vec::with_capacity(ManifestData::space(space));
+    manifest.try_serialize(&mut data).unwrap();
+    data.resize(ManifestData::space(space), 0);
+    data
+}
+
+/// An RPC client answering `getAccountInfo` once with `data` owned by
+/// `owner`, or with no account.
+fn rpc_with_account(owner: &Pubkey, data: Option<Vec<u8>>) -> RpcClient {
+    let value = data.map(|data| {
+        json!({
+            "lamports": 1_000_000,
+            "data": [STANDARD.encode(&data), "base64"],
+            "owner": owner.to_string(),
+            "executable": false,
+            "rentEpoch": 0,
+            "space": data.len(),
+        })
+    });
+    let mut mocks = HashMap::new();
+    mocks.insert(
+        RpcRequest::GetAccountInfo,
+        json!({ "context": { "slot": 1 }, "value": value }),
+    );
+    RpcClient::new_mock_with_mocks("succeeds".to_string(), mocks)
+}
+
+#[test]
+fn fetches_the_published_manifest() {
+    let authority = Pubkey::new_unique();
+    let manifest = X402Manifest::new(authority);
+    let rpc = rpc_with_account(&x402_example::ID, Some(manifest_account(&manifest, manifest.entries.len())));
+
+    let fetched = fetch_manifest(&rpc, &x402_example::ID).unwrap().unwrap();
+    assert_eq!(
+        fetched,
+        ManifestData {
+            authority,
+            manifest_version: 1,
+            entries: manifest_entries(),
+        }
+    );
+}
+
+#[test]
+fn fetches_a_manifest_grown_by_realloc() {
+    let authority = Pubkey::new_unique();
+    let mut manifest = X402Manifest::new(authority);
+    let published = manifest_account(&manifest, manifest.entries.len());
+
+    manifest.manifest_version = 2;
+    manifest.entries.push(ManifestEntry::new([9; 8], &X402_TABLE[0]));
+    let grown = manifest_account(&manifest, manifest.entries.len());
+    assert!(grown.len() > published.len());
+
+    let rpc = rpc_with_account(&x402_example::ID, Some(grown));
+    let fetched = fetch_manifest(&rpc, &x402_example::ID).unwrap().unwrap();
+    assert_eq!(fetched.manifest_version, 2);
+    assert_eq!(fetched.entries.len(), manifest_entries().len() + 1);
+    assert_eq!(fetched.entries.last(), manifest.entries.last());
+}
+
+#[test]
+fn a_program_without_a_manifest_has_none() {
+    let rpc = rpc_with_account(&x402_example::ID, None);
+    assert_eq!(fetch_manifest(&rpc, &x402_example::ID).unwrap(), None);
+
+    // The address is still the program's, so a later publish is found.
+    assert_eq!(
+        manifest_address(&x402_example::ID),
+        Pubkey::find_program_address(&[MANIFEST_SEED], &x402_example::ID).0
+    );
+}
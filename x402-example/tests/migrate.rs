@@ -0,0 +1,390 @@
+//! Upgrades of old accounts, built from raw bytes in the layout they were
+//! written with.
+
+use anchor_lang::prelude::*;
+use anchor_lang::{AccountDeserialize, Discriminator, ZeroCopy};
+use x402_example::migrate::{
+    compute_result_from_v0, compute_result_from_v1, compute_result_from_v2, compute_result_from_v3,
+    compute_result_from_v4, compute_result_from_v5, congestion_level_from_v1, failure_log_from_v1, payment_ledger_from_v0, payment_ledger_from_v1,
+    spending_cap_from_v0, x402_stats_from_v0, x402_config_from_v1, x402_config_from_v2, x402_stats_from_v1, x402_stats_from_v2, x402_stats_from_v3, COMPUTE_RESULT_V0_SPACE, COMPUTE_RESULT_V1_SPACE, COMPUTE_RESULT_V2_SPACE,
+    COMPUTE_RESULT_V3_SPACE, COMPUTE_RESULT_V4_SPACE, COMPUTE_RESULT_V5_SPACE, CONGESTION_LEVEL_V1_SPACE, FAILURE_LOG_V1_SPACE, PAYMENT_LEDGER_V0_SPACE,
+    PAYMENT_LEDGER_V1_SPACE, SPENDING_CAP_V0_SPACE, X402_CONFIG_V1_SPACE, X402_CONFIG_V2_SPACE, X402_STATS_V0_SPACE, X402_STATS_V1_SPACE, X402_STATS_V2_SPACE,
+    X402_STATS_V3_SPACE,
+};
+use x402_runtime::commitment::GENESIS_COMMITMENT;
+use x402_runtime::{Cluster, PriceProvenance};
+use x402_example::{
+    require_current_version, x402_example::COMPUTE_STANDARD_X402_PARAMS, ComputeResult, CongestionLevel, FailureLog,
+    PaymentLedger, SpendingCap, Versioned, X402GlobalConfig, X402Stats, TIER_ENTERPRISE, TIER_FREE, TIER_PREMIUM, TIER_STANDARD,
+};
+
+fn compute_result_v0(owner: &Pubkey, paid: bool, amount_paid: u64) -> Vec<u8> {
+    let mut data = owner.to_bytes().to_vec();
+    data.extend_from_slice(&100u64.to_le_bytes());
+    data.push(paid as u8);
+    data.extend_from_slice(&amount_paid.to_le_bytes());
+    data
+}
+
+/// Serializes an upgraded account and reads it back the way `Account`
+/// would, so the new layout is checked end to end.
+fn round_trip<T: AccountSerialize + AccountDeserialize + Discriminator>(account: &T) -> T {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    assert_eq!(data[..8], T::DISCRIMINATOR);
+    T::try_deserialize(&mut data.as_slice()).unwrap()
+}
+
+/// [`round_trip`] for a zero-copy account, written as `upgrade_zero_copy`
+/// writes it and read back from its bytes.
+fn zero_copy_round_trip<T: ZeroCopy + Discriminator>(account: &T) -> T {
+    let mut data = T::DISCRIMINATOR.to_vec();
+    data.extend_from_slice(bytemuck::bytes_of(account));
+    assert_eq!(data.len(), 8 + std::mem::size_of::<T>());
+    bytemuck::pod_read_unaligned(&data[8..])
+}
+
+#[test]
+fn v0_layouts_have_their_original_sizes() {
+    let owner = Pubkey::new_unique();
+    assert_eq!(compute_result_v0(&owner, true, 1).len(), COMPUTE_RESULT_V0_SPACE);
+    assert_eq!(COMPUTE_RESULT_V0_SPACE + 10, COMPUTE_RESULT_V1_SPACE);
+    assert_eq!(COMPUTE_RESULT_V1_SPACE + 8, COMPUTE_RESULT_V2_SPACE);
+    assert_eq!(COMPUTE_RESULT_V2_SPACE + 33, COMPUTE_RESULT_V3_SPACE);
+    assert_eq!(COMPUTE_RESULT_V3_SPACE + 32, COMPUTE_RESULT_V4_SPACE);
+    assert_eq!(COMPUTE_RESULT_V4_SPACE + 2, COMPUTE_RESULT_V5_SPACE);
+    // The largest provenance, `Congestion`, after its variant byte.
+    assert_eq!(COMPUTE_RESULT_V5_SPACE + 1 + 32 + 1 + 8, ComputeResult::INIT_SPACE);
+    assert_eq!(PAYMENT_LEDGER_V0_SPACE + 1, PAYMENT_LEDGER_V1_SPACE);
+    assert_eq!(PAYMENT_LEDGER_V1_SPACE + 8, PaymentLedger::INIT_SPACE);
+    assert_eq!(X402_STATS_V0_SPACE + 1, X402_STATS_V1_SPACE);
+    assert_eq!(X402_STATS_V1_SPACE + 32, X402_STATS_V2_SPACE);
+    // The zero-copy layout pads the version byte out to the `u64`s.
+    assert_eq!(X402_STATS_V2_SPACE + 7, X402_STATS_V3_SPACE);
+    assert_eq!(X402_STATS_V3_SPACE + 8, std::mem::size_of::<X402Stats>());
+    assert_eq!(SPENDING_CAP_V0_SPACE + 1, SpendingCap::INIT_SPACE);
+}
+
+#[test]
+fn migrates_paid_compute_result() {
+    let owner = Pubkey::new_unique();
+    // Overpaid the standard tier but not enough for enterprise.
+    let amount = COMPUTE_STANDARD_X402_PARAMS.price + 1;
+    let upgraded = round_trip(&compute_result_from_v0(&compute_result_v0(&owner, true, amount)).unwrap());
+
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.value, 100);
+    assert!(upgraded.paid);
+    assert_eq!(upgraded.amount_paid, amount);
+    assert_eq!(upgraded.tier, TIER_STANDARD);
+    assert_eq!(upgraded.price_paid, COMPUTE_STANDARD_X402_PARAMS.price);
+}
+
+#[test]
+fn migrates_free_compute_result() {
+    let owner = Pubkey::new_unique();
+    let upgraded = compute_result_from_v0(&compute_result_v0(&owner, false, 0)).unwrap();
+    assert_eq!(upgraded.tier, TIER_FREE);
+    assert_eq!(upgraded.price_paid, 0);
+}
+
+#[test]
+fn migrates_v1_compute_result_without_a_slot() {
+    let owner = Pubkey::new_unique();
+    let mut data = vec![1u8];
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(&1000u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&50_000_000u64.to_le_bytes());
+    data.push(TIER_ENTERPRISE);
+    data.extend_from_slice(&50_000_000u64.to_le_bytes());
+    assert_eq!(data.len(), COMPUTE_RESULT_V1_SPACE);
+
+    let upgraded = round_trip(&compute_result_from_v1(&data).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.tier, TIER_ENTERPRISE);
+    assert_eq!(upgraded.price_paid, 50_000_000);
+    assert_eq!(upgraded.created_slot, 0);
+}
+
+#[test]
+fn migrates_v2_compute_result_unbound() {
+    let owner = Pubkey::new_unique();
+    let mut data = vec![2u8];
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.push(TIER_PREMIUM);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.extend_from_slice(&987_654u64.to_le_bytes());
+    assert_eq!(data.len(), COMPUTE_RESULT_V2_SPACE);
+
+    let upgraded = round_trip(&compute_result_from_v2(&data).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.tier, TIER_PREMIUM);
+    assert_eq!(upgraded.created_slot, 987_654);
+    assert_eq!(upgraded.bind_to, None);
+}
+
+#[test]
+fn migrates_v3_compute_result_keeping_its_binding() {
+    let (owner, sponsor) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let v3 = |bind_to: Option<Pubkey>| {
+        let mut data = vec![3u8];
+        data.extend_from_slice(owner.as_ref());
+        data.extend_from_slice(&42u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.push(TIER_PREMIUM);
+        data.extend_from_slice(&1_000_000u64.to_le_bytes());
+        data.extend_from_slice(&987_654u64.to_le_bytes());
+        data.extend_from_slice(&bind_to.try_to_vec().unwrap());
+        // An unbound result keeps the zeroed space reserved for a key.
+        data.resize(COMPUTE_RESULT_V3_SPACE, 0);
+        data
+    };
+
+    let upgraded = round_trip(&compute_result_from_v3(&v3(Some(sponsor))).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.created_slot, 987_654);
+    assert_eq!(upgraded.bind_to, Some(sponsor));
+    assert_eq!(upgraded.fingerprint, [0; 32]);
+
+    let upgraded = compute_result_from_v3(&v3(None)).unwrap();
+    assert_eq!(upgraded.bind_to, None);
+}
+
+#[test]
+fn migrates_v4_compute_result_without_dependents() {
+    let owner = Pubkey::new_unique();
+    let mut data = vec![4u8];
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.push(TIER_PREMIUM);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.extend_from_slice(&987_654u64.to_le_bytes());
+    data.extend_from_slice(&None::<Pubkey>.try_to_vec().unwrap());
+    data.extend_from_slice(&[9; 32]);
+    data.resize(COMPUTE_RESULT_V4_SPACE, 0);
+
+    let upgraded = round_trip(&compute_result_from_v4(&data).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.bind_to, None);
+    assert_eq!(upgraded.fingerprint, [9; 32]);
+    assert_eq!(upgraded.ref_count, 0);
+}
+
+#[test]
+fn migrates_v5_compute_result_as_statically_priced() {
+    let owner = Pubkey::new_unique();
+    let mut data = vec![5u8];
+    data.extend_from_slice(owner.as_ref());
+    data.extend_from_slice(&42u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.push(TIER_PREMIUM);
+    data.extend_from_slice(&1_000_000u64.to_le_bytes());
+    data.extend_from_slice(&987_654u64.to_le_bytes());
+    data.extend_from_slice(&None::<Pubkey>.try_to_vec().unwrap());
+    data.extend_from_slice(&[9; 32]);
+    data.extend_from_slice(&3u16.to_le_bytes());
+    data.resize(COMPUTE_RESULT_V5_SPACE, 0);
+
+    let upgraded = round_trip(&compute_result_from_v5(&data).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.owner, owner);
+    assert_eq!(upgraded.ref_count, 3);
+    assert_eq!(upgraded.provenance, PriceProvenance::Static);
+}
+
+#[test]
+fn rejects_truncated_v0_data() {
+    let owner = Pubkey::new_unique();
+    let data = compute_result_v0(&owner, true, 1);
+    assert!(compute_result_from_v0(&data[..data.len() - 1]).is_err());
+}
+
+#[test]
+fn migrates_payment_ledger_and_sets_payer() {
+    let payer = Pubkey::new_unique();
+    let mut data = Pubkey::default().to_bytes().to_vec();
+    data.extend_from_slice(&3u64.to_le_bytes());
+    data.extend_from_slice(&900u64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+
+    let upgraded = round_trip(&payment_ledger_from_v0(&data, payer).unwrap());
+    assert_eq!(upgraded.version, PaymentLedger::CURRENT_VERSION);
+    assert_eq!(upgraded.payer, payer);
+    assert_eq!(upgraded.total_payments, 3);
+    assert_eq!(upgraded.total_amount, 900);
+    assert_eq!(upgraded.last_payment, 1_700_000_000);
+    assert_eq!(upgraded.total_rent_paid, 0);
+}
+
+#[test]
+fn migrates_v1_payment_ledger_with_no_rent_recorded() {
+    let payer = Pubkey::new_unique();
+    let mut data = vec![1];
+    data.extend_from_slice(payer.as_ref());
+    data.extend_from_slice(&5u64.to_le_bytes());
+    data.extend_from_slice(&5_000_000u64.to_le_bytes());
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    assert_eq!(data.len(), PAYMENT_LEDGER_V1_SPACE);
+
+    let upgraded = round_trip(&payment_ledger_from_v1(&data).unwrap());
+    require_current_version(&upgraded).unwrap();
+    assert_eq!(upgraded.payer, payer);
+    assert_eq!((upgraded.total_payments, upgraded.total_amount), (5, 5_000_000));
+    assert_eq!(upgraded.last_payment, 1_700_000_000);
+    assert_eq!(upgraded.total_rent_paid, 0);
+}
+
+#[test]
+fn migrates_stats_preserving_sequence() {
+    let data: Vec<u8> = [7u64, 7, 7_000_000].iter().flat_map(|v| v.to_le_bytes()).collect();
+
+    let upgraded = zero_copy_round_trip(&x402_stats_from_v0(&data).unwrap());
+    assert_eq!(upgraded.version, X402Stats::CURRENT_VERSION);
+    assert_eq!(upgraded.sequence, 7);
+    assert_eq!(upgraded.total_payments, 7);
+    assert_eq!(upgraded.total_amount, 7_000_000);
+    assert_eq!(upgraded.commitment, GENESIS_COMMITMENT);
+}
+
+#[test]
+fn migrates_v1_stats_onto_a_fresh_commitment_chain() {
+    let mut data = vec![1];
+    data.extend([9u64, 9, 9_000_000].iter().flat_map(|v| v.to_le_bytes()));
+    assert_eq!(data.len(), X402_STATS_V1_SPACE);
+
+    let upgraded = zero_copy_round_trip(&x402_stats_from_v1(&data).unwrap());
+    assert_eq!(upgraded.version, X402Stats::CURRENT_VERSION);
+    assert_eq!((upgraded.sequence, upgraded.total_payments, upgraded.total_amount), (9, 9, 9_000_000));
+    assert_eq!(upgraded.commitment, GENESIS_COMMITMENT);
+}
+
+#[test]
+fn migrates_v2_stats_keeping_their_commitment() {
+    let mut data = vec![2];
+    data.extend([11u64, 10, 10_000_000].iter().flat_map(|v| v.to_le_bytes()));
+    data.extend_from_slice(&[5; 32]);
+    assert_eq!(data.len(), X402_STATS_V2_SPACE);
+
+    let upgraded = zero_copy_round_trip(&x402_stats_from_v2(&data).unwrap());
+    assert_eq!(upgraded.version, X402Stats::CURRENT_VERSION);
+    assert_eq!((upgraded.sequence, upgraded.total_payments, upgraded.total_amount), (11, 10, 10_000_000));
+    assert_eq!(upgraded.commitment, [5; 32]);
+}
+
+#[test]
+fn migrates_v3_stats_with_no_rent_recorded() {
+    let mut data = vec![3, 0, 0, 0, 0, 0, 0, 0];
+    data.extend([12u64, 11, 11_000_000].iter().flat_map(|v| v.to_le_bytes()));
+    data.extend_from_slice(&[6; 32]);
+    assert_eq!(data.len(), X402_STATS_V3_SPACE);
+
+    let upgraded = zero_copy_round_trip(&x402_stats_from_v3(&data).unwrap());
+    assert_eq!(upgraded.version, X402Stats::CURRENT_VERSION);
+    assert_eq!((upgraded.sequence, upgraded.total_payments, upgraded.total_amount), (12, 11, 11_000_000));
+    assert_eq!(upgraded.commitment, [6; 32]);
+    assert_eq!(upgraded.total_rent_paid, 0);
+}
+
+#[test]
+fn migrates_failure_log_keeping_its_entries() {
+    let payers: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+    // Three reports, so the ring's head and length are both 3.
+    let mut data = vec![1, 3, 3];
+    for slot in 0..32u64 {
+        let payer = payers.get(slot as usize).copied().unwrap_or_default();
+        data.extend_from_slice(payer.as_ref());
+        data.push(slot as u8);
+        data.extend_from_slice(&(slot * 1_000).to_le_bytes());
+        data.extend_from_slice(&slot.to_le_bytes());
+    }
+    assert_eq!(data.len(), FAILURE_LOG_V1_SPACE);
+
+    let upgraded = zero_copy_round_trip(&failure_log_from_v1(&data).unwrap());
+    assert_eq!(upgraded.version, FailureLog::CURRENT_VERSION);
+    assert_eq!((upgraded.ring.head, upgraded.ring.len), (3, 3));
+    let recent: Vec<(Pubkey, u8, u64, u64)> = upgraded
+        .ring
+        .recent()
+        .map(|entry| (entry.payer, entry.reason, entry.attempted_amount, entry.slot))
+        .collect();
+    assert_eq!(
+        recent,
+        vec![(payers[2], 2, 2_000, 2), (payers[1], 1, 1_000, 1), (payers[0], 0, 0, 0)]
+    );
+    assert!(failure_log_from_v1(&data[1..]).is_err());
+}
+
+#[test]
+fn migrates_spending_cap() {
+    let payer = Pubkey::new_unique();
+    let mut data = payer.to_bytes().to_vec();
+    data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+    data.extend_from_slice(&86_400i64.to_le_bytes());
+    data.extend_from_slice(&10_000_000u64.to_le_bytes());
+    data.extend_from_slice(&2_000_000u64.to_le_bytes());
+
+    let upgraded = round_trip(&spending_cap_from_v0(&data).unwrap());
+    assert_eq!(upgraded.version, SpendingCap::CURRENT_VERSION);
+    assert_eq!(upgraded.payer, payer);
+    assert_eq!(upgraded.period_secs, 86_400);
+    assert_eq!(upgraded.cap, 10_000_000);
+    assert_eq!(upgraded.spent, 2_000_000);
+}
+
+#[test]
+fn migrates_congestion_level_with_its_window_closed() {
+    let authority = Pubkey::new_unique();
+    let mut data = vec![1];
+    data.extend_from_slice(&authority.to_bytes());
+    data.push(3);
+    assert_eq!(data.len(), CONGESTION_LEVEL_V1_SPACE);
+
+    let upgraded = round_trip(&congestion_level_from_v1(&data).unwrap());
+    assert_eq!(upgraded.version, CongestionLevel::CURRENT_VERSION);
+    assert_eq!(upgraded.authority, authority);
+    assert_eq!((upgraded.level, upgraded.previous_level, upgraded.updated_at), (3, 3, 0));
+}
+
+#[test]
+fn migrates_x402_config_with_its_authority_as_operator() {
+    let (authority, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = vec![1];
+    data.extend_from_slice(&authority.to_bytes());
+    data.extend_from_slice(&recipient.to_bytes());
+    assert_eq!(data.len(), X402_CONFIG_V1_SPACE);
+    assert_eq!(X402_CONFIG_V1_SPACE + 32, X402_CONFIG_V2_SPACE);
+
+    let upgraded = round_trip(&x402_config_from_v1(&data).unwrap());
+    assert_eq!(upgraded.version, X402GlobalConfig::CURRENT_VERSION);
+    assert_eq!((upgraded.authority, upgraded.recipient, upgraded.operator), (authority, recipient, authority));
+    assert_eq!(upgraded.cluster, Cluster::Unset);
+}
+
+#[test]
+fn migrates_x402_config_without_a_cluster() {
+    let (authority, recipient, operator) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = vec![2];
+    data.extend_from_slice(&authority.to_bytes());
+    data.extend_from_slice(&recipient.to_bytes());
+    data.extend_from_slice(&operator.to_bytes());
+    assert_eq!(data.len(), X402_CONFIG_V2_SPACE);
+    assert_eq!(X402_CONFIG_V2_SPACE + 1, X402GlobalConfig::INIT_SPACE);
+
+    let upgraded = round_trip(&x402_config_from_v2(&data).unwrap());
+    assert_eq!(upgraded.version, X402GlobalConfig::CURRENT_VERSION);
+    assert_eq!((upgraded.authority, upgraded.recipient, upgraded.operator), (authority, recipient, operator));
+    assert_eq!(upgraded.cluster, Cluster::Unset);
+}
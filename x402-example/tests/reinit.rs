@@ -0,0 +1,201 @@
+//! Per-payer accounts are created by their own `init_*` instruction, and
+//! the instructions that use them require them to exist already, at the
+//! signer's own address. The accounts still created on first use, the
+//! failure log and the snapshot state, are never reset once they exist;
+//! `initialize_all`'s are covered by `initialize.rs`.
+
+use std::collections::BTreeSet;
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::system_program;
+use anchor_lang::{Accounts, Bumps, Discriminator};
+use x402_common::failures::FailureEntry;
+use x402_example::{
+    open_failure_log, open_snapshot, require_uninitialized, snapshot_ledger, Accumulator, Deposit, DepositAccount,
+    ErrorCode, FailureLog, PaymentLedger, SetSpendingCap, SnapshotState, SpendingCap, UsageCounter, Versioned,
+};
+use x402_runtime::deposit::DEPOSIT_SEED;
+use x402_runtime::spending_cap::SPENDING_CAP_SEED;
+
+/// An account as the runtime would pass it, leaked so it lives as long as
+/// `try_accounts` needs. Its data is backed by `u64`s so `AccountLoader`
+/// can cast it in place.
+fn account_info(key: Pubkey, owner: Pubkey, data: &[u8], is_signer: bool, executable: bool) -> AccountInfo<'static> {
+    let words: &'static mut [u64] = Box::leak(vec![0u64; data.len().div_ceil(8)].into_boxed_slice());
+    let bytes = &mut bytemuck::cast_slice_mut(words)[..data.len()];
+    bytes.copy_from_slice(data);
+    AccountInfo::new(
+        Box::leak(Box::new(key)),
+        is_signer,
+        true,
+        Box::leak(Box::new(1_000_000_000)),
+        bytes,
+        Box::leak(Box::new(owner)),
+        executable,
+        0,
+    )
+}
+
+fn account_data(account: &impl AccountSerialize) -> Vec<u8> {
+    let mut data = Vec::new();
+    account.try_serialize(&mut data).unwrap();
+    data
+}
+
+fn address(seed: &[u8], payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[seed, payer.as_ref()], &x402_example::ID).0
+}
+
+/// Runs `T`'s account validation for `signer` against the program account
+/// at `address`, holding `data` or, for `None`, not created yet.
+fn validate<T>(signer: &Pubkey, address: Pubkey, data: Option<Vec<u8>>) -> Result<()>
+where
+    T: Accounts<'static, T::Bumps> + Bumps,
+    T::Bumps: Default,
+{
+    let account = match data {
+        Some(data) => account_info(address, x402_example::ID, &data, false, false),
+        None => account_info(address, system_program::ID, &[], false, false),
+    };
+    let infos: &'static [AccountInfo<'static>] = Box::leak(Box::new([
+        account_info(*signer, system_program::ID, &[], true, false),
+        account,
+        account_info(system_program::ID, Pubkey::default(), &[], false, true),
+    ]));
+
+    let mut accounts = infos;
+    T::try_accounts(&x402_example::ID, &mut accounts, &[], &mut T::Bumps::default(), &mut BTreeSet::new()).map(|_| ())
+}
+
+fn deposit(depositor: Pubkey) -> Vec<u8> {
+    account_data(&DepositAccount {
+        version: DepositAccount::CURRENT_VERSION,
+        depositor,
+    })
+}
+
+fn spending_cap(payer: Pubkey) -> Vec<u8> {
+    account_data(&SpendingCap {
+        version: SpendingCap::CURRENT_VERSION,
+        payer,
+        period_start: 1_700_000_000,
+        period_secs: 86_400,
+        cap: 5_000_000,
+        spent: 1_000_000,
+    })
+}
+
+#[test]
+fn accounts_must_be_initialized_before_use() {
+    let payer = Pubkey::new_unique();
+    let not_created = error!(anchor_lang::error::ErrorCode::AccountOwnedByWrongProgram);
+
+    validate::<Deposit>(&payer, address(DEPOSIT_SEED, &payer), Some(deposit(payer))).unwrap();
+    let err = validate::<Deposit>(&payer, address(DEPOSIT_SEED, &payer), None).unwrap_err();
+    assert_eq!(err, not_created);
+
+    validate::<SetSpendingCap>(&payer, address(SPENDING_CAP_SEED, &payer), Some(spending_cap(payer))).unwrap();
+    let err = validate::<SetSpendingCap>(&payer, address(SPENDING_CAP_SEED, &payer), None).unwrap_err();
+    assert_eq!(err, not_created);
+}
+
+#[test]
+fn cannot_use_another_payers_account() {
+    let (payer, victim) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    // The victim's PDA is not the one the signer's seeds derive.
+    let err = validate::<Deposit>(&payer, address(DEPOSIT_SEED, &victim), Some(deposit(victim))).unwrap_err();
+    assert_eq!(err, error!(anchor_lang::error::ErrorCode::ConstraintSeeds));
+    let cap = Some(spending_cap(victim));
+    let err = validate::<SetSpendingCap>(&payer, address(SPENDING_CAP_SEED, &victim), cap).unwrap_err();
+    assert_eq!(err, error!(anchor_lang::error::ErrorCode::ConstraintSeeds));
+
+    // An account at the signer's PDA naming someone else fails `has_one`.
+    let err = validate::<Deposit>(&payer, address(DEPOSIT_SEED, &payer), Some(deposit(victim))).unwrap_err();
+    assert_eq!(err, error!(anchor_lang::error::ErrorCode::ConstraintHasOne));
+    let cap = Some(spending_cap(victim));
+    let err = validate::<SetSpendingCap>(&payer, address(SPENDING_CAP_SEED, &payer), cap).unwrap_err();
+    assert_eq!(err, error!(anchor_lang::error::ErrorCode::ConstraintHasOne));
+}
+
+#[test]
+fn reinitializing_an_existing_account_fails() {
+    let payer = Pubkey::new_unique();
+    let already = error!(ErrorCode::AccountAlreadyInitialized);
+
+    let blank = DepositAccount {
+        version: 0,
+        depositor: Pubkey::default(),
+    };
+    require_uninitialized(&blank).unwrap();
+    let existing = DepositAccount {
+        version: DepositAccount::CURRENT_VERSION,
+        depositor: payer,
+    };
+    assert_eq!(require_uninitialized(&existing).unwrap_err(), already);
+
+    let accumulator = Accumulator {
+        version: Accumulator::CURRENT_VERSION,
+        balance: 400_000,
+    };
+    assert_eq!(require_uninitialized(&accumulator).unwrap_err(), already);
+    let cap = SpendingCap::try_deserialize(&mut &spending_cap(payer)[..]).unwrap();
+    assert_eq!(require_uninitialized(&cap).unwrap_err(), already);
+    let counter = UsageCounter {
+        version: UsageCounter::CURRENT_VERSION,
+        payer,
+        last_used: 1_700_000_000,
+        uses: 3,
+    };
+    assert_eq!(require_uninitialized(&counter).unwrap_err(), already);
+}
+
+#[test]
+fn an_existing_failure_log_is_not_reset() {
+    let info: &'static AccountInfo<'static> = Box::leak(Box::new(account_info(
+        Pubkey::new_unique(),
+        x402_example::ID,
+        &vec![0; 8 + std::mem::size_of::<FailureLog>()],
+        false,
+        false,
+    )));
+    let payer = Pubkey::new_unique();
+    let loader = AccountLoader::<FailureLog>::try_from_unchecked(&x402_example::ID, info).unwrap();
+
+    // Just created by the first report.
+    let (mut log, created) = open_failure_log(&loader).unwrap();
+    assert!(created);
+    assert_eq!(log.version, FailureLog::CURRENT_VERSION);
+    log.ring.push(FailureEntry::new(payer, 3, 500_000, 42));
+    drop(log);
+    loader.exit(&x402_example::ID).unwrap();
+    assert_eq!(&info.try_borrow_data().unwrap()[..8], FailureLog::DISCRIMINATOR);
+
+    // A later report appends to it.
+    let (log, created) = open_failure_log(&loader).unwrap();
+    assert!(!created);
+    let entries: Vec<_> = log.ring.recent().map(|entry| (entry.payer, entry.attempted_amount)).collect();
+    assert_eq!(entries, [(payer, 500_000)]);
+}
+
+#[test]
+fn a_snapshot_in_progress_is_not_reset() {
+    let mut state = SnapshotState::try_deserialize_unchecked(&mut &[0; 8 + SnapshotState::INIT_SPACE][..]).unwrap();
+    open_snapshot(&mut state).unwrap();
+    assert_eq!(state.version, SnapshotState::CURRENT_VERSION);
+
+    let payer = Pubkey::new_unique();
+    let ledger = PaymentLedger {
+        version: PaymentLedger::CURRENT_VERSION,
+        payer,
+        total_payments: 2,
+        total_amount: 3_000_000,
+        last_payment: 1_700_000_000,
+        total_rent_paid: 0,
+    };
+    snapshot_ledger(&mut state, &payer, &ledger).unwrap();
+
+    // The next crank's batch continues the same snapshot.
+    open_snapshot(&mut state).unwrap();
+    assert_eq!((state.leaves, state.last_payer, state.total_amount), (1, payer, 3_000_000));
+}
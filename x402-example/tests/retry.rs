@@ -0,0 +1,96 @@
+//! Retrying a compute call with the same nonce: the result address already
+//! holds the first attempt's result, so the retry fails with
+//! `ResultAlreadyExists`, and its payment reverts with it, instead of
+//! charging the payer twice.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+use x402_example::{require_fresh_result, ComputeResult, ErrorCode, Versioned, TIER_PREMIUM};
+use x402_runtime::PriceProvenance;
+
+const PRICE: u64 = 1_000_000;
+
+fn result(owner: &Pubkey) -> ComputeResult {
+    ComputeResult {
+        version: ComputeResult::CURRENT_VERSION,
+        owner: *owner,
+        value: 42,
+        paid: true,
+        amount_paid: PRICE,
+        tier: TIER_PREMIUM,
+        price_paid: PRICE,
+        created_slot: 10,
+        bind_to: None,
+        fingerprint: [0; 32],
+        ref_count: 0,
+        provenance: PriceProvenance::Static,
+    }
+}
+
+/// The result address of one call, and the payments landed with it.
+struct Chain {
+    owner: Pubkey,
+    lamports: u64,
+    data: Vec<u8>,
+    payments: u32,
+}
+
+impl Chain {
+    fn new() -> Self {
+        Chain {
+            owner: system_program::ID,
+            lamports: 0,
+            data: Vec::new(),
+            payments: 0,
+        }
+    }
+
+    /// A paid call by `payer`: its payment commits only if the call does.
+    fn call(&mut self, payer: &Pubkey) -> Result<()> {
+        let address = Pubkey::new_unique();
+        let (owner, mut lamports, mut data) = (self.owner, self.lamports, self.data.clone());
+        let info = AccountInfo::new(&address, false, true, &mut lamports, &mut data, &owner, false, 0);
+        require_fresh_result(&info, payer)?;
+
+        self.payments += 1;
+        self.owner = x402_example::ID;
+        self.lamports = 2_000_000;
+        self.data.clear();
+        result(payer).try_serialize(&mut self.data)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn a_retry_after_success_is_refused_without_charging() {
+    let payer = Pubkey::new_unique();
+    let mut chain = Chain::new();
+
+    chain.call(&payer).unwrap();
+    // The confirmation was lost; the client retries with the same nonce.
+    for _ in 0..2 {
+        assert_eq!(chain.call(&payer).unwrap_err(), error!(ErrorCode::ResultAlreadyExists));
+    }
+    assert_eq!(chain.payments, 1);
+}
+
+#[test]
+fn lamports_sent_ahead_do_not_block_the_result() {
+    let mut chain = Chain::new();
+    chain.lamports = 1;
+    chain.call(&Pubkey::new_unique()).unwrap();
+    assert_eq!(chain.payments, 1);
+}
+
+#[test]
+fn another_account_at_the_address_is_not_the_payers() {
+    let mut chain = Chain::new();
+    chain.call(&Pubkey::new_unique()).unwrap();
+    assert_eq!(chain.call(&Pubkey::new_unique()).unwrap_err(), error!(ErrorCode::NotResultOwner));
+
+    let mut garbage = Chain::new();
+    garbage.owner = x402_example::ID;
+    garbage.data = vec![1; 16];
+    assert_eq!(garbage.call(&Pubkey::new_unique()).unwrap_err(), error!(ErrorCode::NotResultOwner));
+    assert_eq!(chain.payments + garbage.payments, 1);
+}
@@ -0,0 +1,149 @@
+//! `snapshot_ledgers` and `finalize_snapshot` over a dozen ledgers cranked
+//! through in batches: the root the program emits is the one the client
+//! computes from the same ledgers, each payer's proof verifies against it,
+//! and ledgers out of order, repeated or mislabelled are refused.
+
+use anchor_lang::prelude::*;
+use x402_client::snapshot::LedgerTree;
+use x402_common::ledger::PaymentLedgerData;
+use x402_common::snapshot::SNAPSHOT_DEPTH;
+use x402_example::{finish_snapshot, snapshot_ledger, ErrorCode, PaymentLedger, SnapshotState, Versioned};
+
+const SLOT: u64 = 250_000_000;
+
+fn ledger(payer: Pubkey, total_payments: u64, total_amount: u64) -> PaymentLedger {
+    PaymentLedger {
+        version: PaymentLedger::CURRENT_VERSION,
+        payer,
+        total_payments,
+        total_amount,
+        last_payment: 1_700_000_000,
+        total_rent_paid: 2_039_280,
+    }
+}
+
+/// The ledger as the client lists it.
+fn listed(ledger: &PaymentLedger) -> PaymentLedgerData {
+    PaymentLedgerData {
+        version: ledger.version,
+        payer: ledger.payer,
+        total_payments: ledger.total_payments,
+        total_amount: ledger.total_amount,
+        last_payment: ledger.last_payment,
+        total_rent_paid: ledger.total_rent_paid,
+    }
+}
+
+/// A dozen ledgers, in no particular order.
+fn ledgers() -> Vec<PaymentLedger> {
+    (1..=12).map(|i| ledger(Pubkey::new_unique(), i, i * 1_000_003)).collect()
+}
+
+/// Cranks `ledgers` through `snapshot_ledger` in batches of `batch`, as
+/// successive `snapshot_ledgers` calls would.
+fn crank(state: &mut SnapshotState, ledgers: &[PaymentLedger], batch: usize) -> Result<()> {
+    for chunk in ledgers.chunks(batch) {
+        for ledger in chunk {
+            snapshot_ledger(state, &ledger.payer, ledger)?;
+        }
+    }
+    Ok(())
+}
+
+/// `ledgers` in the order the program takes them.
+fn in_payer_order(mut ledgers: Vec<PaymentLedger>) -> Vec<PaymentLedger> {
+    ledgers.sort_by_key(|ledger| ledger.payer);
+    ledgers
+}
+
+#[test]
+fn a_snapshot_in_batches_has_the_root_the_client_computes() {
+    let ledgers = in_payer_order(ledgers());
+    let tree = LedgerTree::new(ledgers.iter().map(listed)).unwrap();
+
+    let mut state = SnapshotState::empty();
+    crank(&mut state, &ledgers, 5).unwrap();
+    let event = finish_snapshot(&mut state, 7, SLOT);
+
+    assert_eq!(event.root, tree.root());
+    assert_eq!((event.epoch, event.ledgers, event.slot), (7, 12, SLOT));
+    assert_eq!(event.total_amount, ledgers.iter().map(|ledger| ledger.total_amount).sum::<u64>());
+    // The batch size does not change the root.
+    let mut one_at_a_time = SnapshotState::empty();
+    crank(&mut one_at_a_time, &ledgers, 1).unwrap();
+    assert_eq!(finish_snapshot(&mut one_at_a_time, 7, SLOT).root, event.root);
+
+    // Finalizing starts the next snapshot from nothing.
+    assert_eq!((state.leaves, state.total_amount), (0, 0));
+    assert_eq!(state.frontier, [[0; 32]; SNAPSHOT_DEPTH]);
+    crank(&mut state, &ledgers[..3], 2).unwrap();
+    let partial = LedgerTree::new(ledgers[..3].iter().map(listed)).unwrap();
+    assert_eq!(finish_snapshot(&mut state, 8, SLOT).root, partial.root());
+}
+
+#[test]
+fn every_payer_proves_their_totals() {
+    let ledgers = ledgers();
+    let tree = LedgerTree::new(ledgers.iter().map(listed)).unwrap();
+    let mut state = SnapshotState::empty();
+    crank(&mut state, &in_payer_order(ledgers.clone()), 4).unwrap();
+    let root = finish_snapshot(&mut state, 1, SLOT).root;
+
+    for ledger in &ledgers {
+        let proof = tree.proof(&ledger.payer).unwrap();
+        assert_eq!((proof.total_payments, proof.total_amount), (ledger.total_payments, ledger.total_amount));
+        assert_eq!(proof.siblings.len(), SNAPSHOT_DEPTH);
+        assert!(proof.verify(&root));
+
+        let mut inflated = proof.clone();
+        inflated.total_amount += 1;
+        assert!(!inflated.verify(&root));
+        let mut moved = proof.clone();
+        moved.index ^= 1;
+        assert!(!moved.verify(&root));
+    }
+    assert_eq!(tree.proof(&Pubkey::new_unique()), None);
+
+    // A payment recorded after the snapshot is not in it.
+    let mut paid_again = ledgers.clone();
+    paid_again[0].total_payments += 1;
+    paid_again[0].total_amount += 5_000;
+    let later = LedgerTree::new(paid_again.iter().map(listed)).unwrap();
+    assert!(!later.proof(&ledgers[0].payer).unwrap().verify(&root));
+}
+
+#[test]
+fn ledgers_go_in_once_and_in_payer_order() {
+    let ledgers = in_payer_order(ledgers());
+
+    let mut state = SnapshotState::empty();
+    crank(&mut state, &ledgers[..6], 6).unwrap();
+    // Again, as a repeated batch would.
+    let err = snapshot_ledger(&mut state, &ledgers[5].payer, &ledgers[5]).unwrap_err();
+    assert_eq!(err, ErrorCode::SnapshotOutOfOrder.into());
+    let err = snapshot_ledger(&mut state, &ledgers[2].payer, &ledgers[2]).unwrap_err();
+    assert_eq!(err, ErrorCode::SnapshotOutOfOrder.into());
+    assert_eq!(state.leaves, 6);
+
+    // A ledger passed for another payer of the batch.
+    let err = snapshot_ledger(&mut state, &ledgers[7].payer, &ledgers[6]).unwrap_err();
+    assert_eq!(err, ErrorCode::SnapshotBatchMismatch.into());
+
+    // A ledger still to migrate.
+    let mut legacy = ledgers[6].clone();
+    legacy.version = 1;
+    let err = snapshot_ledger(&mut state, &legacy.payer, &legacy).unwrap_err();
+    assert_eq!(err, ErrorCode::AccountNeedsMigration.into());
+
+    crank(&mut state, &ledgers[6..], 3).unwrap();
+    let tree = LedgerTree::new(ledgers.iter().map(listed)).unwrap();
+    assert_eq!(finish_snapshot(&mut state, 2, SLOT).root, tree.root());
+}
+
+#[test]
+fn the_client_refuses_two_ledgers_of_one_payer() {
+    let ledgers = ledgers();
+    let mut listed: Vec<_> = ledgers.iter().map(listed).collect();
+    listed.push(listed[3]);
+    assert!(LedgerTree::new(listed).is_err());
+}
@@ -0,0 +1,68 @@
+//! `track_stats` against the zero-copy `X402Stats`: the expansion's
+//! writes land in the account's data with nothing serialized on exit, and
+//! accounts still on the Borsh layout are sent to `migrate_stats`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use x402_common::stats::StatsData;
+use x402_example::{zero_copy_version, ErrorCode, Versioned, X402Stats};
+use x402_runtime::stats::{record, StatsAccount};
+use x402_runtime::{Currency, VerifiedPayment};
+
+/// Account data for a stats account of `len` bytes. `AccountLoader` casts
+/// the data in place, so it is backed by `u64`s to be aligned as the
+/// runtime aligns it.
+fn stats_words(version: u8, len: usize) -> Vec<u64> {
+    let mut words = vec![0u64; len.div_ceil(8)];
+    let data: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+    data[..8].copy_from_slice(&X402Stats::DISCRIMINATOR);
+    data[8] = version;
+    words
+}
+
+fn payment(amount: u64) -> VerifiedPayment {
+    VerifiedPayment {
+        amount,
+        payer: Pubkey::new_unique(),
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+#[test]
+fn records_in_place() {
+    let mut words = stats_words(X402Stats::CURRENT_VERSION, 8 + std::mem::size_of::<X402Stats>());
+    {
+        let (key, mut lamports) = (Pubkey::new_unique(), 1);
+        let data: &mut [u8] = bytemuck::cast_slice_mut(&mut words);
+        let info = AccountInfo::new(&key, false, true, &mut lamports, data, &x402_example::ID, false, 0);
+        let mut loader = AccountLoader::<X402Stats>::try_from(&info).unwrap();
+        assert_eq!(zero_copy_version(&loader).unwrap(), X402Stats::CURRENT_VERSION);
+
+        for (amount, expected) in [(1_000, 1), (2_500, 2)] {
+            // As the `#[x402]` expansion does, once per paid call.
+            let mut stats = loader.stats_mut().unwrap();
+            let stats = &mut *stats;
+            let sequence =
+                record(&mut stats.sequence, &mut stats.total_payments, &mut stats.total_amount, &payment(amount));
+            assert_eq!(sequence.unwrap(), expected);
+        }
+    }
+
+    let data: &[u8] = bytemuck::cast_slice(&words);
+    let stats: StatsData = bytemuck::pod_read_unaligned(&data[8..]);
+    assert_eq!((stats.sequence, stats.total_payments, stats.total_amount), (2, 2, 3_500));
+}
+
+#[test]
+fn borsh_stats_need_migrating() {
+    // A version 2 account: the version byte, three `u64`s and the commitment.
+    let len = 8 + 1 + 24 + 32;
+    let mut words = stats_words(2, len);
+    let (key, mut lamports) = (Pubkey::new_unique(), 1);
+    let data = &mut bytemuck::cast_slice_mut::<u64, u8>(&mut words)[..len];
+    let info = AccountInfo::new(&key, false, true, &mut lamports, data, &x402_example::ID, false, 0);
+    let loader = AccountLoader::<X402Stats>::try_from(&info).unwrap();
+    assert_eq!(zero_copy_version(&loader).unwrap_err(), ErrorCode::AccountNeedsMigration.into());
+}
@@ -0,0 +1,42 @@
+//! The instructions `pay_and_invoke` may invoke, and what it must pay.
+
+use anchor_lang::prelude::*;
+use anchor_lang::Discriminator;
+use x402_example::x402_example::{COMPUTE_ENTERPRISE_X402_PARAMS, COMPUTE_PREMIUM_X402_PARAMS};
+use x402_example::{instruction, trampoline_params, ErrorCode};
+use x402_runtime::trampoline::{handler_discriminator, PAY_AND_INVOKE_DISCRIMINATOR};
+
+#[test]
+fn allowlists_the_compute_tiers_at_their_prices() {
+    let mut data = instruction::ComputePremium::DISCRIMINATOR.to_vec();
+    data.push(0);
+    assert_eq!(trampoline_params(&data).unwrap(), COMPUTE_PREMIUM_X402_PARAMS);
+    assert_eq!(
+        trampoline_params(&instruction::ComputeEnterprise::DISCRIMINATOR).unwrap(),
+        COMPUTE_ENTERPRISE_X402_PARAMS
+    );
+}
+
+#[test]
+fn refuses_to_invoke_itself() {
+    assert_eq!(instruction::PayAndInvoke::DISCRIMINATOR, PAY_AND_INVOKE_DISCRIMINATOR);
+    let err = trampoline_params(&PAY_AND_INVOKE_DISCRIMINATOR).unwrap_err();
+    assert_eq!(err, error!(ErrorCode::InstructionNotAllowlisted));
+}
+
+#[test]
+fn refuses_other_instructions() {
+    for data in [
+        instruction::PricedQuery::DISCRIMINATOR.to_vec(),
+        instruction::SetCongestionLevel::DISCRIMINATOR.to_vec(),
+        vec![1, 2, 3],
+    ] {
+        let err = trampoline_params(&data).unwrap_err();
+        assert_eq!(err, error!(ErrorCode::InstructionNotAllowlisted));
+    }
+}
+
+#[test]
+fn tiers_check_the_discriminator_they_are_invoked_with() {
+    assert_eq!(handler_discriminator("compute_premium"), instruction::ComputePremium::DISCRIMINATOR);
+}
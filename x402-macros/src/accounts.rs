@@ -0,0 +1,162 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{Field, Fields, GenericParam, Ident, ItemStruct, LitStr, MetaNameValue, Token};
+
+use crate::args::{mode_lit, parse_pubkey, set_once, str_lit, PaymentMode};
+
+/// Fields `#[x402_accounts]` may inject; a struct declaring one itself is
+/// rejected rather than silently shadowed.
+const INJECTED_FIELDS: &[&str] = &["x402_instructions", "x402_recipient"];
+
+pub struct AccountsArgs {
+    mode: PaymentMode,
+    /// Address constraint on `x402_recipient` in balance delta mode.
+    address: Option<[u8; 32]>,
+}
+
+impl Parse for AccountsArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metas = Punctuated::<MetaNameValue, Token![,]>::parse_terminated(input)?;
+
+        let mut mode = None;
+        let mut address: Option<([u8; 32], LitStr)> = None;
+
+        for meta in metas {
+            let key = meta
+                .path
+                .get_ident()
+                .ok_or_else(|| syn::Error::new_spanned(&meta.path, "expected `mode` or `address`"))?;
+
+            match key.to_string().as_str() {
+                "mode" => set_once(&mut mode, mode_lit(&meta.value)?.0, key)?,
+                "address" | "recipient" => {
+                    let lit = str_lit(&meta.value, "address")?;
+                    set_once(&mut address, (parse_pubkey(lit, "address")?, lit.clone()), key)?;
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        key,
+                        format!("unknown x402_accounts argument `{}`; expected `mode` or `address`", key),
+                    ));
+                }
+            }
+        }
+
+        let mode = mode.unwrap_or(PaymentMode::PrecedingInstruction);
+        if let (PaymentMode::PrecedingInstruction, Some((_, lit))) = (mode, &address) {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "`address` only applies to mode = \"balance_delta\"; preceding-instruction payments name their recipient in #[x402]",
+            ));
+        }
+
+        Ok(AccountsArgs {
+            mode,
+            address: address.map(|(key, _)| key),
+        })
+    }
+}
+
+/// Appends the mode's accounts to `item` and implements
+/// `x402_runtime::accounts::X402Accounts` for it.
+pub fn expand_struct(args: AccountsArgs, mut item: ItemStruct) -> syn::Result<TokenStream> {
+    if !item.attrs.iter().any(is_derive_accounts) {
+        return Err(syn::Error::new_spanned(
+            &item.ident,
+            "#[x402_accounts] must be placed above #[derive(Accounts)]",
+        ));
+    }
+
+    let info = item
+        .generics
+        .params
+        .iter()
+        .find_map(|param| match param {
+            GenericParam::Lifetime(param) => Some(param.lifetime.clone()),
+            _ => None,
+        })
+        .ok_or_else(|| {
+            syn::Error::new_spanned(
+                &item.ident,
+                "#[x402_accounts] needs an accounts struct with a lifetime, e.g. `Compute<'info>`",
+            )
+        })?;
+
+    let fields = match &mut item.fields {
+        Fields::Named(fields) => &mut fields.named,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &item.ident,
+                "#[x402_accounts] needs an accounts struct with named fields",
+            ));
+        }
+    };
+    for field in fields.iter() {
+        let name = field.ident.as_ref().map(Ident::to_string).unwrap_or_default();
+        if INJECTED_FIELDS.contains(&name.as_str()) {
+            return Err(syn::Error::new_spanned(
+                &field.ident,
+                format!("`{}` is injected by #[x402_accounts]; remove it from the struct", name),
+            ));
+        }
+    }
+
+    let (mode, instructions_sysvar) = match args.mode {
+        PaymentMode::PrecedingInstruction => {
+            fields.push(named_field(quote! {
+                /// CHECK: the instructions sysvar read by `#[x402]`, checked by address.
+                #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+                pub x402_instructions: UncheckedAccount<#info>
+            })?);
+            (
+                quote!(x402_runtime::accounts::PrecedingInstruction),
+                quote!(Some(anchor_lang::ToAccountInfo::to_account_info(&self.x402_instructions))),
+            )
+        }
+        PaymentMode::BalanceDelta => {
+            let constraint = args.address.map(|address| {
+                let address = &address[..];
+                quote!(, address = anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#address),*]))
+            });
+            fields.push(named_field(quote! {
+                /// The payment recipient, credited by the handler body.
+                #[account(mut #constraint)]
+                pub x402_recipient: SystemAccount<#info>
+            })?);
+            (quote!(x402_runtime::accounts::BalanceDelta), quote!(None))
+        }
+    };
+
+    let ident = &item.ident;
+    let (impl_generics, ty_generics, where_clause) = item.generics.split_for_impl();
+
+    Ok(quote! {
+        #item
+
+        impl #impl_generics x402_runtime::accounts::X402Accounts<#info> for #ident #ty_generics #where_clause {
+            type Mode = #mode;
+
+            fn instructions_sysvar(&self) -> Option<anchor_lang::solana_program::account_info::AccountInfo<#info>> {
+                #instructions_sysvar
+            }
+        }
+    })
+}
+
+fn named_field(tokens: TokenStream) -> syn::Result<Field> {
+    Field::parse_named.parse2(tokens)
+}
+
+fn is_derive_accounts(attr: &syn::Attribute) -> bool {
+    if !attr.path().is_ident("derive") {
+        return false;
+    }
+    let mut found = false;
+    let _ = attr.parse_nested_meta(|meta| {
+        found |= meta.path.segments.last().is_some_and(|seg| seg.ident == "Accounts");
+        Ok(())
+    });
+    found
+}
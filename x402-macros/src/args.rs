@@ -0,0 +1,1420 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use proc_macro2::Span;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use quote::ToTokens;
+use syn::{
+    Expr, ExprLit, ExprTuple, FnArg, Ident, Lit, LitInt, LitStr, Meta, MetaList, MetaNameValue, PatType, Signature,
+    Token, Type,
+};
+use x402_units::Unit;
+
+use crate::modes::{conflict_message, Mode};
+use crate::table::{NETWORK, TABLE_DEPTH, TABLE_ROW};
+
+/// Every argument `#[x402]` accepts, in the order error messages list them.
+/// `recipient` is additionally accepted as an alias of `address`.
+pub const OPTION_NAMES: &[&str] = &[
+    "price",
+    "price_sol",
+    "price_usdc",
+    "address",
+    "token",
+    "facilitator_fee",
+    "respect_spending_cap",
+    "track_stats",
+    "commitment_log",
+    "emit_event",
+    "allow_split_payers",
+    "max_split_payers",
+    "mode",
+    "deposit",
+    "require_fee_payer",
+    "strict_accounts",
+    "max_lookback",
+    "strict_scan",
+    "legacy_matcher",
+    "emit_latency",
+    "record_fingerprint",
+    "congestion_surcharge",
+    "price_grace_bps",
+    "price_grace_window_secs",
+    "experiment",
+    "splits",
+    "tag",
+    "allow_trampoline",
+    "require_nonce",
+    "allow_cpi",
+    "ledger",
+    "record_provenance",
+    "recipient_from_arg",
+    "group",
+    "group_cap",
+    "reentrancy_guard",
+    "accumulated",
+    "sysvar_position",
+    "accepts_tags",
+    "creates_account_space",
+    "allow_rent_heavy",
+];
+
+/// The names `#[x402_program(network = "...")]` accepts, with the
+/// `x402_runtime::Cluster` variant of each. Keep in sync with
+/// `x402_common::cluster::Cluster`.
+const NETWORKS: [(&str, &str); 4] = [
+    ("mainnet-beta", "MainnetBeta"),
+    ("devnet", "Devnet"),
+    ("testnet", "Testnet"),
+    ("localnet", "Localnet"),
+];
+/// Largest account the runtime lets an instruction create,
+/// `solana_program::system_instruction::MAX_PERMITTED_DATA_LENGTH`.
+const MAX_ACCOUNT_SPACE: u64 = 10 * 1024 * 1024;
+/// Keep in sync with `x402_runtime::MAX_SPLIT_PAYERS`.
+const MAX_SPLIT_PAYERS: u8 = 8;
+/// Keep in sync with `x402_runtime::MAX_LOOKBACK`.
+const MAX_LOOKBACK: u8 = 16;
+/// Keep in sync with `x402_runtime::congestion::MAX_GRACE_BPS`.
+const MAX_GRACE_BPS: u16 = 1_000;
+/// Keep in sync with `x402_runtime::experiment::MAX_EXPERIMENT_ARMS`.
+const MAX_EXPERIMENT_ARMS: usize = 8;
+/// Keep in sync with `x402_runtime::revenue_split::MAX_REVENUE_SHARES`.
+const MAX_REVENUE_SHARES: usize = 5;
+/// Basis points the shares of a revenue split add up to.
+const TOTAL_SHARE_BPS: u32 = 10_000;
+/// Longest PDA seed, which a bucket seed is used as.
+const MAX_BUCKET_SEED_LEN: usize = 32;
+/// Keep in sync with `x402_runtime::MAX_TAG_LEN`.
+pub const MAX_TAG_LEN: usize = 16;
+const DEFAULT_SPLIT_PAYERS: u8 = 4;
+
+pub fn is_option(name: &str) -> bool {
+    name == "recipient" || OPTION_NAMES.contains(&name)
+}
+
+fn known_args() -> String {
+    OPTION_NAMES
+        .iter()
+        .map(|name| format!("`{}`", name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// How a dynamic price moved just before a call is forgiven.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PriceGrace {
+    /// `price_grace_bps`: payments this many basis points below the price
+    /// are accepted.
+    Bps(u16),
+    /// `price_grace_window_secs`: the previous level's price is accepted
+    /// for this many seconds after the level changes.
+    WindowSecs(u32),
+}
+
+/// Where the payment is found.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PaymentMode {
+    /// A transfer instruction immediately before the priced one.
+    PrecedingInstruction,
+    /// A transfer made by the handler body itself, checked through the
+    /// recipient's balance once the body returns.
+    BalanceDelta,
+}
+
+/// The earlier tags of `accepts_tags(...)`.
+#[derive(Clone)]
+pub struct AcceptedTags {
+    /// The earlier tags, less the pinned one.
+    pub tags: Vec<LitStr>,
+    /// The unix time they are accepted until.
+    pub until: i64,
+}
+
+/// Where among the remaining accounts the instructions sysvar is read from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SysvarPosition {
+    /// The last remaining account only; the ones before it are the
+    /// handler's own, bound as `x402_user_accounts`.
+    Last,
+    /// Any remaining account, as earlier versions searched.
+    Any,
+}
+
+pub struct X402Args {
+    /// In lamports, or in base units of `token` for `price_usdc`.
+    pub price: u64,
+    /// `None` reads the recipient from the program's `x402_config` account.
+    pub recipient: Option<[u8; 32]>,
+    pub token: Option<[u8; 32]>,
+    /// The price was given with `price_usdc`, so it is in `token` units.
+    pub token_price: bool,
+    /// Emitted for the facilitator; never charged.
+    pub facilitator_fee: Option<u8>,
+    /// Charge the payer's optional `spending_cap` account.
+    pub respect_spending_cap: bool,
+    /// Count the payment in the program's `x402_stats` account.
+    pub track_stats: bool,
+    /// Fold the payment into the commitment held in `x402_stats`.
+    pub commitment_log: bool,
+    /// Emit `X402PaymentVerifiedEvent`; on unless disabled.
+    pub emit_event: bool,
+    /// How many distinct payers may split the price, when more than one.
+    pub split_payers: Option<u8>,
+    pub mode: PaymentMode,
+    /// Debit the price from the payer's `x402_deposit` instead of finding a
+    /// payment.
+    pub deposit: bool,
+    /// Require the payment to be signed by the handler's `payer`.
+    pub require_fee_payer: bool,
+    /// Take every account from an `#[x402_accounts]` struct and reject
+    /// remaining accounts.
+    pub strict_accounts: bool,
+    /// Non-ignorable instructions the payment scan may step over.
+    pub max_lookback: u8,
+    /// Stop the scan at the first instruction that is not a payment.
+    pub strict_scan: bool,
+    /// Classify instructions with the deprecated legacy matcher.
+    pub legacy_matcher: bool,
+    /// Emit `X402PaymentLatencyEvent` with the slot the payment was used in.
+    pub emit_latency: bool,
+    /// Fingerprint the call and bind it as `x402_fingerprint`.
+    pub record_fingerprint: bool,
+    /// The congestion account and basis points per level of
+    /// `congestion_surcharge(...)`.
+    pub congestion: Option<([u8; 32], u16)>,
+    /// Grace for a congestion surcharge raised just before the call.
+    pub price_grace: Option<PriceGrace>,
+    /// The bucket seed and per-arm prices of `experiment(...)`.
+    pub experiment: Option<(LitStr, Vec<u64>)>,
+    /// The recipients and basis-point shares of `splits(...)`.
+    pub splits: Option<Vec<([u8; 32], u16)>>,
+    /// Also accept a payment made by the program's own `pay_and_invoke`.
+    pub allow_trampoline: bool,
+    /// Take an `x402_nonce: [u8; 32]` argument and emit it with the payment.
+    pub require_nonce: bool,
+    /// Accept being invoked by another program, paid before its call.
+    pub allow_cpi: bool,
+    /// Record the payment in the payer's optional `x402_ledger` account.
+    pub ledger: bool,
+    /// Bind what the price was computed from as `x402_provenance`.
+    pub record_provenance: bool,
+    /// The key that must authorize the `recipient` argument of
+    /// `recipient_from_arg(...)`.
+    pub recipient_authority: Option<[u8; 32]>,
+    /// The name and cap of the instruction group the handler charges.
+    pub group: Option<(LitStr, u64)>,
+    /// Fail with `ReentrantCall` while the program's `x402_reentrancy_guard`
+    /// is set, and hold it set while the handler runs.
+    pub reentrancy_guard: bool,
+    /// Debit the price from the payments the payer accumulated toward the
+    /// handler in its `x402_accumulator` instead of finding a payment.
+    pub accumulated: bool,
+    /// Where the instructions sysvar is among the remaining accounts.
+    pub sysvar_position: SysvarPosition,
+    /// Pinned instruction tag; `None` defaults to the handler's name.
+    pub tag: Option<LitStr>,
+    /// The earlier tags the instruction still accepts.
+    pub accepts_tags: Option<AcceptedTags>,
+    /// Bytes of the accounts the call makes the payer fund, from
+    /// `creates_account_space`.
+    pub created_space: Option<u64>,
+    /// Do not warn when the rent for `created_space` rivals the price.
+    pub allow_rent_heavy: bool,
+    /// The `x402_runtime::Cluster` variant the program is built for,
+    /// passed down by `#[x402_program(network = "...")]`.
+    pub network: Option<Ident>,
+    /// Row of the program's `X402_TABLE`, assigned by `#[x402_program]`.
+    pub table_row: Option<usize>,
+    /// Modules between the handler and `X402_TABLE`, 0 outside of them.
+    pub table_depth: usize,
+}
+
+/// Where each argument was first given, so errors about how arguments
+/// combine point at the argument instead of the whole attribute.
+#[derive(Default)]
+struct ArgSpans(BTreeMap<String, Span>);
+
+impl ArgSpans {
+    fn insert(&mut self, key: &Ident) {
+        self.0.entry(key.to_string()).or_insert_with(|| key.span());
+    }
+
+    fn get(&self, name: &str) -> Option<Span> {
+        self.0.get(name).copied()
+    }
+
+    fn error(&self, name: &str, message: impl Display) -> syn::Error {
+        syn::Error::new(self.get(name).unwrap_or_else(Span::call_site), message)
+    }
+}
+
+/// Errors collected across every argument, reported together so one build
+/// shows every problem.
+#[derive(Default)]
+struct Errors(Option<syn::Error>);
+
+impl Errors {
+    fn push(&mut self, err: syn::Error) {
+        match &mut self.0 {
+            Some(errors) => errors.combine(err),
+            None => self.0 = Some(err),
+        }
+    }
+
+    fn finish(self) -> syn::Result<()> {
+        self.0.map_or(Ok(()), Err)
+    }
+}
+
+/// Arguments as given, before checking how they combine.
+#[derive(Default)]
+struct Given {
+    price: Option<(u64, Unit, Ident)>,
+    recipient: Option<[u8; 32]>,
+    token: Option<[u8; 32]>,
+    facilitator_fee: Option<u8>,
+    respect_spending_cap: Option<bool>,
+    track_stats: Option<bool>,
+    commitment_log: Option<bool>,
+    emit_event: Option<bool>,
+    allow_split_payers: Option<bool>,
+    max_split_payers: Option<u8>,
+    mode: Option<PaymentMode>,
+    deposit: Option<bool>,
+    require_fee_payer: Option<bool>,
+    strict_accounts: Option<bool>,
+    max_lookback: Option<u8>,
+    strict_scan: Option<bool>,
+    legacy_matcher: Option<bool>,
+    emit_latency: Option<bool>,
+    record_fingerprint: Option<bool>,
+    congestion: Option<([u8; 32], u16)>,
+    price_grace_bps: Option<u16>,
+    price_grace_window_secs: Option<u32>,
+    experiment: Option<(LitStr, Vec<u64>)>,
+    splits: Option<Vec<([u8; 32], u16)>>,
+    allow_trampoline: Option<bool>,
+    require_nonce: Option<bool>,
+    allow_cpi: Option<bool>,
+    ledger: Option<bool>,
+    record_provenance: Option<bool>,
+    recipient_authority: Option<[u8; 32]>,
+    group: Option<LitStr>,
+    group_cap: Option<u64>,
+    reentrancy_guard: Option<bool>,
+    accumulated: Option<bool>,
+    sysvar_position: Option<SysvarPosition>,
+    tag: Option<LitStr>,
+    accepts_tags: Option<AcceptedTags>,
+    created_space: Option<u64>,
+    allow_rent_heavy: Option<bool>,
+    network: Option<Ident>,
+    table_row: Option<usize>,
+    table_depth: Option<usize>,
+}
+
+impl Given {
+    /// Records one argument, failing only on that argument's own value.
+    fn add(&mut self, meta: Meta, spans: &mut ArgSpans) -> syn::Result<()> {
+        let meta = match meta {
+            Meta::List(list) if list.path.is_ident("congestion_surcharge") => {
+                let key = &list.path.segments[0].ident;
+                spans.insert(key);
+                return set_once(&mut self.congestion, congestion_surcharge(&list)?, key);
+            }
+            Meta::List(list) if list.path.is_ident("experiment") => {
+                let key = &list.path.segments[0].ident;
+                spans.insert(key);
+                return set_once(&mut self.experiment, experiment(&list)?, key);
+            }
+            Meta::List(list) if list.path.is_ident("splits") => {
+                let key = &list.path.segments[0].ident;
+                spans.insert(key);
+                return set_once(&mut self.splits, splits(&list)?, key);
+            }
+            Meta::List(list) if list.path.is_ident("accepts_tags") => {
+                let key = &list.path.segments[0].ident;
+                spans.insert(key);
+                return set_once(&mut self.accepts_tags, accepts_tags(&list)?, key);
+            }
+            Meta::List(list) if list.path.is_ident("recipient_from_arg") => {
+                let key = &list.path.segments[0].ident;
+                spans.insert(key);
+                return set_once(&mut self.recipient_authority, recipient_from_arg(&list)?, key);
+            }
+            meta => meta.require_name_value()?.clone(),
+        };
+        let key = meta.path.get_ident().ok_or_else(|| {
+            syn::Error::new_spanned(&meta.path, format!("expected one of {}", known_args()))
+        })?;
+        spans.insert(key);
+
+        match key.to_string().as_str() {
+            "price" => {
+                let lit = int_lit(&meta.value, "price")?;
+                let value: u64 = lit.base10_parse()?;
+                set_price(&mut self.price, value, Unit::Sol, lit, key)
+            }
+            "price_sol" | "price_usdc" => {
+                let unit = if key == "price_sol" { Unit::Sol } else { Unit::Usdc };
+                let lit = match &meta.value {
+                    Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+                    value => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            format!("`{}` must be a string literal such as \"0.05\"", key),
+                        ));
+                    }
+                };
+                let value = x402_units::parse_decimal(&lit.value(), unit.decimals())
+                    .map_err(|err| syn::Error::new_spanned(lit, format!("invalid `{}`: {}", key, err)))?;
+                set_price(&mut self.price, value, unit, lit, key)
+            }
+            "address" | "recipient" => {
+                let lit = str_lit(&meta.value, "address")?;
+                set_once(&mut self.recipient, parse_pubkey(lit, "address")?, key)
+            }
+            "token" => {
+                let lit = str_lit(&meta.value, "token")?;
+                set_once(&mut self.token, parse_pubkey(lit, "token")?, key)
+            }
+            "facilitator_fee" => {
+                let lit = int_lit(&meta.value, "facilitator_fee")?;
+                set_once(&mut self.facilitator_fee, lit.base10_parse::<u8>()?, key)
+            }
+            "respect_spending_cap" => {
+                let value = bool_lit(&meta.value, "respect_spending_cap")?;
+                set_once(&mut self.respect_spending_cap, value, key)
+            }
+            "track_stats" => {
+                let value = bool_lit(&meta.value, "track_stats")?;
+                set_once(&mut self.track_stats, value, key)
+            }
+            "commitment_log" => {
+                let value = bool_lit(&meta.value, "commitment_log")?;
+                set_once(&mut self.commitment_log, value, key)
+            }
+            "emit_event" => {
+                let value = bool_lit(&meta.value, "emit_event")?;
+                set_once(&mut self.emit_event, value, key)
+            }
+            "allow_split_payers" => {
+                let value = bool_lit(&meta.value, "allow_split_payers")?;
+                set_once(&mut self.allow_split_payers, value, key)
+            }
+            "max_split_payers" => {
+                let lit = int_lit(&meta.value, "max_split_payers")?;
+                let value: u8 = lit.base10_parse()?;
+                if !(2..=MAX_SPLIT_PAYERS).contains(&value) {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`max_split_payers` must be between 2 and {}", MAX_SPLIT_PAYERS),
+                    ));
+                }
+                set_once(&mut self.max_split_payers, value, key)
+            }
+            "mode" => {
+                let (value, _) = mode_lit(&meta.value)?;
+                set_once(&mut self.mode, value, key)
+            }
+            "deposit" => {
+                let value = bool_lit(&meta.value, "deposit")?;
+                set_once(&mut self.deposit, value, key)
+            }
+            "require_fee_payer" => {
+                let value = bool_lit(&meta.value, "require_fee_payer")?;
+                set_once(&mut self.require_fee_payer, value, key)
+            }
+            "strict_accounts" => {
+                let value = bool_lit(&meta.value, "strict_accounts")?;
+                set_once(&mut self.strict_accounts, value, key)
+            }
+            "max_lookback" => {
+                let lit = int_lit(&meta.value, "max_lookback")?;
+                let value: u8 = lit.base10_parse()?;
+                if !(1..=MAX_LOOKBACK).contains(&value) {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`max_lookback` must be between 1 and {}", MAX_LOOKBACK),
+                    ));
+                }
+                set_once(&mut self.max_lookback, value, key)
+            }
+            "strict_scan" => {
+                let value = bool_lit(&meta.value, "strict_scan")?;
+                set_once(&mut self.strict_scan, value, key)
+            }
+            "legacy_matcher" => {
+                let value = bool_lit(&meta.value, "legacy_matcher")?;
+                set_once(&mut self.legacy_matcher, value, key)
+            }
+            "emit_latency" => {
+                let value = bool_lit(&meta.value, "emit_latency")?;
+                set_once(&mut self.emit_latency, value, key)
+            }
+            "congestion_surcharge" => Err(syn::Error::new_spanned(
+                &meta,
+                "`congestion_surcharge` takes a list, e.g. congestion_surcharge(account = \"...\", bps_per_level = 500)",
+            )),
+            "experiment" => Err(syn::Error::new_spanned(
+                &meta,
+                "`experiment` takes a list, e.g. experiment(bucket_seed = \"exp1\", prices(1_000_000, 800_000))",
+            )),
+            "splits" => Err(syn::Error::new_spanned(
+                &meta,
+                "`splits` takes a list of recipients and basis points, e.g. splits((\"...\", 7000), (\"...\", 3000))",
+            )),
+            "accepts_tags" => Err(syn::Error::new_spanned(
+                &meta,
+                "`accepts_tags` takes a list, e.g. accepts_tags(\"old_v1\", until = 1_767_225_600)",
+            )),
+            "recipient_from_arg" => Err(syn::Error::new_spanned(
+                &meta,
+                "`recipient_from_arg` takes a list, e.g. recipient_from_arg(authorized_by = \"...\")",
+            )),
+            "price_grace_bps" => {
+                let lit = int_lit(&meta.value, "price_grace_bps")?;
+                let value: u16 = lit.base10_parse()?;
+                if !(1..=MAX_GRACE_BPS).contains(&value) {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`price_grace_bps` must be between 1 and {}", MAX_GRACE_BPS),
+                    ));
+                }
+                set_once(&mut self.price_grace_bps, value, key)
+            }
+            "price_grace_window_secs" => {
+                let lit = int_lit(&meta.value, "price_grace_window_secs")?;
+                let value: u32 = lit.base10_parse()?;
+                if value == 0 {
+                    return Err(syn::Error::new_spanned(lit, "`price_grace_window_secs` must be at least 1"));
+                }
+                set_once(&mut self.price_grace_window_secs, value, key)
+            }
+            "record_fingerprint" => {
+                let value = bool_lit(&meta.value, "record_fingerprint")?;
+                set_once(&mut self.record_fingerprint, value, key)
+            }
+            "tag" => {
+                let lit = match &meta.value {
+                    Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+                    value => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "`tag` must be a string literal such as \"premium_v1\"",
+                        ));
+                    }
+                };
+                check_tag(lit)?;
+                set_once(&mut self.tag, lit.clone(), key)
+            }
+            "allow_trampoline" => {
+                let value = bool_lit(&meta.value, "allow_trampoline")?;
+                set_once(&mut self.allow_trampoline, value, key)
+            }
+            "require_nonce" => {
+                let value = bool_lit(&meta.value, "require_nonce")?;
+                set_once(&mut self.require_nonce, value, key)
+            }
+            "allow_cpi" => {
+                let value = bool_lit(&meta.value, "allow_cpi")?;
+                set_once(&mut self.allow_cpi, value, key)
+            }
+            "ledger" => {
+                let value = bool_lit(&meta.value, "ledger")?;
+                set_once(&mut self.ledger, value, key)
+            }
+            "record_provenance" => {
+                let value = bool_lit(&meta.value, "record_provenance")?;
+                set_once(&mut self.record_provenance, value, key)
+            }
+            "reentrancy_guard" => {
+                let value = bool_lit(&meta.value, "reentrancy_guard")?;
+                set_once(&mut self.reentrancy_guard, value, key)
+            }
+            "accumulated" => {
+                let value = bool_lit(&meta.value, "accumulated")?;
+                set_once(&mut self.accumulated, value, key)
+            }
+            "sysvar_position" => set_once(&mut self.sysvar_position, sysvar_position_lit(&meta.value)?, key),
+            "creates_account_space" => {
+                let lit = int_lit(&meta.value, "creates_account_space")?;
+                let value: u64 = lit.base10_parse()?;
+                if !(1..=MAX_ACCOUNT_SPACE).contains(&value) {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`creates_account_space` must be 1 to {} bytes", MAX_ACCOUNT_SPACE),
+                    ));
+                }
+                set_once(&mut self.created_space, value, key)
+            }
+            "allow_rent_heavy" => {
+                let value = bool_lit(&meta.value, "allow_rent_heavy")?;
+                set_once(&mut self.allow_rent_heavy, value, key)
+            }
+            "group" => {
+                let lit = match &meta.value {
+                    Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+                    value => {
+                        return Err(syn::Error::new_spanned(
+                            value,
+                            "`group` must be a string literal such as \"compute\"",
+                        ));
+                    }
+                };
+                let len = lit.value().len();
+                if len == 0 || len > MAX_TAG_LEN {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`group` must be 1 to {} bytes long, but \"{}\" is {}", MAX_TAG_LEN, lit.value(), len),
+                    ));
+                }
+                set_once(&mut self.group, lit.clone(), key)
+            }
+            "group_cap" => {
+                let lit = int_lit(&meta.value, "group_cap")?;
+                let value: u64 = lit.base10_parse()?;
+                if value == 0 {
+                    return Err(syn::Error::new_spanned(lit, "`group_cap` must be greater than zero"));
+                }
+                set_once(&mut self.group_cap, value, key)
+            }
+            // Not in `OPTION_NAMES`: only `#[x402_program]` writes it.
+            key_name if key_name == NETWORK => {
+                let lit = str_lit(&meta.value, NETWORK)?;
+                set_once(&mut self.network, network_lit(lit)?, key)
+            }
+            key_name if key_name == TABLE_ROW => {
+                let lit = int_lit(&meta.value, TABLE_ROW)?;
+                set_once(&mut self.table_row, lit.base10_parse::<usize>()?, key)
+            }
+            key_name if key_name == TABLE_DEPTH => {
+                let lit = int_lit(&meta.value, TABLE_DEPTH)?;
+                set_once(&mut self.table_depth, lit.base10_parse::<usize>()?, key)
+            }
+            _ => Err(syn::Error::new_spanned(
+                key,
+                format!("unknown x402 argument `{}`; expected one of {}", key, known_args()),
+            )),
+        }
+    }
+}
+
+impl Parse for X402Args {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metas = Punctuated::<Meta, Token![,]>::parse_terminated(input)?;
+
+        // Every argument is parsed and every combination checked before
+        // failing, so all problems are reported at once.
+        let mut errors = Errors::default();
+        let mut spans = ArgSpans::default();
+        let mut given = Given::default();
+        for meta in metas {
+            if let Err(err) = given.add(meta, &mut spans) {
+                errors.push(err);
+            }
+        }
+
+        // A price that was given but did not parse is already reported. An
+        // experiment gives its prices itself.
+        let price_given = ["price", "price_sol", "price_usdc", "experiment"]
+            .iter()
+            .any(|name| spans.get(name).is_some());
+        if !price_given {
+            errors.push(syn::Error::new(
+                Span::call_site(),
+                "missing required argument `price`, e.g. #[x402(price = 1_000_000, address = \"...\")]",
+            ));
+        }
+        let unit = given.price.as_ref().map(|(_, unit, _)| *unit);
+        let token_price = unit == Some(Unit::Usdc);
+        if token_price && given.token.is_none() {
+            errors.push(spans.error("price_usdc", "`price_usdc` requires `token`, the address of the USDC mint"));
+        }
+        if given.token.is_some() && given.price.as_ref().is_some_and(|(_, _, key)| key == "price_sol") {
+            errors.push(spans.error("token", "`token` only applies to `price_usdc`; a `price_sol` is paid in SOL"));
+        }
+
+        let split_payers = match (given.allow_split_payers.unwrap_or(false), given.max_split_payers) {
+            (true, max) => Some(max.unwrap_or(DEFAULT_SPLIT_PAYERS)),
+            (false, None) => None,
+            (false, Some(_)) => {
+                errors.push(spans.error("max_split_payers", "`max_split_payers` requires `allow_split_payers = true`"));
+                None
+            }
+        };
+        let require_fee_payer = given.require_fee_payer.unwrap_or(false);
+        if require_fee_payer && split_payers.is_some() {
+            errors.push(spans.error(
+                "require_fee_payer",
+                "`require_fee_payer` cannot be combined with `allow_split_payers`",
+            ));
+        }
+
+        let mode = given.mode.unwrap_or(PaymentMode::PrecedingInstruction);
+        let deposit = given.deposit.unwrap_or(false);
+        let accumulated = given.accumulated.unwrap_or(false);
+        if deposit && accumulated {
+            errors.push(spans.error(
+                "accumulated",
+                "`accumulated = true` cannot be combined with `deposit = true`; choose where the price is debited from",
+            ));
+        }
+
+        // Options the mode gives no meaning to, reported together.
+        let selected = Mode::of(mode, deposit, accumulated);
+        let rule = selected.rule();
+        let conflicts: Vec<&str> = rule.excludes.iter().copied().filter(|name| spans.get(name).is_some()).collect();
+        if !conflicts.is_empty() {
+            errors.push(spans.error(rule.selector, conflict_message(selected, &conflicts)));
+        }
+
+        // The scan options only shape the search for a single preceding
+        // payment.
+        let scan_options = ["max_lookback", "strict_scan", "legacy_matcher"];
+        for name in scan_options.into_iter().filter(|name| spans.get(name).is_some()) {
+            if split_payers.is_some() {
+                errors.push(spans.error(name, format!("`{}` cannot be combined with `allow_split_payers`", name)));
+            }
+        }
+
+        // Strict accounts read the declared `x402_instructions` and leave no
+        // remaining accounts to place the sysvar among.
+        if given.strict_accounts.unwrap_or(false) && spans.get("sysvar_position").is_some() {
+            errors.push(spans.error(
+                "sysvar_position",
+                "`sysvar_position` cannot be combined with `strict_accounts`, which reads the sysvar from `x402_instructions`",
+            ));
+        }
+
+        // Aliases stand for the tag the instruction had before its pinned
+        // one; the pinned tag itself needs no alias.
+        let accepts_tags = match given.accepts_tags {
+            Some(_) if given.tag.is_none() => {
+                errors.push(spans.error(
+                    "accepts_tags",
+                    "`accepts_tags` needs a pinned `tag`, the one the earlier tags migrate to",
+                ));
+                None
+            }
+            Some(mut accepted) => {
+                let primary = given.tag.as_ref().map(LitStr::value);
+                accepted.tags.retain(|alias| Some(alias.value()) != primary);
+                if accepted.tags.is_empty() {
+                    errors.push(spans.error(
+                        "accepts_tags",
+                        "`accepts_tags` lists no tag other than the pinned `tag`",
+                    ));
+                }
+                Some(accepted)
+            }
+            None => None,
+        };
+
+        // The commitment lives next to the sequence in `x402_stats`.
+        let commitment_log = given.commitment_log.unwrap_or(false);
+        if commitment_log && !given.track_stats.unwrap_or(false) {
+            errors.push(spans.error(
+                "commitment_log",
+                "`commitment_log = true` needs `track_stats = true`; the commitment is kept in `x402_stats`",
+            ));
+        }
+
+        let record_fingerprint = given.record_fingerprint.unwrap_or(false);
+
+        // `pay_and_invoke` makes one payment from the handler's payer before
+        // the handler runs.
+        let allow_trampoline = given.allow_trampoline.unwrap_or(false);
+        if allow_trampoline && split_payers.is_some() {
+            errors.push(spans.error(
+                "allow_trampoline",
+                "`allow_trampoline` cannot be combined with `allow_split_payers`",
+            ));
+        }
+
+        // A trampoline call is already a CPI, of a known shape.
+        let allow_cpi = given.allow_cpi.unwrap_or(false);
+        if allow_cpi && allow_trampoline {
+            errors.push(spans.error("allow_cpi", "`allow_cpi` cannot be combined with `allow_trampoline`"));
+        }
+
+        // The nonce travels in `X402PaymentVerifiedEvent`, which split
+        // payments replace with events of their own.
+        let require_nonce = given.require_nonce.unwrap_or(false);
+        if require_nonce {
+            if !given.emit_event.unwrap_or(true) {
+                errors.push(spans.error(
+                    "require_nonce",
+                    "`require_nonce = true` needs the payment event; remove emit_event = false",
+                ));
+            }
+            for name in ["allow_split_payers", "splits"].into_iter().filter(|name| spans.get(name).is_some()) {
+                errors.push(spans.error(name, format!("`{}` cannot be combined with `require_nonce`", name)));
+            }
+        }
+
+        if deposit && token_price {
+            errors.push(spans.error("deposit", "`deposit = true` needs a SOL price; deposits hold lamports"));
+        }
+        if accumulated && token_price {
+            errors.push(spans.error(
+                "accumulated",
+                "`accumulated = true` needs a SOL price; accumulators count lamports",
+            ));
+        }
+
+        // Rent is paid in lamports, so only a SOL price compares to it.
+        if given.created_space.is_some() && token_price {
+            errors.push(spans.error(
+                "creates_account_space",
+                "`creates_account_space` needs a SOL price to compare the rent to",
+            ));
+        }
+        if spans.get("allow_rent_heavy").is_some() && given.created_space.is_none() {
+            errors.push(spans.error(
+                "allow_rent_heavy",
+                "`allow_rent_heavy` requires `creates_account_space`, the bytes the call makes the payer fund",
+            ));
+        }
+
+        // Only a dynamic price can move between a quote and the call.
+        for name in ["price_grace_bps", "price_grace_window_secs"] {
+            if spans.get(name).is_some() && given.congestion.is_none() {
+                errors.push(spans.error(
+                    name,
+                    format!("`{}` only applies to a dynamic price, e.g. congestion_surcharge(...)", name),
+                ));
+            }
+        }
+        let price_grace = match (given.price_grace_bps, given.price_grace_window_secs) {
+            (Some(_), Some(_)) => {
+                errors.push(spans.error(
+                    "price_grace_window_secs",
+                    "`price_grace_window_secs` cannot be combined with `price_grace_bps`; choose one grace",
+                ));
+                None
+            }
+            (Some(bps), None) => Some(PriceGrace::Bps(bps)),
+            (None, Some(secs)) => Some(PriceGrace::WindowSecs(secs)),
+            (None, None) => None,
+        };
+
+        // The experiment picks the price per payer; the payer must be the
+        // handler's one and alone.
+        if spans.get("experiment").is_some() {
+            if let Some((_, _, key)) = &given.price {
+                errors.push(spans.error(
+                    &key.to_string(),
+                    format!("`{}` cannot be combined with `experiment`, which sets the prices", key),
+                ));
+            }
+            for name in ["congestion_surcharge", "allow_split_payers", "allow_trampoline"] {
+                if spans.get(name).is_some() {
+                    errors.push(spans.error(name, format!("`{}` cannot be combined with `experiment`", name)));
+                }
+            }
+        }
+
+        // Each recipient is paid by its own transfer from the handler's
+        // payer, found by a scan of its own; the first recipient stands in
+        // for `address`.
+        if spans.get("splits").is_some() {
+            let unsupported = [
+                "address",
+                "recipient",
+                "token",
+                "price_usdc",
+                "allow_split_payers",
+                "max_split_payers",
+                "max_lookback",
+                "strict_scan",
+                "legacy_matcher",
+                "experiment",
+                "allow_trampoline",
+            ];
+            for name in unsupported.into_iter().filter(|name| spans.get(name).is_some()) {
+                errors.push(spans.error(name, format!("`{}` cannot be combined with `splits`", name)));
+            }
+        }
+
+        // The recipient is the argument, and the authorization signs one
+        // price, paid by a transfer the scan finds.
+        if spans.get("recipient_from_arg").is_some() {
+            let unsupported = [
+                "address",
+                "recipient",
+                "congestion_surcharge",
+                "experiment",
+                "splits",
+                "allow_trampoline",
+            ];
+            for name in unsupported.into_iter().filter(|name| spans.get(name).is_some()) {
+                errors.push(spans.error(name, format!("`{}` cannot be combined with `recipient_from_arg`", name)));
+            }
+        }
+
+        // The budget is kept per payer in lamports, and counts each payment
+        // the handler's one check finds.
+        let group = match (&given.group, given.group_cap) {
+            (Some(name), Some(cap)) => Some((name.clone(), cap)),
+            (Some(_), None) => {
+                errors.push(spans.error(
+                    "group",
+                    "`group` needs `group_cap`, the most the group's calls in one transaction cost, e.g. group_cap = 50_000_000",
+                ));
+                None
+            }
+            (None, Some(_)) => {
+                errors.push(spans.error("group_cap", "`group_cap` requires `group`"));
+                None
+            }
+            (None, None) => None,
+        };
+        if spans.get("group").is_some() {
+            if token_price {
+                errors.push(spans.error("group", "`group` needs a SOL price; `group_cap` is in lamports"));
+            }
+            for name in ["allow_split_payers", "max_split_payers", "splits"] {
+                if spans.get(name).is_some() {
+                    errors.push(spans.error(name, format!("`{}` cannot be combined with `group`", name)));
+                }
+            }
+        }
+
+        errors.finish()?;
+        let price = given
+            .experiment
+            .as_ref()
+            .map(|(_, prices)| prices[0])
+            .or(given.price.as_ref().map(|(price, _, _)| *price));
+        let Some(price) = price else {
+            // Reported above, as missing or as invalid.
+            return Err(syn::Error::new(Span::call_site(), "missing required argument `price`"));
+        };
+
+        Ok(X402Args {
+            price,
+            recipient: given
+                .splits
+                .as_ref()
+                .map(|splits| splits[0].0)
+                .or(given.recipient),
+            token: given.token,
+            token_price,
+            facilitator_fee: given.facilitator_fee,
+            respect_spending_cap: given.respect_spending_cap.unwrap_or(false),
+            track_stats: given.track_stats.unwrap_or(false),
+            commitment_log,
+            emit_event: given.emit_event.unwrap_or(true),
+            split_payers,
+            mode,
+            deposit,
+            require_fee_payer,
+            strict_accounts: given.strict_accounts.unwrap_or(false),
+            max_lookback: given.max_lookback.unwrap_or(1),
+            strict_scan: given.strict_scan.unwrap_or(false),
+            legacy_matcher: given.legacy_matcher.unwrap_or(false),
+            emit_latency: given.emit_latency.unwrap_or(false),
+            record_fingerprint,
+            congestion: given.congestion,
+            price_grace,
+            experiment: given.experiment,
+            splits: given.splits,
+            allow_trampoline,
+            require_nonce,
+            allow_cpi,
+            ledger: given.ledger.unwrap_or(false),
+            record_provenance: given.record_provenance.unwrap_or(false),
+            recipient_authority: given.recipient_authority,
+            group,
+            reentrancy_guard: given.reentrancy_guard.unwrap_or(false),
+            accumulated,
+            sysvar_position: given.sysvar_position.unwrap_or(SysvarPosition::Last),
+            tag: given.tag,
+            accepts_tags,
+            created_space: given.created_space,
+            allow_rent_heavy: given.allow_rent_heavy.unwrap_or(false),
+            network: given.network,
+            table_row: given.table_row,
+            table_depth: given.table_depth.unwrap_or(0),
+        })
+    }
+}
+
+/// Argument through which callers of a handler with a dynamic price bound
+/// what they accept to pay.
+pub const MAX_PRICE_ARG: &str = "max_acceptable_price";
+
+/// Argument carrying the server nonce of a handler with `require_nonce`.
+pub const NONCE_ARG: &str = "x402_nonce";
+
+/// Argument naming the recipient of a handler with `recipient_from_arg`.
+pub const RECIPIENT_ARG: &str = "recipient";
+
+/// Whether `sig` declares an argument named `name`, such as
+/// [`MAX_PRICE_ARG`].
+pub fn has_arg(sig: &Signature, name: &str) -> bool {
+    sig.inputs.iter().any(|arg| match arg {
+        FnArg::Typed(PatType { pat, .. }) => {
+            matches!(pat.as_ref(), syn::Pat::Ident(pat) if pat.ident == name)
+        }
+        FnArg::Receiver(_) => false,
+    })
+}
+
+/// Returns the identifier bound to the handler's `Context<...>` parameter,
+/// rejecting functions that are not Anchor instruction handlers.
+pub fn context_ident(sig: &Signature) -> syn::Result<Ident> {
+    let ctx = sig.inputs.first().and_then(|arg| match arg {
+        FnArg::Typed(PatType { pat, ty, .. }) if is_context(ty) => match pat.as_ref() {
+            syn::Pat::Ident(pat) => Some(pat.ident.clone()),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    ctx.ok_or_else(|| {
+        syn::Error::new_spanned(
+            &sig.ident,
+            format!(
+                "#[x402] can only be applied to an Anchor instruction handler; `{}` must take `ctx: Context<...>` as its first argument",
+                sig.ident
+            ),
+        )
+    })
+}
+
+fn is_context(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|seg| seg.ident == "Context")
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// Parses the `account = "..."` and `bps_per_level = N` of
+/// `congestion_surcharge(...)`, both required.
+fn congestion_surcharge(list: &MetaList) -> syn::Result<([u8; 32], u16)> {
+    let metas = list.parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)?;
+
+    let mut account = None;
+    let mut bps_per_level = None;
+    for meta in &metas {
+        let key = meta.path.get_ident().ok_or_else(|| {
+            syn::Error::new_spanned(&meta.path, "expected `account` or `bps_per_level`")
+        })?;
+        match key.to_string().as_str() {
+            "account" => {
+                let lit = str_lit(&meta.value, "account")?;
+                set_once(&mut account, parse_pubkey(lit, "account")?, key)?;
+            }
+            "bps_per_level" => {
+                let lit = int_lit(&meta.value, "bps_per_level")?;
+                let value: u16 = lit.base10_parse()?;
+                if value == 0 {
+                    return Err(syn::Error::new_spanned(lit, "`bps_per_level` must be greater than zero"));
+                }
+                set_once(&mut bps_per_level, value, key)?;
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    key,
+                    format!(
+                        "unknown congestion_surcharge argument `{}`; expected `account` or `bps_per_level`",
+                        key
+                    ),
+                ));
+            }
+        }
+    }
+
+    match (account, bps_per_level) {
+        (Some(account), Some(bps_per_level)) => Ok((account, bps_per_level)),
+        (None, _) => Err(syn::Error::new_spanned(
+            list,
+            "`congestion_surcharge` needs `account`, the address of the congestion account",
+        )),
+        (_, None) => Err(syn::Error::new_spanned(
+            list,
+            "`congestion_surcharge` needs `bps_per_level`, e.g. bps_per_level = 500",
+        )),
+    }
+}
+
+/// Parses the `bucket_seed = "..."` and `prices(A, B, ...)` of
+/// `experiment(...)`, both required.
+fn experiment(list: &MetaList) -> syn::Result<(LitStr, Vec<u64>)> {
+    let metas = list.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+
+    let mut bucket_seed = None;
+    let mut prices = None;
+    for meta in &metas {
+        let key = meta.path().get_ident().ok_or_else(|| {
+            syn::Error::new_spanned(meta.path(), "expected `bucket_seed` or `prices`")
+        })?;
+        match (key.to_string().as_str(), meta) {
+            ("bucket_seed", Meta::NameValue(meta)) => {
+                let lit = match &meta.value {
+                    Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+                    value => {
+                        return Err(syn::Error::new_spanned(value, "`bucket_seed` must be a string literal"));
+                    }
+                };
+                let len = lit.value().len();
+                if len == 0 || len > MAX_BUCKET_SEED_LEN {
+                    return Err(syn::Error::new_spanned(
+                        lit,
+                        format!("`bucket_seed` must be 1 to {} bytes long, but is {}", MAX_BUCKET_SEED_LEN, len),
+                    ));
+                }
+                set_once(&mut bucket_seed, lit.clone(), key)?;
+            }
+            ("prices", Meta::List(list)) => {
+                let lits = list.parse_args_with(Punctuated::<LitInt, Token![,]>::parse_terminated)?;
+                let mut values = Vec::new();
+                for lit in &lits {
+                    let value: u64 = lit.base10_parse()?;
+                    if value == 0 {
+                        return Err(syn::Error::new_spanned(lit, "experiment prices must be greater than zero"));
+                    }
+                    values.push(value);
+                }
+                if !(2..=MAX_EXPERIMENT_ARMS).contains(&values.len()) {
+                    return Err(syn::Error::new_spanned(
+                        list,
+                        format!("`prices` must list 2 to {} prices, in lamports", MAX_EXPERIMENT_ARMS),
+                    ));
+                }
+                set_once(&mut prices, values, key)?;
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "expected `bucket_seed = \"...\"` or `prices(...)`",
+                ));
+            }
+        }
+    }
+
+    match (bucket_seed, prices) {
+        (Some(bucket_seed), Some(prices)) => Ok((bucket_seed, prices)),
+        (None, _) => Err(syn::Error::new_spanned(
+            list,
+            "`experiment` needs `bucket_seed`, e.g. bucket_seed = \"exp1\"",
+        )),
+        (_, None) => Err(syn::Error::new_spanned(
+            list,
+            "`experiment` needs `prices`, e.g. prices(1_000_000, 800_000)",
+        )),
+    }
+}
+
+/// Parses the `authorized_by = "..."` of `recipient_from_arg(...)`,
+/// required.
+fn recipient_from_arg(list: &MetaList) -> syn::Result<[u8; 32]> {
+    let metas = list.parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)?;
+
+    let mut authority = None;
+    for meta in &metas {
+        let key = meta
+            .path
+            .get_ident()
+            .ok_or_else(|| syn::Error::new_spanned(&meta.path, "expected `authorized_by`"))?;
+        if key != "authorized_by" {
+            return Err(syn::Error::new_spanned(
+                key,
+                format!("unknown recipient_from_arg argument `{}`; expected `authorized_by`", key),
+            ));
+        }
+        let lit = str_lit(&meta.value, "authorized_by")?;
+        set_once(&mut authority, parse_pubkey(lit, "authorized_by")?, key)?;
+    }
+
+    authority.ok_or_else(|| {
+        syn::Error::new_spanned(
+            list,
+            "`recipient_from_arg` needs `authorized_by`, the key that signs each recipient",
+        )
+    })
+}
+
+/// Parses the `("<recipient>", bps)` pairs of `splits(...)`: 2 to
+/// `MAX_REVENUE_SHARES` distinct recipients whose shares sum to
+/// `TOTAL_SHARE_BPS`.
+fn splits(list: &MetaList) -> syn::Result<Vec<([u8; 32], u16)>> {
+    let entries = list.parse_args_with(Punctuated::<ExprTuple, Token![,]>::parse_terminated)?;
+
+    let mut splits: Vec<([u8; 32], u16)> = Vec::new();
+    for entry in &entries {
+        let (recipient, bps) = match (entry.elems.len(), entry.elems.first(), entry.elems.last()) {
+            (2, Some(recipient), Some(bps)) => (recipient, bps),
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    entry,
+                    "each split is a (\"<recipient>\", bps) pair, e.g. (\"...\", 7000)",
+                ));
+            }
+        };
+        let recipient = parse_pubkey(str_lit(recipient, "splits")?, "splits")?;
+        let lit = int_lit(bps, "splits")?;
+        let bps: u16 = lit.base10_parse()?;
+        if bps == 0 {
+            return Err(syn::Error::new_spanned(lit, "a split's share must be greater than zero basis points"));
+        }
+        if splits.iter().any(|(seen, _)| *seen == recipient) {
+            return Err(syn::Error::new_spanned(entry, "each recipient may appear in `splits` only once"));
+        }
+        splits.push((recipient, bps));
+    }
+
+    if !(2..=MAX_REVENUE_SHARES).contains(&splits.len()) {
+        return Err(syn::Error::new_spanned(
+            list,
+            format!(
+                "`splits` must list 2 to {} recipients; a single recipient is `address`",
+                MAX_REVENUE_SHARES
+            ),
+        ));
+    }
+    let total: u32 = splits.iter().map(|(_, bps)| u32::from(*bps)).sum();
+    if total != TOTAL_SHARE_BPS {
+        return Err(syn::Error::new_spanned(
+            list,
+            format!("the shares in `splits` must sum to {} basis points, but sum to {}", TOTAL_SHARE_BPS, total),
+        ));
+    }
+    Ok(splits)
+}
+
+/// Rejects tags that are empty or longer than `MAX_TAG_LEN` bytes, so
+/// every tag fits in a PDA seed next to a pubkey.
+pub fn check_tag(lit: &LitStr) -> syn::Result<()> {
+    let len = lit.value().len();
+    if len == 0 || len > MAX_TAG_LEN {
+        return Err(syn::Error::new_spanned(
+            lit,
+            format!("`tag` must be 1 to {} bytes long, but \"{}\" is {}", MAX_TAG_LEN, lit.value(), len),
+        ));
+    }
+    Ok(())
+}
+
+/// Parses the earlier tags and the `until = <unix time>` of
+/// `accepts_tags(...)`, both required.
+fn accepts_tags(list: &MetaList) -> syn::Result<AcceptedTags> {
+    let entries = list.parse_args_with(Punctuated::<Expr, Token![,]>::parse_terminated)?;
+
+    let mut tags: Vec<LitStr> = Vec::new();
+    let mut until = None;
+    for entry in &entries {
+        match entry {
+            Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => {
+                check_tag(lit)?;
+                if tags.iter().any(|seen| seen.value() == lit.value()) {
+                    return Err(syn::Error::new_spanned(lit, "each tag may appear in `accepts_tags` only once"));
+                }
+                tags.push(lit.clone());
+            }
+            Expr::Assign(assign) => {
+                let key = match &*assign.left {
+                    Expr::Path(path) if path.path.is_ident("until") => path.path.segments[0].ident.clone(),
+                    left => return Err(syn::Error::new_spanned(left, "expected `until = <unix time>`")),
+                };
+                let lit = int_lit(&assign.right, "until")?;
+                let value: i64 = lit.base10_parse()?;
+                if value <= 0 {
+                    return Err(syn::Error::new_spanned(lit, "`until` must be a unix time after 1970"));
+                }
+                set_once(&mut until, value, &key)?;
+            }
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    entry,
+                    "expected a tag such as \"old_v1\" or `until = <unix time>`",
+                ));
+            }
+        }
+    }
+
+    match (tags.is_empty(), until) {
+        (false, Some(until)) => Ok(AcceptedTags { tags, until }),
+        (true, _) => Err(syn::Error::new_spanned(list, "`accepts_tags` needs at least one tag, e.g. \"old_v1\"")),
+        (_, None) => Err(syn::Error::new_spanned(
+            list,
+            "`accepts_tags` needs `until`, the unix time after which only the pinned tag is accepted",
+        )),
+    }
+}
+
+/// The handler's tag: the pinned one, or else its name, which must then
+/// be a valid tag itself.
+pub fn handler_tag(args: &X402Args, handler: &Ident) -> syn::Result<LitStr> {
+    if let Some(tag) = &args.tag {
+        return Ok(tag.clone());
+    }
+    let name = handler.to_string();
+    if name.len() > MAX_TAG_LEN {
+        return Err(syn::Error::new_spanned(
+            handler,
+            format!(
+                "`{}` is longer than {} bytes and cannot be its own tag; pin one with tag = \"...\"",
+                name, MAX_TAG_LEN
+            ),
+        ));
+    }
+    Ok(LitStr::new(&name, handler.span()))
+}
+
+/// The `x402_runtime::Cluster` variant of the network named `lit`.
+pub fn network_lit(lit: &LitStr) -> syn::Result<Ident> {
+    let name = lit.value();
+    match NETWORKS.iter().find(|(network, _)| *network == name) {
+        Some((_, variant)) => Ok(Ident::new(variant, lit.span())),
+        None => Err(syn::Error::new_spanned(
+            lit,
+            format!(
+                "unknown network \"{}\"; expected one of {}",
+                name,
+                NETWORKS.map(|(network, _)| format!("\"{}\"", network)).join(", ")
+            ),
+        )),
+    }
+}
+
+/// Parses `"last"` or `"any"`.
+fn sysvar_position_lit(value: &Expr) -> syn::Result<SysvarPosition> {
+    let lit = match value {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+        value => return Err(syn::Error::new_spanned(value, "`sysvar_position` must be \"last\" or \"any\"")),
+    };
+    match lit.value().as_str() {
+        "last" => Ok(SysvarPosition::Last),
+        "any" => Ok(SysvarPosition::Any),
+        other => Err(syn::Error::new_spanned(
+            lit,
+            format!("unknown sysvar_position \"{}\"; expected \"last\" or \"any\"", other),
+        )),
+    }
+}
+
+/// Parses `"preceding_instruction"` or `"balance_delta"`.
+pub fn mode_lit(value: &Expr) -> syn::Result<(PaymentMode, &LitStr)> {
+    let lit = match value {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => lit,
+        value => {
+            return Err(syn::Error::new_spanned(
+                value,
+                "`mode` must be \"preceding_instruction\" or \"balance_delta\"",
+            ));
+        }
+    };
+    let mode = match lit.value().as_str() {
+        "preceding_instruction" => PaymentMode::PrecedingInstruction,
+        "balance_delta" => PaymentMode::BalanceDelta,
+        other => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!(
+                    "unknown mode \"{}\"; expected \"preceding_instruction\" or \"balance_delta\"",
+                    other
+                ),
+            ));
+        }
+    };
+    Ok((mode, lit))
+}
+
+fn int_lit<'a>(value: &'a Expr, name: &str) -> syn::Result<&'a LitInt> {
+    match value {
+        Expr::Lit(ExprLit { lit: Lit::Int(lit), .. }) => Ok(lit),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` must be an integer literal", name),
+        )),
+    }
+}
+
+fn bool_lit(value: &Expr, name: &str) -> syn::Result<bool> {
+    match value {
+        Expr::Lit(ExprLit { lit: Lit::Bool(lit), .. }) => Ok(lit.value),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` must be `true` or `false`", name),
+        )),
+    }
+}
+
+pub fn str_lit<'a>(value: &'a Expr, name: &str) -> syn::Result<&'a LitStr> {
+    match value {
+        Expr::Lit(ExprLit { lit: Lit::Str(lit), .. }) => Ok(lit),
+        _ => Err(syn::Error::new_spanned(
+            value,
+            format!("`{}` must be a string literal containing a base58 public key", name),
+        )),
+    }
+}
+
+pub fn parse_pubkey(lit: &LitStr, name: &str) -> syn::Result<[u8; 32]> {
+    match bs58::decode(lit.value()).into_vec() {
+        Ok(bytes) if bytes.len() == 32 => {
+            let mut key = [0u8; 32];
+            key.copy_from_slice(&bytes);
+            Ok(key)
+        }
+        Ok(bytes) => Err(syn::Error::new_spanned(
+            lit,
+            format!(
+                "`{}` must be a 32-byte public key, but \"{}\" decodes to {} bytes",
+                name,
+                lit.value(),
+                bytes.len()
+            ),
+        )),
+        Err(err) => Err(syn::Error::new_spanned(
+            lit,
+            format!("`{}` is not a valid base58 public key: {}", name, err),
+        )),
+    }
+}
+
+/// Records the price from whichever of `price`, `price_sol` or
+/// `price_usdc` was given, allowing only one of them.
+fn set_price(
+    slot: &mut Option<(u64, Unit, Ident)>,
+    value: u64,
+    unit: Unit,
+    lit: &impl ToTokens,
+    key: &Ident,
+) -> syn::Result<()> {
+    if value == 0 {
+        let message = format!("`{}` must be greater than zero; remove #[x402] to make the instruction free", key);
+        return Err(syn::Error::new_spanned(lit, message));
+    }
+    if let Some((_, _, previous)) = slot {
+        let message = if *previous == *key {
+            format!("duplicate x402 argument `{}`", key)
+        } else {
+            format!("`{}` and `{}` both set the price; give only one", previous, key)
+        };
+        return Err(syn::Error::new_spanned(key, message));
+    }
+    *slot = Some((value, unit, key.clone()));
+    Ok(())
+}
+
+pub fn set_once<T>(slot: &mut Option<T>, value: T, key: &Ident) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(syn::Error::new_spanned(
+            key,
+            format!("duplicate x402 argument `{}`", key),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
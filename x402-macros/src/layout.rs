@@ -0,0 +1,122 @@
+//! The `<handler>_x402_layout()` function emitted with each handler.
+//!
+//! It lists what the expanded check reads, in transaction order, as an
+//! `x402_runtime::layout::TxLayout`, so the description cannot drift from
+//! the check: both are generated from the same arguments here.
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Ident, Visibility};
+
+use crate::args::X402Args;
+use crate::modes::Mode;
+use crate::{params_ident, recipient_authority_ident, splits_ident, surcharge_ident, tag_ident};
+
+/// `premium_compute` -> `premium_compute_x402_layout`.
+pub fn layout_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_x402_layout", handler)
+}
+
+/// The layout function of `handler`, whose constants are emitted next to it.
+pub fn layout_fn(args: &X402Args, handler: &Ident, vis: &Visibility) -> TokenStream {
+    let name = layout_ident(handler);
+    let params = params_ident(handler);
+    let mode = Mode::of(args.mode, args.deposit, args.accumulated);
+    let mode_value = mode.tokens();
+
+    let authorization = args.recipient_authority.map(|_| {
+        let authority = recipient_authority_ident(handler);
+        quote! {
+            requirements.push(Requirement::RecipientAuthorization { authority: #authority.authority });
+        }
+    });
+
+    // The body pays under `balance_delta`, the deposit under `deposit` and
+    // earlier transactions under `accumulated`, so only a preceding
+    // transfer is part of the transaction. Revenue legs
+    // are read back to back.
+    let transfers = match &args.splits {
+        _ if mode != Mode::PrecedingInstruction => quote!(),
+        Some(_) => {
+            let splits = splits_ident(handler);
+            quote! {
+                let mins = x402_runtime::revenue_split::required_amounts(params.price, #splits);
+                for (share, min) in #splits.iter().zip(mins) {
+                    requirements.push(Requirement::Transfer {
+                        to: Some(share.recipient),
+                        bps: share.bps,
+                        min,
+                        currency: params.currency,
+                        max_payers: 1,
+                    });
+                }
+                requirements.push(Requirement::IgnorableOk { others: 0 });
+            }
+        }
+        None => {
+            let to = match args.recipient {
+                Some(_) if args.recipient_authority.is_none() => quote!(Some(params.recipient)),
+                _ => quote!(None),
+            };
+            let max_payers = args.split_payers.unwrap_or(1);
+            // Split payers are read back to back, and a strict scan stops at
+            // the first other instruction.
+            let others = if args.split_payers.is_some() || args.strict_scan {
+                0
+            } else {
+                args.max_lookback - 1
+            };
+            quote! {
+                requirements.push(Requirement::Transfer {
+                    to: #to,
+                    bps: 10_000,
+                    min: params.price,
+                    currency: params.currency,
+                    max_payers: #max_payers,
+                });
+                requirements.push(Requirement::IgnorableOk { others: #others });
+            }
+        }
+    };
+
+    let mut accounts = match mode {
+        Mode::PrecedingInstruction => vec![quote!(CallAccount::InstructionsSysvar)],
+        Mode::BalanceDelta => vec![quote!(CallAccount::Recipient)],
+        Mode::Deposit => vec![quote!(CallAccount::Deposit), quote!(CallAccount::Recipient)],
+        Mode::Accumulated => {
+            let tag = tag_ident(handler);
+            vec![quote!(CallAccount::Accumulator { tag: #tag.to_string() })]
+        }
+    };
+    if (args.recipient.is_none() && args.recipient_authority.is_none()) || args.network.is_some() {
+        accounts.push(quote!(CallAccount::Config));
+    }
+    if args.congestion.is_some() {
+        let surcharge = surcharge_ident(handler);
+        accounts.push(quote!(CallAccount::Congestion { address: #surcharge.account }));
+    }
+    if let Some((bucket_seed, _)) = &args.experiment {
+        accounts.push(quote!(CallAccount::Experiment { bucket_seed: #bucket_seed.to_string() }));
+    }
+    if args.reentrancy_guard {
+        accounts.push(quote!(CallAccount::ReentrancyGuard));
+    }
+
+    quote! {
+        /// Transaction the `#[x402]` attribute on the handler of the same
+        /// name accepts, in order.
+        #[allow(dead_code)]
+        #vis fn #name() -> x402_runtime::layout::TxLayout {
+            use x402_runtime::layout::{CallAccount, Requirement, TxLayout};
+
+            #[allow(unused_variables)]
+            let params: x402_runtime::X402Params = #params;
+            #[allow(unused_mut)]
+            let mut requirements = Vec::new();
+            #authorization
+            #transfers
+            requirements.push(Requirement::ThisInstruction { accounts: vec![#(#accounts),*] });
+            TxLayout { mode: #mode_value, requirements }
+        }
+    }
+}
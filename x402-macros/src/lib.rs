@@ -1,141 +1,1231 @@
+mod accounts;
+mod args;
+mod layout;
+mod lint;
+mod modes;
+#[cfg(test)]
+mod reproducible;
+mod table;
+mod tiers;
+
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, ItemFn};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{parse_macro_input, Ident, Item, ItemFn, ItemMod, ItemStruct, ReturnType, Stmt};
+
+use crate::accounts::AccountsArgs;
+use crate::args::{
+    context_ident, handler_tag, has_arg, AcceptedTags, PaymentMode, PriceGrace, SysvarPosition, X402Args, MAX_PRICE_ARG,
+    NONCE_ARG, RECIPIENT_ARG,
+};
+use crate::modes::Mode;
 
+/// Gates an Anchor instruction handler behind a payment, by default a
+/// transfer made by an instruction before it in the same transaction.
+///
+/// ```ignore
+/// #[x402(price = 50_000_000, address = "...", tag = "premium_v1")]
+/// pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> { ... }
+/// ```
+///
+/// The attribute applies to a function, once. Each option is described
+/// where it is implemented, in the module named after it; the options a
+/// mode gives no meaning to are rejected together, in one error.
+///
+/// # Price and recipient
+///
+/// - `price = N` in lamports, or `price_sol = "0.05"`, or
+///   `price_usdc = "1.25"` with `token = "<mint>"`, in the grammar of
+///   `x402_units`, paid by a token transfer into the recipient's
+///   associated token account.
+/// - `facilitator_fee = N`, emitted as `<HANDLER>_X402_FACILITATOR_FEE`
+///   for the facilitator and never charged.
+/// - `address = "<key>"`, or without it the `x402_config` account
+///   (`x402_runtime::config`).
+/// - `recipient_from_arg(authorized_by = "<key>")`
+///   (`x402_runtime::authorization`).
+/// - `splits(("<key>", bps), ...)` (`x402_runtime::revenue_split`).
+/// - `congestion_surcharge(account = "<key>", bps_per_level = N)`, with
+///   `price_grace_bps = N` or `price_grace_window_secs = N`
+///   (`x402_runtime::congestion`).
+/// - `experiment(bucket_seed = "...", prices(A, B, ...))`
+///   (`x402_runtime::experiment`).
+/// - `group = "...", group_cap = N` (`x402_runtime::group`).
+/// - `creates_account_space = N`, `allow_rent_heavy = true`
+///   (`x402_runtime::rent`).
+///
+/// # Where the payment comes from
+///
+/// - A preceding instruction, by default: `max_lookback = N`,
+///   `strict_scan = true` (`x402_runtime::native`), `legacy_matcher = true`
+///   (`x402_runtime::matcher`), `allow_split_payers = true` with
+///   `max_split_payers = N`, `require_fee_payer = true`
+///   (`x402_runtime::verify`), `allow_trampoline = true` and
+///   `allow_cpi = true` (`x402_runtime::trampoline`).
+/// - `mode = "balance_delta"` (`x402_runtime::balance_delta`).
+/// - `deposit = true` (`x402_runtime::deposit`).
+/// - `accumulated = true` (`x402_runtime::accumulator`).
+/// - `strict_accounts = true`, `sysvar_position = "any"`
+///   (`x402_runtime::accounts`).
+///
+/// # What is recorded
+///
+/// `track_stats = true` (`x402_runtime::stats`), `commitment_log = true`
+/// (`x402_runtime::commitment`), `ledger = true` (`x402_runtime::ledger`),
+/// `respect_spending_cap = true` (`x402_runtime::spending_cap`),
+/// `emit_event = false`, `emit_latency = true`, `record_fingerprint = true`
+/// and `require_nonce = true` (`x402_runtime::verify`),
+/// `record_provenance = true` (`x402_runtime::PriceProvenance`),
+/// `reentrancy_guard = true` (`x402_runtime::reentrancy`), and
+/// `tag = "..."` with `accepts_tags("...", until = T)` (`x402_runtime::tag`).
+///
+/// # Bindings and items
+///
+/// The body gets `x402_payment: x402_runtime::VerifiedPayment` (not in
+/// `balance_delta` mode, whose check runs after it) and `x402_sequence:
+/// u64`, plus the bindings of the options above: `x402_contributions`,
+/// `x402_ledger`, `x402_fingerprint`, `x402_provenance`,
+/// `x402_revenue_legs` and `x402_user_accounts`. Next to the handler it
+/// emits `<HANDLER>_X402_PARAMS`, `<HANDLER>_X402_MODE`,
+/// `<HANDLER>_X402_TAG` and `<handler>_x402_layout()`
+/// (`x402_runtime::layout`), and a constant per option that needs one.
+///
+/// The body is also read for a transfer to the recipient, and in
+/// `balance_delta` mode for a write before the payment, each reported as
+/// a deprecation warning at the statement.
 #[proc_macro_attribute]
 pub fn x402(args: TokenStream, input: TokenStream) -> TokenStream {
-    let input_fn = parse_macro_input!(input as ItemFn);
-    let args_str = args.to_string();
+    expand_x402(args.into(), input.into()).into()
+}
+
+/// `#[x402]` on `proc_macro2` tokens, which tests can run outside a
+/// compiler.
+fn expand_x402(args: proc_macro2::TokenStream, input: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+    let args = match syn::parse2::<X402Args>(args) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error(),
+    };
+    let input_fn = match syn::parse2::<Item>(input) {
+        Ok(Item::Fn(input_fn)) => input_fn,
+        Ok(item) => return not_a_function(&item).to_compile_error(),
+        Err(err) => return err.to_compile_error(),
+    };
 
-    let price = extract_price(&args_str).unwrap_or(1_000_000);
-    let _token = extract_token(&args_str)
-        .unwrap_or_else(|| "11111111111111111111111111111111".to_string());
-    let _facilitator_fee = extract_facilitator_fee(&args_str).unwrap_or(0);
-    let recipient_address = extract_recipient(&args_str).unwrap_or_else(|| "11111111111111111111111111111111".to_string());
+    if let Err(err) = reject_reapplication(&input_fn) {
+        return err.to_compile_error();
+    }
+    let ctx = match context_ident(&input_fn.sig) {
+        Ok(ctx) => ctx,
+        Err(err) => return err.to_compile_error(),
+    };
 
+    // Inside `#[x402_program]` the requirements are a row of the program
+    // module's `X402_TABLE`, reached from nested modules through `super`;
+    // elsewhere they are spelled out here.
+    let params_value = match args.table_row {
+        Some(row) => {
+            let up = (0..args.table_depth).map(|_| quote!(super::));
+            quote!(#(#up)* X402_TABLE[#row])
+        }
+        None => table::params_tokens(&args),
+    };
+    let attrs = &input_fn.attrs;
     let vis = &input_fn.vis;
     let sig = &input_fn.sig;
     let body = &input_fn.block;
+    let params = params_ident(&sig.ident);
+    let tag = match handler_tag(&args, &sig.ident) {
+        Ok(tag) => tag,
+        Err(err) => return err.to_compile_error(),
+    };
+    let tag_const = tag_ident(&sig.ident);
+    let aliases_ident = tag_aliases_ident(&sig.ident);
+    let aliases_const = args.accepts_tags.as_ref().map(|AcceptedTags { tags, until }| {
+        quote! {
+            /// Earlier tags the handler of the same name still accepts.
+            #vis const #aliases_ident: x402_runtime::TagAliases = x402_runtime::TagAliases {
+                tags: &[#(#tags),*],
+                until: #until,
+            };
+        }
+    });
+    let mode_const = mode_ident(&sig.ident);
+    let mode = Mode::of(args.mode, args.deposit, args.accumulated);
+    let mode_value = mode.tokens();
 
-    let expanded = quote! {
-        #vis #sig {
-            {
-                const X402_REQUIRED_AMOUNT: u64 = #price;
-                const X402_RECIPIENT: &str = #recipient_address;
+    // Stable proc macros cannot emit warnings, but using a deprecated item
+    // does.
+    let unpinned_tag = args.tag.is_none().then(|| {
+        let note = format!(
+            "`{}` has no pinned x402 tag, so accounts and events keyed by it follow the handler's name; add tag = \"{}\" to keep them across renames",
+            sig.ident, tag.value()
+        );
+        quote_spanned! {sig.ident.span()=>
+            #[deprecated(note = #note)]
+            #[allow(non_upper_case_globals)]
+            const x402_tag_not_pinned: () = ();
+            #[allow(clippy::let_unit_value)]
+            let _ = x402_tag_not_pinned;
+        }
+    });
+
+    // The legacy matcher is gone from a runtime built with
+    // `strict-encodings`, which the reference to it reports as an error.
+    let legacy_matcher = args.legacy_matcher.then(|| {
+        let note = format!(
+            "`{}` uses the deprecated legacy matcher, which reads any instruction of this program as a payment; have clients pay through x402_pay and remove legacy_matcher = true",
+            sig.ident
+        );
+        quote_spanned! {sig.ident.span()=>
+            #[deprecated(note = #note)]
+            #[allow(non_upper_case_globals)]
+            const x402_legacy_matcher: () = ();
+            #[allow(clippy::let_unit_value)]
+            let _ = x402_legacy_matcher;
+            let _ = x402_runtime::matcher::match_payment_legacy;
+        }
+    });
+
+    let body_lints = lint::body_lints(&args, &sig.ident, &ctx, body);
+    let rent_lint = lint::rent_lint(&args, &sig.ident);
+    let network_check = args.network.as_ref().map(|cluster| {
+        quote! {
+            x402_runtime::config::require_cluster(
+                #ctx.accounts.x402_config.as_ref().map(|config| config.cluster),
+                x402_runtime::Cluster::#cluster,
+            )?;
+        }
+    });
+    let rent_const = args.created_space.map(|space| {
+        let rent = rent_ident(&sig.ident);
+        let lamports = lint::rent_exempt_minimum(space);
+        quote! {
+            /// Rent-exempt minimum, in lamports, of the accounts the handler
+            /// of the same name makes its payer fund, under the default rent.
+            #vis const #rent: u64 = #lamports;
+        }
+    });
+    let layout_fn = layout::layout_fn(&args, &sig.ident, vis);
 
-                use anchor_lang::solana_program::sysvar::instructions;
-                use anchor_lang::solana_program::program_error::ProgramError;
+    let (strict_accounts, sysvar_position) = (args.strict_accounts, args.sysvar_position);
+    let accounts = |call: proc_macro2::TokenStream| with_accounts(&ctx, strict_accounts, sysvar_position, call);
 
-                let ix_sysvar = match ctx.remaining_accounts.iter()
-                    .find(|a| a.key() == instructions::ID) {
-                    Some(acc) => acc,
-                    None => return Err(ProgramError::InvalidArgument.into()),
+    let verify = match args.split_payers {
+        _ if args.deposit => quote! {
+            #[allow(unused_variables)]
+            let x402_payment: x402_runtime::VerifiedPayment = x402_runtime::deposit::debit(
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.x402_deposit),
+                &#ctx.accounts.x402_deposit.depositor,
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.x402_recipient),
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.payer),
+                &x402_params,
+            )?;
+        },
+        _ if args.accumulated && args.accepts_tags.is_some() => quote! {
+            let x402_accepted_tags = #aliases_ident.accepted(#tag_const, x402_runtime::now()?);
+            #[allow(unused_variables)]
+            let x402_payment: x402_runtime::VerifiedPayment = x402_runtime::accumulator::debit_accepted(
+                &anchor_lang::Key::key(&#ctx.accounts.x402_accumulator),
+                #ctx.program_id,
+                &x402_accepted_tags,
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.payer),
+                &mut #ctx.accounts.x402_accumulator.balance,
+                &x402_params,
+            )?;
+        },
+        _ if args.accumulated => quote! {
+            #[allow(unused_variables)]
+            let x402_payment: x402_runtime::VerifiedPayment = x402_runtime::accumulator::debit(
+                &anchor_lang::Key::key(&#ctx.accounts.x402_accumulator),
+                #ctx.program_id,
+                #tag_const,
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.payer),
+                &mut #ctx.accounts.x402_accumulator.balance,
+                &x402_params,
+            )?;
+        },
+        _ if args.mode == PaymentMode::BalanceDelta => quote! {
+            #[allow(unused_variables)]
+            let x402_payment: x402_runtime::VerifiedPayment = x402_runtime::balance_delta::verify_delta(
+                &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.x402_recipient),
+                x402_balance_before,
+                &anchor_lang::Key::key(&#ctx.accounts.payer),
+                &x402_params,
+            )?;
+        },
+        _ if args.splits.is_some() => {
+            let splits = splits_ident(&sig.ident);
+            let call = accounts(quote! {
+                x402_runtime::revenue_split::verify_revenue_split(
+                    x402_remaining_accounts,
+                    #ctx.program_id,
+                    &x402_params,
+                    #splits,
+                )
+            });
+            quote! {
+                #[allow(unused_variables)]
+                let (x402_payment, x402_revenue_legs): (
+                    x402_runtime::VerifiedPayment,
+                    Vec<x402_runtime::revenue_split::RevenueLeg>,
+                ) = match #call {
+                    Ok(verified) => verified,
+                    Err(err) => return Err(err),
                 };
+            }
+        }
+        None if args.max_lookback != 1 || args.strict_scan || args.legacy_matcher => {
+            let (max_lookback, strict, legacy_matcher) = (args.max_lookback, args.strict_scan, args.legacy_matcher);
+            let call = accounts(quote! {
+                x402_runtime::verify_payment_with(
+                    x402_remaining_accounts,
+                    #ctx.program_id,
+                    &x402_params,
+                    &x402_runtime::ScanOptions {
+                        max_lookback: #max_lookback,
+                        strict: #strict,
+                        legacy_matcher: #legacy_matcher,
+                    },
+                )
+            });
+            quote! {
+                #[allow(unused_variables)]
+                let x402_payment: x402_runtime::VerifiedPayment = match #call {
+                    Ok(payment) => payment,
+                    Err(err) => return Err(err),
+                };
+            }
+        }
+        None => {
+            let call = accounts(quote! {
+                x402_runtime::verify_payment(x402_remaining_accounts, #ctx.program_id, &x402_params)
+            });
+            quote! {
+                #[allow(unused_variables)]
+                let x402_payment: x402_runtime::VerifiedPayment = match #call {
+                    Ok(payment) => payment,
+                    Err(err) => return Err(err),
+                };
+            }
+        }
+        Some(max_payers) => {
+            let call = accounts(quote! {
+                x402_runtime::verify_split_payment(
+                    x402_remaining_accounts,
+                    #ctx.program_id,
+                    &x402_params,
+                    &anchor_lang::Key::key(&#ctx.accounts.payer),
+                    #max_payers,
+                )
+            });
+            quote! {
+                #[allow(unused_variables)]
+                let (x402_payment, x402_contributions): (
+                    x402_runtime::VerifiedPayment,
+                    Vec<x402_runtime::PaymentContribution>,
+                ) = match #call {
+                    Ok(verified) => verified,
+                    Err(err) => return Err(err),
+                };
+            }
+        }
+    };
+
+    // Run as a cross-program invocation, the handler was paid by its
+    // program's `pay_and_invoke` instead.
+    let verify = if args.allow_trampoline {
+        let handler = sig.ident.to_string();
+        let call = accounts(quote! {
+            x402_runtime::trampoline::verify_trampoline_payment(
+                x402_remaining_accounts,
+                #ctx.program_id,
+                &x402_params,
+                &anchor_lang::Key::key(&#ctx.accounts.payer),
+                #handler,
+            )
+        });
+        quote! {
+            #[allow(unused_variables)]
+            let x402_payment: x402_runtime::VerifiedPayment = if x402_runtime::trampoline::is_cpi() {
+                #call?
+            } else {
+                #verify
+                x402_payment
+            };
+        }
+    } else {
+        verify
+    };
+
+    // Invoked by another program, the scan would read the caller's
+    // transaction-level instruction, whose payment then pays for every call
+    // the caller makes. Checked first, before the price is resolved.
+    let reject_cpi = (mode == Mode::PrecedingInstruction && !args.allow_cpi && !args.allow_trampoline).then(|| {
+            let handler = sig.ident.to_string();
+            quote!(x402_runtime::trampoline::require_top_level_call(#handler)?;)
+        });
+
+    // The cheapest checks, which fail the most calls: the instructions
+    // sysvar is there and something precedes the call. Run before the price
+    // is resolved, so such a call reads no config and hashes nothing. Under
+    // a trampoline the call may be the transaction's first, and a group's
+    // covered call may need no payment at all.
+    let precheck = (mode == Mode::PrecedingInstruction && !args.allow_trampoline && args.group.is_none()).then(|| {
+        let call = accounts(quote!(x402_runtime::precheck_payment(x402_remaining_accounts)));
+        quote!(#call?;)
+    });
+
+    // Set before anything is checked or recorded, so a call reached again
+    // through the body's own invocations fails before counting the payment
+    // twice. Loaded only for the instant it is written: the body may invoke
+    // programs that read it.
+    let (guard_enter, guard_exit) = if args.reentrancy_guard {
+        let handler = sig.ident.to_string();
+        (
+            quote! {
+                let x402_reentrancy_guard = #ctx.accounts.x402_reentrancy_guard.clone();
+                x402_runtime::reentrancy::enter(&mut x402_reentrancy_guard.load_mut()?.entered, #handler)?;
+            },
+            quote! {
+                x402_runtime::reentrancy::exit(&mut x402_reentrancy_guard.load_mut()?.entered);
+            },
+        )
+    } else {
+        (quote!(), quote!())
+    };
 
-                let current_ix_index = match instructions::load_current_index_checked(ix_sysvar) {
-                    Ok(idx) => idx,
-                    Err(_) => return Err(ProgramError::InvalidArgument.into()),
+    // An experiment prices the handler's payer, so the payment must be theirs.
+    let fee_payer_implied = args.experiment.is_some() && mode == Mode::PrecedingInstruction;
+    let fee_payer = (args.require_fee_payer || fee_payer_implied).then(|| {
+        let call = accounts(quote! {
+            x402_runtime::require_fee_payer(
+                x402_remaining_accounts,
+                &x402_payment,
+                &anchor_lang::Key::key(&#ctx.accounts.payer),
+            )
+        });
+        quote!(#call?;)
+    });
+
+    // The budget prices the call at what the transaction still owes, and the
+    // payment found is counted into it. Everything after the check sees the
+    // list price again.
+    let (verify, fee_payer) = match &args.group {
+        Some(_) => {
+            let group = group_ident(&sig.ident);
+            let open = accounts(quote! {
+                #group.open(
+                    &x402_group_budget_key,
+                    #ctx.program_id,
+                    &anchor_lang::Key::key(&#ctx.accounts.payer),
+                    &mut budget.budget,
+                    &x402_params,
+                    x402_remaining_accounts,
+                )
+            });
+            // Through the trampoline, `pay_and_invoke` paid the list price
+            // and the call is not counted.
+            let budget = if args.allow_trampoline {
+                quote!(#ctx.accounts.x402_group_budget.as_mut().filter(|_| !x402_runtime::trampoline::is_cpi()))
+            } else {
+                quote!(#ctx.accounts.x402_group_budget.as_mut())
+            };
+            let grouped = quote! {
+                let x402_list_params: x402_runtime::X402Params = x402_params;
+                let x402_params: x402_runtime::X402Params = match #budget {
+                    Some(budget) => {
+                        let x402_group_budget_key = anchor_lang::Key::key(&*budget);
+                        let budget = &mut **budget;
+                        #open?
+                    }
+                    None => x402_params,
+                };
+                #[allow(unused_variables)]
+                let x402_payment: x402_runtime::VerifiedPayment = if x402_params.price == 0 {
+                    x402_runtime::group::covered(&x402_params, &anchor_lang::Key::key(&#ctx.accounts.payer))
+                } else {
+                    #verify
+                    #fee_payer
+                    x402_payment
                 };
+                if let Some(budget) = #budget {
+                    #group.settle(&mut budget.budget, &x402_payment)?;
+                }
+                #[allow(unused_variables)]
+                let x402_params: x402_runtime::X402Params = x402_list_params;
+            };
+            (grouped, None)
+        }
+        None => (verify, fee_payer),
+    };
 
-                if current_ix_index == 0 {
-                    return Err(ProgramError::InvalidArgument.into());
+    let spending_cap = args.respect_spending_cap.then(|| {
+        quote! {
+            if let Some(cap) = #ctx.accounts.spending_cap.as_mut() {
+                let cap = &mut **cap;
+                x402_runtime::spending_cap::charge(
+                    &cap.payer,
+                    cap.cap,
+                    cap.period_secs,
+                    &mut cap.period_start,
+                    &mut cap.spent,
+                    &x402_payment,
+                )?;
+            }
+        }
+    });
+
+    let ledger = args.ledger.then(|| {
+        quote! {
+            #[allow(unused_variables)]
+            let x402_ledger: Option<x402_runtime::ledger::LedgerSnapshot> = match #ctx.accounts.x402_ledger.as_mut() {
+                Some(ledger) => {
+                    let ledger = &mut **ledger;
+                    Some(x402_runtime::ledger::record(
+                        &ledger.payer,
+                        &mut ledger.total_payments,
+                        &mut ledger.total_amount,
+                        &mut ledger.last_payment,
+                        &x402_payment,
+                    )?)
                 }
+                None => None,
+            };
+        }
+    });
+
+    let sequence = if args.track_stats {
+        let commitment = args.commitment_log.then(|| {
+            quote! {
+                x402_runtime::commitment::record(&mut stats.commitment, &x402_payment, sequence, #tag)?;
+            }
+        });
+        quote! {
+            #[allow(unused_variables)]
+            let x402_sequence: u64 = {
+                use x402_runtime::stats::StatsAccount as _;
+                let mut stats = #ctx.accounts.x402_stats.stats_mut()?;
+                let stats = &mut *stats;
+                let sequence = x402_runtime::stats::record(
+                    &mut stats.sequence,
+                    &mut stats.total_payments,
+                    &mut stats.total_amount,
+                    &x402_payment,
+                )?;
+                #commitment
+                sequence
+            };
+        }
+    } else {
+        quote! {
+            #[allow(unused_variables)]
+            let x402_sequence: u64 = 0;
+        }
+    };
+
+    let emit = match (args.emit_event, args.split_payers.is_some()) {
+        (false, _) => quote!(),
+        (true, _) if args.splits.is_some() => quote! {
+            x402_runtime::revenue_split::emit_revenue_split_verified(
+                &x402_payment,
+                &x402_revenue_legs,
+                &x402_params,
+                x402_sequence,
+            );
+        },
+        (true, false) => {
+            let experiment_arm = match args.experiment {
+                Some(_) => quote!(Some(x402_experiment_arm)),
+                None => quote!(None),
+            };
+            let nonce = if args.require_nonce {
+                let nonce = format_ident!("{}", NONCE_ARG);
+                quote!(Some(#nonce))
+            } else {
+                quote!(None)
+            };
+            quote! {
+                x402_runtime::emit_payment_verified(&x402_payment, &x402_params, x402_sequence, #experiment_arm, #nonce);
+            }
+        }
+        (true, true) => quote! {
+            x402_runtime::emit_split_payment_verified(
+                &x402_payment,
+                &x402_contributions,
+                &x402_params,
+                x402_sequence,
+            );
+        },
+    };
 
-                let previous_ix = match instructions::load_instruction_at_checked(
-                    (current_ix_index as usize) - 1,
-                    ix_sysvar
-                ) {
-                    Ok(ix) => ix,
-                    Err(_) => return Err(ProgramError::InvalidArgument.into()),
+    let latency = args.emit_latency.then(|| {
+        quote! {
+            x402_runtime::emit_payment_latency(&x402_payment.payer, x402_sequence, None)?;
+        }
+    });
+
+    let facilitator_fee_const = args.facilitator_fee.map(|fee| {
+        let facilitator_fee = facilitator_fee_ident(&sig.ident);
+        quote! {
+            /// Facilitator fee declared by the `#[x402]` attribute on the
+            /// handler of the same name, for the facilitator settling its
+            /// payments. The handler does not charge it.
+            #vis const #facilitator_fee: u8 = #fee;
+        }
+    });
+    let surcharge_const = args.congestion.map(|(account, bps_per_level)| {
+        let surcharge = surcharge_ident(&sig.ident);
+        let account = &account[..];
+        quote! {
+            /// Congestion surcharge applied by the `#[x402]` attribute on the
+            /// handler of the same name.
+            #vis const #surcharge: x402_runtime::congestion::CongestionSurcharge =
+                x402_runtime::congestion::CongestionSurcharge {
+                    account: anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#account),*]),
+                    bps_per_level: #bps_per_level,
                 };
+        }
+    });
+    if args.congestion.is_some() && !has_arg(sig, MAX_PRICE_ARG) {
+        return syn::Error::new_spanned(
+            &sig.ident,
+            format!(
+                "`{}` has a congestion surcharge, so it takes a `{}: u64` argument bounding the price; #[x402_program] adds it, or declare it",
+                sig.ident, MAX_PRICE_ARG
+            ),
+        )
+        .to_compile_error();
+    }
+    if args.recipient_authority.is_some() && !has_arg(sig, RECIPIENT_ARG) {
+        return syn::Error::new_spanned(
+            &sig.ident,
+            format!(
+                "`{}` sets recipient_from_arg, so it takes a `{}: Pubkey` argument; #[x402_program] adds it, or declare it",
+                sig.ident, RECIPIENT_ARG
+            ),
+        )
+        .to_compile_error();
+    }
+    if args.require_nonce && !has_arg(sig, NONCE_ARG) {
+        return syn::Error::new_spanned(
+            &sig.ident,
+            format!(
+                "`{}` sets require_nonce, so it takes a `{}: [u8; 32]` argument; #[x402_program] adds it, or declare it",
+                sig.ident, NONCE_ARG
+            ),
+        )
+        .to_compile_error();
+    }
+    let surcharge = args.congestion.map(|_| {
+        let surcharge = surcharge_ident(&sig.ident);
+        let max_price = format_ident!("{}", MAX_PRICE_ARG);
+        let apply = match args.price_grace {
+            Some(PriceGrace::WindowSecs(window_secs)) => quote! {
+                #surcharge.apply_with_window(
+                    &x402_params,
+                    &anchor_lang::Key::key(&#ctx.accounts.x402_congestion),
+                    #ctx.accounts.x402_congestion.level,
+                    x402_runtime::congestion::LevelChange {
+                        previous_level: #ctx.accounts.x402_congestion.previous_level,
+                        updated_at: #ctx.accounts.x402_congestion.updated_at,
+                    },
+                    #window_secs,
+                )?
+            },
+            _ => quote! {
+                #surcharge.apply_from(
+                    &x402_params,
+                    &anchor_lang::Key::key(&#ctx.accounts.x402_congestion),
+                    #ctx.accounts.x402_congestion.level,
+                )?
+            },
+        };
+        let grace = match args.price_grace {
+            Some(PriceGrace::Bps(grace_bps)) => quote! {
+                let x402_params = x402_runtime::congestion::apply_grace(&x402_params, #grace_bps);
+            },
+            _ => quote! {},
+        };
+        // Recorded before any grace lowers the price it describes.
+        let provenance = args.record_provenance.then(|| {
+            let previous_level = match args.price_grace {
+                Some(PriceGrace::WindowSecs(_)) => quote!(#ctx.accounts.x402_congestion.previous_level),
+                _ => quote!(#ctx.accounts.x402_congestion.level),
+            };
+            quote! {
+                #[allow(unused_variables)]
+                let x402_provenance: x402_runtime::PriceProvenance = #surcharge.provenance(
+                    &#params,
+                    &x402_params,
+                    &anchor_lang::Key::key(&#ctx.accounts.x402_congestion),
+                    #ctx.accounts.x402_congestion.level,
+                    x402_runtime::congestion::LevelChange {
+                        previous_level: #previous_level,
+                        updated_at: #ctx.accounts.x402_congestion.updated_at,
+                    },
+                );
+            }
+        });
+        quote! {
+            let x402_params: x402_runtime::X402Params = #apply;
+            #provenance
+            #grace
+            x402_runtime::congestion::require_within_maximum(&x402_params, #max_price)?;
+        }
+    });
 
-                let payment_amount = if previous_ix.data.len() >= 16 {
-                    let mut amount_bytes = [0u8; 8];
-                    amount_bytes.copy_from_slice(&previous_ix.data[8..16]);
-                    u64::from_le_bytes(amount_bytes)
-                } else if previous_ix.data.len() == 8 {
-                    let mut amount_bytes = [0u8; 8];
-                    amount_bytes.copy_from_slice(&previous_ix.data[0..8]);
-                    u64::from_le_bytes(amount_bytes)
-                } else {
-                    return Err(ProgramError::InvalidArgument.into());
+    let experiment_const = args.experiment.as_ref().map(|(bucket_seed, prices)| {
+        let experiment = experiment_ident(&sig.ident);
+        quote! {
+            /// Price experiment run by the `#[x402]` attribute on the
+            /// handler of the same name.
+            #vis const #experiment: x402_runtime::experiment::Experiment = x402_runtime::experiment::Experiment {
+                bucket_seed: #bucket_seed,
+                prices: &[#(#prices),*],
+            };
+        }
+    });
+    let splits_const = args.splits.as_ref().map(|splits| {
+        let splits_name = splits_ident(&sig.ident);
+        let shares = splits.iter().map(|(recipient, bps)| {
+            let recipient = &recipient[..];
+            quote! {
+                x402_runtime::revenue_split::RevenueShare {
+                    recipient: anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#recipient),*]),
+                    bps: #bps,
+                }
+            }
+        });
+        quote! {
+            /// Revenue split enforced by the `#[x402]` attribute on the
+            /// handler of the same name.
+            #vis const #splits_name: &[x402_runtime::revenue_split::RevenueShare] = &[#(#shares),*];
+        }
+    });
+    let recipient_authority_const = args.recipient_authority.map(|authority| {
+        let recipient_authority = recipient_authority_ident(&sig.ident);
+        let authority = &authority[..];
+        quote! {
+            /// Key authorizing the recipients of the handler of the same
+            /// name, checked by its `#[x402]` attribute.
+            #vis const #recipient_authority: x402_runtime::authorization::RecipientAuthority =
+                x402_runtime::authorization::RecipientAuthority {
+                    authority: anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#authority),*]),
                 };
+        }
+    });
+    let group_const = args.group.as_ref().map(|(name, cap)| {
+        let group = group_ident(&sig.ident);
+        quote! {
+            /// Instruction group charged by the `#[x402]` attribute on the
+            /// handler of the same name.
+            #vis const #group: x402_runtime::group::Group = x402_runtime::group::Group {
+                name: #name,
+                cap: #cap,
+            };
+        }
+    });
+    let experiment = args.experiment.as_ref().map(|_| {
+        let experiment = experiment_ident(&sig.ident);
+        quote! {
+            #[allow(unused_variables)]
+            let (x402_params, x402_experiment_arm): (x402_runtime::X402Params, u8) = #experiment.apply_from(
+                &x402_params,
+                &anchor_lang::Key::key(&#ctx.accounts.x402_experiment),
+                #ctx.program_id,
+                &anchor_lang::Key::key(&#ctx.accounts.payer),
+                x402_runtime::experiment::ExperimentState {
+                    ended: #ctx.accounts.x402_experiment.ended,
+                    winner: #ctx.accounts.x402_experiment.winner,
+                },
+            )?;
+        }
+    });
 
-                if payment_amount < X402_REQUIRED_AMOUNT {
-                    return Err(ProgramError::InsufficientFunds.into());
+    // A surcharged price's provenance is bound with the surcharge.
+    let provenance = (args.record_provenance && args.congestion.is_none()).then(|| {
+        let provenance = match args.experiment {
+            Some(_) => quote! {
+                x402_runtime::PriceProvenance::Experiment {
+                    account: anchor_lang::Key::key(&#ctx.accounts.x402_experiment),
+                    arm: x402_experiment_arm,
                 }
+            },
+            None => quote!(x402_runtime::PriceProvenance::Static),
+        };
+        quote! {
+            #[allow(unused_variables)]
+            let x402_provenance: x402_runtime::PriceProvenance = #provenance;
+        }
+    });
 
-                let payment_recipient = previous_ix.accounts.get(1)
-                    .map(|acc| acc.pubkey.to_string());
+    let fingerprint = args.record_fingerprint.then(|| {
+        let call = accounts(quote! {
+            x402_runtime::request_fingerprint(x402_remaining_accounts, &x402_payment.payer, x402_params.price)
+        });
+        quote! {
+            #[allow(unused_variables)]
+            let x402_fingerprint: [u8; 32] = #call?;
+            x402_runtime::emit_request_fingerprint(&x402_payment.payer, x402_params.price, x402_fingerprint);
+        }
+    });
 
-                if payment_recipient.as_ref() != Some(&X402_RECIPIENT.to_string()) {
-                    return Err(ProgramError::InvalidArgument.into());
-                }
+    // Without an explicit address, the recipient comes from the program's
+    // config account at run time, or from the handler's argument once
+    // authorized.
+    let resolve_params = if args.recipient_authority.is_some() {
+        let recipient_authority = recipient_authority_ident(&sig.ident);
+        let recipient = format_ident!("{}", RECIPIENT_ARG);
+        let call = accounts(quote! {
+            #recipient_authority.verify(x402_remaining_accounts, &#params, &#recipient)
+        });
+        quote! {
+            let x402_params: x402_runtime::X402Params = #call?;
+        }
+    } else if args.recipient.is_some() {
+        quote! {
+            let x402_params: x402_runtime::X402Params = #params;
+        }
+    } else {
+        quote! {
+            let x402_params = x402_runtime::X402Params {
+                recipient: x402_runtime::config::configured_recipient(
+                    #ctx.accounts.x402_config.as_ref().map(|config| config.recipient),
+                )?,
+                ..#params
+            };
+        }
+    };
+
+    // Checks the mode against an `#[x402_accounts]` declaration, if the
+    // struct has one, and takes the instructions sysvar from it.
+    let mode_marker = match args.mode {
+        PaymentMode::PrecedingInstruction => quote!(x402_runtime::accounts::PrecedingInstruction),
+        PaymentMode::BalanceDelta => quote!(x402_runtime::accounts::BalanceDelta),
+    };
+    let reads_transaction = !matches!(mode, Mode::Deposit | Mode::Accumulated);
+    // The remaining accounts the check leaves to the body: all of them,
+    // unless the sysvar is read from the last one.
+    let user_accounts = if mode == Mode::PrecedingInstruction && args.sysvar_position == SysvarPosition::Last {
+        quote! {
+            #[allow(unused_variables)]
+            let x402_user_accounts = match &x402_declared_sysvar {
+                Some(_) => #ctx.remaining_accounts,
+                None => x402_runtime::accounts::split_sysvar(#ctx.remaining_accounts).0,
+            };
+        }
+    } else {
+        quote! {
+            #[allow(unused_variables)]
+            let x402_user_accounts = #ctx.remaining_accounts;
+        }
+    };
+    let remaining_accounts = if !reads_transaction && args.strict_accounts {
+        // Nothing is read from the transaction, so there is no sysvar to
+        // take from a declared struct.
+        quote! {
+            x402_runtime::accounts::forbid_remaining_accounts(#ctx.remaining_accounts)?;
+            #user_accounts
+        }
+    } else if !reads_transaction {
+        user_accounts
+    } else if args.strict_accounts {
+        quote! {
+            x402_runtime::accounts::forbid_remaining_accounts(#ctx.remaining_accounts)?;
+            let x402_declared_sysvar = x402_runtime::accounts::declared_sysvar::<#mode_marker, _>(&*#ctx.accounts);
+            #[allow(unused_variables)]
+            let x402_remaining_accounts: &[anchor_lang::solana_program::account_info::AccountInfo] = match &x402_declared_sysvar {
+                Some(sysvar) => ::core::slice::from_ref(sysvar),
+                None => &[],
+            };
+            #user_accounts
+        }
+    } else {
+        quote! {
+            let x402_declared_sysvar = {
+                #[allow(unused_imports)]
+                use x402_runtime::accounts::{DeclaredMode as _, UndeclaredMode as _};
+                (&x402_runtime::accounts::ModeProbe::of(&*#ctx.accounts)).require_mode::<#mode_marker>(&*#ctx.accounts)
+            };
+            #user_accounts
+        }
+    };
+
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) => Some(ty),
+        ReturnType::Default => None,
+    };
+    let handler = match args.mode {
+        PaymentMode::PrecedingInstruction if args.reentrancy_guard => {
+            let Some(output) = output else {
+                return syn::Error::new_spanned(&sig.ident, "`reentrancy_guard` needs the handler to return a `Result`")
+                    .to_compile_error();
+            };
+            quote! {
+                #reject_cpi
+                #guard_enter
+                #remaining_accounts
+                #precheck
+                #resolve_params
+                #surcharge
+                #experiment
+                #provenance
+                #verify
+                #fee_payer
+
+                #spending_cap
+                #ledger
+                #sequence
+                #emit
+                #latency
+                #fingerprint
+
+                // The body runs in a closure so its early returns come back
+                // here to clear the guard.
+                #[allow(clippy::redundant_closure_call)]
+                let x402_output: #output = (|| -> #output #body)();
+                #guard_exit
+                x402_output
             }
+        }
+        PaymentMode::PrecedingInstruction => quote! {
+            #reject_cpi
+            #remaining_accounts
+            #precheck
+            #resolve_params
+            #surcharge
+            #experiment
+            #provenance
+            #verify
+            #fee_payer
+
+            #spending_cap
+            #ledger
+            #sequence
+            #emit
+            #latency
+            #fingerprint
 
             #body
+        },
+        PaymentMode::BalanceDelta => {
+            let Some(output) = output else {
+                return syn::Error::new_spanned(
+                    &sig.ident,
+                    "mode = \"balance_delta\" needs the handler to return a `Result`",
+                )
+                .to_compile_error();
+            };
+            quote! {
+                #guard_enter
+                #remaining_accounts
+                #resolve_params
+                #surcharge
+                #experiment
+                #provenance
+                let x402_balance_before: u64 = x402_runtime::balance_delta::snapshot(
+                    &anchor_lang::ToAccountInfo::to_account_info(&#ctx.accounts.x402_recipient),
+                    &x402_params,
+                )?;
+
+                // The body runs in a closure so its early returns come back
+                // here for the balance check instead of leaving the handler.
+                #[allow(clippy::redundant_closure_call)]
+                let x402_output: #output = (|| -> #output #body)();
+                let x402_output = x402_output?;
+
+                #verify
+                #fee_payer
+
+                #spending_cap
+                #ledger
+                #sequence
+                #emit
+                #latency
+                #guard_exit
+
+                Ok(x402_output)
+            }
+        }
+    };
+
+    let expanded = quote! {
+        /// Payment requirements enforced by the `#[x402]` attribute on the
+        /// handler of the same name.
+        #vis const #params: x402_runtime::X402Params = #params_value;
+        /// Instruction tag of the handler of the same name.
+        #vis const #tag_const: &str = #tag;
+        #aliases_const
+        /// How the handler of the same name is paid.
+        #vis const #mode_const: x402_runtime::Mode = #mode_value;
+        #rent_const
+        #facilitator_fee_const
+        #surcharge_const
+        #experiment_const
+        #splits_const
+        #recipient_authority_const
+        #group_const
+        #layout_fn
+
+        #(#attrs)*
+        #vis #sig {
+            // Lets a second `#[x402]` on this handler see the first.
+            #[allow(dead_code)]
+            const X402_APPLIED: () = ();
+            // Fails to compile against an `x402-runtime` without the
+            // verification this expansion relies on.
+            const _: () = x402_runtime::compat::UPDATE_X402_RUNTIME_TO_0_1_OR_LATER;
+            #unpinned_tag
+            #legacy_matcher
+            #body_lints
+            #rent_lint
+
+            #network_check
+            #handler
         }
     };
 
-    TokenStream::from(expanded)
-}
-
-fn extract_price(args: &str) -> Option<u64> {
-    let price_str = args
-        .split("price")
-        .nth(1)?
-        .split('=')
-        .nth(1)?
-        .trim()
-        .split(',')
-        .next()?
-        .trim();
-
-    price_str.parse().ok()
-}
-
-fn extract_token(args: &str) -> Option<String> {
-    let token_str = args
-        .split("token")
-        .nth(1)?
-        .split('=')
-        .nth(1)?
-        .trim()
-        .split(',')
-        .next()?
-        .trim()
-        .trim_matches('"')
-        .to_string();
-
-    Some(token_str)
-}
-
-fn extract_facilitator_fee(args: &str) -> Option<u8> {
-    let fee_str = args
-        .split("facilitator_fee")
-        .nth(1)?
-        .split('=')
-        .nth(1)?
-        .trim()
-        .split(',')
-        .next()?
-        .trim();
-
-    fee_str.parse().ok()
-}
-
-fn extract_recipient(args: &str) -> Option<String> {
-    let recipient_str = args
-        .split("recipient")
-        .nth(1)?
-        .split('=')
-        .nth(1)?
-        .trim()
-        .split(',')
-        .next()?
-        .trim()
-        .trim_matches('"')
-        .to_string();
-
-    Some(recipient_str)
+    expanded
+}
+
+/// `call`, an expression reading `x402_remaining_accounts`, run against
+/// the instructions sysvar declared in the accounts struct, if any, and
+/// the remaining accounts otherwise: the last one only, unless
+/// `sysvar_position = "any"`. Under an elided `Context<...>` the two
+/// have unrelated lifetimes and cannot share one binding, so without
+/// `strict_accounts` (where only the declared sysvar is read) the call is
+/// expanded once per source.
+fn with_accounts(
+    ctx: &Ident,
+    strict_accounts: bool,
+    sysvar_position: SysvarPosition,
+    call: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    if strict_accounts {
+        return call;
+    }
+    let remaining_accounts = match sysvar_position {
+        SysvarPosition::Last => quote!(x402_runtime::accounts::split_sysvar(#ctx.remaining_accounts).1),
+        SysvarPosition::Any => quote!(#ctx.remaining_accounts),
+    };
+    quote! {
+        match &x402_declared_sysvar {
+            Some(x402_sysvar) => {
+                let x402_remaining_accounts = ::core::slice::from_ref(x402_sysvar);
+                #call
+            }
+            None => {
+                let x402_remaining_accounts = #remaining_accounts;
+                #call
+            }
+        }
+    }
+}
+
+/// Rejects a second `#[x402]` on one handler, which would nest two checks
+/// of the same payment. A second attribute below this one is still in
+/// `attrs`; one above it sees the `X402_APPLIED` marker this expansion
+/// leaves in the body.
+fn reject_reapplication(input_fn: &ItemFn) -> syn::Result<()> {
+    let name = &input_fn.sig.ident;
+    let message = format!("x402 applied more than once to `{}`", name);
+
+    if let Some(attr) = input_fn
+        .attrs
+        .iter()
+        .find(|attr| attr.path().segments.last().is_some_and(|seg| seg.ident == "x402"))
+    {
+        return Err(syn::Error::new_spanned(attr, message));
+    }
+    let marked = input_fn.block.stmts.iter().any(|stmt| match stmt {
+        Stmt::Item(Item::Const(item)) => item.ident == "X402_APPLIED",
+        _ => false,
+    });
+    if marked {
+        return Err(syn::Error::new_spanned(name, message));
+    }
+    Ok(())
+}
+
+fn not_a_function(item: &Item) -> syn::Error {
+    let (kind, name): (&str, &dyn ToTokens) = match item {
+        Item::Struct(item) => ("a struct", &item.ident),
+        Item::Enum(item) => ("an enum", &item.ident),
+        Item::Union(item) => ("a union", &item.ident),
+        Item::Mod(item) => ("a module", &item.ident),
+        Item::Const(item) => ("a const", &item.ident),
+        Item::Static(item) => ("a static", &item.ident),
+        Item::Trait(item) => ("a trait", &item.ident),
+        Item::Type(item) => ("a type alias", &item.ident),
+        Item::Impl(item) => ("an impl block", &item.self_ty),
+        item => ("this item", item),
+    };
+    syn::Error::new_spanned(
+        name,
+        format!(
+            "#[x402] can only be applied to a function (an Anchor instruction handler), not {}",
+            kind
+        ),
+    )
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_PARAMS`.
+fn params_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_PARAMS", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_TAG`.
+fn tag_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_TAG", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_TAG_ALIASES`.
+fn tag_aliases_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_TAG_ALIASES", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_MODE`.
+fn mode_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_MODE", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_CREATED_RENT`.
+fn rent_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_CREATED_RENT", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_FACILITATOR_FEE`.
+fn facilitator_fee_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_FACILITATOR_FEE", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_SURCHARGE`.
+fn surcharge_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_SURCHARGE", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_EXPERIMENT`.
+fn experiment_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_EXPERIMENT", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_SPLITS`.
+fn splits_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_SPLITS", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_RECIPIENT_AUTHORITY`.
+fn recipient_authority_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_RECIPIENT_AUTHORITY", handler.to_string().to_uppercase())
+}
+
+/// `premium_compute` -> `PREMIUM_COMPUTE_X402_GROUP`.
+fn group_ident(handler: &Ident) -> Ident {
+    format_ident!("{}_X402_GROUP", handler.to_string().to_uppercase())
+}
+
+/// Injects the accounts a payment mode needs into an Anchor accounts
+/// struct, so Anchor validates them and they appear in the IDL. It must be
+/// placed above `#[derive(Accounts)]`:
+///
+/// - `mode = "preceding_instruction"` (the default) adds
+///   `x402_instructions`, the instructions sysvar checked by address, which
+///   `#[x402]` then reads instead of searching the remaining accounts.
+/// - `mode = "balance_delta"` adds the writable `x402_recipient`, with an
+///   address constraint when `address = "..."` is given.
+///
+/// The struct implements `x402_runtime::accounts::X402Accounts`, and an
+/// `#[x402]` handler using it with a different mode does not compile:
+///
+/// ```compile_fail
+/// use anchor_lang::prelude::*;
+/// use x402_macros::{x402, x402_accounts};
+///
+/// declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+///
+/// #[x402_accounts(mode = "balance_delta")]
+/// #[derive(Accounts)]
+/// pub struct Compute<'info> {
+///     pub payer: Signer<'info>,
+/// }
+///
+/// #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+/// pub fn compute(ctx: Context<Compute>) -> Result<()> {
+///     Ok(())
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn x402_accounts(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as AccountsArgs);
+    let item = parse_macro_input!(input as ItemStruct);
+
+    match accounts::expand_struct(args, item) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Module-level companion to `#[program]` that expands `#[x402_tiers]`
+/// handlers. It must be placed above `#[program]` so the generated
+/// per-tier handlers exist before Anchor builds its dispatcher:
+///
+/// ```ignore
+/// #[x402_program]
+/// #[program]
+/// pub mod my_program {
+///     #[x402_tiers(address = "...", basic = 1_000_000, pro = 5_000_000)]
+///     pub fn compute(ctx: Context<Compute>) -> Result<()> {
+///         msg!("tier {}", tier);
+///         Ok(())
+///     }
+/// }
+/// ```
+///
+/// generates `compute_basic` and `compute_pro`, each gated by `#[x402]` at
+/// its tier's price, with `tier: &str` bound to the tier name in the body.
+///
+/// It also collects the requirements of every `#[x402]` handler in the
+/// module, tiers included, into one `pub const X402_TABLE:
+/// [x402_runtime::X402Params; N]` in declaration order. Each handler's
+/// `<HANDLER>_X402_PARAMS` is then just a reference to its row, so the
+/// table is the single place to audit prices.
+///
+/// Handlers in inline modules nested in the program module are expanded
+/// and tabled as well, depth first; their constants are emitted next to
+/// them and reach the table through `super::`. Handlers in modules from
+/// other files are out of the attribute's sight and keep their
+/// requirements inline.
+///
+/// Handlers whose price is dynamic, those with `congestion_surcharge`, get
+/// a trailing `max_acceptable_price: u64` argument here, before Anchor
+/// reads their signatures for the IDL, unless they already declare one.
+///
+/// `#[x402_program(network = "mainnet-beta")]` builds the program for one
+/// cluster (`"mainnet-beta"`, `"devnet"`, `"testnet"` or `"localnet"`).
+/// A program cannot read the genesis hash, so every handler in the
+/// attribute's sight instead checks the cluster its optional `x402_config`
+/// account records, a `cluster: x402_runtime::Cluster` field written at
+/// initialization, before anything else: another cluster fails with
+/// `WrongCluster`, and a missing config with `ConfigNotInitialized`.
+#[proc_macro_attribute]
+pub fn x402_program(args: TokenStream, input: TokenStream) -> TokenStream {
+    let args = parse_macro_input!(args as tiers::ProgramArgs);
+    let module = parse_macro_input!(input as ItemMod);
+
+    match tiers::expand_module(args, module) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+/// Marker expanded by `#[x402_program]`; on its own it is an error.
+#[proc_macro_attribute]
+pub fn x402_tiers(_args: TokenStream, input: TokenStream) -> TokenStream {
+    let input_fn = parse_macro_input!(input as ItemFn);
+
+    syn::Error::new_spanned(
+        &input_fn.sig.ident,
+        "#[x402_tiers] is only expanded inside a module annotated with #[x402_program] placed above #[program]",
+    )
+    .to_compile_error()
+    .into()
 }
@@ -0,0 +1,223 @@
+//! Best-effort checks of a priced handler's body, reported as warnings.
+//!
+//! They read the body's syntax only, so they miss what it hides: writes
+//! and transfers inside called functions or macro invocations, and
+//! accounts reached through another binding (`let accounts = &mut
+//! ctx.accounts;`). What they find is worth a look rather than a proof of
+//! a bug, so they warn instead of failing the build.
+//!
+//! The price is also compared to the rent of the accounts the call makes
+//! the payer fund, when `creates_account_space` says how many bytes they
+//! take; the accounts struct spells their space as expressions the macro
+//! cannot evaluate, so it is not read.
+
+use proc_macro2::{TokenStream, TokenTree};
+use quote::{quote_spanned, ToTokens};
+use syn::spanned::Spanned;
+use syn::visit::{self, Visit};
+use syn::{BinOp, Block, Expr, ExprCall, Ident, Item, Member, Stmt};
+
+use crate::args::{PaymentMode, X402Args};
+
+/// Warnings for `body`, the body of handler `name` whose context is `ctx`.
+pub fn body_lints(args: &X402Args, name: &Ident, ctx: &Ident, body: &Block) -> TokenStream {
+    let mut lints = TokenStream::new();
+    match args.mode {
+        PaymentMode::BalanceDelta => {
+            if let Some(stmt) = write_before_payment(ctx, body) {
+                let note = format!(
+                    "`{}` writes to `{}.accounts` before it pays; with mode = \"balance_delta\" the payment is only checked once the body returns, so pay first",
+                    name, ctx
+                );
+                lints.extend(warning(&note, "x402_write_before_payment", stmt.span()));
+            }
+        }
+        PaymentMode::PrecedingInstruction => {
+            if let Some(call) = transfer_to_recipient(body) {
+                let paid_by = if args.deposit {
+                    "debited from the payer's deposit"
+                } else if args.accumulated {
+                    "debited from the payments the payer accumulated"
+                } else {
+                    "an earlier instruction of the transaction"
+                };
+                let note = format!(
+                    "`{}` transfers to the recipient itself, but its payment is {}; a body that makes the payment needs mode = \"balance_delta\"",
+                    name, paid_by
+                );
+                lints.extend(warning(&note, "x402_transfer_to_recipient", call.span()));
+            }
+        }
+    }
+    lints
+}
+
+/// Bytes of account metadata rent is charged for on top of the data,
+/// `solana_program::rent::ACCOUNT_STORAGE_OVERHEAD`.
+const ACCOUNT_STORAGE_OVERHEAD: u64 = 128;
+/// Lamports per byte of a rent-exempt account under the default rent:
+/// `DEFAULT_LAMPORTS_PER_BYTE_YEAR` over `DEFAULT_EXEMPTION_THRESHOLD` years.
+const RENT_EXEMPT_LAMPORTS_PER_BYTE: u64 = 3_480 * 2;
+/// Rent above this share of the price, in percent, is warned about.
+const RENT_HEAVY_PERCENT: u64 = 50;
+
+/// The rent-exempt minimum of an account of `space` bytes.
+pub fn rent_exempt_minimum(space: u64) -> u64 {
+    (ACCOUNT_STORAGE_OVERHEAD + space) * RENT_EXEMPT_LAMPORTS_PER_BYTE
+}
+
+/// A warning for handler `name` when the rent of the accounts it makes the
+/// payer fund is more than `RENT_HEAVY_PERCENT` of its lowest price, unless
+/// `allow_rent_heavy` says that is meant.
+pub fn rent_lint(args: &X402Args, name: &Ident) -> TokenStream {
+    let Some(space) = args.created_space.filter(|_| !args.allow_rent_heavy) else {
+        return TokenStream::new();
+    };
+    let price = args
+        .experiment
+        .as_ref()
+        .and_then(|(_, prices)| prices.iter().min().copied())
+        .unwrap_or(args.price);
+    let rent = rent_exempt_minimum(space);
+    if u128::from(rent) * 100 <= u128::from(price) * u128::from(RENT_HEAVY_PERCENT) {
+        return TokenStream::new();
+    }
+    let note = format!(
+        "`{}` costs its payer {} lamports of rent for {} bytes of new accounts on top of its {} lamport price; raise the price, or set allow_rent_heavy = true if that is meant",
+        name, rent, space, price
+    );
+    warning(&note, "x402_rent_heavy", name.span())
+}
+
+/// Stable proc macros cannot emit warnings, but using a deprecated item
+/// does, here at `span`.
+fn warning(note: &str, item: &str, span: proc_macro2::Span) -> TokenStream {
+    let item = Ident::new(item, span);
+    quote_spanned! {span=>
+        #[deprecated(note = #note)]
+        #[allow(non_upper_case_globals)]
+        const #item: () = ();
+        #[allow(clippy::let_unit_value)]
+        let _ = #item;
+    }
+}
+
+/// The first top-level statement of `body` that writes to `ctx.accounts`
+/// before any statement calling a `pay` or `transfer` function. Writes are
+/// assignments, compound assignments and `&mut` borrows of a place under
+/// `ctx.accounts`.
+fn write_before_payment<'a>(ctx: &Ident, body: &'a Block) -> Option<&'a Stmt> {
+    for stmt in &body.stmts {
+        let mut payment = Payments { to_recipient: false, found: None };
+        payment.visit_stmt(stmt);
+        if payment.found.is_some() {
+            return None;
+        }
+        let mut writes = Writes { ctx, found: false };
+        writes.visit_stmt(stmt);
+        if writes.found {
+            return Some(stmt);
+        }
+    }
+    None
+}
+
+/// The first call in `body` to a `pay` or `transfer` function with an
+/// argument naming `recipient` or `x402_recipient`.
+fn transfer_to_recipient(body: &Block) -> Option<&ExprCall> {
+    let mut payments = Payments { to_recipient: true, found: None };
+    payments.visit_block(body);
+    payments.found
+}
+
+struct Writes<'a> {
+    ctx: &'a Ident,
+    found: bool,
+}
+
+impl<'ast> Visit<'ast> for Writes<'_> {
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        let place = match expr {
+            Expr::Assign(assign) => Some(&*assign.left),
+            Expr::Binary(binary) if is_compound_assignment(&binary.op) => Some(&*binary.left),
+            Expr::Reference(reference) if reference.mutability.is_some() => Some(&*reference.expr),
+            _ => None,
+        };
+        if place.is_some_and(|place| under_accounts(place, self.ctx)) {
+            self.found = true;
+        }
+        visit::visit_expr(self, expr);
+    }
+
+    // Items nested in the body do not run as part of it.
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+struct Payments<'a> {
+    to_recipient: bool,
+    found: Option<&'a ExprCall>,
+}
+
+impl<'ast> Visit<'ast> for Payments<'ast> {
+    fn visit_expr_call(&mut self, call: &'ast ExprCall) {
+        let named = match &*call.func {
+            Expr::Path(path) => path.path.segments.last().is_some_and(|seg| is_payment(&seg.ident)),
+            _ => false,
+        };
+        if self.found.is_none()
+            && named
+            && (!self.to_recipient || call.args.iter().any(|arg| names_recipient(arg.to_token_stream())))
+        {
+            self.found = Some(call);
+        }
+        visit::visit_expr_call(self, call);
+    }
+
+    fn visit_item(&mut self, _: &'ast Item) {}
+}
+
+fn is_payment(name: &Ident) -> bool {
+    name == "pay" || name == "transfer"
+}
+
+fn is_compound_assignment(op: &BinOp) -> bool {
+    matches!(
+        op,
+        BinOp::AddAssign(_)
+            | BinOp::SubAssign(_)
+            | BinOp::MulAssign(_)
+            | BinOp::DivAssign(_)
+            | BinOp::RemAssign(_)
+            | BinOp::BitXorAssign(_)
+            | BinOp::BitAndAssign(_)
+            | BinOp::BitOrAssign(_)
+            | BinOp::ShlAssign(_)
+            | BinOp::ShrAssign(_)
+    )
+}
+
+/// Whether `place` is reached through `ctx.accounts`, such as
+/// `ctx.accounts.counter.count` or `**ctx.accounts.vault.try_borrow_mut_lamports()?`.
+fn under_accounts(place: &Expr, ctx: &Ident) -> bool {
+    match place {
+        Expr::Field(field) => {
+            let is_accounts = matches!(&field.member, Member::Named(member) if member == "accounts")
+                && matches!(&*field.base, Expr::Path(base) if base.path.is_ident(ctx));
+            is_accounts || under_accounts(&field.base, ctx)
+        }
+        Expr::Index(index) => under_accounts(&index.expr, ctx),
+        Expr::MethodCall(call) => under_accounts(&call.receiver, ctx),
+        Expr::Unary(unary) => under_accounts(&unary.expr, ctx),
+        Expr::Try(try_expr) => under_accounts(&try_expr.expr, ctx),
+        Expr::Paren(paren) => under_accounts(&paren.expr, ctx),
+        _ => false,
+    }
+}
+
+fn names_recipient(tokens: TokenStream) -> bool {
+    tokens.into_iter().any(|tree| match tree {
+        TokenTree::Ident(ident) => ident == "recipient" || ident == "x402_recipient",
+        TokenTree::Group(group) => names_recipient(group.stream()),
+        _ => false,
+    })
+}
@@ -0,0 +1,179 @@
+//! Which `#[x402]` options each payment mode accepts.
+//!
+//! A handler is paid one way: by a preceding transfer, by a transfer its
+//! body makes, from a deposit, or from payments accumulated toward it. Many
+//! options only shape one of those, so the combinations are checked against
+//! [`MODE_MATRIX`] in one place rather than option by option.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+
+use crate::args::PaymentMode;
+
+/// How a handler is paid, selected by `mode`, `deposit` and `accumulated`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    PrecedingInstruction,
+    BalanceDelta,
+    Deposit,
+    Accumulated,
+}
+
+/// A mode and the options that have no meaning in it.
+pub struct ModeRule {
+    pub mode: Mode,
+    /// How the attribute selects the mode, as the user would write it.
+    pub selected_by: &'static str,
+    /// The argument selecting the mode, whose span errors point at.
+    pub selector: &'static str,
+    pub excludes: &'static [&'static str],
+}
+
+/// Options a preceding transfer gives meaning to: the scan that finds it
+/// and what it may be combined with.
+const TRANSFER_OPTIONS: &[&str] = &[
+    "sysvar_position",
+    "allow_split_payers",
+    "max_split_payers",
+    "max_lookback",
+    "strict_scan",
+    "legacy_matcher",
+    "record_fingerprint",
+    "allow_trampoline",
+    "allow_cpi",
+    "splits",
+    "recipient_from_arg",
+    "group",
+    "group_cap",
+];
+
+/// Every mode, the default first.
+pub const MODE_MATRIX: &[ModeRule] = &[
+    ModeRule {
+        mode: Mode::PrecedingInstruction,
+        selected_by: "mode = \"preceding_instruction\" (the default)",
+        selector: "mode",
+        excludes: &[],
+    },
+    ModeRule {
+        mode: Mode::BalanceDelta,
+        selected_by: "mode = \"balance_delta\"",
+        selector: "mode",
+        excludes: TRANSFER_OPTIONS,
+    },
+    // A deposit is debited by the program itself: there is no payment
+    // instruction to look for, sign or split.
+    ModeRule {
+        mode: Mode::Deposit,
+        selected_by: "`deposit = true`",
+        selector: "deposit",
+        excludes: &[
+            "mode",
+            "require_fee_payer",
+            "sysvar_position",
+            "allow_split_payers",
+            "max_split_payers",
+            "max_lookback",
+            "strict_scan",
+            "legacy_matcher",
+            "record_fingerprint",
+            "allow_trampoline",
+            "allow_cpi",
+            "splits",
+            "recipient_from_arg",
+            "group",
+            "group_cap",
+        ],
+    },
+    // Nor is there one for an accumulated price: the payments were read
+    // when they were accumulated.
+    ModeRule {
+        mode: Mode::Accumulated,
+        selected_by: "`accumulated = true`",
+        selector: "accumulated",
+        excludes: &[
+            "mode",
+            "require_fee_payer",
+            "sysvar_position",
+            "allow_split_payers",
+            "max_split_payers",
+            "max_lookback",
+            "strict_scan",
+            "legacy_matcher",
+            "record_fingerprint",
+            "allow_trampoline",
+            "allow_cpi",
+            "splits",
+            "recipient_from_arg",
+            "group",
+            "group_cap",
+        ],
+    },
+];
+
+impl Mode {
+    /// The mode of a handler with `mode`, `deposit` and `accumulated`.
+    pub fn of(mode: PaymentMode, deposit: bool, accumulated: bool) -> Mode {
+        match (mode, deposit, accumulated) {
+            (_, true, _) => Mode::Deposit,
+            (_, false, true) => Mode::Accumulated,
+            (PaymentMode::PrecedingInstruction, false, false) => Mode::PrecedingInstruction,
+            (PaymentMode::BalanceDelta, false, false) => Mode::BalanceDelta,
+        }
+    }
+
+    pub fn rule(self) -> &'static ModeRule {
+        MODE_MATRIX
+            .iter()
+            .find(|rule| rule.mode == self)
+            .expect("every mode has a row in MODE_MATRIX")
+    }
+
+    /// The `x402_runtime::Mode` this mode is emitted as.
+    pub fn tokens(self) -> TokenStream {
+        match self {
+            Mode::PrecedingInstruction => quote!(x402_runtime::Mode::PrecedingInstruction),
+            Mode::BalanceDelta => quote!(x402_runtime::Mode::BalanceDelta),
+            Mode::Deposit => quote!(x402_runtime::Mode::Deposit),
+            Mode::Accumulated => quote!(x402_runtime::Mode::Accumulated),
+        }
+    }
+}
+
+/// The message rejecting `conflicts`, the given options `mode` excludes,
+/// naming the modes that accept them all.
+pub fn conflict_message(mode: Mode, conflicts: &[&str]) -> String {
+    let names: Vec<String> = conflicts.iter().map(|name| format!("`{}`", name)).collect();
+    let options = match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} and {}", rest.join(", "), last),
+        _ => names.concat(),
+    };
+    let alternatives = MODE_MATRIX
+        .iter()
+        .filter(|rule| rule.mode != mode)
+        .filter(|rule| conflicts.iter().all(|name| !rule.excludes.contains(name)))
+        .map(|rule| rule.selected_by)
+        .collect::<Vec<_>>();
+    let (verb, which) = match conflicts.len() {
+        1 => ("does", "it needs"),
+        _ => ("do", "they need"),
+    };
+
+    if alternatives.is_empty() {
+        format!(
+            "{} {} not apply to {}, and no one mode accepts them together",
+            options,
+            verb,
+            mode.rule().selected_by
+        )
+    } else {
+        format!(
+            "{} {} not apply to {}; {} {}",
+            options,
+            verb,
+            mode.rule().selected_by,
+            which,
+            alternatives.join(" or ")
+        )
+    }
+}
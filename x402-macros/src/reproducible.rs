@@ -0,0 +1,115 @@
+//! The expansion depends on its input alone, so a program builds to the
+//! same bytes every time and a deployed program can be checked against a
+//! reproducible build of its source. Nothing emitted may come from hash
+//! map iteration, the time or the build's paths.
+//!
+//! Proc macros run inside the compiler, so these tests run the attributes'
+//! `proc_macro2` halves on `x402-example`'s source instead: once here, and
+//! once in a second run of this test binary, a separate process with hash
+//! seeds of its own.
+
+use std::path::Path;
+use std::process::Command;
+
+use quote::ToTokens;
+use syn::{Item, ItemMod};
+
+use crate::accounts::AccountsArgs;
+use crate::{accounts, expand_x402, tiers};
+
+/// Set to a file path, has `write_expansion` write the expansion there.
+const OUTPUT_VAR: &str = "X402_EXPANSION_OUTPUT";
+
+fn example_source() -> syn::File {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../x402-example/src/lib.rs");
+    syn::parse_file(&std::fs::read_to_string(path).unwrap()).unwrap()
+}
+
+fn has_attr(attrs: &[syn::Attribute], name: &str) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident(name))
+}
+
+/// Takes the attribute named `name` off `attrs`, returning its arguments.
+fn take_attr(attrs: &mut Vec<syn::Attribute>, name: &str) -> Option<proc_macro2::TokenStream> {
+    let at = attrs.iter().position(|attr| attr.path().is_ident(name))?;
+    let attr = attrs.remove(at);
+    Some(match attr.meta {
+        syn::Meta::List(list) => list.tokens,
+        _ => proc_macro2::TokenStream::new(),
+    })
+}
+
+/// Expands the `#[x402]` handlers among `items`, and in the modules among
+/// them, as the compiler would after `#[x402_program]`.
+fn expand_handlers(items: Vec<Item>, out: &mut String) {
+    for item in items {
+        match item {
+            Item::Fn(mut handler) => {
+                if let Some(args) = take_attr(&mut handler.attrs, "x402") {
+                    out.push_str(&expand_x402(args, handler.into_token_stream()).to_string());
+                }
+            }
+            Item::Mod(ItemMod {
+                content: Some((_, items)),
+                ..
+            }) => expand_handlers(items, out),
+            _ => {}
+        }
+    }
+}
+
+/// Every expansion of the example program, in source order.
+fn expand_example() -> String {
+    let mut out = String::new();
+    for item in example_source().items {
+        match item {
+            Item::Mod(mut module) if has_attr(&module.attrs, "x402_program") => {
+                let args = syn::parse2(take_attr(&mut module.attrs, "x402_program").unwrap()).unwrap();
+                let expanded = tiers::expand_module(args, module).unwrap();
+                out.push_str(&expanded.to_string());
+                let module: ItemMod = syn::parse2(expanded).unwrap();
+                expand_handlers(module.content.unwrap().1, &mut out);
+            }
+            Item::Struct(mut item) if has_attr(&item.attrs, "x402_accounts") => {
+                let args: AccountsArgs = syn::parse2(take_attr(&mut item.attrs, "x402_accounts").unwrap()).unwrap();
+                out.push_str(&accounts::expand_struct(args, item).unwrap().to_string());
+            }
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Run by `expansion_is_the_same_in_another_process` in a child process;
+/// does nothing otherwise.
+#[test]
+fn write_expansion() {
+    if let Some(path) = std::env::var_os(OUTPUT_VAR) {
+        std::fs::write(path, expand_example()).unwrap();
+    }
+}
+
+#[test]
+fn expansion_is_the_same_in_another_process() {
+    let expansion = expand_example();
+    assert!(expansion.contains("X402_TABLE"), "the example program was not expanded");
+    assert_eq!(expansion, expand_example());
+
+    let path = std::env::temp_dir().join(format!("x402-expansion-{}", std::process::id()));
+    let child = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "reproducible::write_expansion"])
+        .env(OUTPUT_VAR, &path)
+        .output()
+        .unwrap();
+    assert!(child.status.success(), "{}", String::from_utf8_lossy(&child.stdout));
+    let other = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(expansion == other, "the expansion differs between processes");
+}
+
+#[test]
+fn expansion_embeds_no_paths() {
+    let expansion = expand_example();
+    let workspace = Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap();
+    assert!(!expansion.contains(workspace.to_str().unwrap()));
+}
@@ -0,0 +1,197 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::punctuated::Punctuated;
+use syn::{parse_quote, Attribute, Item, ItemConst, ItemFn, ItemMod, LitStr, Meta, Token};
+
+use crate::args::{has_arg, AcceptedTags, X402Args, MAX_PRICE_ARG, NONCE_ARG, RECIPIENT_ARG};
+
+/// Hidden `#[x402]` argument naming the handler's row in `X402_TABLE`.
+pub const TABLE_ROW: &str = "x402_table_row";
+/// Hidden `#[x402]` argument naming the network of `#[x402_program(network
+/// = "...")]`.
+pub const NETWORK: &str = "x402_network";
+/// Hidden `#[x402]` argument counting the modules between the handler and
+/// `X402_TABLE`, which it reaches through as many `super::`.
+pub const TABLE_DEPTH: &str = "x402_table_depth";
+
+/// Numbers the `#[x402]` handlers among `items`, and in the inline modules
+/// among them, in declaration order, tells each attribute its row, and
+/// returns the `X402_TABLE` constant holding every row and the `X402_TAGS`
+/// constant mapping each handler's tag to its name and earlier tags. Two
+/// handlers may not share a tag, current or earlier, nor give one
+/// instruction group two caps. Handlers with a
+/// dynamic price also get their `max_acceptable_price` argument, handlers
+/// with `recipient_from_arg` their `recipient` argument, and then handlers
+/// with `require_nonce` their `x402_nonce` argument. With `network`, every
+/// attribute is also told the network the program is built for.
+///
+/// Attributes whose arguments do not parse are left alone, so their own
+/// expansion reports the error against the handler.
+pub fn assign_rows(items: &mut [Item], network: Option<&LitStr>) -> syn::Result<Option<(ItemConst, ItemConst)>> {
+    let mut table = Table {
+        network: network.cloned(),
+        ..Table::default()
+    };
+    table.visit(items, 0)?;
+    Ok(table.finish())
+}
+
+#[derive(Default)]
+struct Table {
+    rows: Vec<TokenStream>,
+    tags: Vec<(LitStr, String, Option<AcceptedTags>)>,
+    groups: Vec<(String, u64, String)>,
+    network: Option<LitStr>,
+}
+
+impl Table {
+    fn visit(&mut self, items: &mut [Item], depth: usize) -> syn::Result<()> {
+        for item in items.iter_mut() {
+            match item {
+                Item::Fn(handler) => self.add(handler, depth)?,
+                Item::Mod(ItemMod {
+                    content: Some((_, items)),
+                    ..
+                }) => self.visit(items, depth + 1)?,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn add(&mut self, handler: &mut ItemFn, depth: usize) -> syn::Result<()> {
+        let (rows, tags, groups) = (&mut self.rows, &mut self.tags, &mut self.groups);
+        let Some(attr) = handler.attrs.iter_mut().find(|attr| is_x402(attr)) else {
+            return Ok(());
+        };
+        let Ok(args) = attr.parse_args::<X402Args>() else {
+            return Ok(());
+        };
+
+        let row = rows.len();
+        let mut metas = attr.parse_args_with(Punctuated::<Meta, Token![,]>::parse_terminated)?;
+        let key = syn::Ident::new(TABLE_ROW, proc_macro2::Span::call_site());
+        metas.push(parse_quote!(#key = #row));
+        if depth > 0 {
+            let key = syn::Ident::new(TABLE_DEPTH, proc_macro2::Span::call_site());
+            metas.push(parse_quote!(#key = #depth));
+        }
+        if let Some(network) = &self.network {
+            let key = syn::Ident::new(NETWORK, proc_macro2::Span::call_site());
+            metas.push(parse_quote!(#key = #network));
+        }
+        let path = attr.path().clone();
+        *attr = parse_quote!(#[#path(#metas)]);
+
+        rows.push(params_tokens(&args));
+        if args.congestion.is_some() && !has_arg(&handler.sig, MAX_PRICE_ARG) {
+            let max_price = syn::Ident::new(MAX_PRICE_ARG, proc_macro2::Span::call_site());
+            handler.sig.inputs.push(parse_quote!(#max_price: u64));
+        }
+        if args.recipient_authority.is_some() && !has_arg(&handler.sig, RECIPIENT_ARG) {
+            let recipient = syn::Ident::new(RECIPIENT_ARG, proc_macro2::Span::call_site());
+            handler.sig.inputs.push(parse_quote!(#recipient: anchor_lang::solana_program::pubkey::Pubkey));
+        }
+        if args.require_nonce && !has_arg(&handler.sig, NONCE_ARG) {
+            let nonce = syn::Ident::new(NONCE_ARG, proc_macro2::Span::call_site());
+            handler.sig.inputs.push(parse_quote!(#nonce: [u8; 32]));
+        }
+
+        let name = handler.sig.ident.to_string();
+        if let Some((group, cap)) = &args.group {
+            match groups.iter().find(|(seen, _, _)| *seen == group.value()) {
+                Some((_, other_cap, other)) if other_cap != cap => {
+                    return Err(syn::Error::new_spanned(
+                        group,
+                        format!(
+                            "`{}` caps the group \"{}\" at {} but `{}` at {}; a group has one cap",
+                            other,
+                            group.value(),
+                            other_cap,
+                            name,
+                            cap
+                        ),
+                    ));
+                }
+                Some(_) => {}
+                None => groups.push((group.value(), *cap, name.clone())),
+            }
+        }
+        let tag = args.tag.unwrap_or_else(|| LitStr::new(&name, handler.sig.ident.span()));
+        let aliases = args.accepts_tags.as_ref().map_or(&[][..], |accepted| &accepted.tags);
+        for claimed in std::iter::once(&tag).chain(aliases) {
+            let other = tags.iter().find(|(seen, _, seen_aliases)| {
+                let seen_aliases = seen_aliases.as_ref().map_or(&[][..], |accepted| &accepted.tags);
+                std::iter::once(seen).chain(seen_aliases).any(|seen| seen.value() == claimed.value())
+            });
+            if let Some((_, other, _)) = other {
+                return Err(syn::Error::new_spanned(
+                    claimed,
+                    format!(
+                        "`{}` and `{}` share the x402 tag \"{}\"; tags, current or accepted, must be unique",
+                        other,
+                        name,
+                        claimed.value()
+                    ),
+                ));
+            }
+        }
+        tags.push((tag, name, args.accepts_tags));
+        Ok(())
+    }
+
+    fn finish(self) -> Option<(ItemConst, ItemConst)> {
+        let Table { rows, tags, .. } = self;
+        if rows.is_empty() {
+            return None;
+        }
+        let len = rows.len();
+        let tags = tags.into_iter().map(|(tag, name, aliases)| {
+            let aliases = match aliases {
+                Some(AcceptedTags { tags, until }) => quote! {
+                    Some(x402_runtime::TagAliases { tags: &[#(#tags),*], until: #until })
+                },
+                None => quote!(None),
+            };
+            quote!(x402_runtime::InstructionTag { tag: #tag, instruction: #name, aliases: #aliases })
+        });
+        Some((
+            parse_quote! {
+                /// Payment requirements of every `#[x402]` handler in this program,
+                /// in declaration order. Each handler's `<HANDLER>_X402_PARAMS` is
+                /// its row, so this one table is what the checks enforce.
+                pub const X402_TABLE: [x402_runtime::X402Params; #len] = [#(#rows),*];
+            },
+            parse_quote! {
+                /// Tag, name and earlier tags of every `#[x402]` handler in this
+                /// program, in the order of `X402_TABLE`.
+                pub const X402_TAGS: [x402_runtime::InstructionTag; #len] = [#(#tags),*];
+            },
+        ))
+    }
+}
+
+/// The `X402Params` literal `args` describe.
+pub fn params_tokens(args: &X402Args) -> TokenStream {
+    let price = args.price;
+    let recipient = args.recipient.unwrap_or_default();
+    let recipient = &recipient[..];
+    let currency = match args.token {
+        Some(mint) if args.token_price => quote! {
+            x402_runtime::Currency::Token(anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#mint),*]))
+        },
+        _ => quote!(x402_runtime::Currency::Sol),
+    };
+
+    quote! {
+        x402_runtime::X402Params {
+            price: #price,
+            recipient: anchor_lang::solana_program::pubkey::Pubkey::new_from_array([#(#recipient),*]),
+            currency: #currency,
+        }
+    }
+}
+
+fn is_x402(attr: &Attribute) -> bool {
+    attr.path().segments.last().is_some_and(|seg| seg.ident == "x402")
+}
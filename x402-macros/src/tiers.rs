@@ -0,0 +1,283 @@
+use std::collections::BTreeSet;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, Expr, ExprLit, Ident, Item, ItemFn, ItemMod, Lit, LitInt, LitStr, Meta, MetaList, MetaNameValue, Token,
+};
+
+use crate::args::{check_tag, is_option, network_lit, str_lit};
+use crate::table;
+
+/// Arguments of `#[x402_program(...)]`.
+#[derive(Default)]
+pub struct ProgramArgs {
+    /// The network of `network = "..."`, which every handler's config
+    /// account must record.
+    network: Option<LitStr>,
+}
+
+impl Parse for ProgramArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metas = Punctuated::<MetaNameValue, Token![,]>::parse_terminated(input)?;
+
+        let mut network = None;
+        for meta in metas {
+            if !meta.path.is_ident("network") {
+                return Err(syn::Error::new_spanned(
+                    &meta.path,
+                    "unknown x402_program argument; expected `network`, e.g. network = \"mainnet-beta\"",
+                ));
+            }
+            let lit = str_lit(&meta.value, "network")?;
+            network_lit(lit)?;
+            if network.is_some() {
+                return Err(syn::Error::new_spanned(&meta.path, "duplicate argument `network`"));
+            }
+            network = Some(lit.clone());
+        }
+        Ok(ProgramArgs { network })
+    }
+}
+
+pub struct TierArgs {
+    /// `None` leaves the recipient to the program's config account.
+    address: Option<LitStr>,
+    tiers: Vec<(Ident, LitInt)>,
+    /// Pinned tag of each tier named in `tags(...)`.
+    tags: Vec<(Ident, LitStr)>,
+    /// Other `#[x402]` arguments, forwarded unchanged to every tier.
+    options: Vec<Meta>,
+}
+
+impl Parse for TierArgs {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let metas = Punctuated::<Meta, Token![,]>::parse_terminated(input)?;
+
+        let mut address = None;
+        let mut tiers: Vec<(Ident, LitInt)> = Vec::new();
+        let mut options = Vec::new();
+        let mut tags = None;
+
+        for meta in metas {
+            // List options such as `congestion_surcharge(...)` are
+            // forwarded whole.
+            let meta = match meta {
+                Meta::List(list) if list.path.is_ident("tags") => {
+                    if tags.is_some() {
+                        return Err(syn::Error::new_spanned(&list.path, "duplicate argument `tags`"));
+                    }
+                    tags = Some(tier_tags(&list)?);
+                    continue;
+                }
+                Meta::List(list) if list.path.get_ident().is_some_and(|key| is_option(&key.to_string())) => {
+                    options.push(Meta::List(list));
+                    continue;
+                }
+                meta => meta.require_name_value()?.clone(),
+            };
+            let key = meta.path.get_ident().cloned().ok_or_else(|| {
+                syn::Error::new_spanned(&meta.path, "expected `address` or a tier name")
+            })?;
+
+            match (key.to_string().as_str(), &meta.value) {
+                ("address" | "recipient", Expr::Lit(ExprLit { lit: Lit::Str(lit), .. })) => {
+                    address = Some(lit.clone());
+                }
+                ("address" | "recipient", value) => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        "`address` must be a string literal containing a base58 public key",
+                    ));
+                }
+                ("tag", _) => {
+                    return Err(syn::Error::new_spanned(
+                        &key,
+                        "every tier needs its own tag; pin them with tags(basic = \"basic_v1\", ...)",
+                    ));
+                }
+                ("price" | "price_sol" | "price_usdc", _) => {
+                    return Err(syn::Error::new_spanned(
+                        &key,
+                        format!(
+                            "#[x402_tiers] takes one price per tier, e.g. basic = 1_000_000, instead of `{}`",
+                            key
+                        ),
+                    ));
+                }
+                (name, _) if is_option(name) => options.push(Meta::NameValue(meta.clone())),
+                (_, Expr::Lit(ExprLit { lit: Lit::Int(lit), .. })) => {
+                    if tiers.iter().any(|(name, _)| *name == key) {
+                        return Err(syn::Error::new_spanned(
+                            &key,
+                            format!("duplicate tier `{}`", key),
+                        ));
+                    }
+                    tiers.push((key, lit.clone()));
+                }
+                (_, value) => {
+                    return Err(syn::Error::new_spanned(
+                        value,
+                        format!("price of tier `{}` must be an integer literal", key),
+                    ));
+                }
+            }
+        }
+
+        if tiers.is_empty() {
+            return Err(syn::Error::new(
+                Span::call_site(),
+                "#[x402_tiers] needs at least one tier, e.g. #[x402_tiers(address = \"...\", basic = 1_000_000)]",
+            ));
+        }
+
+        let tags = tags.unwrap_or_default();
+        if let Some((name, _)) = tags.iter().find(|(name, _)| !tiers.iter().any(|(tier, _)| tier == name)) {
+            return Err(syn::Error::new_spanned(name, format!("`tags` names `{}`, which is not a tier", name)));
+        }
+
+        Ok(TierArgs {
+            address,
+            tiers,
+            tags,
+            options,
+        })
+    }
+}
+
+/// Parses `tags(basic = "basic_v1", ...)`.
+fn tier_tags(list: &MetaList) -> syn::Result<Vec<(Ident, LitStr)>> {
+    let metas = list.parse_args_with(Punctuated::<MetaNameValue, Token![,]>::parse_terminated)?;
+
+    let mut tags: Vec<(Ident, LitStr)> = Vec::new();
+    for meta in &metas {
+        let tier = meta
+            .path
+            .get_ident()
+            .ok_or_else(|| syn::Error::new_spanned(&meta.path, "expected a tier name"))?;
+        let lit = str_lit(&meta.value, "tags")
+            .map_err(|_| syn::Error::new_spanned(&meta.value, "a tier's tag must be a string literal such as \"basic_v1\""))?;
+        check_tag(lit)?;
+        if tags.iter().any(|(name, _)| name == tier) {
+            return Err(syn::Error::new_spanned(tier, format!("duplicate tag for tier `{}`", tier)));
+        }
+        tags.push((tier.clone(), lit.clone()));
+    }
+    Ok(tags)
+}
+
+/// Expands every `#[x402_tiers(...)]` handler in `module`, and in the
+/// inline modules nested in it, into one priced handler per tier, named
+/// `<handler>_<tier>`, then gathers every priced handler's requirements
+/// into the module's `X402_TABLE`.
+pub fn expand_module(args: ProgramArgs, mut module: ItemMod) -> syn::Result<TokenStream> {
+    let Some((_, items)) = module.content.as_mut() else {
+        return Ok(quote!(#module));
+    };
+
+    expand_items(items)?;
+    if let Some((table, tags)) = table::assign_rows(items, args.network.as_ref())? {
+        items.insert(0, Item::Const(tags));
+        items.insert(0, Item::Const(table));
+    }
+
+    Ok(quote!(#module))
+}
+
+/// Expands the tiers among `items`, each module's handlers checked for
+/// name clashes against that module's functions alone.
+fn expand_items(items: &mut Vec<Item>) -> syn::Result<()> {
+    let mut existing: BTreeSet<String> = items
+        .iter()
+        .filter_map(|item| match item {
+            Item::Fn(f) => Some(f.sig.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+
+    let mut expanded = Vec::with_capacity(items.len());
+    for item in items.drain(..) {
+        match item {
+            Item::Fn(mut handler) => match take_tiers_attr(&mut handler)? {
+                Some(args) => {
+                    existing.remove(&handler.sig.ident.to_string());
+                    for tiered in expand_tiers(&handler, &args, &mut existing)? {
+                        expanded.push(Item::Fn(tiered));
+                    }
+                }
+                None => expanded.push(Item::Fn(handler)),
+            },
+            Item::Mod(mut nested) => {
+                if let Some((_, items)) = nested.content.as_mut() {
+                    expand_items(items)?;
+                }
+                expanded.push(Item::Mod(nested));
+            }
+            other => expanded.push(other),
+        }
+    }
+    *items = expanded;
+    Ok(())
+}
+
+fn take_tiers_attr(handler: &mut ItemFn) -> syn::Result<Option<TierArgs>> {
+    let Some(pos) = handler
+        .attrs
+        .iter()
+        .position(|attr| attr.path().is_ident("x402_tiers"))
+    else {
+        return Ok(None);
+    };
+
+    let attr = handler.attrs.remove(pos);
+    attr.parse_args::<TierArgs>().map(Some)
+}
+
+fn expand_tiers(
+    handler: &ItemFn,
+    args: &TierArgs,
+    existing: &mut BTreeSet<String>,
+) -> syn::Result<Vec<ItemFn>> {
+    let address: Vec<&LitStr> = args.address.iter().collect();
+    let options = &args.options;
+    let mut generated = Vec::with_capacity(args.tiers.len());
+
+    for (tier, price) in &args.tiers {
+        let name = format_ident!("{}_{}", handler.sig.ident, tier);
+        if !existing.insert(name.to_string()) {
+            return Err(syn::Error::new_spanned(
+                tier,
+                format!(
+                    "tier `{}` generates instruction `{}`, which already exists in this program",
+                    tier, name
+                ),
+            ));
+        }
+
+        let tier_name = tier.to_string();
+        let tag: Vec<&LitStr> = args
+            .tags
+            .iter()
+            .filter(|(name, _)| name == tier)
+            .map(|(_, tag)| tag)
+            .collect();
+        let mut tiered = handler.clone();
+        tiered.sig.ident = name;
+        tiered.attrs.insert(
+            0,
+            parse_quote!(#[x402_macros::x402(price = #price #(, address = #address)* #(, tag = #tag)* #(, #options)*)]),
+        );
+        tiered.block.stmts.insert(
+            0,
+            parse_quote! {
+                #[allow(unused_variables)]
+                let tier: &str = #tier_name;
+            },
+        );
+        generated.push(tiered);
+    }
+
+    Ok(generated)
+}
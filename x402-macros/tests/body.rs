@@ -0,0 +1,290 @@
+//! The expansion runs the handler body as written: `?`, early `return`,
+//! `break` and closures capturing `ctx` give the same results with and
+//! without `#[x402]` once the payment checks pass. Each handler is defined
+//! twice from one body, gated and plain, and both are called on the same
+//! accounts.
+//!
+//! With `mode = "balance_delta"` the balance check runs after the body, so
+//! an early return still reaches it: a body that returns before paying is
+//! rejected, however it returns.
+
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use x402_runtime::X402Error;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+const PRICE: u64 = 1_000;
+const RECIPIENT: Pubkey = gated::QUESTION_MARK_X402_PARAMS.recipient;
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+#[derive(Accounts)]
+pub struct Priced<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Delta<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: checked against the configured address by the expansion.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+}
+
+#[error_code]
+pub enum BodyError {
+    Odd,
+    TooLarge,
+}
+
+fn halve(input: u64) -> Result<u64> {
+    if input % 2 == 1 {
+        return err!(BodyError::Odd);
+    }
+    Ok(input / 2)
+}
+
+/// Stands in for the body's transfer to the recipient.
+fn credit(recipient: &UncheckedAccount, lamports: u64) -> Result<()> {
+    **recipient.to_account_info().try_borrow_mut_lamports()? += lamports;
+    Ok(())
+}
+
+/// Defines each handler in `gated`, under its `#[x402(...)]`, and
+/// unchanged in `plain`.
+macro_rules! gated_and_plain {
+    ($(#[x402($($args:tt)*)] $handler:item)*) => {
+        mod gated {
+            use super::*;
+            $(#[x402_macros::x402($($args)*)] $handler)*
+        }
+        mod plain {
+            use super::*;
+            $($handler)*
+        }
+    };
+}
+
+gated_and_plain! {
+    #[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "question_mark")]
+    pub fn question_mark(ctx: Context<Priced>, input: u64) -> Result<u64> {
+        let halved = halve(input)?;
+        let offset = u64::from(ctx.accounts.payer.key().to_bytes()[0]);
+        Ok(halved.checked_add(offset).ok_or(BodyError::TooLarge)?)
+    }
+
+    #[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "early_return")]
+    pub fn early_return(ctx: Context<Priced>, input: u64) -> Result<u64> {
+        if input == 0 {
+            return Ok(0);
+        }
+        if input > 100 {
+            return err!(BodyError::TooLarge);
+        }
+        Ok(input + u64::from(ctx.accounts.payer.key().to_bytes()[1]))
+    }
+
+    #[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "loops")]
+    pub fn loops(ctx: Context<Priced>, input: u64) -> Result<u64> {
+        let mut count = 0;
+        let found = loop {
+            count += 1;
+            if count == input {
+                break count;
+            }
+            if count > 10 {
+                break 0;
+            }
+        };
+        let mut total = found;
+        'bytes: for byte in ctx.accounts.payer.key().to_bytes() {
+            for step in 0..byte {
+                if total > 40 {
+                    break 'bytes;
+                }
+                if step % 2 == 0 {
+                    continue 'bytes;
+                }
+                total += u64::from(step);
+            }
+            total += 1;
+        }
+        Ok(total)
+    }
+
+    #[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "closures")]
+    pub fn closures(ctx: Context<Priced>, input: u64) -> Result<u64> {
+        let payer = || ctx.accounts.payer.key();
+        let shifted = |n: u64| -> Result<u64> {
+            if n > 5 {
+                return err!(BodyError::TooLarge);
+            }
+            Ok(n + u64::from(payer().to_bytes()[2]))
+        };
+        let all = (0..input).map(shifted).collect::<Result<Vec<u64>>>()?;
+        let last = || -> Option<u64> { Some(*all.last()? * 2) };
+        Ok(last().unwrap_or_default())
+    }
+
+    #[x402(
+        price = 1_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        mode = "balance_delta",
+        tag = "pay_then_return",
+    )]
+    pub fn pay_then_return(ctx: Context<Delta>, input: u64) -> Result<u64> {
+        credit(&ctx.accounts.x402_recipient, PRICE)?;
+        if input == 0 {
+            return Ok(0);
+        }
+        let halved = halve(input)?;
+        Ok(halved)
+    }
+
+    #[x402(
+        price = 1_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        mode = "balance_delta",
+        tag = "pay_late",
+    )]
+    pub fn return_before_paying(ctx: Context<Delta>, input: u64) -> Result<u64> {
+        if input == 0 {
+            return Ok(0);
+        }
+        let halved = halve(input)?;
+        credit(&ctx.accounts.x402_recipient, PRICE)?;
+        Ok(halved)
+    }
+}
+
+type PricedHandler = fn(Context<Priced>, u64) -> Result<u64>;
+type DeltaHandler = fn(Context<Delta>, u64) -> Result<u64>;
+
+const INPUTS: [u64; 8] = [0, 1, 2, 3, 8, 11, 100, 102];
+
+fn borrow(ix: &Instruction) -> BorrowedInstruction<'_> {
+    BorrowedInstruction {
+        program_id: &ix.program_id,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| BorrowedAccountMeta {
+                pubkey: &meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: &ix.data,
+    }
+}
+
+/// Instructions sysvar data for a transfer of `paid` to the recipient
+/// followed by the priced call.
+fn sysvar_data(paid: Option<u64>) -> Vec<u8> {
+    let mut all: Vec<Instruction> =
+        paid.map(|paid| system_instruction::transfer(&PAYER, &RECIPIENT, paid)).into_iter().collect();
+    all.push(Instruction {
+        program_id: ID,
+        accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+        data: vec![0; 8],
+    });
+    let borrowed: Vec<_> = all.iter().map(borrow).collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, (all.len() - 1) as u16);
+    data
+}
+
+fn call_priced(handler: PricedHandler, input: u64, paid: Option<u64>) -> Result<u64> {
+    let (system, sysvar_owner) = (system_program::ID, sysvar::ID);
+    let (mut payer_lamports, mut payer_data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0);
+    let (mut sysvar_lamports, mut sysvar_data) = (0, sysvar_data(paid));
+    let remaining = [AccountInfo::new(
+        &instructions::ID,
+        false,
+        false,
+        &mut sysvar_lamports,
+        &mut sysvar_data,
+        &sysvar_owner,
+        false,
+        0,
+    )];
+
+    let mut accounts = Priced {
+        payer: Signer::try_from(&payer)?,
+    };
+    handler(Context::new(&ID, &mut accounts, &remaining, PricedBumps::default()), input)
+}
+
+fn call_delta(handler: DeltaHandler, input: u64) -> Result<u64> {
+    let system = system_program::ID;
+    let (mut payer_lamports, mut payer_data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut payer_lamports, &mut payer_data, &system, false, 0);
+    let (mut recipient_lamports, mut recipient_data) = (1_000_000, []);
+    let recipient =
+        AccountInfo::new(&RECIPIENT, false, true, &mut recipient_lamports, &mut recipient_data, &system, false, 0);
+
+    let mut accounts = Delta {
+        payer: Signer::try_from(&payer)?,
+        x402_recipient: UncheckedAccount::try_from(&recipient),
+    };
+    handler(Context::new(&ID, &mut accounts, &[], DeltaBumps::default()), input)
+}
+
+#[test]
+fn paid_handlers_return_what_their_body_returns() {
+    let handlers: [(PricedHandler, PricedHandler); 4] = [
+        (gated::question_mark, plain::question_mark),
+        (gated::early_return, plain::early_return),
+        (gated::loops, plain::loops),
+        (gated::closures, plain::closures),
+    ];
+    for (index, (gated, plain)) in handlers.into_iter().enumerate() {
+        for input in INPUTS {
+            assert_eq!(
+                call_priced(gated, input, Some(PRICE)),
+                call_priced(plain, input, None),
+                "handler {} with input {}",
+                index,
+                input
+            );
+        }
+    }
+}
+
+#[test]
+fn body_does_not_run_before_the_payment_is_verified() {
+    // `early_return` would return `Ok(0)` straight away.
+    let err = call_priced(gated::early_return, 0, None).unwrap_err();
+    assert_eq!(err, X402Error::NoPaymentInstruction.into());
+    let err = call_priced(gated::early_return, 0, Some(PRICE - 1)).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn balance_check_follows_every_return_of_a_paying_body() {
+    for input in INPUTS {
+        assert_eq!(
+            call_delta(gated::pay_then_return, input),
+            call_delta(plain::pay_then_return, input),
+            "input {}",
+            input
+        );
+    }
+}
+
+#[test]
+fn early_return_before_paying_is_rejected() {
+    assert_eq!(call_delta(plain::return_before_paying, 0).unwrap(), 0);
+    let err = call_delta(gated::return_before_paying, 0).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+
+    // A body error is returned as is; the transaction fails either way.
+    assert_eq!(call_delta(gated::return_before_paying, 3), call_delta(plain::return_before_paying, 3));
+    assert_eq!(call_delta(gated::return_before_paying, 8).unwrap(), 4);
+}
@@ -0,0 +1,104 @@
+//! A proc-macro crate's dependencies are compiled for the host as well as
+//! the target, so `x402-macros` must never pull in `anchor-lang` or
+//! `solana-program`: the expansion names their types by path through
+//! `x402_runtime` instead. This pins the manifests that keep it so, which
+//! also holds for the whole tree, since none of the allowed crates depends
+//! on Anchor or Solana.
+
+use std::path::Path;
+
+/// Everything `x402-macros` may depend on outside its dev-dependencies.
+const ALLOWED: &[&str] = &["syn", "quote", "proc-macro2", "bs58", "x402-units"];
+
+/// Names of the dependencies `manifest` builds with: its `[dependencies]`,
+/// `[build-dependencies]` and target-specific equivalents, in either table
+/// form.
+fn dependencies(manifest: &Path) -> Vec<String> {
+    let text = std::fs::read_to_string(manifest).unwrap();
+    let mut names = Vec::new();
+    let mut in_dependencies = false;
+    for line in text.lines().map(str::trim) {
+        if let Some(header) = line.strip_prefix('[').and_then(|line| line.strip_suffix(']')) {
+            // `[target.'cfg(unix)'.dependencies]` names its section after
+            // the target.
+            let parts: Vec<&str> = header.split('.').collect();
+            in_dependencies = false;
+            if let Some(at) = parts.iter().position(|part| part.ends_with("dependencies")) {
+                if matches!(parts[at], "dependencies" | "build-dependencies") {
+                    match parts.get(at + 1) {
+                        // `[dependencies.name]` declares one dependency.
+                        Some(name) => names.push(name.trim_matches('"').to_string()),
+                        None => in_dependencies = true,
+                    }
+                }
+            }
+            continue;
+        }
+        if !in_dependencies || line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some((name, _)) = line.split_once('=') {
+            let name = name.trim().trim_matches('"');
+            names.push(name.split('.').next().unwrap_or(name).to_string());
+        }
+    }
+    names
+}
+
+#[test]
+fn the_macro_crate_depends_on_token_handling_alone() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.toml");
+    let names = dependencies(&manifest);
+
+    assert!(!names.is_empty(), "no dependencies read from {}", manifest.display());
+    for name in &names {
+        assert!(
+            ALLOWED.contains(&name.as_str()),
+            "x402-macros depends on `{}`; anything needing Anchor or Solana types belongs in \
+             x402-runtime or x402-common, named by path in the expansion",
+            name
+        );
+    }
+}
+
+#[test]
+fn x402_units_has_no_dependencies() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR")).join("../x402-units/Cargo.toml");
+
+    assert_eq!(dependencies(&manifest), Vec::<String>::new());
+}
+
+#[test]
+fn dependency_tables_are_read_in_every_form() {
+    let dir = std::env::temp_dir().join(format!("x402-macros-deps-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let manifest = dir.join("Cargo.toml");
+    std::fs::write(
+        &manifest,
+        r#"
+[package]
+name = "probe"
+
+[dependencies]
+syn.workspace = true
+"quote" = "1"
+
+[dependencies.anchor-lang]
+version = "0.30"
+
+[target.'cfg(unix)'.dependencies]
+solana-program = "1"
+
+[dev-dependencies]
+trybuild = "1"
+
+[build-dependencies]
+bs58 = "0.5"
+"#,
+    )
+    .unwrap();
+
+    let names = dependencies(&manifest);
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(names, ["syn", "quote", "anchor-lang", "solana-program", "bs58"]);
+}
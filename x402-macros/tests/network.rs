@@ -0,0 +1,112 @@
+//! Handlers of a program built for one cluster. The config account
+//! records the cluster it was initialized on, and a handler whose config
+//! records another, or that gets no config, fails before reading the
+//! payment, so a build for mainnet-beta never answers calls on devnet.
+
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use anchor_lang::Discriminator;
+use x402_macros::{x402, x402_program};
+use x402_runtime::{Cluster, X402Error};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+const PRICE: u64 = 1_000;
+const RECIPIENT: Pubkey = guarded::QUERY_X402_PARAMS.recipient;
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+#[account]
+pub struct X402GlobalConfig {
+    pub cluster: Cluster,
+}
+
+#[derive(Accounts)]
+pub struct Query<'info> {
+    pub payer: Signer<'info>,
+    pub x402_config: Option<Account<'info, X402GlobalConfig>>,
+}
+
+#[x402_program(network = "mainnet-beta")]
+pub mod guarded {
+    use super::*;
+
+    #[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "query")]
+    pub fn query(ctx: Context<Query>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+}
+
+fn borrow(ix: &Instruction) -> BorrowedInstruction<'_> {
+    BorrowedInstruction {
+        program_id: &ix.program_id,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| BorrowedAccountMeta {
+                pubkey: &meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: &ix.data,
+    }
+}
+
+/// A transfer of the price, then the call.
+fn sysvar_data() -> Vec<u8> {
+    let all = [
+        system_instruction::transfer(&PAYER, &RECIPIENT, PRICE),
+        Instruction {
+            program_id: ID,
+            accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+            data: vec![0; 8],
+        },
+    ];
+    let borrowed: Vec<_> = all.iter().map(borrow).collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, 1);
+    data
+}
+
+/// Calls `query` with a config recording `cluster`, if any.
+fn call(cluster: Option<Cluster>) -> Result<u64> {
+    let (mut lamports, mut data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut lamports, &mut data, &system_program::ID, false, 0);
+
+    let config_key = Pubkey::new_unique();
+    let (mut config_lamports, mut config_data) = (1_000_000, X402GlobalConfig::DISCRIMINATOR.to_vec());
+    if let Some(cluster) = cluster {
+        cluster.serialize(&mut config_data)?;
+    }
+    let config = AccountInfo::new(&config_key, false, false, &mut config_lamports, &mut config_data, &ID, false, 0);
+
+    let mut accounts = Query {
+        payer: Signer::try_from(&payer)?,
+        x402_config: cluster.map(|_| Account::try_from(&config)).transpose()?,
+    };
+    let (mut sysvar_lamports, mut sysvar_data) = (0, sysvar_data());
+    let sysvar_info =
+        AccountInfo::new(&instructions::ID, false, false, &mut sysvar_lamports, &mut sysvar_data, &sysvar::ID, false, 0);
+    guarded::query(Context::new(&ID, &mut accounts, &[sysvar_info], QueryBumps::default()))
+}
+
+#[test]
+fn a_config_recording_the_built_cluster_is_accepted() {
+    assert_eq!(call(Some(Cluster::MainnetBeta)).unwrap(), PRICE);
+}
+
+#[test]
+fn a_config_recording_another_cluster_is_refused() {
+    for cluster in [Cluster::Devnet, Cluster::Testnet, Cluster::Localnet, Cluster::Unset] {
+        assert_eq!(call(Some(cluster)).unwrap_err(), X402Error::WrongCluster.into(), "{:?}", cluster);
+    }
+}
+
+#[test]
+fn a_call_without_a_config_is_refused() {
+    assert_eq!(call(None).unwrap_err(), X402Error::ConfigNotInitialized.into());
+}
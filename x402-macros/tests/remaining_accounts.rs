@@ -0,0 +1,181 @@
+//! Handlers that take a list of remaining accounts of their own. The
+//! instructions sysvar is read from the last remaining account only, and
+//! the body gets the ones before it as `x402_user_accounts`, so it iterates
+//! them without stepping over the sysvar and the check never reads one of
+//! them. A struct declaring the sysvar leaves every remaining account to
+//! the body.
+
+#![allow(unexpected_cfgs)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+use anchor_lang::solana_program::{system_instruction, system_program, sysvar};
+use x402_macros::{x402, x402_accounts};
+use x402_runtime::X402Error;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+const PRICE: u64 = 1_000;
+const RECIPIENT: Pubkey = SUM_BALANCES_X402_PARAMS.recipient;
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+#[derive(Accounts)]
+pub struct Sum<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402_accounts]
+#[derive(Accounts)]
+pub struct DeclaredSum<'info> {
+    pub payer: Signer<'info>,
+}
+
+fn total(accounts: &[AccountInfo]) -> Result<u64> {
+    let mut total = 0u64;
+    for account in accounts {
+        require_keys_neq!(*account.key, instructions::ID);
+        total += account.lamports();
+    }
+    Ok(total)
+}
+
+#[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "sum_balances")]
+pub fn sum_balances(ctx: Context<Sum>) -> Result<u64> {
+    msg!("{}", ctx.accounts.payer.key());
+    total(x402_user_accounts)
+}
+
+#[x402(
+    price = 1_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    sysvar_position = "any",
+    tag = "sum_any",
+)]
+pub fn sum_anywhere(ctx: Context<Sum>) -> Result<usize> {
+    msg!("{}", ctx.accounts.payer.key());
+    Ok(x402_user_accounts.len())
+}
+
+#[x402(price = 1_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "sum_declared")]
+pub fn sum_declared(ctx: Context<DeclaredSum>) -> Result<u64> {
+    msg!("{}", ctx.accounts.x402_instructions.key());
+    total(x402_user_accounts)
+}
+
+fn borrow(ix: &Instruction) -> BorrowedInstruction<'_> {
+    BorrowedInstruction {
+        program_id: &ix.program_id,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| BorrowedAccountMeta {
+                pubkey: &meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: &ix.data,
+    }
+}
+
+/// A transfer of the price, then the call.
+fn sysvar_data() -> Vec<u8> {
+    let all = [
+        system_instruction::transfer(&PAYER, &RECIPIENT, PRICE),
+        Instruction {
+            program_id: ID,
+            accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+            data: vec![0; 8],
+        },
+    ];
+    let borrowed: Vec<_> = all.iter().map(borrow).collect();
+    let mut data = instructions::construct_instructions_data(&borrowed);
+    instructions::store_current_index(&mut data, 1);
+    data
+}
+
+/// The handler's own accounts, holding 1, 2 and 3 lamports, with the
+/// instructions sysvar at `sysvar_at`, if anywhere.
+struct Remaining {
+    keys: Vec<Pubkey>,
+    lamports: Vec<u64>,
+    data: Vec<Vec<u8>>,
+}
+
+impl Remaining {
+    fn new(sysvar_at: Option<usize>) -> Self {
+        let mut remaining = Remaining {
+            keys: (0..3).map(|_| Pubkey::new_unique()).collect(),
+            lamports: vec![1, 2, 3],
+            data: vec![Vec::new(); 3],
+        };
+        if let Some(at) = sysvar_at {
+            remaining.keys.insert(at, instructions::ID);
+            remaining.lamports.insert(at, 0);
+            remaining.data.insert(at, sysvar_data());
+        }
+        remaining
+    }
+
+    fn infos(&mut self) -> Vec<AccountInfo<'_>> {
+        self.keys
+            .iter()
+            .zip(self.lamports.iter_mut())
+            .zip(self.data.iter_mut())
+            .map(|((key, lamports), data)| {
+                let owner = if *key == instructions::ID { &sysvar::ID } else { &system_program::ID };
+                AccountInfo::new(key, false, false, lamports, data, owner, false, 0)
+            })
+            .collect()
+    }
+}
+
+fn call_sum<T>(handler: fn(Context<Sum>) -> Result<T>, remaining: &mut Remaining) -> Result<T> {
+    let (mut lamports, mut data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut lamports, &mut data, &system_program::ID, false, 0);
+    let mut accounts = Sum {
+        payer: Signer::try_from(&payer)?,
+    };
+    let infos = remaining.infos();
+    handler(Context::new(&ID, &mut accounts, &infos, SumBumps::default()))
+}
+
+#[test]
+fn the_body_iterates_the_accounts_before_the_sysvar() {
+    assert_eq!(call_sum(sum_balances, &mut Remaining::new(Some(3))).unwrap(), 6);
+}
+
+#[test]
+fn the_sysvar_is_read_from_the_last_account_only() {
+    for at in [0, 1, 2] {
+        let err = call_sum(sum_balances, &mut Remaining::new(Some(at))).unwrap_err();
+        assert_eq!(err, X402Error::SysvarMissing.into(), "sysvar at {}", at);
+    }
+    let err = call_sum(sum_balances, &mut Remaining::new(None)).unwrap_err();
+    assert_eq!(err, X402Error::SysvarMissing.into());
+}
+
+#[test]
+fn any_position_searches_every_account_and_leaves_them_all() {
+    for at in [0, 1, 2, 3] {
+        assert_eq!(call_sum(sum_anywhere, &mut Remaining::new(Some(at))).unwrap(), 4, "sysvar at {}", at);
+    }
+}
+
+#[test]
+fn a_declared_sysvar_leaves_every_remaining_account_to_the_body() {
+    let (mut lamports, mut data) = (5_000_000, []);
+    let payer = AccountInfo::new(&PAYER, true, true, &mut lamports, &mut data, &system_program::ID, false, 0);
+    let (mut sysvar_lamports, mut sysvar_data) = (0, sysvar_data());
+    let sysvar_info =
+        AccountInfo::new(&instructions::ID, false, false, &mut sysvar_lamports, &mut sysvar_data, &sysvar::ID, false, 0);
+    let mut accounts = DeclaredSum {
+        payer: Signer::try_from(&payer).unwrap(),
+        x402_instructions: UncheckedAccount::try_from(&sysvar_info),
+    };
+    let mut remaining = Remaining::new(None);
+    let infos = remaining.infos();
+    let ctx = Context::new(&ID, &mut accounts, &infos, DeclaredSumBumps::default());
+    assert_eq!(sum_declared(ctx).unwrap(), 6);
+}
@@ -0,0 +1,22 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402_program;
+
+#[x402_program]
+pub mod program {
+    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "basic_v1")]
+    pub fn compute_basic(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(
+        price = 5_000_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        tag = "pro_v2",
+        accepts_tags("pro_v1", "basic_v1", until = 1_767_225_600),
+    )]
+    pub fn compute_pro(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {}
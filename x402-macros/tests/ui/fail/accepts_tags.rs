@@ -0,0 +1,51 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "compute_v2",
+    accepts_tags("compute_v1"),
+)]
+pub fn without_until(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    accepts_tags("compute_v1", until = 1_767_225_600),
+)]
+pub fn unpinned(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "compute_v2",
+    accepts_tags("compute_v2", until = 1_767_225_600),
+)]
+pub fn only_itself(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "compute_v2",
+    accepts_tags = "compute_v1",
+)]
+pub fn not_a_list(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,28 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Delta<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: checked against the configured address by the expansion.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta",
+    allow_cpi = true,
+    tag = "delta",
+)]
+pub fn delta(ctx: Context<Delta>) -> Result<()> {
+    msg!("{}", ctx.accounts.payer.key());
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn premium_compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,22 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price_sol = "0.001",
+    token = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    max_split_payers = 3,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+)]
+pub fn premium_compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    deposit = true,
+    mode = "balance_delta",
+    require_fee_payer = true,
+)]
+pub fn premium_compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    experiment(bucket_seed = "exp1", prices(1_000_000, 800_000)),
+)]
+pub fn premium_compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    experiment(bucket_seed = "exp1", prices(1_000_000)),
+)]
+pub fn single_arm(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
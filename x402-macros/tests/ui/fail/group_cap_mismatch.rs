@@ -0,0 +1,17 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402_program;
+
+#[x402_program]
+pub mod program {
+    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", group = "compute", group_cap = 5_000_000)]
+    pub fn compute_basic(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(price = 5_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", group = "compute", group_cap = 9_000_000)]
+    pub fn compute_pro(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {}
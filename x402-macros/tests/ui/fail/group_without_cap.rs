@@ -0,0 +1,15 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402;
+
+// A group needs its cap, and a group's budget counts one payment per call.
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    group = "compute",
+    allow_split_payers = true,
+)]
+pub fn compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
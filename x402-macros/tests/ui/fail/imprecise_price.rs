@@ -0,0 +1,15 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price_usdc = "1.2500001", token = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn compute(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,103 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta",
+    max_lookback = 4,
+    strict_scan = true,
+    tag = "scanned_delta",
+)]
+pub fn scanned_delta(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta",
+    record_fingerprint = true,
+    tag = "fingerprinted",
+)]
+pub fn fingerprinted_delta(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    mode = "balance_delta",
+    splits(
+        ("ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", 7000),
+        ("EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", 3000),
+    ),
+    tag = "split_delta",
+)]
+pub fn split_delta(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    deposit = true,
+    allow_trampoline = true,
+    tag = "trampolined",
+)]
+pub fn trampoline_deposit(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    deposit = true,
+    recipient_from_arg(authorized_by = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm"),
+    tag = "authorized",
+)]
+pub fn authorized_deposit(ctx: Context<Compute>, recipient: Pubkey) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    deposit = true,
+    allow_split_payers = true,
+    max_split_payers = 3,
+    tag = "split_deposit",
+)]
+pub fn split_deposit(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    accumulated = true,
+    max_lookback = 2,
+    tag = "scanned_savings",
+)]
+pub fn scanned_savings(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    deposit = true,
+    accumulated = true,
+    tag = "both_debits",
+)]
+pub fn deposit_and_savings(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
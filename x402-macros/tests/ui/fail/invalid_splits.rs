@@ -0,0 +1,53 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    splits(
+        ("G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", 7000),
+        ("AoAQ6uhexU2bozTYKXDss6EupjKBzXE8dAUavEMHQkgL", 2000),
+    ),
+    tag = "short",
+)]
+pub fn short_of_whole(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    splits(
+        ("G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", 2000),
+        ("AoAQ6uhexU2bozTYKXDss6EupjKBzXE8dAUavEMHQkgL", 2000),
+        ("AKVUUdeph3XXqu1fHKvtTRZNr2zTDUnfnYwkTppEZZRK", 2000),
+        ("ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", 2000),
+        ("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1", 1000),
+        ("11111111111111111111111111111111", 1000),
+    ),
+    tag = "too_many",
+)]
+pub fn too_many(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    splits(
+        ("G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", 5000),
+        ("AoAQ6uhexU2bozTYKXDss6EupjKBzXE8dAUavEMHQkgL", 5000),
+    ),
+    tag = "with_address",
+)]
+pub fn with_address(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,30 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402_program;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402_program(network = "mainnet")]
+pub mod misspelled {
+    use super::*;
+
+    pub fn compute(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+}
+
+#[x402_program(cluster = "devnet")]
+pub mod unknown {
+    use super::*;
+
+    pub fn compute(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {}
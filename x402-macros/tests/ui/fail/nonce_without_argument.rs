@@ -0,0 +1,22 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    emit_event = true,
+    require_nonce = true,
+)]
+pub fn priced_query(ctx: Context<PricedQuery>) -> Result<u64> {
+    Ok(x402_payment.amount)
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402;
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn double(value: u64) -> u64 {
+    value * 2
+}
+
+fn main() {}
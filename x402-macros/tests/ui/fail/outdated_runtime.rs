@@ -0,0 +1,26 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+// Stands in for an `x402-runtime` released before the macro's contract:
+// everything the expansion calls, but none of the compat markers.
+mod x402_runtime {
+    pub use ::x402_runtime::*;
+
+    pub mod compat {}
+}
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "compute")]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    msg!("paid by {}", ctx.accounts.payer.key());
+    Ok(())
+}
+
+fn main() {}
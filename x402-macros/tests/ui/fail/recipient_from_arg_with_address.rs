@@ -0,0 +1,21 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct BuyItem<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    recipient_from_arg(authorized_by = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm"),
+)]
+pub fn buy_item(ctx: Context<BuyItem>, recipient: Pubkey) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
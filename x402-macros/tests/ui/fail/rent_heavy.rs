@@ -0,0 +1,35 @@
+#![allow(unexpected_cfgs)]
+#![deny(deprecated)]
+
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Report {
+    pub data: [u8; 92],
+}
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(init, payer = payer, space = 8 + 92)]
+    pub report: Account<'info, Report>,
+    pub system_program: Program<'info, System>,
+}
+
+// The payer funds 1,586,880 lamports of rent for a 1,000,000 lamport call.
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "cheap_report",
+    creates_account_space = 100,
+)]
+pub fn cheap_report(ctx: Context<Compute>) -> Result<()> {
+    msg!("{}", ctx.accounts.report.key());
+    Ok(())
+}
+
+fn main() {}
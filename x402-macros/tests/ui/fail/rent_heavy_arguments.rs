@@ -0,0 +1,43 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price_usdc = "1.5",
+    token = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "token_report",
+    creates_account_space = 100,
+)]
+pub fn token_report(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "no_space",
+    allow_rent_heavy = true,
+)]
+pub fn no_space(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "empty",
+    creates_account_space = 0,
+)]
+pub fn empty(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
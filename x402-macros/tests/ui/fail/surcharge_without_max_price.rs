@@ -0,0 +1,28 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct CongestionLevel {
+    pub authority: Pubkey,
+    pub level: u8,
+}
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+)]
+pub fn priced_query(ctx: Context<PricedQuery>) -> Result<u64> {
+    Ok(x402_payment.amount)
+}
+
+fn main() {}
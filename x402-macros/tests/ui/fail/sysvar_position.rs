@@ -0,0 +1,35 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", sysvar_position = "first")]
+pub fn first(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    strict_accounts = true,
+    sysvar_position = "last",
+)]
+pub fn strict(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta",
+    sysvar_position = "any",
+)]
+pub fn delta(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,16 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402_program;
+
+#[x402_program]
+pub mod program {
+    #[x402_tiers(address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", basic = 1_000_000)]
+    pub fn compute(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn compute_basic(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {}
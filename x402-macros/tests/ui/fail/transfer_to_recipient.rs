@@ -0,0 +1,36 @@
+#![allow(unexpected_cfgs)]
+#![deny(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: the recipient named in #[x402].
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Pays in its body as if it were mode = "balance_delta", while the
+// attribute also expects a transfer before the instruction.
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "compute")]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+            },
+        ),
+        PREMIUM_COMPUTE_X402_PARAMS.price,
+    )
+}
+
+fn main() {}
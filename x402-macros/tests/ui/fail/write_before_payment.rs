@@ -0,0 +1,44 @@
+#![allow(unexpected_cfgs)]
+#![deny(deprecated)]
+
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Counter {
+    pub count: u64,
+}
+
+#[derive(Accounts)]
+pub struct InlineCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub counter: Account<'info, Counter>,
+    /// CHECK: checked against the configured address by the expansion.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Counts the call before paying for it.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "inline_compute",
+    mode = "balance_delta"
+)]
+pub fn inline_compute(ctx: Context<InlineCompute>) -> Result<u64> {
+    ctx.accounts.counter.count += 1;
+    x402_runtime::balance_delta::pay(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.x402_recipient.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        INLINE_COMPUTE_X402_PARAMS.price,
+    )?;
+    Ok(42)
+}
+
+fn main() {}
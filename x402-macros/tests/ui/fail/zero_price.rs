@@ -0,0 +1,9 @@
+#![allow(unexpected_cfgs)]
+use x402_macros::x402;
+
+#[x402(price = 0, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    Ok(())
+}
+
+fn main() {}
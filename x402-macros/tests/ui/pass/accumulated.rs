@@ -0,0 +1,57 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+use x402_runtime::layout::CallAccount;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Accumulator {
+    pub balance: u64,
+}
+
+#[derive(Accounts)]
+pub struct Query<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"x402_accumulator", SAVED_QUERY_X402_TAG.as_bytes(), payer.key().as_ref()], bump)]
+    pub x402_accumulator: Account<'info, Accumulator>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    accumulated = true,
+    tag = "saved_query",
+)]
+pub fn saved_query(ctx: Context<Query>) -> Result<u64> {
+    msg!("{}", ctx.accounts.x402_accumulator.balance);
+    Ok(42)
+}
+
+// Accumulators toward the earlier tag are accepted too, so the account
+// is not constrained to the new tag's seeds; the check derives both.
+#[derive(Accounts)]
+pub struct MigratedQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub x402_accumulator: Account<'info, Accumulator>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    accumulated = true,
+    tag = "saved_query_v2",
+    accepts_tags("saved_query_v1", until = 1_767_225_600),
+)]
+pub fn migrated_query(ctx: Context<MigratedQuery>) -> Result<u64> {
+    msg!("{}", ctx.accounts.x402_accumulator.balance);
+    Ok(42)
+}
+
+fn main() {
+    let accumulator = CallAccount::Accumulator {
+        tag: "saved_query".to_string(),
+    };
+    assert!(saved_query_x402_layout().call_accounts().contains(&accumulator));
+}
@@ -0,0 +1,25 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Query<'info> {
+    pub payer: Signer<'info>,
+}
+
+// A router that invokes the handler once per instruction, after its own
+// caller's transfer.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    allow_cpi = true,
+    tag = "routed_query",
+)]
+pub fn routed_query(ctx: Context<Query>) -> Result<u64> {
+    msg!("{}", ctx.accounts.payer.key());
+    Ok(42)
+}
+
+fn main() {}
@@ -0,0 +1,27 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    pub payer: Signer<'info>,
+}
+
+// Renamed context binding, trailing instruction arguments, the `recipient`
+// alias and every optional argument must all be accepted.
+#[x402(
+    price = 5_000_000,
+    recipient = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    token = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    facilitator_fee = 5,
+)]
+pub fn standard_compute(context: Context<PremiumCompute>, input: u64) -> Result<()> {
+    msg!("{} {}", context.accounts.payer.key(), input);
+    Ok(())
+}
+
+const _: u8 = STANDARD_COMPUTE_X402_FACILITATOR_FEE;
+
+fn main() {}
@@ -0,0 +1,37 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct InlineCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: checked against the configured address by the expansion.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta"
+)]
+pub fn inline_compute(ctx: Context<InlineCompute>) -> Result<u64> {
+    x402_runtime::balance_delta::pay(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.x402_recipient.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        INLINE_COMPUTE_X402_PARAMS.price,
+    )?;
+    if ctx.accounts.payer.lamports() == 0 {
+        return Ok(0);
+    }
+    Ok(42)
+}
+
+const _: () = assert!(matches!(INLINE_COMPUTE_X402_MODE, x402_runtime::Mode::BalanceDelta));
+
+fn main() {}
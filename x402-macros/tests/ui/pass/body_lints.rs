@@ -0,0 +1,74 @@
+#![allow(unexpected_cfgs)]
+#![deny(deprecated)]
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Counter {
+    pub count: u64,
+}
+
+#[derive(Accounts)]
+pub struct InlineCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub counter: Account<'info, Counter>,
+    /// CHECK: checked against the configured address by the expansion.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub counter: Account<'info, Counter>,
+    /// CHECK: created by the handler.
+    #[account(mut)]
+    pub result: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+// Pays before counting the call.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "inline_compute",
+    mode = "balance_delta"
+)]
+pub fn inline_compute(ctx: Context<InlineCompute>) -> Result<u64> {
+    x402_runtime::balance_delta::pay(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.x402_recipient.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        INLINE_COMPUTE_X402_PARAMS.price,
+    )?;
+    ctx.accounts.counter.count += 1;
+    Ok(42)
+}
+
+// Writes before verification cannot happen here, and a transfer to
+// anyone but the recipient is the body's own business.
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "compute")]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    ctx.accounts.counter.count += 1;
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.payer.to_account_info(),
+                to: ctx.accounts.result.to_account_info(),
+            },
+        ),
+        1_000,
+    )
+}
+
+fn main() {}
@@ -0,0 +1,24 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct X402GlobalConfig {
+    pub authority: Pubkey,
+    pub recipient: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct TreasuryQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_config: Option<Account<'info, X402GlobalConfig>>,
+}
+
+#[x402(price = 200_000)]
+pub fn treasury_query(ctx: Context<TreasuryQuery>) -> Result<u64> {
+    Ok(x402_payment.amount)
+}
+
+fn main() {}
@@ -0,0 +1,33 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct CongestionLevel {
+    pub authority: Pubkey,
+    pub level: u8,
+}
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+// The surcharge is a list argument; its constant is emitted next to the
+// params. Outside `#[x402_program]` the handler declares its own
+// `max_acceptable_price`.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+)]
+pub fn priced_query(ctx: Context<PricedQuery>, max_acceptable_price: u64) -> Result<u64> {
+    Ok(x402_payment.amount)
+}
+
+const _: u16 = PRICED_QUERY_X402_SURCHARGE.bps_per_level;
+
+fn main() {}
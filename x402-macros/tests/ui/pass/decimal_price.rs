@@ -0,0 +1,29 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price_sol = "0.05", address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn compute_sol(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+#[x402(
+    price_usdc = "1.25",
+    token = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+)]
+pub fn compute_usdc(ctx: Context<Compute>) -> Result<()> {
+    Ok(())
+}
+
+const _: () = assert!(COMPUTE_SOL_X402_PARAMS.price == 50_000_000);
+const _: () = assert!(COMPUTE_USDC_X402_PARAMS.price == 1_250_000);
+
+fn main() {}
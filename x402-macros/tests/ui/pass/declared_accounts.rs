@@ -0,0 +1,44 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_accounts};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+// Gets `x402_instructions`, which the handler reads instead of searching
+// the remaining accounts.
+#[x402_accounts]
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn compute(ctx: Context<Compute>) -> Result<()> {
+    msg!("{}", ctx.accounts.x402_instructions.key());
+    Ok(())
+}
+
+// Gets a writable `x402_recipient` constrained to the address.
+#[x402_accounts(mode = "balance_delta", address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+#[derive(Accounts)]
+pub struct InlineCompute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta"
+)]
+pub fn inline_compute(ctx: Context<InlineCompute>) -> Result<()> {
+    x402_runtime::balance_delta::pay(
+        ctx.accounts.payer.to_account_info(),
+        ctx.accounts.x402_recipient.to_account_info(),
+        ctx.accounts.system_program.to_account_info(),
+        INLINE_COMPUTE_X402_PARAMS.price,
+    )
+}
+
+fn main() {}
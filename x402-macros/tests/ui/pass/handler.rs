@@ -0,0 +1,18 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa")]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    msg!("paid by {}", ctx.accounts.payer.key());
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,59 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+use x402_runtime::group::GroupBudget;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct ComputeBudget {
+    pub budget: GroupBudget,
+}
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"x402_group_budget", b"compute", payer.key().as_ref()], bump)]
+    pub x402_group_budget: Option<Account<'info, ComputeBudget>>,
+}
+
+// Every tier and the plain handler charge one budget, and a call the
+// budget covers sees a payment of 0 at the list price.
+#[x402_program]
+pub mod grouped {
+    use super::*;
+
+    #[x402_tiers(
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        basic = 1_000_000,
+        pro = 5_000_000,
+        group = "compute",
+        group_cap = 5_000_000,
+        tags(basic = "basic", pro = "pro"),
+    )]
+    pub fn compute(ctx: Context<Compute>) -> Result<u64> {
+        msg!("{} {}", tier, x402_params.price);
+        Ok(x402_payment.amount)
+    }
+
+    #[x402(
+        price = 2_000_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        group = "compute",
+        group_cap = 5_000_000,
+        require_fee_payer = true,
+        tag = "batch",
+    )]
+    pub fn batch(ctx: Context<Compute>) -> Result<u64> {
+        let covered = x402_payment.amount < x402_params.price;
+        Ok(u64::from(covered))
+    }
+}
+
+fn main() {
+    let group: x402_runtime::group::Group = grouped::COMPUTE_BASIC_X402_GROUP;
+    assert_eq!((group.name, group.cap), ("compute", 5_000_000));
+    assert_eq!(grouped::COMPUTE_PRO_X402_GROUP, group);
+    assert_eq!(grouped::BATCH_X402_GROUP, group);
+}
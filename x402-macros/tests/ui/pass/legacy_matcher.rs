@@ -0,0 +1,26 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct PremiumCompute<'info> {
+    pub payer: Signer<'info>,
+}
+
+// Still paid by an older client through another instruction of the
+// program; warns until the client moves to `x402_pay`.
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "compute",
+    legacy_matcher = true,
+    max_lookback = 2,
+)]
+pub fn premium_compute(ctx: Context<PremiumCompute>) -> Result<()> {
+    msg!("paid by {}", ctx.accounts.payer.key());
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,81 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+// Outside the program module, so `#[x402_program]` cannot see it: it
+// spells out its own requirements and is not in the table.
+pub mod handlers {
+    use super::*;
+
+    #[x402(price = 300_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "refund")]
+    pub fn refund(ctx: Context<Compute>) -> Result<()> {
+        msg!("{}", ctx.accounts.payer.key());
+        Ok(())
+    }
+}
+
+// Handlers in inline modules of the program, however deep, are rows of
+// the program's one table, and their constants sit next to them, so
+// re-exporting a module's items re-exports its constants too.
+#[x402_program]
+pub mod priced {
+    use super::*;
+
+    pub use crate::handlers::*;
+    pub use instructions::audit::*;
+    pub use instructions::*;
+
+    #[x402(price = 100_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "ping")]
+    pub fn ping(ctx: Context<Compute>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+
+    pub mod instructions {
+        use super::*;
+
+        #[x402_tiers(
+            address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+            basic = 1_000_000,
+            pro = 5_000_000,
+            tags(basic = "basic", pro = "pro"),
+        )]
+        pub fn compute(ctx: Context<Compute>) -> Result<u64> {
+            msg!("{} {}", tier, COMPUTE_BASIC_X402_PARAMS.price);
+            Ok(x402_params.price)
+        }
+
+        pub mod audit {
+            use super::*;
+
+            // `require_nonce` appends its argument here as at the top.
+            #[x402(price = 2_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", require_nonce = true, tag = "audit")]
+            pub fn audit(ctx: Context<Compute>) -> Result<[u8; 32]> {
+                Ok(x402_nonce)
+            }
+        }
+    }
+}
+
+fn main() {
+    let table: [x402_runtime::X402Params; 4] = priced::X402_TABLE;
+    assert_eq!(table.map(|row| row.price), [100_000, 1_000_000, 5_000_000, 2_000_000]);
+    assert_eq!(priced::PING_X402_PARAMS, table[0]);
+    assert_eq!(priced::instructions::COMPUTE_BASIC_X402_PARAMS, table[1]);
+    assert_eq!(priced::instructions::COMPUTE_PRO_X402_PARAMS, table[2]);
+    assert_eq!(priced::instructions::audit::AUDIT_X402_PARAMS, table[3]);
+    assert_eq!(priced::X402_TAGS.map(|tag| tag.instruction), ["ping", "compute_basic", "compute_pro", "audit"]);
+
+    // Re-exported into the program module along with the handlers.
+    assert_eq!(priced::COMPUTE_PRO_X402_PARAMS, table[2]);
+    assert_eq!(priced::AUDIT_X402_PARAMS, table[3]);
+    assert_eq!(priced::REFUND_X402_PARAMS, handlers::REFUND_X402_PARAMS);
+    assert_eq!(priced::REFUND_X402_PARAMS.price, 300_000);
+    let _: fn(Context<Compute>, [u8; 32]) -> Result<[u8; 32]> = priced::audit;
+}
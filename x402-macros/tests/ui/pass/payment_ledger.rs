@@ -0,0 +1,39 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct PaymentLedger {
+    pub payer: Pubkey,
+    pub total_payments: u64,
+    pub total_amount: u64,
+    pub last_payment: i64,
+    pub streak: u8,
+}
+
+#[derive(Accounts)]
+pub struct Loyal<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"payment_ledger", payer.key().as_ref()], bump)]
+    pub x402_ledger: Option<Account<'info, PaymentLedger>>,
+}
+
+// The body reads the snapshot and writes the account it came from.
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    ledger = true,
+    tag = "loyal",
+)]
+pub fn loyal(ctx: Context<Loyal>) -> Result<u64> {
+    let history: Option<x402_runtime::ledger::LedgerSnapshot> = x402_ledger;
+    if let Some(ledger) = ctx.accounts.x402_ledger.as_mut() {
+        ledger.streak = ledger.streak.saturating_add(1);
+    }
+    Ok(history.map_or(0, |history| history.total_payments))
+}
+
+fn main() {}
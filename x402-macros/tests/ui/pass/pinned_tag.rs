@@ -0,0 +1,92 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+const USAGE_SEED: &[u8] = b"usage";
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+// The handler as first deployed.
+#[x402_program]
+pub mod before {
+    use super::*;
+
+    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "premium_v1")]
+    pub fn premium_compute(ctx: Context<Compute>) -> Result<()> {
+        msg!("{}", ctx.accounts.payer.key());
+        Ok(())
+    }
+}
+
+// The same handler renamed, with its tag kept, next to tiers pinned the
+// same way.
+#[x402_program]
+pub mod after {
+    use super::*;
+
+    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "premium_v1")]
+    pub fn compute_premium(ctx: Context<Compute>) -> Result<()> {
+        msg!("{}", ctx.accounts.payer.key());
+        Ok(())
+    }
+
+    #[x402_tiers(
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        basic = 1_000_000,
+        pro = 5_000_000,
+        tags(basic = "basic_v1", pro = "pro_v1"),
+    )]
+    pub fn query(ctx: Context<Compute>) -> Result<()> {
+        msg!("{} {}", tier, ctx.accounts.payer.key());
+        Ok(())
+    }
+}
+
+// The handler given a new tag, still accepting accounts created under the
+// old one until the end of 2025.
+#[x402_program]
+pub mod migrated {
+    use super::*;
+
+    #[x402(
+        price = 1_000_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        tag = "premium_v2",
+        accepts_tags("premium_v1", "premium_v2", until = 1_767_225_600),
+    )]
+    pub fn compute_premium(ctx: Context<Compute>) -> Result<()> {
+        msg!("{}", ctx.accounts.payer.key());
+        Ok(())
+    }
+}
+
+fn usage_counter(tag: &str, payer: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[USAGE_SEED, tag.as_bytes(), payer.as_ref()], &ID).0
+}
+
+fn main() {
+    // Usage counters created before the rename still belong to the handler.
+    let payer = Pubkey::new_unique();
+    assert_eq!(
+        usage_counter(before::PREMIUM_COMPUTE_X402_TAG, &payer),
+        usage_counter(after::COMPUTE_PREMIUM_X402_TAG, &payer),
+    );
+
+    let tags: [x402_runtime::InstructionTag; 3] = after::X402_TAGS;
+    assert_eq!(x402_runtime::tag::instruction_for(&tags, "premium_v1"), Some("compute_premium"));
+    assert_eq!(x402_runtime::tag::tag_for(&tags, "query_pro"), Some("pro_v1"));
+    assert_eq!(after::QUERY_BASIC_X402_TAG, "basic_v1");
+
+    // The pinned tag is not its own alias.
+    let aliases = migrated::COMPUTE_PREMIUM_X402_TAG_ALIASES;
+    assert_eq!(aliases.tags, ["premium_v1"]);
+    assert_eq!(migrated::X402_TAGS[0].aliases, Some(aliases));
+    assert_eq!(after::X402_TAGS[0].aliases, None);
+    assert_eq!(aliases.accepted(migrated::COMPUTE_PREMIUM_X402_TAG, 1_767_225_600), ["premium_v2", "premium_v1"]);
+    assert_eq!(aliases.accepted(migrated::COMPUTE_PREMIUM_X402_TAG, 1_767_225_601), ["premium_v2"]);
+}
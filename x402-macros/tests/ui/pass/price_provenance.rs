@@ -0,0 +1,90 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+use x402_runtime::PriceProvenance;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct CongestionLevel {
+    pub authority: Pubkey,
+    pub level: u8,
+    pub previous_level: u8,
+    pub updated_at: i64,
+}
+
+#[account]
+pub struct PriceExperiment {
+    pub ended: bool,
+    pub winner: u8,
+}
+
+#[account]
+pub struct Receipt {
+    pub price: u64,
+    pub provenance: PriceProvenance,
+}
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+    #[account(mut)]
+    pub receipt: Account<'info, Receipt>,
+}
+
+#[derive(Accounts)]
+pub struct TrialQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_experiment: Account<'info, PriceExperiment>,
+    #[account(mut)]
+    pub receipt: Account<'info, Receipt>,
+}
+
+#[derive(Accounts)]
+pub struct FixedQuery<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub receipt: Account<'info, Receipt>,
+}
+
+// Each body keeps the price it was charged with what it came from.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "priced_query",
+    congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+    price_grace_window_secs = 30,
+    record_provenance = true,
+)]
+pub fn priced_query(ctx: Context<PricedQuery>, max_acceptable_price: u64) -> Result<()> {
+    ctx.accounts.receipt.price = x402_params.price;
+    ctx.accounts.receipt.provenance = x402_provenance;
+    Ok(())
+}
+
+#[x402(
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "trial_query",
+    experiment(bucket_seed = "exp1", prices(200_000, 160_000)),
+    record_provenance = true,
+)]
+pub fn trial_query(ctx: Context<TrialQuery>) -> Result<()> {
+    ctx.accounts.receipt.price = x402_params.price;
+    ctx.accounts.receipt.provenance = x402_provenance;
+    Ok(())
+}
+
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "fixed_query",
+    record_provenance = true,
+)]
+pub fn fixed_query(ctx: Context<FixedQuery>) -> Result<()> {
+    ctx.accounts.receipt.price = x402_params.price;
+    ctx.accounts.receipt.provenance = x402_provenance;
+    Ok(())
+}
+
+fn main() {}
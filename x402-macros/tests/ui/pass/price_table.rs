@@ -0,0 +1,40 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+// Tiers and plain `#[x402]` handlers share one table, in declaration
+// order, and each handler's constant is its row.
+#[x402_program]
+pub mod priced {
+    use super::*;
+
+    #[x402_tiers(address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", basic = 1_000_000, pro = 5_000_000)]
+    pub fn compute(ctx: Context<Compute>) -> Result<()> {
+        msg!("{} {}", tier, ctx.accounts.payer.key());
+        Ok(())
+    }
+
+    pub fn free(_ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(price_sol = "0.0002", address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",)]
+    pub fn query(ctx: Context<Compute>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+}
+
+fn main() {
+    let table: [x402_runtime::X402Params; 3] = priced::X402_TABLE;
+    assert_eq!(table.map(|row| row.price), [1_000_000, 5_000_000, 200_000]);
+    assert_eq!(priced::COMPUTE_BASIC_X402_PARAMS, table[0]);
+    assert_eq!(priced::COMPUTE_PRO_X402_PARAMS, table[1]);
+    assert_eq!(priced::QUERY_X402_PARAMS, table[2]);
+}
@@ -0,0 +1,44 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct CongestionLevel {
+    pub authority: Pubkey,
+    pub level: u8,
+}
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+// `#[x402_program]` appends `max_acceptable_price` to the surcharged
+// handler only; the fixed-price one keeps its signature.
+#[x402_program]
+pub mod priced {
+    use super::*;
+
+    #[x402(
+        price = 200_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+        tag = "surcharged",
+    )]
+    pub fn surcharged(ctx: Context<PricedQuery>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+
+    #[x402(price = 200_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", tag = "fixed")]
+    pub fn fixed(ctx: Context<PricedQuery>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+}
+
+fn main() {
+    let _: fn(Context<PricedQuery>, u64) -> Result<u64> = priced::surcharged;
+    let _: fn(Context<PricedQuery>) -> Result<u64> = priced::fixed;
+}
@@ -0,0 +1,42 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct BuyItem<'info> {
+    pub payer: Signer<'info>,
+}
+
+// `#[x402_program]` appends `recipient` before `x402_nonce`, unless the
+// handler declares it.
+#[x402_program]
+pub mod marketplace {
+    use super::*;
+
+    #[x402(
+        price = 200_000,
+        recipient_from_arg(authorized_by = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm"),
+        require_nonce = true,
+        tag = "buy_item",
+    )]
+    pub fn buy_item(ctx: Context<BuyItem>) -> Result<Pubkey> {
+        Ok(x402_params.recipient)
+    }
+
+    #[x402(
+        price = 200_000,
+        recipient_from_arg(authorized_by = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm"),
+        tag = "declared",
+    )]
+    pub fn declared(ctx: Context<BuyItem>, recipient: Pubkey) -> Result<Pubkey> {
+        Ok(recipient)
+    }
+}
+
+fn main() {
+    let _: fn(Context<BuyItem>, Pubkey, [u8; 32]) -> Result<Pubkey> = marketplace::buy_item;
+    let _: fn(Context<BuyItem>, Pubkey) -> Result<Pubkey> = marketplace::declared;
+    let _: x402_runtime::authorization::RecipientAuthority = marketplace::BUY_ITEM_X402_RECIPIENT_AUTHORITY;
+}
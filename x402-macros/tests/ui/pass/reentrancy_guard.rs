@@ -0,0 +1,61 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+use x402_runtime::layout::CallAccount;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account(zero_copy)]
+pub struct ReentrancyGuard {
+    pub entered: u8,
+}
+
+#[derive(Accounts)]
+pub struct Query<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut, seeds = [b"x402_reentrancy_guard"], bump)]
+    pub x402_reentrancy_guard: AccountLoader<'info, ReentrancyGuard>,
+}
+
+#[derive(Accounts)]
+pub struct PayInBody<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: credited by the body.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+    #[account(mut, seeds = [b"x402_reentrancy_guard"], bump)]
+    pub x402_reentrancy_guard: AccountLoader<'info, ReentrancyGuard>,
+}
+
+// The early return still clears the guard.
+#[x402(
+    price = 200_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    allow_cpi = true,
+    reentrancy_guard = true,
+    tag = "guarded_query",
+)]
+pub fn guarded_query(ctx: Context<Query>, skip: bool) -> Result<u64> {
+    if skip {
+        return Ok(0);
+    }
+    msg!("{}", ctx.accounts.payer.key());
+    Ok(42)
+}
+
+#[x402(
+    price = 500_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    mode = "balance_delta",
+    reentrancy_guard = true,
+    tag = "guarded_in_body",
+)]
+pub fn guarded_in_body(ctx: Context<PayInBody>) -> Result<()> {
+    msg!("{}", ctx.accounts.payer.key());
+    Ok(())
+}
+
+fn main() {
+    assert!(guarded_query_x402_layout().call_accounts().contains(&CallAccount::ReentrancyGuard));
+    assert!(guarded_in_body_x402_layout().call_accounts().contains(&CallAccount::ReentrancyGuard));
+}
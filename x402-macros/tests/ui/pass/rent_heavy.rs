@@ -0,0 +1,52 @@
+#![allow(unexpected_cfgs)]
+#![deny(deprecated)]
+
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Report {
+    pub data: [u8; 92],
+}
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(init, payer = payer, space = 8 + 92)]
+    pub report: Account<'info, Report>,
+    pub system_program: Program<'info, System>,
+}
+
+// The rent is well under half the price.
+#[x402(
+    price = 10_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "full_report",
+    creates_account_space = 100,
+)]
+pub fn full_report(ctx: Context<Compute>) -> Result<()> {
+    msg!("{}", ctx.accounts.report.key());
+    Ok(())
+}
+
+// The rent is more than the price, on purpose: the report is the product.
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    tag = "cheap_report",
+    creates_account_space = 100,
+    allow_rent_heavy = true,
+)]
+pub fn cheap_report(ctx: Context<Compute>) -> Result<()> {
+    msg!("{}", ctx.accounts.report.key());
+    Ok(())
+}
+
+fn main() {
+    let rent = Rent::default().minimum_balance(100);
+    assert_eq!(FULL_REPORT_X402_CREATED_RENT, rent);
+    assert_eq!(CHEAP_REPORT_X402_CREATED_RENT, rent);
+}
@@ -0,0 +1,52 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct CongestionLevel {
+    pub authority: Pubkey,
+    pub level: u8,
+}
+
+#[derive(Accounts)]
+pub struct PricedQuery<'info> {
+    pub payer: Signer<'info>,
+    pub x402_congestion: Account<'info, CongestionLevel>,
+}
+
+// `#[x402_program]` appends `x402_nonce` last, after `max_acceptable_price`,
+// unless the handler declares it.
+#[x402_program]
+pub mod priced {
+    use super::*;
+
+    #[x402(
+        price = 200_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        congestion_surcharge(account = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm", bps_per_level = 500),
+        emit_event = true,
+        require_nonce = true,
+        tag = "surcharged",
+    )]
+    pub fn surcharged(ctx: Context<PricedQuery>) -> Result<u64> {
+        Ok(x402_payment.amount)
+    }
+
+    #[x402(
+        price = 200_000,
+        address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+        emit_event = true,
+        require_nonce = true,
+        tag = "declared",
+    )]
+    pub fn declared(ctx: Context<PricedQuery>, x402_nonce: [u8; 32]) -> Result<[u8; 32]> {
+        Ok(x402_nonce)
+    }
+}
+
+fn main() {
+    let _: fn(Context<PricedQuery>, u64, [u8; 32]) -> Result<u64> = priced::surcharged;
+    let _: fn(Context<PricedQuery>, [u8; 32]) -> Result<[u8; 32]> = priced::declared;
+}
@@ -0,0 +1,31 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    splits(
+        ("G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", 7000),
+        ("AoAQ6uhexU2bozTYKXDss6EupjKBzXE8dAUavEMHQkgL", 2000),
+        ("AKVUUdeph3XXqu1fHKvtTRZNr2zTDUnfnYwkTppEZZRK", 1000),
+    ),
+    tag = "split_compute",
+)]
+pub fn split_compute(ctx: Context<Compute>) -> Result<()> {
+    msg!("{} legs", x402_revenue_legs.len());
+    Ok(())
+}
+
+const _: () = assert!(SPLIT_COMPUTE_X402_SPLITS.len() == 3);
+const _: () = assert!(SPLIT_COMPUTE_X402_SPLITS[0].bps == 7000);
+
+fn main() {
+    assert_eq!(SPLIT_COMPUTE_X402_PARAMS.recipient, SPLIT_COMPUTE_X402_SPLITS[0].recipient);
+}
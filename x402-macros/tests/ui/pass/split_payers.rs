@@ -0,0 +1,23 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct SharedCompute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa",
+    allow_split_payers = true,
+    max_split_payers = 2
+)]
+pub fn shared_compute(ctx: Context<SharedCompute>) -> Result<()> {
+    msg!("{} payers", x402_contributions.len());
+    Ok(())
+}
+
+fn main() {}
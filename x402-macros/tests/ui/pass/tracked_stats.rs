@@ -0,0 +1,54 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::x402;
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[account]
+pub struct Stats {
+    pub sequence: u64,
+    pub total_payments: u64,
+    pub total_amount: u64,
+}
+
+#[account(zero_copy)]
+pub struct ZeroCopyStats {
+    pub sequence: u64,
+    pub total_payments: u64,
+    pub total_amount: u64,
+    pub commitment: [u8; 32],
+}
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub x402_stats: Account<'info, Stats>,
+}
+
+#[derive(Accounts)]
+pub struct ZeroCopyCompute<'info> {
+    pub payer: Signer<'info>,
+    #[account(mut)]
+    pub x402_stats: AccountLoader<'info, ZeroCopyStats>,
+}
+
+#[x402(price = 1_000_000, address = "G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", track_stats = true, tag = "compute")]
+pub fn compute(ctx: Context<Compute>) -> Result<()> {
+    msg!("payment {}", x402_sequence);
+    Ok(())
+}
+
+#[x402(
+    price = 1_000_000,
+    address = "G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp",
+    track_stats = true,
+    commitment_log = true,
+    tag = "zc_compute",
+)]
+pub fn zero_copy_compute(ctx: Context<ZeroCopyCompute>) -> Result<()> {
+    msg!("payment {}", x402_sequence);
+    Ok(())
+}
+
+fn main() {}
@@ -0,0 +1,117 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use x402_macros::{x402, x402_program};
+use x402_runtime::layout::{CallAccount, Requirement, TxLayout};
+use x402_runtime::{Currency, Mode};
+
+declare_id!("9xwTdtTvo4h1tZWakCz3JPSpi4ePht9VHzujtr2Dywb1");
+
+#[derive(Accounts)]
+pub struct Compute<'info> {
+    pub payer: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct PayInBody<'info> {
+    pub payer: Signer<'info>,
+    /// CHECK: credited by the body.
+    #[account(mut)]
+    pub x402_recipient: UncheckedAccount<'info>,
+}
+
+// Each layout follows the options of its own handler.
+#[x402_program]
+pub mod layouts {
+    use super::*;
+
+    #[x402(price = 1_000_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", max_lookback = 3, tag = "scan")]
+    pub fn scan(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(
+        price = 1_000_001,
+        splits(
+            ("G2fMs1QMrTkxYgmGCBaXFMfy8npNMKKV4cgHVqbW9hxp", 7000),
+            ("AoAQ6uhexU2bozTYKXDss6EupjKBzXE8dAUavEMHQkgL", 3000),
+        ),
+        tag = "split",
+    )]
+    pub fn split(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(
+        price = 200_000,
+        recipient_from_arg(authorized_by = "EwfCCfvWhcMYVYGahiqQcDDNPiQtSjzGTZDFcrbUPEqm"),
+        allow_split_payers = true,
+        max_split_payers = 3,
+        tag = "sale",
+    )]
+    pub fn sale(ctx: Context<Compute>) -> Result<()> {
+        Ok(())
+    }
+
+    #[x402(price = 500_000, address = "ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa", mode = "balance_delta", tag = "in_body")]
+    pub fn in_body(ctx: Context<PayInBody>) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    let recipient = layouts::SCAN_X402_PARAMS.recipient;
+    assert_eq!(
+        layouts::scan_x402_layout(),
+        TxLayout {
+            mode: Mode::PrecedingInstruction,
+            requirements: vec![
+                Requirement::Transfer {
+                    to: Some(recipient),
+                    bps: 10_000,
+                    min: 1_000_000,
+                    currency: Currency::Sol,
+                    max_payers: 1,
+                },
+                Requirement::IgnorableOk { others: 2 },
+                Requirement::ThisInstruction {
+                    accounts: vec![CallAccount::InstructionsSysvar],
+                },
+            ],
+        }
+    );
+
+    // The rounding remainder is on the first leg, as the check requires.
+    let legs: Vec<(Option<Pubkey>, u16, u64)> = layouts::split_x402_layout()
+        .requirements
+        .iter()
+        .filter_map(|requirement| match *requirement {
+            Requirement::Transfer { to, bps, min, .. } => Some((to, bps, min)),
+            _ => None,
+        })
+        .collect();
+    let shares = layouts::SPLIT_X402_SPLITS;
+    assert_eq!(
+        legs,
+        [(Some(shares[0].recipient), 7000, 700_001), (Some(shares[1].recipient), 3000, 300_000)]
+    );
+
+    let sale = layouts::sale_x402_layout();
+    assert_eq!(
+        sale.requirements[0],
+        Requirement::RecipientAuthorization {
+            authority: layouts::SALE_X402_RECIPIENT_AUTHORITY.authority,
+        }
+    );
+    assert!(matches!(sale.requirements[1], Requirement::Transfer { to: None, max_payers: 3, .. }));
+    assert_eq!(sale.requirements[2], Requirement::IgnorableOk { others: 0 });
+
+    assert_eq!(
+        layouts::in_body_x402_layout(),
+        TxLayout {
+            mode: Mode::BalanceDelta,
+            requirements: vec![Requirement::ThisInstruction {
+                accounts: vec![CallAccount::Recipient],
+            }],
+        }
+    );
+}
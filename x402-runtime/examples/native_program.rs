@@ -0,0 +1,114 @@
+//! A native (non-Anchor) program gated by the x402 verifier.
+//!
+//! `process_instruction` requires a SOL payment of `PARAMS` in the
+//! instruction before it, as `#[x402]` does for Anchor handlers. It builds
+//! without Anchor:
+//!
+//! ```text
+//! cargo build-sbf --manifest-path x402-runtime/Cargo.toml --no-default-features --example native_program
+//! cargo test -p x402-runtime --no-default-features --example native_program
+//! ```
+//!
+//! Clients pass the instructions sysvar among the accounts; failures come
+//! back as `ProgramError::Custom` with the same codes Anchor programs use.
+
+#![allow(unexpected_cfgs)]
+
+use solana_program::account_info::AccountInfo;
+use solana_program::entrypoint::ProgramResult;
+use solana_program::msg;
+use solana_program::program_error::ProgramError;
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+use x402_runtime::{verify_sol_payment, Currency, ScanOptions, X402Params};
+
+pub const PARAMS: X402Params = X402Params {
+    price: 1_000_000,
+    recipient: pubkey!("ESPyXCB93a6CvrAE2btofpgXAswf4oE3NuziBsHVCAZa"),
+    currency: Currency::Sol,
+};
+
+#[cfg(target_os = "solana")]
+solana_program::entrypoint!(process_instruction);
+
+pub fn process_instruction(program_id: &Pubkey, accounts: &[AccountInfo], _data: &[u8]) -> ProgramResult {
+    let payment = verify_sol_payment(accounts, program_id, &PARAMS, &ScanOptions::DEFAULT)
+        .map_err(|err| ProgramError::Custom(err.into()))?;
+
+    msg!("native: {} paid {} lamports", payment.payer, payment.amount);
+    Ok(())
+}
+
+fn main() {}
+
+#[cfg(test)]
+mod tests {
+    use solana_program::instruction::{AccountMeta, Instruction};
+    use solana_program::system_instruction::transfer;
+    use solana_program::sysvar::{self, instructions};
+    use x402_runtime::X402Error;
+
+    use super::*;
+
+    const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+    /// Instructions sysvar data for `payment` followed by the gated call.
+    fn sysvar_data(payment: Instruction, payer: &Pubkey) -> Vec<u8> {
+        let call = Instruction {
+            program_id: PROGRAM_ID,
+            accounts: vec![AccountMeta::new_readonly(*payer, true)],
+            data: vec![],
+        };
+        let all = [payment, call];
+        let borrowed: Vec<_> = all
+            .iter()
+            .map(|ix| instructions::BorrowedInstruction {
+                program_id: &ix.program_id,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(|meta| instructions::BorrowedAccountMeta {
+                        pubkey: &meta.pubkey,
+                        is_signer: meta.is_signer,
+                        is_writable: meta.is_writable,
+                    })
+                    .collect(),
+                data: &ix.data,
+            })
+            .collect();
+        let mut data = instructions::construct_instructions_data(&borrowed);
+        instructions::store_current_index(&mut data, 1);
+        data
+    }
+
+    fn run(payment: Instruction, payer: &Pubkey) -> ProgramResult {
+        let (key, owner, mut lamports) = (instructions::ID, sysvar::ID, 0);
+        let mut data = sysvar_data(payment, payer);
+        let sysvar = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+        process_instruction(&PROGRAM_ID, &[sysvar], &[])
+    }
+
+    fn code(err: X402Error) -> ProgramError {
+        ProgramError::Custom(err.into())
+    }
+
+    #[test]
+    fn accepts_the_payment() {
+        let payer = Pubkey::new_unique();
+        run(transfer(&payer, &PARAMS.recipient, PARAMS.price), &payer).unwrap();
+    }
+
+    #[test]
+    fn reports_x402_codes() {
+        let payer = Pubkey::new_unique();
+        let err = run(transfer(&payer, &PARAMS.recipient, PARAMS.price - 1), &payer).unwrap_err();
+        assert_eq!(err, code(X402Error::InsufficientPayment));
+        assert_eq!(err, ProgramError::Custom(7004));
+
+        let err = run(transfer(&payer, &Pubkey::new_unique(), PARAMS.price), &payer).unwrap_err();
+        assert_eq!(err, code(X402Error::WrongRecipient));
+
+        let err = process_instruction(&PROGRAM_ID, &[], &[]).unwrap_err();
+        assert_eq!(err, code(X402Error::SysvarMissing));
+    }
+}
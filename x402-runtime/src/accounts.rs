@@ -0,0 +1,123 @@
+//! Accounts structs declared with `#[x402_accounts(mode = "...")]`.
+//!
+//! The attribute injects the accounts a payment mode needs into the struct,
+//! so Anchor validates them and they appear in the IDL, and implements
+//! [`X402Accounts`] for it. Every `#[x402]` handler probes its accounts
+//! struct through [`ModeProbe`]: for a declared struct the handler's mode
+//! must equal the declared one or the program does not compile, and the
+//! declared instructions sysvar is used in place of the remaining accounts.
+//! Undeclared structs keep working as before, with the sysvar passed as the
+//! last remaining account (see [`split_sysvar`]).
+//!
+//! With `strict_accounts = true` on `#[x402]` the struct must be declared
+//! in the handler's mode, the sysvar is read from its `x402_instructions`
+//! field alone, and any remaining account fails the call with
+//! `UnexpectedRemainingAccounts`.
+//!
+//! Otherwise a handler may take a list of accounts of its own before the
+//! sysvar, which the body gets as `x402_user_accounts`: every remaining
+//! account but the last, or all of them when the struct declares the
+//! sysvar or the handler reads none. `sysvar_position = "any"` searches
+//! every remaining account for the sysvar instead, as earlier versions
+//! did, and leaves them all in `x402_user_accounts`.
+
+use std::marker::PhantomData;
+
+use anchor_lang::prelude::*;
+
+use crate::error::X402Error;
+
+/// Marker for `mode = "preceding_instruction"`.
+pub struct PrecedingInstruction;
+
+/// Marker for `mode = "balance_delta"`.
+pub struct BalanceDelta;
+
+/// Implemented by `#[x402_accounts]`.
+pub trait X402Accounts<'info> {
+    /// [`PrecedingInstruction`] or [`BalanceDelta`].
+    type Mode;
+
+    /// The injected `x402_instructions` account, when the mode has one.
+    fn instructions_sysvar(&self) -> Option<AccountInfo<'info>>;
+}
+
+/// Holds when the declared mode `Self` is `M`. A mismatch is reported as
+/// "the trait bound `BalanceDelta: PaymentModeIs<PrecedingInstruction>`
+/// is not satisfied".
+pub trait PaymentModeIs<M> {}
+
+impl<M> PaymentModeIs<M> for M {}
+
+/// Selects [`DeclaredMode`] for structs implementing [`X402Accounts`] and
+/// [`UndeclaredMode`] otherwise, through auto-ref method resolution:
+/// `(&ModeProbe::of(accounts)).require_mode::<Mode>(accounts)`.
+pub struct ModeProbe<T>(PhantomData<T>);
+
+impl<T> ModeProbe<T> {
+    pub fn of(_accounts: &T) -> Self {
+        ModeProbe(PhantomData)
+    }
+}
+
+pub trait DeclaredMode<'info> {
+    type Accounts: X402Accounts<'info>;
+
+    /// Fails to compile unless `M` is the declared mode; returns the
+    /// declared instructions sysvar.
+    fn require_mode<M>(&self, accounts: &Self::Accounts) -> Option<AccountInfo<'info>>
+    where
+        <Self::Accounts as X402Accounts<'info>>::Mode: PaymentModeIs<M>,
+    {
+        accounts.instructions_sysvar()
+    }
+}
+
+impl<'info, T: X402Accounts<'info>> DeclaredMode<'info> for ModeProbe<T> {
+    type Accounts = T;
+}
+
+pub trait UndeclaredMode<'info> {
+    type Accounts;
+
+    fn require_mode<M>(&self, _accounts: &Self::Accounts) -> Option<AccountInfo<'info>> {
+        None
+    }
+}
+
+impl<'info, T> UndeclaredMode<'info> for &ModeProbe<T> {
+    type Accounts = T;
+}
+
+/// The declared instructions sysvar, for `strict_accounts` handlers. Unlike
+/// [`ModeProbe`], it does not compile unless the struct is declared with
+/// `#[x402_accounts]` in mode `M`.
+pub fn declared_sysvar<'info, M, T>(accounts: &T) -> Option<AccountInfo<'info>>
+where
+    T: X402Accounts<'info>,
+    T::Mode: PaymentModeIs<M>,
+{
+    accounts.instructions_sysvar()
+}
+
+/// Rejects any remaining account under `strict_accounts`, where every
+/// account must be declared in the accounts struct.
+pub fn forbid_remaining_accounts(remaining_accounts: &[AccountInfo]) -> Result<()> {
+    if !remaining_accounts.is_empty() {
+        msg!(
+            "x402: strict accounts forbids remaining accounts, got {}",
+            remaining_accounts.len()
+        );
+        return err!(X402Error::UnexpectedRemainingAccounts);
+    }
+    Ok(())
+}
+
+/// Splits `remaining_accounts` under `sysvar_position = "last"`: the
+/// handler's own accounts, and the last account, the only one the check
+/// reads the instructions sysvar from. Either may be empty.
+pub fn split_sysvar<'a, 'info>(
+    remaining_accounts: &'a [AccountInfo<'info>],
+) -> (&'a [AccountInfo<'info>], &'a [AccountInfo<'info>]) {
+    remaining_accounts.split_at(remaining_accounts.len().saturating_sub(1))
+}
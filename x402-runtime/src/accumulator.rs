@@ -0,0 +1,137 @@
+//! Prices paid in parts, accumulated toward the call over earlier
+//! transactions.
+//!
+//! A payer saving toward an `accumulated = true` instruction pays any
+//! amount at a time: a SOL transfer to the instruction's recipient,
+//! immediately before a call to the program's own accumulating instruction,
+//! which checks it with [`verify_contribution`] and adds it with [`credit`]
+//! to the payer's accumulator for that instruction: the PDA of
+//! [`ACCUMULATOR_SEED`], the instruction's tag and the payer, whose type
+//! has a `balance: u64` field. The priced instruction then reads no
+//! transfer: the expansion debits the price from the accounts struct's
+//! mutable `x402_accumulator` with [`debit`], failing with
+//! `InsufficientAccumulation` while the balance is short, and whatever the
+//! balance holds beyond the price is left for the next call.
+//!
+//! Both sides check the account against the address derived from the tag,
+//! so savings toward one instruction never pay for another. An instruction
+//! whose tag changed with `accepts_tags(...)` debits with
+//! [`debit_accepted`] instead, which also takes the accumulators of its
+//! earlier tags until the migration window closes. The lamports
+//! went to the recipient as each part was paid; the accumulator only counts
+//! them, and nothing is refunded from it.
+
+use anchor_lang::prelude::*;
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+use x402_common::seeds::accumulator_address;
+
+use crate::balance_delta::CURRENT_INSTRUCTION;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::trampoline::require_top_level_call;
+use crate::verify::{require_sol, verify_payment, Currency, VerifiedPayment, X402Params};
+
+pub use x402_common::seeds::ACCUMULATOR_SEED;
+
+/// Checks the SOL payment to `params.recipient` before the accumulating
+/// `instruction`, of any amount above zero, among `remaining_accounts` as
+/// [`verify_payment`] does. The instruction must run at transaction level,
+/// or the scan would read its caller's transaction.
+pub fn verify_contribution(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    instruction: &str,
+) -> Result<VerifiedPayment> {
+    require_top_level_call(instruction)?;
+    require_sol(params)?;
+    verify_payment(remaining_accounts, program_id, &X402Params { price: 1, ..*params })
+}
+
+/// Adds `contribution` to `balance`, the accumulator at `account` of the
+/// contribution's payer toward the instruction tagged `tag`, returning the
+/// new balance.
+pub fn credit(
+    account: &Pubkey,
+    program_id: &Pubkey,
+    tag: &str,
+    balance: &mut u64,
+    contribution: &VerifiedPayment,
+) -> Result<u64> {
+    require_accumulator(account, program_id, tag, &contribution.payer)?;
+    *balance = balance.checked_add(contribution.amount).ok_or_else(|| {
+        msg!("x402: accumulator {} overflowed", account);
+        error!(X402Error::StatsOverflow)
+    })?;
+    Ok(*balance)
+}
+
+/// Debits the price from `balance`, the accumulator at `account` of the
+/// signing `payer` toward the instruction tagged `tag`, leaving the rest
+/// for later calls.
+pub fn debit(
+    account: &Pubkey,
+    program_id: &Pubkey,
+    tag: &str,
+    payer: &AccountInfo,
+    balance: &mut u64,
+    params: &X402Params,
+) -> Result<VerifiedPayment> {
+    require_sol(params)?;
+    require_accumulator(account, program_id, tag, payer.key)?;
+    if !payer.is_signer {
+        msg!("x402: payer {} of the accumulated price did not sign", payer.key);
+        return err!(X402Error::AccumulatorMismatch);
+    }
+    let Some(left) = balance.checked_sub(params.price) else {
+        let (mut balance_text, mut price_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+        msg!(
+            "x402: accumulator {} holds {}, {} needed",
+            account,
+            fmt_amount(*balance, Currency::Sol, &mut balance_text),
+            fmt_amount(params.price, Currency::Sol, &mut price_text)
+        );
+        return err!(X402Error::InsufficientAccumulation);
+    };
+
+    *balance = left;
+    Ok(VerifiedPayment {
+        amount: params.price,
+        payer: *payer.key,
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index: CURRENT_INSTRUCTION,
+    })
+}
+
+/// [`debit`] from the accumulator toward whichever of `tags` `account`
+/// belongs to: the instruction's own tag and the aliases it still accepts,
+/// `TagAliases::accepted`.
+pub fn debit_accepted(
+    account: &Pubkey,
+    program_id: &Pubkey,
+    tags: &[&str],
+    payer: &AccountInfo,
+    balance: &mut u64,
+    params: &X402Params,
+) -> Result<VerifiedPayment> {
+    let Some(tag) = tags.iter().find(|tag| keys_eq(&accumulator_address(program_id, tag, payer.key), account)) else {
+        msg!("x402: accumulator account {} is not the payer's for any of {:?}", account, tags);
+        return err!(X402Error::AccumulatorMismatch);
+    };
+    debit(account, program_id, tag, payer, balance, params)
+}
+
+fn require_accumulator(account: &Pubkey, program_id: &Pubkey, tag: &str, payer: &Pubkey) -> Result<()> {
+    let expected = accumulator_address(program_id, tag, payer);
+    if !keys_eq(account, &expected) {
+        msg!(
+            "x402: accumulator account is {} instead of {} for \"{}\"",
+            account,
+            expected,
+            tag
+        );
+        return err!(X402Error::AccumulatorMismatch);
+    }
+    Ok(())
+}
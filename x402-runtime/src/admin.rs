@@ -0,0 +1,127 @@
+//! Who may change a program's x402 configuration.
+//!
+//! The admin of a config is settled when it is created: the initializer
+//! must be the program's upgrade authority, proven with
+//! [`assert_upgrade_authority`] against the program's `ProgramData`
+//! account, instead of whoever happens to initialize first. The admin then
+//! holds the config's `authority` and may hand it over or delegate
+//! day-to-day changes to an `operator` key, both stored in the config; see
+//! [`X402Admin`].
+//!
+//! A program that is not upgradeable has no such authority, so its config
+//! cannot be initialized this way.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+
+use crate::cmp::{expect_key, keys_eq};
+use crate::error::X402Error;
+
+/// `UpgradeableLoaderState::Program`'s bincode tag: a little-endian `u32`
+/// followed by the program data address.
+const PROGRAM_STATE_TAG: [u8; 4] = [2, 0, 0, 0];
+
+/// Requires `signer` to have signed and to be the upgrade authority of
+/// `program`, the account of `program_id`, as recorded in `program_data`,
+/// its `ProgramData` account.
+///
+/// Fails with `ProgramMismatch` when `program` is another program, whose
+/// authority could otherwise pass for this one's,
+/// `ProgramNotUpgradeable` when `program` is not owned by the upgradeable
+/// loader or its authority was removed, and `ProgramDataMismatch` when
+/// `program_data` is not `program`'s.
+pub fn assert_upgrade_authority(
+    program_id: &Pubkey,
+    program: &AccountInfo,
+    program_data: &AccountInfo,
+    signer: &AccountInfo,
+) -> Result<()> {
+    if !keys_eq(program.key, program_id) {
+        msg!("x402: {} is not the program {}", program.key, program_id);
+        return err!(X402Error::ProgramMismatch);
+    }
+    let expected = programdata_address(program)?;
+    if !keys_eq(program_data.key, &expected) || !keys_eq(program_data.owner, &bpf_loader_upgradeable::ID) {
+        msg!("x402: {} is not the program data of {}, which is {}", program_data.key, program.key, expected);
+        return err!(X402Error::ProgramDataMismatch);
+    }
+
+    let data = program_data.try_borrow_data()?;
+    let state = ProgramData::try_deserialize(&mut &data[..]).map_err(|_| {
+        msg!("x402: {} does not hold program data", program_data.key);
+        error!(X402Error::ProgramDataMismatch)
+    })?;
+    let authority = state.upgrade_authority_address.as_ref();
+    expect_key(authority, signer.key, X402Error::ProgramNotUpgradeable, X402Error::NotUpgradeAuthority).inspect_err(
+        |_| match authority {
+            Some(authority) => msg!("x402: {} is not the upgrade authority {}", signer.key, authority),
+            None => msg!("x402: program {} is immutable and has no upgrade authority", program.key),
+        },
+    )?;
+    if !signer.is_signer {
+        msg!("x402: upgrade authority {} did not sign", signer.key);
+        return err!(X402Error::NotUpgradeAuthority);
+    }
+
+    Ok(())
+}
+
+/// The `ProgramData` address recorded in `program`, an upgradeable program
+/// account.
+fn programdata_address(program: &AccountInfo) -> Result<Pubkey> {
+    if !keys_eq(program.owner, &bpf_loader_upgradeable::ID) {
+        msg!("x402: program {} is owned by {} and cannot be upgraded", program.key, program.owner);
+        return err!(X402Error::ProgramNotUpgradeable);
+    }
+    let data = program.try_borrow_data()?;
+    match data.get(..36) {
+        Some(state) if state[..4] == PROGRAM_STATE_TAG => Ok(Pubkey::try_from(&state[4..]).unwrap_or_default()),
+        _ => {
+            msg!("x402: {} is not an upgradeable program account", program.key);
+            err!(X402Error::ProgramNotUpgradeable)
+        }
+    }
+}
+
+/// The keys a config stores for administering it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct X402Admin {
+    /// Set to the upgrade authority at initialization; may hand itself
+    /// over and appoint the `operator`.
+    pub authority: Pubkey,
+    /// May make the operational changes, such as rotating the recipient.
+    /// Equal to `authority` while nothing is delegated.
+    pub operator: Pubkey,
+}
+
+impl X402Admin {
+    /// Requires `signer` to be the authority and to have signed.
+    pub fn require_authority(&self, signer: &AccountInfo) -> Result<()> {
+        if !keys_eq(signer.key, &self.authority) || !signer.is_signer {
+            msg!(
+                "x402: {} (signer: {}) is not the config's authority {}",
+                signer.key,
+                signer.is_signer,
+                self.authority
+            );
+            return err!(X402Error::NotX402Admin);
+        }
+        Ok(())
+    }
+
+    /// Requires `signer` to be the operator or the authority, and to have
+    /// signed.
+    pub fn require_operator(&self, signer: &AccountInfo) -> Result<()> {
+        if !(keys_eq(signer.key, &self.operator) || keys_eq(signer.key, &self.authority)) || !signer.is_signer {
+            msg!(
+                "x402: {} (signer: {}) is neither the config's operator {} nor its authority {}",
+                signer.key,
+                signer.is_signer,
+                self.operator,
+                self.authority
+            );
+            return err!(X402Error::NotX402Admin);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,123 @@
+//! Recipients chosen per call.
+//!
+//! With `recipient_from_arg(authorized_by = "<key>")` on `#[x402]`, the
+//! handler takes a `recipient: Pubkey` argument, which `#[x402_program]`
+//! appends, and is paid to that recipient rather than a fixed address: a
+//! marketplace sells each item for its seller. Since the caller picks the
+//! argument, the transaction must also carry an Ed25519 program
+//! instruction, before the priced one, in which the `authorized_by` key
+//! signed a [`RecipientAuthorization`] of this recipient at the price,
+//! unexpired. The runtime fails the transaction when that signature is
+//! invalid; [`RecipientAuthority::verify`] reads the signed message back
+//! from the instructions sysvar. The payment scan then requires the
+//! preceding payment to go to the recipient, and the payment event
+//! records it. The key is emitted as `<HANDLER>_X402_RECIPIENT_AUTHORITY`.
+//! The signed price is fixed, so a congestion surcharge or an experiment
+//! cannot be combined with it.
+//!
+//! An authorization is not consumed: until it expires it lets anyone pay
+//! that recipient that price.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::ed25519_program;
+use anchor_lang::solana_program::sysvar::instructions;
+use x402_common::authorization::signed_messages;
+pub use x402_common::authorization::{ed25519_instruction, RecipientAuthorization, AUTHORIZATION_MESSAGE_LEN};
+
+use crate::clock::{self, require_valid_expiry};
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::native::{load_sysvar, program_at};
+use crate::verify::X402Params;
+
+/// The key authorizing a handler's recipients, emitted next to it as
+/// `<HANDLER>_X402_RECIPIENT_AUTHORITY`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecipientAuthority {
+    pub authority: Pubkey,
+}
+
+impl RecipientAuthority {
+    /// `params` paid to `recipient`, once an Ed25519 instruction before the
+    /// priced one shows `authority` authorizing `recipient` at
+    /// `params.price`, unexpired by the clock.
+    pub fn verify(
+        &self,
+        remaining_accounts: &[AccountInfo],
+        params: &X402Params,
+        recipient: &Pubkey,
+    ) -> Result<X402Params> {
+        let now = clock::now().inspect_err(|_| msg!("x402: no time to check the recipient authorization against"))?;
+        self.verify_at(remaining_accounts, params, recipient, now)
+    }
+
+    /// [`verify`](Self::verify) at unix time `now`.
+    ///
+    /// Fails with `InvalidExpiry` when a matching authorization expires
+    /// before 1970 or over 100 years after `now`, with
+    /// `RecipientAuthorizationExpired` when the only matching
+    /// authorizations expired at or before `now`, and with
+    /// `RecipientNotAuthorized` when none names this recipient and price.
+    pub fn verify_at(
+        &self,
+        remaining_accounts: &[AccountInfo],
+        params: &X402Params,
+        recipient: &Pubkey,
+        now: i64,
+    ) -> Result<X402Params> {
+        let (ix_sysvar, current_index) = load_sysvar(remaining_accounts)?;
+
+        let mut expired: Option<i64> = None;
+        let mut invalid: Option<Error> = None;
+        let mut other: Option<RecipientAuthorization> = None;
+        for index in 0..current_index {
+            if !keys_eq(&program_at(ix_sysvar, index)?, &ed25519_program::ID) {
+                continue;
+            }
+            let ix = instructions::load_instruction_at_checked(index as usize, ix_sysvar).map_err(|err| {
+                msg!("x402: loading instruction {} failed: {}", index, err);
+                error!(X402Error::SysvarUnavailable)
+            })?;
+            for (signer, message) in signed_messages(&ix.data) {
+                let Some(authorization) = RecipientAuthorization::from_message(message) else {
+                    continue;
+                };
+                if !keys_eq(&signer, &self.authority) {
+                    continue;
+                }
+                if !keys_eq(&authorization.recipient, recipient) || authorization.price != params.price {
+                    other = Some(authorization);
+                } else if let Err(err) = require_valid_expiry(authorization.expiry, now) {
+                    invalid = Some(err);
+                } else if authorization.expiry <= now {
+                    expired = Some(authorization.expiry);
+                } else {
+                    return Ok(X402Params {
+                        recipient: *recipient,
+                        ..*params
+                    });
+                }
+            }
+        }
+
+        if let Some(err) = invalid {
+            return Err(err);
+        }
+        if let Some(expiry) = expired {
+            msg!("x402: the authorization of {} expired at {}, now {}", recipient, expiry, now);
+            return err!(X402Error::RecipientAuthorizationExpired);
+        }
+        match other {
+            Some(other) => msg!(
+                "x402: {} authorized {} at {}, not {} at {}",
+                self.authority,
+                other.recipient,
+                other.price,
+                recipient,
+                params.price
+            ),
+            None => msg!("x402: no Ed25519 instruction carries an authorization signed by {}", self.authority),
+        }
+        err!(X402Error::RecipientNotAuthorized)
+    }
+}
@@ -0,0 +1,89 @@
+//! Payments made inside the priced instruction itself.
+//!
+//! With `mode = "balance_delta"` there is no preceding transfer to read.
+//! The expansion snapshots the recipient's lamports before the handler
+//! body runs and, once the body returns `Ok`, requires the balance to have
+//! grown by at least the price. The body pays with [`pay`] (or any
+//! transfer of its own) into the accounts struct's writable
+//! `x402_recipient`.
+//!
+//! The check runs after the body, so the body gets no `x402_payment`. The
+//! body runs in a closure, so an early `return` or `?` that returns `Ok`
+//! still reaches the check. Writing to `ctx.accounts` before calling a
+//! `pay` or `transfer` function is reported as a warning at the write; the
+//! check is syntactic, so writes in called functions go unnoticed.
+
+use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::verify::{require_sol, Currency, VerifiedPayment, X402Params};
+
+/// `VerifiedPayment::ix_index` of a balance delta payment, which is the
+/// priced instruction itself rather than one before it.
+pub const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+/// Checks `recipient` is the configured, writable recipient and returns its
+/// balance before the body runs.
+pub fn snapshot(recipient: &AccountInfo, params: &X402Params) -> Result<u64> {
+    require_sol(params)?;
+    if !keys_eq(recipient.key, &params.recipient) {
+        msg!("x402: recipient account is {} instead of {}", recipient.key, params.recipient);
+        return err!(X402Error::WrongRecipient);
+    }
+    if !recipient.is_writable {
+        msg!("x402: recipient account {} must be writable", recipient.key);
+        return err!(X402Error::WrongRecipient);
+    }
+    Ok(recipient.lamports())
+}
+
+/// Requires `recipient` to have gained at least the price since
+/// [`snapshot`] returned `before`.
+pub fn verify_delta(
+    recipient: &AccountInfo,
+    before: u64,
+    payer: &Pubkey,
+    params: &X402Params,
+) -> Result<VerifiedPayment> {
+    let received = recipient.lamports().saturating_sub(before);
+    if received < params.price {
+        let (mut received_text, mut price_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+        msg!(
+            "x402: recipient gained {} but the price is {}",
+            fmt_amount(received, Currency::Sol, &mut received_text),
+            fmt_amount(params.price, Currency::Sol, &mut price_text)
+        );
+        return err!(X402Error::InsufficientPayment);
+    }
+
+    Ok(VerifiedPayment {
+        amount: received,
+        payer: *payer,
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index: CURRENT_INSTRUCTION,
+    })
+}
+
+/// Transfers `lamports` from `payer` to `recipient` through the System
+/// program, for handler bodies in balance delta mode.
+pub fn pay<'info>(
+    payer: AccountInfo<'info>,
+    recipient: AccountInfo<'info>,
+    system_program: AccountInfo<'info>,
+    lamports: u64,
+) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            system_program,
+            Transfer {
+                from: payer,
+                to: recipient,
+            },
+        ),
+        lamports,
+    )
+}
@@ -0,0 +1,53 @@
+//! Time for the checks that depend on it.
+//!
+//! Every x402 read of the clock goes through [`get`], [`now`] or [`slot`],
+//! which fail with `ClockUnavailable` where the `Clock` sysvar cannot be
+//! read (some test harnesses, off-chain callers), instead of an error
+//! naming something else.
+//!
+//! Without a clock, the checks guarding a payment fail closed: recipient
+//! authorizations, the congestion grace window, spending caps, payment
+//! ledgers, the commitment log, group budgets and tag migration windows
+//! fail the call rather than guess a time.
+//! The latency event fails open: it only reports on a payment already
+//! verified, so it is skipped with a log.
+//!
+//! Expiries a caller supplies are also bounded: one that is negative, or
+//! more than [`MAX_HORIZON_SECS`] after the current time, is treated as a
+//! mistake rather than as "never".
+
+use anchor_lang::prelude::*;
+
+use crate::error::X402Error;
+
+/// How far ahead an expiry may lie: 100 years of 365 days.
+pub const MAX_HORIZON_SECS: i64 = 100 * 365 * 24 * 60 * 60;
+
+/// The `Clock` sysvar.
+pub fn get() -> Result<Clock> {
+    Clock::get().map_err(|err| {
+        msg!("x402: Clock sysvar unavailable: {}", err);
+        error!(X402Error::ClockUnavailable)
+    })
+}
+
+/// The current unix time, in seconds.
+pub fn now() -> Result<i64> {
+    get().map(|clock| clock.unix_timestamp)
+}
+
+/// The current slot.
+pub fn slot() -> Result<u64> {
+    get().map(|clock| clock.slot)
+}
+
+/// Fails with `InvalidExpiry` unless `expires_at` is a unix time from 0 to
+/// [`MAX_HORIZON_SECS`] after `now`. Expiries already past are valid; it is
+/// the caller's to reject them.
+pub fn require_valid_expiry(expires_at: i64, now: i64) -> Result<()> {
+    if expires_at < 0 || expires_at > now.saturating_add(MAX_HORIZON_SECS) {
+        msg!("x402: expiry {} is negative or over 100 years after {}", expires_at, now);
+        return err!(X402Error::InvalidExpiry);
+    }
+    Ok(())
+}
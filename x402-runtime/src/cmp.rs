@@ -0,0 +1,35 @@
+//! The comparison behind every key check.
+//!
+//! A key read from an instruction's accounts may be absent, and an absent
+//! key is its own failure rather than a mismatch, so [`expect_key`] takes
+//! one error for each. Keys are compared in constant time: how long a
+//! check takes says nothing about how much of a key matched.
+
+use solana_program::pubkey::Pubkey;
+
+/// Whether `a` and `b` are the same key, reading every byte of both
+/// whatever the first difference.
+pub fn keys_eq(a: &Pubkey, b: &Pubkey) -> bool {
+    let diff = a.as_ref().iter().zip(b.as_ref()).fold(0u8, |diff, (a, b)| diff | (a ^ b));
+    std::hint::black_box(diff) == 0
+}
+
+/// Requires `actual` to be `expected`: `missing_err` when there is no key
+/// at all, `mismatch_err` when there is another one.
+pub fn expect_key<E>(actual: Option<&Pubkey>, expected: &Pubkey, missing_err: E, mismatch_err: E) -> Result<(), E> {
+    match actual {
+        None => Err(missing_err),
+        Some(actual) if keys_eq(actual, expected) => Ok(()),
+        Some(_) => Err(mismatch_err),
+    }
+}
+
+/// Whether two optional mints agree, where `None` is SOL: both SOL, or the
+/// same mint under [`keys_eq`].
+pub fn mints_eq(a: Option<&Pubkey>, b: Option<&Pubkey>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => keys_eq(a, b),
+        (None, None) => true,
+        _ => false,
+    }
+}
@@ -0,0 +1,47 @@
+//! The payment commitment log.
+//!
+//! With `commitment_log = true` on `#[x402]` (alongside
+//! `track_stats = true`), each verified payment also folds into the
+//! `commitment: [u8; 32]` field of the handler's `x402_stats` account, as
+//! `x402_common::commitment::next_commitment` defines, and emits
+//! `X402PaymentCommittedEvent` with the value before and after. The chain
+//! starts from [`GENESIS_COMMITMENT`] and can be checked from the events
+//! alone.
+
+use anchor_lang::prelude::*;
+use x402_common::commitment::next_commitment;
+use x402_common::events::X402PaymentCommittedEvent;
+
+use crate::clock;
+use crate::verify::VerifiedPayment;
+
+pub use x402_common::commitment::GENESIS_COMMITMENT;
+
+/// Folds `payment`, made for the instruction tagged `instruction_tag`, into
+/// `commitment` in the current slot and emits the event.
+pub fn record(commitment: &mut [u8; 32], payment: &VerifiedPayment, sequence: u64, instruction_tag: &str) -> Result<()> {
+    let slot = clock::slot().inspect_err(|_| msg!("x402: no slot to commit the payment in"))?;
+    emit!(record_at(commitment, payment, sequence, instruction_tag, slot));
+    Ok(())
+}
+
+/// [`record`] in `slot`, returning the event instead of emitting it.
+pub fn record_at(
+    commitment: &mut [u8; 32],
+    payment: &VerifiedPayment,
+    sequence: u64,
+    instruction_tag: &str,
+    slot: u64,
+) -> X402PaymentCommittedEvent {
+    let previous_commitment = *commitment;
+    *commitment = next_commitment(&previous_commitment, &payment.payer, payment.amount, slot, instruction_tag);
+    X402PaymentCommittedEvent {
+        payer: payment.payer,
+        amount: payment.amount,
+        slot,
+        instruction_tag: instruction_tag.to_string(),
+        sequence,
+        previous_commitment,
+        commitment: *commitment,
+    }
+}
@@ -0,0 +1,20 @@
+//! Markers the `#[x402]` expansion names to check, when the program
+//! compiles, that this runtime implements the verification the macro was
+//! written against.
+//!
+//! The macro and this crate are versioned separately, and the expansion
+//! calls into this crate to verify payments. Paired with an older runtime,
+//! a newer macro would link the older checks and run them silently. Each
+//! marker stands for one contract between the two: the macro names the
+//! latest it relies on, and the runtime keeps every marker it still
+//! honors. An incompatible pairing then fails with
+//! ``cannot find value `UPDATE_X402_RUNTIME_TO_...` ``, which names the
+//! runtime version to update to.
+//!
+//! Add a marker whenever the expansion starts relying on new runtime
+//! behavior, and point the macro at it. Remove one only when the runtime
+//! stops honoring it.
+
+/// `x402-runtime` 0.1: every function and type the expansion calls, with
+/// the checks they make in this release.
+pub const UPDATE_X402_RUNTIME_TO_0_1_OR_LATER: () = ();
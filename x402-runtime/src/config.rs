@@ -0,0 +1,45 @@
+//! Program-wide recipient set once at deploy time.
+//!
+//! An `#[x402]` attribute without `address` resolves its recipient from the
+//! handler's optional `x402_config` account: a PDA seeded by
+//! [`CONFIG_SEED`] whose type has a `recipient: Pubkey` field, written by
+//! the program's `initialize_x402` instruction. Rotating it takes effect on
+//! the next call. A call without the account fails with
+//! `ConfigNotInitialized`, and the recipient of such a handler's
+//! `<HANDLER>_X402_PARAMS` is all zeros.
+//!
+//! The config also records the cluster it was initialized on, in a
+//! `cluster: Cluster` field. Handlers of a program built for one cluster,
+//! `#[x402_program(network = "...")]`, check it with [`require_cluster`]
+//! before anything else.
+
+use anchor_lang::prelude::*;
+
+use crate::error::X402Error;
+use crate::Cluster;
+
+pub use x402_common::seeds::CONFIG_SEED;
+
+/// Returns the recipient held by the config account, or
+/// `ConfigNotInitialized` when the caller did not supply one.
+pub fn configured_recipient(config_recipient: Option<Pubkey>) -> Result<Pubkey> {
+    config_recipient.ok_or_else(|| {
+        msg!("x402: no address configured and no x402_config account supplied");
+        error!(X402Error::ConfigNotInitialized)
+    })
+}
+
+/// Fails with `WrongCluster` unless the config account records `expected`,
+/// the cluster the program was built for, and with `ConfigNotInitialized`
+/// when the caller did not supply one.
+pub fn require_cluster(config_cluster: Option<Cluster>, expected: Cluster) -> Result<()> {
+    let Some(cluster) = config_cluster else {
+        msg!("x402: built for {} but no x402_config account supplied", expected.name());
+        return err!(X402Error::ConfigNotInitialized);
+    };
+    if cluster != expected {
+        msg!("x402: built for {} but the config records {}", expected.name(), cluster.name());
+        return err!(X402Error::WrongCluster);
+    }
+    Ok(())
+}
@@ -0,0 +1,162 @@
+//! Congestion surcharges.
+//!
+//! With `congestion_surcharge(account = "...", bps_per_level = N)` on
+//! `#[x402]`, the handler's accounts struct declares an `x402_congestion`
+//! account at that address whose type has a `level: u8` field, kept by the
+//! program's authority between 0 and [`MAX_CONGESTION_LEVEL`]. The price is
+//! raised by `level * N` basis points at verification time, with the
+//! arithmetic of `x402_common::pricing` that client quotes also run, and
+//! the surcharge is emitted as `<HANDLER>_X402_SURCHARGE` for them.
+//!
+//! Since the level can change between a client's quote and its call, such
+//! handlers also take a `max_acceptable_price: u64` argument, which
+//! `#[x402_program]` adds, and fail with `PriceExceedsClientMaximum` when
+//! the price enforced is above it.
+//!
+//! A level raised at the slot a call lands would reject a payment quoted
+//! just before. `price_grace_bps = N` accepts payments up to `N` basis
+//! points below the enforced price ([`apply_grace`]);
+//! `price_grace_window_secs = N` instead accepts the price at the previous
+//! level for `N` seconds after the level changed, for which the congestion
+//! account also has `previous_level: u8` and `updated_at: i64` fields
+//! ([`CongestionSurcharge::apply_with_window`]). The grace is at most
+//! [`MAX_GRACE_BPS`], and the two cannot be combined.
+
+use anchor_lang::prelude::*;
+pub use x402_common::pricing::MAX_CONGESTION_LEVEL;
+pub use x402_common::pricing::MAX_GRACE_BPS;
+use x402_common::pricing::{graced_price, surcharged_price, PricingError};
+use x402_common::provenance::PriceProvenance;
+
+use crate::clock;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::native::X402Params;
+
+pub use x402_common::seeds::CONGESTION_SEED;
+
+/// A handler's surcharge, emitted next to it as `<HANDLER>_X402_SURCHARGE`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CongestionSurcharge {
+    /// The congestion account the level is read from.
+    pub account: Pubkey,
+    /// Basis points added to the price per congestion level.
+    pub bps_per_level: u16,
+}
+
+impl CongestionSurcharge {
+    /// `params` with the price raised for `level`, rounded down.
+    pub fn apply(&self, params: &X402Params, level: u8) -> Result<X402Params> {
+        let price = surcharged_price(params.price, level, self.bps_per_level).map_err(|err| match err {
+            PricingError::CongestionLevelOutOfRange => {
+                msg!("x402: congestion level {} is above {}", level, MAX_CONGESTION_LEVEL);
+                error!(X402Error::InvalidCongestionLevel)
+            }
+            PricingError::Overflow => {
+                msg!("x402: price {} at congestion level {} overflows", params.price, level);
+                error!(X402Error::SurchargeOverflow)
+            }
+        })?;
+
+        Ok(X402Params { price, ..*params })
+    }
+
+    /// [`apply`](Self::apply) with the level held by `account`, the
+    /// handler's `x402_congestion`, after checking it is the configured
+    /// account. This is what `#[x402]` enforces.
+    pub fn apply_from(&self, params: &X402Params, account: &Pubkey, level: u8) -> Result<X402Params> {
+        if !keys_eq(account, &self.account) {
+            msg!("x402: congestion account {} passed, expected {}", account, self.account);
+            return err!(X402Error::CongestionAccountMismatch);
+        }
+        self.apply(params, level)
+    }
+
+    /// [`apply_from`](Self::apply_from), also accepting the price at
+    /// `change.previous_level` for `window_secs` after the change, by the
+    /// clock. Within the window the lower of the two prices is enforced.
+    pub fn apply_with_window(
+        &self,
+        params: &X402Params,
+        account: &Pubkey,
+        level: u8,
+        change: LevelChange,
+        window_secs: u32,
+    ) -> Result<X402Params> {
+        let now = clock::now().inspect_err(|_| msg!("x402: no time to check the price grace window against"))?;
+        self.apply_with_window_at(params, account, level, change, now, window_secs)
+    }
+
+    /// [`apply_with_window`](Self::apply_with_window) at `now`.
+    pub fn apply_with_window_at(
+        &self,
+        params: &X402Params,
+        account: &Pubkey,
+        level: u8,
+        change: LevelChange,
+        now: i64,
+        window_secs: u32,
+    ) -> Result<X402Params> {
+        let current = self.apply_from(params, account, level)?;
+        if now.saturating_sub(change.updated_at) >= i64::from(window_secs) {
+            return Ok(current);
+        }
+        let previous = self.apply(params, change.previous_level)?;
+        Ok(if previous.price < current.price { previous } else { current })
+    }
+
+    /// Provenance of `enforced`, priced from `params` by one of the
+    /// `apply_*` methods with `account` at `level`: the level whose price
+    /// it is, `change.previous_level` when a grace window enforced that
+    /// one, and `change.updated_at`.
+    pub fn provenance(
+        &self,
+        params: &X402Params,
+        enforced: &X402Params,
+        account: &Pubkey,
+        level: u8,
+        change: LevelChange,
+    ) -> PriceProvenance {
+        let level = match surcharged_price(params.price, level, self.bps_per_level) {
+            Ok(price) if price != enforced.price => change.previous_level,
+            _ => level,
+        };
+        PriceProvenance::Congestion {
+            account: *account,
+            level,
+            updated_at: change.updated_at,
+        }
+    }
+}
+
+/// `params` lowered by `grace_bps` basis points, at most
+/// [`MAX_GRACE_BPS`]: the least a `price_grace_bps` handler accepts.
+pub fn apply_grace(params: &X402Params, grace_bps: u16) -> X402Params {
+    X402Params {
+        price: graced_price(params.price, grace_bps),
+        ..*params
+    }
+}
+
+/// The level a congestion account held before its latest change.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LevelChange {
+    pub previous_level: u8,
+    /// Unix timestamp of the change.
+    pub updated_at: i64,
+}
+
+/// Rejects a call whose enforced price, `params.price`, is above the
+/// `max_acceptable_price` its caller signed for. A price lowered since the
+/// quote passes, and only the lower price is then required.
+pub fn require_within_maximum(params: &X402Params, max_acceptable_price: u64) -> Result<()> {
+    if params.price > max_acceptable_price {
+        msg!(
+            "x402: the price is {}, above the caller's maximum of {}",
+            params.price,
+            max_acceptable_price
+        );
+        return err!(X402Error::PriceExceedsClientMaximum);
+    }
+    Ok(())
+}
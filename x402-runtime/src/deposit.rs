@@ -0,0 +1,109 @@
+//! Prices paid from lamports deposited with the program in advance.
+//!
+//! With `deposit = true` there is no transfer to find: the payer has topped
+//! up a deposit account, owned by the program and seeded by [`DEPOSIT_SEED`]
+//! and the payer, and the expansion moves the price out of it into the
+//! writable `x402_recipient` by lamport arithmetic. The handler's accounts
+//! struct names the deposit `x402_deposit`; its type has a
+//! `depositor: Pubkey` field, and the payer must sign.
+//!
+//! The deposit is an ordinary account and must stay rent-exempt, so only
+//! the lamports above its rent-exempt minimum can be spent or withdrawn; a
+//! call it cannot pay fails with `InsufficientDeposit`. No payment
+//! instruction is read, so the scan, split, fee payer, fingerprint and
+//! trampoline options do not apply.
+
+use anchor_lang::prelude::*;
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+
+use crate::balance_delta::CURRENT_INSTRUCTION;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::verify::{require_sol, Currency, VerifiedPayment, X402Params};
+
+pub use x402_common::seeds::DEPOSIT_SEED;
+
+/// Lamports of `deposit` above its rent-exempt minimum.
+pub fn available(deposit: &AccountInfo, rent: &Rent) -> u64 {
+    deposit
+        .lamports()
+        .saturating_sub(rent.minimum_balance(deposit.data_len()))
+}
+
+/// Moves the price from `deposit`, which must belong to the signing
+/// `payer`, to the configured, writable `recipient`.
+pub fn debit(
+    deposit: &AccountInfo,
+    depositor: &Pubkey,
+    recipient: &AccountInfo,
+    payer: &AccountInfo,
+    params: &X402Params,
+) -> Result<VerifiedPayment> {
+    let rent = Rent::get().inspect_err(|_| msg!("x402: Rent sysvar unavailable while debiting the deposit"))?;
+    debit_with_rent(deposit, depositor, recipient, payer, params, &rent)
+}
+
+/// [`debit`] under `rent`.
+pub fn debit_with_rent(
+    deposit: &AccountInfo,
+    depositor: &Pubkey,
+    recipient: &AccountInfo,
+    payer: &AccountInfo,
+    params: &X402Params,
+    rent: &Rent,
+) -> Result<VerifiedPayment> {
+    require_sol(params)?;
+    if !keys_eq(depositor, payer.key) || !payer.is_signer {
+        msg!("x402: deposit of {} debited for payer {} (signer: {})", depositor, payer.key, payer.is_signer);
+        return err!(X402Error::DepositPayerMismatch);
+    }
+    if !keys_eq(recipient.key, &params.recipient) || !recipient.is_writable {
+        msg!("x402: recipient account {} must be the writable {}", recipient.key, params.recipient);
+        return err!(X402Error::WrongRecipient);
+    }
+
+    move_lamports(deposit, recipient, params.price, rent)?;
+    Ok(VerifiedPayment {
+        amount: params.price,
+        payer: *payer.key,
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index: CURRENT_INSTRUCTION,
+    })
+}
+
+/// Returns `lamports` of `deposit` to `to`, keeping the deposit
+/// rent-exempt. The caller checks who may withdraw.
+pub fn withdraw(deposit: &AccountInfo, to: &AccountInfo, lamports: u64) -> Result<()> {
+    let rent = Rent::get().inspect_err(|_| msg!("x402: Rent sysvar unavailable while withdrawing the deposit"))?;
+    withdraw_with_rent(deposit, to, lamports, &rent)
+}
+
+/// [`withdraw`] under `rent`.
+pub fn withdraw_with_rent(deposit: &AccountInfo, to: &AccountInfo, lamports: u64, rent: &Rent) -> Result<()> {
+    move_lamports(deposit, to, lamports, rent)
+}
+
+/// Moves `lamports` out of the program-owned `deposit`, failing without
+/// moving anything if that would leave it below its rent-exempt minimum.
+fn move_lamports(deposit: &AccountInfo, to: &AccountInfo, lamports: u64, rent: &Rent) -> Result<()> {
+    let available = available(deposit, rent);
+    if lamports > available {
+        let (mut available_text, mut needed_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+        msg!(
+            "x402: deposit {} has {} spendable, {} needed",
+            deposit.key,
+            fmt_amount(available, Currency::Sol, &mut available_text),
+            fmt_amount(lamports, Currency::Sol, &mut needed_text)
+        );
+        return err!(X402Error::InsufficientDeposit);
+    }
+
+    let credited = to.lamports().checked_add(lamports).ok_or_else(|| {
+        msg!("x402: {} would overflow receiving {} lamports", to.key, lamports);
+        error!(X402Error::StatsOverflow)
+    })?;
+    **deposit.try_borrow_mut_lamports()? -= lamports;
+    **to.try_borrow_mut_lamports()? = credited;
+    Ok(())
+}
@@ -0,0 +1,124 @@
+//! The error taxonomy shared by Anchor and native programs.
+//!
+//! With the `anchor` feature `X402Error` is an Anchor `#[error_code]`
+//! enum; without it, a plain enum with the same codes that converts into
+//! `ProgramError::Custom`, so a native program reports the same numbers.
+//! Either way the codes start at [`X402_ERROR_CODE_OFFSET`], clear of the
+//! 6000 range Anchor gives the program's own errors.
+
+pub use x402_common::X402_ERROR_CODE_OFFSET;
+
+macro_rules! x402_errors {
+    ($($(#[$doc:meta])* $variant:ident => $msg:tt,)*) => {
+        /// Failures of the injected payment verification, one per step so a
+        /// missing sysvar, an unreadable sysvar and an actual payment problem
+        /// are distinguishable from the client.
+        // `error_code` takes a literal offset only; it is
+        // `X402_ERROR_CODE_OFFSET`.
+        #[cfg(feature = "anchor")]
+        #[anchor_lang::error_code(offset = 7000)]
+        pub enum X402Error {
+            $($(#[$doc])* #[msg($msg)] $variant,)*
+        }
+
+        /// Failures of the payment verification, numbered as the Anchor
+        /// `#[error_code]` enum is.
+        #[cfg(not(feature = "anchor"))]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        #[repr(u32)]
+        pub enum X402Error {
+            $($(#[$doc])* $variant,)*
+        }
+
+        #[cfg(not(feature = "anchor"))]
+        impl X402Error {
+            pub fn name(&self) -> String {
+                match self {
+                    $(X402Error::$variant => stringify!($variant).to_string(),)*
+                }
+            }
+
+            pub fn message(&self) -> &'static str {
+                match self {
+                    $(X402Error::$variant => $msg,)*
+                }
+            }
+        }
+
+        /// Every [`X402Error`], in code order, for clients mapping a logged
+        /// error back to its variant.
+        pub const X402_ERRORS: [X402Error; [$(stringify!($variant)),*].len()] = [
+            $(X402Error::$variant,)*
+        ];
+    };
+}
+
+x402_errors! {
+    SysvarMissing => "Instructions sysvar account was not supplied",
+    SysvarUnavailable => "Instructions sysvar could not be read",
+    NoPaymentInstruction => "No instruction precedes the priced instruction",
+    NotAPayment => "Preceding instruction is not a recognized payment",
+    InsufficientPayment => "Payment amount is below the required price",
+    WrongRecipient => "Payment was sent to the wrong recipient",
+    SpendingCapExceeded => "Payment would exceed the payer's spending cap",
+    SpendingCapPayerMismatch => "Spending cap account belongs to a different payer",
+    StatsOverflow => "Payment statistics overflowed",
+    InvalidSysvarAccount => "Instructions sysvar was passed writable, as a signer, or with the wrong owner",
+    DuplicateSplitPayer => "A split payer contributed more than one transfer",
+    SignerDidNotPay => "The signer is not among the split payers",
+    FeePayerMismatch => "The payment was not signed by the handler's payer",
+    ConfigNotInitialized => "No recipient configured: pass the initialized x402_config account",
+    UnsupportedCurrency => "Token-denominated prices cannot be paid in this payment mode",
+    UnexpectedRemainingAccounts => "Remaining accounts were passed to an instruction with strict accounts",
+    WrongPaymentToken => "Payment was made in a different token than the price",
+    CongestionAccountMismatch => "The congestion account is not the one the price is configured with",
+    InvalidCongestionLevel => "The congestion level is out of range",
+    SurchargeOverflow => "The price with its congestion surcharge overflows",
+    NotInvokedByTrampoline => "Priced instruction was invoked by something other than this program's pay_and_invoke",
+    TrampolineNotTopLevel => "pay_and_invoke must be a top-level instruction",
+    PriceExceedsClientMaximum => "The enforced price is above the caller's max_acceptable_price",
+    DepositPayerMismatch => "Deposit account belongs to a different payer, or the payer did not sign",
+    InsufficientDeposit => "Deposit balance above its rent-exempt minimum does not cover the amount",
+    ExperimentAccountMismatch => "The experiment account is not the PDA of the handler's bucket seed",
+    InvalidExperimentArm => "The experiment's winning arm has no price",
+    MissingRevenueLeg => "No transfer pays one of the recipients the price is split between",
+    ProgramNotUpgradeable => "The program has no upgrade authority to initialize its config",
+    ProgramDataMismatch => "The program data account does not belong to the program",
+    NotUpgradeAuthority => "The signer is not the program's upgrade authority",
+    NotX402Admin => "The signer is not the config's authority or operator",
+    InvokedByCpi => "Priced instruction was invoked by another program, and its handler does not set allow_cpi",
+    LedgerPayerMismatch => "Ledger account belongs to a different payer",
+    RecipientNotAuthorized => "No authorization by the handler's authority names this recipient and price",
+    RecipientAuthorizationExpired => "The recipient's authorization has expired",
+    ClockUnavailable => "The Clock sysvar could not be read",
+    InvalidExpiry => "An expiry is negative or more than 100 years away",
+    GroupBudgetMismatch => "The group budget account is not the PDA of the group and payer",
+    GroupPaymentReused => "A payment already counted toward the group's budget was found again",
+    ReentrantCall => "A reentrancy-guarded instruction was invoked while another was still running",
+    AccumulatorMismatch => "The accumulator account is not the PDA of the instruction's tag and payer",
+    InsufficientAccumulation => "Payments accumulated toward the instruction do not cover the price",
+    WrongCluster => "The program's config records another cluster than the one the program was built for",
+    MissingPaymentAccount => "The payment instruction does not carry the account the check reads",
+    ProgramMismatch => "The program account is not the program running the check",
+}
+
+#[cfg(not(feature = "anchor"))]
+impl From<X402Error> for u32 {
+    fn from(err: X402Error) -> u32 {
+        X402_ERROR_CODE_OFFSET + err as u32
+    }
+}
+
+#[cfg(not(feature = "anchor"))]
+impl From<X402Error> for solana_program::program_error::ProgramError {
+    fn from(err: X402Error) -> Self {
+        solana_program::program_error::ProgramError::Custom(err.into())
+    }
+}
+
+#[cfg(not(feature = "anchor"))]
+impl std::fmt::Display for X402Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.message())
+    }
+}
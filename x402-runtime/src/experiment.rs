@@ -0,0 +1,88 @@
+//! Price experiments.
+//!
+//! With `experiment(bucket_seed = "...", prices(A, B, ...))` on `#[x402]`,
+//! each payer is charged one of the prices, picked by
+//! `x402_common::pricing::experiment_bucket` from the handler's `payer` key
+//! and the seed alone. The bucket cannot be passed in, and the payment
+//! must come from that payer, so a payer cannot pay another payer's arm
+//! except by signing with a different key. Client quotes run the same
+//! function, so a payer is quoted the price they are charged. The
+//! experiment is emitted as `<HANDLER>_X402_EXPERIMENT`; its first price
+//! is the one in `<HANDLER>_X402_PARAMS`, and `price` is not given. The
+//! arm charged is recorded in `X402PaymentVerifiedEvent::experiment_arm`.
+//!
+//! The handler's accounts struct declares `x402_experiment`, the PDA of
+//! [`EXPERIMENT_SEED`] and the bucket seed, whose type has `ended: bool`
+//! and `winner: u8` fields. Once its authority sets `ended`, every payer is
+//! charged the `winner` arm's price, ending the experiment without a
+//! redeploy.
+
+use anchor_lang::prelude::*;
+use x402_common::pricing::experiment_bucket;
+use x402_common::seeds::experiment_address;
+pub use x402_common::pricing::MAX_EXPERIMENT_ARMS;
+
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::native::X402Params;
+
+pub use x402_common::seeds::EXPERIMENT_SEED;
+
+/// A handler's experiment, emitted next to it as
+/// `<HANDLER>_X402_EXPERIMENT`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Experiment {
+    pub bucket_seed: &'static str,
+    /// One price per arm, in lamports; the first is also the price in
+    /// `<HANDLER>_X402_PARAMS`.
+    pub prices: &'static [u64],
+}
+
+/// Whether an experiment's account has ended it, and on which arm.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExperimentState {
+    pub ended: bool,
+    pub winner: u8,
+}
+
+impl Experiment {
+    /// Address of the account holding the experiment's state.
+    pub fn address(&self, program_id: &Pubkey) -> Pubkey {
+        experiment_address(program_id, self.bucket_seed.as_bytes())
+    }
+
+    /// The arm `payer` is bucketed into while the experiment runs.
+    pub fn bucket(&self, payer: &Pubkey) -> u8 {
+        experiment_bucket(payer, self.bucket_seed.as_bytes(), self.prices.len() as u8)
+    }
+
+    /// `params` priced at the arm `payer` is charged under `state`,
+    /// returned with that arm.
+    pub fn apply(&self, params: &X402Params, payer: &Pubkey, state: ExperimentState) -> Result<(X402Params, u8)> {
+        let arm = if state.ended { state.winner } else { self.bucket(payer) };
+        let Some(&price) = self.prices.get(arm as usize) else {
+            msg!("x402: experiment \"{}\" ended on arm {} of {}", self.bucket_seed, arm, self.prices.len());
+            return err!(X402Error::InvalidExperimentArm);
+        };
+        Ok((X402Params { price, ..*params }, arm))
+    }
+
+    /// [`apply`](Self::apply) with the state held by `account`, the
+    /// handler's `x402_experiment`, after checking it is the experiment's
+    /// PDA under `program_id`.
+    pub fn apply_from(
+        &self,
+        params: &X402Params,
+        account: &Pubkey,
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        state: ExperimentState,
+    ) -> Result<(X402Params, u8)> {
+        let expected = self.address(program_id);
+        if !keys_eq(account, &expected) {
+            msg!("x402: experiment account is {} instead of {}", account, expected);
+            return err!(X402Error::ExperimentAccountMismatch);
+        }
+        self.apply(params, payer, state)
+    }
+}
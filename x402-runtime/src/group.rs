@@ -0,0 +1,183 @@
+//! Priced instruction groups: volume pricing within one transaction.
+//!
+//! With `group = "compute", group_cap = N` on `#[x402]`, the handlers of a
+//! group charge one budget per payer, the accounts struct's optional,
+//! mutable `x402_group_budget` account: the PDA of [`GROUP_BUDGET_SEED`],
+//! the group's name and the handler's `payer`, whose type embeds a
+//! [`GroupBudget`] as its `budget` field. Each call adds its list price to
+//! `consumed_this_slot` and is due only what brings the payments counted
+//! in the transaction up to `min(consumed_this_slot, N)`, so any
+//! combination of the group's calls in one transaction costs at most `N`.
+//! Without the account a call is due its list price and nothing is
+//! recorded. The group is emitted as `<HANDLER>_X402_GROUP`, and
+//! `#[x402_program]` rejects one group given two caps.
+//!
+//! # One transaction
+//!
+//! No grouped call can tell it is the transaction's last, so none can close
+//! the account; it is kept and reused, and [`Group::open`] resets it
+//! whenever it describes another transaction, told apart by:
+//!
+//! - the slot. A transaction runs within one slot, so a budget last written
+//!   at an earlier slot is stale, and a slot boundary needs nothing more.
+//! - the hash of the transaction's instructions ([`transaction_id`]). Two
+//!   transactions of one payer may land in the same slot, and the second
+//!   must not be covered by the first's payments. Transactions with
+//!   identical instructions in one slot do share a budget, but identical
+//!   instructions include identical transfers, so the second still pays
+//!   what the first paid.
+//!
+//! # Failures
+//!
+//! A transaction is atomic: when any of its instructions fails, the
+//! budget's updates revert along with the transfers they counted, so a
+//! budget never holds payments that were not made, nor consumption that
+//! was not paid for. A grouped call that fails its check fails the whole
+//! transaction, and there is no partial failure to account for.
+//!
+//! # Payments
+//!
+//! A call due nothing is not checked for a payment and binds a [`covered`]
+//! payment of 0 from the handler's payer. A call due something is checked
+//! as any other, against the amount due, and [`Group::settle`] counts the
+//! payment found, overpayment included: paying the whole cap before the
+//! first call covers the rest. A payment counts once; the scan finding one
+//! at or before a payment already counted fails the call with
+//! `GroupPaymentReused`. Events, fingerprints, stats and ledgers carry the
+//! list price and the amount actually paid, which is less for a call the
+//! budget covered. A call through the trampoline was paid its list price by
+//! `pay_and_invoke` and leaves the budget alone.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use x402_common::seeds::group_budget_address;
+
+use crate::balance_delta::CURRENT_INSTRUCTION;
+use crate::clock;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::native::{load_sysvar, VerifiedPayment, X402Params};
+
+pub use x402_common::group::GroupBudget;
+pub use x402_common::seeds::GROUP_BUDGET_SEED;
+
+/// A handler's group, emitted next to it as `<HANDLER>_X402_GROUP`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub name: &'static str,
+    /// Most the group's calls in one transaction cost together, in
+    /// lamports.
+    pub cap: u64,
+}
+
+impl Group {
+    /// Address of `payer`'s budget in the group.
+    pub fn address(&self, program_id: &Pubkey, payer: &Pubkey) -> Pubkey {
+        group_budget_address(program_id, self.name, payer)
+    }
+
+    /// Adds a call at `params` to `budget`, reset first if it describes
+    /// another transaction than the one at `slot` with `transaction`, and
+    /// returns `params` priced at what the call is due.
+    pub fn open_at(
+        &self,
+        budget: &mut GroupBudget,
+        params: &X402Params,
+        slot: u64,
+        transaction: [u8; 32],
+    ) -> Result<X402Params> {
+        let mut next = if budget.last_slot == slot && budget.transaction == transaction {
+            *budget
+        } else {
+            GroupBudget {
+                last_slot: slot,
+                transaction,
+                ..GroupBudget::default()
+            }
+        };
+        next.consumed_this_slot = next.consumed_this_slot.checked_add(params.price).ok_or_else(|| {
+            msg!("x402: consumption of group \"{}\" overflowed", self.name);
+            error!(X402Error::StatsOverflow)
+        })?;
+        let due = next.consumed_this_slot.min(self.cap).saturating_sub(next.paid_this_slot);
+
+        *budget = next;
+        Ok(X402Params { price: due, ..*params })
+    }
+
+    /// [`open_at`](Self::open_at) in the current transaction, read from the
+    /// instructions sysvar among `accounts`, after checking `account`, the
+    /// handler's `x402_group_budget`, is `payer`'s budget under
+    /// `program_id`.
+    pub fn open(
+        &self,
+        account: &Pubkey,
+        program_id: &Pubkey,
+        payer: &Pubkey,
+        budget: &mut GroupBudget,
+        params: &X402Params,
+        accounts: &[AccountInfo],
+    ) -> Result<X402Params> {
+        let expected = self.address(program_id, payer);
+        if !keys_eq(account, &expected) {
+            msg!("x402: group budget account is {} instead of {}", account, expected);
+            return err!(X402Error::GroupBudgetMismatch);
+        }
+        let transaction = transaction_id(accounts)?;
+        self.open_at(budget, params, clock::slot()?, transaction)
+    }
+
+    /// Counts `payment`, verified for a call [`open`](Self::open)ed on
+    /// `budget`. Fails without modifying anything when the payment was
+    /// already counted or the total overflows.
+    pub fn settle(&self, budget: &mut GroupBudget, payment: &VerifiedPayment) -> Result<()> {
+        if payment.amount == 0 {
+            return Ok(());
+        }
+        if payment.ix_index < budget.counted_through {
+            msg!(
+                "x402: payment at instruction {} was already counted toward group \"{}\"",
+                payment.ix_index,
+                self.name
+            );
+            return err!(X402Error::GroupPaymentReused);
+        }
+        let paid = budget.paid_this_slot.checked_add(payment.amount).ok_or_else(|| {
+            msg!("x402: payments to group \"{}\" overflowed", self.name);
+            error!(X402Error::StatsOverflow)
+        })?;
+
+        budget.paid_this_slot = paid;
+        budget.counted_through = payment.ix_index.saturating_add(1);
+        Ok(())
+    }
+}
+
+/// The payment bound for a call its group's budget covers: nothing, from
+/// `payer`, at the priced instruction itself.
+pub fn covered(params: &X402Params, payer: &Pubkey) -> VerifiedPayment {
+    VerifiedPayment {
+        amount: 0,
+        payer: *payer,
+        recipient: params.recipient,
+        currency: params.currency,
+        ix_index: CURRENT_INSTRUCTION,
+    }
+}
+
+/// `sha256` of the transaction's instructions, the instructions sysvar
+/// among `accounts` without its trailing current index, so every
+/// instruction of a transaction, and every invocation within it, reads the
+/// same value.
+pub fn transaction_id(accounts: &[AccountInfo]) -> Result<[u8; 32]> {
+    let (ix_sysvar, _) = load_sysvar(accounts)?;
+    let data = ix_sysvar.try_borrow_data().map_err(|err| {
+        msg!("x402: borrowing the instructions sysvar failed: {}", err);
+        X402Error::SysvarUnavailable
+    })?;
+    let instructions = data.len().checked_sub(2).map(|len| &data[..len]).ok_or_else(|| {
+        msg!("x402: instructions sysvar of {} bytes has no current index", data.len());
+        X402Error::SysvarUnavailable
+    })?;
+    Ok(hash(instructions).to_bytes())
+}
@@ -0,0 +1,79 @@
+//! Per-payer payment history.
+//!
+//! A program opts in by giving the priced handler's accounts struct an
+//! optional, mutable `x402_ledger` account whose type has the fields
+//! `payer: Pubkey`, `total_payments: u64`, `total_amount: u64` and
+//! `last_payment: i64`, seeded by [`PAYMENT_LEDGER_SEED`] and the payer,
+//! and setting `ledger = true` on `#[x402]`. Each verified payment is
+//! added to the account, and the handler body gets the updated history as
+//! `x402_ledger: Option<LedgerSnapshot>`, `None` when the account was not
+//! supplied. The snapshot is a copy, so the body may still borrow the
+//! account itself.
+
+use anchor_lang::prelude::*;
+
+use crate::clock;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::verify::VerifiedPayment;
+
+pub use x402_common::seeds::PAYMENT_LEDGER_SEED;
+
+/// A payer's history including the payment just verified. A copy, so the
+/// body may still borrow `x402_ledger` from its accounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LedgerSnapshot {
+    pub payer: Pubkey,
+    /// 1 on the payer's first recorded payment.
+    pub total_payments: u64,
+    pub total_amount: u64,
+    /// Unix timestamp of this payment.
+    pub last_payment: i64,
+}
+
+/// Adds `payment` to a ledger at the current time; see [`record_at`].
+pub fn record(
+    owner: &Pubkey,
+    total_payments: &mut u64,
+    total_amount: &mut u64,
+    last_payment: &mut i64,
+    payment: &VerifiedPayment,
+) -> Result<LedgerSnapshot> {
+    let now = clock::now()?;
+    record_at(owner, total_payments, total_amount, last_payment, payment, now)
+}
+
+/// Adds `payment`, made at `now`, to a ledger and returns its updated
+/// history. Fails without modifying anything when the ledger belongs to
+/// someone else or a total overflows.
+pub fn record_at(
+    owner: &Pubkey,
+    total_payments: &mut u64,
+    total_amount: &mut u64,
+    last_payment: &mut i64,
+    payment: &VerifiedPayment,
+    now: i64,
+) -> Result<LedgerSnapshot> {
+    if !keys_eq(owner, &payment.payer) {
+        msg!("x402: ledger of {} supplied for payer {}", owner, payment.payer);
+        return err!(X402Error::LedgerPayerMismatch);
+    }
+    let next_payments = total_payments.checked_add(1).ok_or_else(|| {
+        msg!("x402: ledger payment count of {} overflowed", owner);
+        error!(X402Error::StatsOverflow)
+    })?;
+    let next_amount = total_amount.checked_add(payment.amount).ok_or_else(|| {
+        msg!("x402: ledger total amount of {} overflowed", owner);
+        error!(X402Error::StatsOverflow)
+    })?;
+
+    *total_payments = next_payments;
+    *total_amount = next_amount;
+    *last_payment = now;
+    Ok(LedgerSnapshot {
+        payer: *owner,
+        total_payments: next_payments,
+        total_amount: next_amount,
+        last_payment: now,
+    })
+}
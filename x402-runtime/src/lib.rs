@@ -0,0 +1,106 @@
+//! Runtime support for the `#[x402]` attribute.
+//!
+//! Everything the macro expansion needs at execution time lives here so it
+//! can be tested and fuzzed as ordinary Rust instead of generated tokens.
+//!
+//! # Versioned transactions and address lookup tables
+//!
+//! Verification reads the instructions sysvar, which the runtime builds
+//! from the *loaded* message: every account meta carries the resolved
+//! pubkey whether it was a static key or came from an address lookup
+//! table, and the sysvar's serialization is the same for legacy and v0
+//! messages. Recipient and payer comparisons therefore need no special
+//! handling for lookup tables, and the sysvar itself may be referenced
+//! through a table since it is located by key among the account infos.
+//!
+//! The one genuine limitation: lookup tables cannot supply signers, so an
+//! account meta resolved through a table always has `is_signer == false`.
+//! A payment instruction's signing source must be a static key, which
+//! every wallet already produces.
+//!
+//! # Native programs
+//!
+//! The `anchor` feature, on by default, provides everything the macro
+//! expansion calls. Without it the crate depends on `solana-program` and the
+//! Anchor-free part of `x402-common`:
+//! [`match_payment`], [`verify_sol_payment`] and [`X402Error`] (as a plain
+//! enum convertible into `ProgramError::Custom`, with the same codes) are
+//! all a native program needs. See `examples/native_program.rs`.
+
+#[cfg(feature = "anchor")]
+pub mod accounts;
+#[cfg(feature = "anchor")]
+pub mod accumulator;
+#[cfg(feature = "anchor")]
+pub mod admin;
+#[cfg(feature = "anchor")]
+pub mod authorization;
+#[cfg(feature = "anchor")]
+pub mod balance_delta;
+#[cfg(feature = "anchor")]
+pub mod clock;
+pub mod cmp;
+#[cfg(feature = "anchor")]
+pub mod commitment;
+pub mod compat;
+#[cfg(feature = "anchor")]
+pub mod config;
+#[cfg(feature = "anchor")]
+pub mod congestion;
+#[cfg(feature = "anchor")]
+pub mod deposit;
+pub mod error;
+#[cfg(feature = "anchor")]
+pub mod experiment;
+#[cfg(feature = "anchor")]
+pub mod group;
+#[cfg(feature = "anchor")]
+pub mod ledger;
+#[cfg(feature = "anchor")]
+pub mod manifest;
+pub mod matcher;
+pub mod native;
+#[cfg(feature = "anchor")]
+pub mod reentrancy;
+#[cfg(feature = "anchor")]
+pub mod rent;
+#[cfg(feature = "anchor")]
+pub mod revenue_split;
+#[cfg(feature = "anchor")]
+pub mod spending_cap;
+#[cfg(feature = "anchor")]
+pub mod stats;
+#[cfg(feature = "anchor")]
+pub mod trampoline;
+#[cfg(feature = "anchor")]
+pub mod verify;
+
+#[cfg(feature = "anchor")]
+pub use clock::now;
+pub use error::{X402Error, X402_ERRORS, X402_ERROR_CODE_OFFSET};
+#[cfg(feature = "anchor")]
+pub use manifest::{ManifestData, ManifestEntry};
+pub use matcher::{
+    advances_nonce, associated_token_address, created_associated_account, is_payment_program, match_payment, MatchError, MatchedPayment, NearMiss, PaymentKind,
+    PaymentScan,
+};
+#[cfg(not(feature = "strict-encodings"))]
+pub use matcher::match_payment_legacy;
+pub use native::{
+    is_ignorable, is_ignorable_instruction, verify_sol_payment, Currency, ScanOptions, VerifiedPayment, X402Params,
+    IGNORABLE_PROGRAMS, MAX_LOOKBACK, MAX_SPLIT_PAYERS,
+};
+pub use x402_common::cluster::Cluster;
+pub use x402_common::layout;
+pub use x402_common::explanation::PaymentExplanation;
+pub use x402_common::payment::Mode;
+pub use x402_common::provenance::PriceProvenance;
+pub use x402_common::tag;
+pub use tag::{InstructionTag, TagAliases, MAX_TAG_LEN};
+#[cfg(feature = "anchor")]
+pub use verify::{
+    emit_payment_latency, emit_payment_verified, emit_request_fingerprint, emit_split_payment_verified,
+    precheck_payment, request_fingerprint, require_fee_payer, verify_payment, verify_payment_with, verify_split_payment,
+};
+#[cfg(feature = "anchor")]
+pub use x402_common::events::PaymentContribution;
@@ -0,0 +1,56 @@
+//! On-chain price manifest.
+//!
+//! Programs publish their priced instructions in an `X402Manifest` PDA
+//! (seeds `[MANIFEST_SEED]`) so clients that only know the program id can
+//! discover prices. The account is laid out as the 8-byte Anchor account
+//! discriminator followed by [`ManifestData`].
+
+use anchor_lang::prelude::*;
+
+use crate::verify::{Currency, X402Params};
+
+pub use x402_common::seeds::MANIFEST_SEED;
+
+/// Anchor discriminator of the `X402Manifest` account,
+/// `sha256("account:X402Manifest")[..8]`.
+pub const MANIFEST_DISCRIMINATOR: [u8; 8] = [21, 7, 98, 234, 184, 207, 231, 13];
+
+/// One priced instruction.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// Anchor instruction discriminator.
+    pub discriminator: [u8; 8],
+    pub price: u64,
+    pub currency: Currency,
+    pub recipient: Pubkey,
+}
+
+impl ManifestEntry {
+    /// Serialized size, with the currency at its largest variant.
+    pub const MAX_SIZE: usize = 8 + 8 + (1 + 32) + 32;
+
+    pub fn new(discriminator: [u8; 8], params: &X402Params) -> Self {
+        ManifestEntry {
+            discriminator,
+            price: params.price,
+            currency: params.currency,
+            recipient: params.recipient,
+        }
+    }
+}
+
+/// Body of the `X402Manifest` account, after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct ManifestData {
+    pub authority: Pubkey,
+    /// Incremented on every publish.
+    pub manifest_version: u32,
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl ManifestData {
+    /// Account size, discriminator included, for `entries` entries.
+    pub fn space(entries: usize) -> usize {
+        8 + 32 + 4 + 4 + entries * ManifestEntry::MAX_SIZE
+    }
+}
@@ -0,0 +1,479 @@
+//! Classification of a single instruction as a qualifying payment.
+//!
+//! `match_payment` is a pure function over an already-loaded
+//! [`Instruction`]: it never touches accounts or sysvars, which keeps it
+//! cheap to fuzz and property test. [`PaymentScan`] compares the decoded
+//! payments against a price and remembers the closest miss, so a failed
+//! scan can say what was wrong with the payment it did find.
+//!
+//! Classification dispatches on the program and the discriminant at the
+//! start of the data; [`PAYMENT_ENCODINGS`] lists every accepted case with
+//! its exact length, and nothing outside that list is accepted.
+//! `x402_common::reference` restates the same rules as a reference model,
+//! and `tests/reference_model.rs` fails unless both agree: change them
+//! together.
+//! [`match_payment_legacy`] keeps the permissive guess earlier versions made
+//! for the invoking program's own instructions, for handlers that opt into
+//! it with `legacy_matcher = true`; the `strict-encodings` feature removes
+//! it. Such a handler warns at compile time and logs `x402: legacy matcher
+//! in use` on every call. To migrate, have clients pay through `x402_pay`
+//! or a plain transfer, then drop the flag.
+
+use solana_program::instruction::Instruction;
+use solana_program::pubkey::Pubkey;
+use solana_program::{pubkey, system_program};
+
+use crate::cmp::{keys_eq, mints_eq};
+use x402_common::payment::Currency;
+
+/// SPL Token program.
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+/// SPL Token-2022 program.
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+/// SPL Associated Token Account program.
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey = pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaymentKind {
+    /// `SystemInstruction::Transfer`.
+    SystemTransfer,
+    /// `SystemInstruction::TransferWithSeed`.
+    SystemTransferWithSeed,
+    /// An instruction of the invoking program itself (e.g. `x402_pay`).
+    ProgramPay,
+    /// SPL Token or Token-2022 `Transfer`.
+    TokenTransfer,
+    /// SPL Token or Token-2022 `TransferChecked`.
+    TokenTransferChecked,
+}
+
+impl PaymentKind {
+    /// Whether the payment moves lamports rather than tokens.
+    pub fn is_native(&self) -> bool {
+        matches!(
+            self,
+            PaymentKind::SystemTransfer
+                | PaymentKind::SystemTransferWithSeed
+                | PaymentKind::ProgramPay
+        )
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MatchedPayment {
+    pub kind: PaymentKind,
+    /// Account that authorized the transfer (the source wallet or token owner).
+    pub payer: Pubkey,
+    /// Account credited by the transfer (a wallet, or a token account).
+    pub recipient: Pubkey,
+    pub amount: u64,
+    /// Mint, when the encoding names one (`TransferChecked` only).
+    pub mint: Option<Pubkey>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchError {
+    /// The instruction targets a program that cannot move funds for us.
+    UnsupportedProgram,
+    /// [`match_payment_legacy`] only: the program is allowed but the
+    /// instruction is not a transfer.
+    NotATransfer,
+    /// The instruction data is too short or has trailing bytes.
+    MalformedData,
+    /// The instruction lacks the accounts its encoding requires.
+    MissingAccounts,
+    /// The data of an allowed program starts with no discriminant in
+    /// [`PAYMENT_ENCODINGS`].
+    UnrecognizedPaymentEncoding,
+}
+
+/// The programs a [`PaymentEncoding`] belongs to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingProgram {
+    System,
+    /// SPL Token and Token-2022, which share their encodings.
+    Token,
+    /// The program running the check.
+    Invoking,
+}
+
+/// One accepted payment encoding: instruction data of `program` that
+/// starts with `discriminant` and is exactly `len` bytes long.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PaymentEncoding {
+    pub kind: PaymentKind,
+    pub program: EncodingProgram,
+    pub discriminant: &'static [u8],
+    /// `None` for `TransferWithSeed`, whose length follows from its seed:
+    /// 52 bytes plus the seed length read at offset 12.
+    pub len: Option<usize>,
+}
+
+/// `SystemInstruction::Transfer`: `u32` tag 2, then `lamports: u64`.
+pub const SYSTEM_TRANSFER: PaymentEncoding = PaymentEncoding {
+    kind: PaymentKind::SystemTransfer,
+    program: EncodingProgram::System,
+    discriminant: &2u32.to_le_bytes(),
+    len: Some(12),
+};
+
+/// `SystemInstruction::TransferWithSeed`: `u32` tag 11, then
+/// `lamports: u64`, `from_seed: String` (`u64` length and bytes) and
+/// `from_owner: Pubkey`.
+pub const SYSTEM_TRANSFER_WITH_SEED: PaymentEncoding = PaymentEncoding {
+    kind: PaymentKind::SystemTransferWithSeed,
+    program: EncodingProgram::System,
+    discriminant: &11u32.to_le_bytes(),
+    len: None,
+};
+
+/// SPL Token `Transfer`: tag 3, then `amount: u64`.
+pub const TOKEN_TRANSFER: PaymentEncoding = PaymentEncoding {
+    kind: PaymentKind::TokenTransfer,
+    program: EncodingProgram::Token,
+    discriminant: &[3],
+    len: Some(9),
+};
+
+/// SPL Token `TransferChecked`: tag 12, then `amount: u64` and
+/// `decimals: u8`.
+pub const TOKEN_TRANSFER_CHECKED: PaymentEncoding = PaymentEncoding {
+    kind: PaymentKind::TokenTransferChecked,
+    program: EncodingProgram::Token,
+    discriminant: &[12],
+    len: Some(10),
+};
+
+/// Anchor discriminator of `x402_pay`, `sha256("global:x402_pay")[..8]`.
+pub const X402_PAY_DISCRIMINATOR: [u8; 8] = [7, 105, 114, 162, 141, 186, 58, 215];
+
+/// The invoking program's `x402_pay`: its discriminator, then
+/// `amount: u64`.
+pub const X402_PAY: PaymentEncoding = PaymentEncoding {
+    kind: PaymentKind::ProgramPay,
+    program: EncodingProgram::Invoking,
+    discriminant: &X402_PAY_DISCRIMINATOR,
+    len: Some(16),
+};
+
+/// Every encoding `match_payment` accepts. [`match_payment_legacy`]
+/// classifies the invoking program's instructions by a guess instead: an
+/// 8-byte discriminator and a `u64` amount followed by anything, or a bare
+/// `u64`.
+pub const PAYMENT_ENCODINGS: [PaymentEncoding; 5] = [
+    SYSTEM_TRANSFER,
+    SYSTEM_TRANSFER_WITH_SEED,
+    TOKEN_TRANSFER,
+    TOKEN_TRANSFER_CHECKED,
+    X402_PAY,
+];
+
+impl EncodingProgram {
+    fn of(candidate: &Pubkey, program_id: &Pubkey) -> Option<Self> {
+        if keys_eq(candidate, &system_program::ID) {
+            Some(EncodingProgram::System)
+        } else if keys_eq(candidate, &TOKEN_PROGRAM_ID) || keys_eq(candidate, &TOKEN_2022_PROGRAM_ID) {
+            Some(EncodingProgram::Token)
+        } else if keys_eq(candidate, program_id) {
+            Some(EncodingProgram::Invoking)
+        } else {
+            None
+        }
+    }
+}
+
+impl PaymentEncoding {
+    /// The encoding whose discriminant starts `data`, an instruction of
+    /// `program`.
+    pub fn find(program: EncodingProgram, data: &[u8]) -> Option<&'static PaymentEncoding> {
+        PAYMENT_ENCODINGS
+            .iter()
+            .find(|encoding| encoding.program == program && data.starts_with(encoding.discriminant))
+    }
+}
+
+/// Returns true when `candidate` is a program whose instructions may count
+/// as a payment to `program_id`.
+pub fn is_payment_program(candidate: &Pubkey, program_id: &Pubkey) -> bool {
+    EncodingProgram::of(candidate, program_id).is_some()
+}
+
+/// Decodes `ix` as a payment, where `program_id` is the program running the
+/// check. Accepted encodings are System transfers, SPL Token / Token-2022
+/// transfers, and `x402_pay` of `program_id` itself; see
+/// [`PAYMENT_ENCODINGS`]. Anything else is `UnrecognizedPaymentEncoding`.
+pub fn match_payment(ix: &Instruction, program_id: &Pubkey) -> Result<MatchedPayment, MatchError> {
+    let program = EncodingProgram::of(&ix.program_id, program_id).ok_or(MatchError::UnsupportedProgram)?;
+    let encoding = PaymentEncoding::find(program, &ix.data).ok_or(MatchError::UnrecognizedPaymentEncoding)?;
+    decode(encoding, ix)
+}
+
+/// The matcher of earlier versions, kept for programs whose clients still
+/// pay through an instruction of the program other than `x402_pay`. Any
+/// instruction of `program_id` whose data fits the legacy guess is read as
+/// a payment, and an unlisted discriminant is `NotATransfer` or
+/// `MalformedData` rather than `UnrecognizedPaymentEncoding`.
+///
+/// Deprecated: migrate those clients to `x402_pay` (or a plain transfer)
+/// and drop `legacy_matcher = true`. Built with `strict-encodings`, this
+/// function does not exist and handlers still asking for it fail to
+/// compile.
+#[cfg(not(feature = "strict-encodings"))]
+pub fn match_payment_legacy(ix: &Instruction, program_id: &Pubkey) -> Result<MatchedPayment, MatchError> {
+    let program = EncodingProgram::of(&ix.program_id, program_id).ok_or(MatchError::UnsupportedProgram)?;
+    if program == EncodingProgram::Invoking {
+        return match_program_pay(ix);
+    }
+    let Some(encoding) = PaymentEncoding::find(program, &ix.data) else {
+        return unrecognized(program, ix);
+    };
+    decode(encoding, ix)
+}
+
+fn decode(encoding: &PaymentEncoding, ix: &Instruction) -> Result<MatchedPayment, MatchError> {
+    let len = match encoding.len {
+        Some(len) => len,
+        None => transfer_with_seed_len(&ix.data)?,
+    };
+    if ix.data.len() != len {
+        return Err(MatchError::MalformedData);
+    }
+
+    let (payer, recipient, mint) = match encoding.kind {
+        PaymentKind::SystemTransfer | PaymentKind::ProgramPay => (0, 1, None),
+        // The base account signs for the source derived from it.
+        PaymentKind::SystemTransferWithSeed => (1, 2, None),
+        PaymentKind::TokenTransfer => (2, 1, None),
+        PaymentKind::TokenTransferChecked => (3, 2, Some(1)),
+    };
+    Ok(MatchedPayment {
+        kind: encoding.kind,
+        payer: account(ix, payer)?,
+        recipient: account(ix, recipient)?,
+        amount: read_u64(&ix.data, encoding.discriminant.len())?,
+        mint: mint.map(|index| account(ix, index)).transpose()?,
+    })
+}
+
+fn transfer_with_seed_len(data: &[u8]) -> Result<usize, MatchError> {
+    let seed_len = read_u64(data, 12)?;
+    usize::try_from(seed_len)
+        .ok()
+        .and_then(|len| len.checked_add(20 + 32))
+        .ok_or(MatchError::MalformedData)
+}
+
+/// Data too short for the program's discriminant is malformed; anything
+/// else names an instruction that moves no funds.
+#[cfg(not(feature = "strict-encodings"))]
+fn unrecognized(program: EncodingProgram, ix: &Instruction) -> Result<MatchedPayment, MatchError> {
+    let discriminant_len = match program {
+        EncodingProgram::Token => 1,
+        _ => 4,
+    };
+    if ix.data.len() < discriminant_len {
+        Err(MatchError::MalformedData)
+    } else {
+        Err(MatchError::NotATransfer)
+    }
+}
+
+/// Legacy heuristic for the program's own payment instruction: an Anchor
+/// discriminator followed by a `u64` amount, or a bare `u64`.
+#[cfg(not(feature = "strict-encodings"))]
+fn match_program_pay(ix: &Instruction) -> Result<MatchedPayment, MatchError> {
+    let amount = if ix.data.len() >= 16 {
+        read_u64(&ix.data, 8)?
+    } else if ix.data.len() == 8 {
+        read_u64(&ix.data, 0)?
+    } else {
+        return Err(MatchError::MalformedData);
+    };
+
+    Ok(MatchedPayment {
+        kind: PaymentKind::ProgramPay,
+        payer: account(ix, 0)?,
+        recipient: account(ix, 1)?,
+        amount,
+        mint: None,
+    })
+}
+
+fn account(ix: &Instruction, index: usize) -> Result<Pubkey, MatchError> {
+    ix.accounts
+        .get(index)
+        .map(|meta| meta.pubkey)
+        .ok_or(MatchError::MissingAccounts)
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Result<u64, MatchError> {
+    offset
+        .checked_add(8)
+        .and_then(|end| data.get(offset..end))
+        .and_then(|bytes| bytes.try_into().ok())
+        .map(u64::from_le_bytes)
+        .ok_or(MatchError::MalformedData)
+}
+
+/// The associated token account of `wallet` for `mint` under
+/// `token_program`, where token payments to `wallet` are credited.
+pub fn associated_token_address(wallet: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[wallet.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+/// `CreateIdempotent`, the associated token account program's instruction
+/// that creates an account unless it already exists.
+const CREATE_IDEMPOTENT: u8 = 1;
+
+/// The wallet and mint whose associated token account `ix` creates, when
+/// it is an associated token account program `CreateIdempotent`.
+pub fn created_associated_account(ix: &Instruction) -> Option<(Pubkey, Pubkey)> {
+    if !keys_eq(&ix.program_id, &ASSOCIATED_TOKEN_PROGRAM_ID) || ix.data != [CREATE_IDEMPOTENT] {
+        return None;
+    }
+    // Funding account, new account, wallet, mint, ...
+    let wallet = ix.accounts.get(2)?.pubkey;
+    let mint = ix.accounts.get(3)?.pubkey;
+    Some((wallet, mint))
+}
+
+/// `SystemInstruction::AdvanceNonceAccount`: `u32` tag 4 and nothing else.
+const ADVANCE_NONCE_ACCOUNT: [u8; 4] = 4u32.to_le_bytes();
+
+/// Whether `ix` advances a durable nonce, as the first instruction of every
+/// transaction signed offline against one does. It is a System
+/// instruction, so only its data tells it apart from a transfer; it moves
+/// no lamports.
+pub fn advances_nonce(ix: &Instruction) -> bool {
+    keys_eq(&ix.program_id, &system_program::ID) && ix.data == ADVANCE_NONCE_ACCOUNT
+}
+
+/// Why a decoded payment did not satisfy a [`PaymentScan`], closest first.
+pub use x402_common::payment::MismatchKind as NearMiss;
+
+fn closeness(miss: &NearMiss) -> u8 {
+    match miss {
+        NearMiss::Insufficient { .. } => 3,
+        NearMiss::WrongToken { .. } => 2,
+        NearMiss::WrongRecipient { .. } => 1,
+    }
+}
+
+/// Compares decoded payments, nearest first, against a price of `price`
+/// lamports (`mint` is `None`) or base units of `mint`, payable to
+/// `recipient` (for tokens, to its associated token account).
+///
+/// Each [`PaymentScan::offer`] either matches or is ranked as a
+/// [`NearMiss`]; the closest, and among equals the nearest, is kept for the
+/// error once nothing matches.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PaymentScan {
+    recipient: Pubkey,
+    price: u64,
+    mint: Option<Pubkey>,
+    best: Option<NearMiss>,
+}
+
+impl PaymentScan {
+    pub fn new(recipient: Pubkey, price: u64, mint: Option<Pubkey>) -> Self {
+        PaymentScan {
+            recipient,
+            price,
+            mint,
+            best: None,
+        }
+    }
+
+    /// Offers `payment`, decoded from an instruction of `payment_program`.
+    /// Returns whether it pays the price in full.
+    pub fn offer(&mut self, payment: &MatchedPayment, payment_program: &Pubkey) -> bool {
+        match self.classify(payment, payment_program) {
+            Ok(()) => true,
+            Err(Some(miss)) => {
+                let closer = match self.best {
+                    Some(best) => closeness(&miss) > closeness(&best),
+                    None => true,
+                };
+                if closer {
+                    self.best = Some(miss);
+                }
+                false
+            }
+            Err(None) => false,
+        }
+    }
+
+    /// The closest miss offered so far.
+    pub fn near_miss(&self) -> Option<NearMiss> {
+        self.best
+    }
+
+    pub fn recipient(&self) -> Pubkey {
+        self.recipient
+    }
+
+    /// The currency the price is in: SOL, or the expected mint.
+    pub fn currency(&self) -> Currency {
+        self.mint.map_or(Currency::Sol, Currency::Token)
+    }
+
+    /// Whether `ix` creates the recipient's associated token account for
+    /// the expected mint, as clients prepend to a first payment to a fresh
+    /// recipient. Scans step over it like an ignorable program; it cannot
+    /// move tokens, and a SOL scan never steps over it.
+    pub fn creates_recipient_account(&self, ix: &Instruction) -> bool {
+        match (self.mint, created_associated_account(ix)) {
+            (Some(mint), Some((owner, created_mint))) => keys_eq(&owner, &self.recipient) && keys_eq(&created_mint, &mint),
+            _ => false,
+        }
+    }
+
+    /// `Err(None)` for payments that say nothing about this price, such as
+    /// a token transfer between unrelated accounts under a SOL price.
+    fn classify(&self, payment: &MatchedPayment, payment_program: &Pubkey) -> Result<(), Option<NearMiss>> {
+        let found = if payment.kind.is_native() {
+            if !keys_eq(&payment.recipient, &self.recipient) {
+                return Err(self.mint.is_none().then_some(NearMiss::WrongRecipient {
+                    found: payment.recipient,
+                }));
+            }
+            None
+        } else {
+            let to_recipient = |mint: &Pubkey| {
+                keys_eq(&payment.recipient, &associated_token_address(&self.recipient, mint, payment_program))
+            };
+            // A plain `Transfer` names no mint, but one into the
+            // recipient's account for the expected mint can only move that
+            // mint.
+            let mint = match (payment.mint, self.mint) {
+                (Some(mint), _) => mint,
+                (None, Some(expected)) if to_recipient(&expected) => expected,
+                (None, _) => return Err(None),
+            };
+            if !to_recipient(&mint) {
+                return Err(self.mint.map(|_| NearMiss::WrongRecipient {
+                    found: payment.recipient,
+                }));
+            }
+            Some(mint)
+        };
+
+        if !mints_eq(found.as_ref(), self.mint.as_ref()) {
+            return Err(Some(NearMiss::WrongToken {
+                expected: self.mint,
+                found,
+            }));
+        }
+        if payment.amount < self.price {
+            return Err(Some(NearMiss::Insufficient {
+                paid: payment.amount,
+                price: self.price,
+            }));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,395 @@
+//! The payment verification itself, free of Anchor.
+//!
+//! Everything here works on plain `solana_program` types and fails with a
+//! bare [`X402Error`], so a native program can call [`verify_sol_payment`]
+//! from its `process_instruction` and return the error as
+//! `ProgramError::Custom`. The Anchor entry points in [`crate::verify`]
+//! wrap these functions.
+//!
+//! The payment is the nearest preceding SOL payment. Instructions of
+//! [`IGNORABLE_PROGRAMS`] (compute budget, memo, signature precompiles) and
+//! durable nonce advances are skipped; `max_lookback = N` on `#[x402]` lets
+//! the scan step over up to `N` other instructions (1 by default) and
+//! `strict_scan = true` makes the first of them fail the call instead.
+
+use std::fmt;
+
+use solana_program::account_info::AccountInfo;
+use solana_program::instruction::Instruction;
+use solana_program::msg;
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+use solana_program::sysvar::{self, instructions};
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::matcher::{
+    advances_nonce, is_payment_program, match_payment, MatchError, MatchedPayment, NearMiss, PaymentScan,
+    ASSOCIATED_TOKEN_PROGRAM_ID,
+};
+
+pub use x402_common::payment::{Currency, VerifiedPayment, X402Params};
+
+/// Most preceding transfers a split payment may combine.
+pub const MAX_SPLIT_PAYERS: u8 = 8;
+
+/// Most non-payment instructions a scan may step over.
+pub const MAX_LOOKBACK: u8 = 16;
+
+/// Programs whose instructions wallets inject unpredictably. The backward
+/// scan for the payment skips them without counting them toward
+/// [`ScanOptions::max_lookback`]: none of them can move funds. Durable
+/// nonce advances are skipped the same way; see [`is_ignorable_instruction`].
+pub const IGNORABLE_PROGRAMS: [Pubkey; 5] = [
+    pubkey!("ComputeBudget111111111111111111111111111111"),
+    pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"),
+    pubkey!("Memo1UhkJRfHyvLMcVucJwxXeuD728EqVDDwQDxFMNo"),
+    pubkey!("Ed25519SigVerify111111111111111111111111111"),
+    pubkey!("KeccakSecp256k11111111111111111111111111111"),
+];
+
+/// How far back from the priced instruction the payment may be, and how
+/// instructions are classified on the way.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Instructions, other than [`IGNORABLE_PROGRAMS`], examined before
+    /// giving up. The first SOL payment of the price to the recipient is
+    /// the payment.
+    pub max_lookback: u8,
+    /// Fail on the first examined instruction that is not a qualifying
+    /// payment instead of looking further back.
+    pub strict: bool,
+    /// Classify instructions with the deprecated `match_payment_legacy`
+    /// instead of [`match_payment`]. Ignored when built with
+    /// `strict-encodings`, which removes the legacy matcher.
+    pub legacy_matcher: bool,
+}
+
+impl ScanOptions {
+    /// Only the nearest instruction that is not ignorable.
+    pub const DEFAULT: ScanOptions = ScanOptions {
+        max_lookback: 1,
+        strict: false,
+        legacy_matcher: false,
+    };
+
+    /// Decodes `ix` with the matcher these options select.
+    pub fn match_payment(&self, ix: &Instruction, program_id: &Pubkey) -> Result<MatchedPayment, MatchError> {
+        #[cfg(not(feature = "strict-encodings"))]
+        if self.legacy_matcher {
+            return crate::matcher::match_payment_legacy(ix, program_id);
+        }
+        match_payment(ix, program_id)
+    }
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        ScanOptions::DEFAULT
+    }
+}
+
+/// Checks that a preceding instruction pays `params`: the nearest payment
+/// within `scan`, skipping [`IGNORABLE_PROGRAMS`]. A token price is paid by
+/// an SPL Token or Token-2022 transfer of the mint into the recipient's
+/// associated token account, signed by the payer's token account owner;
+/// despite the name, token prices are verified here too.
+///
+/// The instructions sysvar is located in `accounts` and must be passed
+/// read-only, unsigned and owned by the sysvar program. Every failing step
+/// logs what it was doing and returns its own [`X402Error`].
+#[inline(never)]
+pub fn verify_sol_payment(
+    accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    scan: &ScanOptions,
+) -> Result<VerifiedPayment, X402Error> {
+    let (ix_sysvar, current_index) = load_sysvar(accounts)?;
+    let mint = match params.currency {
+        Currency::Sol => None,
+        Currency::Token(mint) => Some(mint),
+    };
+    let wanted = PaymentScan::new(params.recipient, params.price, mint);
+    let (payment, ix_index) = find_payment(ix_sysvar, current_index, program_id, wanted, scan)?;
+
+    Ok(VerifiedPayment {
+        amount: payment.amount,
+        payer: payment.payer,
+        // The configured recipient rather than its token account.
+        recipient: params.recipient,
+        currency: params.currency,
+        ix_index,
+    })
+}
+
+/// Whether `program_id` is one of [`IGNORABLE_PROGRAMS`].
+pub fn is_ignorable(program_id: &Pubkey) -> bool {
+    IGNORABLE_PROGRAMS.iter().any(|ignorable| keys_eq(ignorable, program_id))
+}
+
+/// Whether the scan steps over `ix` for free: an instruction of one of
+/// [`IGNORABLE_PROGRAMS`], or a durable nonce advance
+/// ([`advances_nonce`](crate::matcher::advances_nonce)), which wallets
+/// signing offline put first.
+pub fn is_ignorable_instruction(ix: &Instruction) -> bool {
+    is_ignorable(&ix.program_id) || advances_nonce(ix)
+}
+
+/// Rejects token prices (`price_usdc`) where only lamports can pay: every
+/// mode but the preceding-payment scan of [`verify_sol_payment`], rather
+/// than accepting lamports for them.
+pub(crate) fn check_currency(params: &X402Params) -> Result<(), X402Error> {
+    if let Currency::Token(mint) = params.currency {
+        msg!("x402: price is in tokens of {}, which this payment mode cannot take", mint);
+        return Err(X402Error::UnsupportedCurrency);
+    }
+    Ok(())
+}
+
+/// Remaining accounts beyond which [`load_sysvar`] logs every key, so a
+/// client appending accounts can see which of them it sent.
+const MAX_UNLOGGED_ACCOUNTS: usize = 8;
+
+/// The cheapest checks of a preceding payment, which fail the most calls:
+/// the instructions sysvar is among `accounts` and usable, and some
+/// instruction precedes the current one. The expansion runs them before
+/// the price is resolved, so a call failing them reads no other account
+/// and hashes nothing.
+pub fn precheck(accounts: &[AccountInfo]) -> Result<(), X402Error> {
+    let (_, current_index) = load_sysvar(accounts)?;
+    if current_index == 0 {
+        return Err(nothing_precedes(current_index));
+    }
+    Ok(())
+}
+
+/// Locates and vets the instructions sysvar and reads the current index.
+///
+/// Only an account at exactly `instructions::ID` is the sysvar, wherever it
+/// sits in `accounts` and however often it appears; the first one is used.
+pub(crate) fn load_sysvar<'a, 'info>(
+    accounts: &'a [AccountInfo<'info>],
+) -> Result<(&'a AccountInfo<'info>, u16), X402Error> {
+    let Some(ix_sysvar) = accounts.iter().find(|a| keys_eq(a.key, &instructions::ID)) else {
+        return Err(sysvar_missing(accounts));
+    };
+    if ix_sysvar.is_writable || ix_sysvar.is_signer || !keys_eq(ix_sysvar.owner, &sysvar::ID) {
+        return Err(invalid_sysvar(ix_sysvar));
+    }
+
+    // Reads the current index once; callers deserialize only the
+    // instructions they inspect.
+    let current_index = instructions::load_current_index_checked(ix_sysvar)
+        .map_err(|err| sysvar_unavailable("the current instruction index", err))?;
+    Ok((ix_sysvar, current_index))
+}
+
+/// Whether the instruction at `index` is passed the instructions sysvar,
+/// as every priced call of any program is, read like [`program_at`].
+pub(crate) fn reads_instructions(ix_sysvar: &AccountInfo, index: u16) -> Result<bool, X402Error> {
+    let data = ix_sysvar
+        .try_borrow_data()
+        .map_err(|err| sysvar_unavailable("the instructions sysvar", err))?;
+    let u16_at = |at: usize| data.get(at..at + 2).map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])));
+
+    let start = u16_at(2 + 2 * usize::from(index))
+        .ok_or_else(|| sysvar_unavailable(format_args!("instruction {}", index), "the sysvar ends early"))?;
+    let accounts = u16_at(start)
+        .ok_or_else(|| sysvar_unavailable(format_args!("instruction {}", index), "the sysvar ends early"))?;
+    // Each account is a flag byte followed by its key.
+    Ok((0..accounts).any(|account| {
+        let at = start + 2 + 33 * account + 1;
+        data.get(at..at + 32) == Some(instructions::ID.as_ref())
+    }))
+}
+
+/// The program of the instruction at `index`, read from the sysvar's data
+/// without materializing the instruction's accounts and data as
+/// `load_instruction_at_checked` does: a sysvar holds the instruction
+/// count, then an offset per instruction, and at each offset the account
+/// count, a flag byte and key per account, and the program id.
+pub(crate) fn program_at(ix_sysvar: &AccountInfo, index: u16) -> Result<Pubkey, X402Error> {
+    let data = ix_sysvar
+        .try_borrow_data()
+        .map_err(|err| sysvar_unavailable("the instructions sysvar", err))?;
+    let u16_at = |at: usize| data.get(at..at + 2).map(|bytes| usize::from(u16::from_le_bytes([bytes[0], bytes[1]])));
+
+    let offset = 2 + 2 * usize::from(index);
+    let program = u16_at(offset)
+        .and_then(|start| Some(start + 2 + 33 * u16_at(start)?))
+        .and_then(|at| data.get(at..at + 32))
+        .and_then(|bytes| Pubkey::try_from(bytes).ok());
+    program.ok_or_else(|| sysvar_unavailable(format_args!("instruction {}", index), "the sysvar ends early"))
+}
+
+/// A currency as the scan's logs name it: `SOL`, or `tokens of <mint>`.
+struct Unit(Currency);
+
+impl fmt::Display for Unit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Currency::Sol => f.write_str("SOL"),
+            Currency::Token(mint) => write!(f, "tokens of {}", mint),
+        }
+    }
+}
+
+#[cold]
+#[inline(never)]
+fn sysvar_missing(accounts: &[AccountInfo]) -> X402Error {
+    if accounts.len() > MAX_UNLOGGED_ACCOUNTS {
+        for (index, account) in accounts.iter().enumerate() {
+            msg!("x402: remaining account {}: {}", index, account.key);
+        }
+    }
+    msg!(
+        "x402: instructions sysvar {} not found among {} remaining accounts",
+        instructions::ID,
+        accounts.len()
+    );
+    X402Error::SysvarMissing
+}
+
+#[cold]
+#[inline(never)]
+fn invalid_sysvar(ix_sysvar: &AccountInfo) -> X402Error {
+    msg!(
+        "x402: instructions sysvar passed as writable={} signer={} owner={}",
+        ix_sysvar.is_writable,
+        ix_sysvar.is_signer,
+        ix_sysvar.owner
+    );
+    X402Error::InvalidSysvarAccount
+}
+
+#[cold]
+#[inline(never)]
+fn sysvar_unavailable(what: impl fmt::Display, err: impl fmt::Display) -> X402Error {
+    msg!("x402: loading {} failed: {}", what, err);
+    X402Error::SysvarUnavailable
+}
+
+#[cold]
+#[inline(never)]
+fn nothing_precedes(current_index: u16) -> X402Error {
+    msg!("x402: no payment instruction precedes instruction {}", current_index);
+    X402Error::NoPaymentInstruction
+}
+
+/// Walks back from `current_index` to the nearest SOL payment that
+/// `wanted` accepts, skipping ignorable programs (and, for a token price,
+/// the creation of the recipient's token account) and stepping over at
+/// most `options.max_lookback` other instructions. An earlier instruction
+/// of `program_id` that is not a qualifying payment ends the scan: it may
+/// be another priced call, which already took the payments before it. So
+/// does an instruction of any other program passed the instructions
+/// sysvar, which may be another x402 program's priced call.
+/// Only instructions before the current one are read, so nothing after
+/// the priced call ever pays for it.
+///
+/// When nothing qualifies, the closest candidate seen is logged and picks
+/// the error: a short payment to the recipient is `InsufficientPayment`, a
+/// token transfer to it `WrongPaymentToken`, and a payment to someone else
+/// `WrongRecipient`. Without any candidate the error is `NotAPayment`, or
+/// `NoPaymentInstruction` when no instruction was examined.
+pub(crate) fn find_payment(
+    ix_sysvar: &AccountInfo,
+    current_index: u16,
+    program_id: &Pubkey,
+    mut wanted: PaymentScan,
+    options: &ScanOptions,
+) -> Result<(MatchedPayment, u16), X402Error> {
+    let max_lookback = options.max_lookback.clamp(1, MAX_LOOKBACK);
+    let mut examined = 0u8;
+    let mut index = current_index;
+    #[cfg(not(feature = "strict-encodings"))]
+    if options.legacy_matcher {
+        msg!("x402: legacy matcher in use");
+    }
+
+    while examined < max_lookback {
+        let Some(previous) = index.checked_sub(1) else {
+            break;
+        };
+        index = previous;
+        // Peeks at the program first, so an instruction that cannot pay
+        // costs no allocation of its accounts and data.
+        let program = program_at(ix_sysvar, index)?;
+        if is_ignorable(&program) {
+            continue;
+        }
+        if !is_payment_program(&program, program_id) && !keys_eq(&program, &ASSOCIATED_TOKEN_PROGRAM_ID) {
+            examined += 1;
+            if reads_instructions(ix_sysvar, index)? {
+                msg!("x402: instruction {} reads the instructions sysvar; what it follows may be its payment", index);
+                break;
+            }
+            msg!("x402: instruction {} is not a payment: {:?}", index, MatchError::UnsupportedProgram);
+            if options.strict {
+                break;
+            }
+            continue;
+        }
+
+        let ix = instructions::load_instruction_at_checked(index as usize, ix_sysvar)
+            .map_err(|err| sysvar_unavailable(format_args!("instruction {}", index), err))?;
+        if is_ignorable_instruction(&ix) || wanted.creates_recipient_account(&ix) {
+            continue;
+        }
+
+        examined += 1;
+        match options.match_payment(&ix, program_id) {
+            Ok(payment) => {
+                if wanted.offer(&payment, &ix.program_id) {
+                    return Ok((payment, index));
+                }
+                if payment.kind.is_native() != matches!(wanted.currency(), Currency::Sol) {
+                    msg!("x402: {:?} does not pay in {}", payment.kind, Unit(wanted.currency()));
+                }
+            }
+            Err(reason) => msg!("x402: instruction {} is not a payment: {:?}", index, reason),
+        }
+        if keys_eq(&ix.program_id, program_id) {
+            msg!("x402: instruction {} is an earlier call of this program; what it follows is not this call's", index);
+            break;
+        }
+        if ix.accounts.iter().any(|meta| keys_eq(&meta.pubkey, &instructions::ID)) {
+            msg!("x402: instruction {} reads the instructions sysvar; what it follows may be its payment", index);
+            break;
+        }
+        if options.strict {
+            break;
+        }
+    }
+
+    match wanted.near_miss() {
+        Some(NearMiss::Insufficient { paid, price }) => {
+            let (mut paid_text, mut price_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+            msg!(
+                "x402: found a transfer of {} to the recipient, but the price is {}",
+                fmt_amount(paid, wanted.currency(), &mut paid_text),
+                fmt_amount(price, wanted.currency(), &mut price_text)
+            );
+            Err(X402Error::InsufficientPayment)
+        }
+        Some(NearMiss::WrongToken { found, .. }) => {
+            msg!(
+                "x402: found a transfer to the recipient in {}, but the price is in {}",
+                Unit(found.map_or(Currency::Sol, Currency::Token)),
+                Unit(wanted.currency())
+            );
+            Err(X402Error::WrongPaymentToken)
+        }
+        Some(NearMiss::WrongRecipient { found }) => {
+            msg!("x402: found a {} transfer to {} instead of {}", Unit(wanted.currency()), found, wanted.recipient());
+            Err(X402Error::WrongRecipient)
+        }
+        None if examined == 0 => Err(nothing_precedes(current_index)),
+        None => {
+            msg!("x402: no {} transfer within {} instructions", Unit(wanted.currency()), examined);
+            Err(X402Error::NotAPayment)
+        }
+    }
+}
@@ -0,0 +1,38 @@
+//! Reentrancy guard for handlers that invoke other programs.
+//!
+//! A handler with `reentrancy_guard = true` on `#[x402]` marks the accounts
+//! struct's mutable `x402_reentrancy_guard` account, the program's PDA of
+//! [`REENTRANCY_GUARD_SEED`], whose zero-copy type has a `u8` `entered`
+//! field, before its check and clears it after its body. A guarded handler
+//! reached again while one is still running, through a CPI its body made
+//! back into the program, finds the flag set and fails with
+//! `ReentrantCall` before checking or recording anything, so one payment
+//! can neither be counted nor emitted twice.
+//!
+//! The flag is cleared when the handler returns, so guarded calls made one
+//! after another in a transaction each find it clear; the body runs in a
+//! closure, so an early `return` clears it too. A handler that fails
+//! leaves it set, but the failure reverts the transaction, and the flag
+//! with it.
+
+use anchor_lang::prelude::*;
+
+use crate::error::X402Error;
+
+pub use x402_common::seeds::REENTRANCY_GUARD_SEED;
+
+/// Marks `handler` as running, or fails with `ReentrantCall` when a guarded
+/// handler already is.
+pub fn enter(entered: &mut u8, handler: &str) -> Result<()> {
+    if *entered != 0 {
+        msg!("x402: {} was invoked while a reentrancy-guarded instruction was running", handler);
+        return err!(X402Error::ReentrantCall);
+    }
+    *entered = 1;
+    Ok(())
+}
+
+/// Marks the guarded handler that is running as returned.
+pub fn exit(entered: &mut u8) {
+    *entered = 0;
+}
@@ -0,0 +1,44 @@
+//! Rent paid by payers, kept apart from what they paid for.
+//!
+//! A priced instruction that also creates an account charges the payer
+//! twice: the verified payment, which is revenue, and the new account's
+//! rent-exempt minimum, which stays in the account and goes back to
+//! whoever closes it. Counting both in `total_amount` would overstate
+//! revenue, so ledgers and stats that track rent add it to a separate
+//! `total_rent_paid: u64` with [`record`].
+//!
+//! `creates_account_space = N` on `#[x402]` declares that the call makes
+//! the payer fund new accounts of `N` bytes in all, such as an `init`
+//! account with `payer = payer`. Their rent-exempt minimum under the
+//! default rent is emitted as `<HANDLER>_X402_CREATED_RENT: u64`, and the
+//! handler warns when it is more than half its lowest price, since the
+//! payer's real cost is then far above the price; `allow_rent_heavy =
+//! true` silences it. SOL prices only.
+
+use anchor_lang::prelude::*;
+
+use crate::error::X402Error;
+
+/// Rent-exempt minimum of an Anchor account of type `T`, discriminator
+/// included.
+pub fn rent_for<T: Space>() -> Result<u64> {
+    rent_for_len(8 + T::INIT_SPACE)
+}
+
+/// Rent-exempt minimum of an account of `len` bytes, for accounts sized
+/// other than by `Space`, such as zero-copy ones.
+pub fn rent_for_len(len: usize) -> Result<u64> {
+    Ok(Rent::get()?.minimum_balance(len))
+}
+
+/// Adds `rent` to `total_rent_paid` and returns the new total. Fails
+/// without modifying anything on overflow.
+pub fn record(total_rent_paid: &mut u64, rent: u64) -> Result<u64> {
+    let next = total_rent_paid.checked_add(rent).ok_or_else(|| {
+        msg!("x402: total rent paid overflowed");
+        error!(X402Error::StatsOverflow)
+    })?;
+
+    *total_rent_paid = next;
+    Ok(next)
+}
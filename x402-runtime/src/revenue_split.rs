@@ -0,0 +1,161 @@
+//! Prices split between several recipients.
+//!
+//! With `splits(("<recipient>", 7000), ("<recipient>", 3000))` on
+//! `#[x402]` the price is paid as one SOL transfer per recipient, each for
+//! at least its share (see `x402_common::pricing::revenue_shares`). The
+//! shares are checked at compile time to sum to 10 000 basis points, and
+//! the first recipient is the one in `<HANDLER>_X402_PARAMS`, in place of
+//! `address`. There are 2 to [`MAX_REVENUE_SHARES`] of them, emitted as
+//! `<HANDLER>_X402_SPLITS`; shares round down, with the remainder on the
+//! first.
+//!
+//! The legs may come in any order, interleaved with transfers to other
+//! recipients, which are ignored, but all from the same source: the scan
+//! walks back from the priced instruction over SOL payments and
+//! [`IGNORABLE_PROGRAMS`](crate::IGNORABLE_PROGRAMS), stopping at the
+//! first other instruction. The legs are bound as `x402_revenue_legs` and
+//! emitted in `X402RevenueSplitVerifiedEvent` instead of
+//! `X402PaymentVerifiedEvent`.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions;
+use x402_common::events::X402RevenueSplitVerifiedEvent;
+use x402_common::pricing::revenue_shares;
+pub use x402_common::events::RevenueLeg;
+pub use x402_common::pricing::MAX_REVENUE_SHARES;
+
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::matcher::match_payment;
+use crate::native::{is_ignorable_instruction, load_sysvar};
+use crate::verify::{require_sol, Currency, VerifiedPayment, X402Params};
+
+/// One recipient of a handler's revenue split, emitted with the others as
+/// `<HANDLER>_X402_SPLITS`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RevenueShare {
+    pub recipient: Pubkey,
+    /// Share of the price, in basis points.
+    pub bps: u16,
+}
+
+/// The least each of `shares` must be paid out of `price`, in order.
+pub fn required_amounts(price: u64, shares: &[RevenueShare]) -> Vec<u64> {
+    let bps: Vec<u16> = shares.iter().map(|share| share.bps).collect();
+    revenue_shares(price, &bps)
+}
+
+/// Checks that the transfers before the priced instruction pay every one
+/// of `shares` its part of `params.price`.
+///
+/// A transfer qualifies for a recipient when it comes from the source of
+/// the nearest qualifying transfer and pays at least the recipient's
+/// required amount; the first one found for each recipient is its leg.
+/// A recipient left unpaid fails with `InsufficientPayment` when a short
+/// transfer to it was seen, and `MissingRevenueLeg` otherwise.
+///
+/// The returned payment has the summed amount, the legs' source as payer,
+/// the first recipient and the earliest leg's index, alongside every leg
+/// in the order of `shares`.
+#[inline(never)]
+pub fn verify_revenue_split(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    shares: &[RevenueShare],
+) -> Result<(VerifiedPayment, Vec<RevenueLeg>)> {
+    require_sol(params)?;
+    let required = required_amounts(params.price, shares);
+    let (ix_sysvar, current_index) = load_sysvar(remaining_accounts)?;
+
+    let mut legs: Vec<Option<(u64, u16)>> = vec![None; shares.len()];
+    let mut short: Vec<Option<u64>> = vec![None; shares.len()];
+    let mut payer: Option<Pubkey> = None;
+    let mut index = current_index;
+    while legs.iter().any(Option::is_none) {
+        let Some(previous) = index.checked_sub(1) else {
+            break;
+        };
+        index = previous;
+        let ix = instructions::load_instruction_at_checked(index as usize, ix_sysvar).map_err(|err| {
+            msg!("x402: loading instruction {} failed: {}", index, err);
+            error!(X402Error::SysvarUnavailable)
+        })?;
+        if is_ignorable_instruction(&ix) {
+            continue;
+        }
+        let payment = match match_payment(&ix, program_id) {
+            Ok(payment) if payment.kind.is_native() => payment,
+            _ => break,
+        };
+
+        let Some(position) = shares.iter().position(|share| keys_eq(&share.recipient, &payment.recipient)) else {
+            continue;
+        };
+        if legs[position].is_some() || payer.is_some_and(|payer| !keys_eq(&payer, &payment.payer)) {
+            continue;
+        }
+        if payment.amount < required[position] {
+            short[position] = Some(payment.amount);
+            continue;
+        }
+        payer = Some(payment.payer);
+        legs[position] = Some((payment.amount, index));
+    }
+
+    let mut verified = Vec::with_capacity(shares.len());
+    let mut total = 0u64;
+    let mut ix_index = current_index;
+    for (position, share) in shares.iter().enumerate() {
+        let Some((paid, leg_index)) = legs[position] else {
+            return match short[position] {
+                Some(paid) => {
+                    msg!(
+                        "x402: {} was paid {} but its share of {} is {}",
+                        share.recipient,
+                        paid,
+                        params.price,
+                        required[position]
+                    );
+                    err!(X402Error::InsufficientPayment)
+                }
+                None => {
+                    msg!("x402: no transfer pays {} its share of {}", share.recipient, params.price);
+                    err!(X402Error::MissingRevenueLeg)
+                }
+            };
+        };
+        total = total.checked_add(paid).ok_or_else(|| {
+            msg!("x402: revenue split total overflowed");
+            error!(X402Error::InsufficientPayment)
+        })?;
+        ix_index = ix_index.min(leg_index);
+        verified.push(RevenueLeg {
+            recipient: share.recipient,
+            bps: share.bps,
+            required: required[position],
+            paid,
+        });
+    }
+
+    let payment = VerifiedPayment {
+        amount: total,
+        payer: payer.unwrap_or_default(),
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index,
+    };
+    Ok((payment, verified))
+}
+
+/// Emits the `X402RevenueSplitVerifiedEvent` for a payment accepted by
+/// [`verify_revenue_split`], in place of `X402PaymentVerifiedEvent`.
+pub fn emit_revenue_split_verified(payment: &VerifiedPayment, legs: &[RevenueLeg], params: &X402Params, sequence: u64) {
+    emit!(X402RevenueSplitVerifiedEvent {
+        payer: payment.payer,
+        total: payment.amount,
+        price: params.price,
+        legs: legs.to_vec(),
+        sequence,
+    });
+}
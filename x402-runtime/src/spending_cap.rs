@@ -0,0 +1,57 @@
+//! Per-payer spending caps.
+//!
+//! A program opts in by giving the priced handler's accounts struct an
+//! optional, mutable `spending_cap` account whose type has the fields
+//! `payer: Pubkey`, `cap: u64`, `period_secs: i64`, `period_start: i64`
+//! and `spent: u64`, seeded by [`SPENDING_CAP_SEED`] and the payer, and
+//! setting `respect_spending_cap = true` on `#[x402]`. When the account is
+//! not supplied the payer is uncapped.
+
+use anchor_lang::prelude::*;
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+
+use crate::clock;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::verify::VerifiedPayment;
+
+pub use x402_common::seeds::SPENDING_CAP_SEED;
+
+/// Adds `payment` to a cap's running total, starting a new period first if
+/// the current one has elapsed. Fails without modifying anything when the
+/// cap belongs to someone else or the total would exceed it.
+pub fn charge(
+    owner: &Pubkey,
+    cap: u64,
+    period_secs: i64,
+    period_start: &mut i64,
+    spent: &mut u64,
+    payment: &VerifiedPayment,
+) -> Result<()> {
+    if !keys_eq(owner, &payment.payer) {
+        msg!("x402: spending cap of {} supplied for payer {}", owner, payment.payer);
+        return err!(X402Error::SpendingCapPayerMismatch);
+    }
+
+    let now = clock::now()?;
+    let rolled_over = now.saturating_sub(*period_start) >= period_secs;
+    let (start, already_spent) = if rolled_over { (now, 0) } else { (*period_start, *spent) };
+
+    let total = already_spent
+        .checked_add(payment.amount)
+        .ok_or(X402Error::SpendingCapExceeded)?;
+    if total > cap {
+        let (mut amount_text, mut total_text, mut cap_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+        msg!(
+            "x402: payment of {} would bring spending to {} over a cap of {}",
+            fmt_amount(payment.amount, payment.currency, &mut amount_text),
+            fmt_amount(total, payment.currency, &mut total_text),
+            fmt_amount(cap, payment.currency, &mut cap_text)
+        );
+        return err!(X402Error::SpendingCapExceeded);
+    }
+
+    *period_start = start;
+    *spent = total;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+//! Program-wide payment statistics.
+//!
+//! A program opts in by giving the priced handler's accounts struct a
+//! mutable `x402_stats` account whose type has the fields `sequence: u64`,
+//! `total_payments: u64` and `total_amount: u64`, seeded by
+//! [`STATS_SEED`], and setting `track_stats = true` on `#[x402]`. The
+//! payment's sequence number is bound as `x402_sequence: u64`, 0 for
+//! handlers that do not track stats. With `commitment_log = true` it also
+//! needs `commitment: [u8; 32]`; see [`crate::commitment`].
+//!
+//! `x402_stats` may be an `Account` or, for programs paying for every
+//! call's (de)serialization of it, a zero-copy `AccountLoader`; the
+//! expansion reaches its fields through [`StatsAccount`] either way.
+
+use std::cell::RefMut;
+use std::ops::DerefMut;
+
+use anchor_lang::prelude::*;
+use anchor_lang::ZeroCopy;
+
+use crate::error::X402Error;
+use crate::verify::VerifiedPayment;
+
+pub use x402_common::seeds::STATS_SEED;
+
+/// Counts `payment` and returns its sequence number. Sequence numbers start
+/// at 1 and increase by exactly one per verified payment across every
+/// priced instruction sharing the stats account, so a gap in the emitted
+/// events means an event was missed. Fails without modifying anything on
+/// overflow.
+pub fn record(
+    sequence: &mut u64,
+    total_payments: &mut u64,
+    total_amount: &mut u64,
+    payment: &VerifiedPayment,
+) -> Result<u64> {
+    let next_sequence = sequence.checked_add(1).ok_or_else(|| {
+        msg!("x402: payment sequence overflowed");
+        error!(X402Error::StatsOverflow)
+    })?;
+    let next_payments = total_payments.checked_add(1).ok_or_else(|| {
+        msg!("x402: payment count overflowed");
+        error!(X402Error::StatsOverflow)
+    })?;
+    let next_amount = total_amount.checked_add(payment.amount).ok_or_else(|| {
+        msg!("x402: total amount overflowed");
+        error!(X402Error::StatsOverflow)
+    })?;
+
+    *sequence = next_sequence;
+    *total_payments = next_payments;
+    *total_amount = next_amount;
+    Ok(next_sequence)
+}
+
+/// Mutable access to the fields of an `x402_stats` account, whichever way
+/// it is loaded.
+pub trait StatsAccount {
+    type Stats;
+    type Fields<'a>: DerefMut<Target = Self::Stats>
+    where
+        Self: 'a;
+
+    fn stats_mut(&mut self) -> Result<Self::Fields<'_>>;
+}
+
+impl<'info, T: AccountSerialize + AccountDeserialize + Owner + Clone> StatsAccount for Account<'info, T> {
+    type Stats = T;
+    type Fields<'a>
+        = &'a mut T
+    where
+        Self: 'a;
+
+    fn stats_mut(&mut self) -> Result<&mut T> {
+        Ok(&mut **self)
+    }
+}
+
+/// Writes land in the account's data directly; nothing is serialized when
+/// the instruction exits.
+impl<'info, T: ZeroCopy + Owner> StatsAccount for AccountLoader<'info, T> {
+    type Stats = T;
+    type Fields<'a>
+        = RefMut<'a, T>
+    where
+        Self: 'a;
+
+    fn stats_mut(&mut self) -> Result<RefMut<'_, T>> {
+        self.load_mut()
+    }
+}
@@ -0,0 +1,177 @@
+//! Paying and calling in one instruction.
+//!
+//! Some wallets and MPC signers only sign single-instruction transactions,
+//! so they cannot put a transfer before the priced instruction. A program
+//! can offer them a `pay_and_invoke(amount, inner_data)` instruction that
+//! transfers `amount` from its first account (the payer) to its second
+//! (the recipient) and then invokes itself with `inner_data`, which must
+//! be one of an allowlist of its own priced instructions.
+//!
+//! A handler with `allow_trampoline = true` accepts that call in place of
+//! a preceding payment: when it runs as a cross-program invocation it
+//! requires the transaction-level instruction to be its own program's
+//! `pay_and_invoke`, calling it directly, with arguments that pay its
+//! price. Nesting is limited to that one level, and `pay_and_invoke`
+//! itself must run at transaction level (see [`require_top_level`]), so
+//! it cannot invoke itself.
+//!
+//! Any other invocation by a program is refused by handlers paid by a
+//! preceding instruction with `InvokedByCpi` (see
+//! [`require_top_level_call`]): the scan would find the payment before
+//! that program's instruction, which could then be spent on every call it
+//! makes. `allow_cpi = true` accepts such calls anyway, for callers trusted
+//! to make one priced call per instruction. Deposit, accumulated and
+//! `balance_delta` handlers accept calls from other programs regardless.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::solana_program::instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT};
+use anchor_lang::solana_program::sysvar::instructions;
+use x402_common::amount::{fmt_amount, MAX_AMOUNT_LEN};
+
+use crate::cmp::{expect_key, keys_eq};
+use crate::error::X402Error;
+use crate::native::{load_sysvar, Currency, VerifiedPayment, X402Params};
+use crate::verify::require_sol;
+
+/// Anchor discriminator of a `pay_and_invoke` instruction,
+/// `sha256("global:pay_and_invoke")[..8]`.
+pub const PAY_AND_INVOKE_DISCRIMINATOR: [u8; 8] = [199, 176, 225, 63, 147, 194, 212, 215];
+
+/// Arguments of a `pay_and_invoke` instruction, after its discriminator.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, Eq)]
+pub struct PayAndInvokeArgs {
+    pub amount: u64,
+    pub inner_data: Vec<u8>,
+}
+
+/// Anchor discriminator of the instruction handled by `handler`,
+/// `sha256("global:<handler>")[..8]`.
+pub fn handler_discriminator(handler: &str) -> [u8; 8] {
+    let hash = hashv(&[b"global:", handler.as_bytes()]).to_bytes();
+    let mut discriminator = [0; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+/// Whether the current instruction runs as a cross-program invocation.
+pub fn is_cpi() -> bool {
+    get_stack_height() > TRANSACTION_LEVEL_STACK_HEIGHT
+}
+
+/// Fails unless the current instruction runs at transaction level. Called
+/// first by `pay_and_invoke`, so neither it nor anything it invokes can
+/// invoke it again.
+pub fn require_top_level() -> Result<()> {
+    let height = get_stack_height();
+    if height > TRANSACTION_LEVEL_STACK_HEIGHT {
+        msg!("x402: pay_and_invoke invoked at stack height {}", height);
+        return err!(X402Error::TrampolineNotTopLevel);
+    }
+    Ok(())
+}
+
+/// Fails when `handler` runs as a cross-program invocation. Called before
+/// the scan by handlers paid by a preceding instruction, without
+/// `allow_cpi`: invoked by another program, the scan would read that
+/// program's transaction-level instruction, and one payment before it
+/// would pay for every call the program makes.
+pub fn require_top_level_call(handler: &str) -> Result<()> {
+    require_top_level_call_at(handler, get_stack_height())
+}
+
+/// [`require_top_level_call`] at a given stack height.
+pub fn require_top_level_call_at(handler: &str, stack_height: usize) -> Result<()> {
+    if stack_height > TRANSACTION_LEVEL_STACK_HEIGHT {
+        msg!("x402: {} invoked by another program at stack height {}", handler, stack_height);
+        return err!(X402Error::InvokedByCpi);
+    }
+    Ok(())
+}
+
+/// Checks that `handler`, running as a cross-program invocation, was
+/// invoked by this program's `pay_and_invoke` paying `params`; see
+/// [`verify_trampoline_payment_at`].
+#[inline(never)]
+pub fn verify_trampoline_payment(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    payer: &Pubkey,
+    handler: &str,
+) -> Result<VerifiedPayment> {
+    verify_trampoline_payment_at(remaining_accounts, program_id, params, payer, handler, get_stack_height())
+}
+
+/// [`verify_trampoline_payment`] at a given stack height.
+///
+/// The transaction-level instruction, read from the instructions sysvar,
+/// must be `program_id`'s `pay_and_invoke` with `handler`'s instruction as
+/// its inner data, and `stack_height` one above transaction level so that
+/// instruction is the direct caller. Its first account must be `payer`,
+/// signing, its second the recipient, and its amount at least the price.
+/// The payment's index is the `pay_and_invoke` instruction's.
+pub fn verify_trampoline_payment_at(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    payer: &Pubkey,
+    handler: &str,
+    stack_height: usize,
+) -> Result<VerifiedPayment> {
+    require_sol(params)?;
+    if stack_height != TRANSACTION_LEVEL_STACK_HEIGHT + 1 {
+        msg!("x402: {} invoked at stack height {}", handler, stack_height);
+        return err!(X402Error::NotInvokedByTrampoline);
+    }
+
+    let (ix_sysvar, current_index) = load_sysvar(remaining_accounts)?;
+    let outer = instructions::load_instruction_at_checked(current_index as usize, ix_sysvar).map_err(|err| {
+        msg!("x402: loading the current instruction failed: {}", err);
+        error!(X402Error::SysvarUnavailable)
+    })?;
+    if !keys_eq(&outer.program_id, program_id) || !outer.data.starts_with(&PAY_AND_INVOKE_DISCRIMINATOR) {
+        msg!("x402: {} was invoked by {}, not by pay_and_invoke", handler, outer.program_id);
+        return err!(X402Error::NotInvokedByTrampoline);
+    }
+    let args = PayAndInvokeArgs::try_from_slice(&outer.data[8..]).map_err(|_| {
+        msg!("x402: pay_and_invoke arguments could not be decoded");
+        error!(X402Error::NotInvokedByTrampoline)
+    })?;
+    if !args.inner_data.starts_with(&handler_discriminator(handler)) {
+        msg!("x402: pay_and_invoke names another instruction than {}", handler);
+        return err!(X402Error::NotInvokedByTrampoline);
+    }
+
+    let source = outer.accounts.first();
+    expect_key(source.map(|meta| &meta.pubkey), payer, X402Error::MissingPaymentAccount, X402Error::SignerDidNotPay)
+        .inspect_err(|_| msg!("x402: pay_and_invoke was not paid by {}", payer))?;
+    if !source.is_some_and(|meta| meta.is_signer) {
+        msg!("x402: {} did not sign pay_and_invoke", payer);
+        return err!(X402Error::SignerDidNotPay);
+    }
+    let recipient = outer.accounts.get(1).map(|meta| &meta.pubkey);
+    expect_key(recipient, &params.recipient, X402Error::MissingPaymentAccount, X402Error::WrongRecipient).inspect_err(
+        |_| match recipient {
+            Some(recipient) => msg!("x402: pay_and_invoke paid {} instead of {}", recipient, params.recipient),
+            None => msg!("x402: pay_and_invoke carries no recipient account"),
+        },
+    )?;
+    if args.amount < params.price {
+        let (mut paid_text, mut price_text) = ([0; MAX_AMOUNT_LEN], [0; MAX_AMOUNT_LEN]);
+        msg!(
+            "x402: pay_and_invoke paid {} but the price is {}",
+            fmt_amount(args.amount, params.currency, &mut paid_text),
+            fmt_amount(params.price, params.currency, &mut price_text)
+        );
+        return err!(X402Error::InsufficientPayment);
+    }
+
+    Ok(VerifiedPayment {
+        amount: args.amount,
+        payer: *payer,
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index: current_index,
+    })
+}
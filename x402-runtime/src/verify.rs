@@ -0,0 +1,326 @@
+//! The verification shared by every `#[x402]` handler.
+//!
+//! The macro expansion only builds an [`X402Params`] and calls
+//! [`verify_payment`]; keeping the body here, out of line, means a program
+//! with many priced instructions carries one copy of it instead of one per
+//! instruction. The scan itself lives in [`crate::native`]; these are its
+//! Anchor entry points, plus the checks and events only Anchor programs
+//! use.
+//!
+//! The options of `#[x402]` served here:
+//!
+//! - `allow_split_payers = true` accepts up to `max_split_payers` transfers
+//!   from distinct payers summing to the price ([`verify_split_payment`]),
+//!   bound as `x402_contributions`.
+//! - `require_fee_payer = true` requires the payment to come from, and be
+//!   signed by, the handler's payer ([`require_fee_payer`]).
+//! - `emit_event = false` drops `X402PaymentVerifiedEvent`
+//!   ([`emit_payment_verified`]), on by default.
+//! - `emit_latency = true` also emits `X402PaymentLatencyEvent` with the
+//!   current slot ([`emit_payment_latency`]). The payment shares the
+//!   transaction, so its payment and consumed slots are equal.
+//! - `record_fingerprint = true` fingerprints the call from its raw
+//!   instruction data, read back from the instructions sysvar, the payer
+//!   and the price ([`request_fingerprint`], `x402_common::fingerprint`),
+//!   binds it as `x402_fingerprint: [u8; 32]` and emits it in
+//!   `X402RequestFingerprintEvent`, so off-chain caches key results by the
+//!   same value.
+//! - `require_nonce = true` takes an `x402_nonce: [u8; 32]` argument, which
+//!   `#[x402_program]` appends, and emits it untouched in
+//!   `X402PaymentVerifiedEvent::nonce`, so a server that issued it with its
+//!   402 response can check the paid transaction answers that request (see
+//!   `x402_client::challenge`). Nothing on-chain checks it is unused; the
+//!   server accepts each nonce once. Split payments, whose events carry no
+//!   nonce, cannot take one.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::instructions;
+use x402_common::events::{
+    PaymentContribution, X402PaymentLatencyEvent, X402RequestFingerprintEvent, X402SplitPaymentVerifiedEvent,
+};
+use x402_common::fingerprint::instruction_fingerprint;
+
+use crate::balance_delta::CURRENT_INSTRUCTION;
+use crate::clock;
+use crate::cmp::keys_eq;
+use crate::error::X402Error;
+use crate::matcher::{match_payment, PaymentScan};
+use crate::native::{check_currency, find_payment, load_sysvar, precheck, verify_sol_payment};
+use crate::trampoline::{PayAndInvokeArgs, PAY_AND_INVOKE_DISCRIMINATOR};
+pub use crate::native::{
+    is_ignorable, is_ignorable_instruction, Currency, ScanOptions, VerifiedPayment, X402Params, IGNORABLE_PROGRAMS,
+    MAX_LOOKBACK, MAX_SPLIT_PAYERS,
+};
+
+/// Checks that the instruction before the current one pays `params`,
+/// skipping [`IGNORABLE_PROGRAMS`]; see [`verify_payment_with`].
+#[inline(never)]
+pub fn verify_payment(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+) -> Result<VerifiedPayment> {
+    verify_payment_with(remaining_accounts, program_id, params, &ScanOptions::DEFAULT)
+}
+
+/// Checks that a preceding instruction pays `params`: the nearest SOL
+/// payment within `scan`, skipping [`IGNORABLE_PROGRAMS`]. This is
+/// [`verify_sol_payment`] with its failure as an Anchor error.
+///
+/// The instructions sysvar is located in `remaining_accounts` and must be
+/// passed read-only, unsigned and owned by the sysvar program. Every
+/// failing step logs what it was doing and returns its own [`X402Error`].
+/// Nothing is emitted; see [`emit_payment_verified`].
+#[inline(never)]
+pub fn verify_payment_with(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    scan: &ScanOptions,
+) -> Result<VerifiedPayment> {
+    Ok(verify_sol_payment(remaining_accounts, program_id, params, scan)?)
+}
+
+/// Fails a call that no preceding payment can cover before anything else
+/// about it is read: the instructions sysvar is missing or unusable, or
+/// the call is the first instruction. The expansion runs this ahead of
+/// resolving the price; see [`crate::native::precheck`].
+#[inline(never)]
+pub fn precheck_payment(remaining_accounts: &[AccountInfo]) -> Result<()> {
+    Ok(precheck(remaining_accounts)?)
+}
+
+/// Like [`verify_payment`], but lets up to `max_payers` consecutive
+/// transfers from distinct sources jointly cover the price, as when
+/// members of a multisig split a call.
+///
+/// Walking back from the priced instruction, transfers are collected until
+/// an instruction is not a SOL payment to the recipient;
+/// [`IGNORABLE_PROGRAMS`] are stepped over. The nearest one
+/// must qualify exactly as for [`verify_payment`]; `signer` (the handler's
+/// payer) must be one of the sources. The returned payment has the summed
+/// amount, `signer` as payer and the earliest transfer's index, alongside
+/// every contribution, nearest first.
+#[inline(never)]
+pub fn verify_split_payment(
+    remaining_accounts: &[AccountInfo],
+    program_id: &Pubkey,
+    params: &X402Params,
+    signer: &Pubkey,
+    max_payers: u8,
+) -> Result<(VerifiedPayment, Vec<PaymentContribution>)> {
+    require_sol(params)?;
+    let (ix_sysvar, current_index) = load_sysvar(remaining_accounts)?;
+    // The nearest transfer only has to reach the recipient; the price is
+    // checked against the total.
+    let wanted = PaymentScan::new(params.recipient, 0, None);
+    let (nearest, mut ix_index) = find_payment(ix_sysvar, current_index, program_id, wanted, &ScanOptions::DEFAULT)?;
+
+    let mut total = nearest.amount;
+    let mut contributions = vec![PaymentContribution {
+        payer: nearest.payer,
+        amount: nearest.amount,
+    }];
+
+    let mut cursor = ix_index;
+    while contributions.len() < usize::from(max_payers.min(MAX_SPLIT_PAYERS)) {
+        let Some(index) = cursor.checked_sub(1) else {
+            break;
+        };
+        cursor = index;
+        let Ok(ix) = instructions::load_instruction_at_checked(index as usize, ix_sysvar) else {
+            break;
+        };
+        if is_ignorable_instruction(&ix) {
+            continue;
+        }
+        let payment = match match_payment(&ix, program_id) {
+            Ok(payment) if payment.kind.is_native() && keys_eq(&payment.recipient, &params.recipient) => payment,
+            _ => break,
+        };
+
+        if contributions.iter().any(|c| keys_eq(&c.payer, &payment.payer)) {
+            msg!("x402: {} contributed more than one transfer", payment.payer);
+            return err!(X402Error::DuplicateSplitPayer);
+        }
+        total = total.checked_add(payment.amount).ok_or_else(|| {
+            msg!("x402: split payment total overflowed");
+            error!(X402Error::InsufficientPayment)
+        })?;
+        contributions.push(PaymentContribution {
+            payer: payment.payer,
+            amount: payment.amount,
+        });
+        ix_index = index;
+    }
+
+    if total < params.price {
+        msg!(
+            "x402: {} payers paid {} but the price is {}",
+            contributions.len(),
+            total,
+            params.price
+        );
+        return err!(X402Error::InsufficientPayment);
+    }
+
+    if !contributions.iter().any(|c| keys_eq(&c.payer, signer)) {
+        msg!("x402: signer {} is not among the split payers", signer);
+        return err!(X402Error::SignerDidNotPay);
+    }
+
+    let payment = VerifiedPayment {
+        amount: total,
+        payer: *signer,
+        recipient: params.recipient,
+        currency: Currency::Sol,
+        ix_index,
+    };
+    Ok((payment, contributions))
+}
+
+/// Requires the payment's source to be `signer` (the handler's payer) and
+/// to have signed the payment instruction.
+///
+/// This approximates "whoever pays the protocol also pays the transaction
+/// fee": the fee payer is the message's first signer, which the
+/// instructions sysvar does not expose. What can be enforced is that the
+/// payment was authorized by the same key that signs for the handler, so a
+/// relayer cannot attach someone else's transfer to its own call.
+pub fn require_fee_payer(
+    remaining_accounts: &[AccountInfo],
+    payment: &VerifiedPayment,
+    signer: &Pubkey,
+) -> Result<()> {
+    if !keys_eq(&payment.payer, signer) {
+        msg!("x402: paid by {} but the handler's payer is {}", payment.payer, signer);
+        return err!(X402Error::FeePayerMismatch);
+    }
+    if payment.ix_index == CURRENT_INSTRUCTION {
+        // The handler's own transfer, signed by construction.
+        return Ok(());
+    }
+
+    let (ix_sysvar, _) = load_sysvar(remaining_accounts)?;
+    let ix = instructions::load_instruction_at_checked(payment.ix_index as usize, ix_sysvar).map_err(|err| {
+        msg!("x402: loading instruction {} failed: {}", payment.ix_index, err);
+        error!(X402Error::SysvarUnavailable)
+    })?;
+    if !ix.accounts.iter().any(|meta| keys_eq(&meta.pubkey, &payment.payer) && meta.is_signer) {
+        msg!("x402: payment source {} did not sign the payment", payment.payer);
+        return err!(X402Error::FeePayerMismatch);
+    }
+
+    Ok(())
+}
+
+/// [`crate::native::check_currency`] for the Anchor entry points.
+pub(crate) fn require_sol(params: &X402Params) -> Result<()> {
+    Ok(check_currency(params)?)
+}
+
+/// Emits the `X402PaymentVerifiedEventV2` for `payment`, or version 1,
+/// `X402PaymentVerifiedEvent`, when built with `legacy-events`. `sequence`
+/// is the number returned by [`crate::stats::record`], or 0 when the
+/// instruction does not track stats; `experiment_arm` is the arm returned
+/// by [`crate::experiment::Experiment::apply_from`], if any, and `nonce`
+/// the call's `x402_nonce` argument, passed through unchecked.
+pub fn emit_payment_verified(
+    payment: &VerifiedPayment,
+    params: &X402Params,
+    sequence: u64,
+    experiment_arm: Option<u8>,
+    nonce: Option<[u8; 32]>,
+) {
+    #[cfg(feature = "legacy-events")]
+    emit!(x402_common::events::X402PaymentVerifiedEvent {
+        payer: payment.payer,
+        recipient: payment.recipient,
+        amount: payment.amount,
+        price: params.price,
+        sequence,
+        experiment_arm,
+        nonce,
+    });
+    #[cfg(not(feature = "legacy-events"))]
+    emit!(x402_common::events::X402PaymentVerifiedEventV2 {
+        version: x402_common::events::PAYMENT_VERIFIED_VERSION,
+        payer: payment.payer,
+        recipient: payment.recipient,
+        amount: payment.amount,
+        price: params.price,
+        sequence,
+        experiment_arm,
+        nonce,
+    });
+}
+
+/// Emits the `X402SplitPaymentVerifiedEvent` for a payment accepted by
+/// [`verify_split_payment`], in place of `X402PaymentVerifiedEvent`.
+pub fn emit_split_payment_verified(
+    payment: &VerifiedPayment,
+    contributions: &[PaymentContribution],
+    params: &X402Params,
+    sequence: u64,
+) {
+    emit!(X402SplitPaymentVerifiedEvent {
+        signer: payment.payer,
+        recipient: payment.recipient,
+        total: payment.amount,
+        price: params.price,
+        contributions: contributions.to_vec(),
+        sequence,
+    });
+}
+
+/// Emits the `X402PaymentLatencyEvent` for a payment by `payer` used in the
+/// current slot. `payment_slot` is `None` when the payment was made in this
+/// transaction, as with `#[x402]`, and the slot a stored payment was
+/// recorded in otherwise. Without a clock the event is skipped rather than
+/// the call failed.
+pub fn emit_payment_latency(payer: &Pubkey, sequence: u64, payment_slot: Option<u64>) -> Result<()> {
+    let Ok(consumed_slot) = clock::slot() else {
+        msg!("x402: latency of payment {} not reported", sequence);
+        return Ok(());
+    };
+    emit!(X402PaymentLatencyEvent {
+        payer: *payer,
+        sequence,
+        payment_slot: payment_slot.unwrap_or(consumed_slot),
+        consumed_slot,
+    });
+    Ok(())
+}
+
+/// Fingerprints the current instruction, read back from the instructions
+/// sysvar, as a call by `payer` at `price`; see
+/// `x402_common::fingerprint::instruction_fingerprint`. When the current
+/// instruction is a `pay_and_invoke`, the instruction it invokes is
+/// fingerprinted, so a call has the same fingerprint either way.
+pub fn request_fingerprint(accounts: &[AccountInfo], payer: &Pubkey, price: u64) -> Result<[u8; 32]> {
+    let (ix_sysvar, current_index) = load_sysvar(accounts)?;
+    let current = instructions::load_instruction_at_checked(current_index as usize, ix_sysvar).map_err(|err| {
+        msg!("x402: loading the current instruction failed: {}", err);
+        X402Error::SysvarUnavailable
+    })?;
+    let trampoline = current
+        .data
+        .strip_prefix(&PAY_AND_INVOKE_DISCRIMINATOR[..])
+        .and_then(|args| PayAndInvokeArgs::try_from_slice(args).ok());
+    let data = trampoline.map_or(current.data, |args| args.inner_data);
+
+    instruction_fingerprint(payer, &data, price).ok_or_else(|| {
+        msg!("x402: instruction data of {} bytes has no tag to fingerprint", data.len());
+        error!(X402Error::SysvarUnavailable)
+    })
+}
+
+/// Emits the `X402RequestFingerprintEvent` for a fingerprint returned by
+/// [`request_fingerprint`].
+pub fn emit_request_fingerprint(payer: &Pubkey, price: u64, fingerprint: [u8; 32]) {
+    emit!(X402RequestFingerprintEvent {
+        payer: *payer,
+        price,
+        fingerprint,
+    });
+}
@@ -0,0 +1,241 @@
+//! Prices paid in parts: transfers counted into a payer's accumulator for
+//! one instruction tag, each through its own accumulating instruction, and
+//! debited by the `accumulated = true` call once they cover the price.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction;
+use x402_common::seeds::accumulator_address;
+use x402_runtime::accumulator::{credit, debit, debit_accepted, verify_contribution};
+use x402_runtime::balance_delta::CURRENT_INSTRUCTION;
+use x402_runtime::{Currency, TagAliases, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 50_000_000;
+const TAG: &str = "enterprise_v1";
+/// `TAG` renamed, accepting accumulators toward `TAG` until `UNTIL`.
+const RENAMED: &str = "enterprise_v2";
+const UNTIL: i64 = 1_767_225_600;
+const ALIASES: TagAliases = TagAliases { tags: &[TAG], until: UNTIL };
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+    }
+}
+
+/// A payer's accumulator toward `TAG`, starting empty.
+struct Saver {
+    payer: Pubkey,
+    account: Pubkey,
+    balance: u64,
+}
+
+impl Saver {
+    fn new() -> Self {
+        Saver::toward(TAG)
+    }
+
+    fn toward(tag: &str) -> Self {
+        let payer = Pubkey::new_unique();
+        Saver {
+            payer,
+            account: accumulator_address(&PROGRAM_ID, tag, &payer),
+            balance: 0,
+        }
+    }
+
+    /// A transfer of `amount` to the recipient, then the accumulating
+    /// instruction, as one transaction.
+    fn accumulate(&mut self, amount: u64) -> Result<u64> {
+        let transfer = system_instruction::transfer(&self.payer, &RECIPIENT, amount);
+        let mut sysvar = Sysvar::new(&[transfer], &self.payer);
+        let contribution = verify_contribution(
+            &[sysvar.account_info(false, false)],
+            &PROGRAM_ID,
+            &params(),
+            "accumulate",
+        )?;
+        let tag = if self.account == accumulator_address(&PROGRAM_ID, RENAMED, &self.payer) { RENAMED } else { TAG };
+        credit(&self.account, &PROGRAM_ID, tag, &mut self.balance, &contribution)
+    }
+
+    /// The priced call, as an `accumulated = true` handler makes it.
+    fn call(&mut self) -> Result<u64> {
+        let (mut lamports, mut data) = (1_000_000_000, []);
+        let payer = AccountInfo::new(&self.payer, true, true, &mut lamports, &mut data, &PROGRAM_ID, false, 0);
+        let payment = debit(&self.account, &PROGRAM_ID, TAG, &payer, &mut self.balance, &params())?;
+        assert_eq!(payment.payer, self.payer);
+        assert_eq!(payment.recipient, RECIPIENT);
+        assert_eq!(payment.ix_index, CURRENT_INSTRUCTION);
+        Ok(payment.amount)
+    }
+
+    /// The priced call of the instruction renamed to `RENAMED`, at `now`.
+    fn call_renamed(&mut self, now: i64) -> Result<u64> {
+        let (mut lamports, mut data) = (1_000_000_000, []);
+        let payer = AccountInfo::new(&self.payer, true, true, &mut lamports, &mut data, &PROGRAM_ID, false, 0);
+        let tags = ALIASES.accepted(RENAMED, now);
+        let payment = debit_accepted(&self.account, &PROGRAM_ID, &tags, &payer, &mut self.balance, &params())?;
+        Ok(payment.amount)
+    }
+}
+
+#[test]
+fn three_parts_pay_for_a_call() {
+    let mut saver = Saver::new();
+    assert_eq!(saver.accumulate(10_000_000).unwrap(), 10_000_000);
+    assert_eq!(saver.accumulate(15_000_000).unwrap(), 25_000_000);
+    assert_eq!(saver.accumulate(25_000_000).unwrap(), PRICE);
+
+    assert_eq!(saver.call().unwrap(), PRICE);
+    assert_eq!(saver.balance, 0);
+}
+
+#[test]
+fn a_call_before_the_price_is_saved_fails_and_keeps_the_balance() {
+    let mut saver = Saver::new();
+    saver.accumulate(20_000_000).unwrap();
+    saver.accumulate(29_999_999).unwrap();
+
+    assert_eq!(saver.call().unwrap_err(), X402Error::InsufficientAccumulation.into());
+    assert_eq!(saver.balance, PRICE - 1);
+
+    saver.accumulate(1).unwrap();
+    assert_eq!(saver.call().unwrap(), PRICE);
+}
+
+#[test]
+fn the_leftover_carries_to_the_next_call() {
+    let mut saver = Saver::new();
+    saver.accumulate(80_000_000).unwrap();
+
+    saver.call().unwrap();
+    assert_eq!(saver.balance, 30_000_000);
+    assert_eq!(saver.call().unwrap_err(), X402Error::InsufficientAccumulation.into());
+
+    saver.accumulate(20_000_000).unwrap();
+    saver.call().unwrap();
+    assert_eq!(saver.balance, 0);
+}
+
+#[test]
+fn savings_toward_another_tag_pay_nothing() {
+    let mut saver = Saver::new();
+    saver.accumulate(PRICE).unwrap();
+
+    let (mut lamports, mut data) = (0, []);
+    let payer = AccountInfo::new(
+        &saver.payer,
+        true,
+        false,
+        &mut lamports,
+        &mut data,
+        &PROGRAM_ID,
+        false,
+        0,
+    );
+    let err = debit(
+        &saver.account,
+        &PROGRAM_ID,
+        "premium_v1",
+        &payer,
+        &mut saver.balance,
+        &params(),
+    )
+    .unwrap_err();
+    assert_eq!(err, X402Error::AccumulatorMismatch.into());
+    assert_eq!(saver.balance, PRICE);
+}
+
+#[test]
+fn another_payers_accumulator_is_neither_credited_nor_debited() {
+    let mut saver = Saver::new();
+    saver.account = accumulator_address(&PROGRAM_ID, TAG, &Pubkey::new_unique());
+    assert_eq!(
+        saver.accumulate(PRICE).unwrap_err(),
+        X402Error::AccumulatorMismatch.into()
+    );
+
+    saver.balance = PRICE;
+    assert_eq!(saver.call().unwrap_err(), X402Error::AccumulatorMismatch.into());
+    assert_eq!(saver.balance, PRICE);
+}
+
+#[test]
+fn an_unsigned_payer_cannot_spend_the_balance() {
+    let mut saver = Saver::new();
+    saver.accumulate(PRICE).unwrap();
+
+    let (mut lamports, mut data) = (0, []);
+    let payer = AccountInfo::new(
+        &saver.payer,
+        false,
+        false,
+        &mut lamports,
+        &mut data,
+        &PROGRAM_ID,
+        false,
+        0,
+    );
+    let err = debit(&saver.account, &PROGRAM_ID, TAG, &payer, &mut saver.balance, &params()).unwrap_err();
+    assert_eq!(err, X402Error::AccumulatorMismatch.into());
+    assert_eq!(saver.balance, PRICE);
+}
+
+#[test]
+fn a_balance_that_would_overflow_is_not_credited() {
+    let mut saver = Saver::new();
+    saver.balance = u64::MAX - 1;
+    assert_eq!(saver.accumulate(2).unwrap_err(), X402Error::StatsOverflow.into());
+    assert_eq!(saver.balance, u64::MAX - 1);
+}
+
+#[test]
+fn an_accumulating_instruction_without_a_transfer_before_it_fails() {
+    let saver = Saver::new();
+    let mut sysvar = Sysvar::new(&[], &saver.payer);
+    let err = verify_contribution(
+        &[sysvar.account_info(false, false)],
+        &PROGRAM_ID,
+        &params(),
+        "accumulate",
+    )
+    .unwrap_err();
+    assert_eq!(err, X402Error::NoPaymentInstruction.into());
+}
+
+#[test]
+fn savings_toward_the_earlier_tag_pay_the_renamed_instruction_until_the_window_closes() {
+    let mut saver = Saver::new();
+    saver.accumulate(2 * PRICE).unwrap();
+
+    assert_eq!(saver.call_renamed(UNTIL).unwrap(), PRICE);
+    assert_eq!(saver.balance, PRICE);
+
+    assert_eq!(saver.call_renamed(UNTIL + 1).unwrap_err(), X402Error::AccumulatorMismatch.into());
+    assert_eq!(saver.balance, PRICE);
+}
+
+#[test]
+fn savings_toward_the_new_tag_pay_inside_and_after_the_window() {
+    let mut saver = Saver::toward(RENAMED);
+    saver.accumulate(2 * PRICE).unwrap();
+
+    assert_eq!(saver.call_renamed(UNTIL - 1).unwrap(), PRICE);
+    assert_eq!(saver.call_renamed(UNTIL + 1).unwrap(), PRICE);
+    assert_eq!(saver.balance, 0);
+}
+
+#[test]
+fn the_earlier_tag_accepts_nothing_of_the_new_one() {
+    let mut saver = Saver::toward(RENAMED);
+    saver.accumulate(PRICE).unwrap();
+    assert_eq!(saver.call().unwrap_err(), X402Error::AccumulatorMismatch.into());
+    assert_eq!(saver.balance, PRICE);
+}
@@ -0,0 +1,153 @@
+//! The upgrade authority check behind a config's admin, against program
+//! and program data accounts in the upgradeable loader's layout.
+
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::bpf_loader_upgradeable;
+use x402_runtime::admin::{assert_upgrade_authority, X402Admin};
+use x402_runtime::X402Error;
+
+const PROGRAM: Pubkey = Pubkey::new_from_array([7; 32]);
+const BPF_LOADER: Pubkey = Pubkey::new_from_array([2; 32]);
+
+struct Deployment {
+    /// The program running the check.
+    program_id: Pubkey,
+    program_owner: Pubkey,
+    program_data: Pubkey,
+    program_bytes: Vec<u8>,
+    data_bytes: Vec<u8>,
+    program_lamports: u64,
+    data_lamports: u64,
+}
+
+impl Deployment {
+    /// `PROGRAM` deployed with `authority` able to upgrade it, or immutable.
+    fn new(authority: Option<Pubkey>) -> Self {
+        let program_data = Pubkey::find_program_address(&[PROGRAM.as_ref()], &bpf_loader_upgradeable::ID).0;
+        let mut program_bytes = 2u32.to_le_bytes().to_vec();
+        program_bytes.extend_from_slice(program_data.as_ref());
+        let mut data_bytes = 3u32.to_le_bytes().to_vec();
+        data_bytes.extend_from_slice(&42u64.to_le_bytes());
+        match authority {
+            Some(authority) => {
+                data_bytes.push(1);
+                data_bytes.extend_from_slice(authority.as_ref());
+            }
+            None => data_bytes.extend_from_slice(&[0; 33]),
+        }
+        Deployment {
+            program_id: PROGRAM,
+            program_owner: bpf_loader_upgradeable::ID,
+            program_data,
+            program_bytes,
+            data_bytes,
+            program_lamports: 1,
+            data_lamports: 1,
+        }
+    }
+
+    fn check(&mut self, signer: &Pubkey, signed: bool) -> Result<()> {
+        let loader = bpf_loader_upgradeable::ID;
+        let program = AccountInfo::new(
+            &PROGRAM,
+            false,
+            false,
+            &mut self.program_lamports,
+            &mut self.program_bytes,
+            &self.program_owner,
+            true,
+            0,
+        );
+        let program_data = AccountInfo::new(
+            &self.program_data,
+            false,
+            false,
+            &mut self.data_lamports,
+            &mut self.data_bytes,
+            &loader,
+            false,
+            0,
+        );
+        with_signer(signer, signed, |signer| {
+            assert_upgrade_authority(&self.program_id, &program, &program_data, signer)
+        })
+    }
+}
+
+fn with_signer(key: &Pubkey, signed: bool, run: impl FnOnce(&AccountInfo) -> Result<()>) -> Result<()> {
+    let (mut lamports, mut data) = (0, vec![]);
+    let owner = Pubkey::default();
+    run(&AccountInfo::new(key, signed, false, &mut lamports, &mut data, &owner, false, 0))
+}
+
+#[test]
+fn accepts_the_upgrade_authority() {
+    let authority = Pubkey::new_unique();
+    Deployment::new(Some(authority)).check(&authority, true).unwrap();
+}
+
+#[test]
+fn rejects_other_signers_and_an_unsigned_authority() {
+    let authority = Pubkey::new_unique();
+    let mut deployment = Deployment::new(Some(authority));
+    let err = deployment.check(&Pubkey::new_unique(), true).unwrap_err();
+    assert_eq!(err, X402Error::NotUpgradeAuthority.into());
+    let err = deployment.check(&authority, false).unwrap_err();
+    assert_eq!(err, X402Error::NotUpgradeAuthority.into());
+}
+
+#[test]
+fn immutable_programs_have_no_admin() {
+    let err = Deployment::new(None).check(&Pubkey::new_unique(), true).unwrap_err();
+    assert_eq!(err, X402Error::ProgramNotUpgradeable.into());
+}
+
+#[test]
+fn programs_of_other_loaders_are_not_upgradeable() {
+    let authority = Pubkey::new_unique();
+    let mut deployment = Deployment::new(Some(authority));
+    deployment.program_owner = BPF_LOADER;
+    let err = deployment.check(&authority, true).unwrap_err();
+    assert_eq!(err, X402Error::ProgramNotUpgradeable.into());
+}
+
+#[test]
+fn rejects_another_programs_data() {
+    let authority = Pubkey::new_unique();
+    let mut deployment = Deployment::new(Some(authority));
+    deployment.program_data = Pubkey::new_unique();
+    let err = deployment.check(&authority, true).unwrap_err();
+    assert_eq!(err, X402Error::ProgramDataMismatch.into());
+}
+
+#[test]
+fn rejects_another_upgradeable_program() {
+    // A program deployed by the signer, with its own program data.
+    let authority = Pubkey::new_unique();
+    let mut deployment = Deployment::new(Some(authority));
+    deployment.program_id = Pubkey::new_unique();
+    let err = deployment.check(&authority, true).unwrap_err();
+    assert_eq!(err, X402Error::ProgramMismatch.into());
+}
+
+#[test]
+fn operator_acts_but_only_the_authority_administers() {
+    let admin = X402Admin {
+        authority: Pubkey::new_unique(),
+        operator: Pubkey::new_unique(),
+    };
+    with_signer(&admin.operator, true, |operator| {
+        admin.require_operator(operator)?;
+        assert_eq!(admin.require_authority(operator).unwrap_err(), X402Error::NotX402Admin.into());
+        Ok(())
+    })
+    .unwrap();
+    with_signer(&admin.authority, true, |authority| {
+        admin.require_operator(authority)?;
+        admin.require_authority(authority)
+    })
+    .unwrap();
+
+    let err = with_signer(&admin.authority, false, |authority| admin.require_authority(authority)).unwrap_err();
+    assert_eq!(err, X402Error::NotX402Admin.into());
+}
@@ -0,0 +1,121 @@
+//! Round-trip properties for amount decoding across every payment encoding
+//! the matcher accepts. These target offset confusion: a decoder reading the
+//! wrong bytes can report a larger payment than the one actually made.
+
+use proptest::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use x402_runtime::{match_payment, PaymentKind};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+fn x402_pay(payer: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    let mut data = hash(b"global:x402_pay").to_bytes()[..8].to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*recipient, false),
+            AccountMeta::new_readonly(solana_program::system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+fn transfer_checked(payer: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        &Pubkey::new_unique(),
+        &Pubkey::new_unique(),
+        recipient,
+        payer,
+        &[],
+        amount,
+        6,
+    )
+    .unwrap()
+}
+
+/// Flips one byte and checks the matcher either rejects the result or
+/// decodes exactly the amount now stored at `amount_offset`.
+fn assert_mutation_consistent(
+    ix: &Instruction,
+    amount_offset: usize,
+    original: u64,
+    index: usize,
+    byte: u8,
+) -> Result<(), TestCaseError> {
+    let mut mutated = ix.clone();
+    let index = index % mutated.data.len();
+    mutated.data[index] = byte;
+
+    if let Ok(payment) = match_payment(&mutated, &PROGRAM_ID) {
+        let field = amount_offset..amount_offset + 8;
+        let encoded = u64::from_le_bytes(mutated.data[field.clone()].try_into().unwrap());
+        prop_assert_eq!(payment.amount, encoded);
+        if !field.contains(&index) {
+            prop_assert_eq!(payment.amount, original);
+        }
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn system_transfer_round_trips(amount: u64, payer in pubkey(), recipient in pubkey()) {
+        let ix = system_instruction::transfer(&payer, &recipient, amount);
+        let payment = match_payment(&ix, &PROGRAM_ID).unwrap();
+
+        prop_assert_eq!(payment.kind, PaymentKind::SystemTransfer);
+        prop_assert_eq!(payment.amount, amount);
+        prop_assert_eq!(payment.payer, payer);
+        prop_assert_eq!(payment.recipient, recipient);
+    }
+
+    #[test]
+    fn x402_pay_round_trips(amount: u64, payer in pubkey(), recipient in pubkey()) {
+        let ix = x402_pay(&payer, &recipient, amount);
+        let payment = match_payment(&ix, &PROGRAM_ID).unwrap();
+
+        prop_assert_eq!(payment.kind, PaymentKind::ProgramPay);
+        prop_assert_eq!(payment.amount, amount);
+        prop_assert_eq!(payment.payer, payer);
+        prop_assert_eq!(payment.recipient, recipient);
+    }
+
+    #[test]
+    fn transfer_checked_round_trips(amount: u64, payer in pubkey(), recipient in pubkey()) {
+        let ix = transfer_checked(&payer, &recipient, amount);
+        let payment = match_payment(&ix, &PROGRAM_ID).unwrap();
+
+        prop_assert_eq!(payment.kind, PaymentKind::TokenTransferChecked);
+        prop_assert_eq!(payment.amount, amount);
+        prop_assert_eq!(payment.payer, payer);
+        prop_assert_eq!(payment.recipient, recipient);
+    }
+
+    #[test]
+    fn system_transfer_mutation_is_consistent(amount: u64, index: usize, byte: u8) {
+        let ix = system_instruction::transfer(&Pubkey::new_unique(), &Pubkey::new_unique(), amount);
+        assert_mutation_consistent(&ix, 4, amount, index, byte)?;
+    }
+
+    #[test]
+    fn x402_pay_mutation_is_consistent(amount: u64, index: usize, byte: u8) {
+        let ix = x402_pay(&Pubkey::new_unique(), &Pubkey::new_unique(), amount);
+        assert_mutation_consistent(&ix, 8, amount, index, byte)?;
+    }
+
+    #[test]
+    fn transfer_checked_mutation_is_consistent(amount: u64, index: usize, byte: u8) {
+        let ix = transfer_checked(&Pubkey::new_unique(), &Pubkey::new_unique(), amount);
+        assert_mutation_consistent(&ix, 1, amount, index, byte)?;
+    }
+}
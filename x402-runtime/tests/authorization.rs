@@ -0,0 +1,104 @@
+//! Recipients chosen per call: the authorization must name the recipient
+//! and price, be unexpired, and the payment must then go to that recipient.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::Instruction;
+use solana_program::system_instruction;
+use x402_runtime::authorization::{ed25519_instruction, RecipientAuthority, RecipientAuthorization};
+use x402_runtime::{verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+const NOW: i64 = 1_700_000_000;
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        // The handler's fixed recipient is all zeros without `address`.
+        recipient: Pubkey::default(),
+        currency: Currency::Sol,
+    }
+}
+
+/// The Ed25519 instruction carrying `authorization` signed by `authority`.
+/// Its signature is the runtime's to check, not ours.
+fn authorize(authority: &Pubkey, authorization: &RecipientAuthorization) -> Instruction {
+    ed25519_instruction(authority, &[0; 64], &authorization.message())
+}
+
+fn authorization(recipient: &Pubkey, expiry: i64) -> RecipientAuthorization {
+    RecipientAuthorization {
+        recipient: *recipient,
+        price: PRICE,
+        expiry,
+    }
+}
+
+/// Verifies the authorization of `recipient`, then the payment to it.
+fn verify(preceding: &[Instruction], authority: &Pubkey, recipient: &Pubkey, payer: &Pubkey) -> Result<X402Params> {
+    let mut sysvar = Sysvar::new(preceding, payer);
+    let accounts = [sysvar.account_info(false, false)];
+    let params = RecipientAuthority { authority: *authority }.verify_at(&accounts, &params(), recipient, NOW)?;
+    verify_payment(&accounts, &PROGRAM_ID, &params)?;
+    Ok(params)
+}
+
+#[test]
+fn accepts_authorized_recipient() {
+    let (authority, payer, seller) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        authorize(&authority, &authorization(&seller, NOW + 60)),
+        system_instruction::transfer(&payer, &seller, PRICE),
+    ];
+    let params = verify(&preceding, &authority, &seller, &payer).unwrap();
+    assert_eq!(params.recipient, seller);
+    assert_eq!(params.price, PRICE);
+}
+
+#[test]
+fn rejects_recipient_swapped_after_signing() {
+    let (authority, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (seller, attacker) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        authorize(&authority, &authorization(&seller, NOW + 60)),
+        system_instruction::transfer(&payer, &attacker, PRICE),
+    ];
+    let err = verify(&preceding, &authority, &attacker, &payer).unwrap_err();
+    assert_eq!(err, X402Error::RecipientNotAuthorized.into());
+}
+
+#[test]
+fn rejects_expired_authorization() {
+    let (authority, payer, seller) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        authorize(&authority, &authorization(&seller, NOW)),
+        system_instruction::transfer(&payer, &seller, PRICE),
+    ];
+    let err = verify(&preceding, &authority, &seller, &payer).unwrap_err();
+    assert_eq!(err, X402Error::RecipientAuthorizationExpired.into());
+}
+
+#[test]
+fn rejects_authorization_by_another_key() {
+    let (authority, payer, seller) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        authorize(&Pubkey::new_unique(), &authorization(&seller, NOW + 60)),
+        system_instruction::transfer(&payer, &seller, PRICE),
+    ];
+    let err = verify(&preceding, &authority, &seller, &payer).unwrap_err();
+    assert_eq!(err, X402Error::RecipientNotAuthorized.into());
+}
+
+#[test]
+fn rejects_payment_to_another_recipient() {
+    let (authority, payer) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (seller, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        authorize(&authority, &authorization(&seller, NOW + 60)),
+        system_instruction::transfer(&payer, &other, PRICE),
+    ];
+    assert!(verify(&preceding, &authority, &seller, &payer).is_err());
+}
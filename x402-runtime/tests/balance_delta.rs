@@ -0,0 +1,72 @@
+//! Payments made by the handler body itself, checked through the
+//! recipient's balance. The body's transfer is modelled by crediting the
+//! recipient's lamports between `snapshot` and `verify_delta`.
+
+use anchor_lang::prelude::*;
+use x402_runtime::balance_delta::{snapshot, verify_delta, CURRENT_INSTRUCTION};
+use x402_runtime::{Currency, X402Error, X402Params};
+
+const PRICE: u64 = 200_000;
+const OWNER: Pubkey = Pubkey::new_from_array([0; 32]);
+
+fn params(recipient: &Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: *recipient,
+        currency: Currency::Sol,
+    }
+}
+
+/// Runs a handler whose body credits the recipient `paid` lamports.
+fn run(recipient: &Pubkey, is_writable: bool, paid: u64) -> Result<u64> {
+    let payer = Pubkey::new_unique();
+    let mut lamports = 5_000_000;
+    let mut data = [];
+    let info = AccountInfo::new(recipient, false, is_writable, &mut lamports, &mut data, &OWNER, false, 0);
+
+    let before = snapshot(&info, &params(recipient))?;
+    **info.try_borrow_mut_lamports()? += paid;
+    let payment = verify_delta(&info, before, &payer, &params(recipient))?;
+
+    assert_eq!(payment.payer, payer);
+    assert_eq!(payment.ix_index, CURRENT_INSTRUCTION);
+    Ok(payment.amount)
+}
+
+#[test]
+fn body_that_pays_exactly_the_price_is_accepted() {
+    let recipient = Pubkey::new_unique();
+    assert_eq!(run(&recipient, true, PRICE).unwrap(), PRICE);
+}
+
+#[test]
+fn overpayment_is_reported() {
+    let recipient = Pubkey::new_unique();
+    assert_eq!(run(&recipient, true, PRICE + 1).unwrap(), PRICE + 1);
+}
+
+#[test]
+fn body_that_forgets_to_pay_is_rejected() {
+    let recipient = Pubkey::new_unique();
+    let err = run(&recipient, true, 0).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn read_only_recipient_is_rejected() {
+    let recipient = Pubkey::new_unique();
+    let err = run(&recipient, false, PRICE).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+}
+
+#[test]
+fn other_recipient_is_rejected() {
+    let recipient = Pubkey::new_unique();
+    let mut lamports = 0;
+    let mut data = [];
+    let other = Pubkey::new_unique();
+    let info = AccountInfo::new(&other, false, true, &mut lamports, &mut data, &OWNER, false, 0);
+
+    let err = snapshot(&info, &params(&recipient)).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+}
@@ -0,0 +1,133 @@
+//! What each clock-dependent feature does without a clock: payment checks
+//! fail closed with `ClockUnavailable`, the latency event is skipped. And
+//! the bounds on the expiries callers supply.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction;
+use x402_runtime::authorization::{ed25519_instruction, RecipientAuthority, RecipientAuthorization};
+use x402_runtime::clock::{require_valid_expiry, MAX_HORIZON_SECS};
+use x402_runtime::{commitment, ledger, spending_cap, Currency, VerifiedPayment, X402Error, X402Params};
+
+use common::{with_clock, Sysvar};
+
+const NOW: i64 = 1_700_000_000;
+const PRICE: u64 = 1_000_000;
+
+fn at(unix_timestamp: i64) -> Option<Clock> {
+    Some(Clock {
+        slot: 42,
+        unix_timestamp,
+        ..Clock::default()
+    })
+}
+
+fn payment(payer: Pubkey) -> VerifiedPayment {
+    VerifiedPayment {
+        amount: PRICE,
+        payer,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+/// `RecipientAuthority::verify` of `seller`, authorized until `expiry`.
+fn verify_authorization(expiry: i64) -> Result<X402Params> {
+    let (authority, payer, seller) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let authorization = RecipientAuthorization {
+        recipient: seller,
+        price: PRICE,
+        expiry,
+    };
+    let mut sysvar = Sysvar::new(
+        &[
+            ed25519_instruction(&authority, &[0; 64], &authorization.message()),
+            system_instruction::transfer(&payer, &seller, PRICE),
+        ],
+        &payer,
+    );
+    let params = X402Params {
+        price: PRICE,
+        recipient: Pubkey::default(),
+        currency: Currency::Sol,
+    };
+    RecipientAuthority { authority }.verify(&[sysvar.account_info(false, false)], &params, &seller)
+}
+
+#[test]
+fn now_reads_the_clock() {
+    assert_eq!(with_clock(at(NOW), x402_runtime::now).unwrap(), NOW);
+    assert_eq!(with_clock(at(NOW), x402_runtime::clock::slot).unwrap(), 42);
+}
+
+#[test]
+fn now_fails_without_a_clock() {
+    let err = with_clock(None, x402_runtime::now).unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+}
+
+#[test]
+fn authorization_fails_closed() {
+    assert!(with_clock(at(NOW), || verify_authorization(NOW + 60)).is_ok());
+    let err = with_clock(None, || verify_authorization(NOW + 60)).unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+}
+
+#[test]
+fn spending_cap_fails_closed() {
+    let payer = Pubkey::new_unique();
+    let (mut period_start, mut spent) = (NOW, 0);
+    let err = with_clock(None, || {
+        spending_cap::charge(&payer, 10 * PRICE, 86_400, &mut period_start, &mut spent, &payment(payer))
+    })
+    .unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+    assert_eq!(spent, 0);
+}
+
+#[test]
+fn ledger_fails_closed() {
+    let payer = Pubkey::new_unique();
+    let (mut total_payments, mut total_amount, mut last_payment) = (0, 0, 0);
+    let err = with_clock(None, || {
+        ledger::record(&payer, &mut total_payments, &mut total_amount, &mut last_payment, &payment(payer))
+    })
+    .unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+    assert_eq!((total_payments, total_amount), (0, 0));
+}
+
+#[test]
+fn commitment_fails_closed() {
+    let mut stored = commitment::GENESIS_COMMITMENT;
+    let err = with_clock(None, || commitment::record(&mut stored, &payment(Pubkey::new_unique()), 1, "premium_v1"))
+        .unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+    assert_eq!(stored, commitment::GENESIS_COMMITMENT);
+}
+
+#[test]
+fn latency_event_fails_open() {
+    with_clock(None, || x402_runtime::emit_payment_latency(&Pubkey::new_unique(), 1, None)).unwrap();
+}
+
+#[test]
+fn expiries_are_bounded() {
+    require_valid_expiry(0, NOW).unwrap();
+    require_valid_expiry(NOW - 1, NOW).unwrap();
+    require_valid_expiry(NOW + MAX_HORIZON_SECS, NOW).unwrap();
+    for expiry in [-1, i64::MIN, NOW + MAX_HORIZON_SECS + 1, i64::MAX] {
+        let err = require_valid_expiry(expiry, NOW).unwrap_err();
+        assert_eq!(err, X402Error::InvalidExpiry.into(), "expiry {}", expiry);
+    }
+}
+
+#[test]
+fn authorization_rejects_absurd_expiries() {
+    for expiry in [-1, NOW + MAX_HORIZON_SECS + 1] {
+        let err = with_clock(at(NOW), || verify_authorization(expiry)).unwrap_err();
+        assert_eq!(err, X402Error::InvalidExpiry.into(), "expiry {}", expiry);
+    }
+}
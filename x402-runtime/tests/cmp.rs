@@ -0,0 +1,44 @@
+//! The key comparison behind the recipient and mint checks: a missing key
+//! and another key are different failures, and equal keys are equal
+//! whichever byte would have told others apart.
+
+use solana_program::pubkey::Pubkey;
+use x402_runtime::cmp::{expect_key, keys_eq, mints_eq};
+use x402_runtime::X402Error;
+
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+
+fn check(actual: Option<&Pubkey>) -> Result<(), u32> {
+    expect_key(actual, &RECIPIENT, X402Error::MissingPaymentAccount, X402Error::WrongRecipient).map_err(|err| err as u32)
+}
+
+#[test]
+fn a_missing_key_is_not_a_mismatch() {
+    assert_eq!(check(Some(&RECIPIENT)), Ok(()));
+    assert_eq!(check(None), Err(X402Error::MissingPaymentAccount as u32));
+    assert_eq!(check(Some(&Pubkey::new_unique())), Err(X402Error::WrongRecipient as u32));
+    // Not even the default key stands in for a missing one.
+    assert_eq!(check(Some(&Pubkey::default())), Err(X402Error::WrongRecipient as u32));
+}
+
+#[test]
+fn keys_differing_in_any_one_byte_are_unequal() {
+    assert!(keys_eq(&RECIPIENT, &Pubkey::new_from_array([9; 32])));
+    for index in 0..32 {
+        for bit in 0..8 {
+            let mut bytes = RECIPIENT.to_bytes();
+            bytes[index] ^= 1 << bit;
+            assert!(!keys_eq(&RECIPIENT, &Pubkey::new_from_array(bytes)), "byte {} bit {}", index, bit);
+        }
+    }
+}
+
+#[test]
+fn sol_and_a_mint_never_agree() {
+    let mint = Pubkey::new_unique();
+    assert!(mints_eq(None, None));
+    assert!(mints_eq(Some(&mint), Some(&mint)));
+    assert!(!mints_eq(Some(&mint), None));
+    assert!(!mints_eq(None, Some(&mint)));
+    assert!(!mints_eq(Some(&mint), Some(&Pubkey::new_unique())));
+}
@@ -0,0 +1,44 @@
+//! The commitment log: each payment moves the stored commitment to the
+//! hash of the previous one and the payment, and the event reports both.
+
+use solana_program::pubkey::Pubkey;
+use x402_common::commitment::next_commitment;
+use x402_runtime::commitment::{record_at, GENESIS_COMMITMENT};
+use x402_runtime::{Currency, VerifiedPayment};
+
+fn payment(amount: u64) -> VerifiedPayment {
+    VerifiedPayment {
+        amount,
+        payer: Pubkey::new_unique(),
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+#[test]
+fn each_payment_extends_the_stored_commitment() {
+    let mut commitment = GENESIS_COMMITMENT;
+    let first = payment(1_000_000);
+    let event = record_at(&mut commitment, &first, 1, "premium_v1", 42);
+    assert_eq!(event.previous_commitment, GENESIS_COMMITMENT);
+    assert_eq!(event.commitment, commitment);
+    assert_eq!(commitment, next_commitment(&GENESIS_COMMITMENT, &first.payer, 1_000_000, 42, "premium_v1"));
+    assert_eq!((event.payer, event.amount, event.slot, event.sequence), (first.payer, 1_000_000, 42, 1));
+    assert_eq!(event.instruction_tag, "premium_v1");
+
+    let second = record_at(&mut commitment, &payment(5_000_000), 2, "standard_v1", 42);
+    assert_eq!(second.previous_commitment, event.commitment);
+    assert_ne!(second.commitment, event.commitment);
+}
+
+#[test]
+fn every_field_is_committed() {
+    let payer = Pubkey::new_unique();
+    let base = next_commitment(&GENESIS_COMMITMENT, &payer, 100, 7, "tag");
+    assert_ne!(base, next_commitment(&[1; 32], &payer, 100, 7, "tag"));
+    assert_ne!(base, next_commitment(&GENESIS_COMMITMENT, &Pubkey::new_unique(), 100, 7, "tag"));
+    assert_ne!(base, next_commitment(&GENESIS_COMMITMENT, &payer, 101, 7, "tag"));
+    assert_ne!(base, next_commitment(&GENESIS_COMMITMENT, &payer, 100, 8, "tag"));
+    assert_ne!(base, next_commitment(&GENESIS_COMMITMENT, &payer, 100, 7, "tag2"));
+}
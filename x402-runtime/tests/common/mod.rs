@@ -0,0 +1,151 @@
+//! Off-chain instructions sysvar fixtures shared by the verification tests.
+
+#![allow(dead_code)]
+
+use std::sync::Mutex;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::program_stubs::{set_syscall_stubs, SyscallStubs};
+use solana_program::sysvar;
+use solana_program::sysvar::instructions::{self, BorrowedAccountMeta, BorrowedInstruction};
+
+pub const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn borrow(ix: &Instruction) -> BorrowedInstruction<'_> {
+    BorrowedInstruction {
+        program_id: &ix.program_id,
+        accounts: ix
+            .accounts
+            .iter()
+            .map(|meta| BorrowedAccountMeta {
+                pubkey: &meta.pubkey,
+                is_signer: meta.is_signer,
+                is_writable: meta.is_writable,
+            })
+            .collect(),
+        data: &ix.data,
+    }
+}
+
+/// The priced instruction itself, signed by `signer`.
+pub fn priced_instruction(signer: &Pubkey) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(*signer, true)],
+        data: vec![0; 8],
+    }
+}
+
+/// An instructions sysvar account as the runtime would pass it.
+pub struct Sysvar {
+    pub key: Pubkey,
+    pub owner: Pubkey,
+    pub lamports: u64,
+    pub data: Vec<u8>,
+}
+
+impl Sysvar {
+    /// Sysvar for `preceding` followed by a priced instruction from
+    /// `signer`, positioned at the priced instruction.
+    pub fn new(preceding: &[Instruction], signer: &Pubkey) -> Self {
+        Self::with_priced(preceding, priced_instruction(signer))
+    }
+
+    /// As [`Sysvar::new`], with `priced` as the priced instruction.
+    pub fn with_priced(preceding: &[Instruction], priced: Instruction) -> Self {
+        let mut all = preceding.to_vec();
+        all.push(priced);
+        Self::at(&all, preceding.len() as u16)
+    }
+
+    /// Sysvar for a whole transaction of `all`, positioned at `current`, as
+    /// each of several priced instructions in one transaction reads it.
+    pub fn at(all: &[Instruction], current: u16) -> Self {
+        let borrowed: Vec<_> = all.iter().map(borrow).collect();
+
+        let mut data = instructions::construct_instructions_data(&borrowed);
+        instructions::store_current_index(&mut data, current);
+        Sysvar {
+            key: instructions::ID,
+            owner: sysvar::ID,
+            lamports: 0,
+            data,
+        }
+    }
+
+    pub fn account_info(&mut self, is_signer: bool, is_writable: bool) -> AccountInfo<'_> {
+        AccountInfo::new(
+            &self.key,
+            is_signer,
+            is_writable,
+            &mut self.lamports,
+            &mut self.data,
+            &self.owner,
+            false,
+            0,
+        )
+    }
+}
+
+/// Syscalls answering `Clock::get` with a fixed clock, or failing as they
+/// do off-chain by default.
+struct ClockStubs(Option<Clock>);
+
+/// Syscalls answering `Rent::get` with `Rent`.
+struct RentStubs(Rent);
+
+/// Syscalls dropping every log line instead of printing it.
+struct QuietStubs;
+
+impl SyscallStubs for QuietStubs {
+    fn sol_log(&self, _message: &str) {}
+}
+
+impl SyscallStubs for RentStubs {
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        // SAFETY: `Rent::get` passes a pointer to a `Rent`.
+        unsafe { *(var_addr as *mut Rent) = self.0 };
+        solana_program::entrypoint::SUCCESS
+    }
+}
+
+impl SyscallStubs for ClockStubs {
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        match &self.0 {
+            Some(clock) => {
+                // SAFETY: `Clock::get` passes a pointer to a `Clock`.
+                unsafe { *(var_addr as *mut Clock) = clock.clone() };
+                solana_program::entrypoint::SUCCESS
+            }
+            None => solana_program::program_error::UNSUPPORTED_SYSVAR,
+        }
+    }
+}
+
+/// The syscalls are process-wide, so tests setting them take turns.
+static SYSCALLS: Mutex<()> = Mutex::new(());
+
+/// Runs `f` with the `Clock` sysvar at `clock`, or unavailable with `None`,
+/// as in harnesses that do not provide it.
+pub fn with_clock<T>(clock: Option<Clock>, f: impl FnOnce() -> T) -> T {
+    with_stubs(ClockStubs(clock), f)
+}
+
+/// Runs `f` with the `Rent` sysvar at `rent`.
+pub fn with_rent<T>(rent: Rent, f: impl FnOnce() -> T) -> T {
+    with_stubs(RentStubs(rent), f)
+}
+
+/// Runs `f` with its logs dropped, so only its own work allocates.
+pub fn quietly<T>(f: impl FnOnce() -> T) -> T {
+    with_stubs(QuietStubs, f)
+}
+
+fn with_stubs<T>(stubs: impl SyscallStubs + 'static, f: impl FnOnce() -> T) -> T {
+    let _turn = SYSCALLS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    let previous = set_syscall_stubs(Box::new(stubs));
+    let result = f();
+    set_syscall_stubs(previous);
+    result
+}
@@ -0,0 +1,50 @@
+//! Recipients resolved from the program's config account, as the expansion
+//! does for `#[x402]` without `address`.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction::transfer;
+use x402_runtime::config::configured_recipient;
+use x402_runtime::{verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 200_000;
+
+/// A call paying `paid_to`, with `config` holding the configured recipient
+/// (`None` before `initialize_x402`).
+fn call(config: Option<Pubkey>, paid_to: &Pubkey) -> Result<()> {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(&[transfer(&payer, paid_to, PRICE)], &payer);
+
+    let params = X402Params {
+        price: PRICE,
+        recipient: configured_recipient(config)?,
+        currency: Currency::Sol,
+    };
+    verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params).map(|_| ())
+}
+
+#[test]
+fn calls_before_init_fail() {
+    let err = call(None, &Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, X402Error::ConfigNotInitialized.into());
+}
+
+#[test]
+fn calls_after_init_succeed() {
+    let treasury = Pubkey::new_unique();
+    call(Some(treasury), &treasury).unwrap();
+}
+
+#[test]
+fn rotation_takes_effect_immediately() {
+    let (old, new) = (Pubkey::new_unique(), Pubkey::new_unique());
+    call(Some(old), &old).unwrap();
+
+    // After set_recipient(new), paying the old treasury no longer counts.
+    let err = call(Some(new), &old).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+    call(Some(new), &new).unwrap();
+}
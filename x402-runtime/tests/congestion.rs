@@ -0,0 +1,204 @@
+//! Congestion surcharges as the `#[x402]` expansion applies them: the
+//! surcharged params are what `verify_payment` enforces.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction::transfer;
+use x402_runtime::congestion::{
+    apply_grace, require_within_maximum, CongestionSurcharge, LevelChange, MAX_CONGESTION_LEVEL, MAX_GRACE_BPS,
+};
+use x402_runtime::{verify_payment, Currency, PriceProvenance, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+fn params(price: u64) -> X402Params {
+    X402Params {
+        price,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+    }
+}
+
+fn surcharge(bps_per_level: u16) -> CongestionSurcharge {
+    CongestionSurcharge {
+        account: Pubkey::new_unique(),
+        bps_per_level,
+    }
+}
+
+fn verify(params: &X402Params, amount: u64) -> Result<()> {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(&[transfer(&payer, &params.recipient, amount)], &payer);
+    verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, params).map(|_| ())
+}
+
+#[test]
+fn each_level_adds_its_basis_points() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+
+    for level in 0..=MAX_CONGESTION_LEVEL {
+        let surcharged = surcharge.apply(&base, level).unwrap();
+        assert_eq!(surcharged.price, 1_000_000 + level as u64 * 50_000);
+        assert_eq!(surcharged.recipient, base.recipient);
+    }
+}
+
+#[test]
+fn verification_enforces_the_surcharged_price() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+
+    for level in [0, 2, MAX_CONGESTION_LEVEL, 1] {
+        let surcharged = surcharge.apply_from(&base, &surcharge.account, level).unwrap();
+        verify(&surcharged, surcharged.price).unwrap();
+        if level > 0 {
+            // The base price no longer suffices once congested.
+            let err = verify(&surcharged, base.price).unwrap_err();
+            assert_eq!(err, error!(X402Error::InsufficientPayment));
+        }
+    }
+}
+
+#[test]
+fn rounds_down_to_whole_lamports() {
+    let surcharged = surcharge(1).apply(&params(9_999), 1).unwrap();
+    assert_eq!(surcharged.price, 9_999);
+}
+
+#[test]
+fn rejects_bad_levels_accounts_and_overflow() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+
+    let err = surcharge.apply(&base, MAX_CONGESTION_LEVEL + 1).unwrap_err();
+    assert_eq!(err, error!(X402Error::InvalidCongestionLevel));
+
+    let err = surcharge.apply_from(&base, &Pubkey::new_unique(), 0).unwrap_err();
+    assert_eq!(err, error!(X402Error::CongestionAccountMismatch));
+
+    let err = surcharge.apply(&params(u64::MAX), 1).unwrap_err();
+    assert_eq!(err, error!(X402Error::SurchargeOverflow));
+    // Level 0 leaves even the largest price payable.
+    assert_eq!(surcharge.apply(&params(u64::MAX), 0).unwrap().price, u64::MAX);
+}
+
+#[test]
+fn price_raised_after_the_quote_is_rejected() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+    let quoted = surcharge.apply(&base, 1).unwrap().price;
+
+    // The authority raises the level before the call lands; the payment
+    // built from the quote would no longer do, and the caller never agreed
+    // to the new price.
+    let enforced = surcharge.apply(&base, 3).unwrap();
+    let err = require_within_maximum(&enforced, quoted).unwrap_err();
+    assert_eq!(err, error!(X402Error::PriceExceedsClientMaximum));
+}
+
+#[test]
+fn price_lowered_after_the_quote_charges_the_lower_price() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+    let quoted = surcharge.apply(&base, 3).unwrap().price;
+
+    let enforced = surcharge.apply(&base, 1).unwrap();
+    require_within_maximum(&enforced, quoted).unwrap();
+    // Only the lower price is required, not the quoted one.
+    verify(&enforced, enforced.price).unwrap();
+    assert!(enforced.price < quoted);
+}
+
+#[test]
+fn grace_bps_accepts_a_payment_quoted_just_before_a_bump() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(100);
+    let quoted = surcharge.apply(&base, 1).unwrap().price;
+
+    // Bumped at the slot the call lands: 1% more than quoted.
+    let bumped = surcharge.apply(&base, 2).unwrap();
+    assert_eq!(verify(&bumped, quoted).unwrap_err(), error!(X402Error::InsufficientPayment));
+
+    let graced = apply_grace(&bumped, 100);
+    assert_eq!(graced.price, 1_020_000 - 10_200);
+    verify(&graced, quoted).unwrap();
+    // A bump beyond the grace still fails.
+    let bumped = surcharge.apply(&base, 3).unwrap();
+    let err = verify(&apply_grace(&bumped, 100), quoted).unwrap_err();
+    assert_eq!(err, error!(X402Error::InsufficientPayment));
+}
+
+#[test]
+fn grace_bps_is_capped() {
+    let base = params(1_000_000);
+    assert_eq!(apply_grace(&base, 0), base);
+    assert_eq!(apply_grace(&base, u16::MAX), apply_grace(&base, MAX_GRACE_BPS));
+    assert_eq!(apply_grace(&base, MAX_GRACE_BPS).price, 900_000);
+}
+
+#[test]
+fn grace_window_accepts_the_previous_price_until_it_closes() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+    let quoted = surcharge.apply(&base, 1).unwrap().price;
+    let change = LevelChange {
+        previous_level: 1,
+        updated_at: 1_700_000_000,
+    };
+    let enforced_at = |now| surcharge.apply_with_window_at(&base, &surcharge.account, 3, change, now, 30).unwrap();
+
+    // Bumped from 1 to 3 just before the call.
+    let enforced = enforced_at(change.updated_at);
+    assert_eq!(enforced.price, quoted);
+    verify(&enforced, quoted).unwrap();
+    verify(&enforced_at(change.updated_at + 29), quoted).unwrap();
+
+    // Past the window only the new price will do.
+    let enforced = enforced_at(change.updated_at + 30);
+    assert_eq!(enforced, surcharge.apply(&base, 3).unwrap());
+    assert_eq!(verify(&enforced, quoted).unwrap_err(), error!(X402Error::InsufficientPayment));
+}
+
+#[test]
+fn grace_window_never_raises_a_lowered_price() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+    let change = LevelChange {
+        previous_level: 4,
+        updated_at: 1_700_000_000,
+    };
+    let enforced = surcharge
+        .apply_with_window_at(&base, &surcharge.account, 1, change, change.updated_at, 30)
+        .unwrap();
+    assert_eq!(enforced, surcharge.apply(&base, 1).unwrap());
+
+    let err = surcharge
+        .apply_with_window_at(&base, &Pubkey::new_unique(), 1, change, change.updated_at, 30)
+        .unwrap_err();
+    assert_eq!(err, error!(X402Error::CongestionAccountMismatch));
+}
+
+#[test]
+fn provenance_names_the_level_whose_price_was_enforced() {
+    let base = params(1_000_000);
+    let surcharge = surcharge(500);
+    let change = LevelChange {
+        previous_level: 1,
+        updated_at: 1_700_000_000,
+    };
+    let provenance = |now| {
+        let enforced = surcharge.apply_with_window_at(&base, &surcharge.account, 3, change, now, 30).unwrap();
+        surcharge.provenance(&base, &enforced, &surcharge.account, 3, change)
+    };
+    let at_level = |level| PriceProvenance::Congestion {
+        account: surcharge.account,
+        level,
+        updated_at: change.updated_at,
+    };
+
+    // Inside the window the previous level's price was enforced.
+    assert_eq!(provenance(change.updated_at), at_level(1));
+    assert_eq!(provenance(change.updated_at + 30), at_level(3));
+}
@@ -0,0 +1,89 @@
+//! Token prices are paid by a token transfer into the recipient's
+//! associated token account, never by a SOL transfer of the same number of
+//! base units, and modes that only count lamports refuse them.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction;
+use x402_runtime::matcher::TOKEN_PROGRAM_ID;
+use x402_runtime::{associated_token_address, verify_payment, verify_split_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_250_000;
+
+fn params(recipient: Pubkey, mint: Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Token(mint),
+    }
+}
+
+/// `TransferChecked` of `amount` of `mint` from `owner`'s account to
+/// `to`'s, both associated token accounts.
+fn transfer_checked(owner: &Pubkey, to: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    let mut data = vec![12];
+    data.extend_from_slice(&amount.to_le_bytes());
+    data.push(6);
+    Instruction {
+        program_id: TOKEN_PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(associated_token_address(owner, mint, &TOKEN_PROGRAM_ID), false),
+            AccountMeta::new_readonly(*mint, false),
+            AccountMeta::new(associated_token_address(to, mint, &TOKEN_PROGRAM_ID), false),
+            AccountMeta::new_readonly(*owner, true),
+        ],
+        data,
+    }
+}
+
+#[test]
+fn token_price_is_paid_into_the_recipients_account() {
+    let (payer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[transfer_checked(&payer, &recipient, &mint, PRICE)], &payer);
+
+    let payment = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient, mint)).unwrap();
+    assert_eq!((payment.payer, payment.recipient), (payer, recipient));
+    assert_eq!((payment.amount, payment.currency), (PRICE, Currency::Token(mint)));
+}
+
+#[test]
+fn token_price_is_not_paid_in_lamports() {
+    let (payer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[system_instruction::transfer(&payer, &recipient, PRICE)], &payer);
+
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient, mint)).unwrap_err();
+    assert_eq!(err, X402Error::WrongPaymentToken.into());
+}
+
+#[test]
+fn token_price_is_not_paid_in_another_mint() {
+    let (payer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let other = transfer_checked(&payer, &recipient, &Pubkey::new_unique(), PRICE);
+    let mut sysvar = Sysvar::new(&[other], &payer);
+
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient, mint)).unwrap_err();
+    assert_eq!(err, X402Error::WrongPaymentToken.into());
+}
+
+#[test]
+fn token_transfer_elsewhere_is_the_wrong_recipient() {
+    let (payer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[transfer_checked(&payer, &Pubkey::new_unique(), &mint, PRICE)], &payer);
+
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient, mint)).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+}
+
+#[test]
+fn lamport_modes_refuse_token_prices() {
+    let (payer, recipient, mint) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[transfer_checked(&payer, &recipient, &mint, PRICE)], &payer);
+
+    let accounts = [sysvar.account_info(false, false)];
+    let err = verify_split_payment(&accounts, &PROGRAM_ID, &params(recipient, mint), &payer, 2).unwrap_err();
+    assert_eq!(err, X402Error::UnsupportedCurrency.into());
+}
@@ -0,0 +1,194 @@
+//! Prices debited from a payer's deposit, which must stay rent-exempt
+//! through debits and withdrawals.
+
+use anchor_lang::prelude::*;
+use x402_runtime::balance_delta::CURRENT_INSTRUCTION;
+use x402_runtime::deposit::{available, debit_with_rent, withdraw_with_rent};
+use x402_runtime::{Currency, X402Error, X402Params};
+
+const PRICE: u64 = 200_000;
+const PROGRAM: Pubkey = Pubkey::new_from_array([7; 32]);
+const SYSTEM: Pubkey = Pubkey::new_from_array([0; 32]);
+/// `8 + DepositAccount::INIT_SPACE` in the example program.
+const DEPOSIT_SPACE: usize = 8 + 1 + 32;
+
+struct Accounts {
+    payer: Pubkey,
+    /// Recorded in the deposit account; the payer unless a test changes it.
+    depositor: Pubkey,
+    deposit: Pubkey,
+    recipient: Pubkey,
+    deposit_lamports: u64,
+    recipient_lamports: u64,
+    payer_lamports: u64,
+    data: Vec<u8>,
+}
+
+impl Accounts {
+    /// A deposit topped up with `topped_up` lamports over its rent.
+    fn new(topped_up: u64) -> Self {
+        let payer = Pubkey::new_unique();
+        Accounts {
+            payer,
+            depositor: payer,
+            deposit: Pubkey::new_unique(),
+            recipient: Pubkey::new_unique(),
+            deposit_lamports: Rent::default().minimum_balance(DEPOSIT_SPACE) + topped_up,
+            recipient_lamports: 0,
+            payer_lamports: 1_000_000_000,
+            data: vec![0; DEPOSIT_SPACE],
+        }
+    }
+
+    fn params(&self) -> X402Params {
+        X402Params {
+            price: PRICE,
+            recipient: self.recipient,
+            currency: Currency::Sol,
+        }
+    }
+
+    /// Debits the price as a `deposit = true` handler does.
+    fn debit(&mut self, payer_signed: bool) -> Result<u64> {
+        let params = self.params();
+        let deposit = AccountInfo::new(
+            &self.deposit,
+            false,
+            true,
+            &mut self.deposit_lamports,
+            &mut self.data,
+            &PROGRAM,
+            false,
+            0,
+        );
+        let mut recipient_data = [];
+        let recipient = AccountInfo::new(
+            &self.recipient,
+            false,
+            true,
+            &mut self.recipient_lamports,
+            &mut recipient_data,
+            &SYSTEM,
+            false,
+            0,
+        );
+        let mut payer_data = [];
+        let payer = AccountInfo::new(
+            &self.payer,
+            payer_signed,
+            true,
+            &mut self.payer_lamports,
+            &mut payer_data,
+            &SYSTEM,
+            false,
+            0,
+        );
+
+        let payment = debit_with_rent(&deposit, &self.depositor, &recipient, &payer, &params, &Rent::default())?;
+        assert_eq!(payment.payer, self.payer);
+        assert_eq!(payment.ix_index, CURRENT_INSTRUCTION);
+        Ok(payment.amount)
+    }
+
+    /// Withdraws `lamports` back to the payer.
+    fn withdraw(&mut self, lamports: u64) -> Result<()> {
+        let deposit = AccountInfo::new(
+            &self.deposit,
+            false,
+            true,
+            &mut self.deposit_lamports,
+            &mut self.data,
+            &PROGRAM,
+            false,
+            0,
+        );
+        let mut payer_data = [];
+        let payer = AccountInfo::new(
+            &self.payer,
+            true,
+            true,
+            &mut self.payer_lamports,
+            &mut payer_data,
+            &SYSTEM,
+            false,
+            0,
+        );
+        withdraw_with_rent(&deposit, &payer, lamports, &Rent::default())
+    }
+
+    fn available(&mut self) -> u64 {
+        let deposit = AccountInfo::new(
+            &self.deposit,
+            false,
+            true,
+            &mut self.deposit_lamports,
+            &mut self.data,
+            &PROGRAM,
+            false,
+            0,
+        );
+        available(&deposit, &Rent::default())
+    }
+}
+
+#[test]
+fn debits_draw_the_deposit_down_and_the_remainder_is_withdrawn() {
+    let mut accounts = Accounts::new(5 * PRICE + 1_234);
+    for _ in 0..3 {
+        assert_eq!(accounts.debit(true).unwrap(), PRICE);
+    }
+    assert_eq!(accounts.recipient_lamports, 3 * PRICE);
+    assert_eq!(accounts.available(), 2 * PRICE + 1_234);
+
+    let before = accounts.payer_lamports;
+    accounts.withdraw(2 * PRICE + 1_234).unwrap();
+    assert_eq!(accounts.payer_lamports, before + 2 * PRICE + 1_234);
+    assert_eq!(accounts.available(), 0);
+    assert_eq!(accounts.deposit_lamports, Rent::default().minimum_balance(DEPOSIT_SPACE));
+}
+
+#[test]
+fn debit_beyond_the_deposit_is_rejected() {
+    let mut accounts = Accounts::new(PRICE + PRICE / 2);
+    accounts.debit(true).unwrap();
+
+    let err = accounts.debit(true).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientDeposit.into());
+    assert_eq!(accounts.recipient_lamports, PRICE);
+    assert_eq!(accounts.available(), PRICE / 2);
+}
+
+#[test]
+fn withdrawal_cannot_touch_the_rent_exempt_minimum() {
+    let mut accounts = Accounts::new(PRICE);
+    let err = accounts.withdraw(PRICE + 1).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientDeposit.into());
+    assert_eq!(accounts.available(), PRICE);
+}
+
+#[test]
+fn debit_overflowing_the_recipient_moves_nothing() {
+    let mut accounts = Accounts::new(PRICE);
+    accounts.recipient_lamports = u64::MAX - PRICE + 1;
+    let err = accounts.debit(true).unwrap_err();
+    assert_eq!(err, X402Error::StatsOverflow.into());
+    assert_eq!(accounts.recipient_lamports, u64::MAX - PRICE + 1);
+    assert_eq!(accounts.available(), PRICE);
+}
+
+#[test]
+fn another_payers_deposit_is_rejected() {
+    let mut accounts = Accounts::new(PRICE);
+    accounts.depositor = Pubkey::new_unique();
+    let err = accounts.debit(true).unwrap_err();
+    assert_eq!(err, X402Error::DepositPayerMismatch.into());
+    assert_eq!(accounts.available(), PRICE);
+}
+
+#[test]
+fn unsigned_payer_cannot_spend_the_deposit() {
+    let mut accounts = Accounts::new(PRICE);
+    let err = accounts.debit(false).unwrap_err();
+    assert_eq!(err, X402Error::DepositPayerMismatch.into());
+    assert_eq!(accounts.available(), PRICE);
+}
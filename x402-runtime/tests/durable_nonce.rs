@@ -0,0 +1,143 @@
+//! Transactions signed offline against a durable nonce, which start with a
+//! System `AdvanceNonceAccount`. The scan steps over it like a
+//! wallet-injected instruction and never reads it as a transfer, though it
+//! is an instruction of the same program.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::message::Message;
+use solana_program::system_instruction::{advance_nonce_account, transfer};
+use x402_runtime::{
+    advances_nonce, is_ignorable_instruction, match_payment, verify_payment, verify_payment_with,
+    verify_split_payment, Currency, ScanOptions, VerifiedPayment, X402Error, X402Params,
+};
+
+use common::{priced_instruction, Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn params(recipient: Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    }
+}
+
+/// The instructions of a durable nonce transaction of `instructions` paid
+/// for by `payer`, as the runtime loads them into the instructions sysvar.
+fn durable_nonce_transaction(instructions: &[Instruction], payer: &Pubkey) -> Vec<Instruction> {
+    let (nonce, authority) = (Pubkey::new_unique(), *payer);
+    let message = Message::new_with_nonce(instructions.to_vec(), Some(payer), &nonce, &authority);
+    message
+        .instructions
+        .iter()
+        .map(|ix| Instruction {
+            program_id: message.account_keys[usize::from(ix.program_id_index)],
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|&index| {
+                    let index = usize::from(index);
+                    AccountMeta {
+                        pubkey: message.account_keys[index],
+                        is_signer: message.is_signer(index),
+                        is_writable: message.is_writable(index),
+                    }
+                })
+                .collect(),
+            data: ix.data.clone(),
+        })
+        .collect()
+}
+
+/// Verifies the last instruction of `transaction`, the priced call.
+fn verify_last(transaction: &[Instruction], recipient: &Pubkey, scan: ScanOptions) -> Result<VerifiedPayment> {
+    let (priced, preceding) = transaction.split_last().unwrap();
+    let mut sysvar = Sysvar::with_priced(preceding, priced.clone());
+    verify_payment_with(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(*recipient), &scan)
+}
+
+#[test]
+fn nonce_advance_transfer_then_priced_call() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let transaction =
+        durable_nonce_transaction(&[transfer(&payer, &recipient, PRICE), priced_instruction(&payer)], &payer);
+    assert!(advances_nonce(&transaction[0]));
+
+    let payment = verify_last(&transaction, &recipient, ScanOptions::DEFAULT).unwrap();
+    assert_eq!(payment.ix_index, 1);
+    assert_eq!(payment.payer, payer);
+
+    // Even a strict scan reaching past the transfer never trips on it.
+    let strict = ScanOptions {
+        max_lookback: 4,
+        strict: true,
+        legacy_matcher: false,
+    };
+    assert_eq!(verify_last(&transaction, &recipient, strict).unwrap().ix_index, 1);
+}
+
+#[test]
+fn nonce_advance_alone_is_rejected() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let transaction = durable_nonce_transaction(&[priced_instruction(&payer)], &payer);
+
+    for max_lookback in [1, 4] {
+        let scan = ScanOptions {
+            max_lookback,
+            strict: false,
+            legacy_matcher: false,
+        };
+        let err = verify_last(&transaction, &recipient, scan).unwrap_err();
+        assert_eq!(err, X402Error::NoPaymentInstruction.into());
+    }
+
+    let mut sysvar = Sysvar::with_priced(&transaction[..1], transaction[1].clone());
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient)).unwrap_err();
+    assert_eq!(err, X402Error::NoPaymentInstruction.into());
+}
+
+#[test]
+fn nonce_advance_is_never_a_transfer() {
+    let (nonce, authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let advance = advance_nonce_account(&nonce, &authority);
+
+    assert!(match_payment(&advance, &PROGRAM_ID).is_err());
+    assert!(advances_nonce(&advance));
+    assert!(is_ignorable_instruction(&advance));
+
+    // Only the exact encoding is skipped; other System instructions still
+    // count, and a transfer is never mistaken for an advance.
+    let mut padded = advance.clone();
+    padded.data.push(0);
+    assert!(!advances_nonce(&padded));
+    assert!(!advances_nonce(&transfer(&authority, &nonce, 4)));
+    let mut foreign = advance;
+    foreign.program_id = Pubkey::new_unique();
+    assert!(!advances_nonce(&foreign));
+}
+
+#[test]
+fn split_payments_after_a_nonce_advance() {
+    let (alice, bob, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let transaction = durable_nonce_transaction(
+        &[
+            transfer(&bob, &recipient, PRICE / 2),
+            transfer(&alice, &recipient, PRICE / 2),
+            priced_instruction(&alice),
+        ],
+        &alice,
+    );
+
+    let (priced, preceding) = transaction.split_last().unwrap();
+    let mut sysvar = Sysvar::with_priced(preceding, priced.clone());
+    let (payment, contributions) =
+        verify_split_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient), &alice, 4)
+            .unwrap();
+    assert_eq!(payment.amount, PRICE);
+    assert_eq!(payment.ix_index, 1);
+    assert_eq!(contributions.len(), 2);
+}
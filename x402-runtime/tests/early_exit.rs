@@ -0,0 +1,183 @@
+//! The order of the checks, kept by counting allocations. A call without
+//! the instructions sysvar, or with nothing before it, fails on its first
+//! checks without deserializing anything, and an instruction whose program
+//! cannot pay is stepped over by its program id alone, however many
+//! accounts and bytes of data it carries. Logs are dropped, so a count
+//! covers the checks' own work plus one formatted line per log.
+
+mod common;
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use x402_runtime::authorization::RecipientAuthority;
+use x402_runtime::native::precheck;
+use x402_runtime::{verify_sol_payment, Currency, ScanOptions, X402Error, X402Params, IGNORABLE_PROGRAMS};
+
+use common::{priced_instruction, quietly, Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+
+/// The system allocator, counting the allocations of each thread.
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+// SAFETY: every call is forwarded to `System` unchanged.
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// `f`'s result and the allocations it made, with its logs dropped.
+fn allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    quietly(|| {
+        let before = ALLOCATIONS.with(Cell::get);
+        let result = f();
+        (result, ALLOCATIONS.with(Cell::get) - before)
+    })
+}
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+    }
+}
+
+fn lookback(max_lookback: u8) -> ScanOptions {
+    ScanOptions {
+        max_lookback,
+        strict: false,
+        legacy_matcher: false,
+    }
+}
+
+/// An instruction of a program that cannot pay, with `accounts` accounts
+/// and `data` bytes of data.
+fn other(accounts: usize, data: usize) -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_from_array([5; 32]),
+        accounts: (0..accounts).map(|_| AccountMeta::new(Pubkey::new_unique(), false)).collect(),
+        data: vec![1; data],
+    }
+}
+
+/// A memo of `data` bytes, which the scan skips.
+fn memo(data: usize) -> Instruction {
+    Instruction {
+        program_id: IGNORABLE_PROGRAMS[1],
+        accounts: vec![AccountMeta::new_readonly(PAYER, true)],
+        data: vec![b'x'; data],
+    }
+}
+
+/// A full verification of `sysvar` under `scan`: the amount paid, and the
+/// allocations it took. The error becomes an Anchor one only once counted.
+fn verification(sysvar: &mut Sysvar, scan: ScanOptions) -> (Result<u64>, usize) {
+    let accounts = [sysvar.account_info(false, false)];
+    let (result, count) = allocations(|| verify_sol_payment(&accounts, &PROGRAM_ID, &params(), &scan));
+    (result.map(|payment| payment.amount).map_err(Into::into), count)
+}
+
+/// [`precheck`] of `accounts`, and the allocations it took.
+fn prechecked(accounts: &[AccountInfo]) -> (Result<()>, usize) {
+    let (result, count) = allocations(|| precheck(accounts));
+    (result.map_err(Into::into), count)
+}
+
+#[test]
+fn a_missing_sysvar_fails_before_anything_is_read() {
+    let mut unrelated = Sysvar::new(&[], &PAYER);
+    unrelated.key = Pubkey::new_unique();
+    let accounts = [unrelated.account_info(false, false)];
+    let (result, missing) = prechecked(&accounts);
+    assert_eq!(result, Err(X402Error::SysvarMissing.into()));
+    // The one line saying so, and the sysvar's address in it.
+    assert!(missing <= 2, "{}", missing);
+
+    let (result, missing) = verification(&mut unrelated, ScanOptions::DEFAULT);
+    assert_eq!(result, Err(X402Error::SysvarMissing.into()));
+    assert!(missing <= 2, "{}", missing);
+}
+
+#[test]
+fn a_first_instruction_fails_on_its_index_alone() {
+    let mut first = Sysvar::at(&[priced_instruction(&PAYER), other(32, 1_024)], 0);
+    let accounts = [first.account_info(false, false)];
+    let (result, count) = prechecked(&accounts);
+    assert_eq!(result, Err(X402Error::NoPaymentInstruction.into()));
+    assert!(count <= 1, "{}", count);
+
+    let mut first = Sysvar::at(&[priced_instruction(&PAYER), other(32, 1_024)], 0);
+    let (result, count) = verification(&mut first, lookback(16));
+    assert_eq!(result, Err(X402Error::NoPaymentInstruction.into()));
+    assert!(count <= 1, "{}", count);
+
+    let mut paid = Sysvar::new(&[transfer(&PAYER, &RECIPIENT, PRICE)], &PAYER);
+    let accounts = [paid.account_info(false, false)];
+    assert_eq!(prechecked(&accounts), (Ok(()), 0));
+}
+
+#[test]
+fn instructions_that_cannot_pay_are_stepped_over_by_their_program_alone() {
+    let cost = |accounts: usize, data: usize| {
+        let mut sysvar = Sysvar::new(&[other(accounts, data), other(accounts, data), other(accounts, data)], &PAYER);
+        verification(&mut sysvar, lookback(4))
+    };
+    let (result, narrow) = cost(0, 0);
+    assert_eq!(result, Err(X402Error::NotAPayment.into()));
+    assert_eq!(cost(32, 1_024), (Err(X402Error::NotAPayment.into()), narrow));
+    // One line for each, and one for the failure.
+    assert!(narrow <= 4, "{}", narrow);
+
+    // Skipped memos cost nothing at all before a payment.
+    let (result, paid) = verification(&mut Sysvar::new(&[transfer(&PAYER, &RECIPIENT, PRICE)], &PAYER), lookback(4));
+    assert_eq!(result, Ok(PRICE));
+    let mut memos = Sysvar::new(&[transfer(&PAYER, &RECIPIENT, PRICE), memo(512), memo(512)], &PAYER);
+    assert_eq!(verification(&mut memos, lookback(4)), (Ok(PRICE), paid));
+}
+
+#[test]
+fn authorizations_are_read_from_ed25519_instructions_only() {
+    let authority = RecipientAuthority {
+        authority: Pubkey::new_unique(),
+    };
+    let seller = Pubkey::new_unique();
+    let cost = |accounts: usize, data: usize| {
+        let mut sysvar =
+            Sysvar::new(&[other(accounts, data), other(accounts, data), transfer(&PAYER, &seller, PRICE)], &PAYER);
+        let accounts = [sysvar.account_info(false, false)];
+        let (result, count) = allocations(|| authority.verify_at(&accounts, &params(), &seller, 1_700_000_000));
+        assert_eq!(result.unwrap_err(), X402Error::RecipientNotAuthorized.into());
+        count
+    };
+    assert_eq!(cost(32, 1_024), cost(0, 0));
+}
+
+#[test]
+fn a_sysvar_cut_short_is_unavailable_rather_than_misread() {
+    // The count and offsets of two instructions, then the transfer's two
+    // accounts and the start of its program id, then the current index.
+    let mut sysvar = Sysvar::new(&[transfer(&PAYER, &RECIPIENT, PRICE)], &PAYER);
+    sysvar.data.truncate(2 + 2 * 2 + 2 + 33 * 2 + 10);
+    sysvar.data.extend_from_slice(&1u16.to_le_bytes());
+    assert_eq!(verification(&mut sysvar, ScanOptions::DEFAULT).0, Err(X402Error::SysvarUnavailable.into()));
+}
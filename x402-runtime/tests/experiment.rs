@@ -0,0 +1,81 @@
+//! Price experiments: a payer's arm depends only on their key and the
+//! bucket seed, and ending the experiment moves everyone to one arm.
+
+use anchor_lang::prelude::*;
+use x402_runtime::experiment::{Experiment, ExperimentState, EXPERIMENT_SEED};
+use x402_runtime::{Currency, X402Error, X402Params};
+
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const EXPERIMENT: Experiment = Experiment {
+    bucket_seed: "exp1",
+    prices: &[1_000_000, 800_000],
+};
+const RUNNING: ExperimentState = ExperimentState { ended: false, winner: 0 };
+
+fn params() -> X402Params {
+    X402Params {
+        price: 1_000_000,
+        recipient: Pubkey::new_from_array([9; 32]),
+        currency: Currency::Sol,
+    }
+}
+
+fn charged(payer: &Pubkey, state: ExperimentState) -> Result<(u64, u8)> {
+    EXPERIMENT
+        .apply_from(&params(), &EXPERIMENT.address(&PROGRAM_ID), &PROGRAM_ID, payer, state)
+        .map(|(params, arm)| (params.price, arm))
+}
+
+#[test]
+fn a_payer_keeps_their_bucket_across_calls() {
+    for _ in 0..64 {
+        let payer = Pubkey::new_unique();
+        let first = charged(&payer, RUNNING).unwrap();
+        for _ in 0..3 {
+            assert_eq!(charged(&payer, RUNNING).unwrap(), first);
+        }
+        assert_eq!(first.0, EXPERIMENT.prices[first.1 as usize]);
+    }
+}
+
+#[test]
+fn payers_spread_over_every_arm() {
+    let mut counts = [0u32; 2];
+    for _ in 0..1_000 {
+        counts[EXPERIMENT.bucket(&Pubkey::new_unique()) as usize] += 1;
+    }
+    assert!(counts.iter().all(|&count| count > 400), "{:?}", counts);
+}
+
+#[test]
+fn the_seed_changes_the_buckets() {
+    let other = Experiment {
+        bucket_seed: "exp2",
+        ..EXPERIMENT
+    };
+    let moved = (0..64)
+        .map(|_| Pubkey::new_unique())
+        .filter(|payer| EXPERIMENT.bucket(payer) != other.bucket(payer))
+        .count();
+    assert!(moved > 0);
+}
+
+#[test]
+fn ending_collapses_to_the_winner() {
+    let ended = ExperimentState { ended: true, winner: 1 };
+    for _ in 0..16 {
+        assert_eq!(charged(&Pubkey::new_unique(), ended).unwrap(), (800_000, 1));
+    }
+
+    let err = charged(&Pubkey::new_unique(), ExperimentState { ended: true, winner: 2 }).unwrap_err();
+    assert_eq!(err, error!(X402Error::InvalidExperimentArm));
+}
+
+#[test]
+fn another_account_is_rejected() {
+    let (other, _) = Pubkey::find_program_address(&[EXPERIMENT_SEED, b"exp2"], &PROGRAM_ID);
+    let err = EXPERIMENT
+        .apply_from(&params(), &other, &PROGRAM_ID, &Pubkey::new_unique(), RUNNING)
+        .unwrap_err();
+    assert_eq!(err, error!(X402Error::ExperimentAccountMismatch));
+}
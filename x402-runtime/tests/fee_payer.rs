@@ -0,0 +1,73 @@
+//! `require_fee_payer`: the payment must come from, and be signed by, the
+//! handler's payer. The real fee payer is not visible to programs, so the
+//! relayer case is modelled as a relayer signing the priced instruction
+//! while someone else signs the transfer.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use x402_runtime::matcher::X402_PAY_DISCRIMINATOR;
+use x402_runtime::{require_fee_payer, verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn params(recipient: &Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: *recipient,
+        currency: Currency::Sol,
+    }
+}
+
+/// Verifies the payment as the expansion would, applying
+/// `require_fee_payer` only when `flag` is set.
+fn run(payment: Instruction, signer: &Pubkey, recipient: &Pubkey, flag: bool) -> Result<()> {
+    let mut sysvar = Sysvar::new(&[payment], signer);
+    let accounts = [sysvar.account_info(false, false)];
+
+    let verified = verify_payment(&accounts, &PROGRAM_ID, &params(recipient))?;
+    if flag {
+        require_fee_payer(&accounts, &verified, signer)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn payer_who_signs_the_call_is_accepted() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    run(transfer(&payer, &recipient, PRICE), &payer, &recipient, true).unwrap();
+}
+
+#[test]
+fn relayed_payment_is_accepted_without_the_flag() {
+    let (payer, relayer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    run(transfer(&payer, &recipient, PRICE), &relayer, &recipient, false).unwrap();
+}
+
+#[test]
+fn relayed_payment_is_rejected_with_the_flag() {
+    let (payer, relayer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let err = run(transfer(&payer, &recipient, PRICE), &relayer, &recipient, true).unwrap_err();
+    assert_eq!(err, X402Error::FeePayerMismatch.into());
+}
+
+#[test]
+fn unsigned_source_is_rejected_with_the_flag() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // The program's own pay instruction names a payer without requiring
+    // its signature.
+    let mut data = X402_PAY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&PRICE.to_le_bytes());
+    let unsigned = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new(payer, false), AccountMeta::new(recipient, false)],
+        data,
+    };
+
+    let err = run(unsigned, &payer, &recipient, true).unwrap_err();
+    assert_eq!(err, X402Error::FeePayerMismatch.into());
+}
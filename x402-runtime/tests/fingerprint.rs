@@ -0,0 +1,81 @@
+//! Request fingerprints computed on-chain from the instructions sysvar
+//! must match those an off-chain cache computes from the same call.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use x402_common::fingerprint::{fingerprint, instruction_fingerprint};
+use x402_runtime::{request_fingerprint, X402Error};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn call(payer: &Pubkey, data: Vec<u8>) -> Instruction {
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new_readonly(*payer, true)],
+        data,
+    }
+}
+
+/// Anchor-style data: an 8-byte discriminator, then a Borsh `Option<Pubkey>`.
+fn compute_data(bind_to: Option<Pubkey>) -> Vec<u8> {
+    let mut data = vec![1, 2, 3, 4, 5, 6, 7, 8];
+    data.extend_from_slice(&bind_to.try_to_vec().unwrap());
+    data
+}
+
+fn on_chain(payer: &Pubkey, data: Vec<u8>) -> Result<[u8; 32]> {
+    let recipient = Pubkey::new_unique();
+    let mut sysvar = Sysvar::with_priced(&[transfer(payer, &recipient, PRICE)], call(payer, data));
+    request_fingerprint(&[sysvar.account_info(false, false)], payer, PRICE)
+}
+
+#[test]
+fn algorithm_is_fixed() {
+    let payer = Pubkey::new_from_array([1; 32]);
+    let expected = [
+        124, 106, 235, 82, 227, 196, 85, 25, 32, 94, 143, 189, 212, 155, 134, 235, 38, 11, 10, 56, 4, 255, 131,
+        195, 158, 181, 118, 99, 16, 63, 115, 172,
+    ];
+    assert_eq!(fingerprint(&payer, &[2; 8], PRICE, &[3; 32]), expected);
+}
+
+#[test]
+fn matches_the_off_chain_computation() {
+    let payer = Pubkey::new_unique();
+    let data = compute_data(Some(Pubkey::new_unique()));
+
+    let tag: [u8; 8] = data[..8].try_into().unwrap();
+    let off_chain = fingerprint(&payer, &tag, PRICE, &hash(&data).to_bytes());
+    assert_eq!(instruction_fingerprint(&payer, &data, PRICE), Some(off_chain));
+    assert_eq!(on_chain(&payer, data).unwrap(), off_chain);
+}
+
+#[test]
+fn distinguishes_payers_prices_and_arguments() {
+    let payer = Pubkey::new_unique();
+    let data = compute_data(None);
+    let base = instruction_fingerprint(&payer, &data, PRICE).unwrap();
+
+    assert_ne!(instruction_fingerprint(&Pubkey::new_unique(), &data, PRICE).unwrap(), base);
+    assert_ne!(instruction_fingerprint(&payer, &data, PRICE + 1).unwrap(), base);
+    assert_ne!(
+        instruction_fingerprint(&payer, &compute_data(Some(payer)), PRICE).unwrap(),
+        base
+    );
+}
+
+#[test]
+fn untagged_data_is_rejected() {
+    let payer = Pubkey::new_unique();
+    assert_eq!(instruction_fingerprint(&payer, &[1, 2, 3], PRICE), None);
+    assert_eq!(
+        on_chain(&payer, vec![1, 2, 3]).unwrap_err(),
+        error!(X402Error::SysvarUnavailable)
+    );
+}
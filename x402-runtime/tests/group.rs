@@ -0,0 +1,308 @@
+//! Priced instruction groups, run the way a transaction runs them: each
+//! grouped instruction in order opens the payer's budget, pays what it is
+//! due and settles, and a failing call reverts every write the transaction
+//! made, as the runtime would.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction;
+use x402_runtime::group::{covered, transaction_id, Group, GroupBudget};
+use x402_runtime::{verify_payment, Currency, VerifiedPayment, X402Error, X402Params};
+
+use common::{with_clock, Sysvar, PROGRAM_ID};
+
+const PREMIUM: u64 = 1_000_000;
+const STANDARD: u64 = 5_000_000;
+const ENTERPRISE: u64 = 50_000_000;
+const COMPUTE: Group = Group {
+    name: "compute",
+    cap: ENTERPRISE,
+};
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+const SLOT: u64 = 300;
+
+fn params(price: u64) -> X402Params {
+    X402Params {
+        price,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+    }
+}
+
+/// One instruction of a transaction: a grouped call at its list price, or
+/// anything else.
+enum Step {
+    Pay(u64),
+    Call(u64),
+}
+
+impl Step {
+    fn instruction(&self) -> Instruction {
+        match self {
+            Step::Pay(amount) => system_instruction::transfer(&PAYER, &RECIPIENT, *amount),
+            Step::Call(price) => Instruction {
+                program_id: PROGRAM_ID,
+                accounts: vec![AccountMeta::new(PAYER, true)],
+                data: price.to_le_bytes().to_vec(),
+            },
+        }
+    }
+}
+
+/// Runs `steps` as one transaction at `slot` against `budget`, returning
+/// the amount each grouped call was charged. On failure `budget` is left
+/// as it was, as the failed transaction's writes are.
+fn run(budget: &mut GroupBudget, steps: &[Step], slot: u64) -> Result<Vec<u64>> {
+    let instructions: Vec<Instruction> = steps.iter().map(Step::instruction).collect();
+    let mut written = *budget;
+    let mut charged = Vec::new();
+    for (index, step) in steps.iter().enumerate() {
+        let Step::Call(price) = step else {
+            continue;
+        };
+        let mut sysvar = Sysvar::at(&instructions, index as u16);
+        let accounts = [sysvar.account_info(false, false)];
+
+        let due = COMPUTE.open_at(&mut written, &params(*price), slot, transaction_id(&accounts)?)?;
+        let payment = if due.price == 0 {
+            covered(&due, &PAYER)
+        } else {
+            verify_payment(&accounts, &PROGRAM_ID, &due)?
+        };
+        COMPUTE.settle(&mut written, &payment)?;
+        charged.push(payment.amount);
+    }
+    *budget = written;
+    Ok(charged)
+}
+
+#[test]
+fn the_cap_is_hit_mid_transaction() {
+    let mut budget = GroupBudget::default();
+    let steps = [
+        Step::Pay(PREMIUM),
+        Step::Call(PREMIUM),
+        Step::Pay(STANDARD),
+        Step::Call(STANDARD),
+        // 56M of list prices so far; the cap leaves 44M due.
+        Step::Pay(ENTERPRISE - PREMIUM - STANDARD),
+        Step::Call(ENTERPRISE),
+        // Past the cap, with no transfer before it.
+        Step::Call(STANDARD),
+    ];
+
+    let charged = run(&mut budget, &steps, SLOT).unwrap();
+    assert_eq!(charged, [PREMIUM, STANDARD, ENTERPRISE - PREMIUM - STANDARD, 0]);
+    assert_eq!(budget.consumed_this_slot, PREMIUM + 2 * STANDARD + ENTERPRISE);
+    assert_eq!(budget.paid_this_slot, ENTERPRISE);
+    assert_eq!(budget.last_slot, SLOT);
+}
+
+#[test]
+fn calls_under_the_cap_pay_their_list_prices() {
+    let mut budget = GroupBudget::default();
+    let steps = [Step::Pay(PREMIUM), Step::Call(PREMIUM), Step::Pay(STANDARD), Step::Call(STANDARD)];
+
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [PREMIUM, STANDARD]);
+}
+
+#[test]
+fn paying_the_cap_up_front_covers_the_rest() {
+    let mut budget = GroupBudget::default();
+    let steps = [
+        Step::Pay(ENTERPRISE),
+        Step::Call(STANDARD),
+        Step::Call(ENTERPRISE),
+        Step::Call(PREMIUM),
+    ];
+
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [ENTERPRISE, 0, 0]);
+}
+
+#[test]
+fn an_overpayment_counts_toward_later_calls() {
+    let mut budget = GroupBudget::default();
+    let steps = [
+        Step::Pay(2 * PREMIUM),
+        Step::Call(PREMIUM),
+        Step::Call(PREMIUM),
+        Step::Pay(PREMIUM),
+        Step::Call(PREMIUM),
+    ];
+
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [2 * PREMIUM, 0, PREMIUM]);
+}
+
+#[test]
+fn a_short_payment_fails_the_transaction_and_leaves_the_budget() {
+    let mut budget = GroupBudget::default();
+    let steps = [
+        Step::Pay(PREMIUM),
+        Step::Call(PREMIUM),
+        // 49M is due; paying the list price of the cheaper call is not enough.
+        Step::Pay(PREMIUM),
+        Step::Call(ENTERPRISE),
+    ];
+
+    let err = run(&mut budget, &steps, SLOT).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+    assert_eq!(budget, GroupBudget::default());
+}
+
+#[test]
+fn a_retry_after_a_failure_pays_in_full() {
+    let mut budget = GroupBudget::default();
+    let failing = [Step::Pay(PREMIUM), Step::Call(PREMIUM), Step::Pay(PREMIUM), Step::Call(ENTERPRISE)];
+    run(&mut budget, &failing, SLOT).unwrap_err();
+
+    // Nothing of the failed attempt was kept, its first call's payment
+    // included, so the retry pays that call again.
+    let retry = [
+        Step::Pay(PREMIUM),
+        Step::Call(PREMIUM),
+        Step::Pay(ENTERPRISE - PREMIUM),
+        Step::Call(ENTERPRISE),
+    ];
+    assert_eq!(run(&mut budget, &retry, SLOT).unwrap(), [PREMIUM, ENTERPRISE - PREMIUM]);
+    assert_eq!(budget.paid_this_slot, ENTERPRISE);
+}
+
+#[test]
+fn another_transaction_in_the_same_slot_starts_over() {
+    let mut budget = GroupBudget::default();
+    run(&mut budget, &[Step::Pay(ENTERPRISE), Step::Call(ENTERPRISE)], SLOT).unwrap();
+
+    let err = run(&mut budget, &[Step::Call(PREMIUM)], SLOT).unwrap_err();
+    assert_eq!(err, X402Error::NoPaymentInstruction.into());
+    assert_eq!(budget.paid_this_slot, ENTERPRISE);
+}
+
+#[test]
+fn the_same_transaction_in_a_later_slot_starts_over() {
+    let mut budget = GroupBudget::default();
+    let steps = [Step::Pay(ENTERPRISE), Step::Call(ENTERPRISE), Step::Call(STANDARD)];
+
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [ENTERPRISE, 0]);
+    let short = [Step::Pay(STANDARD), Step::Call(ENTERPRISE)];
+    let err = run(&mut budget, &short, SLOT + 1).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+    assert_eq!(run(&mut budget, &steps, SLOT + 1).unwrap(), [ENTERPRISE, 0]);
+    assert_eq!(budget.last_slot, SLOT + 1);
+}
+
+#[test]
+fn identical_instructions_in_one_slot_share_a_budget_and_their_transfers() {
+    let mut budget = GroupBudget::default();
+    let steps = [Step::Pay(ENTERPRISE), Step::Call(ENTERPRISE)];
+    run(&mut budget, &steps, SLOT).unwrap();
+
+    // The second copy is covered, but its own transfer of the cap still
+    // runs: it pays what the first paid.
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [0]);
+    assert_eq!(budget.consumed_this_slot, 2 * ENTERPRISE);
+}
+
+#[test]
+fn a_payment_is_counted_once() {
+    let mut budget = GroupBudget::default();
+    let payment = VerifiedPayment {
+        amount: STANDARD,
+        payer: PAYER,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+        ix_index: 2,
+    };
+    COMPUTE.open_at(&mut budget, &params(STANDARD), SLOT, [1; 32]).unwrap();
+    COMPUTE.settle(&mut budget, &payment).unwrap();
+    COMPUTE.open_at(&mut budget, &params(STANDARD), SLOT, [1; 32]).unwrap();
+    let before = budget;
+
+    let err = COMPUTE.settle(&mut budget, &payment).unwrap_err();
+    assert_eq!(err, X402Error::GroupPaymentReused.into());
+    let earlier = VerifiedPayment { ix_index: 0, ..payment };
+    assert_eq!(COMPUTE.settle(&mut budget, &earlier).unwrap_err(), X402Error::GroupPaymentReused.into());
+    assert_eq!(budget, before);
+}
+
+#[test]
+fn overflowing_consumption_leaves_the_budget() {
+    let mut budget = GroupBudget {
+        last_slot: SLOT,
+        transaction: [1; 32],
+        consumed_this_slot: u64::MAX - 1,
+        ..GroupBudget::default()
+    };
+    let before = budget;
+
+    let err = COMPUTE.open_at(&mut budget, &params(PREMIUM), SLOT, [1; 32]).unwrap_err();
+    assert_eq!(err, X402Error::StatsOverflow.into());
+    assert_eq!(budget, before);
+}
+
+#[test]
+fn open_takes_the_budget_at_its_address_and_the_clocks_slot() {
+    let program_id = Pubkey::new_unique();
+    let steps = [Step::Pay(PREMIUM), Step::Call(PREMIUM)];
+    let instructions: Vec<Instruction> = steps.iter().map(Step::instruction).collect();
+    let mut sysvar = Sysvar::at(&instructions, 1);
+    let accounts = [sysvar.account_info(false, false)];
+    let address = COMPUTE.address(&program_id, &PAYER);
+    let clock = Some(Clock {
+        slot: SLOT,
+        ..Clock::default()
+    });
+
+    let mut budget = GroupBudget::default();
+    let wrong = Pubkey::new_unique();
+    let err = with_clock(clock.clone(), || {
+        COMPUTE.open(&wrong, &program_id, &PAYER, &mut budget, &params(PREMIUM), &accounts)
+    })
+    .unwrap_err();
+    assert_eq!(err, X402Error::GroupBudgetMismatch.into());
+    let err = with_clock(None, || {
+        COMPUTE.open(&address, &program_id, &PAYER, &mut budget, &params(PREMIUM), &accounts)
+    })
+    .unwrap_err();
+    assert_eq!(err, X402Error::ClockUnavailable.into());
+    assert_eq!(budget, GroupBudget::default());
+
+    let due = with_clock(clock, || {
+        COMPUTE.open(&address, &program_id, &PAYER, &mut budget, &params(PREMIUM), &accounts)
+    })
+    .unwrap();
+    assert_eq!(due.price, PREMIUM);
+    assert_eq!(budget.last_slot, SLOT);
+    assert_eq!(budget.transaction, transaction_id(&accounts).unwrap());
+}
+
+#[test]
+fn every_instruction_of_a_transaction_reads_one_id() {
+    let steps = [Step::Pay(PREMIUM), Step::Call(PREMIUM), Step::Call(STANDARD)];
+    let instructions: Vec<Instruction> = steps.iter().map(Step::instruction).collect();
+    let (mut first, mut second) = (Sysvar::at(&instructions, 1), Sysvar::at(&instructions, 2));
+    let mut other = Sysvar::at(&instructions[..2], 1);
+
+    let first = transaction_id(&[first.account_info(false, false)]).unwrap();
+    assert_eq!(first, transaction_id(&[second.account_info(false, false)]).unwrap());
+    assert_ne!(first, transaction_id(&[other.account_info(false, false)]).unwrap());
+}
+
+#[test]
+fn a_transfer_after_the_last_call_counts_toward_nothing() {
+    let mut budget = GroupBudget::default();
+    let steps = [
+        Step::Pay(PREMIUM),
+        Step::Call(PREMIUM),
+        Step::Pay(STANDARD),
+        Step::Call(STANDARD),
+        // A tip to the recipient, after every grouped call.
+        Step::Pay(ENTERPRISE),
+    ];
+
+    assert_eq!(run(&mut budget, &steps, SLOT).unwrap(), [PREMIUM, STANDARD]);
+    assert_eq!(budget.paid_this_slot, PREMIUM + STANDARD);
+    assert_eq!(budget.counted_through, 3);
+}
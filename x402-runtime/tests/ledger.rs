@@ -0,0 +1,85 @@
+//! A payer's history, as `ledger = true` records it: each payment of the
+//! payer adds to it, and a ledger of someone else or a total that would
+//! overflow fails the call without changing it.
+
+use solana_program::pubkey::Pubkey;
+use x402_runtime::ledger::{record_at, LedgerSnapshot};
+use x402_runtime::{Currency, VerifiedPayment, X402Error};
+
+const NOW: i64 = 1_700_000_000;
+
+struct Ledger {
+    payer: Pubkey,
+    total_payments: u64,
+    total_amount: u64,
+    last_payment: i64,
+}
+
+impl Ledger {
+    fn new(payer: Pubkey) -> Self {
+        Ledger {
+            payer,
+            total_payments: 0,
+            total_amount: 0,
+            last_payment: 0,
+        }
+    }
+
+    fn record(&mut self, payment: &VerifiedPayment, now: i64) -> anchor_lang::Result<LedgerSnapshot> {
+        record_at(
+            &self.payer,
+            &mut self.total_payments,
+            &mut self.total_amount,
+            &mut self.last_payment,
+            payment,
+            now,
+        )
+    }
+}
+
+fn payment(payer: Pubkey, amount: u64) -> VerifiedPayment {
+    VerifiedPayment {
+        amount,
+        payer,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+#[test]
+fn snapshot_includes_the_payment_just_recorded() {
+    let payer = Pubkey::new_unique();
+    let mut ledger = Ledger::new(payer);
+    ledger.record(&payment(payer, 1_000_000), NOW - 60).unwrap();
+
+    let snapshot = ledger.record(&payment(payer, 5_000_001), NOW).unwrap();
+    assert_eq!(
+        snapshot,
+        LedgerSnapshot {
+            payer,
+            total_payments: 2,
+            total_amount: 6_000_001,
+            last_payment: NOW,
+        }
+    );
+    assert_eq!((ledger.total_payments, ledger.total_amount, ledger.last_payment), (2, 6_000_001, NOW));
+}
+
+#[test]
+fn another_payers_ledger_is_left_alone() {
+    let mut ledger = Ledger::new(Pubkey::new_unique());
+    let err = ledger.record(&payment(Pubkey::new_unique(), 1_000_000), NOW).unwrap_err();
+    assert_eq!(err, X402Error::LedgerPayerMismatch.into());
+    assert_eq!((ledger.total_payments, ledger.total_amount, ledger.last_payment), (0, 0, 0));
+}
+
+#[test]
+fn overflow_leaves_the_ledger_unchanged() {
+    let payer = Pubkey::new_unique();
+    let mut ledger = Ledger::new(payer);
+    ledger.total_amount = u64::MAX - 1;
+    let err = ledger.record(&payment(payer, 2), NOW).unwrap_err();
+    assert_eq!(err, X402Error::StatsOverflow.into());
+    assert_eq!((ledger.total_payments, ledger.total_amount, ledger.last_payment), (0, u64::MAX - 1, 0));
+}
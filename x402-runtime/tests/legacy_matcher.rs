@@ -0,0 +1,81 @@
+//! The strict matcher is the default; `legacy_matcher` brings back the
+//! deprecated guess for the program's own instructions, and a runtime
+//! built with `strict-encodings` ignores it.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use x402_runtime::{verify_payment, verify_payment_with, Currency, ScanOptions, VerifiedPayment, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+const LEGACY: ScanOptions = ScanOptions {
+    legacy_matcher: true,
+    ..ScanOptions::DEFAULT
+};
+
+fn params(recipient: Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    }
+}
+
+/// An instruction of the program itself named `name`, with the price
+/// right after its discriminator.
+fn program_pay(name: &str, payer: &Pubkey, recipient: &Pubkey) -> Instruction {
+    let mut data = hash(format!("global:{}", name).as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(&PRICE.to_le_bytes());
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(*payer, true),
+            AccountMeta::new(*recipient, false),
+            AccountMeta::new_readonly(solana_program::system_program::ID, false),
+        ],
+        data,
+    }
+}
+
+fn verify(preceding: Instruction, payer: &Pubkey, recipient: &Pubkey, scan: &ScanOptions) -> Result<VerifiedPayment> {
+    let mut sysvar = Sysvar::new(&[preceding], payer);
+    verify_payment_with(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(*recipient), scan)
+}
+
+#[test]
+fn strict_by_default() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[program_pay("tip", &payer, &recipient)], &payer);
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient)).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+}
+
+#[test]
+fn x402_pay_counts_under_both_matchers() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for scan in [ScanOptions::DEFAULT, LEGACY] {
+        let payment = verify(program_pay("x402_pay", &payer, &recipient), &payer, &recipient, &scan).unwrap();
+        assert_eq!((payment.payer, payment.amount), (payer, PRICE));
+    }
+}
+
+#[cfg(not(feature = "strict-encodings"))]
+#[test]
+fn legacy_matcher_accepts_other_program_instructions() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let payment = verify(program_pay("tip", &payer, &recipient), &payer, &recipient, &LEGACY).unwrap();
+    assert_eq!((payment.payer, payment.recipient, payment.amount), (payer, recipient, PRICE));
+}
+
+#[cfg(feature = "strict-encodings")]
+#[test]
+fn strict_encodings_ignores_legacy_matcher() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let err = verify(program_pay("tip", &payer, &recipient), &payer, &recipient, &LEGACY).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+}
@@ -0,0 +1,87 @@
+//! v0 transactions loading the payment's accounts through an address
+//! lookup table. The instructions sysvar holds every meta resolved, with
+//! no trace of the table, so a table-loaded recipient is matched as a
+//! static one; a table cannot load signers, so a source loaded through one
+//! never passes `require_fee_payer`.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use x402_runtime::matcher::X402_PAY_DISCRIMINATOR;
+use x402_runtime::{require_fee_payer, verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn params(recipient: &Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: *recipient,
+        currency: Currency::Sol,
+    }
+}
+
+/// `ix` as the runtime resolves it when `loaded` come from a lookup table:
+/// the same pubkeys and writability, and never a signer.
+fn through_table(mut ix: Instruction, loaded: &[Pubkey]) -> Instruction {
+    for meta in &mut ix.accounts {
+        if loaded.contains(&meta.pubkey) {
+            meta.is_signer = false;
+        }
+    }
+    ix
+}
+
+/// Verifies `payment` as the expansion does with `require_fee_payer`. The
+/// sysvar is passed read-only and unsigned, as it is when a table loads it.
+fn run(payment: Instruction, payer: &Pubkey, recipient: &Pubkey) -> Result<()> {
+    let mut sysvar = Sysvar::new(&[payment], payer);
+    let accounts = [sysvar.account_info(false, false)];
+
+    let verified = verify_payment(&accounts, &PROGRAM_ID, &params(recipient))?;
+    assert_eq!((verified.payer, verified.amount), (*payer, PRICE));
+    require_fee_payer(&accounts, &verified, payer)
+}
+
+#[test]
+fn a_table_loaded_recipient_reads_as_a_static_one() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let static_keys = transfer(&payer, &recipient, PRICE);
+    let loaded = through_table(static_keys.clone(), &[recipient]);
+
+    let data = Sysvar::new(std::slice::from_ref(&loaded), &payer).data;
+    assert_eq!(data, Sysvar::new(&[static_keys], &payer).data);
+    run(loaded, &payer, &recipient).unwrap();
+}
+
+#[test]
+fn table_loaded_writable_accounts_beside_the_payment_are_ignored() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (ledger, stats) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut data = X402_PAY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&PRICE.to_le_bytes());
+    let pay = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new(recipient, false),
+            AccountMeta::new(ledger, false),
+            AccountMeta::new(stats, false),
+        ],
+        data,
+    };
+
+    run(through_table(pay, &[recipient, ledger, stats]), &payer, &recipient).unwrap();
+}
+
+#[test]
+fn a_table_loaded_source_is_not_the_fee_payer() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let loaded = through_table(transfer(&payer, &recipient, PRICE), &[payer, recipient]);
+
+    let err = run(loaded, &payer, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::FeePayerMismatch.into());
+}
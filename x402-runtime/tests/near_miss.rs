@@ -0,0 +1,199 @@
+//! The closest miss a payment scan reports: a payment in the wrong token,
+//! in the right token but short, or fully matching, each distinct.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::Instruction;
+use solana_program::pubkey;
+use solana_program::system_instruction::transfer;
+use spl_associated_token_account::get_associated_token_address_with_program_id;
+use spl_associated_token_account::instruction::create_associated_token_account_idempotent;
+use x402_runtime::matcher::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use x402_runtime::{
+    associated_token_address, created_associated_account, match_payment, verify_payment, verify_payment_with, Currency, NearMiss, PaymentScan,
+    ScanOptions, X402Error, X402Params,
+};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_250_000;
+
+fn usdc() -> Pubkey {
+    pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+}
+
+fn usdt() -> Pubkey {
+    pubkey!("Es9vMFrzaCERmJfrF4H2FYD4KCoNkY11McCe8BenwNYB")
+}
+
+/// A `TransferChecked` of `mint` from `payer` into `recipient`'s
+/// associated token account.
+fn pay_tokens(payer: &Pubkey, recipient: &Pubkey, mint: &Pubkey, amount: u64) -> Instruction {
+    spl_token::instruction::transfer_checked(
+        &spl_token::id(),
+        &Pubkey::new_unique(),
+        mint,
+        &associated_token_address(recipient, mint, &TOKEN_PROGRAM_ID),
+        payer,
+        &[],
+        amount,
+        6,
+    )
+    .unwrap()
+}
+
+fn offer(scan: &mut PaymentScan, ix: &Instruction) -> bool {
+    scan.offer(&match_payment(ix, &PROGRAM_ID).unwrap(), &ix.program_id)
+}
+
+#[test]
+fn associated_token_address_matches_the_ata_program() {
+    let (wallet, mint) = (Pubkey::new_unique(), usdc());
+    for token_program in [TOKEN_PROGRAM_ID, TOKEN_2022_PROGRAM_ID] {
+        assert_eq!(
+            associated_token_address(&wallet, &mint, &token_program),
+            get_associated_token_address_with_program_id(&wallet, &mint, &token_program)
+        );
+    }
+}
+
+#[test]
+fn wrong_mint_is_a_wrong_token() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scan = PaymentScan::new(recipient, PRICE, Some(usdc()));
+
+    assert!(!offer(&mut scan, &pay_tokens(&payer, &recipient, &usdt(), PRICE)));
+    assert_eq!(
+        scan.near_miss(),
+        Some(NearMiss::WrongToken {
+            expected: Some(usdc()),
+            found: Some(usdt())
+        })
+    );
+}
+
+#[test]
+fn right_mint_wrong_amount_is_insufficient() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scan = PaymentScan::new(recipient, PRICE, Some(usdc()));
+
+    assert!(!offer(&mut scan, &pay_tokens(&payer, &recipient, &usdc(), PRICE - 1)));
+    assert_eq!(
+        scan.near_miss(),
+        Some(NearMiss::Insufficient {
+            paid: PRICE - 1,
+            price: PRICE
+        })
+    );
+}
+
+#[test]
+fn right_everything_matches() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scan = PaymentScan::new(recipient, PRICE, Some(usdc()));
+
+    assert!(offer(&mut scan, &pay_tokens(&payer, &recipient, &usdc(), PRICE)));
+    assert_eq!(scan.near_miss(), None);
+
+    // A plain `Transfer` names no mint, but into the recipient's USDC
+    // account it can only move USDC.
+    let plain = spl_token::instruction::transfer(
+        &spl_token::id(),
+        &Pubkey::new_unique(),
+        &associated_token_address(&recipient, &usdc(), &TOKEN_PROGRAM_ID),
+        &payer,
+        &[],
+        PRICE,
+    )
+    .unwrap();
+    assert!(offer(&mut scan, &plain));
+}
+
+#[test]
+fn token_scans_step_over_creating_the_recipient_account() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let create = |wallet: &Pubkey, mint: &Pubkey| {
+        create_associated_token_account_idempotent(&payer, wallet, mint, &TOKEN_PROGRAM_ID)
+    };
+    assert_eq!(created_associated_account(&create(&recipient, &usdc())), Some((recipient, usdc())));
+
+    let scan = PaymentScan::new(recipient, PRICE, Some(usdc()));
+    assert!(scan.creates_recipient_account(&create(&recipient, &usdc())));
+    assert!(!scan.creates_recipient_account(&create(&recipient, &usdt())));
+    assert!(!scan.creates_recipient_account(&create(&Pubkey::new_unique(), &usdc())));
+
+    // Non-idempotent `Create` fails when the account exists, so clients
+    // never prepend it.
+    let mut create_once = create(&recipient, &usdc());
+    create_once.data = vec![0];
+    assert!(!scan.creates_recipient_account(&create_once));
+
+    let sol = PaymentScan::new(recipient, PRICE, None);
+    assert!(!sol.creates_recipient_account(&create(&recipient, &usdc())));
+}
+
+#[test]
+fn sol_scans_count_account_creation_as_an_instruction() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let params = X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    };
+    let create = create_associated_token_account_idempotent(&payer, &recipient, &usdc(), &TOKEN_PROGRAM_ID);
+    let mut sysvar = Sysvar::new(&[transfer(&payer, &recipient, PRICE), create], &payer);
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+}
+
+#[test]
+fn keeps_the_closest_miss() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut scan = PaymentScan::new(recipient, PRICE, Some(usdc()));
+
+    // Nearest first: USDC elsewhere, SOL to the recipient, USDC short,
+    // then USDT to the recipient.
+    offer(&mut scan, &pay_tokens(&payer, &Pubkey::new_unique(), &usdc(), PRICE));
+    assert!(matches!(scan.near_miss(), Some(NearMiss::WrongRecipient { .. })));
+    offer(&mut scan, &transfer(&payer, &recipient, PRICE));
+    assert_eq!(
+        scan.near_miss(),
+        Some(NearMiss::WrongToken {
+            expected: Some(usdc()),
+            found: None
+        })
+    );
+    offer(&mut scan, &pay_tokens(&payer, &recipient, &usdc(), 1));
+    offer(&mut scan, &pay_tokens(&payer, &recipient, &usdt(), PRICE));
+    assert_eq!(scan.near_miss(), Some(NearMiss::Insufficient { paid: 1, price: PRICE }));
+}
+
+#[test]
+fn sol_price_paid_in_tokens_is_a_wrong_token() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let params = X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    };
+
+    let mut sysvar = Sysvar::new(&[pay_tokens(&payer, &recipient, &usdc(), PRICE)], &payer);
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params).unwrap_err();
+    assert_eq!(err, X402Error::WrongPaymentToken.into());
+
+    // Also under a strict scan, which stops at that transfer.
+    let mut sysvar = Sysvar::new(&[pay_tokens(&payer, &recipient, &usdc(), PRICE)], &payer);
+    let strict = ScanOptions {
+        max_lookback: 1,
+        strict: true,
+        legacy_matcher: false,
+    };
+    let err = verify_payment_with(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params, &strict).unwrap_err();
+    assert_eq!(err, X402Error::WrongPaymentToken.into());
+
+    // Tokens sent to someone else are just not a payment.
+    let mut sysvar = Sysvar::new(&[pay_tokens(&payer, &Pubkey::new_unique(), &usdc(), PRICE)], &payer);
+    let err = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+}
@@ -0,0 +1,273 @@
+//! `match_payment` against the reference model of its acceptance rules,
+//! `x402_common::reference::classify`: every instruction of the golden
+//! vectors, of the fuzz corpus and of the generators below must get the
+//! same verdict from both, the same payment or the same rejection. A change
+//! to what the matcher accepts that leaves the model behind, or the other
+//! way round, fails here.
+
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use proptest::prelude::*;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::{system_instruction, system_program};
+use x402_common::reference::{self, Kind, Payment, Rejection};
+use x402_runtime::matcher::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID, X402_PAY_DISCRIMINATOR};
+use x402_runtime::{match_payment, MatchError, PaymentKind};
+
+/// The program running the check, as in the vectors and the fuzz target.
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+
+fn production(ix: &Instruction) -> Result<Payment, Rejection> {
+    match match_payment(ix, &PROGRAM_ID) {
+        Ok(payment) => Ok(Payment {
+            kind: match payment.kind {
+                PaymentKind::SystemTransfer => Kind::SystemTransfer,
+                PaymentKind::SystemTransferWithSeed => Kind::SystemTransferWithSeed,
+                PaymentKind::ProgramPay => Kind::ProgramPay,
+                PaymentKind::TokenTransfer => Kind::TokenTransfer,
+                PaymentKind::TokenTransferChecked => Kind::TokenTransferChecked,
+            },
+            payer: payment.payer.to_bytes(),
+            recipient: payment.recipient.to_bytes(),
+            amount: payment.amount,
+            mint: payment.mint.map(|mint| mint.to_bytes()),
+        }),
+        Err(MatchError::UnsupportedProgram) => Err(Rejection::UnsupportedProgram),
+        Err(MatchError::UnrecognizedPaymentEncoding) => Err(Rejection::UnrecognizedPaymentEncoding),
+        Err(MatchError::MalformedData) => Err(Rejection::MalformedData),
+        Err(MatchError::MissingAccounts) => Err(Rejection::MissingAccounts),
+        Err(MatchError::NotATransfer) => panic!("only the legacy matcher finds an instruction not a transfer"),
+    }
+}
+
+fn model(ix: &Instruction) -> Result<Payment, Rejection> {
+    let accounts: Vec<[u8; 32]> = ix.accounts.iter().map(|meta| meta.pubkey.to_bytes()).collect();
+    reference::classify(&ix.program_id.to_bytes(), &PROGRAM_ID.to_bytes(), &accounts, &ix.data)
+}
+
+/// The golden vectors of `tests/vectors.rs`, as instructions.
+fn vectors() -> Vec<(String, Instruction)> {
+    let pubkey = |value: &serde_json::Value| Pubkey::from_str(value.as_str().unwrap()).unwrap();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors");
+    fs::read_dir(dir)
+        .expect("tests/vectors is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let vector: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+            let ix = &vector["instruction"];
+            let data = ix["data"].as_str().unwrap();
+            let ix = Instruction {
+                program_id: pubkey(&ix["program_id"]),
+                accounts: ix["accounts"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|meta| AccountMeta {
+                        pubkey: pubkey(&meta["pubkey"]),
+                        is_signer: meta["is_signer"].as_bool().unwrap(),
+                        is_writable: meta["is_writable"].as_bool().unwrap(),
+                    })
+                    .collect(),
+                data: (0..data.len())
+                    .step_by(2)
+                    .map(|i| u8::from_str_radix(&data[i..i + 2], 16).unwrap())
+                    .collect(),
+            };
+            (path.file_stem().unwrap().to_string_lossy().into_owned(), ix)
+        })
+        .collect()
+}
+
+/// The seed corpus of `fuzz/fuzz_targets/match_payment.rs`, decoded as the
+/// target decodes its input.
+fn fuzz_corpus() -> Vec<(String, Instruction)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../fuzz/corpus/match_payment");
+    fs::read_dir(dir)
+        .expect("fuzz/corpus/match_payment is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter_map(|path| {
+            let ix = decode_fuzz_input(&fs::read(&path).unwrap())?;
+            Some((path.file_name().unwrap().to_string_lossy().into_owned(), ix))
+        })
+        .collect()
+}
+
+fn decode_fuzz_input(input: &[u8]) -> Option<Instruction> {
+    let (&selector, mut input) = input.split_first()?;
+    let program_id = match selector {
+        0 => system_program::ID,
+        1 => TOKEN_PROGRAM_ID,
+        2 => TOKEN_2022_PROGRAM_ID,
+        3 => PROGRAM_ID,
+        _ => {
+            let (id, rest) = (input.get(..32)?, &input[32..]);
+            input = rest;
+            Pubkey::try_from(id).ok()?
+        }
+    };
+    let (&count, mut input) = input.split_first()?;
+    let mut accounts = Vec::new();
+    for _ in 0..count % 16 {
+        let raw = input.get(..33)?;
+        input = &input[33..];
+        accounts.push(AccountMeta {
+            pubkey: Pubkey::try_from(&raw[..32]).ok()?,
+            is_signer: raw[32] & 1 != 0,
+            is_writable: raw[32] & 2 != 0,
+        });
+    }
+    Some(Instruction {
+        program_id,
+        accounts,
+        data: input[..input.len().min(4096)].to_vec(),
+    })
+}
+
+#[test]
+fn the_model_names_the_matchers_programs() {
+    assert_eq!(reference::SYSTEM_PROGRAM, system_program::ID.to_bytes());
+    assert_eq!(reference::TOKEN_PROGRAM, TOKEN_PROGRAM_ID.to_bytes());
+    assert_eq!(reference::TOKEN_2022_PROGRAM, TOKEN_2022_PROGRAM_ID.to_bytes());
+    assert_eq!(reference::X402_PAY, X402_PAY_DISCRIMINATOR);
+    assert_eq!(reference::X402_PAY[..], hash(b"global:x402_pay").to_bytes()[..8]);
+}
+
+#[test]
+fn the_model_agrees_on_every_vector() {
+    let vectors = vectors();
+    assert!(!vectors.is_empty(), "no vectors in tests/vectors");
+    for (name, ix) in &vectors {
+        assert_eq!(model(ix), production(ix), "vector {}", name);
+    }
+
+    // Every kind the model describes is accepted by some vector.
+    let kinds = [
+        Kind::SystemTransfer,
+        Kind::SystemTransferWithSeed,
+        Kind::ProgramPay,
+        Kind::TokenTransfer,
+        Kind::TokenTransferChecked,
+    ];
+    for kind in kinds {
+        assert!(
+            vectors.iter().any(|(_, ix)| model(ix).is_ok_and(|payment| payment.kind == kind)),
+            "no vector accepted as {:?}",
+            kind
+        );
+    }
+}
+
+#[test]
+fn the_model_agrees_on_the_fuzz_corpus() {
+    let corpus = fuzz_corpus();
+    assert!(!corpus.is_empty(), "no inputs in fuzz/corpus/match_payment");
+    for (name, ix) in &corpus {
+        assert_eq!(model(ix), production(ix), "fuzz input {}", name);
+    }
+}
+
+fn pubkey() -> impl Strategy<Value = Pubkey> {
+    any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+}
+
+fn x402_pay(payer: &Pubkey, recipient: &Pubkey, amount: u64) -> Instruction {
+    let mut data = X402_PAY_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&amount.to_le_bytes());
+    Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![AccountMeta::new(*payer, true), AccountMeta::new(*recipient, false)],
+        data,
+    }
+}
+
+/// Payments as the SDKs build them, to every program the matcher reads.
+fn sdk_payment() -> impl Strategy<Value = Instruction> {
+    let keys = (pubkey(), pubkey(), pubkey(), pubkey());
+    (0..7u8, keys, any::<u64>(), "[a-z0-9]{0,32}").prop_map(|(which, (a, b, c, d), amount, seed)| {
+        let token_2022 = |mut ix: Instruction| {
+            ix.program_id = TOKEN_2022_PROGRAM_ID;
+            ix
+        };
+        let spl_transfer = spl_token::instruction::transfer(&spl_token::id(), &a, &b, &c, &[], amount).unwrap();
+        let spl_transfer_checked =
+            spl_token::instruction::transfer_checked(&spl_token::id(), &a, &b, &c, &d, &[], amount, 6).unwrap();
+        match which {
+            0 => system_instruction::transfer(&a, &b, amount),
+            1 => system_instruction::transfer_with_seed(&a, &b, seed, &c, &d, amount),
+            2 => x402_pay(&a, &b, amount),
+            3 => spl_transfer,
+            4 => spl_transfer_checked,
+            5 => token_2022(spl_transfer),
+            _ => token_2022(spl_transfer_checked),
+        }
+    })
+}
+
+/// An SDK payment with one byte changed, bytes cut or appended, or
+/// accounts dropped, which either matcher may accept or reject.
+fn near_payment() -> impl Strategy<Value = Instruction> {
+    (sdk_payment(), 0..4u8, any::<usize>(), any::<u8>(), 0..4usize).prop_map(|(mut ix, how, index, byte, count)| {
+        match how {
+            0 if !ix.data.is_empty() => {
+                let index = index % ix.data.len();
+                ix.data[index] = byte;
+            }
+            1 => ix.data.truncate(index % (ix.data.len() + 1)),
+            2 => ix.data.resize(ix.data.len() + count, byte),
+            _ => ix.accounts.truncate(ix.accounts.len().saturating_sub(count)),
+        }
+        ix
+    })
+}
+
+/// Arbitrary instructions to the matcher's programs and others, with data
+/// that starts with one of their tags half the time.
+fn any_instruction() -> impl Strategy<Value = Instruction> {
+    let program = prop_oneof![
+        Just(system_program::ID),
+        Just(TOKEN_PROGRAM_ID),
+        Just(TOKEN_2022_PROGRAM_ID),
+        Just(PROGRAM_ID),
+        pubkey(),
+    ];
+    let tag = prop_oneof![
+        Just(Vec::new()),
+        Just(2u32.to_le_bytes().to_vec()),
+        Just(11u32.to_le_bytes().to_vec()),
+        Just(vec![3]),
+        Just(vec![12]),
+        Just(X402_PAY_DISCRIMINATOR.to_vec()),
+    ];
+    let accounts = prop::collection::vec(pubkey(), 0..5);
+    (program, accounts, tag, prop::collection::vec(any::<u8>(), 0..80)).prop_map(|(program_id, keys, tag, rest)| {
+        Instruction {
+            program_id,
+            accounts: keys.iter().map(|key| AccountMeta::new(*key, false)).collect(),
+            data: tag.into_iter().chain(rest).collect(),
+        }
+    })
+}
+
+proptest! {
+    #[test]
+    fn the_model_agrees_on_sdk_payments(ix in sdk_payment()) {
+        let verdict = model(&ix);
+        prop_assert!(verdict.is_ok());
+        prop_assert_eq!(verdict, production(&ix));
+    }
+
+    #[test]
+    fn the_model_agrees_on_near_payments(ix in near_payment()) {
+        prop_assert_eq!(model(&ix), production(&ix));
+    }
+
+    #[test]
+    fn the_model_agrees_on_any_instruction(ix in any_instruction()) {
+        prop_assert_eq!(model(&ix), production(&ix));
+    }
+}
@@ -0,0 +1,95 @@
+//! Rent kept apart from revenue: a call that pays and creates an account
+//! adds the payment to `total_amount` and the account's rent to
+//! `total_rent_paid`, never one to the other.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use x402_runtime::{ledger, rent, Currency, VerifiedPayment, X402Error};
+
+use common::with_rent;
+
+const NOW: i64 = 1_700_000_000;
+const PRICE: u64 = 1_000_000;
+
+#[derive(InitSpace)]
+#[allow(dead_code)]
+struct Receipt {
+    owner: Pubkey,
+    amount: u64,
+}
+
+#[derive(Default)]
+struct Ledger {
+    payer: Pubkey,
+    total_payments: u64,
+    total_amount: u64,
+    last_payment: i64,
+    total_rent_paid: u64,
+}
+
+fn payment(payer: Pubkey) -> VerifiedPayment {
+    VerifiedPayment {
+        amount: PRICE,
+        payer,
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+#[test]
+fn rent_for_is_the_rent_exempt_minimum_with_the_discriminator() {
+    let sysvar = Rent {
+        lamports_per_byte_year: 10,
+        ..Rent::default()
+    };
+    let (typed, sized) = with_rent(sysvar, || {
+        (rent::rent_for::<Receipt>().unwrap(), rent::rent_for_len(100).unwrap())
+    });
+
+    assert_eq!(typed, sysvar.minimum_balance(8 + 32 + 8));
+    assert_eq!(sized, sysvar.minimum_balance(100));
+}
+
+#[test]
+fn paying_and_creating_fills_both_buckets() {
+    let payer = Pubkey::new_unique();
+    let mut account = Ledger {
+        payer,
+        ..Ledger::default()
+    };
+    let receipt_rent = with_rent(Rent::default(), || rent::rent_for::<Receipt>().unwrap());
+
+    ledger::record_at(
+        &account.payer,
+        &mut account.total_payments,
+        &mut account.total_amount,
+        &mut account.last_payment,
+        &payment(payer),
+        NOW,
+    )
+    .unwrap();
+    rent::record(&mut account.total_rent_paid, receipt_rent).unwrap();
+
+    assert_eq!(account.total_payments, 1);
+    assert_eq!(account.total_amount, PRICE);
+    assert_eq!(account.total_rent_paid, receipt_rent);
+    assert_eq!(account.last_payment, NOW);
+}
+
+#[test]
+fn rent_accumulates() {
+    let mut total = 0;
+    assert_eq!(rent::record(&mut total, 2_000_000).unwrap(), 2_000_000);
+    assert_eq!(rent::record(&mut total, 1_500_000).unwrap(), 3_500_000);
+    assert_eq!(total, 3_500_000);
+}
+
+#[test]
+fn overflow_leaves_the_total_untouched() {
+    let mut total = u64::MAX - 1;
+    let err = rent::record(&mut total, 2).unwrap_err();
+    assert_eq!(err, X402Error::StatsOverflow.into());
+    assert_eq!(total, u64::MAX - 1);
+}
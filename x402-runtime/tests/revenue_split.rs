@@ -0,0 +1,143 @@
+//! One price split between several recipients by basis-point shares.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction::transfer;
+use x402_runtime::revenue_split::{required_amounts, verify_revenue_split, RevenueLeg, RevenueShare};
+use x402_runtime::{Currency, VerifiedPayment, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+/// Not a multiple of 10 000, so the first share takes a remainder.
+const PRICE: u64 = 1_000_001;
+const BUILDER: Pubkey = Pubkey::new_from_array([31; 32]);
+const DAO: Pubkey = Pubkey::new_from_array([32; 32]);
+const INFRA: Pubkey = Pubkey::new_from_array([33; 32]);
+const SHARES: &[RevenueShare] = &[
+    RevenueShare { recipient: BUILDER, bps: 7_000 },
+    RevenueShare { recipient: DAO, bps: 2_000 },
+    RevenueShare { recipient: INFRA, bps: 1_000 },
+];
+
+fn verify(sysvar: &mut Sysvar) -> Result<(VerifiedPayment, Vec<RevenueLeg>)> {
+    let params = X402Params {
+        price: PRICE,
+        recipient: BUILDER,
+        currency: Currency::Sol,
+    };
+    verify_revenue_split(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params, SHARES)
+}
+
+#[test]
+fn shares_round_down_with_the_remainder_on_the_first() {
+    assert_eq!(required_amounts(PRICE, SHARES), vec![700_001, 200_000, 100_000]);
+    assert_eq!(required_amounts(PRICE, SHARES).iter().sum::<u64>(), PRICE);
+}
+
+#[test]
+fn every_leg_paid_is_accepted() {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&payer, &BUILDER, 700_001),
+            transfer(&payer, &DAO, 200_000),
+            transfer(&payer, &INFRA, 100_500),
+        ],
+        &payer,
+    );
+
+    let (payment, legs) = verify(&mut sysvar).unwrap();
+    assert_eq!(payment.amount, PRICE + 500);
+    assert_eq!(payment.payer, payer);
+    assert_eq!(payment.recipient, BUILDER);
+    assert_eq!(payment.ix_index, 0);
+    assert_eq!(
+        legs,
+        vec![
+            RevenueLeg { recipient: BUILDER, bps: 7_000, required: 700_001, paid: 700_001 },
+            RevenueLeg { recipient: DAO, bps: 2_000, required: 200_000, paid: 200_000 },
+            RevenueLeg { recipient: INFRA, bps: 1_000, required: 100_000, paid: 100_500 },
+        ]
+    );
+}
+
+#[test]
+fn one_short_leg_is_rejected() {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&payer, &BUILDER, 700_000),
+            transfer(&payer, &DAO, 200_000),
+            transfer(&payer, &INFRA, 100_001),
+        ],
+        &payer,
+    );
+
+    let err = verify(&mut sysvar).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn an_extra_unknown_leg_is_ignored() {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&payer, &INFRA, 100_000),
+            transfer(&payer, &Pubkey::new_unique(), 5_000),
+            transfer(&payer, &BUILDER, 700_001),
+            transfer(&payer, &DAO, 200_000),
+        ],
+        &payer,
+    );
+
+    let (payment, legs) = verify(&mut sysvar).unwrap();
+    assert_eq!(payment.amount, PRICE);
+    assert_eq!(payment.ix_index, 0);
+    assert_eq!(legs.iter().map(|leg| leg.paid).collect::<Vec<_>>(), vec![700_001, 200_000, 100_000]);
+}
+
+#[test]
+fn a_missing_leg_is_rejected() {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(
+        &[transfer(&payer, &BUILDER, 700_001), transfer(&payer, &DAO, 300_000)],
+        &payer,
+    );
+
+    let err = verify(&mut sysvar).unwrap_err();
+    assert_eq!(err, X402Error::MissingRevenueLeg.into());
+}
+
+#[test]
+fn legs_from_another_source_do_not_count() {
+    let (payer, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&payer, &BUILDER, 700_001),
+            transfer(&other, &DAO, 200_000),
+            transfer(&payer, &INFRA, 100_000),
+        ],
+        &payer,
+    );
+
+    let err = verify(&mut sysvar).unwrap_err();
+    assert_eq!(err, X402Error::MissingRevenueLeg.into());
+}
+
+#[test]
+fn the_scan_stops_at_a_non_payment() {
+    let payer = Pubkey::new_unique();
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&payer, &BUILDER, 700_001),
+            common::priced_instruction(&payer),
+            transfer(&payer, &DAO, 200_000),
+            transfer(&payer, &INFRA, 100_000),
+        ],
+        &payer,
+    );
+
+    let err = verify(&mut sysvar).unwrap_err();
+    assert_eq!(err, X402Error::MissingRevenueLeg.into());
+}
@@ -0,0 +1,224 @@
+//! The backward scan for the payment: wallet-injected instructions of
+//! `IGNORABLE_PROGRAMS` are skipped for free, anything else spends
+//! `max_lookback` or, with `strict`, ends the scan. When nothing within
+//! the lookback qualifies, the closest miss picks the error.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use solana_program::sysvar::instructions;
+use x402_runtime::{
+    verify_payment, verify_payment_with, verify_split_payment, Currency, ScanOptions, VerifiedPayment, X402Error,
+    X402Params, IGNORABLE_PROGRAMS,
+};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+const COMPUTE_BUDGET: usize = 0;
+const MEMO: usize = 1;
+const ED25519: usize = 3;
+const SECP256K1: usize = 4;
+
+fn injected(which: usize) -> Instruction {
+    Instruction {
+        program_id: IGNORABLE_PROGRAMS[which],
+        accounts: vec![],
+        data: vec![2, 0x40, 0x0d, 0x03, 0x00],
+    }
+}
+
+/// An instruction of some program that cannot pay.
+fn other() -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        data: vec![1, 2, 3],
+    }
+}
+
+fn params(recipient: Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    }
+}
+
+fn verify(preceding: &[Instruction], payer: &Pubkey, recipient: &Pubkey, scan: ScanOptions) -> Result<VerifiedPayment> {
+    let mut sysvar = Sysvar::new(preceding, payer);
+    verify_payment_with(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(*recipient), &scan)
+}
+
+fn lookback(max_lookback: u8, strict: bool) -> ScanOptions {
+    ScanOptions {
+        max_lookback,
+        strict,
+        legacy_matcher: false,
+    }
+}
+
+#[test]
+fn skips_compute_budget_and_memo_around_the_payment() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        injected(COMPUTE_BUDGET),
+        injected(COMPUTE_BUDGET),
+        transfer(&payer, &recipient, PRICE),
+        injected(MEMO),
+    ];
+    let payment = verify(&preceding, &payer, &recipient, ScanOptions::DEFAULT).unwrap();
+    assert_eq!(payment.ix_index, 2);
+}
+
+#[test]
+fn skips_every_ignorable_program() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    for (which, program) in IGNORABLE_PROGRAMS.iter().enumerate() {
+        let preceding = [transfer(&payer, &recipient, PRICE), injected(which)];
+        let mut sysvar = Sysvar::new(&preceding, &payer);
+        let payment = verify_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient))
+            .unwrap_or_else(|err| panic!("{} not skipped: {:?}", program, err));
+        assert_eq!(payment.ix_index, 0);
+    }
+}
+
+#[test]
+fn skipped_instructions_do_not_spend_the_lookback() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        transfer(&payer, &recipient, PRICE),
+        injected(ED25519),
+        other(),
+        injected(SECP256K1),
+        injected(MEMO),
+        injected(COMPUTE_BUDGET),
+    ];
+    let payment = verify(&preceding, &payer, &recipient, lookback(2, false)).unwrap();
+    assert_eq!(payment.ix_index, 0);
+}
+
+#[test]
+fn other_programs_spend_the_lookback() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [transfer(&payer, &recipient, PRICE), other(), injected(MEMO), other()];
+
+    let err = verify(&preceding, &payer, &recipient, ScanOptions::DEFAULT).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+    let err = verify(&preceding, &payer, &recipient, lookback(2, false)).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+    verify(&preceding, &payer, &recipient, lookback(3, false)).unwrap();
+}
+
+#[test]
+fn another_programs_priced_call_ends_the_scan() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // What `#[x402]` on another program's handler is called with: its
+    // payer and the instructions sysvar it reads the same transfer from.
+    let other_priced = Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![
+            AccountMeta::new_readonly(payer, true),
+            AccountMeta::new_readonly(instructions::ID, false),
+        ],
+        data: vec![0; 8],
+    };
+    let preceding = [transfer(&payer, &recipient, PRICE), other_priced, injected(MEMO)];
+
+    let err = verify(&preceding, &payer, &recipient, lookback(4, false)).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+    // A program that does not read the sysvar is stepped over as before.
+    let preceding = [transfer(&payer, &recipient, PRICE), other(), injected(MEMO)];
+    verify(&preceding, &payer, &recipient, lookback(4, false)).unwrap();
+}
+
+#[test]
+fn strict_scan_stops_at_the_first_other_program() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [transfer(&payer, &recipient, PRICE), other(), injected(COMPUTE_BUDGET)];
+
+    let err = verify(&preceding, &payer, &recipient, lookback(4, true)).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+    // Ignorable instructions alone never trip it.
+    let preceding = [transfer(&payer, &recipient, PRICE), injected(MEMO), injected(COMPUTE_BUDGET)];
+    verify(&preceding, &payer, &recipient, lookback(4, true)).unwrap();
+}
+
+#[test]
+fn nearest_qualifying_payment_wins_over_closer_misses() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [
+        transfer(&payer, &recipient, PRICE),
+        transfer(&payer, &recipient, PRICE - 1),
+        transfer(&payer, &Pubkey::new_unique(), PRICE),
+    ];
+
+    let payment = verify(&preceding, &payer, &recipient, lookback(3, false)).unwrap();
+    assert_eq!(payment.ix_index, 0);
+    // A strict scan stops at the first miss.
+    let err = verify(&preceding, &payer, &recipient, lookback(3, true)).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+}
+
+#[test]
+fn closest_miss_picks_the_error() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+    // A short transfer to the recipient is closer than a full one to
+    // someone else, whichever comes first.
+    let preceding = [
+        transfer(&payer, &recipient, PRICE - 1),
+        other(),
+        transfer(&payer, &Pubkey::new_unique(), PRICE),
+    ];
+    let err = verify(&preceding, &payer, &recipient, lookback(3, false)).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+    let preceding = [
+        transfer(&payer, &Pubkey::new_unique(), PRICE),
+        transfer(&payer, &recipient, PRICE - 1),
+    ];
+    let err = verify(&preceding, &payer, &recipient, lookback(3, false)).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+
+    // Outside the lookback, the short transfer is not seen.
+    let preceding = [transfer(&payer, &recipient, PRICE - 1), transfer(&payer, &Pubkey::new_unique(), PRICE)];
+    let err = verify(&preceding, &payer, &recipient, ScanOptions::DEFAULT).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+
+    // A miss of any kind outranks instructions that are no payment at all.
+    let preceding = [transfer(&payer, &Pubkey::new_unique(), PRICE), other(), other()];
+    let err = verify(&preceding, &payer, &recipient, lookback(3, false)).unwrap_err();
+    assert_eq!(err, X402Error::WrongRecipient.into());
+}
+
+#[test]
+fn only_ignorable_instructions_is_no_payment() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let preceding = [injected(COMPUTE_BUDGET), injected(MEMO)];
+
+    let err = verify(&preceding, &payer, &recipient, ScanOptions::DEFAULT).unwrap_err();
+    assert_eq!(err, X402Error::NoPaymentInstruction.into());
+}
+
+#[test]
+fn split_payments_step_over_memos() {
+    let (alice, bob, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&bob, &recipient, PRICE / 2),
+            injected(MEMO),
+            transfer(&alice, &recipient, PRICE / 2),
+            injected(COMPUTE_BUDGET),
+        ],
+        &alice,
+    );
+
+    let (payment, contributions) =
+        verify_split_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(recipient), &alice, 4)
+            .unwrap();
+    assert_eq!(payment.amount, PRICE);
+    assert_eq!(payment.ix_index, 0);
+    assert_eq!(contributions.len(), 2);
+}
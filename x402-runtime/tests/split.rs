@@ -0,0 +1,101 @@
+//! Several payers jointly covering one price.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction::transfer;
+use x402_runtime::{verify_split_payment, Currency, PaymentContribution, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn verify(
+    sysvar: &mut Sysvar,
+    signer: &Pubkey,
+    recipient: &Pubkey,
+) -> Result<(x402_runtime::VerifiedPayment, Vec<PaymentContribution>)> {
+    let params = X402Params {
+        price: PRICE,
+        recipient: *recipient,
+        currency: Currency::Sol,
+    };
+    verify_split_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params, signer, 4)
+}
+
+#[test]
+fn two_payers_covering_the_price() {
+    let (alice, bob, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[transfer(&alice, &recipient, PRICE / 2), transfer(&bob, &recipient, PRICE / 2)],
+        &alice,
+    );
+
+    let (payment, contributions) = verify(&mut sysvar, &alice, &recipient).unwrap();
+    assert_eq!(payment.amount, PRICE);
+    assert_eq!(payment.payer, alice);
+    assert_eq!(payment.ix_index, 0);
+    assert_eq!(
+        contributions,
+        vec![
+            PaymentContribution { payer: bob, amount: PRICE / 2 },
+            PaymentContribution { payer: alice, amount: PRICE / 2 },
+        ]
+    );
+}
+
+#[test]
+fn two_payers_under_covering() {
+    let (alice, bob, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[transfer(&alice, &recipient, PRICE / 2), transfer(&bob, &recipient, PRICE / 2 - 1)],
+        &alice,
+    );
+
+    let err = verify(&mut sysvar, &alice, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn signer_not_among_payers() {
+    let (alice, bob, mallory, recipient) = (
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+        Pubkey::new_unique(),
+    );
+    let mut sysvar = Sysvar::new(
+        &[transfer(&alice, &recipient, PRICE / 2), transfer(&bob, &recipient, PRICE / 2)],
+        &mallory,
+    );
+
+    let err = verify(&mut sysvar, &mallory, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::SignerDidNotPay.into());
+}
+
+#[test]
+fn repeated_payer_is_rejected() {
+    let (alice, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[transfer(&alice, &recipient, PRICE / 2), transfer(&alice, &recipient, PRICE / 2)],
+        &alice,
+    );
+
+    let err = verify(&mut sysvar, &alice, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::DuplicateSplitPayer.into());
+}
+
+#[test]
+fn stops_at_transfers_to_someone_else() {
+    let (alice, bob, recipient) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(
+        &[
+            transfer(&bob, &Pubkey::new_unique(), PRICE),
+            transfer(&alice, &recipient, PRICE / 2),
+        ],
+        &alice,
+    );
+
+    let err = verify(&mut sysvar, &alice, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
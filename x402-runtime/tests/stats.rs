@@ -0,0 +1,71 @@
+//! Sequence numbering across payments. Indexers detect missed events by
+//! gaps in `sequence`, so every recorded payment must advance it by exactly
+//! one and a failed record must leave the stats untouched.
+
+use solana_program::pubkey::Pubkey;
+use x402_runtime::stats::record;
+use x402_runtime::{Currency, VerifiedPayment};
+
+#[derive(Default)]
+struct Stats {
+    sequence: u64,
+    total_payments: u64,
+    total_amount: u64,
+}
+
+impl Stats {
+    fn record(&mut self, payment: &VerifiedPayment) -> anchor_lang::Result<u64> {
+        record(
+            &mut self.sequence,
+            &mut self.total_payments,
+            &mut self.total_amount,
+            payment,
+        )
+    }
+}
+
+fn payment(amount: u64) -> VerifiedPayment {
+    VerifiedPayment {
+        amount,
+        payer: Pubkey::new_unique(),
+        recipient: Pubkey::new_unique(),
+        currency: Currency::Sol,
+        ix_index: 0,
+    }
+}
+
+#[test]
+fn sequence_is_consecutive_across_prices() {
+    let mut stats = Stats::default();
+    // Premium, standard and enterprise tiers sharing one stats account.
+    let amounts = [1_000_000, 5_000_000, 50_000_000, 1_000_000, 5_000_001];
+
+    let sequences: Vec<u64> = amounts
+        .iter()
+        .map(|&amount| stats.record(&payment(amount)).unwrap())
+        .collect();
+
+    assert_eq!(sequences, vec![1, 2, 3, 4, 5]);
+    assert_eq!(stats.sequence, 5);
+    assert_eq!(stats.total_payments, 5);
+    assert_eq!(stats.total_amount, amounts.iter().sum::<u64>());
+}
+
+#[test]
+fn overflow_leaves_stats_unchanged() {
+    let mut stats = Stats {
+        sequence: u64::MAX,
+        total_payments: 3,
+        total_amount: 10,
+    };
+    assert!(stats.record(&payment(1)).is_err());
+    assert_eq!((stats.sequence, stats.total_payments, stats.total_amount), (u64::MAX, 3, 10));
+
+    let mut stats = Stats {
+        sequence: 3,
+        total_payments: 3,
+        total_amount: u64::MAX,
+    };
+    assert!(stats.record(&payment(1)).is_err());
+    assert_eq!((stats.sequence, stats.total_payments, stats.total_amount), (3, 3, u64::MAX));
+}
@@ -0,0 +1,70 @@
+//! `strict_accounts` takes the sysvar from the declared struct and rejects
+//! remaining accounts; the permissive default finds the sysvar among them.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction;
+use x402_runtime::accounts::{declared_sysvar, forbid_remaining_accounts, PrecedingInstruction, X402Accounts};
+use x402_runtime::{verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+/// What `#[x402_accounts]` generates for a preceding-instruction struct.
+struct Declared<'info> {
+    x402_instructions: AccountInfo<'info>,
+}
+
+impl<'info> X402Accounts<'info> for Declared<'info> {
+    type Mode = PrecedingInstruction;
+
+    fn instructions_sysvar(&self) -> Option<AccountInfo<'info>> {
+        Some(self.x402_instructions.clone())
+    }
+}
+
+fn params(recipient: Pubkey) -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient,
+        currency: Currency::Sol,
+    }
+}
+
+#[test]
+fn strict_rejects_extra_remaining_accounts() {
+    let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut lamports, mut data) = (0, vec![]);
+    let extra = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &owner, false, 0);
+
+    let err = forbid_remaining_accounts(&[extra]).unwrap_err();
+    assert_eq!(err, X402Error::UnexpectedRemainingAccounts.into());
+}
+
+#[test]
+fn strict_verifies_through_the_declared_sysvar() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[system_instruction::transfer(&payer, &recipient, PRICE)], &payer);
+    let accounts = Declared {
+        x402_instructions: sysvar.account_info(false, false),
+    };
+
+    forbid_remaining_accounts(&[]).unwrap();
+    let sysvar = declared_sysvar::<PrecedingInstruction, _>(&accounts).unwrap();
+    let payment = verify_payment(std::slice::from_ref(&sysvar), &PROGRAM_ID, &params(recipient)).unwrap();
+    assert_eq!(payment.payer, payer);
+}
+
+#[test]
+fn permissive_accepts_extra_remaining_accounts() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = Sysvar::new(&[system_instruction::transfer(&payer, &recipient, PRICE)], &payer);
+    let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut lamports, mut data) = (0, vec![]);
+    let extra = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &owner, false, 0);
+
+    let remaining = [extra, sysvar.account_info(false, false)];
+    verify_payment(&remaining, &PROGRAM_ID, &params(recipient)).unwrap();
+}
@@ -0,0 +1,114 @@
+//! How `verify_payment` locates and vets the instructions sysvar. Only the
+//! key identifies the sysvar, wherever it sits among the remaining
+//! accounts; flags and owner are checked by us so a misbehaving client gets
+//! `InvalidSysvarAccount` rather than a generic load failure.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::system_instruction;
+use x402_runtime::{verify_payment, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+
+fn sysvar(payer: &Pubkey, recipient: &Pubkey) -> Sysvar {
+    Sysvar::new(&[system_instruction::transfer(payer, recipient, PRICE)], payer)
+}
+
+/// Unrelated accounts, each holding a copy of the sysvar's data.
+fn lookalikes(payer: &Pubkey, recipient: &Pubkey, count: usize) -> Vec<Sysvar> {
+    (0..count)
+        .map(|_| {
+            let mut fake = sysvar(payer, recipient);
+            fake.key = Pubkey::new_unique();
+            fake
+        })
+        .collect()
+}
+
+fn verify_among(accounts: &[AccountInfo], recipient: &Pubkey) -> Result<()> {
+    let params = X402Params {
+        price: PRICE,
+        recipient: *recipient,
+        currency: Currency::Sol,
+    };
+    verify_payment(accounts, &PROGRAM_ID, &params).map(|_| ())
+}
+
+fn verify(sysvar: &mut Sysvar, is_signer: bool, is_writable: bool, recipient: &Pubkey) -> Result<()> {
+    verify_among(&[sysvar.account_info(is_signer, is_writable)], recipient)
+}
+
+#[test]
+fn accepts_read_only_sysvar() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    verify(&mut sysvar(&payer, &recipient), false, false, &recipient).unwrap();
+}
+
+#[test]
+fn rejects_writable_sysvar() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let err = verify(&mut sysvar(&payer, &recipient), false, true, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::InvalidSysvarAccount.into());
+}
+
+#[test]
+fn rejects_signer_sysvar() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let err = verify(&mut sysvar(&payer, &recipient), true, false, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::InvalidSysvarAccount.into());
+}
+
+#[test]
+fn rejects_sysvar_key_with_wrong_owner() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut sysvar = sysvar(&payer, &recipient);
+    sysvar.owner = PROGRAM_ID;
+    let err = verify(&mut sysvar, false, false, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::InvalidSysvarAccount.into());
+}
+
+#[test]
+fn ignores_lookalike_at_another_key() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut fake = sysvar(&payer, &recipient);
+    fake.key = Pubkey::new_unique();
+    let err = verify(&mut fake, false, false, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::SysvarMissing.into());
+}
+
+#[test]
+fn accepts_sysvar_passed_twice() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let (mut first, mut second) = (sysvar(&payer, &recipient), sysvar(&payer, &recipient));
+    let accounts = [first.account_info(false, false), second.account_info(false, false)];
+    verify_among(&accounts, &recipient).unwrap();
+}
+
+#[test]
+fn accepts_sysvar_after_unrelated_accounts() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    // More than enough accounts for their keys to be logged.
+    let mut others = lookalikes(&payer, &recipient, 10);
+    let mut real = sysvar(&payer, &recipient);
+    let mut accounts: Vec<_> = others.iter_mut().map(|other| other.account_info(false, false)).collect();
+    accounts.push(real.account_info(false, false));
+    verify_among(&accounts, &recipient).unwrap();
+}
+
+#[test]
+fn rejects_many_accounts_without_the_sysvar() {
+    let (payer, recipient) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let mut others = lookalikes(&payer, &recipient, 10);
+    let accounts: Vec<_> = others.iter_mut().map(|other| other.account_info(false, false)).collect();
+    let err = verify_among(&accounts, &recipient).unwrap_err();
+    assert_eq!(err, X402Error::SysvarMissing.into());
+}
+
+#[test]
+fn rejects_no_remaining_accounts() {
+    let err = verify_among(&[], &Pubkey::new_unique()).unwrap_err();
+    assert_eq!(err, X402Error::SysvarMissing.into());
+}
@@ -0,0 +1,177 @@
+//! Transactions that go on after the priced call: a memo, a tip, another
+//! program's instruction or a second call. Every check reads only the
+//! instructions before the call, so nothing after it pays for the call,
+//! and a later call of the same program never reaches the payment of an
+//! earlier one.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::system_instruction::transfer;
+use x402_runtime::authorization::{ed25519_instruction, RecipientAuthority, RecipientAuthorization};
+use x402_runtime::revenue_split::{verify_revenue_split, RevenueShare};
+use x402_runtime::{
+    verify_payment, verify_payment_with, verify_split_payment, Currency, ScanOptions, VerifiedPayment, X402Error,
+    X402Params, IGNORABLE_PROGRAMS,
+};
+
+use common::{priced_instruction, Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+const PAYER: Pubkey = Pubkey::new_from_array([3; 32]);
+/// A Jito tip account.
+const TIP: Pubkey = pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5");
+const NOW: i64 = 1_700_000_000;
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+    }
+}
+
+fn memo() -> Instruction {
+    Instruction {
+        program_id: IGNORABLE_PROGRAMS[1],
+        accounts: vec![],
+        data: b"order 42".to_vec(),
+    }
+}
+
+/// An instruction of some program that cannot pay.
+fn other() -> Instruction {
+    Instruction {
+        program_id: Pubkey::new_unique(),
+        accounts: vec![AccountMeta::new(Pubkey::new_unique(), false)],
+        data: vec![1, 2, 3],
+    }
+}
+
+fn lookback(max_lookback: u8, strict: bool) -> ScanOptions {
+    ScanOptions {
+        max_lookback,
+        strict,
+        legacy_matcher: false,
+    }
+}
+
+/// Verifies the instruction at `current` of `all` with `scan`.
+fn verify_at(all: &[Instruction], current: u16, scan: ScanOptions) -> Result<VerifiedPayment> {
+    let mut sysvar = Sysvar::at(all, current);
+    verify_payment_with(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(), &scan)
+}
+
+#[test]
+fn a_memo_a_tip_and_another_program_may_follow_the_call() {
+    let all = [
+        transfer(&PAYER, &RECIPIENT, PRICE),
+        priced_instruction(&PAYER),
+        memo(),
+        transfer(&PAYER, &TIP, 10_000),
+        other(),
+    ];
+    let payment = verify_at(&all, 1, ScanOptions::DEFAULT).unwrap();
+    assert_eq!((payment.amount, payment.ix_index), (PRICE, 0));
+}
+
+#[test]
+fn a_transfer_to_the_recipient_after_the_call_never_pays_for_it() {
+    for scan in [ScanOptions::DEFAULT, lookback(16, false), lookback(16, true)] {
+        let all = [priced_instruction(&PAYER), transfer(&PAYER, &RECIPIENT, PRICE)];
+        let err = verify_at(&all, 0, scan).unwrap_err();
+        assert_eq!(err, X402Error::NoPaymentInstruction.into(), "{:?}", scan);
+
+        // Nor does it make up the rest of a short payment before the call.
+        let all = [
+            transfer(&PAYER, &RECIPIENT, PRICE - 1),
+            priced_instruction(&PAYER),
+            transfer(&PAYER, &RECIPIENT, PRICE),
+        ];
+        let err = verify_at(&all, 1, scan).unwrap_err();
+        assert_eq!(err, X402Error::InsufficientPayment.into(), "{:?}", scan);
+    }
+}
+
+#[test]
+fn a_second_call_needs_a_payment_of_its_own() {
+    // However far it may look back, the second call stops at the first,
+    // whose payment is not its own.
+    let all = [transfer(&PAYER, &RECIPIENT, PRICE), priced_instruction(&PAYER), priced_instruction(&PAYER)];
+    assert_eq!(verify_at(&all, 1, lookback(4, false)).unwrap().ix_index, 0);
+    let err = verify_at(&all, 2, lookback(4, false)).unwrap_err();
+    assert_eq!(err, X402Error::NotAPayment.into());
+
+    let all = [
+        transfer(&PAYER, &RECIPIENT, PRICE),
+        priced_instruction(&PAYER),
+        memo(),
+        transfer(&PAYER, &RECIPIENT, PRICE),
+        priced_instruction(&PAYER),
+    ];
+    assert_eq!(verify_at(&all, 1, ScanOptions::DEFAULT).unwrap().ix_index, 0);
+    assert_eq!(verify_at(&all, 4, lookback(4, false)).unwrap().ix_index, 3);
+}
+
+#[test]
+fn split_payers_after_the_call_do_not_contribute() {
+    let (alice, bob) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let all = [
+        transfer(&alice, &RECIPIENT, PRICE / 2),
+        priced_instruction(&alice),
+        transfer(&bob, &RECIPIENT, PRICE / 2),
+    ];
+    let mut sysvar = Sysvar::at(&all, 1);
+    let err = verify_split_payment(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(), &alice, 4)
+        .unwrap_err();
+    assert_eq!(err, X402Error::InsufficientPayment.into());
+}
+
+#[test]
+fn a_revenue_leg_after_the_call_is_missing() {
+    let dao = Pubkey::new_unique();
+    let shares = [
+        RevenueShare { recipient: RECIPIENT, bps: 8_000 },
+        RevenueShare { recipient: dao, bps: 2_000 },
+    ];
+    let all = [
+        transfer(&PAYER, &RECIPIENT, PRICE * 8 / 10),
+        priced_instruction(&PAYER),
+        transfer(&PAYER, &dao, PRICE * 2 / 10),
+    ];
+    let mut sysvar = Sysvar::at(&all, 1);
+    let err = verify_revenue_split(&[sysvar.account_info(false, false)], &PROGRAM_ID, &params(), &shares).unwrap_err();
+    assert_eq!(err, X402Error::MissingRevenueLeg.into());
+}
+
+#[test]
+fn a_recipient_authorization_after_the_call_is_not_read() {
+    let (authority, seller) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let authorization = RecipientAuthorization {
+        recipient: seller,
+        price: PRICE,
+        expiry: NOW + 60,
+    };
+    let all = [
+        transfer(&PAYER, &seller, PRICE),
+        priced_instruction(&PAYER),
+        ed25519_instruction(&authority, &[0; 64], &authorization.message()),
+    ];
+    let mut sysvar = Sysvar::at(&all, 1);
+    let accounts = [sysvar.account_info(false, false)];
+    let unpriced = X402Params {
+        recipient: Pubkey::default(),
+        ..params()
+    };
+    let err = RecipientAuthority { authority }.verify_at(&accounts, &unpriced, &seller, NOW).unwrap_err();
+    assert_eq!(err, X402Error::RecipientNotAuthorized.into());
+
+    // The same transaction with the authorization first pays the seller.
+    let all = [all[2].clone(), all[0].clone(), all[1].clone()];
+    let mut sysvar = Sysvar::at(&all, 2);
+    let accounts = [sysvar.account_info(false, false)];
+    let params = RecipientAuthority { authority }.verify_at(&accounts, &unpriced, &seller, NOW).unwrap();
+    assert_eq!(verify_payment(&accounts, &PROGRAM_ID, &params).unwrap().recipient, seller);
+}
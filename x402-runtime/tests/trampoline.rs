@@ -0,0 +1,153 @@
+//! A priced handler invoked by its program's `pay_and_invoke`, the only
+//! instruction of the transaction, as a single-instruction wallet sends it,
+//! and handlers without the trampoline turning away every invocation.
+
+mod common;
+
+use anchor_lang::prelude::*;
+use solana_program::instruction::{AccountMeta, Instruction};
+use x402_common::fingerprint::instruction_fingerprint;
+use x402_runtime::trampoline::{
+    handler_discriminator, require_top_level, require_top_level_call, require_top_level_call_at,
+    verify_trampoline_payment_at, PayAndInvokeArgs, PAY_AND_INVOKE_DISCRIMINATOR,
+};
+use x402_runtime::{request_fingerprint, Currency, X402Error, X402Params};
+
+use common::{Sysvar, PROGRAM_ID};
+
+const PRICE: u64 = 1_000_000;
+const RECIPIENT: Pubkey = Pubkey::new_from_array([9; 32]);
+/// One level below the transaction-level `pay_and_invoke`.
+const INVOKED: usize = 2;
+
+fn params() -> X402Params {
+    X402Params {
+        price: PRICE,
+        recipient: RECIPIENT,
+        currency: Currency::Sol,
+    }
+}
+
+fn pay_and_invoke(program_id: Pubkey, payer: &Pubkey, amount: u64, handler: &str) -> Instruction {
+    let mut inner_data = handler_discriminator(handler).to_vec();
+    inner_data.extend_from_slice(&[0]);
+    let mut data = PAY_AND_INVOKE_DISCRIMINATOR.to_vec();
+    data.extend_from_slice(&PayAndInvokeArgs { amount, inner_data }.try_to_vec().unwrap());
+    Instruction {
+        program_id,
+        accounts: vec![AccountMeta::new(*payer, true), AccountMeta::new(RECIPIENT, false)],
+        data,
+    }
+}
+
+fn verify(outer: Instruction, payer: &Pubkey, stack_height: usize) -> Result<u64> {
+    let mut sysvar = Sysvar::with_priced(&[], outer);
+    let payment = verify_trampoline_payment_at(
+        &[sysvar.account_info(false, false)],
+        &PROGRAM_ID,
+        &params(),
+        payer,
+        "compute_premium",
+        stack_height,
+    )?;
+    assert_eq!(payment.payer, *payer);
+    assert_eq!(payment.ix_index, 0);
+    Ok(payment.amount)
+}
+
+#[test]
+fn accepts_a_single_instruction_pay_and_call() {
+    let payer = Pubkey::new_unique();
+    let outer = pay_and_invoke(PROGRAM_ID, &payer, PRICE + 1, "compute_premium");
+    assert_eq!(verify(outer, &payer, INVOKED).unwrap(), PRICE + 1);
+    // Off-chain the stack height reads as 0, which counts as top level.
+    require_top_level().unwrap();
+}
+
+#[test]
+fn rejects_other_nesting_depths() {
+    let payer = Pubkey::new_unique();
+    for stack_height in [1, INVOKED + 1] {
+        let outer = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+        let err = verify(outer, &payer, stack_height).unwrap_err();
+        assert_eq!(err, error!(X402Error::NotInvokedByTrampoline));
+    }
+}
+
+#[test]
+fn rejects_other_callers_and_instructions() {
+    let payer = Pubkey::new_unique();
+
+    let foreign = pay_and_invoke(Pubkey::new_unique(), &payer, PRICE, "compute_premium");
+    let err = verify(foreign, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::NotInvokedByTrampoline));
+
+    let other = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_enterprise");
+    let err = verify(other, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::NotInvokedByTrampoline));
+
+    let mut not_a_trampoline = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    not_a_trampoline.data[..8].copy_from_slice(&handler_discriminator("compute_premium"));
+    let err = verify(not_a_trampoline, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::NotInvokedByTrampoline));
+}
+
+#[test]
+fn rejects_underpayment_and_other_payers() {
+    let payer = Pubkey::new_unique();
+
+    let short = pay_and_invoke(PROGRAM_ID, &payer, PRICE - 1, "compute_premium");
+    let err = verify(short, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::InsufficientPayment));
+
+    let paid_by_other = pay_and_invoke(PROGRAM_ID, &Pubkey::new_unique(), PRICE, "compute_premium");
+    let err = verify(paid_by_other, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::SignerDidNotPay));
+
+    let mut elsewhere = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    elsewhere.accounts[1].pubkey = Pubkey::new_unique();
+    let err = verify(elsewhere, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::WrongRecipient));
+}
+
+#[test]
+fn a_missing_account_is_told_apart_from_another_one() {
+    let payer = Pubkey::new_unique();
+
+    let mut no_recipient = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    no_recipient.accounts.truncate(1);
+    let err = verify(no_recipient, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::MissingPaymentAccount));
+
+    let mut no_accounts = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    no_accounts.accounts.clear();
+    let err = verify(no_accounts, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::MissingPaymentAccount));
+
+    let mut unsigned = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    unsigned.accounts[0].is_signer = false;
+    let err = verify(unsigned, &payer, INVOKED).unwrap_err();
+    assert_eq!(err, error!(X402Error::SignerDidNotPay));
+}
+
+#[test]
+fn fingerprints_the_invoked_instruction() {
+    let payer = Pubkey::new_unique();
+    let outer = pay_and_invoke(PROGRAM_ID, &payer, PRICE, "compute_premium");
+    let args = PayAndInvokeArgs::try_from_slice(&outer.data[8..]).unwrap();
+
+    let mut sysvar = Sysvar::with_priced(&[], outer);
+    let fingerprint = request_fingerprint(&[sysvar.account_info(false, false)], &payer, PRICE).unwrap();
+    assert_eq!(Some(fingerprint), instruction_fingerprint(&payer, &args.inner_data, PRICE));
+}
+
+#[test]
+fn handlers_paid_by_a_preceding_instruction_reject_invocations() {
+    require_top_level_call_at("priced_query", 1).unwrap();
+    for stack_height in [INVOKED, INVOKED + 1] {
+        let err = require_top_level_call_at("priced_query", stack_height).unwrap_err();
+        assert_eq!(err, error!(X402Error::InvokedByCpi));
+    }
+    // Off-chain the stack height reads as 0, which counts as top level.
+    require_top_level_call("priced_query").unwrap();
+}
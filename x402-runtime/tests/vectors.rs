@@ -0,0 +1,370 @@
+//! Golden matcher vectors: every file in `tests/vectors/` is a serialized
+//! instruction and the outcome `match_payment` must produce for it, for
+//! reviewers who want to see exactly which byte patterns pass.
+//!
+//! The `accept_*` vectors for real encodings are produced by SDK
+//! constructors in [`sdk_vectors`]; `accepted_vectors_match_sdk` fails if a
+//! fixture drifts from them, and
+//! `cargo test -p x402-runtime --test vectors -- --ignored regenerate`
+//! rewrites them. The `reject_*` and `legacy_*` vectors and hand-built
+//! near-misses are maintained by hand.
+//!
+//! `expect` is the outcome of `match_payment`. A vector's `legacy_expect`,
+//! when present, is what the deprecated `match_payment_legacy` produces
+//! instead; the `legacy_*` vectors are guesses only it accepts.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::system_instruction;
+use x402_runtime::matcher::{TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use x402_runtime::{match_payment, MatchError, MatchedPayment, PaymentKind};
+
+/// The program running the check, so `x402_pay` vectors count as its own.
+const PROGRAM_ID: Pubkey = Pubkey::new_from_array([7; 32]);
+const PAYER: Pubkey = Pubkey::new_from_array([1; 32]);
+const RECIPIENT: Pubkey = Pubkey::new_from_array([2; 32]);
+const MINT: Pubkey = Pubkey::new_from_array([3; 32]);
+const SOURCE: Pubkey = Pubkey::new_from_array([4; 32]);
+const DEST: Pubkey = Pubkey::new_from_array([5; 32]);
+const SEED_OWNER: Pubkey = Pubkey::new_from_array([8; 32]);
+const AMOUNT: u64 = 1_000_000;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Vector {
+    description: String,
+    instruction: SerializedInstruction,
+    expect: Expect,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    legacy_expect: Option<Expect>,
+}
+
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct SerializedInstruction {
+    program_id: String,
+    accounts: Vec<SerializedMeta>,
+    /// Lowercase hex.
+    data: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct SerializedMeta {
+    pubkey: String,
+    is_signer: bool,
+    is_writable: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+enum Expect {
+    Accept(Accepted),
+    /// A `MatchError` variant name.
+    Reject(String),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct Accepted {
+    /// A `PaymentKind` variant name.
+    kind: String,
+    payer: String,
+    recipient: String,
+    amount: u64,
+    mint: Option<String>,
+}
+
+impl SerializedInstruction {
+    fn from_instruction(ix: &Instruction) -> Self {
+        SerializedInstruction {
+            program_id: ix.program_id.to_string(),
+            accounts: ix
+                .accounts
+                .iter()
+                .map(|meta| SerializedMeta {
+                    pubkey: meta.pubkey.to_string(),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: ix.data.iter().map(|b| format!("{:02x}", b)).collect(),
+        }
+    }
+
+    fn to_instruction(&self) -> Instruction {
+        Instruction {
+            program_id: pubkey(&self.program_id),
+            accounts: self
+                .accounts
+                .iter()
+                .map(|meta| AccountMeta {
+                    pubkey: pubkey(&meta.pubkey),
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable,
+                })
+                .collect(),
+            data: (0..self.data.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&self.data[i..i + 2], 16).expect("data is not hex"))
+                .collect(),
+        }
+    }
+}
+
+impl Expect {
+    fn from_outcome(outcome: &Result<MatchedPayment, MatchError>) -> Self {
+        match outcome {
+            Ok(payment) => Expect::Accept(Accepted {
+                kind: format!("{:?}", payment.kind),
+                payer: payment.payer.to_string(),
+                recipient: payment.recipient.to_string(),
+                amount: payment.amount,
+                mint: payment.mint.map(|mint| mint.to_string()),
+            }),
+            Err(reason) => Expect::Reject(format!("{:?}", reason)),
+        }
+    }
+}
+
+fn pubkey(s: &str) -> Pubkey {
+    Pubkey::from_str(s).unwrap_or_else(|_| panic!("{} is not a pubkey", s))
+}
+
+fn vectors_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/vectors")
+}
+
+fn load_vectors() -> Vec<(String, Vector)> {
+    let mut vectors: Vec<(String, Vector)> = fs::read_dir(vectors_dir())
+        .expect("tests/vectors is missing")
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .map(|path| {
+            let name = path.file_stem().unwrap().to_string_lossy().into_owned();
+            let json = fs::read_to_string(&path).unwrap();
+            let vector = serde_json::from_str(&json).unwrap_or_else(|err| panic!("{}: {}", name, err));
+            (name, vector)
+        })
+        .collect();
+    vectors.sort_by(|a, b| a.0.cmp(&b.0));
+    vectors
+}
+
+/// The account `PAYER` transfers from with `TransferWithSeed`.
+fn seed_source() -> Pubkey {
+    Pubkey::create_with_seed(&PAYER, "x402", &SEED_OWNER).unwrap()
+}
+
+/// The accepted encodings, built the way wallets build them.
+fn sdk_vectors() -> Vec<(&'static str, &'static str, Instruction)> {
+    let mut x402_pay_data = hash(b"global:x402_pay").to_bytes()[..8].to_vec();
+    x402_pay_data.extend_from_slice(&AMOUNT.to_le_bytes());
+    let x402_pay = Instruction {
+        program_id: PROGRAM_ID,
+        accounts: vec![
+            AccountMeta::new(PAYER, true),
+            AccountMeta::new(RECIPIENT, false),
+            AccountMeta::new_readonly(solana_program::system_program::ID, false),
+        ],
+        data: x402_pay_data,
+    };
+
+    let spl_transfer = |amount| {
+        spl_token::instruction::transfer(&spl_token::id(), &SOURCE, &DEST, &PAYER, &[], amount).unwrap()
+    };
+    let spl_transfer_checked = |amount| {
+        spl_token::instruction::transfer_checked(&spl_token::id(), &SOURCE, &MINT, &DEST, &PAYER, &[], amount, 6)
+            .unwrap()
+    };
+    // Token-2022 shares the encodings; only the program id differs.
+    let token_2022 = |mut ix: Instruction| {
+        ix.program_id = TOKEN_2022_PROGRAM_ID;
+        ix
+    };
+    assert_eq!(spl_token::id(), TOKEN_PROGRAM_ID);
+
+    vec![
+        (
+            "accept_system_transfer",
+            "System Transfer",
+            system_instruction::transfer(&PAYER, &RECIPIENT, AMOUNT),
+        ),
+        (
+            "accept_system_transfer_with_seed",
+            "System TransferWithSeed; the base account signs for the source, and the third account is credited",
+            system_instruction::transfer_with_seed(
+                &seed_source(),
+                &PAYER,
+                "x402".to_string(),
+                &SEED_OWNER,
+                &RECIPIENT,
+                AMOUNT,
+            ),
+        ),
+        (
+            "accept_x402_pay",
+            "The invoking program's x402_pay: discriminator then amount",
+            x402_pay,
+        ),
+        (
+            "accept_spl_transfer",
+            "SPL Token Transfer; the payer is the authority",
+            spl_transfer(AMOUNT),
+        ),
+        (
+            "accept_spl_transfer_checked",
+            "SPL Token TransferChecked",
+            spl_transfer_checked(AMOUNT),
+        ),
+        (
+            "accept_token_2022_transfer",
+            "Token-2022 Transfer",
+            token_2022(spl_transfer(AMOUNT)),
+        ),
+        (
+            "accept_token_2022_transfer_checked",
+            "Token-2022 TransferChecked",
+            token_2022(spl_transfer_checked(AMOUNT)),
+        ),
+        (
+            "accept_system_transfer_u64_max",
+            "The matcher reports u64::MAX as is; the price check decides",
+            system_instruction::transfer(&PAYER, &RECIPIENT, u64::MAX),
+        ),
+        (
+            "accept_spl_transfer_checked_u64_max",
+            "u64::MAX through TransferChecked",
+            spl_transfer_checked(u64::MAX),
+        ),
+    ]
+}
+
+fn sdk_vector(name: &str, description: &str, ix: &Instruction) -> Vector {
+    Vector {
+        description: description.to_string(),
+        instruction: SerializedInstruction::from_instruction(ix),
+        expect: Expect::from_outcome(&match_payment(ix, &PROGRAM_ID)),
+        legacy_expect: None,
+    }
+    .checked(name)
+}
+
+impl Vector {
+    /// The outcome `match_payment_legacy` must produce.
+    #[cfg(not(feature = "strict-encodings"))]
+    fn legacy_expected(&self) -> &Expect {
+        self.legacy_expect.as_ref().unwrap_or(&self.expect)
+    }
+
+    fn checked(self, name: &str) -> Self {
+        assert!(
+            matches!(self.expect, Expect::Accept(_)),
+            "{}: the SDK encoding is rejected: {:?}",
+            name,
+            self.expect
+        );
+        self
+    }
+}
+
+#[test]
+fn vectors() {
+    let vectors = load_vectors();
+    assert!(!vectors.is_empty(), "no vectors in tests/vectors");
+
+    let kinds = [
+        PaymentKind::SystemTransfer,
+        PaymentKind::SystemTransferWithSeed,
+        PaymentKind::ProgramPay,
+        PaymentKind::TokenTransfer,
+        PaymentKind::TokenTransferChecked,
+    ];
+    for kind in kinds {
+        let kind = format!("{:?}", kind);
+        assert!(
+            vectors
+                .iter()
+                .any(|(_, v)| matches!(&v.expect, Expect::Accept(a) if a.kind == kind)),
+            "no accepted vector of kind {}",
+            kind
+        );
+    }
+
+    for (name, vector) in &vectors {
+        let ix = vector.instruction.to_instruction();
+        let actual = Expect::from_outcome(&match_payment(&ix, &PROGRAM_ID));
+        assert_eq!(actual, vector.expect, "vector {} ({})", name, vector.description);
+    }
+}
+
+#[test]
+fn transfer_with_seed_is_paid_by_its_base() {
+    let ix =
+        system_instruction::transfer_with_seed(&seed_source(), &PAYER, "x402".to_string(), &SEED_OWNER, &RECIPIENT, 1);
+    let payment = match_payment(&ix, &PROGRAM_ID).unwrap();
+    assert_eq!(payment.kind, PaymentKind::SystemTransferWithSeed);
+    assert_eq!(payment.payer, PAYER, "the signer, not the source derived from it");
+    assert_eq!(payment.recipient, RECIPIENT);
+}
+
+#[cfg(not(feature = "strict-encodings"))]
+#[test]
+fn legacy_vectors() {
+    use x402_runtime::match_payment_legacy;
+
+    for (name, vector) in load_vectors() {
+        let ix = vector.instruction.to_instruction();
+        let actual = Expect::from_outcome(&match_payment_legacy(&ix, &PROGRAM_ID));
+        assert_eq!(actual, *vector.legacy_expected(), "vector {} ({})", name, vector.description);
+    }
+}
+
+#[test]
+fn accepts_only_enumerated_encodings() {
+    use x402_runtime::matcher::{EncodingProgram, PaymentEncoding};
+
+    for (name, vector) in load_vectors() {
+        let ix = vector.instruction.to_instruction();
+        let Ok(payment) = match_payment(&ix, &PROGRAM_ID) else {
+            continue;
+        };
+        let program = if ix.program_id == PROGRAM_ID {
+            EncodingProgram::Invoking
+        } else if ix.program_id == solana_program::system_program::ID {
+            EncodingProgram::System
+        } else {
+            EncodingProgram::Token
+        };
+        let encoding = PaymentEncoding::find(program, &ix.data)
+            .unwrap_or_else(|| panic!("vector {} was accepted without an enumerated encoding", name));
+        assert_eq!(payment.kind, encoding.kind, "vector {}", name);
+        if let Some(len) = encoding.len {
+            assert_eq!(ix.data.len(), len, "vector {}", name);
+        }
+    }
+}
+
+#[test]
+fn accepted_vectors_match_sdk() {
+    let vectors = load_vectors();
+    for (name, description, ix) in sdk_vectors() {
+        let (_, fixture) = vectors
+            .iter()
+            .find(|(file, _)| file == name)
+            .unwrap_or_else(|| panic!("missing vector {}; run the regenerate test", name));
+        assert_eq!(*fixture, sdk_vector(name, description, &ix), "vector {} drifted from the SDK", name);
+    }
+}
+
+#[test]
+#[ignore = "rewrites tests/vectors; run explicitly after changing an encoding"]
+fn regenerate() {
+    for (name, description, ix) in sdk_vectors() {
+        let json = serde_json::to_string_pretty(&sdk_vector(name, description, &ix)).unwrap();
+        fs::write(vectors_dir().join(format!("{}.json", name)), json + "\n").unwrap();
+    }
+}
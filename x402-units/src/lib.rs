@@ -0,0 +1,151 @@
+//! The amount grammar shared by `#[x402(price_sol = "...", price_usdc =
+//! "...")]` and the client's `units` module, so expansion-time and
+//! client-time parsing cannot diverge.
+//!
+//! An amount is a plain decimal, `digits` or `digits.digits`, with no sign,
+//! exponent or separators, optionally followed by a unit (`"0.05 SOL"`,
+//! `"1.25 USDC"`). It is converted to base units exactly: a decimal with
+//! more significant fractional digits than the unit has decimals is
+//! rejected rather than rounded. The crate has no dependencies so the
+//! proc-macro crate can use it.
+
+use std::fmt;
+
+/// Decimals of SOL (lamports per SOL is `10^9`).
+pub const SOL_DECIMALS: u8 = 9;
+/// Decimals of the USDC mint.
+pub const USDC_DECIMALS: u8 = 6;
+
+/// A unit an amount may be written in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Unit {
+    Sol,
+    Usdc,
+}
+
+impl Unit {
+    pub fn decimals(self) -> u8 {
+        match self {
+            Unit::Sol => SOL_DECIMALS,
+            Unit::Usdc => USDC_DECIMALS,
+        }
+    }
+
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Unit::Sol => "SOL",
+            Unit::Usdc => "USDC",
+        }
+    }
+
+    /// Matches `SOL` or `USDC`, ignoring ASCII case.
+    pub fn from_symbol(symbol: &str) -> Option<Unit> {
+        [Unit::Sol, Unit::Usdc]
+            .into_iter()
+            .find(|unit| unit.symbol().eq_ignore_ascii_case(symbol))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum UnitsError {
+    /// The amount is empty.
+    Empty,
+    /// The amount is not a plain decimal.
+    InvalidNumber(String),
+    /// The amount has more significant fractional digits than `decimals`.
+    TooPrecise { decimals: u8 },
+    /// The amount does not fit in a `u64` of base units.
+    Overflow,
+    /// The amount names a unit other than SOL or USDC.
+    UnknownUnit(String),
+    /// The amount has no unit where one is required.
+    MissingUnit,
+    /// The amount is in a different unit than the one required.
+    WrongUnit { expected: Unit, found: Unit },
+}
+
+impl fmt::Display for UnitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UnitsError::Empty => write!(f, "empty amount"),
+            UnitsError::InvalidNumber(s) => write!(f, "\"{}\" is not a decimal number", s),
+            UnitsError::TooPrecise { decimals } => {
+                write!(f, "amount has more than {} decimal places", decimals)
+            }
+            UnitsError::Overflow => write!(f, "amount does not fit in a u64 of base units"),
+            UnitsError::UnknownUnit(unit) => write!(f, "unknown unit \"{}\"; expected SOL or USDC", unit),
+            UnitsError::MissingUnit => write!(f, "amount has no unit; expected SOL or USDC"),
+            UnitsError::WrongUnit { expected, found } => {
+                write!(f, "amount is in {} but {} is required", found.symbol(), expected.symbol())
+            }
+        }
+    }
+}
+
+impl std::error::Error for UnitsError {}
+
+/// Converts a decimal such as `"0.05"` to base units of a currency with
+/// `decimals` decimals (`50_000_000` for SOL).
+pub fn parse_decimal(s: &str, decimals: u8) -> Result<u64, UnitsError> {
+    if s.is_empty() {
+        return Err(UnitsError::Empty);
+    }
+
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, "0"));
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole) || !is_digits(fraction) {
+        return Err(UnitsError::InvalidNumber(s.to_string()));
+    }
+
+    // Trailing zeros carry no precision, so "0.050" is fine for USDC.
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.len() > usize::from(decimals) {
+        return Err(UnitsError::TooPrecise { decimals });
+    }
+
+    let scale = 10u128.checked_pow(u32::from(decimals)).ok_or(UnitsError::Overflow)?;
+    let whole: u128 = whole.parse().map_err(|_| UnitsError::Overflow)?;
+    // At most `decimals` digits, and `10^decimals` fits in a u128.
+    let fraction = fraction
+        .bytes()
+        .fold(0u128, |acc, b| acc * 10 + u128::from(b - b'0'))
+        * 10u128.pow(u32::from(decimals) - fraction.len() as u32);
+
+    let total = whole
+        .checked_mul(scale)
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or(UnitsError::Overflow)?;
+    u64::try_from(total).map_err(|_| UnitsError::Overflow)
+}
+
+/// Formats base units as the shortest decimal [`parse_decimal`] reads back
+/// to the same value: `50_000_000` with 9 decimals is `"0.05"`.
+pub fn format_decimal(base_units: u64, decimals: u8) -> String {
+    let decimals = usize::from(decimals);
+    if decimals == 0 {
+        return base_units.to_string();
+    }
+
+    let padded = format!("{:0>width$}", base_units, width = decimals + 1);
+    let (whole, fraction) = padded.split_at(padded.len() - decimals);
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        whole.to_string()
+    } else {
+        format!("{}.{}", whole, fraction)
+    }
+}
+
+/// Parses an amount with its unit, such as `"0.05 SOL"` or `"1.25 USDC"`,
+/// into base units of that unit.
+pub fn parse_amount(s: &str) -> Result<(u64, Unit), UnitsError> {
+    let mut parts = s.split_whitespace();
+    let number = parts.next().ok_or(UnitsError::Empty)?;
+    let symbol = parts.next().ok_or(UnitsError::MissingUnit)?;
+    if let Some(extra) = parts.next() {
+        return Err(UnitsError::InvalidNumber(extra.to_string()));
+    }
+
+    let unit = Unit::from_symbol(symbol).ok_or_else(|| UnitsError::UnknownUnit(symbol.to_string()))?;
+    Ok((parse_decimal(number, unit.decimals())?, unit))
+}